@@ -0,0 +1,80 @@
+/* C bindings for delirium, built with the `capi` feature; see
+ * src/capi/mod.rs for the documentation of each function.
+ *
+ * Objects returned are owned by the caller, and are released with the
+ * corresponding `_free` function; functions returning pointers return
+ * NULL on failure, and functions returning ints return zero on success,
+ * and DELIRIUM_ERROR or DELIRIUM_PANIC otherwise.
+ */
+
+#ifndef DELIRIUM_H
+#define DELIRIUM_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define DELIRIUM_ERROR (-1)
+#define DELIRIUM_PANIC (-2)
+
+typedef struct DeliriumBuilder DeliriumBuilder;
+typedef struct DeliriumProject DeliriumProject;
+
+typedef enum DeliriumStmtKind {
+    DELIRIUM_STMT_PHI = 0,
+    DELIRIUM_STMT_DEF = 1,
+    DELIRIUM_STMT_JMP = 2,
+} DeliriumStmtKind;
+
+/* A statement of the IR. */
+typedef struct DeliriumStmt {
+    DeliriumStmtKind kind;
+    /* The index of the statement's Blk within the IR returned */
+    size_t blk;
+    /* The address of the statement's Blk, or zero */
+    uint64_t addr;
+    /* The target of a Jmp to a fixed address, or zero */
+    uint64_t target;
+    /* The statement, as rendered by the default Printer */
+    char *text;
+} DeliriumStmt;
+
+/* The statements of one or more Blks, in order. */
+typedef struct DeliriumIr {
+    DeliriumStmt *stmts;
+    size_t len;
+    /* The number of Blks the statements belong to */
+    size_t blks;
+} DeliriumIr;
+
+DeliriumBuilder *delirium_builder_new(const char *path);
+void delirium_builder_free(DeliriumBuilder *builder);
+
+DeliriumProject *delirium_project_new(
+    const DeliriumBuilder *builder,
+    const char *name,
+    const char *arch,
+    const char *convention);
+void delirium_project_free(DeliriumProject *project);
+
+int delirium_project_map(
+    DeliriumProject *project,
+    const char *name,
+    uint64_t addr,
+    const uint8_t *bytes,
+    size_t len,
+    bool big_endian);
+
+DeliriumIr *delirium_project_lift_blk(DeliriumProject *project, uint64_t addr);
+DeliriumIr *delirium_project_add_sub(DeliriumProject *project, uint64_t addr);
+void delirium_ir_free(DeliriumIr *ir);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* DELIRIUM_H */
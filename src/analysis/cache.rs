@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CacheError {
+    #[error("cannot access cache entry `{0}`: {1}")]
+    Io(PathBuf, #[source] io::Error),
+}
+
+/// A stable (i.e., independent of the platform, process, and compiler
+/// version) 64-bit FNV-1a hash of content, e.g., the bytes of a function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentHash(u64);
+
+impl Default for ContentHash {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl ContentHash {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> &mut Self {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        self
+    }
+
+    pub fn write_u64(&mut self, value: u64) -> &mut Self {
+        self.write(&value.to_le_bytes())
+    }
+
+    /// Hashes `text` followed by its length, so that the hashes of
+    /// consecutive strings do not depend on where one ends and the next
+    /// begins.
+    pub fn write_str(&mut self, text: &str) -> &mut Self {
+        self.write(text.as_bytes()).write_u64(text.len() as u64)
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// The key of a cached analysis result: the hash of the content analysed
+/// (e.g., a function's bytes) and the hash of the configuration of the
+/// pipeline that produced the result (e.g., the language and lifter
+/// settings, and the analysis' parameters).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CacheKey {
+    pub content: u64,
+    pub config: u64,
+}
+
+impl Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:016x}-{:016x}", self.content, self.config)
+    }
+}
+
+/// The number of lookups of an analysis' results that were answered by a
+/// cache, or were not, and the number of results stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub stores: usize,
+}
+
+impl CacheStats {
+    fn merge(&mut self, other: &CacheStats) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.stores += other.stores;
+    }
+}
+
+#[derive(Default)]
+struct Entries {
+    results: BTreeMap<(Arc<str>, CacheKey), Arc<str>>,
+    stats: BTreeMap<Arc<str>, CacheStats>,
+}
+
+/// A store of analysis results keyed by `CacheKey`, so that reopening a
+/// Project, or analysing a near-identical binary, reuses the results of
+/// analysing the functions it has in common with those analysed before.
+///
+/// Results are stored as text, encoded and decoded by the analysis (see
+/// `Project::resolve_jump_tables`), and addresses within them should be
+/// relative to the function analysed, so that they remain valid if the
+/// function is moved. A cache opened on a directory persists each result
+/// as a file named by its key, within a subdirectory for its analysis;
+/// clones of a cache share their results and statistics.
+#[derive(Clone, Default)]
+pub struct AnalysisCache {
+    root: Option<PathBuf>,
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl AnalysisCache {
+    /// A cache whose results are held in memory only.
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// A cache whose results are persisted within `root`; the directory is
+    /// created if it does not exist.
+    pub fn open(root: impl AsRef<Path>) -> Result<Self, CacheError> {
+        let root = root.as_ref().to_owned();
+        fs::create_dir_all(&root).map_err(|e| CacheError::Io(root.clone(), e))?;
+        Ok(Self {
+            root: Some(root),
+            entries: Default::default(),
+        })
+    }
+
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn path(&self, analysis: &str, key: &CacheKey) -> Option<PathBuf> {
+        self.root
+            .as_ref()
+            .map(|root| root.join(analysis).join(key.to_string()))
+    }
+
+    /// The result of `analysis` stored for `key`, as decoded by `decode`;
+    /// results that cannot be decoded (e.g., as they are stale) are
+    /// treated as misses.
+    pub fn get<T, F>(&self, analysis: &str, key: &CacheKey, decode: F) -> Option<T>
+    where
+        F: FnOnce(&str) -> Option<T>,
+    {
+        let analysis = Arc::<str>::from(analysis);
+        let mut entries = self.entries();
+
+        let text = entries
+            .results
+            .get(&(analysis.clone(), *key))
+            .cloned()
+            .or_else(|| {
+                let text = Arc::<str>::from(fs::read_to_string(self.path(&analysis, key)?).ok()?);
+                entries.results.insert((analysis.clone(), *key), text.clone());
+                Some(text)
+            });
+
+        let result = text.and_then(|text| decode(&text));
+
+        let stats = entries.stats.entry(analysis).or_default();
+        if result.is_some() {
+            stats.hits += 1;
+        } else {
+            stats.misses += 1;
+        }
+
+        result
+    }
+
+    /// Stores `result` as the result of `analysis` for `key`.
+    pub fn put(&self, analysis: &str, key: CacheKey, result: &str) -> Result<(), CacheError> {
+        let analysis = Arc::<str>::from(analysis);
+
+        if let Some(path) = self.path(&analysis, &key) {
+            let dir = path.parent().unwrap_or(&path).to_owned();
+            fs::create_dir_all(&dir).map_err(|e| CacheError::Io(dir, e))?;
+
+            // write then rename, so that concurrent readers never observe a
+            // partial result
+            let partial = path.with_extension("partial");
+            fs::write(&partial, result)
+                .and_then(|_| fs::rename(&partial, &path))
+                .map_err(|e| CacheError::Io(path.clone(), e))?;
+        }
+
+        let mut entries = self.entries();
+        entries.results.insert((analysis.clone(), key), Arc::from(result));
+        entries.stats.entry(analysis).or_default().stores += 1;
+
+        Ok(())
+    }
+
+    /// The hits, misses, and stores for `analysis`.
+    pub fn stats(&self, analysis: &str) -> CacheStats {
+        self.entries()
+            .stats
+            .get(analysis)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The hits, misses, and stores for all analyses.
+    pub fn total_stats(&self) -> CacheStats {
+        let entries = self.entries();
+        let mut total = CacheStats::default();
+        for stats in entries.stats.values() {
+            total.merge(stats);
+        }
+        total
+    }
+
+    pub fn reset_stats(&self) {
+        self.entries().stats.clear();
+    }
+}
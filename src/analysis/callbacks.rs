@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+
+use crate::analysis::constants::{ConstantPropagation, Constants};
+use crate::analysis::dataflow::Dataflow;
+use crate::ir::{Addr, BitVec, Blk, Def, Jmp, Mem, Sub, Var};
+use crate::prelude::{Endian, Entity, Erased, Id, Identifiable};
+
+/// How the address of a Sub is taken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallbackKind {
+    /// Stored to memory by a Def, at the address given, if constant
+    Stored(Option<Addr>),
+    /// Passed to a call as the argument with the given index, i.e., in the
+    /// argument register or explicit argument of the call with that index
+    Argument(usize),
+    /// Held in memory (e.g., by a table of handlers, or a vtable)
+    Data,
+}
+
+/// A "registered-callback" reference to a Sub: a point at which the
+/// address of the Sub is taken, from which it may later be called
+/// indirectly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallbackRef {
+    sub: Id<Sub>,
+    kind: CallbackKind,
+    // the Sub and statement taking the address, for references from code
+    from: Option<(Id<Sub>, Id<Erased>)>,
+    // the address holding the Sub's address, for references from data
+    addr: Option<Addr>,
+}
+
+impl CallbackRef {
+    /// The Sub whose address is taken.
+    pub fn sub(&self) -> Id<Sub> {
+        self.sub
+    }
+
+    pub fn kind(&self) -> &CallbackKind {
+        &self.kind
+    }
+
+    /// The Sub taking the address, for references from code.
+    pub fn caller(&self) -> Option<Id<Sub>> {
+        self.from.map(|(sub, _)| sub)
+    }
+
+    /// The statement (a Def or Jmp) taking the address, for references from
+    /// code.
+    pub fn statement(&self) -> Option<Id<Erased>> {
+        self.from.map(|(_, stmt)| stmt)
+    }
+
+    /// The address of the memory holding the Sub's address, for references
+    /// from data.
+    pub fn addr(&self) -> Option<&Addr> {
+        self.addr.as_ref()
+    }
+}
+
+/// Finds the Subs whose addresses are taken, i.e., those that may be the
+/// targets of indirect calls: their addresses are stored to memory or
+/// passed to calls by the code of other Subs (see `scan`), or held in
+/// memory (see `scan_memory`), e.g., as callbacks registered with an event
+/// loop, or handlers in an interrupt vector table.
+///
+/// Values are taken to be the addresses of Subs if they are constant (see
+/// `ConstantPropagation`) and equal to the address of a known Sub.
+#[derive(Debug, Clone, Default)]
+pub struct AddressTaken {
+    starts: BTreeMap<u64, Id<Sub>>,
+    arguments: Vec<Var>,
+}
+
+impl AddressTaken {
+    /// Finds the references to the Subs starting at `starts`.
+    pub fn new<'a>(starts: impl IntoIterator<Item = (&'a Addr, Id<Sub>)>) -> Self {
+        Self {
+            starts: starts
+                .into_iter()
+                .filter_map(|(addr, sub)| Some((u64::try_from(addr).ok()?, sub)))
+                .collect(),
+            arguments: Vec::new(),
+        }
+    }
+
+    /// The registers passing arguments to calls, in order (see
+    /// `Lifter::integer_argument_registers`); without them, only the
+    /// explicit arguments of calls are considered.
+    pub fn arguments(&mut self, arguments: impl IntoIterator<Item = Var>) -> &mut Self {
+        self.arguments = arguments.into_iter().collect();
+        self
+    }
+
+    fn sub_at(&self, value: Option<BitVec>) -> Option<Id<Sub>> {
+        value
+            .and_then(|value| value.to_u64())
+            .and_then(|value| self.starts.get(&value).copied())
+    }
+
+    /// The references to Subs from the code of `sub`.
+    pub fn scan(&self, sub: &Entity<Sub>) -> Vec<CallbackRef> {
+        let analysis = ConstantPropagation::new();
+        let result = analysis.solve(sub);
+        let mut refs = Vec::new();
+
+        for blk in sub.blks() {
+            let mut constants = match result.entry(blk) {
+                Some(constants) if constants.is_reachable() => constants.clone(),
+                _ => continue,
+            };
+            self.scan_blk(&analysis, sub.id(), blk, &mut constants, &mut refs);
+        }
+
+        refs
+    }
+
+    fn scan_blk(
+        &self,
+        analysis: &ConstantPropagation,
+        caller: Id<Sub>,
+        blk: &Blk,
+        constants: &mut Constants,
+        refs: &mut Vec<CallbackRef>,
+    ) {
+        analysis.transfer_start(blk, constants);
+        for phi in blk.phis() {
+            analysis.transfer_phi(phi, constants);
+        }
+
+        for def in blk.defs() {
+            if let Def::Store(ref addr, ref value, _, _) = **def {
+                if let Some(sub) = self.sub_at(constants.evaluate(value)) {
+                    let addr = constants.evaluate(addr).map(Addr::from);
+                    refs.push(CallbackRef {
+                        sub,
+                        kind: CallbackKind::Stored(addr),
+                        from: Some((caller, def.id().erase())),
+                        addr: None,
+                    });
+                }
+            }
+            analysis.transfer_def(def, constants);
+        }
+
+        for jmp in blk.jmps() {
+            let args = match **jmp {
+                Jmp::Call(_, ref args) => args,
+                _ => continue,
+            };
+
+            let values = if args.is_empty() {
+                self.arguments.iter().map(|var| constants.get(var).cloned()).collect::<Vec<_>>()
+            } else {
+                args.iter().map(|arg| constants.evaluate(arg)).collect::<Vec<_>>()
+            };
+
+            for (index, value) in values.into_iter().enumerate() {
+                if let Some(sub) = self.sub_at(value) {
+                    refs.push(CallbackRef {
+                        sub,
+                        kind: CallbackKind::Argument(index),
+                        from: Some((caller, jmp.id().erase())),
+                        addr: None,
+                    });
+                }
+            }
+        }
+    }
+
+    /// The references to Subs held by the aligned words of `memory` of
+    /// `bits` bits.
+    pub fn scan_memory(&self, memory: &Mem, bits: u32) -> Vec<CallbackRef> {
+        let size = (bits / 8) as usize;
+        let mut refs = Vec::new();
+
+        if size == 0 || size > 8 {
+            return refs
+        }
+
+        for region in memory.regions().values() {
+            let base = match u64::try_from(region.address()) {
+                Ok(base) => base,
+                Err(_) => continue,
+            };
+            // the first aligned word of the region
+            let skip = ((size as u64 - base % size as u64) % size as u64) as usize;
+
+            for (index, word) in region.bytes().get(skip..).unwrap_or(&[]).chunks_exact(size).enumerate() {
+                let value = match region.endian() {
+                    Endian::Big => word.iter().fold(0u64, |value, byte| (value << 8) | *byte as u64),
+                    Endian::Little => word.iter().rev().fold(0u64, |value, byte| (value << 8) | *byte as u64),
+                };
+
+                if let Some(sub) = self.starts.get(&value) {
+                    refs.push(CallbackRef {
+                        sub: *sub,
+                        kind: CallbackKind::Data,
+                        from: None,
+                        addr: Some(Addr::from(base + (skip + index * size) as u64)),
+                    });
+                }
+            }
+        }
+
+        refs
+    }
+}
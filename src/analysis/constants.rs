@@ -0,0 +1,266 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use crate::analysis::dataflow::{self, Dataflow, DataflowResult, Direction, Lattice};
+use crate::analysis::hints::Hints;
+use crate::ir::{Addr, BitVec, Blk, Cfg, Def, Expr, Jmp, Phi, Sub, Var};
+use crate::passes::simplify;
+use crate::prelude::{Entity, Identifiable};
+
+/// The variables known to hold constant values at some point of a Sub.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constants {
+    // None if the point is not (yet) known to be reachable
+    values: Option<BTreeMap<Var, BitVec>>,
+}
+
+impl Constants {
+    /// Returns false if the point cannot be reached from the entry of the
+    /// Sub via resolved flows.
+    pub fn is_reachable(&self) -> bool {
+        self.values.is_some()
+    }
+
+    pub fn get(&self, var: &Var) -> Option<&BitVec> {
+        self.values.as_ref().and_then(|values| values.get(var))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Var, &BitVec)> {
+        self.values.iter().flat_map(|values| values.iter())
+    }
+
+    /// Evaluates `expr` by substituting the known constants for the
+    /// variables it reads and simplifying the result; returns None if the
+    /// result is not a constant.
+    pub fn evaluate(&self, expr: &Expr) -> Option<BitVec> {
+        let mut expr = expr.clone();
+        self.substitute(&mut expr);
+        simplify(expr).val().cloned()
+    }
+
+    /// Replaces each variable read by `expr` that holds a known constant
+    /// with that constant.
+    pub fn substitute(&self, expr: &mut Expr) {
+        if let Some(ref values) = self.values {
+            expr.substitute(&mut |var| values.get(var).cloned().map(Expr::Val));
+        }
+    }
+
+    // forgets the values of the physical variables not named in preserved
+    fn clobber(&mut self, preserved: &BTreeSet<Arc<str>>) {
+        if let Some(ref mut values) = self.values {
+            values.retain(|var, _| !var.is_physical() || preserved.contains(var.name()));
+        }
+    }
+
+    fn assign(&mut self, var: &Var, value: Option<BitVec>) {
+        if let Some(ref mut values) = self.values {
+            if let Some(value) = value {
+                values.insert(var.clone(), value);
+            } else {
+                values.remove(var);
+            }
+        }
+    }
+}
+
+impl Lattice for Constants {
+    fn bottom() -> Self {
+        Self { values: None }
+    }
+
+    fn join(&mut self, other: &Self) {
+        match (&mut self.values, &other.values) {
+            (_, None) => (),
+            (None, Some(_)) => {
+                self.values = other.values.clone();
+            }
+            (Some(ref mut values), Some(ref ovalues)) => {
+                values.retain(|var, value| ovalues.get(var) == Some(value));
+            }
+        }
+    }
+}
+
+/// A forward constant propagation analysis over the variables of a Sub.
+///
+/// Variables hold no known value on entry to the Sub; stores are not
+/// tracked, hence loads never evaluate to constants, unless they read from
+/// memory asserted to be constant by the analysis' hints. Registers whose
+/// values are asserted by hints at the address of a Blk are assigned those
+/// values on entry to the Blk.
+///
+/// Calls and intrinsics are assumed to clobber all physical variables,
+/// except those preserved (see `preserve`), e.g., the callee-saved
+/// registers of the calling convention (see `Lifter::callee_saved`).
+#[derive(Debug, Clone, Default)]
+pub struct ConstantPropagation {
+    hints: Option<Arc<Hints>>,
+    preserve: BTreeSet<Arc<str>>,
+}
+
+impl ConstantPropagation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_hints(hints: Arc<Hints>) -> Self {
+        Self {
+            hints: Some(hints),
+            ..Self::default()
+        }
+    }
+
+    /// Treat (physical) variables named `name` as preserved by calls and
+    /// intrinsics.
+    pub fn preserve(&mut self, name: impl Into<Arc<str>>) -> &mut Self {
+        self.preserve.insert(name.into());
+        self
+    }
+
+    pub fn preserved(&self) -> impl Iterator<Item = &Arc<str>> {
+        self.preserve.iter()
+    }
+
+    // the values after a call or intrinsic, given the values before it
+    pub(crate) fn clobber(&self, constants: &mut Constants) {
+        constants.clobber(&self.preserve);
+    }
+
+    fn evaluate(&self, constants: &Constants, expr: &Expr) -> Option<BitVec> {
+        let hints = match self.hints {
+            Some(ref hints) if !hints.is_empty() => hints,
+            _ => return constants.evaluate(expr),
+        };
+
+        let mut expr = expr.clone();
+        constants.substitute(&mut expr);
+
+        let mut expr = simplify(expr);
+        if Self::fold_loads(hints, &mut expr) {
+            expr = simplify(expr);
+        }
+        expr.val().cloned()
+    }
+
+    // replaces the loads of expr from constant memory with their values
+    fn fold_loads(hints: &Hints, expr: &mut Expr) -> bool {
+        let mut folded = false;
+        for operand in expr.operands_mut() {
+            folded |= Self::fold_loads(hints, operand);
+        }
+
+        if let Expr::Load(ref addr, bits, _) = expr {
+            let value = addr
+                .val()
+                .and_then(|addr| hints.read_constant(&Addr::from(addr.clone()), *bits));
+            if let Some(value) = value {
+                *expr = Expr::Val(value);
+                folded = true;
+            }
+        }
+
+        folded
+    }
+
+    pub fn solve(&self, sub: &Sub) -> DataflowResult<Constants> {
+        self.solve_with(sub, &sub.cfg())
+    }
+
+    pub fn solve_with(&self, sub: &Sub, cfg: &Cfg) -> DataflowResult<Constants> {
+        dataflow::solve(self, sub, cfg)
+    }
+}
+
+impl Dataflow for ConstantPropagation {
+    type Value = Constants;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn boundary(&self, sub: &Sub, blk: &Entity<Blk>) -> Constants {
+        if sub.entry().map(|entry| entry.id() == blk.id()).unwrap_or(false) {
+            Constants { values: Some(BTreeMap::new()) }
+        } else {
+            Constants::bottom()
+        }
+    }
+
+    fn transfer_phi(&self, phi: &Entity<Phi>, constants: &mut Constants) {
+        let mut values = phi
+            .choices()
+            .iter()
+            .map(|(_, expr)| self.evaluate(constants, expr));
+
+        let value = values.next().flatten().and_then(|value| {
+            if values.all(|v| v.as_ref() == Some(&value)) {
+                Some(value)
+            } else {
+                None
+            }
+        });
+
+        constants.assign(phi.var(), value);
+    }
+
+    fn transfer_def(&self, def: &Entity<Def>, constants: &mut Constants) {
+        if let Def::Assign(ref var, ref expr) = **def {
+            let value = self.evaluate(constants, expr);
+            constants.assign(var, value);
+        }
+    }
+
+    fn transfer_jmp(&self, jmp: &Entity<Jmp>, constants: &mut Constants) {
+        if let Jmp::Call(_, _) | Jmp::Intrinsic(_, _) = **jmp {
+            self.clobber(constants);
+        }
+    }
+
+    fn transfer_start(&self, blk: &Blk, constants: &mut Constants) {
+        if let (Some(hints), Some(addr)) = (self.hints.as_ref(), blk.addr()) {
+            for (var, value) in hints.registers_at(addr) {
+                constants.assign(var, Some(value.clone()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::Loc;
+    use crate::types::bv::BitVecT;
+
+    #[test]
+    fn test_clobbered_by_call() {
+        let typ = BitVecT::with_bits(32, false);
+        let reg = |name: &str| -> Var { Var::physical(name, typ).into() };
+        let (eax, ebx) = (reg("EAX"), reg("EBX"));
+        let tmp: Var = Var::transient("t", typ).into();
+
+        // EAX := 1; EBX := 2; t := 3; call 0x2000
+        let mut call = Blk::new(None);
+        let mut next = Blk::new(None);
+        call.add_def(Def::assign(eax.clone(), BitVec::from_u64(1, 32)));
+        call.add_def(Def::assign(ebx.clone(), BitVec::from_u64(2, 32)));
+        call.add_def(Def::assign(tmp.clone(), BitVec::from_u64(3, 32)));
+        call.add_jmp(Jmp::call(Loc::Fixed(Addr::from(0x2000u32)), []));
+        call.add_jmp(Jmp::branch(next.id()));
+        next.add_jmp(Jmp::return_(Expr::from(eax.clone())));
+        let (call_id, next_id) = (call.id(), next.id());
+        let sub: Entity<Sub> = Sub::new(None, None, vec![call, next]);
+
+        let mut analysis = ConstantPropagation::new();
+
+        let result = analysis.solve(&sub);
+        assert_eq!(result.exit(call_id).unwrap().get(&eax), None);
+        assert_eq!(result.entry(next_id).unwrap().get(&ebx), None);
+        assert_eq!(result.entry(next_id).unwrap().get(&tmp), Some(&BitVec::from_u64(3, 32)));
+
+        // callee-saved registers survive the call
+        analysis.preserve("EBX");
+
+        let result = analysis.solve(&sub);
+        assert_eq!(result.entry(next_id).unwrap().get(&eax), None);
+        assert_eq!(result.entry(next_id).unwrap().get(&ebx), Some(&BitVec::from_u64(2, 32)));
+    }
+}
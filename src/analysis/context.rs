@@ -0,0 +1,463 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::analysis::constants::{ConstantPropagation, Constants};
+use crate::analysis::dataflow::{self, Dataflow, DataflowResult, Direction, Lattice};
+use crate::ir::{Addr, Blk, Jmp, Loc, Sub};
+use crate::prelude::{Entity, Id, Identifiable};
+
+/// The (at most k) most recent call sites through which a Sub was
+/// reached, most recent last.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CallString {
+    sites: Vec<Id<Jmp>>,
+}
+
+impl CallString {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn sites(&self) -> &[Id<Jmp>] {
+        &self.sites
+    }
+
+    pub fn len(&self) -> usize {
+        self.sites.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sites.is_empty()
+    }
+
+    /// The call string extended by `site`, keeping the `k` most recent
+    /// call sites.
+    pub fn push(&self, site: impl Identifiable<Jmp>, k: usize) -> Self {
+        let mut sites = self.sites.clone();
+        sites.push(site.id());
+        if sites.len() > k {
+            sites.drain(..sites.len() - k);
+        }
+        Self { sites }
+    }
+}
+
+/// A forward dataflow analysis whose values can be propagated across
+/// calls.
+pub trait Interprocedural: Dataflow {
+    /// The value on entry to a callee, given the value before `call`.
+    #[allow(unused)]
+    fn call_entry(&self, call: &Entity<Jmp>, before: &Self::Value) -> Self::Value {
+        before.clone()
+    }
+
+    /// The value after `call`, given the value before it and the value
+    /// at the callee's returns.
+    #[allow(unused)]
+    fn call_return(
+        &self,
+        call: &Entity<Jmp>,
+        before: &Self::Value,
+        returned: &Self::Value,
+    ) -> Self::Value {
+        returned.clone()
+    }
+
+    /// The value after `call` when its callee is not known (e.g., it is
+    /// computed, or not among the Subs analysed).
+    #[allow(unused)]
+    fn call_unknown(&self, call: &Entity<Jmp>, before: &Self::Value) -> Self::Value {
+        before.clone()
+    }
+}
+
+impl Interprocedural for ConstantPropagation {
+    /// An unknown callee may write to any physical variable other than
+    /// those preserved (e.g., the caller-saved registers and the return
+    /// registers); see `ConstantPropagation::preserve`.
+    fn call_unknown(&self, _call: &Entity<Jmp>, before: &Constants) -> Constants {
+        let mut after = before.clone();
+        self.clobber(&mut after);
+        after
+    }
+}
+
+/// The fixed point of an interprocedural analysis: the result of the
+/// analysis for each Sub in each context (call string) it is reached in.
+#[derive(Debug, Clone)]
+pub struct ContextResult<V> {
+    results: BTreeMap<(Id<Sub>, CallString), DataflowResult<V>>,
+}
+
+impl<V> ContextResult<V> {
+    pub fn get(&self, sub: impl Identifiable<Sub>, context: &CallString) -> Option<&DataflowResult<V>> {
+        self.results.get(&(sub.id(), context.clone()))
+    }
+
+    /// The contexts `sub` is reached in, and its results in each.
+    pub fn contexts(
+        &self,
+        sub: impl Identifiable<Sub>,
+    ) -> impl Iterator<Item = (&CallString, &DataflowResult<V>)> + '_ {
+        let sub = sub.id();
+        self.results
+            .iter()
+            .filter(move |((id, _), _)| *id == sub)
+            .map(|((_, context), result)| (context, result))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Id<Sub>, &CallString, &DataflowResult<V>)> + '_ {
+        self.results
+            .iter()
+            .map(|((sub, context), result)| (*sub, context, result))
+    }
+}
+
+impl<V> ContextResult<V>
+where
+    V: Lattice,
+{
+    /// The value on entry to `blk` (of `sub`) joined over all contexts.
+    pub fn entry(&self, sub: impl Identifiable<Sub>, blk: impl Identifiable<Blk>) -> V {
+        let blk = blk.id();
+        self.contexts(sub)
+            .filter_map(|(_, result)| result.entry(blk))
+            .fold(V::bottom(), |mut value, other| {
+                value.join(other);
+                value
+            })
+    }
+
+    /// The value on exit from `blk` (of `sub`) joined over all contexts.
+    pub fn exit(&self, sub: impl Identifiable<Sub>, blk: impl Identifiable<Blk>) -> V {
+        let blk = blk.id();
+        self.contexts(sub)
+            .filter_map(|(_, result)| result.exit(blk))
+            .fold(V::bottom(), |mut value, other| {
+                value.join(other);
+                value
+            })
+    }
+}
+
+type Context = (Id<Sub>, CallString);
+
+/// Solves forward analyses across the Subs of a program with k-limited
+/// call-string context sensitivity.
+///
+/// Each Sub is analysed separately for each context it is reached in
+/// from the root Sub, where a context is the most recent k call sites
+/// leading to it; hence, values propagated into a Sub from different call
+/// sites are only merged when their call strings agree. Calls are
+/// resolved to the Subs starting at their fixed targets; the value after
+/// a call is derived from the join of the values at the callee's returns,
+/// or from the value before it, if its callee is not known (see
+/// `Interprocedural`). With k = 0 the analysis is context insensitive.
+#[derive(Debug, Clone)]
+pub struct CallStrings {
+    k: usize,
+}
+
+impl Default for CallStrings {
+    fn default() -> Self {
+        Self { k: 1 }
+    }
+}
+
+impl CallStrings {
+    pub fn new(k: usize) -> Self {
+        Self { k }
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Solves `analysis` from `root` across `subs` (which should include
+    /// `root`).
+    ///
+    /// # Panics
+    ///
+    /// If `analysis` is a backward analysis.
+    pub fn solve<'a, D>(
+        &self,
+        analysis: &D,
+        subs: impl IntoIterator<Item = &'a Entity<Sub>>,
+        root: impl Identifiable<Sub>,
+    ) -> ContextResult<D::Value>
+    where
+        D: Interprocedural,
+    {
+        assert_eq!(
+            D::DIRECTION,
+            Direction::Forward,
+            "call-string analyses must be forward analyses"
+        );
+
+        let subs = subs
+            .into_iter()
+            .map(|sub| (sub.id(), sub))
+            .collect::<BTreeMap<_, _>>();
+
+        let starts = subs
+            .values()
+            .filter_map(|sub| Some((sub.addr()?.clone(), sub.id())))
+            .collect::<BTreeMap<_, _>>();
+
+        let cfgs = subs
+            .iter()
+            .map(|(id, sub)| (*id, sub.cfg()))
+            .collect::<BTreeMap<_, _>>();
+
+        let root = (root.id(), CallString::new());
+
+        let mut state = State {
+            entries: BTreeMap::new(),
+            returns: BTreeMap::new(),
+            callers: BTreeMap::new(),
+        };
+
+        let mut results = BTreeMap::new();
+        let mut worklist = VecDeque::from([root.clone()]);
+        let mut queued = BTreeSet::from([root.clone()]);
+
+        while let Some(context) = worklist.pop_front() {
+            queued.remove(&context);
+
+            let sub = if let Some(sub) = subs.get(&context.0) {
+                sub
+            } else {
+                continue
+            };
+
+            let in_context = InContext {
+                analysis,
+                k: self.k,
+                starts: &starts,
+                context: &context,
+                root: context == root,
+                state: RefCell::new(state),
+                updated: RefCell::new(BTreeSet::new()),
+                returned: RefCell::new(D::Value::bottom()),
+            };
+
+            let result = dataflow::solve(&in_context, sub, &cfgs[&context.0]);
+
+            state = in_context.state.into_inner();
+
+            let mut updated = in_context.updated.into_inner();
+
+            let returned = in_context.returned.into_inner();
+            if state.returns.get(&context) != Some(&returned) {
+                state.returns.insert(context.clone(), returned);
+                updated.extend(state.callers.get(&context).into_iter().flatten().cloned());
+            }
+
+            results.insert(context, result);
+
+            for context in updated {
+                if queued.insert(context.clone()) {
+                    worklist.push_back(context);
+                }
+            }
+        }
+
+        ContextResult { results }
+    }
+}
+
+struct State<V> {
+    // the value on entry to each Sub in each context
+    entries: BTreeMap<Context, V>,
+    // the value at the returns of each Sub in each context
+    returns: BTreeMap<Context, V>,
+    // the contexts containing calls to each context
+    callers: BTreeMap<Context, BTreeSet<Context>>,
+}
+
+// an analysis of a Sub within a context, which records the values flowing
+// into its callees and the value at its returns
+struct InContext<'a, D>
+where
+    D: Interprocedural,
+{
+    analysis: &'a D,
+    k: usize,
+    starts: &'a BTreeMap<Addr, Id<Sub>>,
+    context: &'a Context,
+    root: bool,
+    state: RefCell<State<D::Value>>,
+    // the contexts whose entry values have changed
+    updated: RefCell<BTreeSet<Context>>,
+    returned: RefCell<D::Value>,
+}
+
+impl<'a, D> InContext<'a, D>
+where
+    D: Interprocedural,
+{
+    fn callee(&self, call: &Entity<Jmp>) -> Option<Context> {
+        match **call {
+            Jmp::Call(Loc::Fixed(ref addr), _) => {
+                let sub = *self.starts.get(addr)?;
+                Some((sub, self.context.1.push(call, self.k)))
+            }
+            _ => None,
+        }
+    }
+
+    fn call(&self, call: &Entity<Jmp>, value: &mut D::Value) {
+        let callee = if let Some(callee) = self.callee(call) {
+            callee
+        } else {
+            *value = self.analysis.call_unknown(call, value);
+            return
+        };
+
+        let mut state = self.state.borrow_mut();
+
+        state
+            .callers
+            .entry(callee.clone())
+            .or_default()
+            .insert(self.context.clone());
+
+        let entry = self.analysis.call_entry(call, value);
+        let analysed = state.returns.contains_key(&callee);
+        let current = state
+            .entries
+            .entry(callee.clone())
+            .or_insert_with(D::Value::bottom);
+
+        let mut joined = current.clone();
+        joined.join(&entry);
+
+        if joined != *current || !analysed {
+            *current = joined;
+            self.updated.borrow_mut().insert(callee.clone());
+        }
+
+        // until the callee is known to return, the point after the call is
+        // not reached
+        *value = match state.returns.get(&callee) {
+            Some(returned) => self.analysis.call_return(call, value, returned),
+            None => D::Value::bottom(),
+        };
+    }
+}
+
+impl<'a, D> Dataflow for InContext<'a, D>
+where
+    D: Interprocedural,
+{
+    type Value = D::Value;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn boundary(&self, sub: &Sub, blk: &Entity<Blk>) -> Self::Value {
+        let is_entry = sub.entry().map(|entry| entry.id() == blk.id()).unwrap_or(false);
+        if is_entry && !self.root {
+            self.state
+                .borrow()
+                .entries
+                .get(self.context)
+                .cloned()
+                .unwrap_or_else(D::Value::bottom)
+        } else {
+            self.analysis.boundary(sub, blk)
+        }
+    }
+
+    fn transfer_blk(&self, blk: &Blk, value: &mut Self::Value) {
+        self.analysis.transfer_start(blk, value);
+
+        for phi in blk.phis() {
+            self.analysis.transfer_phi(phi, value);
+        }
+
+        for def in blk.defs() {
+            self.analysis.transfer_def(def, value);
+        }
+
+        for jmp in blk.jmps() {
+            match **jmp {
+                Jmp::Call(_, _) => self.call(jmp, value),
+                Jmp::Return(_) => {
+                    self.returned.borrow_mut().join(value);
+                    self.analysis.transfer_jmp(jmp, value);
+                }
+                _ => self.analysis.transfer_jmp(jmp, value),
+            }
+        }
+    }
+}
+
+/// Propagates constants from `root` across `subs` with `k`-limited
+/// call-string context sensitivity.
+pub fn constants<'a>(
+    subs: impl IntoIterator<Item = &'a Entity<Sub>>,
+    root: impl Identifiable<Sub>,
+    k: usize,
+) -> ContextResult<Constants> {
+    CallStrings::new(k).solve(&ConstantPropagation::new(), subs, root)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::{BitVec, Def, Expr, Var};
+    use crate::types::bv::BitVecT;
+
+    fn reg(name: &str) -> Var {
+        Var::physical(name, BitVecT::with_bits(32, false)).into()
+    }
+
+    // a Sub at addr assigning 1 to EAX and 2 to EBX, then calling target
+    fn caller(addr: u32, target: u32) -> (Entity<Sub>, Id<Blk>) {
+        let mut call = Blk::new(None);
+        let mut next = Blk::new(None);
+        call.add_def(Def::assign(reg("EAX"), BitVec::from_u64(1, 32)));
+        call.add_def(Def::assign(reg("EBX"), BitVec::from_u64(2, 32)));
+        call.add_jmp(Jmp::call(Loc::Fixed(Addr::from(target)), []));
+        call.add_jmp(Jmp::branch(next.id()));
+        next.add_jmp(Jmp::return_(Expr::from(reg("EAX"))));
+        let next_id = next.id();
+
+        (Sub::new(None, Addr::from(addr), vec![call, next]), next_id)
+    }
+
+    #[test]
+    fn test_known_callee() {
+        // EAX := 5; return
+        let mut blk = Blk::new(None);
+        blk.add_def(Def::assign(reg("EAX"), BitVec::from_u64(5, 32)));
+        blk.add_jmp(Jmp::return_(Expr::from(reg("EAX"))));
+        let callee = Sub::new(None, Addr::from(0x1000u32), vec![blk]);
+
+        let (caller, next) = caller(0x2000, 0x1000);
+
+        let result = constants([&callee, &caller], &caller, 1);
+        let after = result.entry(&caller, next);
+        assert_eq!(after.get(&reg("EAX")), Some(&BitVec::from_u64(5, 32)));
+        assert_eq!(after.get(&reg("EBX")), Some(&BitVec::from_u64(2, 32)));
+    }
+
+    #[test]
+    fn test_unknown_callee() {
+        let (caller, next) = caller(0x2000, 0x3000);
+
+        let result = constants([&caller], &caller, 1);
+        let after = result.entry(&caller, next);
+        assert_eq!(after.get(&reg("EAX")), None);
+        assert_eq!(after.get(&reg("EBX")), None);
+
+        // callee-saved registers survive the call
+        let mut analysis = ConstantPropagation::new();
+        analysis.preserve("EBX");
+
+        let result = CallStrings::new(1).solve(&analysis, [&caller], &caller);
+        let after = result.entry(&caller, next);
+        assert_eq!(after.get(&reg("EAX")), None);
+        assert_eq!(after.get(&reg("EBX")), Some(&BitVec::from_u64(2, 32)));
+    }
+}
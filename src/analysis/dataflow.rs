@@ -0,0 +1,182 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::ir::{Blk, Cfg, Def, Jmp, Phi, Sub};
+use crate::prelude::{Entity, Id, Identifiable};
+
+/// The values computed by a dataflow analysis.
+pub trait Lattice: Clone + PartialEq {
+    /// The least element of the lattice, i.e., the value of program points
+    /// that have not (yet) been reached.
+    fn bottom() -> Self;
+
+    /// Updates `self` to the least upper bound of `self` and `other`.
+    fn join(&mut self, other: &Self);
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+/// A dataflow analysis over the Blks of a Sub.
+///
+/// For a forward analysis, each transfer function maps the value before a
+/// statement to the value after it; for a backward analysis, it maps the
+/// value after a statement to the value before it. Statements are visited
+/// in the order they are executed (or its reverse for backward analyses):
+/// phis, defs, then jmps.
+pub trait Dataflow {
+    type Value: Lattice;
+
+    const DIRECTION: Direction;
+
+    /// The value joined into the entry of `blk` (forward) or its exit
+    /// (backward), in addition to those of its predecessors (forward) or
+    /// successors (backward); e.g., the initial state on entry to the Sub,
+    /// or the state assumed when leaving the Sub via `blk`.
+    fn boundary(&self, sub: &Sub, blk: &Entity<Blk>) -> Self::Value;
+
+    #[allow(unused)]
+    fn transfer_phi(&self, phi: &Entity<Phi>, value: &mut Self::Value) {}
+    #[allow(unused)]
+    fn transfer_def(&self, def: &Entity<Def>, value: &mut Self::Value) {}
+    #[allow(unused)]
+    fn transfer_jmp(&self, jmp: &Entity<Jmp>, value: &mut Self::Value) {}
+    /// Transfers the value over the start of `blk`, before its phis, e.g.,
+    /// to apply facts known to hold at its address.
+    #[allow(unused)]
+    fn transfer_start(&self, blk: &Blk, value: &mut Self::Value) {}
+
+    fn transfer_blk(&self, blk: &Blk, value: &mut Self::Value) {
+        match Self::DIRECTION {
+            Direction::Forward => {
+                self.transfer_start(blk, value);
+                for phi in blk.phis() {
+                    self.transfer_phi(phi, value);
+                }
+                for def in blk.defs() {
+                    self.transfer_def(def, value);
+                }
+                for jmp in blk.jmps() {
+                    self.transfer_jmp(jmp, value);
+                }
+            }
+            Direction::Backward => {
+                for jmp in blk.jmps().iter().rev() {
+                    self.transfer_jmp(jmp, value);
+                }
+                for def in blk.defs().iter().rev() {
+                    self.transfer_def(def, value);
+                }
+                for phi in blk.phis().iter().rev() {
+                    self.transfer_phi(phi, value);
+                }
+                self.transfer_start(blk, value);
+            }
+        }
+    }
+}
+
+/// The fixed point of a dataflow analysis: the values on entry to (i.e.,
+/// before the first statement of) and exit from (i.e., after the last
+/// statement of) each Blk, irrespective of the analysis' direction.
+#[derive(Debug, Clone)]
+pub struct DataflowResult<V> {
+    entry: BTreeMap<Id<Blk>, V>,
+    exit: BTreeMap<Id<Blk>, V>,
+}
+
+impl<V> DataflowResult<V> {
+    pub fn entry(&self, blk: impl Identifiable<Blk>) -> Option<&V> {
+        self.entry.get(&blk.id())
+    }
+
+    pub fn exit(&self, blk: impl Identifiable<Blk>) -> Option<&V> {
+        self.exit.get(&blk.id())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Id<Blk>, &V, &V)> + '_ {
+        self.entry
+            .iter()
+            .map(move |(id, entry)| (*id, entry, &self.exit[id]))
+    }
+}
+
+/// Solves `analysis` over `sub` using a worklist algorithm; Blks are
+/// initially visited in reverse post-order (forward) or post-order
+/// (backward) of `cfg`.
+pub fn solve<D>(analysis: &D, sub: &Sub, cfg: &Cfg) -> DataflowResult<D::Value>
+where
+    D: Dataflow,
+{
+    let blks = sub
+        .blks()
+        .iter()
+        .map(|blk| (blk.id(), blk))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut order = cfg.reverse_post_order();
+    if D::DIRECTION == Direction::Backward {
+        order.reverse();
+    }
+
+    let boundaries = blks
+        .iter()
+        .map(|(id, blk)| (*id, analysis.boundary(sub, blk)))
+        .collect::<BTreeMap<_, _>>();
+
+    // values flowing into (input) and out of (output) each Blk w.r.t. the
+    // direction of the analysis
+    let mut input = BTreeMap::new();
+    let mut output = blks
+        .keys()
+        .map(|id| (*id, D::Value::bottom()))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut worklist = order.iter().copied().collect::<VecDeque<_>>();
+    let mut queued = order.iter().copied().collect::<BTreeSet<_>>();
+
+    while let Some(id) = worklist.pop_front() {
+        queued.remove(&id);
+
+        let blk = if let Some(blk) = blks.get(&id) {
+            blk
+        } else {
+            continue
+        };
+
+        let mut value = boundaries[&id].clone();
+        let sources = match D::DIRECTION {
+            Direction::Forward => cfg.predecessors(id).collect::<Vec<_>>(),
+            Direction::Backward => cfg.successors(id).collect::<Vec<_>>(),
+        };
+        for source in sources {
+            value.join(&output[&source]);
+        }
+
+        input.insert(id, value.clone());
+        analysis.transfer_blk(blk, &mut value);
+
+        if output[&id] != value {
+            output.insert(id, value);
+
+            let sinks = match D::DIRECTION {
+                Direction::Forward => cfg.successors(id).collect::<Vec<_>>(),
+                Direction::Backward => cfg.predecessors(id).collect::<Vec<_>>(),
+            };
+            for sink in sinks {
+                if queued.insert(sink) {
+                    worklist.push_back(sink);
+                }
+            }
+        }
+    }
+
+    let (entry, exit) = match D::DIRECTION {
+        Direction::Forward => (input, output),
+        Direction::Backward => (output, input),
+    };
+
+    DataflowResult { entry, exit }
+}
@@ -0,0 +1,262 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::analysis::dataflow::{Dataflow, DataflowResult};
+use crate::analysis::vsa::{AbsRegion, StridedInterval, ValueSetAnalysis, ValueSets};
+use crate::ir::{Blk, Def, Expr, Sub, Var};
+use crate::prelude::{Entity, Id, Identifiable};
+use crate::types::Type;
+use crate::types::bv::BitVecT;
+
+/// Where a frame member lies relative to the stack pointer on entry to its
+/// Sub.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SlotKind {
+    /// Below the stack pointer on entry: allocated by the Sub itself
+    Local,
+    /// At or above the stack pointer on entry: allocated by the caller,
+    /// e.g., stack arguments (and, where calls push it, the return
+    /// address)
+    Argument,
+}
+
+/// A slot of a stack frame: one or more (for arrays) elements accessed at
+/// a fixed offset from the stack pointer on entry to the Sub.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameMember {
+    offset: i64,
+    bits: u32,
+    count: usize,
+    kind: SlotKind,
+    reads: BTreeSet<Id<Def>>,
+    writes: BTreeSet<Id<Def>>,
+}
+
+impl FrameMember {
+    /// The offset of the member from the stack pointer on entry.
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    pub fn kind(&self) -> SlotKind {
+        self.kind
+    }
+
+    /// The number of bits of each element of the member.
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// The number of elements of the member; members accessed at a range
+    /// of offsets are arrays.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.count > 1
+    }
+
+    /// The number of bytes spanned by the member.
+    pub fn size(&self) -> usize {
+        (self.bits as usize + 7) / 8 * self.count
+    }
+
+    /// The type of each element of the member: an (unsigned) bit-vector of
+    /// the width of its widest access.
+    pub fn element_type(&self) -> Id<Type> {
+        BitVecT::with_bits(self.bits, false).id()
+    }
+
+    /// The Defs that load from the member.
+    pub fn reads(&self) -> &BTreeSet<Id<Def>> {
+        &self.reads
+    }
+
+    /// The Defs that store to the member.
+    pub fn writes(&self) -> &BTreeSet<Id<Def>> {
+        &self.writes
+    }
+}
+
+/// The stack frame of a Sub: the height of the stack pointer (relative to
+/// its value on entry) at each Blk, and the members of the frame accessed
+/// by the Sub's loads and stores; see `Sub::stack_frame`.
+///
+/// Heights and offsets are computed by a value-set analysis (see
+/// `ValueSetAnalysis`), so that accesses via registers copied from the
+/// stack pointer (e.g., a frame pointer) are included; accesses at offsets
+/// that are not bounded are not.
+#[derive(Debug, Clone)]
+pub struct StackFrame {
+    stack_pointer: Var,
+    heights: BTreeMap<Id<Blk>, (Option<i64>, Option<i64>)>,
+    members: BTreeMap<i64, FrameMember>,
+}
+
+impl StackFrame {
+    pub fn new(sub: &Sub, stack_pointer: &Var) -> Self {
+        Self::with_analysis(sub, &ValueSetAnalysis::new(Some(stack_pointer.clone())))
+    }
+
+    // reconstructs the frame of sub using analysis, whose stack pointer must
+    // be known
+    pub(crate) fn with_analysis(sub: &Sub, analysis: &ValueSetAnalysis) -> Self {
+        // unwrap is safe here: frames are only built with a stack pointer
+        let stack_pointer = analysis.stack_pointer().cloned().unwrap();
+        let result = analysis.solve(sub);
+
+        let mut frame = Self {
+            heights: BTreeMap::new(),
+            members: BTreeMap::new(),
+            stack_pointer,
+        };
+
+        frame.record_heights(&result);
+        for blk in sub.blks() {
+            frame.record_accesses(analysis, blk, &result);
+        }
+        frame
+    }
+
+    fn height(&self, sets: &ValueSets) -> Option<i64> {
+        sets.get(&self.stack_pointer)
+            .only(AbsRegion::Stack)
+            .and_then(|si| si.as_constant().map(|offset| StridedInterval::signed(offset, si.bits())))
+    }
+
+    fn record_heights(&mut self, result: &DataflowResult<ValueSets>) {
+        for (blk, entry, exit) in result.iter() {
+            if entry.is_reachable() {
+                let heights = (self.height(entry), self.height(exit));
+                self.heights.insert(blk, heights);
+            }
+        }
+    }
+
+    fn record_accesses(&mut self, analysis: &ValueSetAnalysis, blk: &Entity<Blk>, result: &DataflowResult<ValueSets>) {
+        let mut sets = match result.entry(blk.id()) {
+            Some(sets) if sets.is_reachable() => sets.clone(),
+            _ => return,
+        };
+
+        analysis.transfer_start(blk, &mut sets);
+        for phi in blk.phis() {
+            analysis.transfer_phi(phi, &mut sets);
+        }
+
+        for def in blk.defs() {
+            let mut loads = Vec::new();
+            match **def {
+                Def::Assign(_, ref expr) | Def::Assume(ref expr) => {
+                    Self::collect_loads(expr, &mut loads);
+                }
+                Def::Store(ref addr, ref value, bits, _) => {
+                    Self::collect_loads(addr, &mut loads);
+                    Self::collect_loads(value, &mut loads);
+                    if let Some(si) = analysis.evaluate(&sets, addr).only(AbsRegion::Stack) {
+                        self.record(def.id(), si, bits, true);
+                    }
+                }
+            }
+
+            for (addr, bits) in loads {
+                if let Some(si) = analysis.evaluate(&sets, addr).only(AbsRegion::Stack) {
+                    self.record(def.id(), si, bits, false);
+                }
+            }
+
+            analysis.transfer_def(def, &mut sets);
+        }
+    }
+
+    fn collect_loads<'a>(expr: &'a Expr, loads: &mut Vec<(&'a Expr, u32)>) {
+        if let Expr::Load(ref addr, bits, _) = expr {
+            loads.push((addr, *bits));
+        }
+        for operand in expr.operands() {
+            Self::collect_loads(operand, loads);
+        }
+    }
+
+    fn record(&mut self, def: Id<Def>, si: &StridedInterval, bits: u32, write: bool) {
+        let offset = StridedInterval::signed(si.lo(), si.bits());
+        // accesses must not wrap around the stack pointer on entry
+        if offset.checked_add((si.hi() - si.lo()) as i64).is_none() {
+            return
+        }
+
+        // elements accessed with a stride wider than their size are padded
+        let bits = bits.max(si.stride().saturating_mul(8).min(u32::MAX as u64) as u32);
+        let count = usize::try_from(si.count()).unwrap_or(usize::MAX);
+        let kind = if offset < 0 { SlotKind::Local } else { SlotKind::Argument };
+
+        let member = self.members.entry(offset).or_insert_with(|| FrameMember {
+            offset,
+            bits,
+            count,
+            kind,
+            reads: BTreeSet::new(),
+            writes: BTreeSet::new(),
+        });
+
+        member.bits = member.bits.max(bits);
+        member.count = member.count.max(count);
+        if write {
+            member.writes.insert(def);
+        } else {
+            member.reads.insert(def);
+        }
+    }
+
+    pub fn stack_pointer(&self) -> &Var {
+        &self.stack_pointer
+    }
+
+    /// The height of the stack pointer on entry to `blk`, relative to its
+    /// value on entry to the Sub, if it is fixed.
+    pub fn height_at_entry(&self, blk: impl Identifiable<Blk>) -> Option<i64> {
+        self.heights.get(&blk.id()).and_then(|(entry, _)| *entry)
+    }
+
+    /// The height of the stack pointer on exit from `blk`, relative to its
+    /// value on entry to the Sub, if it is fixed.
+    pub fn height_at_exit(&self, blk: impl Identifiable<Blk>) -> Option<i64> {
+        self.heights.get(&blk.id()).and_then(|(_, exit)| *exit)
+    }
+
+    /// The members of the frame, by offset.
+    pub fn members(&self) -> impl Iterator<Item = &FrameMember> {
+        self.members.values()
+    }
+
+    pub fn locals(&self) -> impl Iterator<Item = &FrameMember> {
+        self.members().filter(|member| member.kind == SlotKind::Local)
+    }
+
+    pub fn arguments(&self) -> impl Iterator<Item = &FrameMember> {
+        self.members().filter(|member| member.kind == SlotKind::Argument)
+    }
+
+    /// The member spanning `offset`, if any.
+    pub fn member_at(&self, offset: i64) -> Option<&FrameMember> {
+        self.members
+            .range(..=offset)
+            .rev()
+            .map(|(_, member)| member)
+            .find(|member| offset < member.offset.saturating_add(member.size() as i64))
+    }
+
+    /// The number of bytes of the frame allocated by the Sub, i.e., the
+    /// depth of the lowest local, or of the stack pointer, whichever is
+    /// lower.
+    pub fn local_size(&self) -> u64 {
+        let lowest_local = self.locals().map(|member| member.offset).min().unwrap_or(0);
+        let lowest_height = self.heights
+            .values()
+            .flat_map(|(entry, exit)| entry.iter().chain(exit.iter()))
+            .copied()
+            .min()
+            .unwrap_or(0);
+        lowest_local.min(lowest_height).min(0).unsigned_abs()
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::ir::{Addr, BitVec, Var};
+use crate::prelude::Endian;
+use crate::types::bv::BitVecT;
+
+#[derive(Debug, Error)]
+pub enum HintError {
+    #[error("line {0}: {1}")]
+    Json(usize, #[source] serde_json::Error),
+    #[error("line {0}: malformed hint {1}")]
+    Malformed(usize, String),
+}
+
+/// A fact asserted by the user, which analyses treat as ground truth.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hint {
+    /// The register `var` holds `value` before the instruction at `addr`
+    Register { addr: Addr, var: Var, value: BitVec },
+    /// The call at `addr`, or any call to the Sub starting at `addr`, never
+    /// returns
+    NoReturn { addr: Addr },
+    /// The memory at `addr` holds `bytes` and is never modified
+    ConstantMemory {
+        addr: Addr,
+        endian: Endian,
+        bytes: Arc<[u8]>,
+    },
+}
+
+/// The hints asserted for a program, in the order they were asserted.
+///
+/// Hints can be written to, and read from, JSON lines (see `to_jsonl`), so
+/// that they can be replayed into other Projects; e.g.:
+///
+/// ```json
+/// { "hint": "register", "addr": "0x401000", "register": "RDI", "bits": 64, "value": "0x10" }
+/// { "hint": "no_return", "addr": "0x401200" }
+/// { "hint": "constant", "addr": "0x402000", "endian": "little", "bytes": "00104000" }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Hints {
+    hints: Vec<Hint>,
+    registers: BTreeMap<Addr, Vec<(Var, BitVec)>>,
+    no_return: BTreeSet<Addr>,
+    memory: BTreeMap<Addr, (Endian, Arc<[u8]>)>,
+}
+
+impl Hints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, hint: Hint) -> &mut Self {
+        match hint {
+            Hint::Register { ref addr, ref var, ref value } => {
+                let values = self.registers.entry(addr.clone()).or_default();
+                values.retain(|(rvar, _)| rvar != var);
+                values.push((var.clone(), value.clone()));
+            }
+            Hint::NoReturn { ref addr } => {
+                self.no_return.insert(addr.clone());
+            }
+            Hint::ConstantMemory { ref addr, endian, ref bytes } => {
+                self.memory.insert(addr.clone(), (endian, bytes.clone()));
+            }
+        }
+        self.hints.push(hint);
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Hint> {
+        self.hints.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.hints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hints.is_empty()
+    }
+
+    /// The values of registers asserted before the instruction at `addr`.
+    pub fn registers_at(&self, addr: &Addr) -> &[(Var, BitVec)] {
+        self.registers.get(addr).map(|values| &values[..]).unwrap_or(&[])
+    }
+
+    pub fn is_no_return(&self, addr: &Addr) -> bool {
+        self.no_return.contains(addr)
+    }
+
+    /// Reads a value of `bits` bits from memory asserted to be constant;
+    /// returns None if any of its bytes are not known to be constant.
+    pub fn read_constant(&self, addr: &Addr, bits: u32) -> Option<BitVec> {
+        if bits % 8 != 0 {
+            return None
+        }
+        let size = bits as usize / 8;
+
+        let (start, (endian, bytes)) = self.memory.range(..=addr.clone()).next_back()?;
+        let offset = addr.absolute_difference(start)?;
+        let bytes = bytes.get(offset..offset + size)?;
+
+        Some(if endian.is_little() {
+            BitVec::from_le_bytes(bytes).unsigned()
+        } else {
+            BitVec::from_be_bytes(bytes).unsigned()
+        })
+    }
+
+    /// The hints as JSON lines, in the order they were asserted.
+    pub fn to_jsonl(&self) -> String {
+        let mut text = String::new();
+
+        for hint in self.hints.iter() {
+            let mut object = Map::new();
+            match hint {
+                Hint::Register { addr, var, value } => {
+                    let bits = value.bits();
+                    let mut bytes = vec![0u8; (bits + 7) / 8];
+                    value.to_be_bytes(&mut bytes);
+
+                    object.insert("hint".to_owned(), Value::from("register"));
+                    object.insert("addr".to_owned(), Value::from(hex_address(addr)));
+                    object.insert("register".to_owned(), Value::from(&**var.name()));
+                    object.insert("bits".to_owned(), Value::from(bits as u64));
+                    object.insert("value".to_owned(), Value::from(format!("0x{}", hex(&bytes))));
+                }
+                Hint::NoReturn { addr } => {
+                    object.insert("hint".to_owned(), Value::from("no_return"));
+                    object.insert("addr".to_owned(), Value::from(hex_address(addr)));
+                }
+                Hint::ConstantMemory { addr, endian, bytes } => {
+                    let endian = if endian.is_little() { "little" } else { "big" };
+                    object.insert("hint".to_owned(), Value::from("constant"));
+                    object.insert("addr".to_owned(), Value::from(hex_address(addr)));
+                    object.insert("endian".to_owned(), Value::from(endian));
+                    object.insert("bytes".to_owned(), Value::from(hex(bytes)));
+                }
+            }
+            text.push_str(&Value::Object(object).to_string());
+            text.push('\n');
+        }
+
+        text
+    }
+
+    /// Reads hints from JSON lines (see `to_jsonl`), whose addresses are
+    /// `bits` wide.
+    pub fn from_jsonl(text: &str, bits: u32) -> Result<Self, HintError> {
+        let mut hints = Self::new();
+
+        for (i, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let line_no = i + 1;
+            let value = line
+                .parse::<Value>()
+                .map_err(|e| HintError::Json(line_no, e))?;
+            let malformed = || HintError::Malformed(line_no, line.to_owned());
+
+            let field = |name: &str| value.get(name).and_then(|value| value.as_str()).ok_or_else(malformed);
+            let addr = parse_hex(field("addr")?)
+                .and_then(|bytes| bytes_to_u64(&bytes))
+                .map(|addr| Addr::from(addr).as_bits(bits))
+                .ok_or_else(malformed)?;
+
+            let hint = match field("hint")? {
+                "register" => {
+                    let rbits = value
+                        .get("bits")
+                        .and_then(|bits| bits.as_u64())
+                        .ok_or_else(malformed)? as u32;
+                    let bytes = parse_hex(field("value")?).ok_or_else(malformed)?;
+                    let value = BitVec::from_be_bytes(&bytes).unsigned().cast(rbits as usize);
+                    let var = Var::physical(field("register")?, BitVecT::with_bits(rbits, false)).into();
+                    Hint::Register { addr, var, value }
+                }
+                "no_return" => Hint::NoReturn { addr },
+                "constant" => {
+                    let endian = match field("endian")? {
+                        "little" => Endian::Little,
+                        "big" => Endian::Big,
+                        _ => return Err(malformed()),
+                    };
+                    let bytes = parse_hex(field("bytes")?).ok_or_else(malformed)?;
+                    Hint::ConstantMemory {
+                        addr,
+                        endian,
+                        bytes: bytes.into(),
+                    }
+                }
+                _ => return Err(malformed()),
+            };
+
+            hints.add(hint);
+        }
+
+        Ok(hints)
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hex_address(addr: &Addr) -> String {
+    format!("{:#x}", u64::try_from(addr).unwrap_or(0))
+}
+
+// parses (optionally 0x-prefixed) hexadecimal digits as big-endian bytes
+fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    let digits = text.trim();
+    let digits = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+        .unwrap_or(digits);
+    let padded = if digits.len() % 2 == 0 {
+        digits.to_owned()
+    } else {
+        format!("0{}", digits)
+    };
+
+    (0..padded.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(padded.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn bytes_to_u64(bytes: &[u8]) -> Option<u64> {
+    let significant = bytes.iter().skip_while(|b| **b == 0).count();
+    if significant > 8 {
+        return None
+    }
+    Some(bytes.iter().fold(0u64, |value, b| (value << 8) | *b as u64))
+}
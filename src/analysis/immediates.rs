@@ -0,0 +1,409 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::sync::Arc;
+
+use crate::ir::{Def, Expr, Jmp, Loc, Sub};
+use crate::prelude::{Endian, Entity, Erased, Id, Identifiable};
+use crate::types::enumeration::EnumT;
+
+/// What an immediate is known to denote.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ConstantNote {
+    /// A member of an enumeration (see `ConstantPool::add_enum`)
+    EnumMember {
+        enumeration: Option<Arc<str>>,
+        variant: Arc<str>,
+    },
+    /// An error code, e.g., `-EINVAL` or `STATUS_ACCESS_VIOLATION`
+    ErrorCode(Arc<str>),
+    /// Printable ASCII characters, in memory order
+    Ascii(String),
+    /// A constant known to identify an algorithm or format, e.g., the
+    /// multiplier of MurmurHash2
+    Magic(Arc<str>),
+}
+
+impl Display for ConstantNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EnumMember { enumeration: Some(enumeration), variant } => {
+                write!(f, "{}::{}", enumeration, variant)
+            }
+            Self::EnumMember { enumeration: None, variant } => write!(f, "{}", variant),
+            Self::ErrorCode(name) | Self::Magic(name) => write!(f, "{}", name),
+            Self::Ascii(text) => write!(f, "{:?}", text),
+        }
+    }
+}
+
+/// An immediate of a statement, and the notes found for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Immediate {
+    value: u64,
+    bits: u32,
+    notes: Vec<ConstantNote>,
+}
+
+impl Immediate {
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn notes(&self) -> &[ConstantNote] {
+        &self.notes
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum MagicKind {
+    Magic,
+    ErrorCode,
+}
+
+/// A database of magic numbers and error codes, keyed by their values;
+/// entries may be restricted to immediates of a given width.
+#[derive(Debug, Clone, Default)]
+pub struct MagicNumbers {
+    entries: BTreeMap<u64, Vec<(Option<u32>, MagicKind, Arc<str>)>>,
+}
+
+const ERRNO: &[(u64, &str)] = &[
+    (1, "EPERM"), (2, "ENOENT"), (3, "ESRCH"), (4, "EINTR"), (5, "EIO"),
+    (6, "ENXIO"), (7, "E2BIG"), (8, "ENOEXEC"), (9, "EBADF"), (10, "ECHILD"),
+    (11, "EAGAIN"), (12, "ENOMEM"), (13, "EACCES"), (14, "EFAULT"), (16, "EBUSY"),
+    (17, "EEXIST"), (18, "EXDEV"), (19, "ENODEV"), (20, "ENOTDIR"), (21, "EISDIR"),
+    (22, "EINVAL"), (23, "ENFILE"), (24, "EMFILE"), (25, "ENOTTY"), (27, "EFBIG"),
+    (28, "ENOSPC"), (29, "ESPIPE"), (30, "EROFS"), (31, "EMLINK"), (32, "EPIPE"),
+    (34, "ERANGE"), (35, "EDEADLK"), (36, "ENAMETOOLONG"), (38, "ENOSYS"),
+    (39, "ENOTEMPTY"), (40, "ELOOP"), (95, "EOPNOTSUPP"), (98, "EADDRINUSE"),
+    (104, "ECONNRESET"), (110, "ETIMEDOUT"), (111, "ECONNREFUSED"),
+];
+
+const STATUS: &[(u64, &str)] = &[
+    (0x80000005, "STATUS_BUFFER_OVERFLOW"),
+    (0xc0000005, "STATUS_ACCESS_VIOLATION"),
+    (0xc000000d, "STATUS_INVALID_PARAMETER"),
+    (0xc0000017, "STATUS_NO_MEMORY"),
+    (0xc0000022, "STATUS_ACCESS_DENIED"),
+    (0xc0000034, "STATUS_OBJECT_NAME_NOT_FOUND"),
+    (0xc00000bb, "STATUS_NOT_SUPPORTED"),
+    (0xc0000409, "STATUS_STACK_BUFFER_OVERRUN"),
+    (0x80004001, "E_NOTIMPL"),
+    (0x80004003, "E_POINTER"),
+    (0x80004005, "E_FAIL"),
+    (0x8007000e, "E_OUTOFMEMORY"),
+    (0x80070057, "E_INVALIDARG"),
+];
+
+const MAGIC: &[(u64, Option<u32>, &str)] = &[
+    (0x5bd1e995, None, "MurmurHash2 multiplier"),
+    (0xcc9e2d51, None, "MurmurHash3 c1"),
+    (0x1b873593, None, "MurmurHash3 c2"),
+    (0x811c9dc5, None, "FNV-1 32-bit offset basis"),
+    (0x01000193, None, "FNV-1 32-bit prime"),
+    (0xcbf29ce484222325, None, "FNV-1 64-bit offset basis"),
+    (0x00000100000001b3, None, "FNV-1 64-bit prime"),
+    (0xedb88320, None, "CRC-32 polynomial (reversed)"),
+    (0x04c11db7, None, "CRC-32 polynomial"),
+    (0x82f63b78, None, "CRC-32C polynomial (reversed)"),
+    (0x9e3779b9, None, "golden ratio (TEA, hash mixing)"),
+    (0x9e3779b97f4a7c15, None, "golden ratio (64-bit)"),
+    (0x67452301, None, "MD5/SHA-1 initial state"),
+    (0xefcdab89, None, "MD5/SHA-1 initial state"),
+    (0x98badcfe, None, "MD5/SHA-1 initial state"),
+    (0x10325476, None, "MD5/SHA-1 initial state"),
+    (0xc3d2e1f0, None, "SHA-1 initial state"),
+    (0x6a09e667, None, "SHA-256 initial state"),
+    (0xbb67ae85, None, "SHA-256 initial state"),
+    (0x428a2f98, None, "SHA-256 round constant"),
+    (0x6a09e667f3bcc908, None, "SHA-512 initial state"),
+    (0x464c457f, Some(32), "ELF magic"),
+    (0x00905a4d, Some(32), "PE/MZ header"),
+    (0x00004550, Some(32), "PE signature"),
+    (0xfeedface, None, "Mach-O magic (32-bit)"),
+    (0xfeedfacf, None, "Mach-O magic (64-bit)"),
+    (0xcafebabe, None, "Java class/Mach-O fat magic"),
+    (0xdeadbeef, None, "debug fill pattern"),
+    (0xbaadf00d, None, "debug fill pattern (uninitialised heap)"),
+    (0xfeeefeee, None, "debug fill pattern (freed heap)"),
+];
+
+impl MagicNumbers {
+    /// An empty database.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A database of well-known magic numbers (e.g., of hash functions and
+    /// file formats), Linux errno values (as negative return values), and
+    /// Windows NTSTATUS and HRESULT error codes.
+    pub fn builtin() -> Self {
+        let mut magic = Self::new();
+        for (value, name) in ERRNO {
+            magic.insert(value.wrapping_neg(), None, MagicKind::ErrorCode, &format!("-{}", name));
+        }
+        for (value, name) in STATUS {
+            magic.insert(*value, Some(32), MagicKind::ErrorCode, name);
+        }
+        for (value, bits, name) in MAGIC {
+            magic.insert(*value, *bits, MagicKind::Magic, name);
+        }
+        magic
+    }
+
+    fn insert(&mut self, value: u64, bits: Option<u32>, kind: MagicKind, name: &str) {
+        self.entries.entry(value).or_default().push((bits, kind, Arc::from(name)));
+    }
+
+    /// Notes immediates equal to `value`, of any width, as `name`.
+    pub fn add(&mut self, value: u64, name: impl AsRef<str>) -> &mut Self {
+        self.insert(value, None, MagicKind::Magic, name.as_ref());
+        self
+    }
+
+    /// Notes immediates of `bits` bits equal to `value` as `name`.
+    pub fn add_with_bits(&mut self, value: u64, bits: u32, name: impl AsRef<str>) -> &mut Self {
+        self.insert(value, Some(bits), MagicKind::Magic, name.as_ref());
+        self
+    }
+
+    /// Notes immediates equal to `value` as the error code `name`; negative
+    /// codes match immediates of any width.
+    pub fn add_error_code(&mut self, value: i64, name: impl AsRef<str>) -> &mut Self {
+        self.insert(value as u64, None, MagicKind::ErrorCode, name.as_ref());
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn lookup(&self, value: u64, bits: u32) -> Vec<ConstantNote> {
+        let mut candidates = vec![value];
+        // negative values narrower than 64 bits also match their sign
+        // extensions, e.g., -EINVAL as a 32-bit immediate
+        if bits > 0 && bits < 64 && value >> (bits - 1) & 1 == 1 {
+            candidates.push(value | (!0u64 << bits));
+        }
+
+        candidates
+            .into_iter()
+            .flat_map(|candidate| self.entries.get(&candidate).into_iter().flatten())
+            .filter(|(ebits, _, _)| ebits.map(|ebits| ebits == bits).unwrap_or(true))
+            .map(|(_, kind, name)| match kind {
+                MagicKind::Magic => ConstantNote::Magic(name.clone()),
+                MagicKind::ErrorCode => ConstantNote::ErrorCode(name.clone()),
+            })
+            .collect()
+    }
+}
+
+/// Maps the immediates of a Sub's statements to what they are known to
+/// denote: members of enumerations, error codes, ASCII text, and magic
+/// numbers (see `MagicNumbers`).
+///
+/// Members of enumerations are noted for every immediate with their value,
+/// regardless of how it is used, so only enumerations whose values are
+/// distinctive (e.g., flags, or error codes) should be added; immediates
+/// below `min_value` are never noted.
+#[derive(Debug, Clone)]
+pub struct ConstantPool {
+    magic: MagicNumbers,
+    enums: Vec<EnumT>,
+    endian: Endian,
+    min_ascii: usize,
+    min_value: u64,
+}
+
+impl ConstantPool {
+    /// A pool using the builtin magic numbers (see `MagicNumbers::builtin`),
+    /// reading ASCII text in the byte order `endian`.
+    pub fn new(endian: Endian) -> Self {
+        Self {
+            magic: MagicNumbers::builtin(),
+            enums: Vec::new(),
+            endian,
+            min_ascii: 3,
+            min_value: 2,
+        }
+    }
+
+    pub fn magic_numbers(&self) -> &MagicNumbers {
+        &self.magic
+    }
+
+    pub fn magic_numbers_mut(&mut self) -> &mut MagicNumbers {
+        &mut self.magic
+    }
+
+    pub fn add_enum(&mut self, enumeration: EnumT) -> &mut Self {
+        self.enums.push(enumeration);
+        self
+    }
+
+    /// The minimum number of printable characters of immediates noted as
+    /// ASCII text.
+    pub fn min_ascii(&mut self, count: usize) -> &mut Self {
+        self.min_ascii = count.max(1);
+        self
+    }
+
+    /// The minimum (unsigned) value of immediates noted.
+    pub fn min_value(&mut self, value: u64) -> &mut Self {
+        self.min_value = value;
+        self
+    }
+
+    /// The notes for an immediate `value` of `bits` bits.
+    pub fn notes(&self, value: u64, bits: u32) -> Vec<ConstantNote> {
+        if value < self.min_value || bits > 64 {
+            return Vec::new()
+        }
+
+        let mut notes = Vec::new();
+
+        let signed = if bits > 0 && bits < 64 && value >> (bits - 1) & 1 == 1 {
+            (value | (!0u64 << bits)) as i64
+        } else {
+            value as i64
+        };
+        for enumeration in self.enums.iter() {
+            if let Some(variant) = enumeration
+                .variant_named(value as i128)
+                .or_else(|| enumeration.variant_named(signed as i128))
+            {
+                notes.push(ConstantNote::EnumMember {
+                    enumeration: enumeration.name().cloned(),
+                    variant: variant.clone(),
+                });
+            }
+        }
+
+        notes.extend(self.magic.lookup(value, bits));
+        notes.extend(self.ascii(value, bits));
+
+        notes
+    }
+
+    fn ascii(&self, value: u64, bits: u32) -> Option<ConstantNote> {
+        let bytes = value.to_le_bytes();
+        let mut bytes = bytes[..(bits as usize / 8).min(8)].to_vec();
+        if matches!(self.endian, Endian::Big) {
+            bytes.reverse();
+        }
+
+        // text may be followed by NULs (e.g., the terminator of a string)
+        while bytes.last() == Some(&0) {
+            bytes.pop();
+        }
+
+        if bytes.len() < self.min_ascii || !bytes.iter().all(|b| (0x20..0x7f).contains(b)) {
+            return None
+        }
+
+        Some(ConstantNote::Ascii(bytes.into_iter().map(char::from).collect()))
+    }
+
+    fn collect(&self, expr: &Expr, immediates: &mut Vec<Immediate>) {
+        if let Expr::Val(bv) = expr {
+            if let Some(value) = bv.to_u64() {
+                let bits = bv.bits() as u32;
+                let notes = self.notes(value, bits);
+                if !notes.is_empty() {
+                    immediates.push(Immediate { value, bits, notes });
+                }
+            }
+        }
+        for operand in expr.operands() {
+            self.collect(operand, immediates);
+        }
+    }
+
+    /// The noted immediates of each Def and Jmp of `sub`, keyed by the ids
+    /// of the statements containing them.
+    pub fn scan(&self, sub: &Sub) -> BTreeMap<Id<Erased>, Vec<Immediate>> {
+        let mut found = BTreeMap::new();
+
+        for blk in sub.blks() {
+            for def in blk.defs() {
+                let mut immediates = Vec::new();
+                match **def {
+                    Def::Assign(_, ref expr) | Def::Assume(ref expr) => self.collect(expr, &mut immediates),
+                    Def::Store(ref addr, ref value, _, _) => {
+                        self.collect(addr, &mut immediates);
+                        self.collect(value, &mut immediates);
+                    }
+                }
+                Self::record(&mut found, def, immediates);
+            }
+
+            for jmp in blk.jmps() {
+                let mut immediates = Vec::new();
+                match **jmp {
+                    Jmp::Branch(ref loc) | Jmp::Return(ref loc) => self.collect_loc(loc, &mut immediates),
+                    Jmp::CBranch(ref loc, ref cond) => {
+                        self.collect_loc(loc, &mut immediates);
+                        self.collect(cond, &mut immediates);
+                    }
+                    Jmp::Call(ref loc, ref args) => {
+                        self.collect_loc(loc, &mut immediates);
+                        for arg in args.iter() {
+                            self.collect(arg, &mut immediates);
+                        }
+                    }
+                    Jmp::Intrinsic(_, ref args) => {
+                        for arg in args.iter() {
+                            self.collect(arg, &mut immediates);
+                        }
+                    }
+                }
+                Self::record(&mut found, jmp, immediates);
+            }
+        }
+
+        found
+    }
+
+    fn collect_loc(&self, loc: &Loc, immediates: &mut Vec<Immediate>) {
+        if let Loc::Computed(ref expr) = loc {
+            self.collect(expr, immediates);
+        }
+    }
+
+    fn record<V>(found: &mut BTreeMap<Id<Erased>, Vec<Immediate>>, stmt: &Entity<V>, immediates: Vec<Immediate>) {
+        if !immediates.is_empty() {
+            found.insert(stmt.id().erase(), immediates);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_notes() {
+        let mut pool = ConstantPool::new(Endian::Little);
+
+        assert!(pool.notes(0x5bd1e995, 32).contains(&ConstantNote::Magic(Arc::from("MurmurHash2 multiplier"))));
+        assert!(pool.notes(0xffffffea, 32).contains(&ConstantNote::ErrorCode(Arc::from("-EINVAL"))));
+        assert!(pool.notes(0xffffffffffffffea, 64).contains(&ConstantNote::ErrorCode(Arc::from("-EINVAL"))));
+        assert_eq!(pool.notes(0x0000000064636261, 64), vec![ConstantNote::Ascii("abcd".to_owned())]);
+        assert!(pool.notes(0x0000000000006261, 64).is_empty());
+        assert!(pool.notes(1, 32).is_empty());
+
+        pool.magic_numbers_mut().add(0x1337c0de, "custom");
+        assert_eq!(pool.notes(0x1337c0de, 32), vec![ConstantNote::Magic(Arc::from("custom"))]);
+
+        let big = ConstantPool::new(Endian::Big);
+        assert_eq!(big.notes(0x61626364, 32), vec![ConstantNote::Ascii("abcd".to_owned())]);
+    }
+}
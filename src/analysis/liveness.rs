@@ -0,0 +1,243 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use crate::analysis::dataflow::{self, Dataflow, DataflowResult, Direction, Lattice};
+use crate::ir::visit::Visit;
+use crate::ir::{Blk, Cfg, Def, Expr, Jmp, Loc, Phi, Sub, Var};
+use crate::prelude::Entity;
+
+/// The variables that are live at some point of a Sub.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LiveVars {
+    vars: BTreeSet<Var>,
+    // all physical variables are live
+    physical: bool,
+}
+
+impl LiveVars {
+    pub fn contains(&self, var: &Var) -> bool {
+        self.vars.contains(var) || (self.physical && var.is_physical())
+    }
+
+    /// Returns true if all physical variables are live (e.g., on exit from
+    /// a Sub via a call).
+    pub fn all_physical(&self) -> bool {
+        self.physical
+    }
+
+    /// The variables that are live, excluding those only implied to be live
+    /// by `all_physical`.
+    pub fn vars(&self) -> impl Iterator<Item = &Var> {
+        self.vars.iter()
+    }
+
+    fn kill(&mut self, var: &Var) {
+        self.vars.remove(var);
+    }
+}
+
+impl Lattice for LiveVars {
+    fn bottom() -> Self {
+        Self::default()
+    }
+
+    fn join(&mut self, other: &Self) {
+        self.vars.extend(other.vars.iter().cloned());
+        self.physical |= other.physical;
+    }
+}
+
+struct Uses<'a>(&'a mut LiveVars);
+
+impl<'ir, 'a> Visit<'ir> for Uses<'a> {
+    fn visit_var(&mut self, var: &'ir Var) {
+        if !var.is_memory() {
+            self.0.vars.insert(var.clone());
+        }
+    }
+
+    // the variable assigned is defined, not read
+    fn visit_def_assign(&mut self, _var: &'ir Var, expr: &'ir Expr) {
+        self.visit_expr(expr)
+    }
+}
+
+/// A backward liveness analysis over the variables of a Sub.
+///
+/// At calls, intrinsics, and flows whose targets are not Blks of the Sub,
+/// we conservatively assume that all physical variables are read; at
+/// returns, only the preserved physical variables are assumed to be read
+/// (e.g., the calling convention's return register).
+///
+/// Variables are live only if they are read before being reassigned, and
+/// dead assignments (see `is_dead`) do not make the variables they read
+/// live.
+#[derive(Debug, Clone, Default)]
+pub struct Liveness {
+    preserve: BTreeSet<Arc<str>>,
+    pure: BTreeSet<Arc<str>>,
+}
+
+impl Liveness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treat (physical) variables named `name` as always live.
+    pub fn preserve(&mut self, name: impl Into<Arc<str>>) -> &mut Self {
+        self.preserve.insert(name.into());
+        self
+    }
+
+    pub fn preserved(&self) -> impl Iterator<Item = &Arc<str>> {
+        self.preserve.iter()
+    }
+
+    /// Treat intrinsic expressions named `name` as having no side-effects
+    /// (see `IntrinsicRegistry::is_pure`), so that assignments of them can
+    /// be dead.
+    pub fn pure_intrinsic(&mut self, name: impl Into<Arc<str>>) -> &mut Self {
+        self.pure.insert(name.into());
+        self
+    }
+
+    fn is_preserved(&self, var: &Var) -> bool {
+        var.is_physical() && self.preserve.contains(var.name())
+    }
+
+    pub fn is_live(&self, live: &LiveVars, var: &Var) -> bool {
+        live.contains(var) || self.is_preserved(var)
+    }
+
+    fn has_side_effects(&self, expr: &Expr) -> bool {
+        struct Intrinsics<'a>(&'a BTreeSet<Arc<str>>, bool);
+
+        impl<'ir, 'a> Visit<'ir> for Intrinsics<'a> {
+            fn visit_expr_intrinsic(&mut self, name: &'ir str, args: &'ir [Box<Expr>], _bits: u32) {
+                self.1 |= !self.0.contains(name);
+                for arg in args {
+                    self.visit_expr(arg);
+                }
+            }
+        }
+
+        let mut intrinsics = Intrinsics(&self.pure, false);
+        intrinsics.visit_expr(expr);
+        intrinsics.1
+    }
+
+    /// Returns true if `def` is an assignment to a variable that is not in
+    /// `live` (i.e., the variables live after it) whose expression has no
+    /// side-effects. Stores and assumptions are never dead.
+    pub fn is_dead(&self, live: &LiveVars, def: &Def) -> bool {
+        match def {
+            Def::Assign(var, expr) => {
+                !self.is_live(live, var) && !self.has_side_effects(expr)
+            }
+            Def::Assume(_) | Def::Store(_, _, _, _) => false,
+        }
+    }
+
+    pub fn solve(&self, sub: &Sub) -> DataflowResult<LiveVars> {
+        self.solve_with(sub, &sub.cfg())
+    }
+
+    pub fn solve_with(&self, sub: &Sub, cfg: &Cfg) -> DataflowResult<LiveVars> {
+        dataflow::solve(self, sub, cfg)
+    }
+}
+
+impl Dataflow for Liveness {
+    type Value = LiveVars;
+
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn boundary(&self, sub: &Sub, blk: &Entity<Blk>) -> LiveVars {
+        let mut live = LiveVars::default();
+        for jmp in blk.jmps() {
+            match &**jmp {
+                Jmp::Branch(Loc::Resolved(id)) | Jmp::CBranch(Loc::Resolved(id), _)
+                    if sub.blk(*id).is_some() => (),
+                Jmp::Return(_) => (),
+                _ => {
+                    live.physical = true;
+                }
+            }
+        }
+        live
+    }
+
+    fn transfer_phi(&self, phi: &Entity<Phi>, live: &mut LiveVars) {
+        live.kill(phi.var());
+        let mut uses = Uses(live);
+        for (cond, expr) in phi.choices() {
+            uses.visit_expr(cond);
+            uses.visit_expr(expr);
+        }
+    }
+
+    fn transfer_def(&self, def: &Entity<Def>, live: &mut LiveVars) {
+        if self.is_dead(live, def) {
+            return
+        }
+
+        if let Def::Assign(ref var, _) = **def {
+            live.kill(var);
+        }
+
+        Uses(live).visit_def(def);
+    }
+
+    fn transfer_jmp(&self, jmp: &Entity<Jmp>, live: &mut LiveVars) {
+        Uses(live).visit_jmp(jmp);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::BitVec;
+    use crate::passes::DcePass;
+    use crate::prelude::Identifiable;
+    use crate::types::bv::BitVecT;
+
+    #[test]
+    fn test_overwritten_assignment() {
+        let typ = BitVecT::with_bits(32, false);
+        let x: Var = Var::transient("x", typ).into();
+
+        let first = Def::assign(x.clone(), BitVec::from_u64(1, 32));
+        let second = Def::assign(x.clone(), BitVec::from_u64(2, 32));
+        let second_id = second.id();
+
+        // x := 1; x := 2; return x
+        let mut blk = Blk::new(None);
+        blk.add_def(first);
+        blk.add_def(second);
+        blk.add_jmp(Jmp::return_(Expr::from(x.clone())));
+        let blk_id = blk.id();
+        let mut sub: Entity<Sub> = Sub::new(None, None, vec![blk]);
+
+        let liveness = Liveness::new();
+        let result = liveness.solve(&sub);
+
+        // x is assigned before it is read
+        assert!(!result.entry(blk_id).unwrap().contains(&x));
+
+        let blk = sub.blk(blk_id).unwrap();
+        let mut live = result.exit(blk_id).cloned().unwrap();
+        for jmp in blk.jmps().iter().rev() {
+            liveness.transfer_jmp(jmp, &mut live);
+        }
+
+        assert!(!liveness.is_dead(&live, &blk.defs()[1]));
+        liveness.transfer_def(&blk.defs()[1], &mut live);
+        assert!(liveness.is_dead(&live, &blk.defs()[0]));
+
+        assert_eq!(DcePass::new().apply(&mut sub), 1);
+
+        let blk = sub.blk(blk_id).unwrap();
+        assert_eq!(blk.defs().len(), 1);
+        assert_eq!(blk.defs()[0].id(), second_id);
+    }
+}
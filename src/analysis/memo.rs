@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::analysis::cache::CacheStats;
+use crate::ir::Expr;
+
+/// The identity of a hash-consed expression within an `ExprCache`:
+/// structurally equal expressions interned by the same cache share an id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ExprId(u32);
+
+/// The version of an environment (e.g., the bindings of a symbolic state)
+/// that expressions are evaluated in. Each call to `fresh` returns a
+/// version distinct from all others in the process, so an environment
+/// takes a fresh version whenever it changes, and copies of an unchanged
+/// environment can share the results evaluated in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EnvVersion(u64);
+
+static NEXT_ENV_VERSION: AtomicU64 = AtomicU64::new(0);
+
+impl EnvVersion {
+    pub fn fresh() -> Self {
+        Self(NEXT_ENV_VERSION.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl Default for EnvVersion {
+    fn default() -> Self {
+        Self::fresh()
+    }
+}
+
+/// A cache of the simplified forms of expressions, and of the results of
+/// evaluating them in versioned environments, keyed by the identities of
+/// the expressions after hash-consing.
+///
+/// Simplification does not depend on an environment, so its results are
+/// kept until the cache is cleared; expressions are only simplified when
+/// first requested. Results of evaluation (of type `V`, e.g., the values
+/// of a symbolic state, or value-sets) are kept per `EnvVersion`, and are
+/// dropped when their version is invalidated. If the number of results
+/// exceeds the cache's capacity (see `max_entries`), all results are
+/// dropped, bounding the memory used by long-running analyses.
+pub struct ExprCache<V = Expr> {
+    ids: HashMap<Arc<Expr>, ExprId>,
+    exprs: Vec<Arc<Expr>>,
+    simplified: HashMap<ExprId, ExprId>,
+    evaluated: HashMap<EnvVersion, HashMap<ExprId, V>>,
+    entries: usize,
+    max_entries: usize,
+    stats: CacheStats,
+}
+
+impl<V> Default for ExprCache<V> {
+    fn default() -> Self {
+        Self {
+            ids: HashMap::new(),
+            exprs: Vec::new(),
+            simplified: HashMap::new(),
+            evaluated: HashMap::new(),
+            entries: 0,
+            max_entries: 1 << 16,
+            stats: CacheStats::default(),
+        }
+    }
+}
+
+impl<V> ExprCache<V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The maximum number of simplified and evaluated results retained.
+    pub fn max_entries(&mut self, max: usize) -> &mut Self {
+        self.max_entries = max;
+        self
+    }
+
+    /// The id of `expr`, interning it if it has not been seen before.
+    pub fn intern(&mut self, expr: &Expr) -> ExprId {
+        if let Some(id) = self.ids.get(expr) {
+            return *id
+        }
+
+        let id = ExprId(self.exprs.len() as u32);
+        let expr = Arc::new(expr.clone());
+        self.exprs.push(expr.clone());
+        self.ids.insert(expr, id);
+        id
+    }
+
+    /// The id of `expr`, if it has been interned.
+    pub fn id(&self, expr: &Expr) -> Option<ExprId> {
+        self.ids.get(expr).copied()
+    }
+
+    pub fn expr(&self, id: ExprId) -> &Arc<Expr> {
+        &self.exprs[id.0 as usize]
+    }
+
+    /// The simplified form of `expr` (see `Expr::simplify`).
+    pub fn simplify(&mut self, expr: &Expr) -> Arc<Expr> {
+        if let Some(sid) = self.id(expr).and_then(|id| self.simplified.get(&id)) {
+            self.stats.hits += 1;
+            return self.expr(*sid).clone()
+        }
+        self.stats.misses += 1;
+        self.reserve(2);
+
+        let simplified = expr.clone().simplify();
+        let id = self.intern(expr);
+        let sid = self.intern(&simplified);
+
+        self.simplified.insert(id, sid);
+        // a simplified expression is its own simplified form
+        self.simplified.insert(sid, sid);
+        self.stats.stores += 1;
+
+        self.expr(sid).clone()
+    }
+
+    /// The result of evaluating `expr` in the environment `env`, if cached.
+    pub fn get(&mut self, env: EnvVersion, expr: &Expr) -> Option<&V> {
+        let id = self.ids.get(expr);
+        let value = id.and_then(|id| self.evaluated.get(&env)?.get(id));
+        if value.is_some() {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+        value
+    }
+
+    /// Records `value` as the result of evaluating `expr` in `env`.
+    pub fn insert(&mut self, env: EnvVersion, expr: &Expr, value: V) {
+        self.reserve(1);
+        let id = self.intern(expr);
+        if self.evaluated.entry(env).or_default().insert(id, value).is_none() {
+            self.entries += 1;
+        }
+        self.stats.stores += 1;
+    }
+
+    /// The result of evaluating `expr` in `env`, computing it using `eval`
+    /// if it is not cached.
+    pub fn evaluate<F>(&mut self, env: EnvVersion, expr: &Expr, eval: F) -> V
+    where
+        F: FnOnce(&Expr) -> V,
+        V: Clone,
+    {
+        if let Some(value) = self.get(env, expr) {
+            return value.clone()
+        }
+        let value = eval(expr);
+        self.insert(env, expr, value.clone());
+        value
+    }
+
+    /// Drops the results evaluated in `env`, e.g., as it has changed
+    /// without taking a fresh version.
+    pub fn invalidate(&mut self, env: EnvVersion) {
+        if let Some(values) = self.evaluated.remove(&env) {
+            self.entries -= values.len();
+        }
+    }
+
+    /// Drops all cached results and interned expressions.
+    pub fn clear(&mut self) {
+        self.ids.clear();
+        self.exprs.clear();
+        self.simplified.clear();
+        self.evaluated.clear();
+        self.entries = 0;
+    }
+
+    /// The number of cached results.
+    pub fn len(&self) -> usize {
+        self.simplified.len() + self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    fn reserve(&mut self, entries: usize) {
+        if self.len() + entries > self.max_entries {
+            self.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::Var;
+    use crate::types::bv::BitVecT;
+
+    #[test]
+    fn test_evaluate_per_env() {
+        let typ = BitVecT::with_bits(32, false);
+        let x = Expr::from(Var::transient("x", typ));
+        let y = Expr::from(Var::transient("y", typ));
+
+        let mut cache = ExprCache::<usize>::new();
+        assert_eq!(cache.intern(&x), cache.intern(&x.clone()));
+        assert_ne!(cache.intern(&x), cache.intern(&y));
+
+        let (env1, env2) = (EnvVersion::fresh(), EnvVersion::fresh());
+        assert_eq!(cache.evaluate(env1, &x, |_| 1), 1);
+        assert_eq!(cache.evaluate(env1, &x, |_| 2), 1);
+        assert_eq!(cache.evaluate(env2, &x, |_| 3), 3);
+
+        cache.invalidate(env1);
+        assert!(cache.get(env1, &x).is_none());
+        assert_eq!(cache.get(env2, &x), Some(&3));
+        assert_eq!(cache.stats().hits, 2);
+    }
+}
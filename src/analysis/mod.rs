@@ -0,0 +1,118 @@
+/// Analyses over our IR/IL.
+///
+/// Unlike passes, analyses do not modify the Blks and Subs they are
+/// applied to; instead, they produce results that can be queried by
+/// passes and users of the crate.
+///
+/// - `dataflow` provides a generic worklist solver for forward and
+///   backward dataflow analyses over the Cfg of a Sub; the remaining
+///   analyses are instances of it.
+///
+/// - `context` solves forward dataflow analyses across the Subs of a
+///   program, with k-limited call-string context sensitivity.
+///
+/// - `reaching` computes the definitions reaching each Blk of a Sub, and
+///   the use-def chains derived from them.
+///
+/// - `liveness` computes the variables live at the entry and exit of each
+///   Blk of a Sub.
+///
+/// - `constants` computes the variables holding known constant values at
+///   the entry and exit of each Blk of a Sub.
+///
+/// - `frame` reconstructs the stack frame of a Sub: the height of its stack
+///   pointer at each Blk, and the locals and arguments it accesses.
+///
+/// - `prototype` infers the argument registers of Subs, and whether they
+///   return values, from the uses of registers by them and their callers.
+///
+/// - `callbacks` finds the Subs whose addresses are taken, i.e., stored to
+///   memory or passed to calls, as the candidate targets of indirect calls.
+///
+/// - `obfuscation` computes metrics of Subs indicating whether they are
+///   obfuscated (e.g., opaque predicates, flattened Cfgs, and mixed
+///   boolean-arithmetic expressions), to rank them for deobfuscation.
+///
+/// - `slice` computes the statements of a Sub that may affect, or may be
+///   affected by, a given statement via data and control dependencies.
+///
+/// - `switch` recovers the jump tables used by computed branches.
+///
+/// - `taint` tracks the flow of data from sources to sinks across the
+///   Subs of a program, via per-Sub summaries, suppressing flows through
+///   sanitizers.
+///
+/// - `typing` infers the types of the variables and memory of a Sub from
+///   the ways they are used (e.g., as addresses, or in signed comparisons).
+///
+/// - `vsa` computes the sets of values (strided intervals relative to
+///   global memory or the stack) of the variables of a Sub, bounding the
+///   targets of computed jumps and the stack accesses of the Sub.
+///
+/// - `immediates` notes what the immediates of a Sub denote: members of
+///   enumerations, error codes, ASCII text, and magic numbers.
+///
+/// - `hints` records facts asserted by the user (e.g., the value of a
+///   register at an address), which analyses treat as ground truth.
+///
+/// - `memo` caches the simplified forms of expressions, and their values
+///   in versioned environments, keyed by their hash-consed identities, so
+///   that analyses do not re-simplify the same expressions at each point.
+///
+/// - `cache` stores the results of analyses keyed by the hashes of the
+///   code analysed and the configuration analysed with, so that they can
+///   be reused across Projects.
+
+pub mod cache;
+pub use cache::{AnalysisCache, CacheError, CacheKey, CacheStats, ContentHash};
+
+pub mod dataflow;
+pub use dataflow::{Dataflow, DataflowResult, Direction, Lattice};
+
+pub mod context;
+pub use context::{CallString, CallStrings, ContextResult, Interprocedural};
+
+pub mod constants;
+pub use constants::{ConstantPropagation, Constants};
+
+pub mod callbacks;
+pub use callbacks::{AddressTaken, CallbackKind, CallbackRef};
+
+pub mod frame;
+pub use frame::{FrameMember, SlotKind, StackFrame};
+
+pub mod hints;
+pub use hints::{Hint, HintError, Hints};
+
+pub mod immediates;
+pub use immediates::{ConstantNote, ConstantPool, Immediate, MagicNumbers};
+
+pub mod liveness;
+pub use liveness::{LiveVars, Liveness};
+
+pub mod memo;
+pub use memo::{EnvVersion, ExprCache, ExprId};
+
+pub mod obfuscation;
+pub use obfuscation::{ObfuscationMetrics, ObfuscationReport};
+
+pub mod prototype;
+pub use prototype::{InferredPrototype, PrototypeInference, Prototypes};
+
+pub mod reaching;
+pub use reaching::{Definition, ReachingDefinitions, Site, UseDefChains};
+
+pub mod slice;
+pub use slice::{Dependencies, Slice};
+
+pub mod switch;
+pub use switch::{JumpTable, SwitchAnalysis, TableKind};
+
+pub mod taint;
+pub use taint::{Callee, TaintAnalysis, TaintFinding, TaintLabel, TaintResult, TaintRule, TaintSpec, TaintSpecError, TaintSummary};
+
+pub mod typing;
+pub use typing::{InferredTypes, TypeFacts, TypeInference, TypeVar};
+
+pub mod vsa;
+pub use vsa::{AbsRegion, StridedInterval, ValueSet, ValueSetAnalysis, ValueSets};
@@ -0,0 +1,393 @@
+use std::fmt::{self, Display};
+use std::sync::Arc;
+
+use crate::analysis::constants::ConstantPropagation;
+use crate::analysis::dataflow::Dataflow;
+use crate::ir::visit::Visit;
+use crate::ir::{BinOp, BinRel, Expr, Jmp, Sub, UnOp};
+use crate::prelude::{Entity, Id, Identifiable};
+
+// the least number of Blks a Sub must have to be scored as flattened
+const MIN_DISPATCHER_BLKS: usize = 4;
+
+// the least number of operators an expression must have to be counted as
+// a mixed boolean-arithmetic (MBA) expression
+const MIN_MBA_OPS: usize = 4;
+
+/// Metrics of a Sub indicating whether it is obfuscated:
+///
+/// - its cyclomatic complexity, i.e., `E - N + 2` for a Cfg of `N` Blks
+///   and `E` edges;
+/// - the number of opaque-looking predicates, i.e., conditional branches
+///   whose conditions are constant (under constant propagation), compare
+///   an expression to itself, or test the parity of a product of
+///   consecutive values (e.g., `x * (x + 1)`), which is always even;
+/// - how much it resembles a flattened Cfg, i.e., whether a single
+///   dispatcher Blk is the predecessor of most of its Blks (see
+///   `dispatcher_score`);
+/// - the density of its mixed boolean-arithmetic (MBA) expressions, i.e.,
+///   the fraction of its statements whose expressions mix bitwise and
+///   arithmetic operators (e.g., `(x ^ y) + 2 * (x & y)`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObfuscationMetrics {
+    blks: usize,
+    edges: usize,
+    conditional_branches: usize,
+    opaque_predicates: usize,
+    dispatcher_score: f64,
+    statements: usize,
+    mba_statements: usize,
+}
+
+impl ObfuscationMetrics {
+    pub fn new(sub: &Sub) -> Self {
+        let cfg = sub.cfg();
+        let edges = cfg.edges().count();
+
+        // the dispatcher of a flattened Cfg is a successor of most Blks
+        // and a predecessor of most Blks
+        let dispatcher_score = if cfg.len() >= MIN_DISPATCHER_BLKS {
+            let others = (cfg.len() - 1) as f64;
+            cfg.blks()
+                .map(|blk| {
+                    let preds = cfg.predecessors(blk).filter(|pred| *pred != blk).count();
+                    let succs = cfg.successors(blk).filter(|succ| *succ != blk).count();
+                    (preds as f64 / others).min(1.0) * 0.75 + (succs as f64 / others).min(1.0) * 0.25
+                })
+                .fold(0.0, f64::max)
+        } else {
+            0.0
+        };
+
+        let mut metrics = Self {
+            blks: cfg.len(),
+            edges,
+            conditional_branches: 0,
+            opaque_predicates: 0,
+            dispatcher_score,
+            statements: 0,
+            mba_statements: 0,
+        };
+
+        let analysis = ConstantPropagation::new();
+        let result = analysis.solve_with(sub, &cfg);
+
+        for blk in sub.blks() {
+            let mut constants = result.entry(blk).filter(|constants| constants.is_reachable()).cloned();
+
+            if let Some(ref mut constants) = constants {
+                analysis.transfer_start(blk, constants);
+                for phi in blk.phis() {
+                    analysis.transfer_phi(phi, constants);
+                }
+            }
+
+            for def in blk.defs() {
+                let mut operators = Operators::default();
+                operators.visit_def(def);
+                metrics.count_statement(&operators);
+
+                if let Some(ref mut constants) = constants {
+                    analysis.transfer_def(def, constants);
+                }
+            }
+
+            for jmp in blk.jmps() {
+                let cond = match **jmp {
+                    Jmp::CBranch(_, ref cond) => cond,
+                    _ => continue,
+                };
+
+                let mut operators = Operators::default();
+                operators.visit_expr(cond);
+                metrics.count_statement(&operators);
+
+                metrics.conditional_branches += 1;
+
+                // syntactic checks are cheaper than evaluation
+                let opaque = is_opaque(cond) || constants
+                    .as_ref()
+                    .map(|constants| constants.evaluate(cond).is_some())
+                    .unwrap_or(false);
+
+                if opaque {
+                    metrics.opaque_predicates += 1;
+                }
+            }
+        }
+
+        metrics
+    }
+
+    fn count_statement(&mut self, operators: &Operators) {
+        if operators.total() > 0 {
+            self.statements += 1;
+            if operators.is_mba() {
+                self.mba_statements += 1;
+            }
+        }
+    }
+
+    pub fn blks(&self) -> usize {
+        self.blks
+    }
+
+    pub fn edges(&self) -> usize {
+        self.edges
+    }
+
+    pub fn cyclomatic_complexity(&self) -> usize {
+        (self.edges + 2).saturating_sub(self.blks)
+    }
+
+    pub fn conditional_branches(&self) -> usize {
+        self.conditional_branches
+    }
+
+    pub fn opaque_predicates(&self) -> usize {
+        self.opaque_predicates
+    }
+
+    /// How much the Cfg resembles a flattened Cfg, from 0 to 1: for the
+    /// Blk most resembling a dispatcher, the fraction of the other Blks
+    /// that are its predecessors (weighted by 3/4) and successors (by 1/4).
+    pub fn dispatcher_score(&self) -> f64 {
+        self.dispatcher_score
+    }
+
+    /// The fraction of statements with operators whose expressions are
+    /// MBA expressions.
+    pub fn mba_density(&self) -> f64 {
+        if self.statements == 0 {
+            0.0
+        } else {
+            self.mba_statements as f64 / self.statements as f64
+        }
+    }
+
+    /// The metrics combined into a score from 0 to 1, for ranking Subs by
+    /// how likely they are to be obfuscated.
+    pub fn score(&self) -> f64 {
+        let opaque = if self.conditional_branches == 0 {
+            0.0
+        } else {
+            self.opaque_predicates as f64 / self.conditional_branches as f64
+        };
+
+        // complexity alone is weak evidence: large Subs are complex
+        let complexity = (self.cyclomatic_complexity() as f64 / 50.0).min(1.0);
+
+        0.1 * complexity + 0.3 * opaque + 0.3 * self.dispatcher_score + 0.3 * self.mba_density()
+    }
+}
+
+// counts the operators of the expressions visited
+#[derive(Default)]
+struct Operators {
+    bitwise: usize,
+    arithmetic: usize,
+    other: usize,
+}
+
+impl Operators {
+    fn total(&self) -> usize {
+        self.bitwise + self.arithmetic + self.other
+    }
+
+    // returns true if the expressions visited mix bitwise and arithmetic
+    // operators
+    fn is_mba(&self) -> bool {
+        self.bitwise > 0 && self.arithmetic > 0 && self.total() >= MIN_MBA_OPS
+    }
+}
+
+impl<'ir> Visit<'ir> for Operators {
+    fn visit_expr_unop_op(&mut self, op: UnOp) {
+        match op {
+            UnOp::Not => self.bitwise += 1,
+            UnOp::Neg => self.arithmetic += 1,
+            _ => self.other += 1,
+        }
+    }
+
+    fn visit_expr_binop_op(&mut self, op: BinOp) {
+        match op {
+            BinOp::And | BinOp::Or | BinOp::Xor => self.bitwise += 1,
+            BinOp::Add | BinOp::Sub | BinOp::Mul => self.arithmetic += 1,
+            _ => self.other += 1,
+        }
+    }
+}
+
+// x * (x + 1) or x * (x - 1), for any x
+fn is_consecutive_product(expr: &Expr) -> bool {
+    let (lexpr, rexpr) = match expr {
+        Expr::BinOp(BinOp::Mul, lexpr, rexpr) => (lexpr, rexpr),
+        _ => return false,
+    };
+
+    let adjacent = |x: &Expr, y: &Expr| match y {
+        Expr::BinOp(BinOp::Add | BinOp::Sub, base, offset) => {
+            **base == *x && offset.val().and_then(|v| v.to_u64()) == Some(1)
+        }
+        _ => false,
+    };
+
+    adjacent(lexpr, rexpr) || adjacent(rexpr, lexpr)
+}
+
+// conditions whose values do not depend on the values of their variables
+fn is_opaque(cond: &Expr) -> bool {
+    match cond {
+        Expr::BinRel(BinRel::Eq | BinRel::Neq | BinRel::Lt | BinRel::Le | BinRel::SLt | BinRel::SLe, lexpr, rexpr) => {
+            if lexpr == rexpr {
+                return true
+            }
+
+            // (x * (x + 1)) & 1 or (x * (x + 1)) % 2, compared to a constant
+            let parity = |expr: &Expr| match expr {
+                Expr::BinOp(BinOp::And, product, mask) | Expr::BinOp(BinOp::Rem, product, mask) => {
+                    let modulus = mask.val().and_then(|v| v.to_u64());
+                    let is_mask = matches!(expr, Expr::BinOp(BinOp::And, ..)) && modulus == Some(1);
+                    let is_modulus = matches!(expr, Expr::BinOp(BinOp::Rem, ..)) && modulus == Some(2);
+                    (is_mask || is_modulus) && is_consecutive_product(product)
+                }
+                _ => false,
+            };
+
+            (parity(lexpr) && rexpr.val().is_some()) || (parity(rexpr) && lexpr.val().is_some())
+        }
+        Expr::UnOp(UnOp::Not, expr) => is_opaque(expr),
+        _ => false,
+    }
+}
+
+/// The metrics of a set of Subs, ranked by their scores (see
+/// `ObfuscationMetrics::score`), most likely obfuscated first.
+#[derive(Debug, Clone, Default)]
+pub struct ObfuscationReport {
+    subs: Vec<(Id<Sub>, Option<Arc<str>>, ObfuscationMetrics)>,
+}
+
+impl ObfuscationReport {
+    pub fn new<'a>(subs: impl IntoIterator<Item = &'a Entity<Sub>>) -> Self {
+        let mut subs = subs
+            .into_iter()
+            .map(|sub| (sub.id(), sub.symbol().cloned(), ObfuscationMetrics::new(sub)))
+            .collect::<Vec<_>>();
+
+        // ties are ranked by complexity, then by symbol, so that reports are
+        // stable
+        subs.sort_by(|(_, lsym, lm), (_, rsym, rm)| {
+            rm.score()
+                .total_cmp(&lm.score())
+                .then(rm.cyclomatic_complexity().cmp(&lm.cyclomatic_complexity()))
+                .then(lsym.cmp(rsym))
+        });
+
+        Self { subs }
+    }
+
+    pub fn subs(&self) -> impl Iterator<Item = (Id<Sub>, Option<&Arc<str>>, &ObfuscationMetrics)> {
+        self.subs.iter().map(|(id, symbol, metrics)| (*id, symbol.as_ref(), metrics))
+    }
+
+    pub fn get(&self, sub: impl Identifiable<Sub>) -> Option<&ObfuscationMetrics> {
+        let id = sub.id();
+        self.subs
+            .iter()
+            .find_map(|(sid, _, metrics)| if *sid == id { Some(metrics) } else { None })
+    }
+
+    /// The Subs scoring at least `threshold`, e.g., as candidates for
+    /// deobfuscation.
+    pub fn above(&self, threshold: f64) -> impl Iterator<Item = Id<Sub>> + '_ {
+        self.subs
+            .iter()
+            .filter(move |(_, _, metrics)| metrics.score() >= threshold)
+            .map(|(id, _, _)| *id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.subs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subs.is_empty()
+    }
+}
+
+impl Display for ObfuscationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "score\tcc\topaque\tdispatch\tmba\tsub")?;
+        for (id, symbol, metrics) in self.subs.iter() {
+            write!(
+                f,
+                "{:.2}\t{}\t{}/{}\t{:.2}\t{:.2}\t",
+                metrics.score(),
+                metrics.cyclomatic_complexity(),
+                metrics.opaque_predicates(),
+                metrics.conditional_branches(),
+                metrics.dispatcher_score(),
+                metrics.mba_density(),
+            )?;
+            match symbol {
+                Some(symbol) => writeln!(f, "{}", symbol)?,
+                None => writeln!(f, "{}", id)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::{Blk, Def, Var};
+    use crate::types::bv::BitVecT;
+
+    #[test]
+    fn test_metrics() {
+        let typ = BitVecT::with_bits(32, false);
+        let (x, y, s) = (
+            Var::from(Var::physical("x", typ)),
+            Var::from(Var::physical("y", typ)),
+            Var::from(Var::physical("s", typ)),
+        );
+        let (ex, ey) = (Expr::from(x.clone()), Expr::from(y.clone()));
+
+        // a dispatcher branching to three cases, each returning to it
+        let mut dispatcher = Blk::new(None);
+        let mut cases = (0..3).map(|_| Blk::new(None)).collect::<Vec<_>>();
+
+        for case in cases.iter_mut() {
+            let cond = Expr::bin_rel(BinRel::Eq, s.clone(), s.clone());
+            dispatcher.add_jmp(Jmp::cbranch(case.id(), cond));
+
+            // (x ^ y) + ((x & y) + (x & y))
+            let mba = Expr::bin_op(
+                BinOp::Add,
+                Expr::bin_op(BinOp::Xor, ex.clone(), ey.clone()),
+                Expr::bin_op(
+                    BinOp::Add,
+                    Expr::bin_op(BinOp::And, ex.clone(), ey.clone()),
+                    Expr::bin_op(BinOp::And, ex.clone(), ey.clone()),
+                ),
+            );
+            case.add_def(Def::assign(x.clone(), mba));
+            case.add_jmp(Jmp::branch(dispatcher.id()));
+        }
+
+        let mut blks = vec![dispatcher];
+        blks.append(&mut cases);
+        let sub = Sub::new(None, None, blks);
+
+        let metrics = ObfuscationMetrics::new(&sub);
+        assert_eq!(metrics.blks(), 4);
+        assert_eq!(metrics.cyclomatic_complexity(), 4);
+        assert_eq!((metrics.opaque_predicates(), metrics.conditional_branches()), (3, 3));
+        assert_eq!(metrics.dispatcher_score(), 1.0);
+        assert_eq!(metrics.mba_density(), 1.0);
+        assert!(metrics.score() > 0.9);
+    }
+}
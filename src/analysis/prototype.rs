@@ -0,0 +1,474 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use crate::analysis::dataflow::{self, Dataflow, Direction, Lattice};
+use crate::ir::visit::Visit;
+use crate::ir::{Addr, Blk, Def, Expr, Jmp, Loc, Phi, Sub, Var};
+use crate::lift::Lifter;
+use crate::prelude::{Entity, Id, Identifiable};
+
+/// A best-effort prototype of a Sub, inferred from the argument registers
+/// it reads before defining them, and from whether its callers read its
+/// return register after calling it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InferredPrototype {
+    arguments: Vec<Var>,
+    reads: Vec<Var>,
+    returns: Option<Var>,
+    call_sites: usize,
+    return_uses: usize,
+}
+
+impl InferredPrototype {
+    /// The integer argument registers, in order, up to and including the
+    /// last read by the Sub before it defines it; registers preceding one
+    /// that is read are assumed to pass (unused) arguments.
+    pub fn arguments(&self) -> &[Var] {
+        &self.arguments
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arguments.len()
+    }
+
+    /// All argument registers (integer or otherwise) read by the Sub
+    /// before it defines them.
+    pub fn reads(&self) -> &[Var] {
+        &self.reads
+    }
+
+    /// The register the Sub returns its value in, if any of its callers
+    /// read it after calling the Sub.
+    pub fn returns(&self) -> Option<&Var> {
+        self.returns.as_ref()
+    }
+
+    pub fn returns_value(&self) -> bool {
+        self.returns.is_some()
+    }
+
+    /// The number of calls to the Sub found.
+    pub fn call_sites(&self) -> usize {
+        self.call_sites
+    }
+
+    /// The number of calls to the Sub after which its return register is
+    /// read.
+    pub fn return_uses(&self) -> usize {
+        self.return_uses
+    }
+
+    /// The arguments of calls to the Sub, i.e., its argument registers.
+    pub fn call_args(&self) -> Vec<Expr> {
+        self.arguments.iter().cloned().map(Expr::from).collect()
+    }
+}
+
+/// The registers (of those tracked) that are read before being defined.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Exposed(BTreeSet<Arc<str>>);
+
+impl Lattice for Exposed {
+    fn bottom() -> Self {
+        Self::default()
+    }
+
+    fn join(&mut self, other: &Self) {
+        self.0.extend(other.0.iter().cloned());
+    }
+}
+
+struct Uses<'a> {
+    tracked: &'a BTreeSet<Arc<str>>,
+    exposed: &'a mut Exposed,
+}
+
+impl<'ir, 'a> Visit<'ir> for Uses<'a> {
+    fn visit_var(&mut self, var: &'ir Var) {
+        if var.is_physical() && self.tracked.contains(var.name()) {
+            self.exposed.0.insert(var.name().clone());
+        }
+    }
+
+    // the variable assigned is defined, not read
+    fn visit_def_assign(&mut self, _var: &'ir Var, expr: &'ir Expr) {
+        self.visit_expr(expr)
+    }
+}
+
+/// Infers the prototypes of Subs from the uses of the registers of a
+/// calling convention (see `Lifter::integer_argument_registers` and
+/// `Lifter::return_registers`).
+///
+/// The argument registers of a Sub are those read before being defined on
+/// some path from its entry; calls are assumed to define (i.e., clobber)
+/// all argument and return registers, and to read only their explicit
+/// arguments. A Sub returns a value if the return register is read after
+/// some call to it, before being defined; as a caller that returns
+/// immediately after a call may pass on its callee's value, this is solved
+/// as a fixed point over the Subs.
+///
+/// Since flows out of a Blk are treated as taken in order, prototypes are
+/// best-effort (e.g., for Blks with conditional branches preceding
+/// calls), and are intended for Subs without known prototypes (e.g.,
+/// without symbols), to recover the arguments of calls to them (see
+/// `Prototypes::recover_call_args`).
+#[derive(Debug, Clone)]
+pub struct PrototypeInference {
+    arguments: Vec<Var>,
+    other_arguments: Vec<Var>,
+    returns: Vec<Var>,
+    tracked: BTreeSet<Arc<str>>,
+    max_rounds: usize,
+}
+
+impl PrototypeInference {
+    /// An inference for a convention passing integer arguments in the
+    /// registers `arguments` (in order), and returning values in the
+    /// registers `returns` (the primary return register first).
+    pub fn new(arguments: Vec<Var>, returns: Vec<Var>) -> Self {
+        let mut inference = Self {
+            arguments,
+            other_arguments: Vec::new(),
+            returns,
+            tracked: BTreeSet::new(),
+            max_rounds: 64,
+        };
+        inference.track();
+        inference
+    }
+
+    /// An inference for the calling convention of `lifter`, if its
+    /// registers are known for its language.
+    pub fn from_lifter(lifter: &Lifter) -> Option<Self> {
+        let arguments = lifter.integer_argument_registers()?;
+        let returns = lifter.return_registers()?;
+        let others = lifter.argument_registers()?;
+
+        let mut inference = Self::new(arguments, returns);
+        inference.other_arguments(others);
+        Some(inference)
+    }
+
+    fn track(&mut self) {
+        self.tracked = self
+            .arguments
+            .iter()
+            .chain(self.other_arguments.iter())
+            .chain(self.returns.iter())
+            .map(|var| var.name().clone())
+            .collect();
+    }
+
+    /// Registers passing other arguments (e.g., floating-point values),
+    /// reported by `InferredPrototype::reads` but not counted as integer
+    /// arguments.
+    pub fn other_arguments(&mut self, vars: impl IntoIterator<Item = Var>) -> &mut Self {
+        for var in vars {
+            if !self.arguments.contains(&var) && !self.other_arguments.contains(&var) {
+                self.other_arguments.push(var);
+            }
+        }
+        self.track();
+        self
+    }
+
+    /// The maximum number of times the uses of return values are
+    /// recomputed for all Subs.
+    pub fn max_rounds(&mut self, max: usize) -> &mut Self {
+        self.max_rounds = max.max(1);
+        self
+    }
+
+    fn return_register(&self) -> Option<&Var> {
+        self.returns.first()
+    }
+
+    /// Infers the prototypes of each of `subs`.
+    pub fn infer<'a>(&self, subs: impl IntoIterator<Item = &'a Entity<Sub>>) -> Prototypes {
+        let subs = subs
+            .into_iter()
+            .map(|sub| (sub.id(), sub))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut callees = Callees::default();
+        for (id, sub) in subs.iter() {
+            if let Some(addr) = sub.addr() {
+                callees.addrs.insert(addr.clone(), *id);
+            }
+            if let Some(entry) = sub.entry() {
+                callees.entries.insert(entry.id(), *id);
+            }
+        }
+
+        // the Subs returning values; this only grows, so a fixed point is
+        // reached after at most as many rounds as there are Subs
+        let mut returning = BTreeSet::new();
+        let mut sites = BTreeMap::<Id<Sub>, (usize, usize)>::new();
+
+        for _ in 0..self.max_rounds {
+            sites.clear();
+            for (id, sub) in subs.iter() {
+                let in_sub = InSub {
+                    inference: self,
+                    returns: returning.contains(id),
+                };
+                for (callee, used) in in_sub.call_sites(sub, &callees) {
+                    let (calls, uses) = sites.entry(callee).or_default();
+                    *calls += 1;
+                    *uses += used as usize;
+                }
+            }
+
+            let nreturning = sites
+                .iter()
+                .filter(|(_, (_, uses))| *uses > 0)
+                .map(|(id, _)| *id)
+                .collect::<BTreeSet<_>>();
+
+            if nreturning == returning {
+                break
+            }
+            returning = nreturning;
+        }
+
+        let prototypes = subs
+            .iter()
+            .map(|(id, sub)| {
+                let in_sub = InSub {
+                    inference: self,
+                    returns: returning.contains(id),
+                };
+                let reads = in_sub.exposed(sub);
+
+                let arity = self
+                    .arguments
+                    .iter()
+                    .rposition(|var| reads.0.contains(var.name()))
+                    .map(|i| i + 1)
+                    .unwrap_or(0);
+
+                let (call_sites, return_uses) = sites.get(id).copied().unwrap_or_default();
+
+                let prototype = InferredPrototype {
+                    arguments: self.arguments[..arity].to_vec(),
+                    reads: self
+                        .arguments
+                        .iter()
+                        .chain(self.other_arguments.iter())
+                        .filter(|var| reads.0.contains(var.name()))
+                        .cloned()
+                        .collect(),
+                    returns: if returning.contains(id) { self.return_register().cloned() } else { None },
+                    call_sites,
+                    return_uses,
+                };
+
+                (*id, prototype)
+            })
+            .collect();
+
+        Prototypes { prototypes, callees }
+    }
+}
+
+// the Subs called by calls to addresses and Blks
+#[derive(Debug, Clone, Default)]
+struct Callees {
+    addrs: BTreeMap<Addr, Id<Sub>>,
+    entries: BTreeMap<Id<Blk>, Id<Sub>>,
+}
+
+impl Callees {
+    fn callee(&self, jmp: &Jmp) -> Option<Id<Sub>> {
+        match jmp {
+            Jmp::Call(Loc::Fixed(addr), _) => self.addrs.get(addr).copied(),
+            Jmp::Call(Loc::Resolved(id), _) => self.entries.get(id).copied(),
+            _ => None,
+        }
+    }
+}
+
+// the analysis of the registers exposed within a Sub, which may return a
+// value
+struct InSub<'a> {
+    inference: &'a PrototypeInference,
+    returns: bool,
+}
+
+impl<'a> InSub<'a> {
+    fn uses<'b>(&'b self, exposed: &'b mut Exposed) -> Uses<'b> {
+        Uses { tracked: &self.inference.tracked, exposed }
+    }
+
+    fn exposed(&self, sub: &Sub) -> Exposed {
+        let result = dataflow::solve(self, sub, &sub.cfg());
+        sub.entry()
+            .and_then(|entry| result.entry(entry).cloned())
+            .unwrap_or_default()
+    }
+
+    // the callees of the calls within `sub`, and if the return register is
+    // read after each call
+    fn call_sites(&self, sub: &Sub, callees: &Callees) -> Vec<(Id<Sub>, bool)> {
+        let result = dataflow::solve(self, sub, &sub.cfg());
+        let mut sites = Vec::new();
+
+        let ret = match self.inference.return_register() {
+            Some(ret) => ret.name().clone(),
+            None => return sites,
+        };
+
+        for blk in sub.blks() {
+            let mut exposed = result.exit(blk).cloned().unwrap_or_default();
+            for jmp in blk.jmps().iter().rev() {
+                if let Some(callee) = callees.callee(jmp) {
+                    sites.push((callee, exposed.0.contains(&ret)));
+                }
+                self.transfer_jmp(jmp, &mut exposed);
+            }
+        }
+
+        sites
+    }
+}
+
+impl<'a> Dataflow for InSub<'a> {
+    type Value = Exposed;
+
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn boundary(&self, _sub: &Sub, blk: &Entity<Blk>) -> Exposed {
+        let mut exposed = Exposed::default();
+        if self.returns && blk.jmps().iter().any(|jmp| matches!(**jmp, Jmp::Return(_))) {
+            if let Some(ret) = self.inference.return_register() {
+                exposed.0.insert(ret.name().clone());
+            }
+        }
+        exposed
+    }
+
+    fn transfer_phi(&self, phi: &Entity<Phi>, exposed: &mut Exposed) {
+        exposed.0.remove(phi.var().name());
+        let mut uses = self.uses(exposed);
+        for (cond, expr) in phi.choices() {
+            uses.visit_expr(cond);
+            uses.visit_expr(expr);
+        }
+    }
+
+    fn transfer_def(&self, def: &Entity<Def>, exposed: &mut Exposed) {
+        if let Def::Assign(ref var, _) = **def {
+            if var.is_physical() {
+                exposed.0.remove(var.name());
+            }
+        }
+        self.uses(exposed).visit_def(def);
+    }
+
+    fn transfer_jmp(&self, jmp: &Entity<Jmp>, exposed: &mut Exposed) {
+        if let Jmp::Call(_, _) = **jmp {
+            // calls clobber all argument and return registers
+            exposed.0.clear();
+        }
+        self.uses(exposed).visit_jmp(jmp);
+    }
+}
+
+/// The prototypes inferred for a set of Subs (see `PrototypeInference`).
+#[derive(Debug, Clone, Default)]
+pub struct Prototypes {
+    prototypes: BTreeMap<Id<Sub>, InferredPrototype>,
+    callees: Callees,
+}
+
+impl Prototypes {
+    pub fn get(&self, sub: impl Identifiable<Sub>) -> Option<&InferredPrototype> {
+        self.prototypes.get(&sub.id())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Id<Sub>, &InferredPrototype)> {
+        self.prototypes.iter().map(|(id, prototype)| (*id, prototype))
+    }
+
+    pub fn len(&self) -> usize {
+        self.prototypes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prototypes.is_empty()
+    }
+
+    /// The prototype of the Sub called by `jmp`, if it is a call to one of
+    /// the Subs inferred.
+    pub fn callee(&self, jmp: &Jmp) -> Option<&InferredPrototype> {
+        self.prototypes.get(&self.callees.callee(jmp)?)
+    }
+
+    /// Sets the arguments of the calls within `sub` without arguments to
+    /// those of the prototypes of their callees; returns the number of
+    /// calls updated.
+    pub fn recover_call_args(&self, sub: &mut Sub) -> usize {
+        let mut recovered = 0;
+        for blk in sub.blks_mut().iter_mut() {
+            for jmp in blk.jmps_mut().iter_mut() {
+                let args = match self.callee(jmp) {
+                    Some(prototype) if prototype.arity() > 0 => prototype.call_args(),
+                    _ => continue,
+                };
+                if let Jmp::Call(_, ref mut cargs) = **jmp {
+                    if cargs.is_empty() {
+                        cargs.extend(args);
+                        recovered += 1;
+                    }
+                }
+            }
+        }
+        recovered
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::bv::BitVecT;
+
+    #[test]
+    fn test_infer() {
+        let typ = BitVecT::with_bits(64, false);
+        let reg = |name: &str| -> Var { Var::physical(name, typ).into() };
+        let (rdi, rsi, rax) = (reg("RDI"), reg("RSI"), reg("RAX"));
+        let tmp = Var::transient("t", typ);
+
+        // f(RDI) returns RAX := RDI
+        let mut entry = Blk::new(None);
+        entry.add_def(Def::assign(rax.clone(), Expr::from(rdi.clone())));
+        entry.add_jmp(Jmp::return_(Expr::from(tmp.clone())));
+        let f_entry = entry.id();
+        let f: Entity<Sub> = Sub::new(None, None, vec![entry]);
+
+        // g calls f(RDI), and reads its result
+        let mut call = Blk::new(None);
+        let mut next = Blk::new(None);
+        call.add_def(Def::assign(rdi.clone(), Expr::from(tmp.clone())));
+        call.add_jmp(Jmp::call(f_entry, []));
+        call.add_jmp(Jmp::branch(next.id()));
+        next.add_def(Def::assign(tmp.clone(), Expr::from(rax.clone())));
+        next.add_jmp(Jmp::return_(Expr::from(tmp)));
+        let mut g: Entity<Sub> = Sub::new(None, None, vec![call, next]);
+
+        let inference = PrototypeInference::new(vec![rdi.clone(), rsi], vec![rax.clone()]);
+        let prototypes = inference.infer([&f, &g]);
+
+        let pf = prototypes.get(&f).unwrap();
+        assert_eq!(pf.arguments(), &[rdi]);
+        assert_eq!(pf.returns(), Some(&rax));
+        assert_eq!((pf.call_sites(), pf.return_uses()), (1, 1));
+
+        let pg = prototypes.get(&g).unwrap();
+        assert_eq!(pg.arity(), 0);
+        assert!(!pg.returns_value());
+
+        assert_eq!(prototypes.recover_call_args(&mut g), 1);
+    }
+}
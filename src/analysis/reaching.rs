@@ -0,0 +1,326 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use crate::analysis::dataflow::{self, Dataflow, Direction, Lattice};
+use crate::ir::visit::Visit;
+use crate::ir::{Blk, Cfg, Def, Jmp, Phi, Sub, Var};
+use crate::prelude::{Entity, Id, Identifiable};
+
+/// A definition of a variable.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Definition {
+    /// The value of the variable on entry to the Sub
+    Entry,
+    Phi(Id<Phi>),
+    Def(Id<Def>),
+    /// The unknown value left in a physical variable by a call or
+    /// intrinsic that does not preserve it
+    Jmp(Id<Jmp>),
+}
+
+/// A statement that may read variables.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Site {
+    Phi(Id<Phi>),
+    Def(Id<Def>),
+    Jmp(Id<Jmp>),
+}
+
+type Reaching = BTreeMap<Var, BTreeSet<Definition>>;
+
+/// The definitions of each variable that reach the entry and exit of each
+/// Blk of a Sub.
+///
+/// Memory variables are not tracked: stores are not considered to define
+/// them. Calls and intrinsics are assumed to define all physical
+/// variables, except those preserved (see `new_preserving`), e.g., the
+/// callee-saved registers of the calling convention (see
+/// `Lifter::callee_saved`).
+#[derive(Debug, Clone, Default)]
+pub struct ReachingDefinitions {
+    entry: BTreeMap<Id<Blk>, Reaching>,
+    exit: BTreeMap<Id<Blk>, Reaching>,
+    preserve: BTreeSet<Arc<str>>,
+}
+
+struct Vars<'a>(&'a mut BTreeSet<Var>);
+
+impl<'ir, 'a> Visit<'ir> for Vars<'a> {
+    fn visit_var(&mut self, var: &'ir Var) {
+        if !var.is_memory() {
+            self.0.insert(var.clone());
+        }
+    }
+}
+
+fn uses(f: impl FnOnce(&mut Vars)) -> BTreeSet<Var> {
+    let mut vars = BTreeSet::new();
+    f(&mut Vars(&mut vars));
+    vars
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct ReachingState(Reaching);
+
+impl Lattice for ReachingState {
+    fn bottom() -> Self {
+        Self::default()
+    }
+
+    fn join(&mut self, other: &Self) {
+        for (var, defs) in other.0.iter() {
+            self.0
+                .entry(var.clone())
+                .or_default()
+                .extend(defs.iter().copied());
+        }
+    }
+}
+
+// the physical variables not named in preserved are defined by jmp
+fn clobber(jmp: &Entity<Jmp>, preserved: &BTreeSet<Arc<str>>, reaching: &mut Reaching) {
+    if let Jmp::Call(_, _) | Jmp::Intrinsic(_, _) = **jmp {
+        for (var, defs) in reaching.iter_mut() {
+            if var.is_physical() && !preserved.contains(var.name()) {
+                *defs = BTreeSet::from([Definition::Jmp(jmp.id())]);
+            }
+        }
+    }
+}
+
+struct Analysis<'a> {
+    // all variables initially hold their values on entry
+    initial: ReachingState,
+    preserve: &'a BTreeSet<Arc<str>>,
+}
+
+impl<'a> Dataflow for Analysis<'a> {
+    type Value = ReachingState;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn boundary(&self, sub: &Sub, blk: &Entity<Blk>) -> ReachingState {
+        if sub.entry().map(|entry| entry.id() == blk.id()).unwrap_or(false) {
+            self.initial.clone()
+        } else {
+            ReachingState::bottom()
+        }
+    }
+
+    fn transfer_phi(&self, phi: &Entity<Phi>, reaching: &mut ReachingState) {
+        reaching.0.insert(phi.var().clone(), BTreeSet::from([Definition::Phi(phi.id())]));
+    }
+
+    fn transfer_def(&self, def: &Entity<Def>, reaching: &mut ReachingState) {
+        if let Def::Assign(ref var, _) = **def {
+            reaching.0.insert(var.clone(), BTreeSet::from([Definition::Def(def.id())]));
+        }
+    }
+
+    fn transfer_jmp(&self, jmp: &Entity<Jmp>, reaching: &mut ReachingState) {
+        clobber(jmp, self.preserve, &mut reaching.0);
+    }
+}
+
+impl ReachingDefinitions {
+    pub fn new(sub: &Sub) -> Self {
+        Self::new_with(sub, &sub.cfg())
+    }
+
+    pub fn new_with(sub: &Sub, cfg: &Cfg) -> Self {
+        Self::new_preserving(sub, cfg, std::iter::empty::<Arc<str>>())
+    }
+
+    /// As `new_with`, but treating the (physical) variables named by
+    /// `preserved` as preserved by calls and intrinsics.
+    pub fn new_preserving(
+        sub: &Sub,
+        cfg: &Cfg,
+        preserved: impl IntoIterator<Item = impl Into<Arc<str>>>,
+    ) -> Self {
+        let preserve = preserved.into_iter().map(Into::into).collect::<BTreeSet<_>>();
+
+        let initial = uses(|vars| vars.visit_sub(sub))
+            .into_iter()
+            .map(|var| (var, BTreeSet::from([Definition::Entry])))
+            .collect::<Reaching>();
+
+        let analysis = Analysis {
+            initial: ReachingState(initial),
+            preserve: &preserve,
+        };
+        let result = dataflow::solve(&analysis, sub, cfg);
+
+        let mut entry = BTreeMap::new();
+        let mut exit = BTreeMap::new();
+
+        for (id, ientry, iexit) in result.iter() {
+            entry.insert(id, ientry.0.clone());
+            exit.insert(id, iexit.0.clone());
+        }
+
+        Self { entry, exit, preserve }
+    }
+
+    /// The definitions reaching the entry of `blk`.
+    pub fn entry(&self, blk: impl Identifiable<Blk>) -> Option<&BTreeMap<Var, BTreeSet<Definition>>> {
+        self.entry.get(&blk.id())
+    }
+
+    /// The definitions reaching the exit of `blk`.
+    pub fn exit(&self, blk: impl Identifiable<Blk>) -> Option<&BTreeMap<Var, BTreeSet<Definition>>> {
+        self.exit.get(&blk.id())
+    }
+}
+
+/// For each use of a variable, the definitions of the variable that may
+/// reach it.
+#[derive(Debug, Clone, Default)]
+pub struct UseDefChains {
+    chains: BTreeMap<Site, BTreeMap<Var, BTreeSet<Definition>>>,
+}
+
+impl UseDefChains {
+    pub fn new(sub: &Sub) -> Self {
+        Self::from_reaching(sub, &ReachingDefinitions::new(sub))
+    }
+
+    pub fn from_reaching(sub: &Sub, reaching: &ReachingDefinitions) -> Self {
+        let mut chains = BTreeMap::new();
+
+        let mut link = |site: Site, vars: BTreeSet<Var>, state: &Reaching| {
+            let defs = vars
+                .into_iter()
+                .map(|var| {
+                    let defs = state.get(&var).cloned().unwrap_or_default();
+                    (var, defs)
+                })
+                .collect::<BTreeMap<_, _>>();
+            if !defs.is_empty() {
+                chains.insert(site, defs);
+            }
+        };
+
+        for blk in sub.blks() {
+            let mut state = reaching.entry(blk).cloned().unwrap_or_default();
+
+            // phis read their operands as they are on entry to the Blk
+            for phi in blk.phis() {
+                let vars = uses(|vars| {
+                    for (cond, expr) in phi.choices() {
+                        vars.visit_expr(cond);
+                        vars.visit_expr(expr);
+                    }
+                });
+                link(Site::Phi(phi.id()), vars, &state);
+            }
+
+            for phi in blk.phis() {
+                state.insert(phi.var().clone(), BTreeSet::from([Definition::Phi(phi.id())]));
+            }
+
+            for def in blk.defs() {
+                let vars = uses(|vars| match **def {
+                    Def::Assign(_, ref expr) | Def::Assume(ref expr) => vars.visit_expr(expr),
+                    Def::Store(ref loc, ref val, _, _) => {
+                        vars.visit_expr(loc);
+                        vars.visit_expr(val);
+                    }
+                });
+                link(Site::Def(def.id()), vars, &state);
+
+                if let Def::Assign(ref var, _) = **def {
+                    state.insert(var.clone(), BTreeSet::from([Definition::Def(def.id())]));
+                }
+            }
+
+            for jmp in blk.jmps() {
+                let vars = uses(|vars| vars.visit_jmp(jmp));
+                link(Site::Jmp(jmp.id()), vars, &state);
+
+                // the jmps following a call read the variables it defines
+                clobber(jmp, &reaching.preserve, &mut state);
+            }
+        }
+
+        Self { chains }
+    }
+
+    /// The definitions of `var` that may reach its use at `site`.
+    pub fn defs(&self, site: Site, var: &Var) -> Option<&BTreeSet<Definition>> {
+        self.chains.get(&site).and_then(|vars| vars.get(var))
+    }
+
+    /// The variables read at `site`, and the definitions reaching each.
+    pub fn uses_at(&self, site: Site) -> Option<&BTreeMap<Var, BTreeSet<Definition>>> {
+        self.chains.get(&site)
+    }
+
+    /// The uses reached by `def` (i.e., its def-use chain).
+    pub fn uses(&self, def: Definition) -> impl Iterator<Item = (Site, &Var)> + '_ {
+        self.chains.iter().flat_map(move |(site, vars)| {
+            vars.iter()
+                .filter(move |(_, defs)| defs.contains(&def))
+                .map(move |(var, _)| (*site, var))
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Site, &Var, &BTreeSet<Definition>)> + '_ {
+        self.chains.iter().flat_map(|(site, vars)| {
+            vars.iter().map(move |(var, defs)| (*site, var, defs))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::{Addr, BinOp, Expr, Loc};
+    use crate::ir::value::bv::BitVec;
+    use crate::types::bv::BitVecT;
+
+    #[test]
+    fn test_clobbered_by_call() {
+        let typ = BitVecT::with_bits(32, false);
+        let reg = |name: &str| -> Var { Var::physical(name, typ).into() };
+        let (eax, ebx) = (reg("EAX"), reg("EBX"));
+        let tmp: Var = Var::transient("t", typ).into();
+
+        // EAX := 1; EBX := 2; call 0x2000; if EAX goto next; ...
+        // next: t := EAX + EBX; return t
+        let mut call = Blk::new(None);
+        let mut next = Blk::new(None);
+
+        let ebx_def = Def::assign(ebx.clone(), BitVec::from_u64(2, 32));
+        let call_jmp = Jmp::call(Loc::Fixed(Addr::from(0x2000u32)), []);
+        let cbranch = Jmp::cbranch(next.id(), Expr::from(eax.clone()));
+        let sum = Def::assign(
+            tmp.clone(),
+            Expr::BinOp(BinOp::Add, Box::new(eax.clone().into()), Box::new(ebx.clone().into())),
+        );
+        let (ebx_def_id, call_id, cbranch_id, sum_id) = (ebx_def.id(), call_jmp.id(), cbranch.id(), sum.id());
+
+        call.add_def(Def::assign(eax.clone(), BitVec::from_u64(1, 32)));
+        call.add_def(ebx_def);
+        call.add_jmp(call_jmp);
+        call.add_jmp(cbranch);
+        call.add_jmp(Jmp::branch(next.id()));
+        next.add_def(sum);
+        next.add_jmp(Jmp::return_(Expr::from(tmp)));
+        let sub: Entity<Sub> = Sub::new(None, None, vec![call, next]);
+
+        let clobbered = BTreeSet::from([Definition::Jmp(call_id)]);
+
+        // uses after the call are reached by the call, not the defs before it
+        let chains = UseDefChains::new(&sub);
+        assert_eq!(chains.defs(Site::Jmp(cbranch_id), &eax), Some(&clobbered));
+        assert_eq!(chains.defs(Site::Def(sum_id), &eax), Some(&clobbered));
+        assert_eq!(chains.defs(Site::Def(sum_id), &ebx), Some(&clobbered));
+
+        // callee-saved registers survive the call
+        let reaching = ReachingDefinitions::new_preserving(&sub, &sub.cfg(), ["EBX"]);
+        let chains = UseDefChains::from_reaching(&sub, &reaching);
+        assert_eq!(chains.defs(Site::Def(sum_id), &eax), Some(&clobbered));
+        assert_eq!(chains.defs(Site::Def(sum_id), &ebx), Some(&BTreeSet::from([Definition::Def(ebx_def_id)])));
+    }
+}
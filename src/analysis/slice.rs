@@ -0,0 +1,250 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use petgraph::algo::dominators;
+use petgraph::graphmap::DiGraphMap;
+use petgraph::visit::Reversed;
+
+use crate::analysis::reaching::{Definition, Site, UseDefChains};
+use crate::ir::{Blk, Def, Jmp, Loc, Sub};
+use crate::prelude::{Id, Identifiable};
+
+/// The statements (and the Blks containing them) of a Sub within a slice.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Slice {
+    sites: BTreeSet<Site>,
+    blks: BTreeSet<Id<Blk>>,
+}
+
+impl Slice {
+    pub fn contains(&self, site: Site) -> bool {
+        self.sites.contains(&site)
+    }
+
+    pub fn contains_blk(&self, blk: impl Identifiable<Blk>) -> bool {
+        self.blks.contains(&blk.id())
+    }
+
+    pub fn sites(&self) -> impl Iterator<Item = Site> + '_ {
+        self.sites.iter().copied()
+    }
+
+    pub fn blks(&self) -> impl Iterator<Item = Id<Blk>> + '_ {
+        self.blks.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sites.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sites.is_empty()
+    }
+}
+
+/// The data and control dependencies between the statements of a Sub.
+///
+/// Data dependencies are given by the Sub's use-def chains; hence, flows
+/// through memory are not considered. A Blk is control dependent on
+/// another if the latter has a successor the former post-dominates, but
+/// the former does not post-dominate the latter; a statement is control
+/// dependent on the Jmps of each Blk its Blk is control dependent on.
+#[derive(Debug, Clone, Default)]
+pub struct Dependencies {
+    chains: UseDefChains,
+    sites: BTreeMap<Site, Id<Blk>>,
+    blks: BTreeMap<Id<Blk>, Vec<Site>>,
+    // blk -> the blks it is control dependent on
+    controllers: BTreeMap<Id<Blk>, BTreeSet<Id<Blk>>>,
+    // blk -> the blks control dependent on it
+    controlled: BTreeMap<Id<Blk>, BTreeSet<Id<Blk>>>,
+}
+
+impl Dependencies {
+    pub fn new(sub: &Sub) -> Self {
+        let mut deps = Self {
+            chains: sub.use_def_chains(),
+            ..Default::default()
+        };
+
+        for blk in sub.blks() {
+            let sites = blk
+                .phis()
+                .iter()
+                .map(|phi| Site::Phi(phi.id()))
+                .chain(blk.defs().iter().map(|def| Site::Def(def.id())))
+                .chain(blk.jmps().iter().map(|jmp| Site::Jmp(jmp.id())))
+                .collect::<Vec<_>>();
+
+            for site in sites.iter() {
+                deps.sites.insert(*site, blk.id());
+            }
+            deps.blks.insert(blk.id(), sites);
+        }
+
+        deps.control_dependencies(sub);
+        deps
+    }
+
+    fn control_dependencies(&mut self, sub: &Sub) {
+        let blks = sub.blks();
+        let indices = blks
+            .iter()
+            .enumerate()
+            .map(|(i, blk)| (blk.id(), i))
+            .collect::<BTreeMap<_, _>>();
+
+        // we add a virtual exit node, to which each Blk that may leave the
+        // Sub flows
+        let exit = blks.len();
+        let mut graph = DiGraphMap::<usize, ()>::new();
+
+        graph.add_node(exit);
+        for i in 0..blks.len() {
+            graph.add_node(i);
+        }
+
+        for (i, blk) in blks.iter().enumerate() {
+            let mut exits = blk.jmps().is_empty();
+            for jmp in blk.jmps() {
+                match **jmp {
+                    Jmp::Branch(Loc::Resolved(id)) | Jmp::CBranch(Loc::Resolved(id), _)
+                        if indices.contains_key(&id) =>
+                    {
+                        graph.add_edge(i, indices[&id], ());
+                    }
+                    _ => {
+                        exits = true;
+                    }
+                }
+            }
+            if exits {
+                graph.add_edge(i, exit, ());
+            }
+        }
+
+        let pdoms = dominators::simple_fast(Reversed(&graph), exit);
+
+        for (from, to, _) in graph.all_edges() {
+            if to == exit {
+                continue
+            }
+
+            let stop = pdoms.immediate_dominator(from);
+            let mut runner = Some(to);
+
+            while let Some(node) = runner {
+                if Some(node) == stop || node == exit {
+                    break
+                }
+
+                let (controller, controlled) = (blks[from].id(), blks[node].id());
+                self.controllers.entry(controlled).or_default().insert(controller);
+                self.controlled.entry(controller).or_default().insert(controlled);
+
+                runner = pdoms.immediate_dominator(node);
+            }
+        }
+    }
+
+    pub fn use_def_chains(&self) -> &UseDefChains {
+        &self.chains
+    }
+
+    /// The Blks that `blk` is control dependent on.
+    pub fn controllers(&self, blk: impl Identifiable<Blk>) -> impl Iterator<Item = Id<Blk>> + '_ {
+        self.controllers.get(&blk.id()).into_iter().flatten().copied()
+    }
+
+    /// The Blks control dependent on `blk`.
+    pub fn controlled(&self, blk: impl Identifiable<Blk>) -> impl Iterator<Item = Id<Blk>> + '_ {
+        self.controlled.get(&blk.id()).into_iter().flatten().copied()
+    }
+
+    /// The statements `site` depends on, directly.
+    fn dependencies(&self, site: Site) -> Vec<Site> {
+        let mut sites = Vec::new();
+
+        for (_, defs) in self.chains.uses_at(site).into_iter().flatten() {
+            sites.extend(defs.iter().filter_map(|def| match def {
+                Definition::Entry => None,
+                Definition::Phi(id) => Some(Site::Phi(*id)),
+                Definition::Def(id) => Some(Site::Def(*id)),
+                Definition::Jmp(id) => Some(Site::Jmp(*id)),
+            }));
+        }
+
+        if let Some(blk) = self.sites.get(&site) {
+            for controller in self.controllers(*blk) {
+                sites.extend(
+                    self.blks[&controller]
+                        .iter()
+                        .filter(|site| matches!(site, Site::Jmp(_))),
+                );
+            }
+        }
+
+        sites
+    }
+
+    /// The statements that depend on `site`, directly.
+    fn dependents(&self, site: Site) -> Vec<Site> {
+        let def = match site {
+            Site::Phi(id) => Some(Definition::Phi(id)),
+            Site::Def(id) => Some(Definition::Def(id)),
+            Site::Jmp(id) => Some(Definition::Jmp(id)),
+        };
+
+        let mut sites = def
+            .map(|def| self.chains.uses(def).map(|(site, _)| site).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        if let (Site::Jmp(_), Some(blk)) = (site, self.sites.get(&site)) {
+            for controlled in self.controlled(*blk) {
+                sites.extend(self.blks[&controlled].iter().copied());
+            }
+        }
+
+        sites
+    }
+
+    fn closure(&self, site: Site, next: impl Fn(Site) -> Vec<Site>) -> Slice {
+        let mut slice = Slice::default();
+        let mut worklist = vec![site];
+
+        while let Some(site) = worklist.pop() {
+            if !slice.sites.insert(site) {
+                continue
+            }
+
+            if let Some(blk) = self.sites.get(&site) {
+                slice.blks.insert(*blk);
+            }
+
+            worklist.extend(next(site));
+        }
+
+        slice
+    }
+
+    /// The statements that may affect `site`, including itself.
+    pub fn backward(&self, site: Site) -> Slice {
+        self.closure(site, |site| self.dependencies(site))
+    }
+
+    /// The statements that may be affected by `site`, including itself.
+    pub fn forward(&self, site: Site) -> Slice {
+        self.closure(site, |site| self.dependents(site))
+    }
+}
+
+/// Computes the statements of `sub` that may affect the value assigned,
+/// assumed or stored by `def` (i.e., a backward slice).
+pub fn backward(sub: &Sub, def: impl Identifiable<Def>) -> Slice {
+    Dependencies::new(sub).backward(Site::Def(def.id()))
+}
+
+/// Computes the statements of `sub` that may be affected by `def` (i.e., a
+/// forward slice).
+pub fn forward(sub: &Sub, def: impl Identifiable<Def>) -> Slice {
+    Dependencies::new(sub).forward(Site::Def(def.id()))
+}
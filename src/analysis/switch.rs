@@ -0,0 +1,580 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::Arc;
+
+use crate::analysis::reaching::{Definition, ReachingDefinitions, Site, UseDefChains};
+use crate::ir::memory::Mem;
+use crate::ir::{Addr, BinOp, BinRel, BitVec, Blk, Cast, Cfg, Def, Expr, Jmp, Loc, Sub, UnOp};
+use crate::passes::simplify;
+use crate::prelude::{Entity, Id, Identifiable};
+
+/// How the entries of a jump table encode their targets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableKind {
+    /// Each entry is the address of a target
+    Absolute,
+    /// Each entry is a signed offset of a target from `base`
+    Relative { base: Addr },
+}
+
+/// A jump table recovered for a computed branch.
+#[derive(Debug, Clone)]
+pub struct JumpTable {
+    blk: Id<Blk>,
+    jmp: Id<Jmp>,
+    table: Addr,
+    entry_bits: u32,
+    kind: TableKind,
+    targets: Vec<Addr>,
+}
+
+impl JumpTable {
+    /// The Blk containing the computed branch.
+    pub fn blk(&self) -> Id<Blk> {
+        self.blk
+    }
+
+    /// The computed branch.
+    pub fn jmp(&self) -> Id<Jmp> {
+        self.jmp
+    }
+
+    /// The address of the first entry of the table.
+    pub fn table(&self) -> &Addr {
+        &self.table
+    }
+
+    pub fn entry_bits(&self) -> u32 {
+        self.entry_bits
+    }
+
+    pub fn kind(&self) -> &TableKind {
+        &self.kind
+    }
+
+    /// The target of each entry of the table, in order.
+    pub fn targets(&self) -> &[Addr] {
+        &self.targets
+    }
+
+    /// Materialises the targets of the table within `blk`: the computed
+    /// branch is followed by a branch to each distinct target, which the
+    /// Cfg treats as a successor of `blk` (see `Edge::Switch`). Each branch
+    /// is to the Blk `resolve` gives for its target, if any, or to its
+    /// address otherwise; targets already materialised are not repeated.
+    /// Returns false if `blk` does not contain the computed branch.
+    pub fn materialise(&self, blk: &mut Blk, resolve: impl Fn(&Addr) -> Option<Id<Blk>>) -> bool {
+        if !blk.jmps().iter().any(|jmp| jmp.id() == self.jmp) {
+            return false
+        }
+        materialise_switch(blk, self.jmp, &self.targets, resolve);
+        true
+    }
+}
+
+// follows the computed branch jmp of blk with a branch to each of targets
+// not already following it, returning the ids of the branches added
+pub(crate) fn materialise_switch<'a>(
+    blk: &mut Blk,
+    jmp: Id<Jmp>,
+    targets: impl IntoIterator<Item = &'a Addr>,
+    resolve: impl Fn(&Addr) -> Option<Id<Blk>>,
+) -> Vec<Id<Jmp>> {
+    let position = match blk.jmps().iter().position(|j| j.id() == jmp) {
+        Some(i) if matches!(*blk.jmps()[i], Jmp::Branch(Loc::Computed(_))) => i,
+        _ => return Vec::new(),
+    };
+
+    // the branches following the computed branch are never taken directly;
+    // they record its targets
+    let switch = blk.jmps()[position + 1..]
+        .iter()
+        .map_while(|jmp| match **jmp {
+            Jmp::Branch(ref loc @ (Loc::Fixed(_) | Loc::Resolved(_))) => Some(loc.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let end = position + 1 + switch.len();
+
+    let mut existing = switch;
+    let mut branches = Vec::new();
+    for addr in targets.into_iter().collect::<BTreeSet<_>>() {
+        let loc = resolve(addr).map(Loc::Resolved).unwrap_or_else(|| Loc::Fixed(addr.clone()));
+        if !existing.contains(&loc) {
+            existing.push(loc.clone());
+            branches.push(Jmp::branch(loc));
+        }
+    }
+
+    let ids = branches.iter().map(|branch| branch.id()).collect();
+    blk.jmps_mut().splice(end..end, branches);
+    ids
+}
+
+/// Recovers jump tables for the computed branches of a Sub.
+///
+/// We recognise computed branches whose targets are loaded from tables
+/// (`load(table + index * size)`), or are offsets loaded from tables
+/// added to a fixed base (`base + sext(load(table + index * size))`),
+/// where the number of entries is bounded by a conditional branch that
+/// compares the index to a constant (e.g., the default case of a switch).
+/// Target expressions are expanded by substituting the definitions of the
+/// variables they read when each has a unique reaching definition; calls
+/// and intrinsics define the physical variables they do not preserve (see
+/// `preserve`).
+#[derive(Debug, Clone)]
+pub struct SwitchAnalysis {
+    max_entries: usize,
+    max_depth: usize,
+    preserve: BTreeSet<Arc<str>>,
+}
+
+impl Default for SwitchAnalysis {
+    fn default() -> Self {
+        Self {
+            max_entries: 1024,
+            max_depth: 8,
+            preserve: BTreeSet::new(),
+        }
+    }
+}
+
+struct Candidate {
+    table: Addr,
+    entry_bits: u32,
+    index: Expr,
+    base: Option<Addr>,
+}
+
+impl SwitchAnalysis {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ignore tables with more than `max` entries; defaults to 1024.
+    pub fn max_entries(&mut self, max: usize) -> &mut Self {
+        self.max_entries = max;
+        self
+    }
+
+    /// Substitute definitions at most `max` times along any path when
+    /// expanding expressions; defaults to 8.
+    pub fn max_depth(&mut self, max: usize) -> &mut Self {
+        self.max_depth = max;
+        self
+    }
+
+    /// Treat (physical) variables named `name` as preserved by calls and
+    /// intrinsics, e.g., the callee-saved registers of the calling
+    /// convention (see `Lifter::callee_saved`).
+    pub fn preserve(&mut self, name: impl Into<Arc<str>>) -> &mut Self {
+        self.preserve.insert(name.into());
+        self
+    }
+
+    pub fn recover(&self, sub: &Sub, memory: &Mem) -> Vec<JumpTable> {
+        let cfg = sub.cfg();
+        let reaching = ReachingDefinitions::new_preserving(sub, &cfg, self.preserve.iter().cloned());
+        let chains = UseDefChains::from_reaching(sub, &reaching);
+
+        let defs = sub
+            .blks()
+            .iter()
+            .flat_map(|blk| blk.defs().iter())
+            .filter_map(|def| match **def {
+                Def::Assign(_, ref expr) => Some((def.id(), expr)),
+                _ => None,
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let mut tables = Vec::new();
+
+        for blk in sub.blks() {
+            for jmp in blk.jmps() {
+                let target = if let Jmp::Branch(Loc::Computed(ref target)) = **jmp {
+                    target
+                } else {
+                    continue
+                };
+
+                let site = Site::Jmp(jmp.id());
+                let target = self.expand(&chains, &defs, site, target, self.max_depth);
+
+                let candidate = if let Some(candidate) = Self::candidate(&target) {
+                    candidate
+                } else {
+                    continue
+                };
+
+                let count = if let Some(count) =
+                    self.bound(sub, &cfg, &chains, &defs, blk.id(), jmp.id(), &candidate.index)
+                {
+                    count.min(self.max_entries)
+                } else {
+                    continue
+                };
+
+                let targets = Self::read_targets(memory, &candidate, count);
+                if targets.is_empty() {
+                    continue
+                }
+
+                tables.push(JumpTable {
+                    blk: blk.id(),
+                    jmp: jmp.id(),
+                    table: candidate.table,
+                    entry_bits: candidate.entry_bits,
+                    kind: candidate
+                        .base
+                        .map(|base| TableKind::Relative { base })
+                        .unwrap_or(TableKind::Absolute),
+                    targets,
+                });
+            }
+        }
+
+        tables
+    }
+
+    fn expand(
+        &self,
+        chains: &UseDefChains,
+        defs: &BTreeMap<Id<Def>, &Expr>,
+        site: Site,
+        expr: &Expr,
+        depth: usize,
+    ) -> Expr {
+        let mut expr = expr.clone();
+
+        if depth > 0 {
+            expr.substitute(&mut |var| {
+                let reaching = chains.defs(site, var)?;
+                if reaching.len() != 1 {
+                    return None
+                }
+
+                match reaching.iter().next() {
+                    Some(Definition::Def(id)) => defs.get(id).map(|expr| {
+                        self.expand(chains, defs, Site::Def(*id), expr, depth - 1)
+                    }),
+                    _ => None,
+                }
+            });
+        }
+
+        simplify(expr)
+    }
+
+    fn strip_casts(expr: &Expr) -> &Expr {
+        match expr {
+            Expr::Cast(expr, Cast::Signed(_) | Cast::Unsigned(_)) => Self::strip_casts(expr),
+            _ => expr,
+        }
+    }
+
+    fn constant_operand(lexpr: &Expr, rexpr: &Expr) -> Option<(BitVec, Expr)> {
+        match (lexpr, rexpr) {
+            (expr, Expr::Val(bv)) | (Expr::Val(bv), expr) => Some((bv.clone(), expr.clone())),
+            _ => None,
+        }
+    }
+
+    // table + index * scale, or table + (index << shift)
+    fn table_address(expr: &Expr) -> Option<(Addr, u64, Expr)> {
+        let (table, offset) = match expr {
+            Expr::BinOp(BinOp::Add, lexpr, rexpr) => Self::constant_operand(lexpr, rexpr)?,
+            _ => return None,
+        };
+
+        let (scale, index) = match offset {
+            Expr::BinOp(BinOp::Mul, ref lexpr, ref rexpr) => {
+                let (scale, index) = Self::constant_operand(lexpr, rexpr)?;
+                (scale.to_u64()?, index)
+            }
+            Expr::BinOp(BinOp::Shl, ref index, ref shift) => {
+                let shift = shift.val()?.to_u64().filter(|shift| *shift < 64)?;
+                (1u64 << shift, (**index).clone())
+            }
+            _ => return None,
+        };
+
+        Some((Addr::from(table), scale, index))
+    }
+
+    fn candidate(target: &Expr) -> Option<Candidate> {
+        let (base, load) = match target {
+            Expr::Load(_, _, _) => (None, target),
+            Expr::BinOp(BinOp::Add, lexpr, rexpr) => {
+                let (base, load) = match (&**lexpr, &**rexpr) {
+                    (load, Expr::Val(bv)) | (Expr::Val(bv), load) => (bv, load),
+                    _ => return None,
+                };
+                (Some(Addr::from(base.clone())), Self::strip_casts(load))
+            }
+            _ => return None,
+        };
+
+        let (address, entry_bits) = match load {
+            Expr::Load(address, bits, _) => (address, *bits),
+            _ => return None,
+        };
+
+        let (table, scale, index) = Self::table_address(address)?;
+        if scale * 8 != entry_bits as u64 {
+            return None
+        }
+
+        Some(Candidate {
+            table,
+            entry_bits,
+            index: Self::strip_casts(&index).clone(),
+            base,
+        })
+    }
+
+    // the number of entries permitted by a comparison of index with a
+    // constant, given whether the comparison holds
+    fn guard_count(cond: &Expr, index: &Expr, holds: bool) -> Option<usize> {
+        let (op, lexpr, rexpr) = match cond {
+            Expr::UnOp(UnOp::Not, cond) => return Self::guard_count(cond, index, !holds),
+            Expr::BinRel(op @ (BinRel::Lt | BinRel::Le), lexpr, rexpr) => (*op, lexpr, rexpr),
+            _ => return None,
+        };
+
+        // !(a < b) is b <= a; !(a <= b) is b < a
+        let (op, lexpr, rexpr) = match (holds, op) {
+            (true, op) => (op, lexpr, rexpr),
+            (false, BinRel::Lt) => (BinRel::Le, rexpr, lexpr),
+            (false, _) => (BinRel::Lt, rexpr, lexpr),
+        };
+
+        let (lexpr, rexpr) = (Self::strip_casts(lexpr), Self::strip_casts(rexpr));
+
+        let bound = match (lexpr, rexpr) {
+            (expr, Expr::Val(bv)) if expr == index => bv.to_usize()?,
+            // n < index and n <= index do not bound the index
+            _ => return None,
+        };
+
+        // index < n: n entries; index <= n: n + 1
+        match op {
+            BinRel::Lt => Some(bound),
+            _ => bound.checked_add(1),
+        }
+    }
+
+    // the number of entries permitted by the conditional branch jmp, given
+    // whether it is taken
+    fn guard(
+        &self,
+        chains: &UseDefChains,
+        defs: &BTreeMap<Id<Def>, &Expr>,
+        jmp: &Entity<Jmp>,
+        index: &Expr,
+        taken: bool,
+    ) -> Option<usize> {
+        if let Jmp::CBranch(_, ref cond) = **jmp {
+            let cond = self.expand(chains, defs, Site::Jmp(jmp.id()), cond, self.max_depth);
+            Self::guard_count(&cond, index, taken)
+        } else {
+            None
+        }
+    }
+
+    // finds the tightest bound on the index among the conditional branches
+    // on the paths to the computed branch jmp of blk: those taken towards
+    // it bound the index by their conditions, and those not taken (i.e.,
+    // preceding the flow towards it within their Blks) by the negations of
+    // their conditions
+    #[allow(clippy::too_many_arguments)]
+    fn bound(
+        &self,
+        sub: &Sub,
+        cfg: &Cfg,
+        chains: &UseDefChains,
+        defs: &BTreeMap<Id<Def>, &Expr>,
+        blk: Id<Blk>,
+        jmp: Id<Jmp>,
+        index: &Expr,
+    ) -> Option<usize> {
+        let mut counts = Vec::new();
+
+        if let Some(blk) = sub.blk(blk) {
+            for jmp in blk.jmps().iter().take_while(|j| j.id() != jmp) {
+                counts.extend(self.guard(chains, defs, jmp, index, false));
+            }
+        }
+
+        let mut visited = BTreeSet::from([blk]);
+        let mut worklist = VecDeque::from([blk]);
+
+        while let Some(id) = worklist.pop_front() {
+            for pred in cfg.predecessors(id) {
+                if let Some(blk) = sub.blk(pred) {
+                    // the first flow towards id is that taken
+                    let position = blk.jmps().iter().position(|jmp| {
+                        matches!(**jmp, Jmp::Branch(Loc::Resolved(to)) | Jmp::CBranch(Loc::Resolved(to), _) if to == id)
+                    });
+
+                    if let Some(position) = position {
+                        for jmp in blk.jmps()[..position].iter() {
+                            counts.extend(self.guard(chains, defs, jmp, index, false));
+                        }
+                        counts.extend(self.guard(chains, defs, &blk.jmps()[position], index, true));
+                    }
+                }
+
+                if visited.insert(pred) {
+                    worklist.push_back(pred);
+                }
+            }
+        }
+
+        counts.into_iter().min().filter(|count| *count > 0)
+    }
+
+    fn read_targets(memory: &Mem, candidate: &Candidate, count: usize) -> Vec<Addr> {
+        let size = (candidate.entry_bits / 8) as usize;
+        let mut targets = Vec::with_capacity(count);
+
+        for i in 0..count {
+            let addr = &candidate.table + i * size;
+            let entry = match memory
+                .read_bits(&addr, candidate.entry_bits)
+                .ok()
+                .flatten()
+            {
+                Some(entry) => entry,
+                // the table ends at the first unmapped entry
+                None => break,
+            };
+
+            let target = if let Some(ref base) = candidate.base {
+                let base = BitVec::from(base.clone());
+                let bits = base.bits();
+                base + entry.signed_cast(bits)
+            } else {
+                entry.unsigned_cast(candidate.table.bits() as usize)
+            };
+
+            if target.is_zero() {
+                break
+            }
+
+            targets.push(Addr::from(target));
+        }
+
+        targets
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::memory::Region;
+    use crate::ir::cfg::Edge;
+    use crate::ir::Var;
+    use crate::prelude::Endian;
+    use crate::types::bv::BitVecT;
+
+    // a Sub branching on the guard to the Blk holding the computed branch
+    // through a table of six entries at 0x2000 when taken, if table_taken,
+    // or otherwise when not taken; the first entry's target is a Blk of the
+    // Sub
+    fn switch(guard: Expr, table_taken: bool) -> (Entity<Sub>, Mem<'static>) {
+        let typ = BitVecT::with_bits(32, false);
+        let index: Var = Var::physical("EAX", typ).into();
+
+        let mut mem = Mem::new("M");
+        let entries = (1..=6u32).flat_map(|i| (0x1000 + i * 0x100).to_le_bytes()).collect::<Vec<_>>();
+        mem.add_region(Region::try_new("table", 0x2000u32, Endian::Little, entries).unwrap());
+
+        let mut entry = Blk::new(Some(Addr::from(0x1000u32)));
+        let mut table = Blk::new(Some(Addr::from(0x1010u32)));
+        let mut default = Blk::new(Some(Addr::from(0x1020u32)));
+        let mut case = Blk::new(Some(Addr::from(0x1100u32)));
+
+        let (taken, not_taken) = if table_taken { (table.id(), default.id()) } else { (default.id(), table.id()) };
+        entry.add_jmp(Jmp::cbranch(taken, guard));
+        entry.add_jmp(Jmp::branch(not_taken));
+
+        let address = Expr::bin_op(
+            BinOp::Add,
+            BitVec::from_u64(0x2000, 32),
+            Expr::bin_op(BinOp::Mul, index, BitVec::from_u64(4, 32)),
+        );
+        table.add_jmp(Jmp::branch(Expr::load(address, 32, Var::memory(&mem))));
+        default.add_jmp(Jmp::return_(Expr::from(BitVec::from_u64(0, 32))));
+        case.add_jmp(Jmp::return_(Expr::from(BitVec::from_u64(1, 32))));
+
+        (Sub::new(None, Addr::from(0x1000u32), vec![entry, table, default, case]), mem)
+    }
+
+    fn index() -> Expr {
+        Expr::from(Var::from(Var::physical("EAX", BitVecT::with_bits(32, false))))
+    }
+
+    fn targets(n: u32) -> Vec<Addr> {
+        (1..=n).map(|i| Addr::from(0x1000 + i * 0x100)).collect()
+    }
+
+    #[test]
+    fn test_bound_by_guard_not_taken() {
+        // ja default: the table is reached when 3 < index does not hold
+        let (sub, mem) = switch(Expr::bin_rel(BinRel::Lt, BitVec::from_u64(3, 32), index()), false);
+        let tables = SwitchAnalysis::new().recover(&sub, &mem);
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].targets(), &targets(4)[..]);
+
+        // jbe default: the table is reached when index <= 3 does not hold,
+        // which does not bound the index
+        let (sub, mem) = switch(Expr::bin_rel(BinRel::Le, index(), BitVec::from_u64(3, 32)), false);
+        assert!(SwitchAnalysis::new().recover(&sub, &mem).is_empty());
+    }
+
+    #[test]
+    fn test_bound_by_guard_taken() {
+        // jbe case: the table is reached when index <= 3 holds
+        let (sub, mem) = switch(Expr::bin_rel(BinRel::Le, index(), BitVec::from_u64(3, 32)), true);
+        let tables = SwitchAnalysis::new().recover(&sub, &mem);
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].targets(), &targets(4)[..]);
+
+        // jb case
+        let (sub, mem) = switch(Expr::bin_rel(BinRel::Lt, index(), BitVec::from_u64(2, 32)), true);
+        let tables = SwitchAnalysis::new().recover(&sub, &mem);
+        assert_eq!(tables[0].targets(), &targets(2)[..]);
+
+        // ja case: 3 < index does not bound the index
+        let (sub, mem) = switch(Expr::bin_rel(BinRel::Lt, BitVec::from_u64(3, 32), index()), true);
+        assert!(SwitchAnalysis::new().recover(&sub, &mem).is_empty());
+    }
+
+    #[test]
+    fn test_materialise() {
+        let (mut sub, mem) = switch(Expr::bin_rel(BinRel::Le, index(), BitVec::from_u64(1, 32)), true);
+        let tables = SwitchAnalysis::new().recover(&sub, &mem);
+        assert_eq!(tables.len(), 1);
+
+        let blks = sub
+            .blks()
+            .iter()
+            .filter_map(|blk| Some((blk.addr()?.clone(), blk.id())))
+            .collect::<BTreeMap<_, _>>();
+        let case = blks[&Addr::from(0x1100u32)];
+
+        let blk = sub.blk_mut(tables[0].blk()).unwrap();
+        assert!(tables[0].materialise(blk, |addr| blks.get(addr).copied()));
+        // targets already materialised are not repeated
+        assert!(tables[0].materialise(blk, |addr| blks.get(addr).copied()));
+
+        let jmps = blk.jmps();
+        assert_eq!(jmps.len(), 3);
+        assert!(matches!(*jmps[0], Jmp::Branch(Loc::Computed(_))));
+        assert!(matches!(*jmps[1], Jmp::Branch(Loc::Resolved(id)) if id == case));
+        assert!(matches!(*jmps[2], Jmp::Branch(Loc::Fixed(ref addr)) if *addr == Addr::from(0x1200u32)));
+
+        let cfg = sub.cfg();
+        assert!(cfg.edges().any(|edge| edge == (tables[0].blk(), case, Edge::Switch)));
+    }
+}
@@ -0,0 +1,704 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::Arc;
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::analysis::dataflow::{self, Dataflow, Direction, Lattice};
+use crate::ir::{Addr, Blk, Def, Expr, Jmp, Loc, Phi, Sub, Var};
+use crate::prelude::{Entity, Id, Identifiable};
+
+#[derive(Debug, Error)]
+pub enum TaintSpecError {
+    #[error("line {0}: {1}")]
+    Json(usize, #[source] serde_json::Error),
+    #[error("line {0}: malformed rule {1}")]
+    Malformed(usize, String),
+}
+
+/// A function named by a taint rule: by its symbol or its address.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Callee {
+    Symbol(Arc<str>),
+    Addr(Addr),
+}
+
+impl Callee {
+    fn matches(&self, sub: &Sub) -> bool {
+        match self {
+            Self::Symbol(symbol) => sub.symbol() == Some(symbol),
+            Self::Addr(addr) => sub.addr() == Some(addr),
+        }
+    }
+}
+
+/// How calls to a function affect taint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaintRule {
+    /// Calls to `function` return tainted data, or, if `arg` is given,
+    /// leave tainted data in their `arg`th argument
+    Source { function: Callee, arg: Option<usize> },
+    /// Tainted data must not reach the `arg`th argument of `function`
+    Sink { function: Callee, arg: usize },
+    /// `function` validates its `arg`th argument: the data it was derived
+    /// from is no longer tainted after calls to `function`
+    Sanitizer { function: Callee, arg: usize },
+}
+
+/// The sources, sinks, and sanitizers of a taint analysis.
+///
+/// Specifications can be written to, and read from, JSON lines (see
+/// `to_jsonl`); e.g.:
+///
+/// ```json
+/// { "rule": "source", "function": "recv", "arg": 1 }
+/// { "rule": "source", "function": "getenv" }
+/// { "rule": "sink", "function": "system", "arg": 0 }
+/// { "rule": "sanitizer", "addr": "0x401200", "arg": 0 }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TaintSpec {
+    rules: Vec<TaintRule>,
+}
+
+impl TaintSpec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, rule: TaintRule) -> &mut Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn source(&mut self, function: Callee, arg: Option<usize>) -> &mut Self {
+        self.add(TaintRule::Source { function, arg })
+    }
+
+    pub fn sink(&mut self, function: Callee, arg: usize) -> &mut Self {
+        self.add(TaintRule::Sink { function, arg })
+    }
+
+    pub fn sanitizer(&mut self, function: Callee, arg: usize) -> &mut Self {
+        self.add(TaintRule::Sanitizer { function, arg })
+    }
+
+    pub fn rules(&self) -> &[TaintRule] {
+        &self.rules
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The rules as JSON lines, in the order they were added.
+    pub fn to_jsonl(&self) -> String {
+        let mut text = String::new();
+
+        for rule in self.rules.iter() {
+            let mut object = Map::new();
+            let (kind, function, arg) = match rule {
+                TaintRule::Source { function, arg } => ("source", function, *arg),
+                TaintRule::Sink { function, arg } => ("sink", function, Some(*arg)),
+                TaintRule::Sanitizer { function, arg } => ("sanitizer", function, Some(*arg)),
+            };
+
+            object.insert("rule".to_owned(), Value::from(kind));
+            match function {
+                Callee::Symbol(symbol) => {
+                    object.insert("function".to_owned(), Value::from(&**symbol));
+                }
+                Callee::Addr(addr) => {
+                    let addr = format!("{:#x}", u64::try_from(addr).unwrap_or(0));
+                    object.insert("addr".to_owned(), Value::from(addr));
+                }
+            }
+            if let Some(arg) = arg {
+                object.insert("arg".to_owned(), Value::from(arg as u64));
+            }
+
+            text.push_str(&Value::Object(object).to_string());
+            text.push('\n');
+        }
+
+        text
+    }
+
+    /// Reads rules from JSON lines (see `to_jsonl`), whose addresses are
+    /// `bits` wide.
+    pub fn from_jsonl(text: &str, bits: u32) -> Result<Self, TaintSpecError> {
+        let mut spec = Self::new();
+
+        for (i, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let line_no = i + 1;
+            let value = line
+                .parse::<Value>()
+                .map_err(|e| TaintSpecError::Json(line_no, e))?;
+            let malformed = || TaintSpecError::Malformed(line_no, line.to_owned());
+
+            let function = if let Some(symbol) = value.get("function").and_then(|f| f.as_str()) {
+                Callee::Symbol(Arc::from(symbol))
+            } else {
+                let addr = value
+                    .get("addr")
+                    .and_then(|addr| addr.as_str())
+                    .and_then(|addr| {
+                        let digits = addr.trim();
+                        let digits = digits.strip_prefix("0x").unwrap_or(digits);
+                        u64::from_str_radix(digits, 16).ok()
+                    })
+                    .ok_or_else(malformed)?;
+                Callee::Addr(Addr::from(addr).as_bits(bits))
+            };
+
+            let arg = match value.get("arg") {
+                Some(arg) => Some(arg.as_u64().ok_or_else(malformed)? as usize),
+                None => None,
+            };
+
+            let rule = match value.get("rule").and_then(|rule| rule.as_str()) {
+                Some("source") => TaintRule::Source { function, arg },
+                Some("sink") => TaintRule::Sink { function, arg: arg.ok_or_else(malformed)? },
+                Some("sanitizer") => TaintRule::Sanitizer { function, arg: arg.ok_or_else(malformed)? },
+                _ => return Err(malformed()),
+            };
+
+            spec.add(rule);
+        }
+
+        Ok(spec)
+    }
+}
+
+/// The origin of tainted data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TaintLabel {
+    /// The call to a source
+    Source(Id<Jmp>),
+    /// The value of an argument on entry to the Sub being summarised
+    Arg(usize),
+}
+
+type Labels = BTreeSet<TaintLabel>;
+
+/// How a Sub propagates taint from its arguments, and the taint it
+/// introduces itself, in terms of the labels of its arguments (i.e.,
+/// `TaintLabel::Arg`) and of the sources it (transitively) calls.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaintSummary {
+    returns: Labels,
+    sanitizes: BTreeSet<usize>,
+    sinks: BTreeMap<(Id<Jmp>, usize), Labels>,
+}
+
+impl TaintSummary {
+    /// The labels of the data returned.
+    pub fn returns(&self) -> &BTreeSet<TaintLabel> {
+        &self.returns
+    }
+
+    /// The arguments validated by the Sub (or its callees).
+    pub fn sanitizes(&self) -> &BTreeSet<usize> {
+        &self.sanitizes
+    }
+
+    /// The labels of the data reaching each argument of each call to a
+    /// sink made by the Sub (or its callees).
+    pub fn sinks(&self) -> impl Iterator<Item = (Id<Jmp>, usize, &BTreeSet<TaintLabel>)> {
+        self.sinks.iter().map(|((site, arg), labels)| (*site, *arg, labels))
+    }
+}
+
+/// Tainted data from the call to a source reaching an argument of a call
+/// to a sink.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaintFinding {
+    pub source: Id<Jmp>,
+    pub sink: Id<Jmp>,
+    pub arg: usize,
+}
+
+/// The labels of the variables and memory of a Sub at some point.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Taint {
+    // None if the point is not (yet) known to be reachable
+    vars: Option<BTreeMap<Var, Labels>>,
+    // labels of the values stored at (syntactic) addresses
+    memory: BTreeMap<Expr, Labels>,
+}
+
+impl Taint {
+    pub fn is_reachable(&self) -> bool {
+        self.vars.is_some()
+    }
+
+    pub fn labels(&self, var: &Var) -> Option<&BTreeSet<TaintLabel>> {
+        self.vars.as_ref().and_then(|vars| vars.get(var))
+    }
+
+    /// The labels of the data `expr` is derived from.
+    pub fn labels_of(&self, expr: &Expr) -> BTreeSet<TaintLabel> {
+        let mut labels = Labels::new();
+        self.collect(expr, &mut labels);
+        labels
+    }
+
+    fn collect(&self, expr: &Expr, labels: &mut Labels) {
+        match expr {
+            Expr::Var(var) => labels.extend(self.labels(var).into_iter().flatten().copied()),
+            Expr::Load(addr, _, _) => {
+                labels.extend(self.memory.get(addr).into_iter().flatten().copied());
+            }
+            _ => (),
+        }
+        for operand in expr.operands() {
+            self.collect(operand, labels);
+        }
+    }
+
+    fn assign(&mut self, var: &Var, labels: Labels) {
+        if let Some(ref mut vars) = self.vars {
+            if labels.is_empty() {
+                vars.remove(var);
+            } else {
+                vars.insert(var.clone(), labels);
+            }
+            // cells whose addresses are derived from var no longer refer to
+            // the same memory
+            self.memory.retain(|addr, _| !reads(addr, var));
+        }
+    }
+
+    fn store(&mut self, addr: &Expr, labels: Labels) {
+        if labels.is_empty() {
+            self.memory.remove(addr);
+        } else {
+            self.memory.insert(addr.clone(), labels);
+        }
+    }
+
+    fn sanitize(&mut self, labels: &Labels) {
+        if let Some(ref mut vars) = self.vars {
+            vars.retain(|_, vlabels| {
+                vlabels.retain(|label| !labels.contains(label));
+                !vlabels.is_empty()
+            });
+        }
+        self.memory.retain(|_, mlabels| {
+            mlabels.retain(|label| !labels.contains(label));
+            !mlabels.is_empty()
+        });
+    }
+}
+
+fn reads(expr: &Expr, var: &Var) -> bool {
+    match expr {
+        Expr::Var(evar) => evar == var,
+        _ => expr.operands().into_iter().any(|operand| reads(operand, var)),
+    }
+}
+
+impl Lattice for Taint {
+    fn bottom() -> Self {
+        Self { vars: None, memory: BTreeMap::new() }
+    }
+
+    fn join(&mut self, other: &Self) {
+        match (&mut self.vars, &other.vars) {
+            (_, None) => (),
+            (None, Some(_)) => {
+                *self = other.clone();
+            }
+            (Some(ref mut vars), Some(ref ovars)) => {
+                for (var, labels) in ovars.iter() {
+                    vars.entry(var.clone()).or_default().extend(labels.iter().copied());
+                }
+                for (addr, labels) in other.memory.iter() {
+                    self.memory.entry(addr.clone()).or_default().extend(labels.iter().copied());
+                }
+            }
+        }
+    }
+}
+
+/// A summary-based, whole-program taint analysis.
+///
+/// Each Sub is analysed once per change to the summaries of its callees
+/// (see `TaintSummary`), rather than once per call: within a Sub, labels
+/// flow through the variables it assigns and the memory it stores to (at
+/// syntactically equal addresses); at calls, the summaries of callees are
+/// applied to the labels of the arguments. Arguments and return values are
+/// passed in the variables given (or, for calls with explicit arguments,
+/// via those arguments).
+///
+/// A sanitizer is assumed to validate its argument on every path through
+/// the call to it: after the call, the labels of that argument are removed
+/// from all variables and memory, suppressing findings for data derived
+/// from validated inputs.
+#[derive(Debug, Clone)]
+pub struct TaintAnalysis {
+    spec: TaintSpec,
+    arguments: Vec<Var>,
+    return_value: Var,
+    max_rounds: usize,
+}
+
+// the summaries and rules that the calls of a Sub are analysed with
+struct InSub<'a> {
+    analysis: &'a TaintAnalysis,
+    callees: &'a BTreeMap<Addr, (Id<Sub>, Vec<&'a TaintRule>)>,
+    summaries: &'a BTreeMap<Id<Sub>, TaintSummary>,
+}
+
+impl TaintAnalysis {
+    pub fn new(spec: TaintSpec, arguments: Vec<Var>, return_value: Var) -> Self {
+        Self {
+            spec,
+            arguments,
+            return_value,
+            max_rounds: 64,
+        }
+    }
+
+    /// The maximum number of times each Sub is summarised.
+    pub fn max_rounds(&mut self, max: usize) -> &mut Self {
+        self.max_rounds = max;
+        self
+    }
+
+    pub fn spec(&self) -> &TaintSpec {
+        &self.spec
+    }
+
+    /// Summarises each of `subs` until their summaries reach a fixed point,
+    /// and reports the flows from sources to sinks.
+    pub fn solve<'a>(&self, subs: impl IntoIterator<Item = &'a Entity<Sub>>) -> TaintResult {
+        let subs = subs
+            .into_iter()
+            .map(|sub| (sub.id(), sub))
+            .collect::<BTreeMap<_, _>>();
+
+        let callees = subs
+            .values()
+            .filter_map(|sub| {
+                let rules = self.spec.rules.iter().filter(|rule| match rule {
+                    TaintRule::Source { function, .. }
+                    | TaintRule::Sink { function, .. }
+                    | TaintRule::Sanitizer { function, .. } => function.matches(sub),
+                });
+                Some((sub.addr()?.clone(), (sub.id(), rules.collect())))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        // rules naming addresses without Subs (e.g., imports)
+        let mut callees = callees;
+        for rule in self.spec.rules.iter() {
+            if let TaintRule::Source { function: Callee::Addr(addr), .. }
+            | TaintRule::Sink { function: Callee::Addr(addr), .. }
+            | TaintRule::Sanitizer { function: Callee::Addr(addr), .. } = rule
+            {
+                callees
+                    .entry(addr.clone())
+                    .or_insert_with(|| (Id::invalid("sub"), Vec::new()))
+                    .1
+                    .push(rule);
+            }
+        }
+        for (_, rules) in callees.values_mut() {
+            let mut seen = Vec::new();
+            rules.retain(|rule| {
+                let fresh = !seen.contains(rule);
+                seen.push(*rule);
+                fresh
+            });
+        }
+
+        let callers = subs
+            .values()
+            .flat_map(|sub| {
+                let callees = &callees;
+                sub.blks().iter().flat_map(|blk| blk.jmps()).filter_map(move |jmp| match **jmp {
+                    Jmp::Call(Loc::Fixed(ref addr), _) => Some((callees.get(addr)?.0, sub.id())),
+                    _ => None,
+                })
+            })
+            .fold(BTreeMap::<Id<Sub>, BTreeSet<Id<Sub>>>::new(), |mut callers, (callee, caller)| {
+                callers.entry(callee).or_default().insert(caller);
+                callers
+            });
+
+        let mut summaries = BTreeMap::<Id<Sub>, TaintSummary>::new();
+        let mut rounds = BTreeMap::<Id<Sub>, usize>::new();
+
+        let mut worklist = subs.keys().copied().collect::<VecDeque<_>>();
+        let mut queued = subs.keys().copied().collect::<BTreeSet<_>>();
+
+        while let Some(id) = worklist.pop_front() {
+            queued.remove(&id);
+
+            let round = rounds.entry(id).or_default();
+            if *round >= self.max_rounds {
+                continue
+            }
+            *round += 1;
+
+            let in_sub = InSub {
+                analysis: self,
+                callees: &callees,
+                summaries: &summaries,
+            };
+            let summary = in_sub.summarise(subs[&id]);
+
+            if summaries.get(&id) != Some(&summary) {
+                summaries.insert(id, summary);
+                for caller in callers.get(&id).into_iter().flatten() {
+                    if queued.insert(*caller) {
+                        worklist.push_back(*caller);
+                    }
+                }
+            }
+        }
+
+        let findings = summaries
+            .values()
+            .flat_map(|summary| summary.sinks())
+            .flat_map(|(sink, arg, labels)| {
+                labels.iter().filter_map(move |label| match label {
+                    TaintLabel::Source(source) => Some(TaintFinding { source: *source, sink, arg }),
+                    TaintLabel::Arg(_) => None,
+                })
+            })
+            .collect();
+
+        TaintResult { summaries, findings }
+    }
+}
+
+impl<'a> InSub<'a> {
+    fn summarise(&self, sub: &Entity<Sub>) -> TaintSummary {
+        let result = dataflow::solve(self, sub, &sub.cfg());
+        let mut summary = TaintSummary::default();
+
+        for blk in sub.blks() {
+            let mut taint = match result.entry(blk) {
+                Some(taint) if taint.is_reachable() => taint.clone(),
+                _ => continue,
+            };
+
+            for phi in blk.phis() {
+                self.transfer_phi(phi, &mut taint);
+            }
+            for def in blk.defs() {
+                self.transfer_def(def, &mut taint);
+            }
+
+            for jmp in blk.jmps() {
+                match **jmp {
+                    Jmp::Call(_, _) => self.summarise_call(jmp, &taint, &mut summary),
+                    Jmp::Return(_) => {
+                        summary.returns.extend(taint.labels(&self.analysis.return_value).into_iter().flatten().copied());
+                    }
+                    _ => (),
+                }
+                self.transfer_jmp(jmp, &mut taint);
+            }
+        }
+
+        summary
+    }
+
+    fn summarise_call(&self, call: &Entity<Jmp>, taint: &Taint, summary: &mut TaintSummary) {
+        let (callee, rules) = match self.callee(call) {
+            Some(callee) => callee,
+            None => return,
+        };
+
+        for rule in rules {
+            match rule {
+                TaintRule::Sink { arg, .. } => {
+                    let labels = self.argument(call, taint, *arg);
+                    if !labels.is_empty() {
+                        summary.sinks.entry((call.id(), *arg)).or_default().extend(labels);
+                    }
+                }
+                TaintRule::Sanitizer { arg, .. } => {
+                    summary.sanitizes.extend(self.argument(call, taint, *arg).into_iter().filter_map(|label| match label {
+                        TaintLabel::Arg(i) => Some(i),
+                        TaintLabel::Source(_) => None,
+                    }));
+                }
+                TaintRule::Source { .. } => (),
+            }
+        }
+
+        if let Some(csummary) = self.summaries.get(&callee) {
+            for ((site, arg), labels) in csummary.sinks.iter() {
+                let labels = self.instantiate(call, taint, labels);
+                if !labels.is_empty() {
+                    summary.sinks.entry((*site, *arg)).or_default().extend(labels);
+                }
+            }
+
+            for arg in csummary.sanitizes.iter() {
+                summary.sanitizes.extend(self.argument(call, taint, *arg).into_iter().filter_map(|label| match label {
+                    TaintLabel::Arg(i) => Some(i),
+                    TaintLabel::Source(_) => None,
+                }));
+            }
+        }
+    }
+
+    fn callee(&self, call: &Entity<Jmp>) -> Option<(Id<Sub>, &[&'a TaintRule])> {
+        match **call {
+            Jmp::Call(Loc::Fixed(ref addr), _) => {
+                let (id, rules) = self.callees.get(addr)?;
+                Some((*id, &rules[..]))
+            }
+            _ => None,
+        }
+    }
+
+    // the labels of the nth argument of call
+    fn argument(&self, call: &Entity<Jmp>, taint: &Taint, n: usize) -> Labels {
+        match **call {
+            Jmp::Call(_, ref args) if !args.is_empty() => {
+                args.get(n).map(|arg| taint.labels_of(arg)).unwrap_or_default()
+            }
+            _ => self.analysis.arguments
+                .get(n)
+                .and_then(|var| taint.labels(var))
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    // labels of a callee's summary in terms of the labels at call
+    fn instantiate(&self, call: &Entity<Jmp>, taint: &Taint, labels: &Labels) -> Labels {
+        labels
+            .iter()
+            .flat_map(|label| match label {
+                TaintLabel::Source(_) => Labels::from([*label]),
+                TaintLabel::Arg(n) => self.argument(call, taint, *n),
+            })
+            .collect()
+    }
+}
+
+impl<'a> Dataflow for InSub<'a> {
+    type Value = Taint;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn boundary(&self, sub: &Sub, blk: &Entity<Blk>) -> Taint {
+        if !sub.entry().map(|entry| entry.id() == blk.id()).unwrap_or(false) {
+            return Taint::bottom()
+        }
+
+        let vars = self.analysis.arguments
+            .iter()
+            .enumerate()
+            .map(|(i, var)| (var.clone(), Labels::from([TaintLabel::Arg(i)])))
+            .collect();
+
+        Taint { vars: Some(vars), memory: BTreeMap::new() }
+    }
+
+    fn transfer_phi(&self, phi: &Entity<Phi>, taint: &mut Taint) {
+        let labels = phi
+            .choices()
+            .iter()
+            .flat_map(|(_, expr)| taint.labels_of(expr))
+            .collect();
+        taint.assign(phi.var(), labels);
+    }
+
+    fn transfer_def(&self, def: &Entity<Def>, taint: &mut Taint) {
+        match **def {
+            Def::Assign(ref var, ref expr) => {
+                let labels = taint.labels_of(expr);
+                taint.assign(var, labels);
+            }
+            Def::Store(ref addr, ref value, _, _) => {
+                let labels = taint.labels_of(value);
+                taint.store(addr, labels);
+            }
+            Def::Assume(_) => (),
+        }
+    }
+
+    fn transfer_jmp(&self, jmp: &Entity<Jmp>, taint: &mut Taint) {
+        if !matches!(**jmp, Jmp::Call(_, _)) || !taint.is_reachable() {
+            return
+        }
+
+        let (callee, rules) = match self.callee(jmp) {
+            Some(callee) => callee,
+            None => return,
+        };
+
+        let mut returned = Labels::new();
+        let mut sanitized = Labels::new();
+        let mut tainted_args = Vec::new();
+
+        for rule in rules {
+            match rule {
+                TaintRule::Source { arg: None, .. } => {
+                    returned.insert(TaintLabel::Source(jmp.id()));
+                }
+                TaintRule::Source { arg: Some(arg), .. } => tainted_args.push(*arg),
+                TaintRule::Sanitizer { arg, .. } => sanitized.extend(self.argument(jmp, taint, *arg)),
+                TaintRule::Sink { .. } => (),
+            }
+        }
+
+        if let Some(summary) = self.summaries.get(&callee) {
+            returned.extend(self.instantiate(jmp, taint, &summary.returns));
+            for arg in summary.sanitizes.iter() {
+                sanitized.extend(self.argument(jmp, taint, *arg));
+            }
+        }
+
+        taint.sanitize(&sanitized);
+
+        for arg in tainted_args {
+            if let Some(var) = self.analysis.arguments.get(arg) {
+                let mut labels = taint.labels(var).cloned().unwrap_or_default();
+                labels.insert(TaintLabel::Source(jmp.id()));
+                taint.assign(var, labels);
+            }
+        }
+
+        // the return values of calls to unsummarised Subs are left as-is
+        if !returned.is_empty() || self.summaries.contains_key(&callee) {
+            taint.assign(&self.analysis.return_value, returned);
+        }
+    }
+}
+
+/// The summaries of the Subs analysed by `TaintAnalysis`, and the flows
+/// from sources to sinks found.
+#[derive(Debug, Clone, Default)]
+pub struct TaintResult {
+    summaries: BTreeMap<Id<Sub>, TaintSummary>,
+    findings: BTreeSet<TaintFinding>,
+}
+
+impl TaintResult {
+    pub fn summary(&self, sub: impl Identifiable<Sub>) -> Option<&TaintSummary> {
+        self.summaries.get(&sub.id())
+    }
+
+    pub fn findings(&self) -> &BTreeSet<TaintFinding> {
+        &self.findings
+    }
+
+    /// The flows reaching the sink called at `site`.
+    pub fn findings_at(&self, site: impl Identifiable<Jmp>) -> impl Iterator<Item = &TaintFinding> {
+        let site = site.id();
+        self.findings.iter().filter(move |finding| finding.sink == site)
+    }
+}
@@ -0,0 +1,571 @@
+use std::collections::BTreeMap;
+
+use crate::ir::{BinOp, BinRel, Cast, Def, Expr, Jmp, Loc, Sub, UnOp, Var};
+use crate::types::{Type, TypeSort, BOOL, F32, F64, F80, U8};
+use crate::types::bv::BitVecT;
+use crate::types::pointer::PointerT;
+use crate::prelude::{Id, Identifiable};
+
+/// A value whose type is inferred: a variable, or the memory at an address
+/// (identified syntactically, by the expression computing it).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TypeVar {
+    Var(Var),
+    Cell(Expr),
+}
+
+/// The facts about a value derived from its uses and definitions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeFacts {
+    bits: Option<u32>,
+    // the widest extract from the value, if it is only used via extracts
+    // from its low bits
+    used_bits: Option<u32>,
+    used_whole: bool,
+    pointer: bool,
+    float: bool,
+    boolean: bool,
+    signed_uses: usize,
+    unsigned_uses: usize,
+}
+
+impl TypeFacts {
+    fn with_bits(bits: u32) -> Self {
+        Self { bits: Some(bits), ..Default::default() }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.bits = self.bits.max(other.bits);
+        self.used_bits = self.used_bits.max(other.used_bits);
+        self.used_whole |= other.used_whole;
+        self.pointer |= other.pointer;
+        self.float |= other.float;
+        self.boolean |= other.boolean;
+        self.signed_uses += other.signed_uses;
+        self.unsigned_uses += other.unsigned_uses;
+    }
+
+    /// The width of the value; for values only used via extracts from
+    /// their low bits (e.g., a 64-bit register used as a 32-bit integer),
+    /// the width of the widest extract.
+    pub fn bits(&self) -> Option<u32> {
+        match self.used_bits {
+            Some(bits) if !self.used_whole => Some(bits),
+            _ => self.bits,
+        }
+    }
+
+    /// True if the value is used as an address.
+    pub fn is_pointer(&self) -> bool {
+        self.pointer
+    }
+
+    pub fn is_float(&self) -> bool {
+        self.float
+    }
+
+    pub fn is_bool(&self) -> bool {
+        self.boolean
+    }
+
+    /// True if the value is used by more signed operations (e.g., signed
+    /// comparisons, arithmetic shifts) than unsigned ones.
+    pub fn is_signed(&self) -> bool {
+        self.signed_uses > self.unsigned_uses
+    }
+}
+
+// what is known about an expression while generating constraints
+#[derive(Default)]
+struct Shape {
+    // the type variable of the expression, for variables and loads
+    tv: Option<usize>,
+    // the facts of the expression, otherwise
+    facts: TypeFacts,
+    // the type variable the expression is an offset from, e.g., x for
+    // x + 4, if the expression may be derived from a pointer
+    offset_of: Option<usize>,
+}
+
+impl Shape {
+    fn of(tv: usize) -> Self {
+        Self { tv: Some(tv), ..Default::default() }
+    }
+
+    fn facts(facts: TypeFacts) -> Self {
+        Self { facts, ..Default::default() }
+    }
+}
+
+/// Constraint-based recovery of the types of the variables and memory of
+/// a Sub, on a per-Sub basis.
+///
+/// Each use and definition of a value constrains its type: values used as
+/// addresses are pointers (to the memory at those addresses); values
+/// compared or divided with signed operations are signed; values only used
+/// via extracts from their low bits are as wide as the widest extract; and
+/// values copied between one another have equal types. Constraints are
+/// solved by unification, and each value is then assigned a type (see
+/// `InferredTypes`).
+///
+/// Memory is identified syntactically, and constraints are generated
+/// independently of the flow of the Sub, so the values loaded from, and
+/// stored to, the same address expression are unified, even if the
+/// variables it reads are reassigned between them.
+#[derive(Debug, Clone)]
+pub struct TypeInference {
+    max_depth: usize,
+}
+
+impl Default for TypeInference {
+    fn default() -> Self {
+        Self { max_depth: 4 }
+    }
+}
+
+impl TypeInference {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The maximum number of pointers followed to assign the type of a
+    /// pointer's pointee; beyond it (e.g., for recursive types), pointees
+    /// are assumed to be bytes.
+    pub fn max_depth(&mut self, depth: usize) -> &mut Self {
+        self.max_depth = depth;
+        self
+    }
+
+    pub fn infer(&self, sub: &Sub) -> InferredTypes {
+        let mut solver = Solver::default();
+
+        for blk in sub.blks() {
+            for phi in blk.phis() {
+                let var = solver.var(phi.var());
+                for (_, expr) in phi.choices().iter() {
+                    let shape = solver.visit(expr);
+                    solver.assign(var, shape);
+                }
+            }
+
+            for def in blk.defs() {
+                match **def {
+                    Def::Assign(ref var, ref expr) => {
+                        let var = solver.var(var);
+                        let shape = solver.visit(expr);
+                        solver.assign(var, shape);
+                    }
+                    Def::Store(ref addr, ref value, bits, _) => {
+                        let cell = solver.cell(addr, bits);
+                        let shape = solver.visit(addr);
+                        solver.pointer_to(&shape, cell);
+                        let shape = solver.visit(value);
+                        solver.assign(cell, shape);
+                    }
+                    Def::Assume(ref expr) => {
+                        solver.visit(expr);
+                    }
+                }
+            }
+
+            for jmp in blk.jmps() {
+                match **jmp {
+                    Jmp::Branch(ref loc) | Jmp::Return(ref loc) => solver.visit_loc(loc),
+                    Jmp::CBranch(ref loc, ref cond) => {
+                        solver.visit_loc(loc);
+                        let shape = solver.visit(cond);
+                        solver.apply(&shape, |facts| facts.boolean = true);
+                    }
+                    Jmp::Call(ref loc, ref args) => {
+                        solver.visit_loc(loc);
+                        for arg in args.iter() {
+                            solver.visit(arg);
+                        }
+                    }
+                    Jmp::Intrinsic(_, ref args) => {
+                        for arg in args.iter() {
+                            solver.visit(arg);
+                        }
+                    }
+                }
+            }
+        }
+
+        solver.propagate_offsets();
+        solver.resolve(self.max_depth)
+    }
+}
+
+#[derive(Default)]
+struct Solver {
+    tvs: Vec<TypeVar>,
+    index: BTreeMap<TypeVar, usize>,
+    parent: Vec<usize>,
+    facts: Vec<TypeFacts>,
+    pointee: Vec<Option<usize>>,
+    // (result, base) for results that are offsets from bases
+    offsets: Vec<(usize, usize)>,
+}
+
+impl Solver {
+    fn tv(&mut self, tv: TypeVar) -> usize {
+        if let Some(index) = self.index.get(&tv) {
+            return *index
+        }
+
+        let index = self.tvs.len();
+        self.tvs.push(tv.clone());
+        self.index.insert(tv, index);
+        self.parent.push(index);
+        self.facts.push(TypeFacts::default());
+        self.pointee.push(None);
+        index
+    }
+
+    fn var(&mut self, var: &Var) -> usize {
+        let tv = self.tv(TypeVar::Var(var.clone()));
+        if let Some(bits) = var.bits() {
+            let root = self.find(tv);
+            self.facts[root].merge(&TypeFacts::with_bits(bits));
+        }
+        tv
+    }
+
+    fn cell(&mut self, addr: &Expr, bits: u32) -> usize {
+        let tv = self.tv(TypeVar::Cell(addr.clone()));
+        let root = self.find(tv);
+        self.facts[root].merge(&TypeFacts::with_bits(bits));
+        tv
+    }
+
+    fn find(&mut self, tv: usize) -> usize {
+        let mut root = tv;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+
+        let mut tv = tv;
+        while self.parent[tv] != root {
+            let next = self.parent[tv];
+            self.parent[tv] = root;
+            tv = next;
+        }
+        root
+    }
+
+    fn unify(&mut self, a: usize, b: usize) {
+        let mut pending = vec![(a, b)];
+
+        while let Some((a, b)) = pending.pop() {
+            let (a, b) = (self.find(a), self.find(b));
+            if a == b {
+                continue
+            }
+
+            self.parent[b] = a;
+            let facts = self.facts[b].clone();
+            self.facts[a].merge(&facts);
+
+            match (self.pointee[a], self.pointee[b]) {
+                (Some(pa), Some(pb)) => pending.push((pa, pb)),
+                (None, Some(pb)) => self.pointee[a] = Some(pb),
+                _ => (),
+            }
+        }
+    }
+
+    fn apply(&mut self, shape: &Shape, f: impl FnOnce(&mut TypeFacts)) {
+        if let Some(tv) = shape.tv {
+            let root = self.find(tv);
+            f(&mut self.facts[root]);
+        }
+    }
+
+    fn assign(&mut self, tv: usize, shape: Shape) {
+        if let Some(other) = shape.tv {
+            self.unify(tv, other);
+        } else {
+            let root = self.find(tv);
+            self.facts[root].merge(&shape.facts);
+        }
+
+        if let Some(base) = shape.offset_of {
+            self.offsets.push((tv, base));
+        }
+    }
+
+    fn pointer_to(&mut self, shape: &Shape, cell: usize) {
+        if let Some(tv) = shape.tv {
+            let root = self.find(tv);
+            self.facts[root].pointer = true;
+            match self.pointee[root] {
+                Some(pointee) => self.unify(pointee, cell),
+                None => self.pointee[root] = Some(cell),
+            }
+        } else if let Some(base) = shape.offset_of {
+            // the base of an address computation is a pointer, but not to
+            // the memory at the address
+            let root = self.find(base);
+            self.facts[root].pointer = true;
+        }
+    }
+
+    fn visit_loc(&mut self, loc: &Loc) {
+        if let Loc::Computed(ref expr) = loc {
+            let shape = self.visit(expr);
+            self.apply(&shape, |facts| facts.pointer = true);
+        }
+    }
+
+    fn visit(&mut self, expr: &Expr) -> Shape {
+        match expr {
+            Expr::Var(var) => {
+                let tv = self.var(var);
+                let shape = Shape::of(tv);
+                self.apply(&shape, |facts| facts.used_whole = true);
+                shape
+            }
+            Expr::Val(bv) => Shape::facts(TypeFacts::with_bits(bv.bits() as u32)),
+            Expr::Load(addr, bits, _) => {
+                let cell = self.cell(addr, *bits);
+                let shape = self.visit(addr);
+                self.pointer_to(&shape, cell);
+                Shape::of(cell)
+            }
+            Expr::Extract(expr, lsb, msb) => {
+                let shape = match **expr {
+                    Expr::Var(ref var) if *lsb == 0 => Shape::of(self.var(var)),
+                    _ => self.visit(expr),
+                };
+                self.apply(&shape, |facts| {
+                    if *lsb == 0 {
+                        facts.used_bits = facts.used_bits.max(Some(*msb));
+                    } else {
+                        facts.used_whole = true;
+                    }
+                });
+                Shape::facts(TypeFacts::with_bits(msb.saturating_sub(*lsb)))
+            }
+            Expr::UnRel(_, expr) => {
+                let shape = self.visit(expr);
+                self.apply(&shape, |facts| facts.float = true);
+                Shape::facts(TypeFacts { boolean: true, ..TypeFacts::with_bits(BOOL.bits()) })
+            }
+            Expr::BinRel(op, lexpr, rexpr) => {
+                let shapes = [self.visit(lexpr), self.visit(rexpr)];
+                for shape in shapes.iter() {
+                    match op {
+                        BinRel::SLt | BinRel::SLe | BinRel::SBorrow | BinRel::SCarry => {
+                            self.apply(shape, |facts| facts.signed_uses += 1)
+                        }
+                        BinRel::Lt | BinRel::Le | BinRel::Carry => {
+                            self.apply(shape, |facts| facts.unsigned_uses += 1)
+                        }
+                        BinRel::Eq | BinRel::Neq => (),
+                    }
+                }
+                Shape::facts(TypeFacts { boolean: true, ..TypeFacts::with_bits(BOOL.bits()) })
+            }
+            Expr::UnOp(op, expr) => {
+                let shape = self.visit(expr);
+                let float = matches!(op, UnOp::Sqrt | UnOp::Ceiling | UnOp::Floor | UnOp::Round);
+                if float {
+                    self.apply(&shape, |facts| facts.float = true);
+                }
+                let bits = shape.tv.and_then(|tv| self.facts[self.find_ro(tv)].bits).or(shape.facts.bits);
+                Shape::facts(TypeFacts { float, bits, ..Default::default() })
+            }
+            Expr::BinOp(op, lexpr, rexpr) => {
+                let (lshape, rshape) = (self.visit(lexpr), self.visit(rexpr));
+                match op {
+                    BinOp::SDiv | BinOp::SRem => {
+                        self.apply(&lshape, |facts| facts.signed_uses += 1);
+                        self.apply(&rshape, |facts| facts.signed_uses += 1);
+                    }
+                    BinOp::Div | BinOp::Rem => {
+                        self.apply(&lshape, |facts| facts.unsigned_uses += 1);
+                        self.apply(&rshape, |facts| facts.unsigned_uses += 1);
+                    }
+                    BinOp::Sar => self.apply(&lshape, |facts| facts.signed_uses += 1),
+                    BinOp::Shr => self.apply(&lshape, |facts| facts.unsigned_uses += 1),
+                    _ => (),
+                }
+
+                let offset_of = match op {
+                    BinOp::Add | BinOp::Sub if rshape.tv.is_none() => lshape.tv.or(lshape.offset_of),
+                    BinOp::Add if lshape.tv.is_none() => rshape.tv.or(rshape.offset_of),
+                    _ => None,
+                };
+
+                let bits = lshape.tv
+                    .and_then(|tv| self.facts[self.find_ro(tv)].bits)
+                    .or(lshape.facts.bits);
+
+                Shape {
+                    tv: None,
+                    facts: TypeFacts { bits, ..Default::default() },
+                    offset_of,
+                }
+            }
+            Expr::Cast(expr, cast) => {
+                let shape = self.visit(expr);
+                let mut facts = TypeFacts::with_bits(cast.bits());
+                match cast {
+                    Cast::Signed(_) => {
+                        self.apply(&shape, |facts| facts.signed_uses += 1);
+                        facts.signed_uses += 1;
+                    }
+                    Cast::Unsigned(_) => {
+                        self.apply(&shape, |facts| facts.unsigned_uses += 1);
+                        facts.unsigned_uses += 1;
+                    }
+                    Cast::Float(_) => facts.float = true,
+                    Cast::Bool => facts.boolean = true,
+                    Cast::High(_) | Cast::Low(_) => (),
+                }
+                Shape::facts(facts)
+            }
+            Expr::Concat(lexpr, rexpr) => {
+                let (lshape, rshape) = (self.visit(lexpr), self.visit(rexpr));
+                let bits = |solver: &Self, shape: &Shape| {
+                    shape.tv.and_then(|tv| solver.facts[solver.find_ro(tv)].bits).or(shape.facts.bits)
+                };
+                let bits = bits(self, &lshape).zip(bits(self, &rshape)).map(|(l, r)| l + r);
+                Shape::facts(TypeFacts { bits, ..Default::default() })
+            }
+            Expr::IfElse(cond, texpr, fexpr) => {
+                let shape = self.visit(cond);
+                self.apply(&shape, |facts| facts.boolean = true);
+
+                let (tshape, fshape) = (self.visit(texpr), self.visit(fexpr));
+                match (tshape.tv, fshape.tv) {
+                    (Some(t), Some(f)) => {
+                        self.unify(t, f);
+                        tshape
+                    }
+                    (Some(_), None) => tshape,
+                    _ => fshape,
+                }
+            }
+            Expr::Intrinsic(_, args, bits) => {
+                for arg in args.iter() {
+                    self.visit(arg);
+                }
+                Shape::facts(TypeFacts::with_bits(*bits))
+            }
+        }
+    }
+
+    // find without path compression
+    fn find_ro(&self, tv: usize) -> usize {
+        let mut root = tv;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        root
+    }
+
+    // offsets from pointers are pointers
+    fn propagate_offsets(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (result, base) in self.offsets.clone() {
+                let (result, base) = (self.find(result), self.find(base));
+                if self.facts[base].pointer && !self.facts[result].pointer {
+                    self.facts[result].pointer = true;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    fn type_of(&self, root: usize, depth: usize, pointers: &mut BTreeMap<Id<Type>, PointerT>) -> Option<Id<Type>> {
+        let facts = &self.facts[root];
+        let bits = facts.bits()?;
+
+        if facts.pointer {
+            let pointee = self.pointee[root]
+                .filter(|_| depth > 0)
+                .and_then(|pointee| self.type_of(self.find_ro(pointee), depth - 1, pointers))
+                .unwrap_or_else(|| U8.id());
+            let pointer = PointerT::to(pointee, bits);
+            pointers.insert(pointer.id(), pointer);
+            return Some(pointer.id())
+        }
+
+        if facts.boolean && bits == BOOL.bits() {
+            return Some(BOOL.id())
+        }
+
+        if facts.float {
+            match bits {
+                32 => return Some(F32.id()),
+                64 => return Some(F64.id()),
+                80 => return Some(F80.id()),
+                _ => (),
+            }
+        }
+
+        Some(BitVecT::with_bits(bits, facts.is_signed()).id())
+    }
+
+    fn resolve(mut self, max_depth: usize) -> InferredTypes {
+        let mut pointers = BTreeMap::new();
+        let mut types = BTreeMap::new();
+        let mut facts = BTreeMap::new();
+
+        for tv in 0..self.tvs.len() {
+            let root = self.find(tv);
+            if let Some(typ) = self.type_of(root, max_depth, &mut pointers) {
+                types.insert(self.tvs[tv].clone(), typ);
+            }
+            facts.insert(self.tvs[tv].clone(), self.facts[root].clone());
+        }
+
+        InferredTypes { types, facts, pointers }
+    }
+}
+
+/// The types inferred for the variables and memory of a Sub by
+/// `TypeInference`.
+#[derive(Debug, Clone, Default)]
+pub struct InferredTypes {
+    types: BTreeMap<TypeVar, Id<Type>>,
+    facts: BTreeMap<TypeVar, TypeFacts>,
+    pointers: BTreeMap<Id<Type>, PointerT>,
+}
+
+impl InferredTypes {
+    /// The type of `var`, if its width is known.
+    pub fn var_type(&self, var: &Var) -> Option<Id<Type>> {
+        self.types.get(&TypeVar::Var(var.clone())).copied()
+    }
+
+    /// The type of the memory at `addr`, if it is loaded or stored to.
+    pub fn cell_type(&self, addr: &Expr) -> Option<Id<Type>> {
+        self.types.get(&TypeVar::Cell(addr.clone())).copied()
+    }
+
+    pub fn facts(&self, tv: &TypeVar) -> Option<&TypeFacts> {
+        self.facts.get(tv)
+    }
+
+    /// The pointer type with id `id`, if it was inferred; pointer types
+    /// derive their ids from their pointees (see `PointerT::to`).
+    pub fn pointer(&self, id: Id<Type>) -> Option<&PointerT> {
+        self.pointers.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&TypeVar, Id<Type>)> {
+        self.types.iter().map(|(tv, typ)| (tv, *typ))
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+}
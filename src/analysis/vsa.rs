@@ -0,0 +1,1010 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+use std::sync::Arc;
+
+use crate::analysis::dataflow::{self, Dataflow, DataflowResult, Direction, Lattice};
+use crate::ir::{Addr, BinOp, BinRel, Blk, Cast, Cfg, Def, Expr, Jmp, Loc, Phi, Sub, UnOp, Var};
+use crate::ir::memory::Mem;
+use crate::prelude::{Entity, Id, Identifiable};
+
+// the number of times the value of a variable may grow at a Blk before it
+// is widened
+const WIDEN_AFTER: u8 = 3;
+
+fn mask(bits: u32) -> u64 {
+    if bits >= 64 {
+        u64::MAX
+    } else {
+        (1u64 << bits) - 1
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+/// A strided interval: the unsigned values `lo`, `lo + stride`, ..., `hi`
+/// of `bits` (at most 64) bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StridedInterval {
+    bits: u32,
+    stride: u64,
+    lo: u64,
+    hi: u64,
+}
+
+impl StridedInterval {
+    pub fn new(bits: u32, stride: u64, lo: u64, hi: u64) -> Self {
+        let bits = bits.clamp(1, 64);
+        let lo = lo & mask(bits);
+        let hi = (hi & mask(bits)).max(lo);
+
+        if lo == hi {
+            return Self { bits, stride: 0, lo, hi }
+        }
+
+        let stride = stride.max(1);
+        let hi = lo + (hi - lo) / stride * stride;
+        Self { bits, stride: if lo == hi { 0 } else { stride }, lo, hi }
+    }
+
+    pub fn constant(value: u64, bits: u32) -> Self {
+        Self::new(bits, 0, value, value)
+    }
+
+    pub fn top(bits: u32) -> Self {
+        Self::new(bits, 1, 0, u64::MAX)
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn stride(&self) -> u64 {
+        self.stride
+    }
+
+    pub fn lo(&self) -> u64 {
+        self.lo
+    }
+
+    pub fn hi(&self) -> u64 {
+        self.hi
+    }
+
+    pub fn is_top(&self) -> bool {
+        self.lo == 0 && self.hi == mask(self.bits) && self.stride == 1
+    }
+
+    pub fn as_constant(&self) -> Option<u64> {
+        (self.lo == self.hi).then(|| self.lo)
+    }
+
+    /// The number of values of the interval.
+    pub fn count(&self) -> u128 {
+        if self.stride == 0 {
+            1
+        } else {
+            ((self.hi - self.lo) / self.stride) as u128 + 1
+        }
+    }
+
+    pub fn contains(&self, value: u64) -> bool {
+        value >= self.lo
+            && value <= self.hi
+            && (self.stride == 0 || (value - self.lo) % self.stride == 0)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = u64> + '_ {
+        let stride = self.stride.max(1);
+        (0..self.count()).map(move |i| self.lo + i as u64 * stride)
+    }
+
+    pub fn join(&self, other: &Self) -> Self {
+        if self.bits != other.bits {
+            return Self::top(self.bits.max(other.bits))
+        }
+
+        let stride = gcd(gcd(self.stride, other.stride), self.lo.abs_diff(other.lo));
+        Self::new(self.bits, stride, self.lo.min(other.lo), self.hi.max(other.hi))
+    }
+
+    /// Joins `other` into `self`, moving the bounds that grow to the
+    /// extremes of the values of the same stride.
+    pub fn widen(&self, other: &Self) -> Self {
+        let joined = self.join(other);
+        if joined.bits != self.bits || joined.stride == 0 {
+            return joined
+        }
+
+        let stride = joined.stride;
+        let lo = if joined.lo < self.lo { joined.lo % stride } else { joined.lo };
+        let hi = if joined.hi > self.hi {
+            mask(self.bits) - (mask(self.bits) - lo) % stride
+        } else {
+            joined.hi
+        };
+        Self::new(self.bits, stride, lo, hi)
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        if let (Some(a), Some(b)) = (self.as_constant(), other.as_constant()) {
+            return Self::constant(a.wrapping_add(b), self.bits)
+        }
+
+        match (self.lo.checked_add(other.lo), self.hi.checked_add(other.hi)) {
+            (Some(lo), Some(hi)) if hi <= mask(self.bits) => {
+                Self::new(self.bits, gcd(self.stride, other.stride), lo, hi)
+            }
+            _ => Self::top(self.bits),
+        }
+    }
+
+    fn neg(&self) -> Self {
+        if let Some(a) = self.as_constant() {
+            Self::constant(a.wrapping_neg(), self.bits)
+        } else if self.lo == 0 {
+            Self::top(self.bits)
+        } else {
+            let m = mask(self.bits);
+            Self::new(self.bits, self.stride, m - self.hi + 1, m - self.lo + 1)
+        }
+    }
+
+    fn not(&self) -> Self {
+        let m = mask(self.bits);
+        Self::new(self.bits, self.stride, m - self.hi, m - self.lo)
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        match (self.as_constant(), other.as_constant()) {
+            (Some(a), Some(b)) => Self::constant(a.wrapping_mul(b), self.bits),
+            (Some(c), None) => other.scale(c),
+            (None, Some(c)) => self.scale(c),
+            (None, None) => match (self.lo.checked_mul(other.lo), self.hi.checked_mul(other.hi)) {
+                (Some(lo), Some(hi)) if hi <= mask(self.bits) => Self::new(self.bits, 1, lo, hi),
+                _ => Self::top(self.bits),
+            },
+        }
+    }
+
+    fn scale(&self, c: u64) -> Self {
+        if c == 0 {
+            return Self::constant(0, self.bits)
+        }
+
+        match (self.lo.checked_mul(c), self.hi.checked_mul(c), self.stride.checked_mul(c)) {
+            (Some(lo), Some(hi), Some(stride)) if hi <= mask(self.bits) => Self::new(self.bits, stride, lo, hi),
+            _ => Self::top(self.bits),
+        }
+    }
+
+    fn and(&self, other: &Self) -> Self {
+        match (self.as_constant(), other.as_constant()) {
+            (Some(a), Some(b)) => Self::constant(a & b, self.bits),
+            (Some(m), None) | (None, Some(m)) => {
+                let value = if self.as_constant().is_some() { other } else { self };
+                if m == mask(self.bits) {
+                    return *value
+                }
+                // x & m is at most min(x, m), and a multiple of the lowest
+                // bit set in m
+                let align = if m == 0 { 0 } else { m.trailing_zeros() };
+                let hi = value.hi.min(m) >> align << align;
+                Self::new(self.bits, 1 << align, 0, hi)
+            }
+            (None, None) => Self::new(self.bits, 1, 0, self.hi.min(other.hi)),
+        }
+    }
+
+    fn shl(&self, amount: &Self) -> Self {
+        match amount.as_constant() {
+            Some(k) if k >= self.bits as u64 => Self::constant(0, self.bits),
+            Some(k) => self.mul(&Self::constant(1 << k, self.bits)),
+            None => Self::top(self.bits),
+        }
+    }
+
+    fn shr(&self, amount: &Self) -> Self {
+        match amount.as_constant() {
+            Some(k) if k >= self.bits as u64 => Self::constant(0, self.bits),
+            Some(k) => {
+                let stride = if self.stride % (1 << k) == 0 { self.stride >> k } else { 1 };
+                Self::new(self.bits, stride, self.lo >> k, self.hi >> k)
+            }
+            None => Self::new(self.bits, 1, 0, self.hi),
+        }
+    }
+
+    /// The values as `bits` bits, zero-extended or truncated.
+    fn resize(&self, bits: u32) -> Self {
+        if bits >= self.bits || self.hi <= mask(bits) {
+            Self::new(bits, self.stride, self.lo, self.hi)
+        } else if let Some(a) = self.as_constant() {
+            Self::constant(a, bits)
+        } else {
+            Self::top(bits)
+        }
+    }
+
+    /// The values as `bits` bits, sign-extended or truncated.
+    fn sign_extend(&self, bits: u32) -> Self {
+        if bits <= self.bits || self.hi <= mask(self.bits - 1) {
+            return self.resize(bits)
+        }
+
+        match self.as_constant() {
+            Some(a) => Self::constant(a | (mask(bits) & !mask(self.bits)), bits),
+            None => Self::top(bits),
+        }
+    }
+
+    pub(crate) fn signed(value: u64, bits: u32) -> i64 {
+        let shift = 64 - bits;
+        ((value << shift) as i64) >> shift
+    }
+}
+
+impl Display for StridedInterval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}[{:#x}, {:#x}]:{}", self.stride, self.lo, self.hi, self.bits)
+    }
+}
+
+/// The regions of memory to which the values of a ValueSet are relative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AbsRegion {
+    /// Absolute values, e.g., constants and global addresses
+    Global,
+    /// Offsets relative to the stack pointer on entry to the Sub
+    Stack,
+}
+
+/// The values of an expression: a strided interval for each region those
+/// values may be relative to, or any value of its bits (top).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueSet {
+    bits: u32,
+    // None if any value is possible
+    regions: Option<BTreeMap<AbsRegion, StridedInterval>>,
+}
+
+impl ValueSet {
+    pub fn top(bits: u32) -> Self {
+        Self { bits, regions: None }
+    }
+
+    pub fn global(si: StridedInterval) -> Self {
+        Self::with_region(AbsRegion::Global, si)
+    }
+
+    pub fn stack(si: StridedInterval) -> Self {
+        Self::with_region(AbsRegion::Stack, si)
+    }
+
+    fn with_region(region: AbsRegion, si: StridedInterval) -> Self {
+        if si.is_top() {
+            Self::top(si.bits())
+        } else {
+            Self { bits: si.bits(), regions: Some(BTreeMap::from([(region, si)])) }
+        }
+    }
+
+    pub fn constant(value: u64, bits: u32) -> Self {
+        if bits == 0 || bits > 64 {
+            Self::top(bits)
+        } else {
+            Self::global(StridedInterval::constant(value, bits))
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn is_top(&self) -> bool {
+        self.regions.is_none()
+    }
+
+    pub fn region(&self, region: AbsRegion) -> Option<&StridedInterval> {
+        self.regions.as_ref().and_then(|regions| regions.get(&region))
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item = (AbsRegion, &StridedInterval)> {
+        self.regions.iter().flat_map(|regions| regions.iter().map(|(r, si)| (*r, si)))
+    }
+
+    /// The values, if they are all relative to `region`.
+    pub fn only(&self, region: AbsRegion) -> Option<&StridedInterval> {
+        let regions = self.regions.as_ref()?;
+        if regions.len() == 1 {
+            regions.get(&region)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_constant(&self) -> Option<u64> {
+        self.only(AbsRegion::Global)?.as_constant()
+    }
+
+    /// The absolute values, if there are at most `max` of them, and they
+    /// are not relative to the stack.
+    pub fn targets(&self, max: usize) -> Option<Vec<Addr>> {
+        let si = self.only(AbsRegion::Global)?;
+        if si.count() > max as u128 {
+            return None
+        }
+        Some(si.values().map(|value| Addr::from(value).as_bits(self.bits)).collect())
+    }
+
+    pub fn join(&self, other: &Self) -> Self {
+        self.combine(other, StridedInterval::join)
+    }
+
+    pub fn widen(&self, other: &Self) -> Self {
+        self.combine(other, StridedInterval::widen)
+    }
+
+    fn combine(&self, other: &Self, f: impl Fn(&StridedInterval, &StridedInterval) -> StridedInterval) -> Self {
+        let (lhs, rhs) = match (&self.regions, &other.regions) {
+            (Some(lhs), Some(rhs)) if self.bits == other.bits => (lhs, rhs),
+            _ => return Self::top(self.bits.max(other.bits)),
+        };
+
+        let mut regions = lhs.clone();
+        for (region, si) in rhs.iter() {
+            let joined = match regions.get(region) {
+                Some(existing) => f(existing, si),
+                None => *si,
+            };
+            if joined.is_top() && *region == AbsRegion::Global {
+                return Self::top(self.bits)
+            }
+            regions.insert(*region, joined);
+        }
+
+        Self { bits: self.bits, regions: Some(regions) }
+    }
+
+    // applies f to the values of the same region; values relative to the
+    // stack can only be offset by (or compared with) absolute values
+    fn binop(&self, op: BinOp, other: &Self) -> Self {
+        let (lhs, rhs) = match (&self.regions, &other.regions) {
+            (Some(lhs), Some(rhs)) => (lhs, rhs),
+            _ => return Self::top(self.bits),
+        };
+
+        let mut result: Option<Self> = None;
+        for ((lregion, lsi), (rregion, rsi)) in lhs.iter().flat_map(|l| rhs.iter().map(move |r| (l, r))) {
+            let (region, si) = match (op, lregion, rregion) {
+                (BinOp::Add, region, AbsRegion::Global) | (BinOp::Add, AbsRegion::Global, region) => {
+                    (*region, lsi.add(rsi))
+                }
+                (BinOp::Sub, region, AbsRegion::Global) => (*region, lsi.add(&rsi.neg())),
+                (BinOp::Sub, AbsRegion::Stack, AbsRegion::Stack) => (AbsRegion::Global, lsi.add(&rsi.neg())),
+                (_, AbsRegion::Global, AbsRegion::Global) => {
+                    let si = match op {
+                        BinOp::Mul => lsi.mul(rsi),
+                        BinOp::And => lsi.and(rsi),
+                        BinOp::Shl => lsi.shl(rsi),
+                        BinOp::Shr => lsi.shr(rsi),
+                        _ => match (lsi.as_constant(), rsi.as_constant()) {
+                            (Some(a), Some(b)) => match op {
+                                BinOp::Or => StridedInterval::constant(a | b, self.bits),
+                                BinOp::Xor => StridedInterval::constant(a ^ b, self.bits),
+                                BinOp::Div if b != 0 => StridedInterval::constant(a / b, self.bits),
+                                BinOp::Rem if b != 0 => StridedInterval::constant(a % b, self.bits),
+                                _ => return Self::top(self.bits),
+                            },
+                            (_, Some(b)) if op == BinOp::Rem && b != 0 => {
+                                StridedInterval::new(self.bits, 1, 0, b - 1)
+                            }
+                            _ => return Self::top(self.bits),
+                        },
+                    };
+                    (AbsRegion::Global, si)
+                }
+                _ => return Self::top(self.bits),
+            };
+
+            let value = Self::with_region(region, si);
+            result = Some(match result {
+                Some(result) => result.join(&value),
+                None => value,
+            });
+        }
+
+        result.unwrap_or_else(|| Self::top(self.bits))
+    }
+
+    fn relation(&self, rel: BinRel, other: &Self) -> Self {
+        let unknown = Self::global(StridedInterval::new(8, 1, 0, 1));
+        let (a, b) = match (self.as_constant(), other.as_constant()) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return unknown,
+        };
+
+        let bits = self.bits.clamp(1, 64);
+        let holds = match rel {
+            BinRel::Eq => a == b,
+            BinRel::Neq => a != b,
+            BinRel::Lt => a < b,
+            BinRel::Le => a <= b,
+            BinRel::SLt => StridedInterval::signed(a, bits) < StridedInterval::signed(b, bits),
+            BinRel::SLe => StridedInterval::signed(a, bits) <= StridedInterval::signed(b, bits),
+            _ => return unknown,
+        };
+        Self::constant(holds as u64, 8)
+    }
+
+    fn map_global(&self, bits: u32, f: impl Fn(&StridedInterval) -> StridedInterval) -> Self {
+        match self.only(AbsRegion::Global) {
+            Some(si) if bits <= 64 => Self::global(f(si)),
+            _ => Self::top(bits),
+        }
+    }
+}
+
+impl Display for ValueSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.regions {
+            None => write!(f, "T:{}", self.bits),
+            Some(ref regions) => {
+                write!(f, "{{")?;
+                for (i, (region, si)) in regions.iter().enumerate() {
+                    let region = match region {
+                        AbsRegion::Global => "global",
+                        AbsRegion::Stack => "stack",
+                    };
+                    write!(f, "{}{}: {}", if i == 0 { "" } else { ", " }, region, si)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// The values of the variables and memory of a Sub at some point, as
+/// computed by `ValueSetAnalysis`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueSets {
+    // None if the point is not (yet) known to be reachable; variables
+    // absent may hold any value
+    vars: Option<BTreeMap<Var, ValueSet>>,
+    // the values stored at fixed offsets of each region, and their sizes
+    memory: BTreeMap<(AbsRegion, u64), (u32, ValueSet)>,
+    // the number of times each variable's value has grown, for widening
+    growth: BTreeMap<Var, u8>,
+}
+
+impl ValueSets {
+    pub fn is_reachable(&self) -> bool {
+        self.vars.is_some()
+    }
+
+    pub fn get(&self, var: &Var) -> ValueSet {
+        self.vars
+            .as_ref()
+            .and_then(|vars| vars.get(var))
+            .cloned()
+            .unwrap_or_else(|| ValueSet::top(var.bits().unwrap_or(0)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Var, &ValueSet)> {
+        self.vars.iter().flat_map(|vars| vars.iter())
+    }
+
+    fn assign(&mut self, var: &Var, value: ValueSet) {
+        if let Some(ref mut vars) = self.vars {
+            if value.is_top() {
+                vars.remove(var);
+            } else {
+                vars.insert(var.clone(), value);
+            }
+        }
+    }
+
+    fn store(&mut self, addr: &ValueSet, bits: u32, value: ValueSet) {
+        let size = (bits as u64 + 7) / 8;
+        let regions = match addr.regions {
+            Some(ref regions) => regions,
+            None => {
+                self.memory.clear();
+                return
+            }
+        };
+
+        for (region, si) in regions.iter() {
+            let end = si.hi().saturating_add(size);
+            self.memory.retain(|(mregion, offset), (mbits, _)| {
+                let mend = offset.saturating_add((*mbits as u64 + 7) / 8);
+                *mregion != *region || mend <= si.lo() || *offset >= end
+            });
+        }
+
+        if let Some(offset) = regions.iter().next().and_then(|(_, si)| si.as_constant()) {
+            if regions.len() == 1 && !value.is_top() {
+                // unwrap is safe here: we know that there is a region
+                let region = *regions.keys().next().unwrap();
+                self.memory.insert((region, offset), (bits, value));
+            }
+        }
+    }
+
+    fn load(&self, addr: &ValueSet, bits: u32) -> Option<ValueSet> {
+        let regions = addr.regions.as_ref()?;
+        let (region, si) = regions.iter().next().filter(|_| regions.len() == 1)?;
+        let offset = si.as_constant()?;
+        match self.memory.get(&(*region, offset)) {
+            Some((mbits, value)) if *mbits == bits => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    // the physical variables named by clobbered may hold any value
+    fn clobber(&mut self, clobbered: &BTreeSet<Arc<str>>) {
+        if let Some(ref mut vars) = self.vars {
+            vars.retain(|var, _| !var.is_physical() || !clobbered.contains(var.name()));
+        }
+    }
+
+    // whether the bytes at addr may have been written within the Sub
+    fn may_be_stored(&self, region: AbsRegion) -> bool {
+        self.memory.keys().any(|(mregion, _)| *mregion == region)
+    }
+}
+
+impl Lattice for ValueSets {
+    fn bottom() -> Self {
+        Self {
+            vars: None,
+            memory: BTreeMap::new(),
+            growth: BTreeMap::new(),
+        }
+    }
+
+    fn join(&mut self, other: &Self) {
+        let (vars, ovars) = match (&mut self.vars, &other.vars) {
+            (_, None) => return,
+            (None, Some(_)) => {
+                *self = other.clone();
+                return
+            }
+            (Some(vars), Some(ovars)) => (vars, ovars),
+        };
+
+        let growth = &mut self.growth;
+        vars.retain(|var, value| {
+            let ovalue = if let Some(ovalue) = ovars.get(var) {
+                ovalue
+            } else {
+                return false
+            };
+
+            let joined = value.join(ovalue);
+            if joined == *value {
+                return true
+            }
+
+            let count = growth.entry(var.clone()).or_default();
+            *count = count.saturating_add(1);
+            *value = if *count > WIDEN_AFTER {
+                value.widen(ovalue)
+            } else {
+                joined
+            };
+            !value.is_top()
+        });
+
+        let omemory = &other.memory;
+        self.memory.retain(|key, (bits, value)| match omemory.get(key) {
+            Some((obits, ovalue)) if obits == bits => {
+                *value = value.join(ovalue);
+                !value.is_top()
+            }
+            _ => false,
+        });
+    }
+}
+
+/// A forward value-set analysis over the variables and memory of a Sub.
+///
+/// The value of each variable is abstracted by a strided interval for each
+/// region of memory (see `AbsRegion`) it may point into: the stack pointer
+/// holds offset zero of the stack on entry to the Sub, and other variables
+/// hold unknown values. Stores to fixed offsets are tracked, and loads from
+/// global addresses not stored to by the Sub read from the analysis'
+/// memory (if any). Values that keep growing around loops are widened.
+///
+/// The values of the targets of computed jumps (see `computed_targets`)
+/// bound the targets of indirect jumps and calls, and the offsets of the
+/// stack accessed (see `stack_accesses`) bound the Sub's frame.
+///
+/// Calls may write to any global memory; calls and intrinsic jmps may
+/// write to the registers given by `caller_saved`.
+#[derive(Clone, Default)]
+pub struct ValueSetAnalysis<'a> {
+    stack_pointer: Option<Var>,
+    caller_saved: BTreeSet<Arc<str>>,
+    memory: Option<&'a Mem<'a>>,
+    max_loads: usize,
+}
+
+impl<'a> ValueSetAnalysis<'a> {
+    pub fn new(stack_pointer: Option<Var>) -> Self {
+        Self {
+            stack_pointer,
+            caller_saved: BTreeSet::new(),
+            memory: None,
+            max_loads: 256,
+        }
+    }
+
+    pub fn stack_pointer(&self) -> Option<&Var> {
+        self.stack_pointer.as_ref()
+    }
+
+    /// Calls (and intrinsic jmps) may modify the registers `clobbered`
+    /// (e.g., those given by `Lifter::caller_saved`), which hold unknown
+    /// values after them.
+    pub fn caller_saved(&mut self, clobbered: impl IntoIterator<Item = Var>) -> &mut Self {
+        self.caller_saved = clobbered
+            .into_iter()
+            .filter(|var| var.is_physical())
+            .map(|var| var.name().clone())
+            .collect();
+        self
+    }
+
+    /// Loads from global addresses not stored to by the Sub read from
+    /// `memory`.
+    pub fn memory(&mut self, memory: &'a Mem<'a>) -> &mut Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// The maximum number of addresses read from the analysis' memory by a
+    /// single load.
+    pub fn max_loads(&mut self, max: usize) -> &mut Self {
+        self.max_loads = max;
+        self
+    }
+
+    pub fn solve(&self, sub: &Sub) -> DataflowResult<ValueSets> {
+        self.solve_with(sub, &sub.cfg())
+    }
+
+    pub fn solve_with(&self, sub: &Sub, cfg: &Cfg) -> DataflowResult<ValueSets> {
+        dataflow::solve(self, sub, cfg)
+    }
+
+    /// The values of `expr` given the values `sets`.
+    pub fn evaluate(&self, sets: &ValueSets, expr: &Expr) -> ValueSet {
+        let bits = expr.bits();
+        match expr {
+            Expr::Val(bv) => bv
+                .to_u64()
+                .filter(|_| bv.bits() <= 64)
+                .map(|value| ValueSet::constant(value, bits))
+                .unwrap_or_else(|| ValueSet::top(bits)),
+            Expr::Var(var) => sets.get(var),
+            Expr::Load(addr, lbits, _) => {
+                let addr = self.evaluate(sets, addr);
+                self.load(sets, &addr, *lbits)
+            }
+            Expr::BinOp(op, lexpr, rexpr) => {
+                let lhs = self.evaluate(sets, lexpr);
+                let rhs = self.evaluate(sets, rexpr);
+                lhs.binop(*op, &rhs)
+            }
+            Expr::BinRel(rel, lexpr, rexpr) => {
+                let lhs = self.evaluate(sets, lexpr);
+                let rhs = self.evaluate(sets, rexpr);
+                lhs.relation(*rel, &rhs)
+            }
+            Expr::UnOp(UnOp::Neg, expr) => {
+                let value = self.evaluate(sets, expr);
+                value.map_global(bits, |si| si.neg())
+            }
+            Expr::UnOp(UnOp::Not, expr) => {
+                let value = self.evaluate(sets, expr);
+                value.map_global(bits, |si| si.not())
+            }
+            Expr::Cast(expr, cast) => {
+                let value = self.evaluate(sets, expr);
+                match cast {
+                    Cast::Unsigned(_) | Cast::Low(_) => value.map_global(bits, |si| si.resize(bits)),
+                    Cast::Signed(_) => value.map_global(bits, |si| si.sign_extend(bits)),
+                    Cast::High(_) => {
+                        let shift = StridedInterval::constant(value.bits().saturating_sub(bits) as u64, value.bits().clamp(1, 64));
+                        value.map_global(bits, |si| si.shr(&shift).resize(bits))
+                    }
+                    Cast::Bool => ValueSet::global(StridedInterval::new(8, 1, 0, 1)),
+                    Cast::Float(_) => ValueSet::top(bits),
+                }
+            }
+            Expr::Extract(expr, lsb, _) => {
+                let value = self.evaluate(sets, expr);
+                let shift = StridedInterval::constant(*lsb as u64, value.bits().clamp(1, 64));
+                value.map_global(bits, |si| si.shr(&shift).resize(bits))
+            }
+            Expr::IfElse(cond, texpr, fexpr) => match self.evaluate(sets, cond).as_constant() {
+                Some(0) => self.evaluate(sets, fexpr),
+                Some(_) => self.evaluate(sets, texpr),
+                None => self.evaluate(sets, texpr).join(&self.evaluate(sets, fexpr)),
+            },
+            _ => ValueSet::top(bits),
+        }
+    }
+
+    fn load(&self, sets: &ValueSets, addr: &ValueSet, bits: u32) -> ValueSet {
+        if let Some(value) = sets.load(addr, bits) {
+            return value
+        }
+
+        let (memory, si) = match (self.memory, addr.only(AbsRegion::Global)) {
+            (Some(memory), Some(si)) if !sets.may_be_stored(AbsRegion::Global) => (memory, si),
+            _ => return ValueSet::top(bits),
+        };
+
+        if si.count() > self.max_loads as u128 || bits > 64 {
+            return ValueSet::top(bits)
+        }
+
+        let mut result: Option<ValueSet> = None;
+        for offset in si.values() {
+            let address = Addr::from(offset).as_bits(addr.bits());
+            let value = memory
+                .read_bits(&address, bits)
+                .ok()
+                .flatten()
+                .and_then(|value| value.to_u64())
+                .map(|value| ValueSet::constant(value, bits));
+
+            let value = if let Some(value) = value {
+                value
+            } else {
+                return ValueSet::top(bits)
+            };
+
+            result = Some(match result {
+                Some(result) => result.join(&value),
+                None => value,
+            });
+        }
+
+        result.unwrap_or_else(|| ValueSet::top(bits))
+    }
+
+    // the values before each jmp of blk, given those on entry to blk
+    fn before_jmps(&self, blk: &Blk, sets: &ValueSets) -> ValueSets {
+        let mut sets = sets.clone();
+        self.transfer_start(blk, &mut sets);
+        for phi in blk.phis() {
+            self.transfer_phi(phi, &mut sets);
+        }
+        for def in blk.defs() {
+            self.transfer_def(def, &mut sets);
+        }
+        sets
+    }
+
+    /// The values of the targets of the computed jumps of `sub` (i.e.,
+    /// those whose targets are `Loc::Computed`), given the values computed
+    /// by `solve`.
+    pub fn computed_targets(&self, sub: &Sub, result: &DataflowResult<ValueSets>) -> BTreeMap<Id<Jmp>, ValueSet> {
+        let mut targets = BTreeMap::new();
+
+        for blk in sub.blks() {
+            let sets = match result.entry(blk) {
+                Some(sets) if sets.is_reachable() => self.before_jmps(blk, sets),
+                _ => continue,
+            };
+
+            for jmp in blk.jmps() {
+                match **jmp {
+                    Jmp::Branch(Loc::Computed(ref expr))
+                    | Jmp::CBranch(Loc::Computed(ref expr), _)
+                    | Jmp::Call(Loc::Computed(ref expr), _)
+                    | Jmp::Return(Loc::Computed(ref expr)) => {
+                        targets.insert(jmp.id(), self.evaluate(&sets, expr));
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        targets
+    }
+
+    /// The offsets of the stack (relative to the stack pointer on entry)
+    /// accessed by the loads and stores of each Def of `sub`, given the
+    /// values computed by `solve`.
+    pub fn stack_accesses(&self, sub: &Sub, result: &DataflowResult<ValueSets>) -> BTreeMap<Id<Def>, Vec<StridedInterval>> {
+        let mut accesses = BTreeMap::new();
+
+        for blk in sub.blks() {
+            let mut sets = match result.entry(blk) {
+                Some(sets) if sets.is_reachable() => sets.clone(),
+                _ => continue,
+            };
+
+            self.transfer_start(blk, &mut sets);
+            for phi in blk.phis() {
+                self.transfer_phi(phi, &mut sets);
+            }
+
+            for def in blk.defs() {
+                let mut addrs = Vec::new();
+                let mut collect = |expr: &Expr| self.stack_loads(&sets, expr, &mut addrs);
+                match **def {
+                    Def::Assign(_, ref expr) | Def::Assume(ref expr) => collect(expr),
+                    Def::Store(ref addr, ref value, _, _) => {
+                        collect(addr);
+                        collect(value);
+                        if let Some(si) = self.evaluate(&sets, addr).only(AbsRegion::Stack) {
+                            addrs.push(*si);
+                        }
+                    }
+                }
+
+                if !addrs.is_empty() {
+                    accesses.insert(def.id(), addrs);
+                }
+                self.transfer_def(def, &mut sets);
+            }
+        }
+
+        accesses
+    }
+
+    fn stack_loads(&self, sets: &ValueSets, expr: &Expr, addrs: &mut Vec<StridedInterval>) {
+        if let Expr::Load(ref addr, _, _) = expr {
+            if let Some(si) = self.evaluate(sets, addr).only(AbsRegion::Stack) {
+                addrs.push(*si);
+            }
+        }
+        for operand in expr.operands() {
+            self.stack_loads(sets, operand, addrs);
+        }
+    }
+}
+
+impl<'a> Dataflow for ValueSetAnalysis<'a> {
+    type Value = ValueSets;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn boundary(&self, sub: &Sub, blk: &Entity<Blk>) -> ValueSets {
+        if !sub.entry().map(|entry| entry.id() == blk.id()).unwrap_or(false) {
+            return ValueSets::bottom()
+        }
+
+        let mut sets = ValueSets {
+            vars: Some(BTreeMap::new()),
+            ..ValueSets::bottom()
+        };
+
+        if let Some(ref sp) = self.stack_pointer {
+            sets.assign(sp, ValueSet::stack(StridedInterval::constant(0, sp.bits().unwrap_or(64))));
+        }
+        sets
+    }
+
+    fn transfer_phi(&self, phi: &Entity<Phi>, sets: &mut ValueSets) {
+        let mut values = phi
+            .choices()
+            .iter()
+            .map(|(_, expr)| self.evaluate(sets, expr));
+
+        let value = values.next().map(|first| values.fold(first, |value, other| value.join(&other)));
+        sets.assign(phi.var(), value.unwrap_or_else(|| ValueSet::top(phi.var().bits().unwrap_or(0))));
+    }
+
+    fn transfer_def(&self, def: &Entity<Def>, sets: &mut ValueSets) {
+        match **def {
+            Def::Assign(ref var, ref expr) => {
+                let value = self.evaluate(sets, expr);
+                sets.assign(var, value);
+            }
+            Def::Store(ref addr, ref value, bits, _) => {
+                let addr = self.evaluate(sets, addr);
+                let value = self.evaluate(sets, value);
+                sets.store(&addr, bits, value);
+            }
+            Def::Assume(_) => (),
+        }
+    }
+
+    fn transfer_jmp(&self, jmp: &Entity<Jmp>, sets: &mut ValueSets) {
+        match **jmp {
+            // callees may write to any global memory, and to the
+            // caller-saved registers
+            Jmp::Call(_, _) => {
+                sets.memory.retain(|(region, _), _| *region == AbsRegion::Stack);
+                sets.clobber(&self.caller_saved);
+            }
+            Jmp::Intrinsic(_, _) => sets.clobber(&self.caller_saved),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_strided_intervals() {
+        let a = StridedInterval::constant(0x1000, 32);
+        let b = StridedInterval::constant(0x1010, 32);
+
+        // a table of four 8-byte entries
+        let joined = a.join(&b).join(&StridedInterval::constant(0x1008, 32)).join(&StridedInterval::constant(0x1018, 32));
+        assert_eq!((joined.stride(), joined.lo(), joined.hi()), (8, 0x1000, 0x1018));
+        assert_eq!(joined.values().collect::<Vec<_>>(), vec![0x1000, 0x1008, 0x1010, 0x1018]);
+
+        // index * 4 + base
+        let index = StridedInterval::new(32, 1, 0, 3);
+        let addrs = index.scale(4).add(&a);
+        assert_eq!((addrs.stride(), addrs.lo(), addrs.hi()), (4, 0x1000, 0x100c));
+
+        // masking bounds and aligns
+        let masked = StridedInterval::top(32).and(&StridedInterval::constant(0xf8, 32));
+        assert_eq!((masked.stride(), masked.lo(), masked.hi()), (8, 0, 0xf8));
+
+        // stack offsets wrap
+        let frame = StridedInterval::constant(0, 32).add(&StridedInterval::constant(8, 32).neg());
+        assert_eq!(frame.as_constant(), Some(0xffff_fff8));
+
+        // growing bounds are widened to the extremes of the stride
+        let widened = index.widen(&StridedInterval::new(32, 1, 0, 4));
+        assert!(widened.is_top());
+    }
+
+    #[test]
+    fn test_caller_saved_clobbered() {
+        use crate::ir::BitVec;
+        use crate::types::bv::BitVecT;
+
+        let reg = |name: &str| -> Var { Var::physical(name, BitVecT::with_bits(32, false)).into() };
+
+        // EAX := 1; EBX := 2; call 0x3000; syscall(); return
+        let mut call = Blk::new(None);
+        let mut intrinsic = Blk::new(None);
+        let mut next = Blk::new(None);
+
+        call.add_def(Def::assign(reg("EAX"), BitVec::from_u64(1, 32)));
+        call.add_def(Def::assign(reg("EBX"), BitVec::from_u64(2, 32)));
+        call.add_jmp(Jmp::call(Loc::Fixed(Addr::from(0x3000u32)), []));
+        call.add_jmp(Jmp::branch(intrinsic.id()));
+
+        intrinsic.add_def(Def::assign(reg("EAX"), BitVec::from_u64(3, 32)));
+        intrinsic.add_jmp(Jmp::intrinsic("syscall", []));
+        intrinsic.add_jmp(Jmp::branch(next.id()));
+
+        next.add_jmp(Jmp::return_(Expr::from(reg("EAX"))));
+
+        let (intrinsic_id, next_id) = (intrinsic.id(), next.id());
+        let sub = Sub::new(None, Addr::from(0x2000u32), vec![call, intrinsic, next]);
+
+        let mut analysis = ValueSetAnalysis::new(None);
+        analysis.caller_saved([reg("EAX")]);
+
+        let result = analysis.solve(&sub);
+
+        let after_call = result.entry(intrinsic_id).unwrap();
+        assert!(after_call.get(&reg("EAX")).is_top());
+        assert_eq!(after_call.get(&reg("EBX")).as_constant(), Some(2));
+
+        let after_intrinsic = result.entry(next_id).unwrap();
+        assert!(after_intrinsic.get(&reg("EAX")).is_top());
+        assert_eq!(after_intrinsic.get(&reg("EBX")).as_constant(), Some(2));
+    }
+}
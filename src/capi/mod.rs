@@ -0,0 +1,387 @@
+/// C bindings (with the `capi` feature), for embedding in C and C++
+/// tools; built as a library with the `cdylib` or `staticlib` crate type
+/// (e.g., `cargo rustc --features capi --crate-type cdylib`).
+///
+/// - `delirium_builder_new` and `delirium_project_new` create projects
+///   for a language, given the path to the processor specifications.
+///
+/// - `delirium_project_map` maps regions of bytes into a project.
+///
+/// - `delirium_project_lift_blk` and `delirium_project_add_sub` lift the
+///   IR at an address, returning it as a flat array of statements (see
+///   `DeliriumIr`), to be released with `delirium_ir_free`.
+///
+/// Objects returned are owned by the caller, and are released with the
+/// corresponding `_free` function; functions returning pointers return
+/// null on failure, and functions returning ints return zero on success,
+/// and `DELIRIUM_ERROR` or `DELIRIUM_PANIC` otherwise. Panics do not
+/// unwind into the caller. The declarations are given for C in
+/// `include/delirium.h`.
+
+use std::borrow::Cow;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::ir::{Blk, Jmp, Loc, Printer, Project, ProjectBuilder};
+use crate::prelude::{Endian, Entity};
+
+/// Returned on failure (e.g., an invalid argument).
+pub const DELIRIUM_ERROR: c_int = -1;
+/// Returned if the function panicked.
+pub const DELIRIUM_PANIC: c_int = -2;
+
+/// A project, as created by `delirium_project_new`.
+pub type DeliriumProject = Entity<Project<'static>>;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DeliriumStmtKind {
+    Phi = 0,
+    Def = 1,
+    Jmp = 2,
+}
+
+/// A statement of the IR.
+#[repr(C)]
+#[derive(Debug)]
+pub struct DeliriumStmt {
+    pub kind: DeliriumStmtKind,
+    /// The index of the statement's Blk within the IR returned
+    pub blk: usize,
+    /// The address of the statement's Blk, or zero
+    pub addr: u64,
+    /// The target of a Jmp to a fixed address, or zero
+    pub target: u64,
+    /// The statement, as rendered by the default Printer
+    pub text: *mut c_char,
+}
+
+/// The statements of one or more Blks, in order.
+#[repr(C)]
+#[derive(Debug)]
+pub struct DeliriumIr {
+    pub stmts: *mut DeliriumStmt,
+    pub len: usize,
+    /// The number of Blks the statements belong to
+    pub blks: usize,
+}
+
+unsafe fn to_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        None
+    } else {
+        CStr::from_ptr(s).to_str().ok()
+    }
+}
+
+fn to_c_string(s: String) -> *mut c_char {
+    // statements cannot contain NULs, hence this never fails in practice
+    CString::new(s)
+        .map(CString::into_raw)
+        .unwrap_or(ptr::null_mut())
+}
+
+// runs f, returning on_panic if it panics, rather than unwinding into C
+fn guard<T>(name: &str, on_panic: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|_| {
+        log::error!("{} panicked", name);
+        on_panic
+    })
+}
+
+fn to_ir<'b>(blks: impl IntoIterator<Item = &'b Entity<Blk>>) -> *mut DeliriumIr {
+    let printer = Printer::new();
+    let mut stmts = Vec::new();
+    let mut count = 0;
+
+    for (index, blk) in blks.into_iter().enumerate() {
+        let addr = blk.addr().and_then(|addr| u64::try_from(addr).ok()).unwrap_or(0);
+        let stmt = |kind, target, text| DeliriumStmt {
+            kind,
+            blk: index,
+            addr,
+            target,
+            text: to_c_string(text),
+        };
+
+        for phi in blk.phis() {
+            stmts.push(stmt(DeliriumStmtKind::Phi, 0, printer.phi(phi)));
+        }
+        for def in blk.defs() {
+            stmts.push(stmt(DeliriumStmtKind::Def, 0, printer.def(def)));
+        }
+        for jmp in blk.jmps() {
+            let target = match **jmp {
+                Jmp::Branch(Loc::Fixed(ref target))
+                | Jmp::CBranch(Loc::Fixed(ref target), _)
+                | Jmp::Call(Loc::Fixed(ref target), _) => u64::try_from(target).unwrap_or(0),
+                _ => 0,
+            };
+            stmts.push(stmt(DeliriumStmtKind::Jmp, target, printer.jmp(jmp)));
+        }
+        count += 1;
+    }
+
+    let stmts = stmts.into_boxed_slice();
+    let len = stmts.len();
+    Box::into_raw(Box::new(DeliriumIr {
+        stmts: Box::into_raw(stmts) as *mut DeliriumStmt,
+        len,
+        blks: count,
+    }))
+}
+
+/// Creates a builder for projects, given the path to the processor
+/// specifications.
+///
+/// # Safety
+///
+/// `path` must be a valid, NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn delirium_builder_new(path: *const c_char) -> *mut ProjectBuilder {
+    guard("delirium_builder_new", ptr::null_mut(), || {
+        match to_str(path).map(ProjectBuilder::new) {
+            Some(Ok(builder)) => Box::into_raw(Box::new(builder)),
+            _ => ptr::null_mut(),
+        }
+    })
+}
+
+/// # Safety
+///
+/// `builder` must be null or returned by `delirium_builder_new`, and not
+/// already released.
+#[no_mangle]
+pub unsafe extern "C" fn delirium_builder_free(builder: *mut ProjectBuilder) {
+    guard("delirium_builder_free", (), || {
+        if !builder.is_null() {
+            drop(Box::from_raw(builder));
+        }
+    })
+}
+
+/// Creates a project for `arch` (e.g., `"x86:LE:64:default"`).
+///
+/// # Safety
+///
+/// `builder` must be returned by `delirium_builder_new`; the strings must
+/// be valid and NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn delirium_project_new(
+    builder: *const ProjectBuilder,
+    name: *const c_char,
+    arch: *const c_char,
+    convention: *const c_char,
+) -> *mut DeliriumProject {
+    guard("delirium_project_new", ptr::null_mut(), || {
+        let builder = match builder.as_ref() {
+            Some(builder) => builder,
+            None => return ptr::null_mut(),
+        };
+
+        match (to_str(name), to_str(arch), to_str(convention)) {
+            (Some(name), Some(arch), Some(convention)) => {
+                match builder.project(name.to_owned(), arch.to_owned(), convention) {
+                    Ok(project) => Box::into_raw(Box::new(project)),
+                    Err(e) => {
+                        log::warn!("cannot create project: {}", e);
+                        ptr::null_mut()
+                    }
+                }
+            }
+            _ => ptr::null_mut(),
+        }
+    })
+}
+
+/// # Safety
+///
+/// `project` must be null or returned by `delirium_project_new`, and not
+/// already released.
+#[no_mangle]
+pub unsafe extern "C" fn delirium_project_free(project: *mut DeliriumProject) {
+    guard("delirium_project_free", (), || {
+        if !project.is_null() {
+            drop(Box::from_raw(project));
+        }
+    })
+}
+
+/// Maps the `len` bytes at `bytes` at `addr`, copying them; returns zero
+/// on success.
+///
+/// # Safety
+///
+/// `project` must be returned by `delirium_project_new`; `name` must be
+/// a valid, NUL-terminated string, and `bytes` must point to `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn delirium_project_map(
+    project: *mut DeliriumProject,
+    name: *const c_char,
+    addr: u64,
+    bytes: *const u8,
+    len: usize,
+    big_endian: bool,
+) -> c_int {
+    guard("delirium_project_map", DELIRIUM_PANIC, || {
+        let (project, name) = match (project.as_mut(), to_str(name)) {
+            (Some(project), Some(name)) if !bytes.is_null() => (project, name),
+            _ => return DELIRIUM_ERROR,
+        };
+
+        let bytes = std::slice::from_raw_parts(bytes, len).to_vec();
+        let endian = if big_endian { Endian::Big } else { Endian::Little };
+
+        match project.add_region_mapping_with(name, addr, endian, Cow::Owned(bytes)) {
+            Ok(()) => 0,
+            Err(e) => {
+                log::warn!("cannot map region {} at {:#x}: {}", name, addr, e);
+                DELIRIUM_ERROR
+            }
+        }
+    })
+}
+
+/// Lifts the Blks starting at `addr`.
+///
+/// # Safety
+///
+/// `project` must be returned by `delirium_project_new`.
+#[no_mangle]
+pub unsafe extern "C" fn delirium_project_lift_blk(
+    project: *mut DeliriumProject,
+    addr: u64,
+) -> *mut DeliriumIr {
+    guard("delirium_project_lift_blk", ptr::null_mut(), || {
+        let project = match project.as_mut() {
+            Some(project) => project,
+            None => return ptr::null_mut(),
+        };
+
+        match project.add_blk(addr) {
+            Ok(ids) => to_ir(ids.iter().filter_map(|id| project.blk(*id))),
+            Err(e) => {
+                log::warn!("cannot lift Blk at {:#x}: {}", addr, e);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Lifts the Sub starting at `addr`, returning its Blks.
+///
+/// # Safety
+///
+/// `project` must be returned by `delirium_project_new`.
+#[no_mangle]
+pub unsafe extern "C" fn delirium_project_add_sub(
+    project: *mut DeliriumProject,
+    addr: u64,
+) -> *mut DeliriumIr {
+    guard("delirium_project_add_sub", ptr::null_mut(), || {
+        let project = match project.as_mut() {
+            Some(project) => project,
+            None => return ptr::null_mut(),
+        };
+
+        match project.add_sub(addr).and_then(|id| project.sub(id)) {
+            Some(sub) => to_ir(sub.blks()),
+            None => ptr::null_mut(),
+        }
+    })
+}
+
+/// # Safety
+///
+/// `ir` must be null or returned by one of the functions above, and not
+/// already released.
+#[no_mangle]
+pub unsafe extern "C" fn delirium_ir_free(ir: *mut DeliriumIr) {
+    guard("delirium_ir_free", (), || {
+        if ir.is_null() {
+            return
+        }
+
+        let ir = Box::from_raw(ir);
+        let stmts = Box::from_raw(ptr::slice_from_raw_parts_mut(ir.stmts, ir.len));
+        for stmt in stmts.iter() {
+            if !stmt.text.is_null() {
+                drop(CString::from_raw(stmt.text));
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::ffi::CString;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_null_arguments() {
+        unsafe {
+            assert!(delirium_builder_new(ptr::null()).is_null());
+            assert!(delirium_project_new(ptr::null(), ptr::null(), ptr::null(), ptr::null()).is_null());
+            assert_eq!(delirium_project_map(ptr::null_mut(), ptr::null(), 0, ptr::null(), 0, false), DELIRIUM_ERROR);
+            assert!(delirium_project_lift_blk(ptr::null_mut(), 0x1000).is_null());
+            assert!(delirium_project_add_sub(ptr::null_mut(), 0x1000).is_null());
+
+            delirium_builder_free(ptr::null_mut());
+            delirium_project_free(ptr::null_mut());
+            delirium_ir_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_guard() {
+        assert_eq!(guard("test", DELIRIUM_PANIC, || panic!("unwinding")), DELIRIUM_PANIC);
+        assert_eq!(guard("test", DELIRIUM_PANIC, || 0), 0);
+    }
+
+    #[test]
+    fn test_lift_blk_order() -> Result<(), Box<dyn std::error::Error>> {
+        let root = env::var("DELIRIUM_TEST_ENV_ROOT")?;
+        let path = CString::new(PathBuf::from_iter([&root, "processors"]).to_string_lossy().into_owned())?;
+        let (name, arch, convention) = (CString::new("test")?, CString::new("x86:LE:32:default")?, CString::new("gcc")?);
+
+        // rep stosb; ret: the instruction's loop is lifted as several Blks
+        let bytes = [0xf3, 0xaa, 0xc3];
+
+        unsafe {
+            let builder = delirium_builder_new(path.as_ptr());
+            assert!(!builder.is_null());
+
+            let project = delirium_project_new(builder, name.as_ptr(), arch.as_ptr(), convention.as_ptr());
+            assert!(!project.is_null());
+            assert_eq!(delirium_project_map(project, name.as_ptr(), 0x1000, bytes.as_ptr(), bytes.len(), false), 0);
+
+            let ir = delirium_project_lift_blk(project, 0x1000);
+            assert!(!ir.is_null());
+
+            // the Blks are given in the order of their group, starting
+            // with the Blk at the address lifted
+            let ids = (*project).add_blk(0x1000u64)?;
+            let stmts = std::slice::from_raw_parts((*ir).stmts, (*ir).len);
+
+            assert!((*ir).blks > 1);
+            assert_eq!((*ir).blks, ids.len());
+            assert_eq!((stmts[0].blk, stmts[0].addr), (0, 0x1000));
+            for stmt in stmts {
+                let blk = (*project).blk(ids[stmt.blk]).unwrap();
+                let addr = blk.addr().map(|addr| u64::try_from(addr).unwrap()).unwrap_or(0);
+                assert_eq!(stmt.addr, addr);
+            }
+
+            delirium_ir_free(ir);
+            delirium_project_free(project);
+            delirium_builder_free(builder);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,367 @@
+/// Batch processing of corpora of binaries.
+///
+/// A `Corpus` is a directory of files; each file is processed by a
+/// `Pipeline`: a loader, which creates a Project for the file, followed by
+/// a sequence of named stages (e.g., exploration, analyses, and export)
+/// applied to the Project. Files are processed in parallel, and failures
+/// (errors or panics) are confined to the file being processed; the
+/// outcome for each file is recorded in a `Manifest`.
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use crate::ir::Project;
+use crate::prelude::Entity;
+
+pub type StageError = Box<dyn Error + Send + Sync>;
+
+type Loader = Box<dyn Fn(&Path) -> Result<Entity<Project<'static>>, StageError> + Send + Sync>;
+type Stage = Box<dyn Fn(&Path, &mut Project<'static>) -> Result<(), StageError> + Send + Sync>;
+
+type Filter = Box<dyn Fn(&Path) -> bool + Send + Sync>;
+
+#[derive(Debug, Error)]
+pub enum CorpusError {
+    #[error("cannot read corpus directory `{0}`: {1}")]
+    Directory(PathBuf, #[source] io::Error),
+}
+
+/// The steps applied to each file of a corpus.
+pub struct Pipeline {
+    loader: Loader,
+    stages: Vec<(Arc<str>, Stage)>,
+}
+
+impl Pipeline {
+    /// Creates a pipeline that builds a Project for each file using `loader`
+    /// (e.g., by mapping the file's segments and adding its entry points).
+    pub fn new<F>(loader: F) -> Self
+    where
+        F: Fn(&Path) -> Result<Entity<Project<'static>>, StageError> + Send + Sync + 'static,
+    {
+        Self {
+            loader: Box::new(loader),
+            stages: Vec::new(),
+        }
+    }
+
+    /// Appends a stage named `name`; stages are applied in the order they
+    /// are added, and the first stage to fail ends the processing of a file.
+    pub fn stage<F>(&mut self, name: impl Into<Arc<str>>, stage: F) -> &mut Self
+    where
+        F: Fn(&Path, &mut Project<'static>) -> Result<(), StageError> + Send + Sync + 'static,
+    {
+        self.stages.push((name.into(), Box::new(stage)));
+        self
+    }
+
+    pub fn stages(&self) -> impl Iterator<Item = &Arc<str>> {
+        self.stages.iter().map(|(name, _)| name)
+    }
+
+    fn run_stage<T>(
+        name: &Arc<str>,
+        f: impl FnOnce() -> Result<T, StageError>,
+    ) -> Result<T, Status> {
+        match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(error)) => Err(Status::Failed {
+                stage: name.clone(),
+                error: error.to_string(),
+            }),
+            Err(payload) => {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown cause".to_owned());
+                Err(Status::Panicked {
+                    stage: name.clone(),
+                    message,
+                })
+            }
+        }
+    }
+
+    /// Processes a single file, recording the outcome.
+    pub fn process(&self, path: &Path) -> Entry {
+        let start = Instant::now();
+        let mut completed = Vec::with_capacity(self.stages.len() + 1);
+
+        let load = Arc::<str>::from("load");
+        let status = match Self::run_stage(&load, || (self.loader)(path)) {
+            Ok(mut project) => {
+                completed.push(load);
+                self.stages
+                    .iter()
+                    .try_for_each(|(name, stage)| {
+                        Self::run_stage(name, || stage(path, &mut project))?;
+                        completed.push(name.clone());
+                        Ok(())
+                    })
+                    .err()
+                    .unwrap_or(Status::Completed)
+            }
+            Err(status) => status,
+        };
+
+        Entry {
+            path: path.to_owned(),
+            status,
+            completed,
+            elapsed: start.elapsed(),
+        }
+    }
+}
+
+/// The outcome of processing a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    Completed,
+    /// A stage returned an error
+    Failed { stage: Arc<str>, error: String },
+    /// A stage panicked
+    Panicked { stage: Arc<str>, message: String },
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Completed => write!(f, "completed"),
+            Self::Failed { stage, error } => write!(f, "failed at {}: {}", stage, error),
+            Self::Panicked { stage, message } => write!(f, "panicked at {}: {}", stage, message),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    path: PathBuf,
+    status: Status,
+    completed: Vec<Arc<str>>,
+    elapsed: Duration,
+}
+
+impl Entry {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.status == Status::Completed
+    }
+
+    /// The stages (including `load`) that completed successfully.
+    pub fn completed_stages(&self) -> &[Arc<str>] {
+        &self.completed
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+/// A summary of the processing of a corpus; entries are ordered by path.
+#[derive(Debug, Clone, Default)]
+pub struct Manifest {
+    entries: Vec<Entry>,
+}
+
+impl Manifest {
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn completed(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(|entry| entry.is_completed())
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(|entry| !entry.is_completed())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Writes the manifest as tab-separated values: one line per file,
+    /// giving its path, status, the time taken in milliseconds, the last
+    /// stage completed, and any error message.
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "path\tstatus\tmillis\tstage\tmessage")?;
+        for entry in self.entries.iter() {
+            let (status, message) = match entry.status {
+                Status::Completed => ("completed", ""),
+                Status::Failed { ref error, .. } => ("failed", &**error),
+                Status::Panicked { ref message, .. } => ("panicked", &**message),
+            };
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}",
+                entry.path.display(),
+                status,
+                entry.elapsed.as_millis(),
+                entry.completed.last().map(|s| &**s).unwrap_or("-"),
+                message.replace(['\t', '\n'], " "),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A directory of files to process.
+pub struct Corpus {
+    root: PathBuf,
+    recursive: bool,
+    jobs: usize,
+    filter: Option<Filter>,
+}
+
+impl Corpus {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_owned(),
+            recursive: true,
+            jobs: thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            filter: None,
+        }
+    }
+
+    /// Include files within sub-directories of the corpus root; enabled by
+    /// default.
+    pub fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Process at most `jobs` files at once; defaults to the available
+    /// parallelism.
+    pub fn jobs(&mut self, jobs: usize) -> &mut Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Only process files for which `filter` returns true.
+    pub fn filter<F>(&mut self, filter: F) -> &mut Self
+    where
+        F: Fn(&Path) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// The files of the corpus, ordered by path; symbolic links to
+    /// directories are not followed.
+    pub fn files(&self) -> Result<Vec<PathBuf>, CorpusError> {
+        let mut files = Vec::new();
+        let mut dirs = vec![self.root.clone()];
+
+        while let Some(dir) = dirs.pop() {
+            let entries = fs::read_dir(&dir)
+                .map_err(|e| CorpusError::Directory(dir.clone(), e))?;
+
+            for entry in entries {
+                let entry = entry.map_err(|e| CorpusError::Directory(dir.clone(), e))?;
+                let kind = entry
+                    .file_type()
+                    .map_err(|e| CorpusError::Directory(dir.clone(), e))?;
+                let path = entry.path();
+
+                // we do not follow symbolic links to directories, as they
+                // may form loops; symbolic links to files are processed
+                if kind.is_dir() {
+                    if self.recursive {
+                        dirs.push(path);
+                    }
+                } else if path.is_file() && self.filter.as_ref().map(|f| f(&path)).unwrap_or(true) {
+                    files.push(path);
+                }
+            }
+        }
+
+        files.sort();
+        Ok(files)
+    }
+
+    /// Processes each file of the corpus using `pipeline`.
+    pub fn run(&self, pipeline: &Pipeline) -> Result<Manifest, CorpusError> {
+        let files = self.files()?;
+        let next = AtomicUsize::new(0);
+        let entries = Mutex::new(Vec::with_capacity(files.len()));
+
+        thread::scope(|scope| {
+            for _ in 0..self.jobs.min(files.len()) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let path = if let Some(path) = files.get(i) {
+                        path
+                    } else {
+                        break
+                    };
+
+                    let entry = pipeline.process(path);
+                    log::debug!("{}: {}", entry.path.display(), entry.status);
+
+                    // unwrap is safe here: stages cannot panic while the
+                    // lock is held
+                    entries.lock().unwrap().push(entry);
+                });
+            }
+        });
+
+        // unwrap is safe here: all workers have completed
+        let mut entries = entries.into_inner().unwrap();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(Manifest { entries })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_files_symlink_loop() -> Result<(), Box<dyn std::error::Error>> {
+        use std::os::unix::fs::symlink;
+
+        let root = std::env::temp_dir().join(format!("delirium-corpus-{}", std::process::id()));
+        let nested = root.join("nested");
+        fs::create_dir_all(&nested)?;
+
+        fs::write(root.join("a.bin"), [0u8])?;
+        fs::write(nested.join("b.bin"), [0u8])?;
+        symlink(&root, nested.join("loop"))?;
+        symlink(root.join("a.bin"), nested.join("c.bin"))?;
+
+        let files = Corpus::new(&root).files();
+        fs::remove_dir_all(&root)?;
+
+        assert_eq!(
+            files?,
+            vec![root.join("a.bin"), nested.join("b.bin"), nested.join("c.bin")]
+        );
+
+        Ok(())
+    }
+}
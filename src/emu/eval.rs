@@ -0,0 +1,242 @@
+use std::sync::Arc;
+
+use crate::ir::{Addr, BitVec, Blk, Def, Expr, Jmp, Loc, Phi, Var};
+use crate::passes::simplify::{fold_binop, fold_binrel, fold_cast, fold_concat, fold_extract, fold_unop};
+use crate::prelude::{Entity, Id, Identifiable};
+
+use super::{EmuError, State};
+
+/// The effect of executing a phi or def on a State.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Effect {
+    /// The variable was assigned `new`; `old` is its previous value
+    Assign {
+        var: Var,
+        old: Option<BitVec>,
+        new: BitVec,
+    },
+    /// The value was written to memory at the address
+    Store { addr: Addr, value: BitVec },
+    /// The state is unchanged (e.g., an assumption that holds)
+    None,
+}
+
+/// Where control flows after executing a jmp (or a Blk).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Flow {
+    /// To the statement following the jmp (i.e., a conditional branch was
+    /// not taken)
+    Next,
+    /// To the start of a Blk
+    Blk(Id<Blk>),
+    /// To an address, which may not have a Blk
+    Addr(Addr),
+    /// An intrinsic jmp was executed; its effects are not evaluated
+    Intrinsic(Arc<str>),
+}
+
+impl<'a, 'r> State<'a, 'r> {
+    /// Evaluates `expr` concretely; floating-point operations, and
+    /// intrinsics without an evaluator (see `set_intrinsic`), are not
+    /// supported.
+    pub fn eval(&self, expr: &Expr) -> Result<BitVec, EmuError> {
+        let undefined = || EmuError::Undefined(expr.clone());
+        let unsupported = || EmuError::Unsupported(expr.clone());
+
+        match expr {
+            Expr::Val(bv) => Ok(bv.clone()),
+            Expr::Var(var) => self
+                .var(var)
+                .cloned()
+                .ok_or_else(|| EmuError::Unassigned(var.clone())),
+            Expr::UnOp(op, iexpr) => fold_unop(*op, &self.eval(iexpr)?).ok_or_else(unsupported),
+            Expr::BinOp(op, lexpr, rexpr) => {
+                fold_binop(*op, &self.eval(lexpr)?, &self.eval(rexpr)?).ok_or_else(undefined)
+            }
+            Expr::BinRel(op, lexpr, rexpr) => {
+                fold_binrel(*op, &self.eval(lexpr)?, &self.eval(rexpr)?).ok_or_else(undefined)
+            }
+            Expr::Cast(iexpr, cast) => fold_cast(&self.eval(iexpr)?, *cast).ok_or_else(unsupported),
+            Expr::Load(addr, bits, _) => self.read(&Addr::from(self.eval(addr)?), *bits),
+            Expr::Extract(iexpr, lsb, msb) => Ok(fold_extract(&self.eval(iexpr)?, *lsb, *msb)),
+            Expr::Concat(lexpr, rexpr) => Ok(fold_concat(&self.eval(lexpr)?, &self.eval(rexpr)?)),
+            Expr::IfElse(cond, texpr, fexpr) => {
+                if self.eval(cond)?.is_zero() {
+                    self.eval(fexpr)
+                } else {
+                    self.eval(texpr)
+                }
+            }
+            Expr::Intrinsic(name, args, bits) => {
+                let f = self.intrinsic(name).ok_or_else(unsupported)?;
+                let args = args.iter().map(|arg| self.eval(arg)).collect::<Result<Vec<_>, _>>()?;
+                f(&args, *bits).ok_or_else(unsupported)
+            }
+            Expr::UnRel(_, _) => Err(unsupported()),
+        }
+    }
+
+    /// The value of the phi's first choice whose condition holds.
+    pub fn eval_phi(&self, phi: &Entity<Phi>) -> Result<BitVec, EmuError> {
+        for (cond, expr) in phi.choices() {
+            if !self.eval(cond)?.is_zero() {
+                return self.eval(expr)
+            }
+        }
+        Err(EmuError::Phi(phi.id()))
+    }
+
+    /// Assigns the phi's variable the value of its first choice whose
+    /// condition holds.
+    pub fn exec_phi(&mut self, phi: &Entity<Phi>) -> Result<Effect, EmuError> {
+        let new = self.eval_phi(phi)?;
+        let old = self.set_var(phi.var().clone(), new.clone());
+        Ok(Effect::Assign { var: phi.var().clone(), old, new })
+    }
+
+    /// Executes the phis of a Blk together: their choices are evaluated
+    /// before any of their variables are assigned, so that a phi may use
+    /// the variable of another (e.g., when swapping two variables).
+    pub fn exec_phis(&mut self, phis: &[Entity<Phi>]) -> Result<Vec<Effect>, EmuError> {
+        let values = phis
+            .iter()
+            .map(|phi| self.eval_phi(phi))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(phis
+            .iter()
+            .zip(values)
+            .map(|(phi, new)| {
+                let old = self.set_var(phi.var().clone(), new.clone());
+                Effect::Assign { var: phi.var().clone(), old, new }
+            })
+            .collect())
+    }
+
+    pub fn exec_def(&mut self, def: &Entity<Def>) -> Result<Effect, EmuError> {
+        match **def {
+            Def::Assign(ref var, ref expr) => {
+                let new = self.eval(expr)?;
+                let old = self.set_var(var.clone(), new.clone());
+                Ok(Effect::Assign { var: var.clone(), old, new })
+            }
+            Def::Assume(ref expr) => {
+                if self.eval(expr)?.is_zero() {
+                    Err(EmuError::Assumption(def.id()))
+                } else {
+                    Ok(Effect::None)
+                }
+            }
+            Def::Store(ref loc, ref val, _, _) => {
+                let addr = Addr::from(self.eval(loc)?);
+                let value = self.eval(val)?;
+                self.write(&addr, &value)?;
+                Ok(Effect::Store { addr, value })
+            }
+        }
+    }
+
+    /// The target of `loc`; computed targets are evaluated to addresses.
+    pub fn eval_loc(&self, loc: &Loc) -> Result<Flow, EmuError> {
+        Ok(match loc {
+            Loc::Resolved(id) => Flow::Blk(*id),
+            Loc::Fixed(addr) => Flow::Addr(addr.clone()),
+            Loc::Computed(expr) => Flow::Addr(Addr::from(self.eval(expr)?)),
+        })
+    }
+
+    /// Where control flows if `jmp` is executed. Calls and returns are
+    /// treated as branches to their targets.
+    pub fn follow(&self, jmp: &Jmp) -> Result<Flow, EmuError> {
+        match jmp {
+            Jmp::Branch(loc) | Jmp::Call(loc, _) | Jmp::Return(loc) => self.eval_loc(loc),
+            Jmp::CBranch(loc, cond) => {
+                if self.eval(cond)?.is_zero() {
+                    Ok(Flow::Next)
+                } else {
+                    self.eval_loc(loc)
+                }
+            }
+            Jmp::Intrinsic(name, _) => Ok(Flow::Intrinsic(name.clone())),
+        }
+    }
+
+    /// Executes the phis and defs of `blk`, then its jmps until one is
+    /// taken; returns `Flow::Next` if none are.
+    pub fn exec_blk(&mut self, blk: &Blk) -> Result<Flow, EmuError> {
+        self.exec_phis(blk.phis())?;
+
+        for def in blk.defs() {
+            self.exec_def(def)?;
+        }
+
+        for jmp in blk.jmps() {
+            let flow = self.follow(jmp)?;
+            if flow != Flow::Next {
+                return Ok(flow)
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::emu::{Emulator, State};
+    use crate::ir::{BitVec, Blk, Expr, Phi, Var};
+    use crate::prelude::{Endian, Entity, Identifiable};
+    use crate::types::bv::BitVecT;
+
+    use super::Flow;
+
+    fn var(name: &str) -> Var {
+        Var::physical(name, BitVecT::with_bits(32, false)).into()
+    }
+
+    // a := b; b := a
+    fn swap() -> Entity<Blk> {
+        let always = || Expr::from(BitVec::from_u64(1, 1));
+
+        let mut blk = Blk::new(None);
+        blk.add_phi(Phi::new(var("a"), vec![(always(), var("b").into())]));
+        blk.add_phi(Phi::new(var("b"), vec![(always(), var("a").into())]));
+        blk
+    }
+
+    fn state<'a, 'r>() -> State<'a, 'r> {
+        let mut state = State::new(Endian::Little);
+        state.set_var(var("a"), BitVec::from_u64(1, 32));
+        state.set_var(var("b"), BitVec::from_u64(2, 32));
+        state
+    }
+
+    #[test]
+    fn test_swap_phis() -> Result<(), Box<dyn std::error::Error>> {
+        let mut state = state();
+
+        assert_eq!(state.exec_blk(&swap())?, Flow::Next);
+        assert_eq!(state.var(&var("a")), Some(&BitVec::from_u64(2, 32)));
+        assert_eq!(state.var(&var("b")), Some(&BitVec::from_u64(1, 32)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_step_swap_phis() -> Result<(), Box<dyn std::error::Error>> {
+        let blk = swap();
+        let id = blk.id();
+
+        let mut emu = Emulator::new(state());
+        emu.add_blk(blk);
+        emu.set_pc(id)?;
+
+        emu.step()?;
+        emu.step()?;
+
+        assert_eq!(emu.state().var(&var("a")), Some(&BitVec::from_u64(2, 32)));
+        assert_eq!(emu.state().var(&var("b")), Some(&BitVec::from_u64(1, 32)));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::emu::State;
+use crate::ir::memory::address::AddrConvertError;
+use crate::ir::memory::RegionError;
+use crate::ir::{Addr, BitVec, Project, Region};
+use crate::lift::Lifter;
+use crate::prelude::{Endian, Entity};
+
+#[derive(Debug, Error)]
+pub enum GdbError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Address(#[from] AddrConvertError),
+    #[error("malformed response from target: {0}")]
+    Protocol(String),
+    #[error("target responded with error {0:02x}")]
+    Target(u8),
+    #[error("target does not support request `{0}`")]
+    Unsupported(String),
+    #[error("target description has no registers")]
+    NoRegisters,
+    #[error("cannot snapshot an empty range of memory")]
+    EmptyRange,
+    #[error("variable for register `{0}` has no size")]
+    UnsizedRegister(Arc<str>),
+    #[error(transparent)]
+    Region(#[from] RegionError),
+}
+
+/// A register of the target, as numbered by its target description.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GdbRegister {
+    name: Arc<str>,
+    bits: u32,
+}
+
+impl GdbRegister {
+    pub fn new(name: impl Into<Arc<str>>, bits: u32) -> Self {
+        Self {
+            name: name.into(),
+            bits,
+        }
+    }
+
+    pub fn name(&self) -> &Arc<str> {
+        &self.name
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    fn bytes(&self) -> usize {
+        ((self.bits + 7) / 8) as usize
+    }
+}
+
+// the maximum number of bytes requested by a single memory read
+const READ_CHUNK: usize = 0x800;
+
+/// A client for the GDB remote serial protocol, for importing the state of
+/// a running (stopped) target, e.g., a gdbserver, QEMU's gdbstub, or a
+/// JTAG probe.
+///
+/// The registers of the target are taken from its target description (see
+/// `load_target_description`), or can be supplied via `set_registers` for
+/// stubs that do not provide one. Register values are imported into
+/// emulator State by matching their names against those of the lifter's
+/// language, and memory is imported as snapshot Regions of a Project, so
+/// that analyses can be anchored to concrete runtime values.
+pub struct GdbClient<S = TcpStream> {
+    stream: S,
+    endian: Endian,
+    registers: Vec<GdbRegister>,
+    pending: VecDeque<u8>,
+}
+
+impl GdbClient<TcpStream> {
+    pub fn connect(addr: impl ToSocketAddrs, endian: Endian) -> Result<Self, GdbError> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Ok(Self::new(stream, endian))
+    }
+}
+
+impl<S> GdbClient<S>
+where
+    S: Read + Write,
+{
+    /// A client communicating over `stream` with a target whose byte order
+    /// is `endian`.
+    pub fn new(stream: S, endian: Endian) -> Self {
+        Self {
+            stream,
+            endian,
+            registers: Vec::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    pub fn registers(&self) -> &[GdbRegister] {
+        &self.registers
+    }
+
+    /// Sets the layout of the register file returned by the target, in
+    /// register number order.
+    pub fn set_registers(&mut self, registers: impl IntoIterator<Item = GdbRegister>) -> &mut Self {
+        self.registers = registers.into_iter().collect();
+        self
+    }
+
+    fn byte(&mut self) -> Result<u8, GdbError> {
+        while self.pending.is_empty() {
+            let mut buffer = [0u8; 4096];
+            let n = self.stream.read(&mut buffer)?;
+            if n == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+            }
+            self.pending.extend(&buffer[..n]);
+        }
+        // unwrap is safe here: we know that pending is non-empty
+        Ok(self.pending.pop_front().unwrap())
+    }
+
+    fn send(&mut self, payload: &str) -> Result<(), GdbError> {
+        let mut packet = Vec::with_capacity(payload.len() + 4);
+        packet.push(b'$');
+        for b in payload.bytes() {
+            if matches!(b, b'#' | b'$' | b'}' | b'*') {
+                packet.extend([b'}', b ^ 0x20]);
+            } else {
+                packet.push(b);
+            }
+        }
+        let checksum = packet[1..].iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        packet.extend(format!("#{:02x}", checksum).bytes());
+
+        // resend until the packet is acknowledged
+        loop {
+            self.stream.write_all(&packet)?;
+            self.stream.flush()?;
+            match self.byte()? {
+                b'+' => return Ok(()),
+                b'-' => continue,
+                b => return Err(GdbError::Protocol(format!("expected acknowledgement, got {:?}", b as char))),
+            }
+        }
+    }
+
+    fn receive(&mut self) -> Result<Vec<u8>, GdbError> {
+        loop {
+            while self.byte()? != b'$' {}
+
+            let mut raw = Vec::new();
+            loop {
+                match self.byte()? {
+                    b'#' => break,
+                    b => raw.push(b),
+                }
+            }
+
+            let checksum = [self.byte()?, self.byte()?];
+            let expected = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|checksum| u8::from_str_radix(checksum, 16).ok());
+
+            if expected != Some(raw.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))) {
+                self.stream.write_all(b"-")?;
+                continue
+            }
+            self.stream.write_all(b"+")?;
+
+            // undo escaping and run-length encoding
+            let mut payload = Vec::with_capacity(raw.len());
+            let mut bytes = raw.into_iter();
+            while let Some(b) = bytes.next() {
+                match b {
+                    b'}' => payload.push(bytes.next().unwrap_or_default() ^ 0x20),
+                    b'*' => {
+                        let last = *payload
+                            .last()
+                            .ok_or_else(|| GdbError::Protocol("run-length encoding without a value".to_owned()))?;
+                        let count = bytes.next().unwrap_or(29).saturating_sub(29);
+                        payload.extend(std::iter::repeat(last).take(count as usize));
+                    }
+                    b => payload.push(b),
+                }
+            }
+
+            return Ok(payload)
+        }
+    }
+
+    /// Sends `request` and returns the target's response; error and empty
+    /// (unsupported) responses are returned as errors.
+    pub fn request(&mut self, request: &str) -> Result<String, GdbError> {
+        self.send(request)?;
+        let response = self.receive()?;
+
+        if response.is_empty() {
+            return Err(GdbError::Unsupported(request.to_owned()))
+        }
+
+        if response.len() == 3 && response[0] == b'E' {
+            if let Some(code) = std::str::from_utf8(&response[1..])
+                .ok()
+                .and_then(|code| u8::from_str_radix(code, 16).ok())
+            {
+                return Err(GdbError::Target(code))
+            }
+        }
+
+        String::from_utf8(response).map_err(|e| GdbError::Protocol(e.to_string()))
+    }
+
+    /// The reason the target is stopped (e.g., `T05...` for SIGTRAP).
+    pub fn halt_reason(&mut self) -> Result<String, GdbError> {
+        self.request("?")
+    }
+
+    fn read_object(&mut self, object: &str, annex: &str) -> Result<String, GdbError> {
+        let mut data = String::new();
+        loop {
+            let response = self.request(&format!(
+                "qXfer:{}:read:{}:{:x},{:x}",
+                object,
+                annex,
+                data.len(),
+                READ_CHUNK
+            ))?;
+            let (more, chunk) = response.split_at(1);
+            data.push_str(chunk);
+            match more {
+                "m" => continue,
+                "l" => return Ok(data),
+                _ => return Err(GdbError::Protocol(format!("unexpected qXfer response {}", response))),
+            }
+        }
+    }
+
+    /// Reads the target's description and uses its registers as the layout
+    /// of the register file.
+    pub fn load_target_description(&mut self) -> Result<&[GdbRegister], GdbError> {
+        let mut registers = Vec::new();
+        let mut annexes = VecDeque::from(["target.xml".to_owned()]);
+
+        while let Some(annex) = annexes.pop_front() {
+            let xml = self.read_object("features", &annex)?;
+
+            // included features are numbered after those that include them
+            for (tag, attributes) in xml_tags(&xml) {
+                match tag {
+                    "reg" => {
+                        let name = attribute(attributes, "name");
+                        let bits = attribute(attributes, "bitsize").and_then(|bits| bits.parse().ok());
+                        let (name, bits) = name
+                            .zip(bits)
+                            .ok_or_else(|| GdbError::Protocol(format!("malformed register <{}>", attributes)))?;
+
+                        let regnum = attribute(attributes, "regnum")
+                            .and_then(|regnum| regnum.parse::<usize>().ok())
+                            .unwrap_or(registers.len());
+                        if registers.len() <= regnum {
+                            registers.resize(regnum + 1, None);
+                        }
+                        registers[regnum] = Some(GdbRegister::new(name, bits));
+                    }
+                    "xi:include" => {
+                        if let Some(href) = attribute(attributes, "href") {
+                            annexes.push_back(href.to_owned());
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        // gaps in the numbering are not transferred by the g packet
+        self.registers = registers.into_iter().flatten().collect();
+        if self.registers.is_empty() {
+            return Err(GdbError::NoRegisters)
+        }
+
+        Ok(&self.registers)
+    }
+
+    fn value(&self, bytes: &[u8], bits: u32) -> BitVec {
+        let bv = if self.endian.is_little() {
+            BitVec::from_le_bytes(bytes).unsigned()
+        } else {
+            BitVec::from_be_bytes(bytes).unsigned()
+        };
+        if bv.bits() as u32 != bits {
+            bv.cast(bits as usize)
+        } else {
+            bv
+        }
+    }
+
+    /// Reads the values of the target's registers; registers whose values
+    /// are unavailable are omitted.
+    pub fn read_registers(&mut self) -> Result<Vec<(GdbRegister, BitVec)>, GdbError> {
+        if self.registers.is_empty() {
+            return Err(GdbError::NoRegisters)
+        }
+
+        let response = self.request("g")?;
+        let response = response.as_bytes();
+        let mut offset = 0;
+        let mut values = Vec::new();
+
+        for register in self.registers.iter() {
+            let size = register.bytes() * 2;
+            if offset + size > response.len() {
+                // targets may omit trailing registers
+                break
+            }
+
+            let hex = &response[offset..offset + size];
+            offset += size;
+
+            if hex.contains(&b'x') {
+                continue
+            }
+
+            let bytes = decode_hex(hex)?;
+            values.push((register.clone(), self.value(&bytes, register.bits)));
+        }
+
+        Ok(values)
+    }
+
+    pub fn read_memory(&mut self, addr: &Addr, count: usize) -> Result<Vec<u8>, GdbError> {
+        let mut bytes = Vec::with_capacity(count);
+
+        while bytes.len() < count {
+            let start = u64::try_from(&(addr + bytes.len()))?;
+            let size = READ_CHUNK.min(count - bytes.len());
+
+            let response = self.request(&format!("m{:x},{:x}", start, size))?;
+            let chunk = decode_hex(response.as_bytes())?;
+            if chunk.is_empty() {
+                return Err(GdbError::Protocol(format!("empty read of memory at {:#x}", start)))
+            }
+            bytes.extend(chunk);
+        }
+
+        bytes.truncate(count);
+        Ok(bytes)
+    }
+
+    /// A Region containing the current contents of the `count` bytes of
+    /// the target's memory at `addr`.
+    pub fn snapshot(
+        &mut self,
+        name: impl Into<Arc<str>>,
+        addr: &Addr,
+        count: usize,
+    ) -> Result<Entity<Region<'static>>, GdbError> {
+        if count == 0 {
+            return Err(GdbError::EmptyRange)
+        }
+        let bytes = self.read_memory(addr, count)?;
+        Ok(Region::try_new(name, addr.clone(), self.endian, bytes)?)
+    }
+
+    /// Maps snapshots of each of the `ranges` of the target's memory into
+    /// `project`.
+    pub fn import_memory(
+        &mut self,
+        project: &mut Project,
+        ranges: impl IntoIterator<Item = (Addr, usize)>,
+    ) -> Result<(), GdbError> {
+        for (addr, count) in ranges {
+            let name = format!("gdb_{:x}", u64::try_from(&addr)?);
+            project.add_region_mapping(self.snapshot(name, &addr, count)?);
+        }
+        Ok(())
+    }
+
+    /// Assigns the values of the target's registers to the corresponding
+    /// variables of `lifter`'s language in `state`; returns the names of
+    /// the registers that have no corresponding variable.
+    pub fn import_registers(
+        &mut self,
+        lifter: &Lifter,
+        state: &mut State,
+    ) -> Result<Vec<Arc<str>>, GdbError> {
+        let mut unknown = Vec::new();
+
+        for (register, value) in self.read_registers()? {
+            match lifter.register(register.name()) {
+                Some(var) => {
+                    let bits = var
+                        .bits()
+                        .ok_or_else(|| GdbError::UnsizedRegister(register.name().clone()))?
+                        as usize;
+                    let value = if value.bits() != bits {
+                        value.cast(bits)
+                    } else {
+                        value
+                    };
+                    state.set_var(var, value);
+                }
+                None => unknown.push(register.name().clone()),
+            }
+        }
+
+        Ok(unknown)
+    }
+
+    /// Writes the current contents of the `count` bytes of the target's
+    /// memory at `addr` into `state`.
+    pub fn import_memory_into(&mut self, state: &mut State, addr: &Addr, count: usize) -> Result<(), GdbError> {
+        let bytes = self.read_memory(addr, count)?;
+        state.write_bytes(addr, &bytes);
+        Ok(())
+    }
+}
+
+fn decode_hex(hex: &[u8]) -> Result<Vec<u8>, GdbError> {
+    if hex.len() % 2 != 0 {
+        return Err(GdbError::Protocol("odd-length hexadecimal data".to_owned()))
+    }
+
+    hex.chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| GdbError::Protocol("invalid hexadecimal data".to_owned()))
+        })
+        .collect()
+}
+
+// the names and (unparsed) attributes of the start tags of an XML document;
+// target descriptions are simple enough not to need a full parser
+fn xml_tags(xml: &str) -> impl Iterator<Item = (&str, &str)> {
+    xml.split('<').skip(1).filter_map(|tag| {
+        let tag = tag.split('>').next()?.trim_end_matches('/').trim();
+        if tag.starts_with(|c: char| matches!(c, '/' | '!' | '?')) {
+            return None
+        }
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        Some((&tag[..name_end], &tag[name_end..]))
+    })
+}
+
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attributes;
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim();
+        let value = rest[eq + 1..].trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = value[1..].find(quote)?;
+        if key == name {
+            return Some(&value[1..end + 1])
+        }
+        rest = &value[end + 2..];
+    }
+    None
+}
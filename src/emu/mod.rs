@@ -0,0 +1,564 @@
+/// Concrete execution of our IR/IL.
+///
+/// - `state` holds the values of variables and the contents of memory of
+///   an emulated program.
+///
+/// - `eval` is a concrete interpreter over a State: it evaluates
+///   expressions, executes phis and defs, and determines where jmps flow.
+///
+/// - `preset` constructs the initial machine states of platforms (e.g., the
+///   entry of a Linux x86-64 process, or the reset state of a Cortex-M
+///   core), for emulation and symbolic execution.
+///
+/// - `gdb` imports register and memory state from live targets via the GDB
+///   remote serial protocol.
+///
+/// - `Emulator` executes Blks statement-by-statement over a State, with
+///   breakpoints, watchpoints, and hooks, for building IR-level debuggers
+///   and emulating small pieces of code (e.g., decoders and unpackers);
+///   Blks can be lifted on demand from the memory of a Project.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use fugue::ir::disassembly::ContextDatabase;
+
+use crate::ir::{Addr, BitVec, Blk, Def, Jmp, Mem, Phi, Project, Sub, Var};
+use crate::lift::Lifter;
+use crate::prelude::{Endian, Entity, Erased, Id, Identifiable};
+
+pub mod eval;
+pub use eval::{Effect, Flow};
+
+pub mod gdb;
+pub use gdb::{GdbClient, GdbError, GdbRegister};
+
+pub mod preset;
+pub use preset::{LoaderInfo, Platform, Preset, PresetError};
+
+pub mod state;
+pub use state::{EmuError, IntrinsicFn, State};
+
+// the most bytes lifted for a group of Blks lifted on demand
+const MAX_LIFT_BYTES: usize = 4096;
+
+/// Called with the State after a hooked variable or range of memory is
+/// written, and the write's effect; hooks may modify the State.
+pub type Hook<'a, 'r> = Arc<dyn Fn(&mut State<'a, 'r>, &Effect) + Send + Sync + 'a>;
+
+/// Called with the State and the values of the arguments of an intrinsic
+/// jmp, in place of stopping execution at it.
+pub type IntrinsicHook<'a, 'r> = Arc<dyn Fn(&mut State<'a, 'r>, &[BitVec]) -> Result<(), EmuError> + Send + Sync + 'a>;
+
+/// A point of execution: the statement at `index` of `blk`, where the
+/// statements of a Blk are its phis, then its defs, then its jmps.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub blk: Id<Blk>,
+    pub index: usize,
+}
+
+/// A statement of a Blk.
+#[derive(Copy, Clone)]
+pub enum Statement<'a> {
+    Phi(&'a Entity<Phi>),
+    Def(&'a Entity<Def>),
+    Jmp(&'a Entity<Jmp>),
+}
+
+impl<'a> Statement<'a> {
+    pub fn id(&self) -> Id<Erased> {
+        match self {
+            Self::Phi(phi) => phi.id().erase(),
+            Self::Def(def) => def.id().erase(),
+            Self::Jmp(jmp) => jmp.id().erase(),
+        }
+    }
+}
+
+/// Why execution stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The next statement has a breakpoint
+    Breakpoint(Id<Erased>),
+    /// Execution reached the start of the Blk at the address
+    Address(Addr),
+    /// The predicate passed to `run_until` holds
+    Predicate,
+    /// A watched variable was assigned
+    VarWritten {
+        var: Var,
+        old: Option<BitVec>,
+        new: BitVec,
+    },
+    /// A watched range of memory was written
+    MemoryWritten { addr: Addr, value: BitVec },
+    /// An intrinsic jmp was executed; its effects are not emulated
+    Intrinsic(Arc<str>),
+    /// Control flowed to an address without a known Blk
+    Unresolved(Addr),
+    /// No jmp of the last Blk executed was taken
+    Halted,
+}
+
+/// Executes Blks over a concrete State, one statement at a time.
+///
+/// Calls and returns are treated as branches to their targets (their
+/// effects on the stack, etc., are made explicit by the defs preceding
+/// them when lifted). Execution stops when a breakpoint or watchpoint is
+/// hit, when an intrinsic jmp without a hook is executed, or when control
+/// flows to an address for which no Blk is known and none can be lifted.
+///
+/// With a Lifter (see `set_lifter` and `for_project`), flows to addresses
+/// without known Blks lift the code at them from the State's memory, i.e.,
+/// including the bytes written by the program. Writes to code lifted on
+/// demand discard its Blks, so that later flows to it lift the code
+/// written (Blks being executed are completed as lifted).
+#[derive(Clone)]
+pub struct Emulator<'a, 'r> {
+    blks: BTreeMap<Id<Blk>, Entity<Blk>>,
+    addrs: BTreeMap<Addr, Id<Blk>>,
+    state: State<'a, 'r>,
+    pc: Option<Position>,
+    steps: usize,
+    // the values of the phis of the Blk at pc, evaluated on entering it
+    phis: Vec<BitVec>,
+
+    breakpoints: BTreeSet<Id<Erased>>,
+    watched_vars: BTreeSet<Arc<str>>,
+    watched_memory: Vec<(Addr, usize)>,
+
+    var_hooks: BTreeMap<Arc<str>, Vec<Hook<'a, 'r>>>,
+    memory_hooks: Vec<(Addr, usize, Hook<'a, 'r>)>,
+    intrinsic_hooks: BTreeMap<Arc<str>, IntrinsicHook<'a, 'r>>,
+
+    lifter: Option<(&'a Lifter, ContextDatabase)>,
+    // the end of each group of Blks lifted on demand, keyed by its start,
+    // and the addresses it makes Blks available for
+    lifted: BTreeMap<Addr, (Addr, Vec<Addr>)>,
+}
+
+impl<'a, 'r> Emulator<'a, 'r> {
+    pub fn new(state: State<'a, 'r>) -> Self {
+        Self {
+            blks: BTreeMap::new(),
+            addrs: BTreeMap::new(),
+            state,
+            pc: None,
+            steps: 0,
+            phis: Vec::new(),
+            breakpoints: BTreeSet::new(),
+            watched_vars: BTreeSet::new(),
+            watched_memory: Vec::new(),
+            var_hooks: BTreeMap::new(),
+            memory_hooks: Vec::new(),
+            intrinsic_hooks: BTreeMap::new(),
+            lifter: None,
+            lifted: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_memory(memory: &'a Mem<'r>, endian: Endian) -> Self {
+        Self::new(State::with_memory(memory, endian))
+    }
+
+    /// An Emulator over the memory of `project`, which lifts Blks on
+    /// demand using its Lifter; use `jump` to set where execution begins.
+    pub fn for_project(project: &'a Project<'r>) -> Self {
+        let mut emu = Self::with_memory(project.memory(), project.lifter().endian());
+        emu.set_lifter(project.lifter());
+        emu
+    }
+
+    /// Lifts the code at addresses without known Blks using `lifter`.
+    pub fn set_lifter(&mut self, lifter: &'a Lifter) {
+        self.lifter = Some((lifter, lifter.context()));
+    }
+
+    /// Makes `blk` available for execution; Blks with addresses are the
+    /// targets of flows to their addresses.
+    pub fn add_blk(&mut self, blk: Entity<Blk>) {
+        if let Some(addr) = blk.addr() {
+            self.addrs.entry(addr.clone()).or_insert_with(|| blk.id());
+        }
+        self.blks.insert(blk.id(), blk);
+    }
+
+    pub fn add_blks(&mut self, blks: impl IntoIterator<Item = Entity<Blk>>) {
+        for blk in blks {
+            self.add_blk(blk);
+        }
+    }
+
+    pub fn add_sub(&mut self, sub: &Sub) {
+        self.add_blks(sub.blks().iter().cloned());
+    }
+
+    pub fn blk(&self, id: impl Identifiable<Blk>) -> Option<&Entity<Blk>> {
+        self.blks.get(&id.id())
+    }
+
+    pub fn blk_at(&self, addr: &Addr) -> Option<&Entity<Blk>> {
+        self.addrs.get(addr).and_then(|id| self.blks.get(id))
+    }
+
+    pub fn state(&self) -> &State<'a, 'r> {
+        &self.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut State<'a, 'r> {
+        &mut self.state
+    }
+
+    /// The position of the next statement to execute.
+    pub fn pc(&self) -> Option<Position> {
+        self.pc
+    }
+
+    /// Continues execution at the start of `blk`.
+    pub fn set_pc(&mut self, blk: impl Identifiable<Blk>) -> Result<(), EmuError> {
+        let blk = blk.id();
+        if !self.blks.contains_key(&blk) {
+            return Err(EmuError::UnknownBlk(blk))
+        }
+        self.pc = Some(Position { blk, index: 0 });
+        Ok(())
+    }
+
+    /// Continues execution at the start of the Blk at `addr`, lifting it
+    /// if needed (and possible).
+    pub fn jump(&mut self, addr: &Addr) -> Result<(), EmuError> {
+        let blk = self
+            .blk_id_at(addr)
+            .ok_or_else(|| EmuError::Unmapped(addr.clone()))?;
+        self.set_pc(blk)
+    }
+
+    // the Blk at addr, lifted from the State's memory if not known
+    fn blk_id_at(&mut self, addr: &Addr) -> Option<Id<Blk>> {
+        if let Some(id) = self.addrs.get(addr) {
+            return Some(*id)
+        }
+
+        self.lifter.as_ref()?;
+
+        if !self.state.is_executable(addr) {
+            log::debug!("not lifting block at {} from a non-executable region", addr);
+            return None
+        }
+
+        let mut bytes = Vec::new();
+        while bytes.len() < MAX_LIFT_BYTES {
+            match self.state.read_byte(&(addr + bytes.len())) {
+                Ok(byte) => bytes.push(byte),
+                Err(_) => break,
+            }
+        }
+
+        // unwrap is safe here: we checked the lifter above
+        let (lifter, ctxt) = self.lifter.as_mut().unwrap();
+        let (blks, size) = match lifter.lift_blk_extent(ctxt, addr, &bytes, None) {
+            Ok((blks, size)) if !blks.is_empty() && size > 0 => (blks, size),
+            Ok(_) => return None,
+            Err(e) => {
+                log::debug!("could not lift block at {}: {}", addr, e);
+                return None
+            }
+        };
+
+        let entry = blks[0].id();
+        let addrs = blks
+            .iter()
+            .filter_map(|blk| blk.addr())
+            .filter(|baddr| !self.addrs.contains_key(*baddr))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        self.add_blks(blks);
+        self.addrs.insert(addr.clone(), entry);
+        self.lifted.insert(addr.clone(), (addr + size, addrs));
+
+        Some(entry)
+    }
+
+    // discards the Blks lifted on demand from the size bytes at addr
+    fn invalidate(&mut self, addr: &Addr, size: usize) {
+        let end = addr + size;
+        let stale = self
+            .lifted
+            .range(..end)
+            .filter(|(_, (gend, _))| gend > addr)
+            .map(|(start, _)| start.clone())
+            .collect::<Vec<_>>();
+
+        for start in stale {
+            // unwrap is safe here: we found the group above
+            let (_, addrs) = self.lifted.remove(&start).unwrap();
+            for baddr in addrs {
+                self.addrs.remove(&baddr);
+            }
+        }
+    }
+
+    /// The number of statements executed.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// The next statement to execute.
+    pub fn statement(&self) -> Option<Statement<'_>> {
+        let pc = self.pc?;
+        let blk = self.blks.get(&pc.blk)?;
+        Self::statement_at(blk, pc.index)
+    }
+
+    fn statement_at(blk: &Entity<Blk>, index: usize) -> Option<Statement<'_>> {
+        let (phis, defs, jmps) = (blk.phis(), blk.defs(), blk.jmps());
+        if index < phis.len() {
+            Some(Statement::Phi(&phis[index]))
+        } else if index < phis.len() + defs.len() {
+            Some(Statement::Def(&defs[index - phis.len()]))
+        } else {
+            jmps.get(index - phis.len() - defs.len()).map(Statement::Jmp)
+        }
+    }
+
+    /// Stops before executing the statement `stmt` (a Phi, Def, or Jmp).
+    pub fn add_breakpoint<V>(&mut self, stmt: impl Identifiable<V>) {
+        self.breakpoints.insert(stmt.id().erase());
+    }
+
+    pub fn remove_breakpoint<V>(&mut self, stmt: impl Identifiable<V>) -> bool {
+        self.breakpoints.remove(&stmt.id().erase())
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = Id<Erased>> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Stops after any variable named `name` (e.g., a register) is
+    /// assigned.
+    pub fn watch_var(&mut self, name: impl Into<Arc<str>>) {
+        self.watched_vars.insert(name.into());
+    }
+
+    pub fn unwatch_var(&mut self, name: &str) -> bool {
+        self.watched_vars.remove(name)
+    }
+
+    /// Stops after any of the `size` bytes at `addr` are written.
+    pub fn watch_memory(&mut self, addr: impl Into<Addr>, size: usize) {
+        self.watched_memory.push((addr.into(), size));
+    }
+
+    pub fn unwatch_memory(&mut self, addr: &Addr) {
+        self.watched_memory.retain(|(waddr, _)| waddr != addr);
+    }
+
+    fn is_watched(&self, addr: &Addr, bits: u32) -> bool {
+        let size = ((bits + 7) / 8) as usize;
+        self.watched_memory
+            .iter()
+            .any(|(waddr, wsize)| overlaps(addr, size, waddr, *wsize))
+    }
+
+    /// Calls `hook` after any variable named `name` (e.g., a register) is
+    /// assigned.
+    pub fn hook_var<F>(&mut self, name: impl Into<Arc<str>>, hook: F)
+    where
+        F: Fn(&mut State<'a, 'r>, &Effect) + Send + Sync + 'a,
+    {
+        self.var_hooks.entry(name.into()).or_default().push(Arc::new(hook));
+    }
+
+    /// Calls `hook` after any of the `size` bytes at `addr` are written.
+    pub fn hook_memory<F>(&mut self, addr: impl Into<Addr>, size: usize, hook: F)
+    where
+        F: Fn(&mut State<'a, 'r>, &Effect) + Send + Sync + 'a,
+    {
+        self.memory_hooks.push((addr.into(), size, Arc::new(hook)));
+    }
+
+    /// Calls `hook` in place of stopping at intrinsic jmps named `name`;
+    /// execution continues with the following statement.
+    pub fn hook_intrinsic<F>(&mut self, name: impl Into<Arc<str>>, hook: F)
+    where
+        F: Fn(&mut State<'a, 'r>, &[BitVec]) -> Result<(), EmuError> + Send + Sync + 'a,
+    {
+        self.intrinsic_hooks.insert(name.into(), Arc::new(hook));
+    }
+
+    pub fn clear_hooks(&mut self) {
+        self.var_hooks.clear();
+        self.memory_hooks.clear();
+        self.intrinsic_hooks.clear();
+    }
+
+    // runs the hooks for effect, and returns the event it triggers
+    fn apply_effect(&mut self, effect: Effect) -> Option<Event> {
+        let hooks = match effect {
+            Effect::Assign { ref var, .. } => self.var_hooks.get(var.name()).cloned().unwrap_or_default(),
+            Effect::Store { ref addr, ref value } => {
+                let size = (value.bits() + 7) / 8;
+                self.invalidate(addr, size);
+                self.memory_hooks
+                    .iter()
+                    .filter(|(haddr, hsize, _)| overlaps(addr, size, haddr, *hsize))
+                    .map(|(_, _, hook)| hook.clone())
+                    .collect()
+            }
+            Effect::None => Vec::new(),
+        };
+
+        for hook in hooks {
+            hook(&mut self.state, &effect);
+        }
+
+        self.effect_event(effect)
+    }
+
+    fn effect_event(&self, effect: Effect) -> Option<Event> {
+        match effect {
+            Effect::Assign { var, old, new } if self.watched_vars.contains(var.name()) => {
+                Some(Event::VarWritten { var, old, new })
+            }
+            Effect::Store { addr, value } if self.is_watched(&addr, value.bits() as u32) => {
+                Some(Event::MemoryWritten { addr, value })
+            }
+            _ => None,
+        }
+    }
+
+    /// Executes the next statement, returning the event it triggers, if
+    /// any; breakpoints are ignored.
+    pub fn step(&mut self) -> Result<Option<Event>, EmuError> {
+        let pc = self.pc.ok_or(EmuError::Halted)?;
+        let blk = self
+            .blks
+            .get(&pc.blk)
+            .cloned()
+            .ok_or(EmuError::UnknownBlk(pc.blk))?;
+
+        let stmt = if let Some(stmt) = Self::statement_at(&blk, pc.index) {
+            stmt
+        } else {
+            // no jmp was taken
+            self.pc = None;
+            return Ok(Some(Event::Halted))
+        };
+
+        self.steps += 1;
+        let next = Position { blk: pc.blk, index: pc.index + 1 };
+
+        let (event, pc) = match stmt {
+            Statement::Phi(phi) => {
+                // the phis of a Blk are evaluated together, as by exec_phis;
+                // execution only enters Blks at their start
+                if pc.index == 0 {
+                    self.phis = blk
+                        .phis()
+                        .iter()
+                        .map(|phi| self.state.eval_phi(phi))
+                        .collect::<Result<Vec<_>, _>>()?;
+                }
+                let new = self.phis[pc.index].clone();
+                let old = self.state.set_var(phi.var().clone(), new.clone());
+                let effect = Effect::Assign { var: phi.var().clone(), old, new };
+                (self.apply_effect(effect), Some(next))
+            }
+            Statement::Def(def) => {
+                let effect = self.state.exec_def(def)?;
+                (self.apply_effect(effect), Some(next))
+            }
+            Statement::Jmp(jmp) => match self.state.follow(jmp)? {
+                Flow::Intrinsic(name) if self.intrinsic_hooks.contains_key(&name) => {
+                    let args = match **jmp {
+                        Jmp::Intrinsic(_, ref args) => args
+                            .iter()
+                            .map(|arg| self.state.eval(arg))
+                            .collect::<Result<Vec<_>, _>>()?,
+                        _ => Vec::new(),
+                    };
+                    // unwrap is safe here: we checked the hook exists
+                    let hook = self.intrinsic_hooks.get(&name).cloned().unwrap();
+                    hook(&mut self.state, &args)?;
+                    (None, Some(next))
+                }
+                Flow::Next => (None, Some(next)),
+                Flow::Intrinsic(name) => (Some(Event::Intrinsic(name)), Some(next)),
+                Flow::Blk(blk) => {
+                    if !self.blks.contains_key(&blk) {
+                        return Err(EmuError::UnknownBlk(blk))
+                    }
+                    (None, Some(Position { blk, index: 0 }))
+                }
+                Flow::Addr(addr) => match self.blk_id_at(&addr) {
+                    Some(blk) => (None, Some(Position { blk, index: 0 })),
+                    None => (Some(Event::Unresolved(addr)), None),
+                },
+            },
+        };
+
+        self.pc = pc;
+        Ok(event)
+    }
+
+    /// Executes statements until `predicate` holds (checked before each
+    /// statement), a breakpoint is reached, or a statement triggers an
+    /// event. The statement at the current position is always executed,
+    /// even if it has a breakpoint.
+    pub fn run_until<F>(&mut self, mut predicate: F) -> Result<Event, EmuError>
+    where
+        F: FnMut(&Self) -> bool,
+    {
+        let mut first = true;
+
+        loop {
+            if !first {
+                if predicate(self) {
+                    return Ok(Event::Predicate)
+                }
+                if let Some(stmt) = self.statement() {
+                    let id = stmt.id();
+                    if self.breakpoints.contains(&id) {
+                        return Ok(Event::Breakpoint(id))
+                    }
+                }
+            }
+            first = false;
+
+            if let Some(event) = self.step()? {
+                return Ok(event)
+            }
+        }
+    }
+
+    /// Executes statements until the start of the Blk at `addr` is
+    /// reached (or execution stops for another reason).
+    pub fn run_until_addr(&mut self, addr: &Addr) -> Result<Event, EmuError> {
+        let event = self.run_until(|emu| {
+            emu.pc
+                .filter(|pc| pc.index == 0)
+                .and_then(|pc| emu.blks.get(&pc.blk))
+                .and_then(|blk| blk.addr())
+                .map(|baddr| baddr == addr)
+                .unwrap_or(false)
+        })?;
+
+        Ok(if event == Event::Predicate {
+            Event::Address(addr.clone())
+        } else {
+            event
+        })
+    }
+
+    /// Executes statements until a breakpoint is reached or execution
+    /// stops for another reason.
+    pub fn run(&mut self) -> Result<Event, EmuError> {
+        self.run_until(|_| false)
+    }
+}
+
+fn overlaps(addr: &Addr, size: usize, other: &Addr, other_size: usize) -> bool {
+    *other < addr + size && *addr < other + other_size
+}
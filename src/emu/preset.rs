@@ -0,0 +1,355 @@
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::emu::{EmuError, State};
+use crate::ir::{Addr, BitVec, Mem, Var};
+use crate::lift::Lifter;
+use crate::prelude::Endian;
+use crate::symbolic::SymState;
+
+#[derive(Debug, Error)]
+pub enum PresetError {
+    #[error("register `{0}` is not defined by the lifter's language")]
+    UnknownRegister(Arc<str>),
+    #[error("the lifter's language has no known stack pointer")]
+    NoStackPointer,
+    #[error("cannot read the vector table at {0}")]
+    VectorTable(Addr),
+    #[error(transparent)]
+    Emulation(#[from] EmuError),
+}
+
+/// The platforms with presets; see `Preset`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Platform {
+    /// The entry of a userland Linux x86-64 process
+    LinuxX86_64,
+    /// The reset state of an ARM Cortex-M core
+    CortexM,
+    /// The entry of a Windows x64 process
+    WindowsX64,
+}
+
+/// What is known about the program loaded, as used to construct presets;
+/// anything unknown takes a default suited to the platform.
+#[derive(Debug, Clone, Default)]
+pub struct LoaderInfo {
+    entry: Option<u64>,
+    image_base: Option<u64>,
+    stack_top: Option<u64>,
+    stack_size: Option<u64>,
+    args: Vec<String>,
+    env: Vec<String>,
+    vector_table: Option<u64>,
+    teb: Option<u64>,
+    peb: Option<u64>,
+}
+
+impl LoaderInfo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn entry(&mut self, addr: u64) -> &mut Self {
+        self.entry = Some(addr);
+        self
+    }
+
+    pub fn image_base(&mut self, addr: u64) -> &mut Self {
+        self.image_base = Some(addr);
+        self
+    }
+
+    /// The (exclusive) upper bound of the initial stack.
+    pub fn stack_top(&mut self, addr: u64) -> &mut Self {
+        self.stack_top = Some(addr);
+        self
+    }
+
+    pub fn stack_size(&mut self, size: u64) -> &mut Self {
+        self.stack_size = Some(size);
+        self
+    }
+
+    pub fn arg(&mut self, arg: impl Into<String>) -> &mut Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn env(&mut self, var: impl Into<String>) -> &mut Self {
+        self.env.push(var.into());
+        self
+    }
+
+    /// The address of the vector table of a Cortex-M image (by default, 0).
+    pub fn vector_table(&mut self, addr: u64) -> &mut Self {
+        self.vector_table = Some(addr);
+        self
+    }
+
+    /// The addresses at which the TEB and PEB stubs of a Windows process
+    /// are placed.
+    pub fn teb_peb(&mut self, teb: u64, peb: u64) -> &mut Self {
+        self.teb = Some(teb);
+        self.peb = Some(peb);
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+enum PresetReg {
+    // the stack pointer of the lifter's language
+    StackPointer,
+    Named { name: Arc<str>, required: bool },
+}
+
+/// An initial machine state for a platform: the values of registers and
+/// the contents of memory (e.g., of the stack) on entry, constructed from
+/// the lifter's language (for its stack pointer) and what is known about
+/// the program loaded (see `LoaderInfo`). Presets are applied to States
+/// for emulation (`apply`), or to SymStates for symbolic execution
+/// (`apply_symbolic`).
+///
+/// Registers not set by a preset are left as they are (i.e., unset in a
+/// fresh State, and symbolic in a fresh SymState).
+#[derive(Debug, Clone)]
+pub struct Preset {
+    platform: Platform,
+    pc: Option<Addr>,
+    registers: Vec<(PresetReg, BitVec)>,
+    memory: Vec<(Addr, BitVec)>,
+}
+
+const LINUX_STACK_TOP: u64 = 0x7fff_ffff_f000;
+const WINDOWS_STACK_TOP: u64 = 0x0000_0000_0015_0000;
+const WINDOWS_STACK_SIZE: u64 = 0x0010_0000;
+const WINDOWS_TEB: u64 = 0x0000_07ff_fffd_e000;
+const WINDOWS_PEB: u64 = 0x0000_07ff_fffd_f000;
+
+// auxiliary vector entries
+const AT_NULL: u64 = 0;
+const AT_PAGESZ: u64 = 6;
+const AT_ENTRY: u64 = 9;
+
+impl Preset {
+    fn new(platform: Platform) -> Self {
+        Self {
+            platform,
+            pc: None,
+            registers: Vec::new(),
+            memory: Vec::new(),
+        }
+    }
+
+    /// The preset for `platform`; `memory` is read for the vector table of
+    /// Cortex-M images.
+    pub fn for_platform(platform: Platform, info: &LoaderInfo, memory: &Mem, endian: Endian) -> Result<Self, PresetError> {
+        match platform {
+            Platform::LinuxX86_64 => Ok(Self::linux_x86_64(info)),
+            Platform::CortexM => Self::cortex_m(info, memory, endian),
+            Platform::WindowsX64 => Ok(Self::windows_x64(info)),
+        }
+    }
+
+    fn register(&mut self, name: &str, value: u64, bits: u32) {
+        let name = Arc::from(name);
+        self.registers.push((PresetReg::Named { name, required: true }, BitVec::from_u64(value, bits as usize)));
+    }
+
+    fn optional_register(&mut self, name: &str, value: u64, bits: u32) {
+        let name = Arc::from(name);
+        self.registers.push((PresetReg::Named { name, required: false }, BitVec::from_u64(value, bits as usize)));
+    }
+
+    fn stack_pointer(&mut self, value: u64, bits: u32) {
+        self.registers.push((PresetReg::StackPointer, BitVec::from_u64(value, bits as usize)));
+    }
+
+    fn write(&mut self, addr: u64, value: u64, bits: u32) {
+        self.memory.push((Addr::from(addr), BitVec::from_u64(value, bits as usize)));
+    }
+
+    fn write_bytes(&mut self, addr: u64, bytes: &[u8]) {
+        for (i, byte) in bytes.iter().enumerate() {
+            self.write(addr.wrapping_add(i as u64), *byte as u64, 8);
+        }
+    }
+
+    /// The state on entry to a userland Linux x86-64 process, per the
+    /// System V ABI: the stack holds argc, argv, envp, and the auxiliary
+    /// vector, with the strings they point to above them; rdx (the
+    /// finaliser registered with atexit) is null.
+    pub fn linux_x86_64(info: &LoaderInfo) -> Self {
+        let mut preset = Self::new(Platform::LinuxX86_64);
+        let top = info.stack_top.unwrap_or(LINUX_STACK_TOP);
+
+        let mut strings = Vec::new();
+        let mut offsets = Vec::new();
+        for string in info.args.iter().chain(info.env.iter()) {
+            offsets.push(strings.len() as u64);
+            strings.extend_from_slice(string.as_bytes());
+            strings.push(0);
+        }
+        let strings_addr = top.wrapping_sub(strings.len() as u64) & !0xf;
+
+        let mut words = vec![info.args.len() as u64];
+        let (args, env) = offsets.split_at(info.args.len());
+        words.extend(args.iter().map(|offset| strings_addr + offset));
+        words.push(0);
+        words.extend(env.iter().map(|offset| strings_addr + offset));
+        words.push(0);
+        words.extend([AT_PAGESZ, 0x1000]);
+        if let Some(entry) = info.entry {
+            words.extend([AT_ENTRY, entry]);
+        }
+        words.extend([AT_NULL, 0]);
+
+        let sp = strings_addr.wrapping_sub(8 * words.len() as u64) & !0xf;
+        for (i, word) in words.into_iter().enumerate() {
+            preset.write(sp + 8 * i as u64, word, 64);
+        }
+        preset.write_bytes(strings_addr, &strings);
+
+        preset.stack_pointer(sp, 64);
+        preset.register("RDX", 0, 64);
+        preset.register("RBP", 0, 64);
+        preset.optional_register("DF", 0, 8);
+
+        if let Some(entry) = info.entry {
+            preset.pc = Some(Addr::from(entry));
+            preset.register("RIP", entry, 64);
+        }
+
+        preset
+    }
+
+    /// The reset state of an ARM Cortex-M core: the stack pointer and
+    /// program counter are read from the first two entries of the vector
+    /// table, and the link register holds the reset value 0xffffffff.
+    ///
+    /// Cortex-M cores only execute Thumb code; as the mode is part of the
+    /// disassembly context, rather than a register, it is not set.
+    pub fn cortex_m(info: &LoaderInfo, memory: &Mem, endian: Endian) -> Result<Self, PresetError> {
+        let mut preset = Self::new(Platform::CortexM);
+        let table = info.vector_table.unwrap_or(0);
+
+        let state = State::with_memory(memory, endian);
+        let read = |offset: u64| {
+            let addr = Addr::from(table.wrapping_add(offset)).as_bits(32);
+            state.read(&addr, 32)
+                .ok()
+                .and_then(|value| value.to_u64())
+                .ok_or(PresetError::VectorTable(addr))
+        };
+
+        let sp = read(0)?;
+        let reset = read(4)? & !1;
+
+        preset.stack_pointer(sp, 32);
+        preset.register("lr", 0xffff_ffff, 32);
+        preset.register("pc", reset, 32);
+        preset.pc = Some(Addr::from(reset).as_bits(32));
+
+        Ok(preset)
+    }
+
+    /// The state on entry to a Windows x64 process: gs points to a stub of
+    /// the thread environment block (whose self, stack, and PEB pointers
+    /// are set), which points to a stub of the process environment block
+    /// (whose image base is set, and which is not being debugged); rcx
+    /// holds the address of the PEB, and the stack is aligned as if the
+    /// entry point were called, with shadow space for its arguments.
+    pub fn windows_x64(info: &LoaderInfo) -> Self {
+        let mut preset = Self::new(Platform::WindowsX64);
+
+        let top = info.stack_top.unwrap_or(WINDOWS_STACK_TOP);
+        let limit = top.saturating_sub(info.stack_size.unwrap_or(WINDOWS_STACK_SIZE));
+        let teb = info.teb.unwrap_or(WINDOWS_TEB);
+        let peb = info.peb.unwrap_or(WINDOWS_PEB);
+
+        // NT_TIB: StackBase, StackLimit, and Self
+        preset.write(teb + 0x08, top, 64);
+        preset.write(teb + 0x10, limit, 64);
+        preset.write(teb + 0x30, teb, 64);
+        // ProcessEnvironmentBlock
+        preset.write(teb + 0x60, peb, 64);
+
+        // BeingDebugged and ImageBaseAddress
+        preset.write(peb + 0x02, 0, 8);
+        preset.write(peb + 0x10, info.image_base.unwrap_or(0), 64);
+
+        // the return address, below 32 bytes of shadow space
+        let sp = (top.wrapping_sub(0x20) & !0xf).wrapping_sub(8);
+        preset.write(sp, 0, 64);
+        preset.stack_pointer(sp, 64);
+
+        preset.register("RCX", peb, 64);
+        preset.optional_register("GS_OFFSET", teb, 64);
+        preset.optional_register("DF", 0, 8);
+
+        if let Some(entry) = info.entry {
+            preset.pc = Some(Addr::from(entry));
+            preset.register("RIP", entry, 64);
+        }
+
+        preset
+    }
+
+    pub fn platform(&self) -> Platform {
+        self.platform
+    }
+
+    /// The address execution starts from, if known.
+    pub fn pc(&self) -> Option<&Addr> {
+        self.pc.as_ref()
+    }
+
+    /// The memory written by the preset, as (address, value) pairs.
+    pub fn memory(&self) -> impl Iterator<Item = (&Addr, &BitVec)> {
+        self.memory.iter().map(|(addr, value)| (addr, value))
+    }
+
+    fn resolve(&self, lifter: &Lifter) -> Result<Vec<(Var, BitVec)>, PresetError> {
+        let mut registers = Vec::new();
+        for (reg, value) in self.registers.iter() {
+            let var = match reg {
+                PresetReg::StackPointer => Some(lifter.stack_pointer().ok_or(PresetError::NoStackPointer)?),
+                PresetReg::Named { name, required } => match lifter.register(name) {
+                    Some(var) => Some(var),
+                    None if *required => return Err(PresetError::UnknownRegister(name.clone())),
+                    None => None,
+                },
+            };
+            if let Some(var) = var {
+                registers.push((var, value.clone()));
+            }
+        }
+        Ok(registers)
+    }
+
+    /// Sets the registers (as named by `lifter`) and writes the memory of
+    /// the preset in `state`.
+    pub fn apply(&self, lifter: &Lifter, state: &mut State) -> Result<(), PresetError> {
+        for (var, value) in self.resolve(lifter)? {
+            state.set_var(var, value);
+        }
+        for (addr, value) in self.memory.iter() {
+            state.write(&addr.as_bits(lifter.architecture().bits() as u32), value)?;
+        }
+        Ok(())
+    }
+
+    /// Binds the registers (as named by `lifter`) of the preset in `state`,
+    /// and records its memory as stores.
+    pub fn apply_symbolic(&self, lifter: &Lifter, state: &mut SymState) -> Result<(), PresetError> {
+        for (var, value) in self.resolve(lifter)? {
+            state.bind(var, value);
+        }
+        for (addr, value) in self.memory.iter() {
+            let addr = addr.as_bits(lifter.architecture().bits() as u32);
+            state.store(BitVec::from(addr), value.clone(), value.bits() as u32);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::ir::{Addr, BitVec, Blk, Def, Expr, Mem, Phi, Var};
+use crate::prelude::{Endian, Id};
+
+#[derive(Debug, Error)]
+pub enum EmuError {
+    #[error("variable {0} is not assigned")]
+    Unassigned(Var),
+    #[error("address {0} is not mapped")]
+    Unmapped(Addr),
+    #[error("expression {0:?} cannot be evaluated concretely")]
+    Unsupported(Expr),
+    #[error("expression {0:?} has an undefined value (e.g., division by zero)")]
+    Undefined(Expr),
+    #[error("assumption {0} does not hold")]
+    Assumption(Id<Def>),
+    #[error("no choice of phi {0} holds")]
+    Phi(Id<Phi>),
+    #[error("Blk {0} is not known")]
+    UnknownBlk(Id<Blk>),
+    #[error("no statement to execute")]
+    Halted,
+    #[error("intrinsic {0} cannot be emulated")]
+    Intrinsic(Arc<str>),
+    #[error("no stub emulates system call {0}")]
+    Syscall(u64),
+}
+
+/// Evaluates an intrinsic expression of the given size in bits, given the
+/// values of its arguments; returns None if it cannot be evaluated.
+pub type IntrinsicFn<'a> = Arc<dyn Fn(&[BitVec], u32) -> Option<BitVec> + Send + Sync + 'a>;
+
+/// The concrete state of an emulated program: the values of its variables,
+/// and the bytes of its memory.
+///
+/// Reads of memory are performed on the bytes written by the program,
+/// falling back to the Regions of an underlying Mem (which is never
+/// modified). All loads and stores are performed on the same memory,
+/// irrespective of the memory variable they name.
+#[derive(Clone)]
+pub struct State<'a, 'r> {
+    endian: Endian,
+    memory: Option<&'a Mem<'r>>,
+    vars: BTreeMap<Var, BitVec>,
+    writes: BTreeMap<Addr, u8>,
+    intrinsics: BTreeMap<Arc<str>, IntrinsicFn<'a>>,
+}
+
+impl<'a, 'r> State<'a, 'r> {
+    /// A state without underlying memory, whose multi-byte values are laid
+    /// out in memory according to `endian`.
+    pub fn new(endian: Endian) -> Self {
+        Self {
+            endian,
+            memory: None,
+            vars: BTreeMap::new(),
+            writes: BTreeMap::new(),
+            intrinsics: BTreeMap::new(),
+        }
+    }
+
+    /// A state whose memory is initially that of `memory`.
+    pub fn with_memory(memory: &'a Mem<'r>, endian: Endian) -> Self {
+        Self {
+            memory: Some(memory),
+            ..Self::new(endian)
+        }
+    }
+
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    pub fn var(&self, var: &Var) -> Option<&BitVec> {
+        self.vars.get(var)
+    }
+
+    /// The value of the variable named `name` (of any generation).
+    pub fn var_by_name(&self, name: &str) -> Option<(&Var, &BitVec)> {
+        self.vars.iter().find(|(var, _)| &**var.name() == name)
+    }
+
+    pub fn set_var(&mut self, var: impl Into<Var>, value: BitVec) -> Option<BitVec> {
+        self.vars.insert(var.into(), value)
+    }
+
+    pub fn vars(&self) -> impl Iterator<Item = (&Var, &BitVec)> {
+        self.vars.iter()
+    }
+
+    /// Evaluates intrinsic expressions named `name` using `f`; other
+    /// intrinsics cannot be evaluated.
+    pub fn set_intrinsic<F>(&mut self, name: impl Into<Arc<str>>, f: F)
+    where
+        F: Fn(&[BitVec], u32) -> Option<BitVec> + Send + Sync + 'a,
+    {
+        self.intrinsics.insert(name.into(), Arc::new(f));
+    }
+
+    pub(crate) fn intrinsic(&self, name: &str) -> Option<&IntrinsicFn<'a>> {
+        self.intrinsics.get(name)
+    }
+
+    /// The bytes written to memory by the program.
+    pub fn writes(&self) -> impl Iterator<Item = (&Addr, u8)> {
+        self.writes.iter().map(|(addr, byte)| (addr, *byte))
+    }
+
+    pub fn read_byte(&self, addr: &Addr) -> Result<u8, EmuError> {
+        if let Some(byte) = self.writes.get(addr) {
+            return Ok(*byte)
+        }
+
+        self.memory
+            .and_then(|memory| memory.read_bytes(addr, 1).ok().flatten())
+            .map(|bytes| bytes[0])
+            .ok_or_else(|| EmuError::Unmapped(addr.clone()))
+    }
+
+    /// The bytes at `addr` may be executed, i.e., they are not within a
+    /// region of the underlying memory that is not executable.
+    pub fn is_executable(&self, addr: &Addr) -> bool {
+        self.memory
+            .and_then(|memory| memory.find_region(addr))
+            .map(|region| region.permissions().is_executable())
+            .unwrap_or(true)
+    }
+
+    pub fn read_bytes(&self, addr: &Addr, count: usize) -> Result<Vec<u8>, EmuError> {
+        (0..count).map(|i| self.read_byte(&(addr + i))).collect()
+    }
+
+    pub fn write_bytes(&mut self, addr: &Addr, bytes: &[u8]) {
+        for (i, byte) in bytes.iter().enumerate() {
+            self.writes.insert(addr + i, *byte);
+        }
+    }
+
+    /// Reads a value of `bits` bits from `addr`; see `Region::read_bits`
+    /// for the layout of values whose sizes are not multiples of eight.
+    pub fn read(&self, addr: &Addr, bits: u32) -> Result<BitVec, EmuError> {
+        let aligned = bits % 8 == 0;
+        let count = (bits / 8 + if aligned { 0 } else { 1 }) as usize;
+        let bytes = self.read_bytes(addr, count)?;
+
+        let bv = if self.endian.is_little() {
+            BitVec::from_le_bytes(&bytes).unsigned()
+        } else {
+            BitVec::from_be_bytes(&bytes).unsigned()
+        };
+
+        Ok(if aligned {
+            bv
+        } else if self.endian.is_little() {
+            bv.cast(bits as usize)
+        } else {
+            (bv >> (8 - (bits % 8))).cast(bits as usize)
+        })
+    }
+
+    /// Writes `value` to `addr`; see `Region::write_bits` for the layout
+    /// of values whose sizes are not multiples of eight.
+    pub fn write(&mut self, addr: &Addr, value: &BitVec) -> Result<(), EmuError> {
+        let bits = value.bits() as u32;
+        let aligned = bits % 8 == 0;
+        let count = (bits / 8 + if aligned { 0 } else { 1 }) as usize;
+        let mut bytes = vec![0u8; count];
+
+        if aligned {
+            if self.endian.is_little() {
+                value.to_le_bytes(&mut bytes)
+            } else {
+                value.to_be_bytes(&mut bytes)
+            }
+        } else {
+            // preserve the bits of the bytes written not covered by value
+            let nbits = count * 8;
+            let shift = 8 - (bits % 8);
+            let orig = self.read(addr, nbits as u32)?;
+
+            if self.endian.is_little() {
+                let mask = BitVec::max_value_with(nbits, false) >> shift;
+                let bv = (value.unsigned_cast(nbits) & &mask) | (orig & !&mask);
+                bv.to_le_bytes(&mut bytes)
+            } else {
+                let mask = BitVec::max_value_with(nbits, false) << shift;
+                let bv = ((value.unsigned_cast(nbits) << shift) & &mask) | (orig & !&mask);
+                bv.to_be_bytes(&mut bytes)
+            }
+        }
+
+        self.write_bytes(addr, &bytes);
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+use thiserror::Error;
+
+use crate::ir::{Addr, Confidence, Jmp, Loc, Mem, Sub};
+use crate::lift::{Lifter, LifterError};
+
+#[derive(Debug, Error)]
+pub enum AsmError {
+    #[error("assembly cannot be emitted for {0} languages")]
+    UnsupportedArch(String),
+    #[error("instruction at {0} is not mapped")]
+    Unmapped(Addr),
+    #[error("instruction at {0} overlaps the instruction preceding it")]
+    Overlap(Addr),
+    #[error("cannot disassemble instruction at {0}: {1}")]
+    Disassembly(Addr, #[source] LifterError),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Dialect {
+    X86,
+    AArch64,
+}
+
+impl Dialect {
+    fn new(lifter: &Lifter) -> Result<Self, AsmError> {
+        let arch = lifter.architecture();
+        match arch.processor().to_ascii_uppercase().as_str() {
+            "X86" => Ok(Self::X86),
+            "AARCH64" => Ok(Self::AArch64),
+            processor => Err(AsmError::UnsupportedArch(processor.to_owned())),
+        }
+    }
+
+    fn directives(&self) -> &'static [&'static str] {
+        match self {
+            // SLEIGH formats x86 operands in Intel syntax
+            Self::X86 => &[".intel_syntax noprefix", ".text"],
+            Self::AArch64 => &[".text"],
+        }
+    }
+
+    fn comment(&self) -> &'static str {
+        match self {
+            Self::X86 => "#",
+            Self::AArch64 => "//",
+        }
+    }
+}
+
+/// Re-emits the instructions of Subs as assembly (in the syntax accepted
+/// by the GNU assembler), for reassembly after patching; x86 (32 and
+/// 64-bit) and AArch64 languages are supported.
+///
+/// Instructions are taken to start at the addresses of the Sub's Blks, and
+/// are disassembled from memory and emitted in address order; any bytes
+/// between instructions are emitted as data, so that the reassembled
+/// code has the same layout as the original. The Sub is labelled by its
+/// symbol (or `sub_<addr>`), instructions that are the targets of flows
+/// within the Sub are labelled `.L_<addr>`, and flow targets are replaced
+/// by their labels or known symbols. If the confidence of the Sub's start
+/// is known, it is noted in a comment following its label; notes on
+/// instructions (see `note`) are emitted as comments following them.
+pub struct AsmExport<'a, 'r> {
+    lifter: &'a Lifter,
+    memory: &'a Mem<'r>,
+    symbols: BTreeMap<Addr, String>,
+    confidences: BTreeMap<Addr, Confidence>,
+    notes: BTreeMap<Addr, Vec<String>>,
+}
+
+// a disassembled instruction
+struct Insn {
+    mnemonic: String,
+    operands: String,
+    length: usize,
+    targets: BTreeSet<Addr>,
+}
+
+impl<'a, 'r> AsmExport<'a, 'r> {
+    pub fn new(lifter: &'a Lifter, memory: &'a Mem<'r>) -> Self {
+        Self {
+            lifter,
+            memory,
+            symbols: BTreeMap::new(),
+            confidences: BTreeMap::new(),
+            notes: BTreeMap::new(),
+        }
+    }
+
+    /// Use `name` as the label of `addr`, e.g., when it is the target of
+    /// a call.
+    pub fn symbol(&mut self, addr: impl Into<Addr>, name: impl Into<String>) -> &mut Self {
+        self.symbols.insert(addr.into(), name.into());
+        self
+    }
+
+    pub fn symbols(&mut self, symbols: impl IntoIterator<Item = (Addr, String)>) -> &mut Self {
+        self.symbols.extend(symbols);
+        self
+    }
+
+    /// Notes that a Sub starting at `addr` is trusted with `confidence`.
+    pub fn confidence(&mut self, addr: impl Into<Addr>, confidence: Confidence) -> &mut Self {
+        self.confidences.insert(addr.into(), confidence);
+        self
+    }
+
+    pub fn confidences(&mut self, confidences: impl IntoIterator<Item = (Addr, Confidence)>) -> &mut Self {
+        self.confidences.extend(confidences);
+        self
+    }
+
+    // the targets of the flows of each instruction of sub
+    /// Notes `note` in a comment following the instruction at `addr`, e.g.,
+    /// what its immediates denote.
+    pub fn note(&mut self, addr: impl Into<Addr>, note: impl Into<String>) -> &mut Self {
+        let notes = self.notes.entry(addr.into()).or_default();
+        let note = note.into();
+        if !notes.contains(&note) {
+            notes.push(note);
+        }
+        self
+    }
+
+    pub fn notes(&mut self, notes: impl IntoIterator<Item = (Addr, String)>) -> &mut Self {
+        for (addr, note) in notes {
+            self.note(addr, note);
+        }
+        self
+    }
+
+    fn targets(sub: &Sub) -> BTreeMap<Addr, BTreeSet<Addr>> {
+        let mut targets = BTreeMap::<Addr, BTreeSet<Addr>>::new();
+        let mut current = None;
+
+        // the Blks of each instruction follow its first Blk, which is
+        // given its address when lifted
+        for blk in sub.blks() {
+            if let Some(addr) = blk.addr() {
+                current = Some(addr.clone());
+            }
+
+            let addr = if let Some(ref addr) = current {
+                addr
+            } else {
+                continue
+            };
+
+            for jmp in blk.jmps() {
+                let loc = match **jmp {
+                    Jmp::Branch(ref loc) | Jmp::CBranch(ref loc, _) | Jmp::Call(ref loc, _) => loc,
+                    _ => continue,
+                };
+
+                let target = match loc {
+                    Loc::Fixed(target) => Some(target.clone()),
+                    Loc::Resolved(id) => sub.blk(*id).and_then(|blk| blk.addr().cloned()),
+                    Loc::Computed(_) => None,
+                };
+
+                if let Some(target) = target.filter(|target| target != addr) {
+                    targets.entry(addr.clone()).or_default().insert(target);
+                }
+            }
+        }
+
+        targets
+    }
+
+    fn disassemble(&self, addr: &Addr, targets: BTreeSet<Addr>) -> Result<Insn, AsmError> {
+        let bytes = self
+            .memory
+            .read_bytes_from(addr)
+            .ok()
+            .flatten()
+            .ok_or_else(|| AsmError::Unmapped(addr.clone()))?;
+
+        let mut ctxt = self.lifter.context();
+        let (mnemonic, operands, length) = self
+            .lifter
+            .disassemble_insn(&mut ctxt, addr, &bytes)
+            .map_err(|e| AsmError::Disassembly(addr.clone(), e))?;
+
+        Ok(Insn {
+            mnemonic: mnemonic.to_lowercase(),
+            operands: operands.to_lowercase(),
+            length,
+            targets,
+        })
+    }
+
+    // replaces each hexadecimal literal in operands that is a target with
+    // the target's label
+    fn symbolise(operands: &str, targets: &BTreeSet<Addr>, labels: &BTreeMap<Addr, String>) -> String {
+        let mut output = String::with_capacity(operands.len());
+        let mut rest = operands;
+
+        while let Some(start) = rest.find("0x") {
+            let (before, literal) = rest.split_at(start);
+            let end = literal[2..]
+                .find(|c: char| !c.is_ascii_hexdigit())
+                .map(|end| end + 2)
+                .unwrap_or(literal.len());
+
+            output.push_str(before);
+
+            let label = u64::from_str_radix(&literal[2..end], 16)
+                .ok()
+                .map(Addr::from)
+                .filter(|addr| targets.contains(addr))
+                .and_then(|addr| labels.get(&addr));
+
+            if let Some(label) = label.filter(|_| !before.ends_with('#')) {
+                output.push_str(label);
+            } else {
+                output.push_str(&literal[..end]);
+            }
+
+            rest = &literal[end..];
+        }
+
+        output.push_str(rest);
+        output
+    }
+
+    fn bytes(&self, asm: &mut String, addr: &Addr, count: usize) -> Result<(), AsmError> {
+        let bytes = self
+            .memory
+            .read_bytes(addr, count)
+            .ok()
+            .flatten()
+            .map(|bytes| bytes.into_owned())
+            .ok_or_else(|| AsmError::Unmapped(addr.clone()))?;
+
+        for chunk in bytes.chunks(16) {
+            let bytes = chunk
+                .iter()
+                .map(|b| format!("0x{:02x}", b))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let _ = writeln!(asm, "    .byte {}", bytes);
+        }
+
+        Ok(())
+    }
+
+    pub fn emit(&self, sub: &Sub) -> Result<String, AsmError> {
+        let dialect = Dialect::new(self.lifter)?;
+        let mut targets = Self::targets(sub);
+
+        let mut insns = BTreeMap::new();
+        for addr in sub.blks().iter().filter_map(|blk| blk.addr()) {
+            if !insns.contains_key(addr) {
+                let insn = self.disassemble(addr, targets.remove(addr).unwrap_or_default())?;
+                insns.insert(addr.clone(), insn);
+            }
+        }
+
+        let entry = sub
+            .addr()
+            .cloned()
+            .or_else(|| insns.keys().next().cloned());
+
+        let name = sub
+            .symbol()
+            .map(|symbol| symbol.to_string())
+            .or_else(|| entry.as_ref().and_then(|addr| self.symbols.get(addr).cloned()))
+            .or_else(|| entry.as_ref().map(|addr| format!("sub_{:x}", u64::try_from(addr).unwrap_or(0))))
+            .unwrap_or_else(|| "sub".to_owned());
+
+        // labels for the targets of flows
+        let mut labels = self.symbols.clone();
+        for target in insns.values().flat_map(|insn| insn.targets.iter()) {
+            if insns.contains_key(target) && !labels.contains_key(target) {
+                let offset = u64::try_from(target).unwrap_or(0);
+                labels.insert(target.clone(), format!(".L_{:x}", offset));
+            }
+        }
+        if let Some(entry) = entry.as_ref() {
+            labels.insert(entry.clone(), name.clone());
+        }
+
+        let mut asm = String::new();
+
+        for directive in dialect.directives() {
+            let _ = writeln!(asm, "    {}", directive);
+        }
+        let _ = writeln!(asm, "    .globl {}", name);
+
+        let mut next: Option<Addr> = None;
+        for (addr, insn) in insns.iter() {
+            if let Some(ref next) = next {
+                if next > addr {
+                    return Err(AsmError::Overlap(addr.clone()))
+                }
+                if let Some(gap) = addr.absolute_difference(next).filter(|gap| *gap > 0) {
+                    self.bytes(&mut asm, next, gap)?;
+                }
+            }
+
+            if let Some(label) = labels.get(addr).filter(|_| {
+                Some(addr) == entry.as_ref() || insns.values().any(|insn| insn.targets.contains(addr))
+            }) {
+                let _ = writeln!(asm, "{}:", label);
+
+                if Some(addr) == entry.as_ref() {
+                    if let Some(confidence) = self.confidences.get(addr) {
+                        let _ = writeln!(asm, "    {} confidence: {}", dialect.comment(), confidence);
+                    }
+                }
+            }
+
+            let operands = Self::symbolise(&insn.operands, &insn.targets, &labels);
+            if operands.is_empty() {
+                let _ = write!(asm, "    {}", insn.mnemonic);
+            } else {
+                let _ = write!(asm, "    {} {}", insn.mnemonic, operands);
+            }
+
+            if let Some(notes) = self.notes.get(addr) {
+                let _ = write!(asm, " {} {}", dialect.comment(), notes.join(", "));
+            }
+            asm.push('\n');
+
+            next = Some(addr + insn.length);
+        }
+
+        Ok(asm)
+    }
+}
@@ -0,0 +1,426 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::ir::{Addr, BinOp, BinRel, BitVec, Blk, Cast, Def, Expr, Jmp, Loc, Sub, UnOp, Var};
+use crate::prelude::{Endian, Entity, Id, Identifiable};
+
+/// Exports Subs as BAP's intermediate representation (BIR), in the ADT
+/// format read by BAP's tooling (e.g., `bap --read-program` and the
+/// Python bindings), so that pipelines built on BAP can use our lifter.
+///
+/// Each Sub becomes a `Sub`, each Blk a `Blk`, and each Def and Jmp a
+/// `Def` or `Jmp`; terms are given fresh Tids (numbered from 1 within a
+/// program, and named `%<n>` or `@<symbol>`), and addresses are recorded
+/// as `address` attributes. As BIR Blks end with explicit gotos, calls
+/// followed by branches become calls returning to the branch targets.
+///
+/// BIR has no counterparts to some of our IR, which is mapped as follows:
+///
+/// - phis become Defs of nested `Ite`s of their choices, as our phis are
+///   gated by conditions rather than by predecessors;
+/// - intrinsic expressions, floating-point operations, and the flag
+///   relations (e.g., `carry`) become `Unknown` expressions named after
+///   them;
+/// - intrinsic Jmps become gotos to `Unknown` targets named after them,
+///   with an `intrinsic` attribute;
+/// - fixed targets become `Indirect` targets of constant addresses,
+///   unless they are the addresses of Blks of the same Sub.
+#[derive(Debug, Clone)]
+pub struct BirExport {
+    addr_bits: u32,
+    endian: Endian,
+}
+
+impl Default for BirExport {
+    fn default() -> Self {
+        Self {
+            addr_bits: 64,
+            endian: Endian::Little,
+        }
+    }
+}
+
+// the Tids assigned to the terms of a program
+struct Tids {
+    next: u64,
+    blks: BTreeMap<Id<Blk>, u64>,
+    addrs: BTreeMap<Addr, u64>,
+}
+
+impl Tids {
+    fn fresh(&mut self) -> u64 {
+        self.next += 1;
+        self.next
+    }
+
+    fn tid(id: u64) -> String {
+        format!("Tid({}, \"%{:08x}\")", id, id)
+    }
+}
+
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn attrs(addr: Option<&Addr>) -> String {
+    match addr {
+        Some(addr) => format!("Attrs([Attr(\"address\", \"0x{}\")])", addr),
+        None => "Attrs([])".to_owned(),
+    }
+}
+
+fn int(bv: &BitVec) -> String {
+    match bv.to_u64() {
+        Some(value) => format!("Int({}, {})", value, bv.bits()),
+        None => format!("Int(0x{:x}, {})", bv, bv.bits()),
+    }
+}
+
+fn unknown(name: &str, bits: u32) -> String {
+    format!("Unknown({}, Imm({}))", quote(name), bits)
+}
+
+impl BirExport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The size of addresses, i.e., of the indices of memories; 64 by
+    /// default.
+    pub fn addr_bits(&mut self, bits: u32) -> &mut Self {
+        self.addr_bits = bits;
+        self
+    }
+
+    /// The byte order of loads and stores; little-endian by default.
+    pub fn endian(&mut self, endian: Endian) -> &mut Self {
+        self.endian = endian;
+        self
+    }
+
+    /// A `Program` holding `subs`.
+    pub fn program<'a>(&self, subs: impl IntoIterator<Item = &'a Entity<Sub>>) -> String {
+        let mut tids = Tids {
+            next: 0,
+            blks: BTreeMap::new(),
+            addrs: BTreeMap::new(),
+        };
+
+        let program = tids.fresh();
+        let subs = subs
+            .into_iter()
+            .map(|sub| self.write_sub(&mut tids, sub))
+            .collect::<Vec<_>>();
+
+        format!("Program({}, Attrs([]), Subs([{}]))", Tids::tid(program), subs.join(", "))
+    }
+
+    /// A `Sub`; Tids are numbered from 1 within it.
+    pub fn sub(&self, sub: &Entity<Sub>) -> String {
+        let mut tids = Tids {
+            next: 0,
+            blks: BTreeMap::new(),
+            addrs: BTreeMap::new(),
+        };
+        self.write_sub(&mut tids, sub)
+    }
+
+    fn write_sub(&self, tids: &mut Tids, sub: &Sub) -> String {
+        let id = tids.fresh();
+        let name = match (sub.symbol(), sub.addr()) {
+            (Some(symbol), _) => symbol.to_string(),
+            (None, Some(addr)) => format!("sub_{}", addr),
+            (None, None) => format!("sub_{}", id),
+        };
+
+        // Blks are assigned Tids up-front, as they are referenced by the
+        // Jmps of the Blks preceding them
+        tids.blks.clear();
+        tids.addrs.clear();
+        for blk in sub.blks() {
+            let tid = tids.fresh();
+            tids.blks.insert(blk.id(), tid);
+            if let Some(addr) = blk.addr() {
+                tids.addrs.entry(addr.clone()).or_insert(tid);
+            }
+        }
+
+        let blks = sub
+            .blks()
+            .iter()
+            .map(|blk| self.write_blk(tids, blk))
+            .collect::<Vec<_>>();
+
+        format!(
+            "Sub(Tid({}, {}), {}, {}, Args([]), Blks([{}]))",
+            id,
+            quote(&format!("@{}", name)),
+            attrs(sub.addr()),
+            quote(&name),
+            blks.join(", "),
+        )
+    }
+
+    fn write_blk(&self, tids: &mut Tids, blk: &Entity<Blk>) -> String {
+        // unwrap is safe here: the Tids of all Blks of the Sub are assigned
+        // before any are written
+        let id = *tids.blks.get(&blk.id()).unwrap();
+
+        let mut defs = Vec::new();
+        for phi in blk.phis() {
+            let value = phi
+                .choices()
+                .iter()
+                .rev()
+                .fold(None, |otherwise: Option<String>, (cond, expr)| {
+                    Some(match otherwise {
+                        None => self.expr(expr),
+                        Some(otherwise) => format!("Ite({}, {}, {})", self.expr(cond), self.expr(expr), otherwise),
+                    })
+                })
+                .unwrap_or_else(|| unknown("phi", phi.var().bits().unwrap_or(0)));
+
+            let tid = tids.fresh();
+            defs.push(format!("Def({}, Attrs([]), {}, {})", Tids::tid(tid), self.var(phi.var()), value));
+        }
+
+        for def in blk.defs() {
+            let tid = tids.fresh();
+            defs.push(format!("Def({}, {}, {})", Tids::tid(tid), attrs(blk.addr()), self.def(def)));
+        }
+
+        let mut jmps = Vec::new();
+        let mut pending = blk.jmps().iter().peekable();
+        while let Some(jmp) = pending.next() {
+            let tid = tids.fresh();
+            let jmp = match **jmp {
+                Jmp::Call(ref loc, _) => {
+                    // calls followed by branches return to their targets
+                    let ret = match pending.peek().map(|next| &***next) {
+                        Some(Jmp::Branch(ret)) => {
+                            let ret = self.label(tids, ret);
+                            pending.next();
+                            format!("Some({})", ret)
+                        }
+                        _ => "None()".to_owned(),
+                    };
+                    format!(
+                        "Call({}, {}, Int(1, 1), Calls({}, {}))",
+                        Tids::tid(tid),
+                        attrs(blk.addr()),
+                        self.label(tids, loc),
+                        ret,
+                    )
+                }
+                Jmp::Branch(ref loc) => format!(
+                    "Goto({}, {}, Int(1, 1), {})",
+                    Tids::tid(tid),
+                    attrs(blk.addr()),
+                    self.label(tids, loc),
+                ),
+                Jmp::CBranch(ref loc, ref cond) => format!(
+                    "Goto({}, {}, {}, {})",
+                    Tids::tid(tid),
+                    attrs(blk.addr()),
+                    self.expr(cond),
+                    self.label(tids, loc),
+                ),
+                Jmp::Return(ref loc) => format!(
+                    "Ret({}, {}, Int(1, 1), {})",
+                    Tids::tid(tid),
+                    attrs(blk.addr()),
+                    self.label(tids, loc),
+                ),
+                Jmp::Intrinsic(ref name, _) => format!(
+                    "Goto({}, Attrs([Attr(\"intrinsic\", {})]), Int(1, 1), Indirect({}))",
+                    Tids::tid(tid),
+                    quote(name),
+                    unknown(name, self.addr_bits),
+                ),
+            };
+            jmps.push(jmp);
+        }
+
+        format!(
+            "Blk({}, {}, Phis([]), Defs([{}]), Jmps([{}]))",
+            Tids::tid(id),
+            attrs(blk.addr()),
+            defs.join(", "),
+            jmps.join(", "),
+        )
+    }
+
+    fn label(&self, tids: &Tids, loc: &Loc) -> String {
+        match loc {
+            Loc::Resolved(id) => match tids.blks.get(id) {
+                Some(tid) => format!("Direct({})", Tids::tid(*tid)),
+                None => format!("Indirect({})", unknown("blk", self.addr_bits)),
+            },
+            Loc::Fixed(addr) => match tids.addrs.get(addr) {
+                Some(tid) => format!("Direct({})", Tids::tid(*tid)),
+                None => format!("Indirect(Int(0x{}, {}))", addr, self.addr_bits),
+            },
+            Loc::Computed(expr) => format!("Indirect({})", self.expr(expr)),
+        }
+    }
+
+    fn endianness(&self) -> &'static str {
+        match self.endian {
+            Endian::Big => "BigEndian()",
+            Endian::Little => "LittleEndian()",
+        }
+    }
+
+    pub fn var(&self, var: &Var) -> String {
+        let name = if var.generation() == 0 {
+            var.name().to_string()
+        } else {
+            format!("{}.{}", var.name(), var.generation())
+        };
+
+        let typ = match var.bits() {
+            Some(bits) => format!("Imm({})", bits),
+            None => format!("Mem({}, 8)", self.addr_bits),
+        };
+
+        format!("Var({}, {})", quote(&name), typ)
+    }
+
+    fn def(&self, def: &Def) -> String {
+        match def {
+            Def::Assign(var, expr) => format!("{}, {}", self.var(var), self.expr(expr)),
+            // BIR has no assumptions; they are kept as assignments to a
+            // variable named after them
+            Def::Assume(expr) => format!("Var(\"assume\", Imm(1)), {}", self.expr(expr)),
+            Def::Store(addr, val, bits, mem) => format!(
+                "{}, Store({}, {}, {}, {}, {})",
+                self.var(mem),
+                self.var(mem),
+                self.expr(addr),
+                self.expr(val),
+                self.endianness(),
+                bits,
+            ),
+        }
+    }
+
+    /// A BIL expression.
+    pub fn expr(&self, expr: &Expr) -> String {
+        let mut s = String::new();
+        self.write_expr(&mut s, expr);
+        s
+    }
+
+    fn write_expr(&self, s: &mut String, expr: &Expr) {
+        // writes to strings are infallible
+        let _ = match expr {
+            Expr::UnOp(UnOp::Not, expr) => write!(s, "NOT({})", self.expr(expr)),
+            Expr::UnOp(UnOp::Neg, expr) => write!(s, "NEG({})", self.expr(expr)),
+            Expr::UnOp(op, expr) => {
+                write!(s, "{}", unknown(&format!("{:?}", op).to_lowercase(), expr.bits()))
+            }
+            Expr::UnRel(op, _) => write!(s, "{}", unknown(&format!("{:?}", op).to_lowercase(), 1)),
+            Expr::BinOp(op, lexpr, rexpr) => {
+                let name = match op {
+                    BinOp::And => "AND",
+                    BinOp::Or => "OR",
+                    BinOp::Xor => "XOR",
+                    BinOp::Add => "PLUS",
+                    BinOp::Sub => "MINUS",
+                    BinOp::Div => "DIVIDE",
+                    BinOp::SDiv => "SDIVIDE",
+                    BinOp::Mul => "TIMES",
+                    BinOp::Rem => "MOD",
+                    BinOp::SRem => "SMOD",
+                    BinOp::Shl => "LSHIFT",
+                    BinOp::Sar => "ARSHIFT",
+                    BinOp::Shr => "RSHIFT",
+                };
+                write!(s, "{}({}, {})", name, self.expr(lexpr), self.expr(rexpr))
+            }
+            Expr::BinRel(op, lexpr, rexpr) => {
+                let name = match op {
+                    BinRel::Eq => "EQ",
+                    BinRel::Neq => "NEQ",
+                    BinRel::Lt => "LT",
+                    BinRel::Le => "LE",
+                    BinRel::SLt => "SLT",
+                    BinRel::SLe => "SLE",
+                    BinRel::SBorrow | BinRel::Carry | BinRel::SCarry => {
+                        let name = format!("{:?}", op).to_lowercase();
+                        return s.push_str(&unknown(&name, 1))
+                    }
+                };
+                write!(s, "{}({}, {})", name, self.expr(lexpr), self.expr(rexpr))
+            }
+            Expr::Cast(inner, cast) => match cast {
+                Cast::Bool => write!(s, "NEQ({}, Int(0, {}))", self.expr(inner), inner.bits()),
+                Cast::Float(bits) => write!(s, "{}", unknown("float", *bits)),
+                Cast::Signed(bits) => write!(s, "SIGNED({}, {})", bits, self.expr(inner)),
+                Cast::Unsigned(bits) => write!(s, "UNSIGNED({}, {})", bits, self.expr(inner)),
+                Cast::High(bits) => write!(s, "HIGH({}, {})", bits, self.expr(inner)),
+                Cast::Low(bits) => write!(s, "LOW({}, {})", bits, self.expr(inner)),
+            },
+            Expr::Load(addr, bits, mem) => write!(
+                s,
+                "Load({}, {}, {}, {})",
+                self.var(mem),
+                self.expr(addr),
+                self.endianness(),
+                bits,
+            ),
+            // our bounds are [lsb, msb), BIL's are [lo, hi]
+            Expr::Extract(expr, lsb, msb) => {
+                write!(s, "Extract({}, {}, {})", msb.saturating_sub(1), lsb, self.expr(expr))
+            }
+            Expr::Concat(lexpr, rexpr) => write!(s, "Concat({}, {})", self.expr(lexpr), self.expr(rexpr)),
+            Expr::IfElse(cond, texpr, fexpr) => write!(
+                s,
+                "Ite({}, {}, {})",
+                self.expr(cond),
+                self.expr(texpr),
+                self.expr(fexpr),
+            ),
+            Expr::Intrinsic(name, _, bits) => write!(s, "{}", unknown(name, *bits)),
+            Expr::Val(bv) => write!(s, "{}", int(bv)),
+            Expr::Var(var) => write!(s, "{}", self.var(var)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::bv::BitVecT;
+
+    #[test]
+    fn test_bir_sub() {
+        let typ = BitVecT::with_bits(64, false);
+        let (rax, rdi) = (Var::from(Var::physical("RAX", typ)), Var::from(Var::physical("RDI", typ)));
+
+        let mut entry = Blk::new(None);
+        let mut exit = Blk::new(None);
+        entry.add_def(Def::assign(rax.clone(), Expr::bin_op(BinOp::Add, rax.clone(), rdi)));
+        entry.add_jmp(Jmp::call(Loc::Computed(Expr::from(rax.clone())), []));
+        entry.add_jmp(Jmp::branch(exit.id()));
+        exit.add_jmp(Jmp::return_(Expr::from(rax)));
+        let sub = Sub::new(None, None, vec![entry, exit]);
+
+        let bir = BirExport::new().sub(&sub);
+        assert!(bir.starts_with("Sub(Tid(1, \"@sub_1\"), Attrs([]), \"sub_1\""));
+        assert!(bir.contains("Def(Tid(4, \"%00000004\"), Attrs([]), Var(\"RAX\", Imm(64)), PLUS(Var(\"RAX\", Imm(64)), Var(\"RDI\", Imm(64))))"));
+        assert!(bir.contains("Calls(Indirect(Var(\"RAX\", Imm(64))), Some(Direct(Tid(3, \"%00000003\"))))"));
+        assert!(bir.contains("Ret(Tid(6, \"%00000006\"), Attrs([]), Int(1, 1), Indirect(Var(\"RAX\", Imm(64))))"));
+    }
+}
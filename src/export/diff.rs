@@ -0,0 +1,395 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::io::{self, Write as _};
+use std::process::{Command, Stdio};
+
+use petgraph::Direction;
+use thiserror::Error;
+
+use crate::export::TokenExport;
+use crate::ir::cfg::{Cfg, Edge};
+use crate::ir::{Blk, Sub};
+use crate::prelude::{Id, Identifiable};
+
+#[derive(Debug, Error)]
+pub enum DiffError {
+    #[error("cannot run graphviz: {0}")]
+    Graphviz(#[source] io::Error),
+    #[error("graphviz failed: {0}")]
+    GraphvizFailed(String),
+}
+
+/// How a Blk (or a flow between Blks) differs between two versions of a
+/// Sub.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Change {
+    Unchanged,
+    /// Present in both versions, with different statements
+    Changed,
+    /// Present only in the new version
+    Added,
+    /// Present only in the old version
+    Removed,
+}
+
+impl Change {
+    fn colour(&self) -> Option<&'static str> {
+        match self {
+            Self::Unchanged => None,
+            Self::Changed => Some("orange"),
+            Self::Added => Some("green3"),
+            Self::Removed => Some("red"),
+        }
+    }
+}
+
+// a Blk of the combined Cfg
+#[derive(Debug, Clone)]
+struct Node {
+    old: Option<Id<Blk>>,
+    new: Option<Id<Blk>>,
+    change: Change,
+    statements: Vec<Vec<String>>,
+}
+
+/// The differences between the Cfgs of two versions of a Sub (e.g., of a
+/// function before and after a patch).
+///
+/// Blks are matched between versions in the following order: the entry
+/// Blks; Blks whose statements are identical (up to the names of variables
+/// and the labels of Blks), where the match is unique; Blks at the same
+/// address; and finally, Blks reached from matched Blks via the same kind
+/// of flow, where there is a single candidate in each version. Matched
+/// Blks whose statements differ (where the flows between matched Blks are
+/// considered equal) are changed; unmatched Blks are added or removed.
+///
+/// The combined Cfg can be rendered in DOT (or SVG, via graphviz), with
+/// added, removed, and changed Blks and flows coloured green, red, and
+/// orange, respectively.
+#[derive(Debug, Clone)]
+pub struct CfgDiff {
+    nodes: Vec<Node>,
+    edges: BTreeMap<(usize, usize), (Edge, Change)>,
+}
+
+impl CfgDiff {
+    pub fn new(old: &Sub, new: &Sub) -> Self {
+        let export = TokenExport::new();
+        let (ocfg, ncfg) = (old.cfg(), new.cfg());
+
+        let unlabelled = BTreeMap::new();
+        let fingerprints = |sub: &Sub| {
+            sub.blks()
+                .iter()
+                .map(|blk| (blk.id(), export.statements(sub.addr(), &unlabelled, blk)))
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        let (oprints, nprints) = (fingerprints(old), fingerprints(new));
+
+        let mut matching = Matching::default();
+
+        // entry Blks
+        if let (Some(oentry), Some(nentry)) = (old.entry(), new.entry()) {
+            matching.insert(oentry.id(), nentry.id());
+        }
+
+        // identical statements, where unique
+        let nunique = Self::unique(&nprints);
+        for (print, oid) in Self::unique(&oprints) {
+            if let Some(nid) = nunique.get(print) {
+                matching.insert(oid, *nid);
+            }
+        }
+
+        // same address
+        let naddrs = new
+            .blks()
+            .iter()
+            .filter_map(|blk| Some((blk.addr()?.clone(), blk.id())))
+            .collect::<BTreeMap<_, _>>();
+
+        for blk in old.blks() {
+            if let Some(nid) = blk.addr().and_then(|addr| naddrs.get(addr)) {
+                matching.insert(blk.id(), *nid);
+            }
+        }
+
+        // unique successors (and predecessors) via the same kind of flow
+        loop {
+            let pairs = matching.pairs().collect::<Vec<_>>();
+            let mut progress = false;
+
+            for (oid, nid) in pairs {
+                for direction in [Direction::Outgoing, Direction::Incoming] {
+                    for kind in [Edge::Branch, Edge::CBranch, Edge::Switch] {
+                        let ocands = matching.unmatched_old(&ocfg, oid, direction, kind);
+                        let ncands = matching.unmatched_new(&ncfg, nid, direction, kind);
+
+                        if ocands.len() == 1 && ncands.len() == 1 {
+                            progress |= matching.insert(ocands[0], ncands[0]);
+                        }
+                    }
+                }
+            }
+
+            if !progress {
+                break
+            }
+        }
+
+        // nodes: matched, removed, then added Blks, each in the order of
+        // their Sub
+        let mut nodes = Vec::new();
+        let mut onodes = BTreeMap::new();
+        let mut nnodes = BTreeMap::new();
+
+        for blk in old.blks() {
+            if let Some(nid) = matching.old_to_new.get(&blk.id()) {
+                onodes.insert(blk.id(), nodes.len());
+                nnodes.insert(*nid, nodes.len());
+                nodes.push(Node {
+                    old: Some(blk.id()),
+                    new: Some(*nid),
+                    change: Change::Unchanged,
+                    statements: Vec::new(),
+                });
+            }
+        }
+
+        for blk in old.blks() {
+            if !onodes.contains_key(&blk.id()) {
+                onodes.insert(blk.id(), nodes.len());
+                nodes.push(Node {
+                    old: Some(blk.id()),
+                    new: None,
+                    change: Change::Removed,
+                    statements: Vec::new(),
+                });
+            }
+        }
+
+        for blk in new.blks() {
+            if !nnodes.contains_key(&blk.id()) {
+                nnodes.insert(blk.id(), nodes.len());
+                nodes.push(Node {
+                    old: None,
+                    new: Some(blk.id()),
+                    change: Change::Added,
+                    statements: Vec::new(),
+                });
+            }
+        }
+
+        // labelling Blks by their nodes makes flows between matched Blks
+        // equal
+        for node in nodes.iter_mut() {
+            let ostmts = node
+                .old
+                .and_then(|id| old.blk(id))
+                .map(|blk| export.statements(old.addr(), &onodes, blk));
+            let nstmts = node
+                .new
+                .and_then(|id| new.blk(id))
+                .map(|blk| export.statements(new.addr(), &nnodes, blk));
+
+            if node.change == Change::Unchanged && ostmts != nstmts {
+                node.change = Change::Changed;
+            }
+
+            node.statements = nstmts.or(ostmts).unwrap_or_default();
+        }
+
+        let mut edges = BTreeMap::new();
+
+        for (from, to, kind) in ocfg.edges() {
+            edges.insert((onodes[&from], onodes[&to]), (kind, Change::Removed));
+        }
+
+        for (from, to, kind) in ncfg.edges() {
+            let key = (nnodes[&from], nnodes[&to]);
+            let change = match edges.get(&key) {
+                Some((okind, _)) if *okind == kind => Change::Unchanged,
+                Some(_) => Change::Changed,
+                None => Change::Added,
+            };
+            edges.insert(key, (kind, change));
+        }
+
+        Self { nodes, edges }
+    }
+
+    // the Blks whose statements are not shared with other Blks
+    fn unique(
+        prints: &BTreeMap<Id<Blk>, Vec<Vec<String>>>,
+    ) -> BTreeMap<&Vec<Vec<String>>, Id<Blk>> {
+        let mut counts = BTreeMap::<&Vec<Vec<String>>, Vec<Id<Blk>>>::new();
+        for (id, print) in prints.iter() {
+            counts.entry(print).or_default().push(*id);
+        }
+        counts
+            .into_iter()
+            .filter_map(|(print, ids)| if ids.len() == 1 { Some((print, ids[0])) } else { None })
+            .collect()
+    }
+
+    /// The change to `blk`, which may be from either version.
+    pub fn change(&self, blk: impl Identifiable<Blk>) -> Option<Change> {
+        let id = Some(blk.id());
+        self.nodes
+            .iter()
+            .find(|node| node.old == id || node.new == id)
+            .map(|node| node.change)
+    }
+
+    /// The matched Blks of the old and new versions, and their changes
+    /// (either `Unchanged` or `Changed`).
+    pub fn matched(&self) -> impl Iterator<Item = (Id<Blk>, Id<Blk>, Change)> + '_ {
+        self.nodes
+            .iter()
+            .filter_map(|node| Some((node.old?, node.new?, node.change)))
+    }
+
+    pub fn added(&self) -> impl Iterator<Item = Id<Blk>> + '_ {
+        self.nodes
+            .iter()
+            .filter(|node| node.change == Change::Added)
+            .filter_map(|node| node.new)
+    }
+
+    pub fn removed(&self) -> impl Iterator<Item = Id<Blk>> + '_ {
+        self.nodes
+            .iter()
+            .filter(|node| node.change == Change::Removed)
+            .filter_map(|node| node.old)
+    }
+
+    /// Returns true if the versions' Blks and flows are the same.
+    pub fn is_unchanged(&self) -> bool {
+        self.nodes.iter().all(|node| node.change == Change::Unchanged)
+            && self.edges.values().all(|(_, change)| *change == Change::Unchanged)
+    }
+
+    fn escape(s: &str) -> String {
+        s.replace('\\', "\\\\").replace('"', "\\\"")
+    }
+
+    /// Renders the combined Cfg in DOT; each Blk is labelled with its
+    /// statements as tokens (see `TokenExport`).
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+
+        // writes to strings are infallible
+        let _ = writeln!(dot, "digraph cfg_diff {{");
+        let _ = writeln!(dot, "  node [shape=box, fontname=\"monospace\"];");
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            let mut label = format!("b{}\\l", i);
+            for stmt in node.statements.iter() {
+                label.push_str(&Self::escape(&stmt.join(" ")));
+                label.push_str("\\l");
+            }
+
+            let _ = write!(dot, "  n{} [label=\"{}\"", i, label);
+            if let Some(colour) = node.change.colour() {
+                let _ = write!(dot, ", color={}, style=bold", colour);
+            }
+            let _ = writeln!(dot, "];");
+        }
+
+        for ((from, to), (kind, change)) in self.edges.iter() {
+            let _ = write!(dot, "  n{} -> n{} [", from, to);
+            match kind {
+                Edge::Branch => {
+                    let _ = write!(dot, "style=solid");
+                }
+                Edge::CBranch => {
+                    let _ = write!(dot, "style=dashed");
+                }
+                Edge::Switch => {
+                    let _ = write!(dot, "style=dotted");
+                }
+            }
+            if let Some(colour) = change.colour() {
+                let _ = write!(dot, ", color={}, penwidth=2", colour);
+            }
+            let _ = writeln!(dot, "];");
+        }
+
+        let _ = writeln!(dot, "}}");
+        dot
+    }
+
+    /// Renders the combined Cfg in SVG using graphviz's `dot`, which must
+    /// be available on the `PATH`.
+    pub fn to_svg(&self) -> Result<String, DiffError> {
+        let mut child = Command::new("dot")
+            .arg("-Tsvg")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(DiffError::Graphviz)?;
+
+        // unwrap is safe here: we requested a pipe for stdin
+        let mut stdin = child.stdin.take().unwrap();
+        let dot = self.to_dot();
+        let writer = std::thread::spawn(move || stdin.write_all(dot.as_bytes()));
+
+        let output = child.wait_with_output().map_err(DiffError::Graphviz)?;
+        writer
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "writer panicked")))
+            .map_err(DiffError::Graphviz)?;
+
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        } else {
+            Err(DiffError::GraphvizFailed(
+                String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            ))
+        }
+    }
+}
+
+#[derive(Default)]
+struct Matching {
+    old_to_new: BTreeMap<Id<Blk>, Id<Blk>>,
+    new_matched: BTreeSet<Id<Blk>>,
+}
+
+impl Matching {
+    // returns false if either Blk is already matched
+    fn insert(&mut self, old: Id<Blk>, new: Id<Blk>) -> bool {
+        if self.old_to_new.contains_key(&old) || self.new_matched.contains(&new) {
+            return false
+        }
+        self.old_to_new.insert(old, new);
+        self.new_matched.insert(new);
+        true
+    }
+
+    fn pairs(&self) -> impl Iterator<Item = (Id<Blk>, Id<Blk>)> + '_ {
+        self.old_to_new.iter().map(|(old, new)| (*old, *new))
+    }
+
+    fn neighbours(cfg: &Cfg, blk: Id<Blk>, direction: Direction, kind: Edge) -> Vec<Id<Blk>> {
+        cfg.graph()
+            .edges_directed(blk, direction)
+            .filter(|(_, _, edge)| **edge == kind)
+            .map(|(from, to, _)| if direction == Direction::Outgoing { to } else { from })
+            .collect()
+    }
+
+    fn unmatched_old(&self, cfg: &Cfg, blk: Id<Blk>, direction: Direction, kind: Edge) -> Vec<Id<Blk>> {
+        let mut blks = Self::neighbours(cfg, blk, direction, kind);
+        blks.retain(|blk| !self.old_to_new.contains_key(blk));
+        blks
+    }
+
+    fn unmatched_new(&self, cfg: &Cfg, blk: Id<Blk>, direction: Direction, kind: Edge) -> Vec<Id<Blk>> {
+        let mut blks = Self::neighbours(cfg, blk, direction, kind);
+        blks.retain(|blk| !self.new_matched.contains(blk));
+        blks
+    }
+}
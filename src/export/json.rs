@@ -0,0 +1,254 @@
+use serde_json::{Map, Value};
+
+use crate::ir::{Addr, BitVec, Blk, Cast, Def, Expr, Jmp, Loc, Phi, Sub, Var};
+use crate::prelude::{Entity, Identifiable};
+
+/// The version of the schema of the documents produced by `JsonExport`;
+/// it is incremented whenever a field is removed or changes meaning.
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// Exports Subs as JSON, for consumption by tools not written in Rust.
+///
+/// Documents (see `document`) are objects of the form
+/// `{"schema": "delirium-ir", "version": 1, "subs": [...]}`. Subs have a
+/// `symbol`, `addr`, and `blks`; Blks have an `addr`, `landing_pad`, and
+/// `phis`, `defs`, and `jmps`; every entity has an `id` (e.g.,
+/// `"blk/..."`), unless ids are disabled. Statements, locations and
+/// expressions are objects tagged by their `kind` (e.g., `"assign"`,
+/// `"fixed"`, `"binop"`), with their operands as nested objects; operators
+/// are given in lower case (e.g., `"op": "sdiv"`). Variables are tagged
+/// by their kinds: `"physical"`, `"transient"`, or `"memory"`.
+///
+/// Addresses and constants are hexadecimal strings (e.g., `"0x401000"`),
+/// as they may not fit within the integers of JSON consumers; constants
+/// and variables carry their sizes in `bits`. Absent values are `null`.
+#[derive(Debug, Clone)]
+pub struct JsonExport {
+    ids: bool,
+}
+
+impl Default for JsonExport {
+    fn default() -> Self {
+        Self { ids: true }
+    }
+}
+
+fn object<const N: usize>(kind: &str, fields: [(&str, Value); N]) -> Value {
+    let mut object = Map::new();
+    object.insert("kind".to_owned(), Value::from(kind));
+    for (name, value) in fields {
+        object.insert(name.to_owned(), value);
+    }
+    Value::Object(object)
+}
+
+fn addr(addr: Option<&Addr>) -> Value {
+    addr.map(|addr| Value::from(format!("0x{}", addr)))
+        .unwrap_or(Value::Null)
+}
+
+fn op(op: impl std::fmt::Debug) -> Value {
+    Value::from(format!("{:?}", op).to_lowercase())
+}
+
+impl JsonExport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Include the ids of entities; enabled by default. Ids are unique
+    /// within a run, hence documents without them can be compared across
+    /// runs.
+    pub fn ids(&mut self, ids: bool) -> &mut Self {
+        self.ids = ids;
+        self
+    }
+
+    fn with_id<V>(&self, entity: &impl Identifiable<V>, value: Value) -> Value {
+        let mut value = value;
+        if let (true, Value::Object(ref mut object)) = (self.ids, &mut value) {
+            object.insert("id".to_owned(), Value::from(entity.id().to_string()));
+        }
+        value
+    }
+
+    /// A document holding `subs`.
+    pub fn document<'a>(&self, subs: impl IntoIterator<Item = &'a Entity<Sub>>) -> Value {
+        let mut object = Map::new();
+        object.insert("schema".to_owned(), Value::from("delirium-ir"));
+        object.insert("version".to_owned(), Value::from(SCHEMA_VERSION));
+        object.insert(
+            "subs".to_owned(),
+            Value::Array(subs.into_iter().map(|sub| self.sub(sub)).collect()),
+        );
+        Value::Object(object)
+    }
+
+    pub fn sub(&self, sub: &Entity<Sub>) -> Value {
+        let value = object("sub", [
+            ("symbol", sub.symbol().map(|symbol| Value::from(&**symbol)).unwrap_or(Value::Null)),
+            ("addr", addr(sub.addr())),
+            ("blks", Value::Array(sub.blks().iter().map(|blk| self.blk(blk)).collect())),
+        ]);
+        self.with_id(sub, value)
+    }
+
+    pub fn blk(&self, blk: &Entity<Blk>) -> Value {
+        let value = object("blk", [
+            ("addr", addr(blk.addr())),
+            ("landing_pad", Value::from(blk.is_landing_pad())),
+            ("phis", Value::Array(blk.phis().iter().map(|phi| self.phi(phi)).collect())),
+            ("defs", Value::Array(blk.defs().iter().map(|def| self.def(def)).collect())),
+            ("jmps", Value::Array(blk.jmps().iter().map(|jmp| self.jmp(jmp)).collect())),
+        ]);
+        self.with_id(blk, value)
+    }
+
+    pub fn phi(&self, phi: &Entity<Phi>) -> Value {
+        let choices = phi
+            .choices()
+            .iter()
+            .map(|(cond, expr)| {
+                let mut choice = Map::new();
+                choice.insert("cond".to_owned(), self.expr(cond));
+                choice.insert("expr".to_owned(), self.expr(expr));
+                Value::Object(choice)
+            })
+            .collect();
+
+        let value = object("phi", [
+            ("var", self.var(phi.var())),
+            ("choices", Value::Array(choices)),
+        ]);
+        self.with_id(phi, value)
+    }
+
+    pub fn def(&self, def: &Entity<Def>) -> Value {
+        let value = match &**def {
+            Def::Assign(var, expr) => object("assign", [
+                ("var", self.var(var)),
+                ("expr", self.expr(expr)),
+            ]),
+            Def::Assume(expr) => object("assume", [("expr", self.expr(expr))]),
+            Def::Store(loc, val, bits, mem) => object("store", [
+                ("mem", self.var(mem)),
+                ("addr", self.expr(loc)),
+                ("val", self.expr(val)),
+                ("bits", Value::from(*bits as u64)),
+            ]),
+        };
+        self.with_id(def, value)
+    }
+
+    pub fn jmp(&self, jmp: &Entity<Jmp>) -> Value {
+        let args = |args: &[Expr]| Value::Array(args.iter().map(|arg| self.expr(arg)).collect());
+        let value = match &**jmp {
+            Jmp::Branch(loc) => object("branch", [("target", self.loc(loc))]),
+            Jmp::CBranch(loc, cond) => object("cbranch", [
+                ("target", self.loc(loc)),
+                ("cond", self.expr(cond)),
+            ]),
+            Jmp::Call(loc, arguments) => object("call", [
+                ("target", self.loc(loc)),
+                ("args", args(arguments)),
+            ]),
+            Jmp::Intrinsic(name, arguments) => object("intrinsic", [
+                ("name", Value::from(&**name)),
+                ("args", args(arguments)),
+            ]),
+            Jmp::Return(loc) => object("return", [("target", self.loc(loc))]),
+        };
+        self.with_id(jmp, value)
+    }
+
+    pub fn loc(&self, loc: &Loc) -> Value {
+        match loc {
+            Loc::Resolved(id) => object("resolved", [
+                ("blk", if self.ids { Value::from(id.to_string()) } else { Value::Null }),
+            ]),
+            Loc::Fixed(target) => object("fixed", [("addr", addr(Some(target)))]),
+            Loc::Computed(expr) => object("computed", [("expr", self.expr(expr))]),
+        }
+    }
+
+    pub fn var(&self, var: &Var) -> Value {
+        let kind = if var.is_memory() {
+            "memory"
+        } else if var.is_physical() {
+            "physical"
+        } else {
+            "transient"
+        };
+
+        object(kind, [
+            ("name", Value::from(&**var.name())),
+            ("bits", var.bits().map(|bits| Value::from(bits as u64)).unwrap_or(Value::Null)),
+            ("generation", Value::from(var.generation() as u64)),
+        ])
+    }
+
+    fn val(&self, bv: &BitVec) -> Value {
+        object("val", [
+            ("value", Value::from(format!("0x{:x}", bv))),
+            ("bits", Value::from(bv.bits() as u64)),
+        ])
+    }
+
+    pub fn expr(&self, expr: &Expr) -> Value {
+        match expr {
+            Expr::UnRel(rel, expr) => object("unrel", [("op", op(rel)), ("expr", self.expr(expr))]),
+            Expr::UnOp(unop, expr) => object("unop", [("op", op(unop)), ("expr", self.expr(expr))]),
+            Expr::BinRel(rel, lexpr, rexpr) => object("binrel", [
+                ("op", op(rel)),
+                ("lhs", self.expr(lexpr)),
+                ("rhs", self.expr(rexpr)),
+            ]),
+            Expr::BinOp(binop, lexpr, rexpr) => object("binop", [
+                ("op", op(binop)),
+                ("lhs", self.expr(lexpr)),
+                ("rhs", self.expr(rexpr)),
+            ]),
+            Expr::Cast(expr, cast) => {
+                let (name, bits) = match cast {
+                    Cast::Bool => ("bool", None),
+                    Cast::Float(bits) => ("float", Some(*bits)),
+                    Cast::Signed(bits) => ("signed", Some(*bits)),
+                    Cast::Unsigned(bits) => ("unsigned", Some(*bits)),
+                    Cast::High(bits) => ("high", Some(*bits)),
+                    Cast::Low(bits) => ("low", Some(*bits)),
+                };
+                object("cast", [
+                    ("cast", Value::from(name)),
+                    ("bits", bits.map(|bits| Value::from(bits as u64)).unwrap_or(Value::Null)),
+                    ("expr", self.expr(expr)),
+                ])
+            }
+            Expr::Load(addr, bits, mem) => object("load", [
+                ("mem", self.var(mem)),
+                ("addr", self.expr(addr)),
+                ("bits", Value::from(*bits as u64)),
+            ]),
+            Expr::Extract(expr, lsb, msb) => object("extract", [
+                ("expr", self.expr(expr)),
+                ("lsb", Value::from(*lsb as u64)),
+                ("msb", Value::from(*msb as u64)),
+            ]),
+            Expr::Concat(lexpr, rexpr) => object("concat", [
+                ("lhs", self.expr(lexpr)),
+                ("rhs", self.expr(rexpr)),
+            ]),
+            Expr::IfElse(cond, texpr, fexpr) => object("ite", [
+                ("cond", self.expr(cond)),
+                ("then", self.expr(texpr)),
+                ("else", self.expr(fexpr)),
+            ]),
+            Expr::Intrinsic(name, args, bits) => object("intrinsic", [
+                ("name", Value::from(&**name)),
+                ("args", Value::Array(args.iter().map(|arg| self.expr(arg)).collect())),
+                ("bits", Value::from(*bits as u64)),
+            ]),
+            Expr::Val(bv) => self.val(bv),
+            Expr::Var(var) => self.var(var),
+        }
+    }
+}
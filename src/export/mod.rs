@@ -0,0 +1,31 @@
+/// Exporters of our IR/IL to formats consumed by other tools.
+///
+/// - `tokens` produces normalised, position-independent token sequences
+///   for Subs, e.g., as features for similarity models.
+///
+/// - `asm` re-emits the instructions of Subs as assembly, with labels
+///   and symbols recovered, for reassembly after patching.
+///
+/// - `bir` converts Subs to BAP's intermediate representation (BIR), in
+///   its ADT format, for use by pipelines built on BAP.
+///
+/// - `json` serialises Subs as JSON documents of a stable schema, for
+///   consumption by tools not written in Rust.
+///
+/// - `diff` matches the Blks of two versions of a Sub, and renders their
+///   combined Cfg with the differences highlighted (in DOT or SVG).
+
+pub mod asm;
+pub use asm::{AsmError, AsmExport};
+
+pub mod bir;
+pub use bir::BirExport;
+
+pub mod diff;
+pub use diff::{CfgDiff, Change, DiffError};
+
+pub mod json;
+pub use json::JsonExport;
+
+pub mod tokens;
+pub use tokens::TokenExport;
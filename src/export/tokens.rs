@@ -0,0 +1,367 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::ir::{Addr, BitVec, Blk, Cast, Def, Expr, Jmp, Loc, Phi, Sub, Var};
+use crate::prelude::{Entity, Id, Identifiable};
+
+/// Exports Subs as sequences of tokens that do not depend on where the
+/// Sub is located, or on the names chosen for its variables and Blks
+/// during lifting; hence, the same code lifted at different addresses (or
+/// within different binaries) yields the same tokens.
+///
+/// Blks are labelled (`b0`, `b1`, ...) in reverse post-order of the Sub's
+/// Cfg. Variables are renamed in order of their first occurrence:
+/// transient variables become `t0`, `t1`, ..., memories become `m0`,
+/// `m1`, ..., and each SSA generation of a physical variable becomes
+/// `<name>.0`, `<name>.1`, ... (or `r0`, `r1`, ... if physical names are
+/// not kept). Fixed flow targets are given relative to the address of the
+/// Sub (e.g., `+0x10`).
+///
+/// Expressions and statements are written in prefix form; each operator
+/// has a fixed arity, or has its arity as part of its token (e.g., for
+/// intrinsics), hence token sequences are unambiguous.
+#[derive(Debug, Clone)]
+pub struct TokenExport {
+    physical_names: bool,
+    max_literal: Option<u64>,
+}
+
+impl Default for TokenExport {
+    fn default() -> Self {
+        Self {
+            physical_names: true,
+            max_literal: None,
+        }
+    }
+}
+
+impl TokenExport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep the names of physical variables (e.g., registers); enabled by
+    /// default.
+    pub fn physical_names(&mut self, keep: bool) -> &mut Self {
+        self.physical_names = keep;
+        self
+    }
+
+    /// Replace constants larger than `max` by the token `imm:<bits>`; this
+    /// abstracts away values that are likely to be (absolute) addresses.
+    pub fn max_literal(&mut self, max: impl Into<Option<u64>>) -> &mut Self {
+        self.max_literal = max.into();
+        self
+    }
+
+    pub fn tokens(&self, sub: &Sub) -> Vec<String> {
+        let order = sub.cfg().reverse_post_order();
+
+        let mut tokeniser = Tokeniser::new(
+            self,
+            sub.addr(),
+            order.iter().enumerate().map(|(i, id)| (*id, i)).collect(),
+        );
+
+        for id in order {
+            if let Some(blk) = sub.blk(id) {
+                tokeniser.blk(blk);
+            }
+        }
+
+        tokeniser.tokens
+    }
+
+    /// The tokens of each statement of `blk` (of a Sub located at `base`),
+    /// with variables renamed within `blk` alone, and Blks labelled by
+    /// `labels`; Blks without labels are given the token `blk`.
+    pub(crate) fn statements(
+        &self,
+        base: Option<&Addr>,
+        labels: &BTreeMap<Id<Blk>, usize>,
+        blk: &Blk,
+    ) -> Vec<Vec<String>> {
+        let mut tokeniser = Tokeniser::new(self, base, labels.clone());
+
+        let mut statements = Vec::new();
+
+        for phi in blk.phis() {
+            tokeniser.phi(phi);
+            statements.push(std::mem::take(&mut tokeniser.tokens));
+        }
+
+        for def in blk.defs() {
+            tokeniser.def(def);
+            statements.push(std::mem::take(&mut tokeniser.tokens));
+        }
+
+        for jmp in blk.jmps() {
+            tokeniser.jmp(jmp);
+            statements.push(std::mem::take(&mut tokeniser.tokens));
+        }
+
+        statements
+    }
+
+    /// The tokens of `expr` (of a Sub located at `base`), with variables
+    /// renamed within `expr` alone.
+    pub(crate) fn expr(&self, base: Option<&Addr>, expr: &Expr) -> Vec<String> {
+        let mut tokeniser = Tokeniser::new(self, base, BTreeMap::new());
+        tokeniser.expr(expr);
+        tokeniser.tokens
+    }
+}
+
+struct Tokeniser<'a> {
+    export: &'a TokenExport,
+    base: Option<&'a Addr>,
+    blks: BTreeMap<Id<Blk>, usize>,
+    vars: BTreeMap<Var, String>,
+    // number of generations of each physical variable seen
+    generations: BTreeMap<Arc<str>, usize>,
+    transients: usize,
+    physicals: usize,
+    memories: usize,
+    tokens: Vec<String>,
+}
+
+impl<'a> Tokeniser<'a> {
+    fn new(
+        export: &'a TokenExport,
+        base: Option<&'a Addr>,
+        blks: BTreeMap<Id<Blk>, usize>,
+    ) -> Self {
+        Self {
+            export,
+            base,
+            blks,
+            vars: BTreeMap::new(),
+            generations: BTreeMap::new(),
+            transients: 0,
+            physicals: 0,
+            memories: 0,
+            tokens: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, token: impl Into<String>) {
+        self.tokens.push(token.into());
+    }
+
+    fn blk(&mut self, blk: &Entity<Blk>) {
+        let label = format!("{}:", self.label(blk.id()));
+        self.push(label);
+
+        for phi in blk.phis() {
+            self.phi(phi);
+        }
+
+        for def in blk.defs() {
+            self.def(def);
+        }
+
+        for jmp in blk.jmps() {
+            self.jmp(jmp);
+        }
+    }
+
+    fn label(&self, id: Id<Blk>) -> String {
+        self.blks
+            .get(&id)
+            .map(|i| format!("b{}", i))
+            .unwrap_or_else(|| "blk".to_owned())
+    }
+
+    fn phi(&mut self, phi: &Phi) {
+        self.push(format!("phi/{}", phi.choices().len()));
+        self.var(phi.var());
+        for (cond, expr) in phi.choices() {
+            self.expr(cond);
+            self.expr(expr);
+        }
+    }
+
+    fn def(&mut self, def: &Def) {
+        match def {
+            Def::Assign(var, expr) => {
+                self.push("=");
+                self.var(var);
+                self.expr(expr);
+            }
+            Def::Assume(expr) => {
+                self.push("assume");
+                self.expr(expr);
+            }
+            Def::Store(loc, val, bits, mem) => {
+                self.push(format!("store:{}", bits));
+                self.var(mem);
+                self.expr(loc);
+                self.expr(val);
+            }
+        }
+    }
+
+    fn jmp(&mut self, jmp: &Jmp) {
+        match jmp {
+            Jmp::Branch(loc) => {
+                self.push("goto");
+                self.loc(loc);
+            }
+            Jmp::CBranch(loc, cond) => {
+                self.push("if");
+                self.expr(cond);
+                self.loc(loc);
+            }
+            Jmp::Call(loc, args) => {
+                self.push(format!("call/{}", args.len()));
+                self.loc(loc);
+                for arg in args.iter() {
+                    self.expr(arg);
+                }
+            }
+            Jmp::Intrinsic(name, args) => {
+                self.push(format!("@{}/{}", name, args.len()));
+                for arg in args.iter() {
+                    self.expr(arg);
+                }
+            }
+            Jmp::Return(loc) => {
+                self.push("ret");
+                self.loc(loc);
+            }
+        }
+    }
+
+    fn loc(&mut self, loc: &Loc) {
+        match loc {
+            Loc::Resolved(id) => {
+                let label = self.label(*id);
+                self.push(label);
+            }
+            Loc::Fixed(addr) => {
+                let token = self.relative(addr);
+                self.push(token);
+            }
+            Loc::Computed(expr) => {
+                self.push("*");
+                self.expr(expr);
+            }
+        }
+    }
+
+    fn relative(&self, addr: &Addr) -> String {
+        let base = if let Some(base) = self.base {
+            base
+        } else {
+            return "addr".to_owned()
+        };
+
+        match addr.absolute_difference(base) {
+            Some(offset) if addr >= base => format!("+{:#x}", offset),
+            Some(offset) => format!("-{:#x}", offset),
+            None => "addr".to_owned(),
+        }
+    }
+
+    fn var(&mut self, var: &Var) {
+        if let Some(token) = self.vars.get(var) {
+            self.tokens.push(token.clone());
+            return
+        }
+
+        let token = if var.is_memory() {
+            self.memories += 1;
+            format!("m{}", self.memories - 1)
+        } else if var.is_transient() {
+            self.transients += 1;
+            format!("t{}", self.transients - 1)
+        } else if self.export.physical_names {
+            let generation = self.generations.entry(var.name().clone()).or_default();
+            *generation += 1;
+            format!("{}.{}", var.name(), *generation - 1)
+        } else {
+            self.physicals += 1;
+            format!("r{}", self.physicals - 1)
+        };
+
+        self.vars.insert(var.clone(), token.clone());
+        self.tokens.push(token);
+    }
+
+    fn val(&mut self, bv: &BitVec) {
+        let abstracted = self
+            .export
+            .max_literal
+            .map(|max| bv.to_u64().map(|v| v > max).unwrap_or(true))
+            .unwrap_or(false);
+
+        if abstracted {
+            self.push(format!("imm:{}", bv.bits()));
+        } else {
+            self.push(format!("0x{:x}:{}", bv, bv.bits()));
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::UnRel(op, expr) => {
+                self.push(format!("{:?}", op).to_lowercase());
+                self.expr(expr);
+            }
+            Expr::UnOp(op, expr) => {
+                self.push(format!("{:?}", op).to_lowercase());
+                self.expr(expr);
+            }
+            Expr::BinRel(op, lexpr, rexpr) => {
+                self.push(format!("{:?}", op).to_lowercase());
+                self.expr(lexpr);
+                self.expr(rexpr);
+            }
+            Expr::BinOp(op, lexpr, rexpr) => {
+                self.push(format!("{:?}", op).to_lowercase());
+                self.expr(lexpr);
+                self.expr(rexpr);
+            }
+            Expr::Cast(expr, cast) => {
+                let token = match cast {
+                    Cast::Bool => "cast.bool".to_owned(),
+                    Cast::Float(bits) => format!("cast.float:{}", bits),
+                    Cast::Signed(bits) => format!("cast.signed:{}", bits),
+                    Cast::Unsigned(bits) => format!("cast.unsigned:{}", bits),
+                    Cast::High(bits) => format!("cast.high:{}", bits),
+                    Cast::Low(bits) => format!("cast.low:{}", bits),
+                };
+                self.push(token);
+                self.expr(expr);
+            }
+            Expr::Load(expr, bits, mem) => {
+                self.push(format!("load:{}", bits));
+                self.var(mem);
+                self.expr(expr);
+            }
+            Expr::Extract(expr, lsb, msb) => {
+                self.push(format!("extract:{}:{}", lsb, msb));
+                self.expr(expr);
+            }
+            Expr::Concat(lexpr, rexpr) => {
+                self.push("concat");
+                self.expr(lexpr);
+                self.expr(rexpr);
+            }
+            Expr::IfElse(cond, texpr, fexpr) => {
+                self.push("ite");
+                self.expr(cond);
+                self.expr(texpr);
+                self.expr(fexpr);
+            }
+            Expr::Intrinsic(name, args, bits) => {
+                self.push(format!("@{}/{}:{}", name, args.len(), bits));
+                for arg in args.iter() {
+                    self.expr(arg);
+                }
+            }
+            Expr::Val(bv) => self.val(bv),
+            Expr::Var(var) => self.var(var),
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::ir::{Blk, Def, Jmp, Phi};
+use crate::prelude::{Entity, Id, Identifiable};
+
+/// The kinds of statement held by a Blk, each in its own sequence.
+pub trait BlkStmt: Sized + 'static {
+    fn stmts(blk: &Blk) -> &Vec<Entity<Self>>;
+    fn stmts_mut(blk: &mut Blk) -> &mut Vec<Entity<Self>>;
+}
+
+impl BlkStmt for Phi {
+    fn stmts(blk: &Blk) -> &Vec<Entity<Self>> {
+        &blk.phis
+    }
+
+    fn stmts_mut(blk: &mut Blk) -> &mut Vec<Entity<Self>> {
+        &mut blk.phis
+    }
+}
+
+impl BlkStmt for Def {
+    fn stmts(blk: &Blk) -> &Vec<Entity<Self>> {
+        &blk.defs
+    }
+
+    fn stmts_mut(blk: &mut Blk) -> &mut Vec<Entity<Self>> {
+        &mut blk.defs
+    }
+}
+
+impl BlkStmt for Jmp {
+    fn stmts(blk: &Blk) -> &Vec<Entity<Self>> {
+        &blk.jmps
+    }
+
+    fn stmts_mut(blk: &mut Blk) -> &mut Vec<Entity<Self>> {
+        &mut blk.jmps
+    }
+}
+
+/// A handle to the position of a statement within a Blk.
+///
+/// Handles identify their statement by its id, and remember the index it
+/// was last seen at; they remain valid as statements are inserted, moved,
+/// and removed around them (unlike bare indices), and are resolved in
+/// constant time unless the statement has moved far from where it was last
+/// seen (unlike searching by id).
+pub struct StmtIdx<T> {
+    id: Id<T>,
+    hint: usize,
+    marker: PhantomData<T>,
+}
+
+pub type PhiIdx = StmtIdx<Phi>;
+pub type DefIdx = StmtIdx<Def>;
+pub type JmpIdx = StmtIdx<Jmp>;
+
+impl<T> StmtIdx<T> {
+    fn new(id: Id<T>, hint: usize) -> Self {
+        Self {
+            id,
+            hint,
+            marker: PhantomData,
+        }
+    }
+
+    /// The index the statement was last seen at.
+    pub fn hint(&self) -> usize {
+        self.hint
+    }
+}
+
+impl<T> Clone for StmtIdx<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.id, self.hint)
+    }
+}
+impl<T> Copy for StmtIdx<T> { }
+
+impl<T> PartialEq for StmtIdx<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+impl<T> Eq for StmtIdx<T> { }
+
+impl<T> fmt::Debug for StmtIdx<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{}", self.id, self.hint)
+    }
+}
+
+impl<T> Identifiable<T> for StmtIdx<T> {
+    fn id(&self) -> Id<T> {
+        self.id
+    }
+}
+
+impl Blk {
+    /// The handle to `stmt` within the Blk, if it is present.
+    pub fn stmt_idx<T: BlkStmt>(&self, stmt: impl Identifiable<T>) -> Option<StmtIdx<T>> {
+        let id = stmt.id();
+        T::stmts(self)
+            .iter()
+            .position(|stmt| stmt.id() == id)
+            .map(|index| StmtIdx::new(id, index))
+    }
+
+    /// The handles to the statements of the Blk, in order.
+    pub fn stmt_indices<T: BlkStmt>(&self) -> impl Iterator<Item = StmtIdx<T>> + '_ {
+        T::stmts(self)
+            .iter()
+            .enumerate()
+            .map(|(index, stmt)| StmtIdx::new(stmt.id(), index))
+    }
+
+    /// The current index of the statement of `idx`, if it is still within
+    /// the Blk; `idx` is updated to remember it.
+    pub fn resolve<T: BlkStmt>(&self, idx: &mut StmtIdx<T>) -> Option<usize> {
+        let stmts = T::stmts(self);
+        let matches = |index: usize| stmts[index].id() == idx.id;
+
+        // search outwards from the index last seen, as edits are usually
+        // local
+        let len = stmts.len();
+        let hint = idx.hint.min(len);
+        let index = (0..=len).find_map(|distance| {
+            let above = Some(hint + distance).filter(|index| *index < len);
+            let below = hint.checked_sub(distance).filter(|index| *index < len);
+            above.filter(|index| matches(*index)).or_else(|| below.filter(|index| matches(*index)))
+        })?;
+
+        idx.hint = index;
+        Some(index)
+    }
+
+    pub fn stmt<T: BlkStmt>(&self, mut idx: StmtIdx<T>) -> Option<&Entity<T>> {
+        let index = self.resolve(&mut idx)?;
+        T::stmts(self).get(index)
+    }
+
+    pub fn stmt_mut<T: BlkStmt>(&mut self, mut idx: StmtIdx<T>) -> Option<&mut Entity<T>> {
+        let index = self.resolve(&mut idx)?;
+        T::stmts_mut(self).get_mut(index)
+    }
+
+    /// The handle to the statement following that of `idx`.
+    pub fn next_stmt<T: BlkStmt>(&self, mut idx: StmtIdx<T>) -> Option<StmtIdx<T>> {
+        let index = self.resolve(&mut idx)? + 1;
+        T::stmts(self).get(index).map(|stmt| StmtIdx::new(stmt.id(), index))
+    }
+
+    /// The handle to the statement preceding that of `idx`.
+    pub fn prev_stmt<T: BlkStmt>(&self, mut idx: StmtIdx<T>) -> Option<StmtIdx<T>> {
+        let index = self.resolve(&mut idx)?.checked_sub(1)?;
+        T::stmts(self).get(index).map(|stmt| StmtIdx::new(stmt.id(), index))
+    }
+
+    /// Inserts `stmt` before the statement of `at`, returning its handle;
+    /// returns `stmt` if `at` is not within the Blk.
+    pub fn insert_before<T: BlkStmt>(&mut self, mut at: StmtIdx<T>, stmt: Entity<T>) -> Result<StmtIdx<T>, Entity<T>> {
+        match self.resolve(&mut at) {
+            Some(index) => Ok(self.insert_stmt(index, stmt)),
+            None => Err(stmt),
+        }
+    }
+
+    /// Inserts `stmt` after the statement of `at`, returning its handle;
+    /// returns `stmt` if `at` is not within the Blk.
+    pub fn insert_after<T: BlkStmt>(&mut self, mut at: StmtIdx<T>, stmt: Entity<T>) -> Result<StmtIdx<T>, Entity<T>> {
+        match self.resolve(&mut at) {
+            Some(index) => Ok(self.insert_stmt(index + 1, stmt)),
+            None => Err(stmt),
+        }
+    }
+
+    /// Appends `stmt` to the statements of its kind, returning its handle.
+    pub fn push_stmt<T: BlkStmt>(&mut self, stmt: Entity<T>) -> StmtIdx<T> {
+        let index = T::stmts(self).len();
+        self.insert_stmt(index, stmt)
+    }
+
+    fn insert_stmt<T: BlkStmt>(&mut self, index: usize, stmt: Entity<T>) -> StmtIdx<T> {
+        let idx = StmtIdx::new(stmt.id(), index);
+        T::stmts_mut(self).insert(index, stmt);
+        idx
+    }
+
+    /// Removes the statement of `idx`, if it is within the Blk.
+    pub fn remove_stmt<T: BlkStmt>(&mut self, mut idx: StmtIdx<T>) -> Option<Entity<T>> {
+        let index = self.resolve(&mut idx)?;
+        Some(T::stmts_mut(self).remove(index))
+    }
+
+    /// Moves the statement of `stmt` before that of `at`; returns false
+    /// (and leaves the Blk unchanged) if either is not within the Blk.
+    pub fn move_before<T: BlkStmt>(&mut self, stmt: StmtIdx<T>, at: StmtIdx<T>) -> bool {
+        self.move_stmt(stmt, at, false)
+    }
+
+    /// Moves the statement of `stmt` after that of `at`; returns false
+    /// (and leaves the Blk unchanged) if either is not within the Blk.
+    pub fn move_after<T: BlkStmt>(&mut self, stmt: StmtIdx<T>, at: StmtIdx<T>) -> bool {
+        self.move_stmt(stmt, at, true)
+    }
+
+    fn move_stmt<T: BlkStmt>(&mut self, mut stmt: StmtIdx<T>, mut at: StmtIdx<T>, after: bool) -> bool {
+        let (from, to) = match (self.resolve(&mut stmt), self.resolve(&mut at)) {
+            (Some(from), Some(to)) => (from, to),
+            _ => return false,
+        };
+
+        if from == to {
+            return true
+        }
+
+        let stmts = T::stmts_mut(self);
+        let moved = stmts.remove(from);
+        // at shifts down if stmt preceded it
+        let to = if from < to { to - 1 } else { to };
+        stmts.insert(if after { to + 1 } else { to }, moved);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::{Expr, Var};
+    use crate::types::bv::BitVecT;
+
+    fn def(name: &str) -> Entity<Def> {
+        let var = Var::transient(name, BitVecT::with_bits(8, false));
+        Def::assign(var.clone(), Expr::from(var))
+    }
+
+    fn names(blk: &Blk) -> Vec<String> {
+        blk.defs()
+            .iter()
+            .map(|def| match **def {
+                Def::Assign(ref var, _) => var.name().to_string(),
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_handles_survive_edits() {
+        let mut blk = Blk::new(None);
+        let a = blk.push_stmt(def("a"));
+        let b = blk.push_stmt(def("b"));
+        let c = blk.push_stmt(def("c"));
+
+        let x = blk.insert_before(a, def("x")).unwrap_or_else(|_| panic!("a is not within the Blk"));
+        assert_eq!(names(&blk), ["x", "a", "b", "c"]);
+
+        // c was seen at index 2, but is now at index 3
+        assert!(blk.insert_after(c, def("y")).is_ok());
+        assert_eq!(names(&blk), ["x", "a", "b", "c", "y"]);
+
+        assert!(blk.move_after(x, c));
+        assert_eq!(names(&blk), ["a", "b", "c", "x", "y"]);
+        assert!(blk.move_before(c, a));
+        assert_eq!(names(&blk), ["c", "a", "b", "x", "y"]);
+
+        assert!(blk.remove_stmt(b).is_some());
+        assert!(blk.remove_stmt(b).is_none());
+        assert!(blk.stmt(b).is_none());
+        assert!(!blk.move_before(a, b));
+        assert_eq!(names(&blk), ["c", "a", "x", "y"]);
+
+        assert_eq!(blk.next_stmt(c), Some(a));
+        assert_eq!(blk.prev_stmt(c), None);
+        assert_eq!(blk.stmt_idx(x), blk.next_stmt(a));
+    }
+}
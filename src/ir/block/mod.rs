@@ -1,16 +1,52 @@
-use crate::ir::{Addr, Def, Jmp, Phi};
-use crate::prelude::{Identifiable, Entity};
+use crate::ir::{Addr, Def, Jmp, Loc, Phi, SourceLoc};
+use crate::prelude::{Identifiable, Entity, Id, Tagged};
 
 use std::mem::take;
+use std::sync::Arc;
+
+mod index;
+pub use index::{BlkStmt, DefIdx, JmpIdx, PhiIdx, StmtIdx};
+
+/// The granularity of the Blks of a Sub (see `Sub::normalise_blocks`).
+///
+/// Each instruction is lifted into one or more Blks, each of which is a
+/// strict basic block, i.e., flows only leave at its end. Merging joins a
+/// Blk with its successor when the Blk falls through to it unconditionally
+/// and is its only predecessor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BlkMerge {
+    /// Keep the Blks as lifted
+    Strict,
+    /// Merge fall-through chains, but not across the starts of the blocks
+    /// of IDA's model (i.e., the groups of Blks lifted together by
+    /// `Project::add_blk`), so that each Blk is within one of them
+    Ida,
+    /// Merge all fall-through chains, yielding maximal basic blocks
+    Chains,
+}
+
+impl Default for BlkMerge {
+    fn default() -> Self {
+        Self::Strict
+    }
+}
 
 #[derive(Clone)]
 pub struct Blk {
     addr: Option<Addr>,
+    // a valid target for indirect flows (e.g., an AArch64 BTI)
+    landing_pad: bool,
+    // the instruction the Blk was lifted from
+    source: Option<Arc<SourceLoc>>,
     phis: Vec<Entity<Phi>>,
     defs: Vec<Entity<Def>>,
     jmps: Vec<Entity<Jmp>>,
 }
 
+impl Tagged for Blk {
+    const TAG: &'static str = "blk";
+}
+
 impl Blk {
     pub fn new(addr: impl Into<Option<Addr>>) -> Entity<Blk> {
         Self::new_with(
@@ -24,23 +60,63 @@ impl Blk {
     pub fn new_with(addr: impl Into<Option<Addr>>, phis: Vec<Entity<Phi>>, defs: Vec<Entity<Def>>, jmps: Vec<Entity<Jmp>>) -> Entity<Blk> {
         Entity::new("blk", Self {
             addr: addr.into(),
+            landing_pad: false,
+            source: None,
             phis,
             defs,
             jmps,
         })
     }
     
+    pub fn addr(&self) -> Option<&Addr> {
+        self.addr.as_ref()
+    }
+    
+    pub fn is_landing_pad(&self) -> bool {
+        self.landing_pad
+    }
+
+    pub fn set_landing_pad(&mut self, landing_pad: bool) {
+        self.landing_pad = landing_pad;
+    }
+    
+    /// The instruction the Blk was lifted from, if it was lifted; the Blks
+    /// lifted from the same instruction share its location.
+    pub fn source(&self) -> Option<&SourceLoc> {
+        self.source.as_deref()
+    }
+
+    pub fn set_source(&mut self, source: impl Into<Option<Arc<SourceLoc>>>) {
+        self.source = source.into();
+    }
+
     pub fn defs(&self) -> &[Entity<Def>] {
         &self.defs
     }
     
+    pub fn defs_mut(&mut self) -> &mut Vec<Entity<Def>> {
+        &mut self.defs
+    }
+    
     pub fn phis(&self) -> &[Entity<Phi>] {
         &self.phis
     }
 
+    pub fn phis_mut(&mut self) -> &mut Vec<Entity<Phi>> {
+        &mut self.phis
+    }
+
     pub fn jmps(&self) -> &[Entity<Jmp>] {
         &self.jmps
     }
+
+    pub fn jmps_mut(&mut self) -> &mut Vec<Entity<Jmp>> {
+        &mut self.jmps
+    }
+
+    pub fn parts_mut(&mut self) -> (&mut Vec<Entity<Phi>>, &mut Vec<Entity<Def>>, &mut Vec<Entity<Jmp>>) {
+        (&mut self.phis, &mut self.defs, &mut self.jmps)
+    }
     
     pub fn add_def(&mut self, def: Entity<Def>) {
         self.defs.push(def);
@@ -54,6 +130,35 @@ impl Blk {
         self.jmps.push(jmp);
     } 
     
+    /// The successor the Blk falls through to unconditionally, if its only
+    /// flow is a branch to a Blk.
+    pub fn fall_through(&self) -> Option<Id<Blk>> {
+        match self.jmps.as_slice() {
+            [jmp] => match **jmp {
+                Jmp::Branch(Loc::Resolved(id)) => Some(id),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Appends the statements of `other` to the Blk, replacing its flows
+    /// with those of `other`; the Blk must fall through to `other` (see
+    /// `fall_through`), and `other` must have no phis and not be a landing
+    /// pad. The Blk keeps its address and source location. Returns `other`
+    /// if the Blks cannot be merged.
+    pub fn merge(&mut self, other: Entity<Blk>) -> Result<(), Entity<Blk>> {
+        if self.fall_through() != Some(other.id()) || !other.phis.is_empty() || other.landing_pad {
+            return Err(other)
+        }
+
+        let other = other.into_value();
+        self.defs.extend(other.defs);
+        self.jmps = other.jmps;
+
+        Ok(())
+    }
+
     fn split_off(&mut self, pos: Option<usize>) -> Entity<Self> {
         let ndefs = if let Some(pos) = pos {
             self.defs.split_off(pos)
@@ -61,12 +166,13 @@ impl Blk {
             Default::default()
         };
 
-        let nblk = Self::new_with(
+        let mut nblk = Self::new_with(
             None,
             Default::default(),
             ndefs,
             take(&mut self.jmps),
         );
+        nblk.source = self.source.clone();
         
         self.add_jmp(Jmp::branch(nblk.id()));
         
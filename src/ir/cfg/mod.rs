@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use petgraph::graphmap::DiGraphMap;
+use petgraph::visit::DfsPostOrder;
+use petgraph::Direction;
+
+use crate::export::TokenExport;
+use crate::ir::{Blk, Jmp, Loc, Sub};
+use crate::prelude::{Id, Identifiable};
+
+/// The kind of flow an edge of a Cfg represents.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Edge {
+    /// An unconditional branch, including fall-through
+    Branch,
+    /// A conditional branch, taken when its condition holds
+    CBranch,
+    /// A branch to a known target of a computed branch preceding it (see
+    /// `JumpTable::materialise`)
+    Switch,
+}
+
+/// The control-flow graph of a Sub, over the flows between its Blks that
+/// have been resolved (i.e., via `Loc::Resolved`).
+#[derive(Debug, Clone, Default)]
+pub struct Cfg {
+    entry: Option<Id<Blk>>,
+    graph: DiGraphMap<Id<Blk>, Edge>,
+}
+
+impl Cfg {
+    pub fn new(sub: &Sub) -> Self {
+        let mut graph = DiGraphMap::new();
+
+        for blk in sub.blks() {
+            graph.add_node(blk.id());
+        }
+
+        for blk in sub.blks() {
+            let mut computed = false;
+            for jmp in blk.jmps() {
+                let (id, edge) = match **jmp {
+                    Jmp::Branch(Loc::Computed(_)) => {
+                        computed = true;
+                        continue
+                    }
+                    Jmp::Branch(Loc::Resolved(id)) if computed => (id, Edge::Switch),
+                    Jmp::Branch(Loc::Resolved(id)) => (id, Edge::Branch),
+                    Jmp::CBranch(Loc::Resolved(id), _) => (id, Edge::CBranch),
+                    _ => continue,
+                };
+
+                // the first flow between two Blks determines the kind of
+                // its edge
+                if graph.contains_node(id) && !graph.contains_edge(blk.id(), id) {
+                    graph.add_edge(blk.id(), id, edge);
+                }
+            }
+        }
+
+        Self {
+            entry: sub.entry().map(|blk| blk.id()),
+            graph,
+        }
+    }
+
+    pub fn entry(&self) -> Option<Id<Blk>> {
+        self.entry
+    }
+
+    pub fn graph(&self) -> &DiGraphMap<Id<Blk>, Edge> {
+        &self.graph
+    }
+
+    pub fn len(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.graph.node_count() == 0
+    }
+
+    pub fn contains(&self, blk: impl Identifiable<Blk>) -> bool {
+        self.graph.contains_node(blk.id())
+    }
+
+    pub fn blks(&self) -> impl Iterator<Item = Id<Blk>> + '_ {
+        self.graph.nodes()
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = (Id<Blk>, Id<Blk>, Edge)> + '_ {
+        self.graph.all_edges().map(|(from, to, edge)| (from, to, *edge))
+    }
+
+    pub fn successors(&self, blk: impl Identifiable<Blk>) -> impl Iterator<Item = Id<Blk>> + '_ {
+        self.graph.neighbors_directed(blk.id(), Direction::Outgoing)
+    }
+
+    pub fn predecessors(&self, blk: impl Identifiable<Blk>) -> impl Iterator<Item = Id<Blk>> + '_ {
+        self.graph.neighbors_directed(blk.id(), Direction::Incoming)
+    }
+
+    /// The Blks of the Cfg in reverse post-order from its entry; Blks that
+    /// are unreachable from the entry follow, in no particular order.
+    pub fn reverse_post_order(&self) -> Vec<Id<Blk>> {
+        let mut order = Vec::with_capacity(self.len());
+
+        if let Some(entry) = self.entry {
+            let mut dfs = DfsPostOrder::new(&self.graph, entry);
+            while let Some(id) = dfs.next(&self.graph) {
+                order.push(id);
+            }
+        }
+
+        order.reverse();
+
+        if order.len() != self.len() {
+            let reached = order.iter().copied().collect::<std::collections::BTreeSet<_>>();
+            order.extend(self.blks().filter(|id| !reached.contains(id)));
+        }
+
+        order
+    }
+
+    /// Renders the Cfg of `sub` in DOT. Blks are labelled `b0`, `b1`, ...
+    /// in reverse post-order (with their addresses, if any), followed by
+    /// their statements as tokens (see `TokenExport`); edges of conditional
+    /// branches are dashed, and labelled with their conditions, and those
+    /// to the targets of computed branches are dotted.
+    pub fn to_dot(&self, sub: &Sub) -> String {
+        let order = self.reverse_post_order();
+        let labels = order
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (*id, i))
+            .collect::<BTreeMap<_, _>>();
+        let export = TokenExport::new();
+
+        let mut dot = String::new();
+
+        // writes to strings are infallible
+        let _ = writeln!(dot, "digraph cfg {{");
+        let _ = writeln!(dot, "  node [shape=box, fontname=\"monospace\"];");
+
+        for (i, id) in order.iter().enumerate() {
+            let mut label = format!("b{}", i);
+            let blk = sub.blk(*id);
+            if let Some(addr) = blk.and_then(|blk| blk.addr()) {
+                let _ = write!(label, " @ {}", addr);
+            }
+            label.push_str("\\l");
+
+            for stmt in blk.map(|blk| export.statements(sub.addr(), &labels, blk)).unwrap_or_default() {
+                label.push_str(&escape(&stmt.join(" ")));
+                label.push_str("\\l");
+            }
+
+            let _ = write!(dot, "  n{} [label=\"{}\"", i, label);
+            if Some(*id) == self.entry {
+                let _ = write!(dot, ", style=bold");
+            }
+            let _ = writeln!(dot, "];");
+        }
+
+        for (from, to, edge) in self.edges() {
+            let _ = write!(dot, "  n{} -> n{} [", labels[&from], labels[&to]);
+            match edge {
+                Edge::Branch => {
+                    let _ = write!(dot, "style=solid");
+                }
+                Edge::Switch => {
+                    let _ = write!(dot, "style=dotted");
+                }
+                Edge::CBranch => {
+                    let _ = write!(dot, "style=dashed");
+                    let cond = sub.blk(from).and_then(|blk| {
+                        blk.jmps().iter().find_map(|jmp| match **jmp {
+                            Jmp::CBranch(Loc::Resolved(id), ref cond) if id == to => Some(cond),
+                            _ => None,
+                        })
+                    });
+                    if let Some(cond) = cond {
+                        let _ = write!(dot, ", label=\"{}\"", escape(&export.expr(sub.addr(), cond).join(" ")));
+                    }
+                }
+            }
+            let _ = writeln!(dot, "];");
+        }
+
+        let _ = writeln!(dot, "}}");
+        dot
+    }
+}
+
+/// Escapes `s` for use within a quoted DOT string.
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,312 @@
+use std::collections::BTreeSet;
+
+use thiserror::Error;
+
+use crate::ir::{Blk, Def, Expr, Jmp, Loc, Sub};
+use crate::prelude::{Erased, Id, Identifiable};
+
+#[derive(Debug, Error)]
+pub enum ChangeError {
+    #[error("statement {0} is not within the Sub")]
+    UnknownStmt(Id<Erased>),
+    #[error("Blk {0} is not within the Sub")]
+    UnknownBlk(Id<Blk>),
+    #[error("the expression to replace does not occur in statement {0}")]
+    MissingExpr(Id<Erased>),
+    #[error("replacing a {0}-bit expression with a {1}-bit expression")]
+    Size(u32, u32),
+    #[error("jmp {0} has no target to redirect")]
+    Unredirectable(Id<Jmp>),
+    #[error("statement {0} is changed after it is deleted, or redirected twice")]
+    Conflict(Id<Erased>),
+}
+
+/// An edit of the IR of a Sub, recorded in a ChangeSet.
+#[derive(Clone)]
+pub enum Change {
+    /// Replace each occurrence of `old` within the expressions of the
+    /// statement `stmt` (a Phi, Def, or Jmp) with `new`
+    ReplaceExpr {
+        stmt: Id<Erased>,
+        old: Expr,
+        new: Expr,
+    },
+    /// Remove the def from its Blk
+    DeleteDef(Id<Def>),
+    /// Replace the target of the jmp
+    RedirectJmp { jmp: Id<Jmp>, target: Loc },
+}
+
+/// A sequence of edits of the IR of a Sub, applied atomically.
+///
+/// Passes (and analyses) can record the edits they intend to make while
+/// holding the Sub immutably, e.g., while analysing many Subs in parallel;
+/// the edits are then validated and applied together by `apply` (or
+/// `Project::apply_changes`), or previewed without modifying the Sub by
+/// `preview`. Edits are applied in the order they are recorded; if any edit
+/// cannot be applied (e.g., its statement is not within the Sub), none are.
+#[derive(Clone, Default)]
+pub struct ChangeSet {
+    changes: Vec<Change>,
+}
+
+impl ChangeSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the replacement of `old` with `new` within the expressions
+    /// of `stmt`; both must have the same size.
+    pub fn replace_expr<V>(&mut self, stmt: impl Identifiable<V>, old: impl Into<Expr>, new: impl Into<Expr>) -> &mut Self {
+        self.changes.push(Change::ReplaceExpr {
+            stmt: stmt.id().erase(),
+            old: old.into(),
+            new: new.into(),
+        });
+        self
+    }
+
+    pub fn delete_def(&mut self, def: impl Identifiable<Def>) -> &mut Self {
+        self.changes.push(Change::DeleteDef(def.id()));
+        self
+    }
+
+    /// Records the redirection of `jmp` to `target`; resolved targets must
+    /// be Blks of the Sub the changes are applied to.
+    pub fn redirect_jmp(&mut self, jmp: impl Identifiable<Jmp>, target: impl Into<Loc>) -> &mut Self {
+        self.changes.push(Change::RedirectJmp {
+            jmp: jmp.id(),
+            target: target.into(),
+        });
+        self
+    }
+
+    /// Appends the changes of `other`, e.g., those recorded by another
+    /// pass or thread.
+    pub fn extend(&mut self, other: ChangeSet) -> &mut Self {
+        self.changes.extend(other.changes);
+        self
+    }
+
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.changes.clear();
+    }
+
+    /// Checks that the changes can be applied to `sub`.
+    pub fn validate(&self, sub: &Sub) -> Result<(), ChangeError> {
+        self.preview(sub).map(|_| ())
+    }
+
+    /// A copy of `sub` with the changes applied; `sub` is not modified.
+    pub fn preview(&self, sub: &Sub) -> Result<Sub, ChangeError> {
+        let mut nsub = sub.clone();
+        self.apply_unchecked(&mut nsub)?;
+        Ok(nsub)
+    }
+
+    /// Applies the changes to `sub`, if all of them can be applied;
+    /// otherwise, `sub` is not modified. Returns the number of changes
+    /// applied.
+    pub fn apply(&self, sub: &mut Sub) -> Result<usize, ChangeError> {
+        *sub = self.preview(sub)?;
+        Ok(self.changes.len())
+    }
+
+    // applies the changes in order, stopping at the first that cannot be
+    // applied
+    fn apply_unchecked(&self, sub: &mut Sub) -> Result<(), ChangeError> {
+        let blks = sub.blks().iter().map(|blk| blk.id()).collect::<BTreeSet<_>>();
+        let mut deleted = BTreeSet::new();
+        let mut redirected = BTreeSet::new();
+
+        for change in self.changes.iter() {
+            match change {
+                Change::ReplaceExpr { stmt, old, new } => {
+                    if old.bits() != new.bits() {
+                        return Err(ChangeError::Size(old.bits(), new.bits()))
+                    }
+                    if deleted.contains(stmt) {
+                        return Err(ChangeError::Conflict(*stmt))
+                    }
+
+                    let count = sub
+                        .blks_mut()
+                        .iter_mut()
+                        .find_map(|blk| replace_in_stmt(blk, *stmt, old, new))
+                        .ok_or(ChangeError::UnknownStmt(*stmt))?;
+
+                    if count == 0 {
+                        return Err(ChangeError::MissingExpr(*stmt))
+                    }
+                }
+                Change::DeleteDef(def) => {
+                    let id = def.erase();
+                    if !deleted.insert(id) {
+                        return Err(ChangeError::Conflict(id))
+                    }
+
+                    let blk = sub
+                        .blks_mut()
+                        .iter_mut()
+                        .find(|blk| blk.defs().iter().any(|bdef| bdef.id() == *def))
+                        .ok_or(ChangeError::UnknownStmt(id))?;
+                    blk.defs_mut().retain(|bdef| bdef.id() != *def);
+                }
+                Change::RedirectJmp { jmp, target } => {
+                    let id = jmp.erase();
+                    if !redirected.insert(id) {
+                        return Err(ChangeError::Conflict(id))
+                    }
+                    if let Loc::Resolved(blk) = target {
+                        if !blks.contains(blk) {
+                            return Err(ChangeError::UnknownBlk(*blk))
+                        }
+                    }
+
+                    let bjmp = sub
+                        .blks_mut()
+                        .iter_mut()
+                        .flat_map(|blk| blk.jmps_mut().iter_mut())
+                        .find(|bjmp| bjmp.id() == *jmp)
+                        .ok_or(ChangeError::UnknownStmt(id))?;
+
+                    match **bjmp {
+                        Jmp::Branch(ref mut loc)
+                        | Jmp::CBranch(ref mut loc, _)
+                        | Jmp::Call(ref mut loc, _)
+                        | Jmp::Return(ref mut loc) => *loc = target.clone(),
+                        Jmp::Intrinsic(_, _) => return Err(ChangeError::Unredirectable(*jmp)),
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// replaces old with new within the statement stmt of blk; returns the
+// number of occurrences replaced, or None if stmt is not within blk
+fn replace_in_stmt(blk: &mut Blk, stmt: Id<Erased>, old: &Expr, new: &Expr) -> Option<usize> {
+    let (phis, defs, jmps) = blk.parts_mut();
+
+    if let Some(phi) = phis.iter_mut().find(|phi| phi.id().erase() == stmt) {
+        return Some(phi.choices_mut().iter_mut().fold(0, |count, (cond, expr)| {
+            count + replace_expr(cond, old, new) + replace_expr(expr, old, new)
+        }))
+    }
+
+    if let Some(def) = defs.iter_mut().find(|def| def.id().erase() == stmt) {
+        return Some(match **def {
+            Def::Assign(_, ref mut expr) | Def::Assume(ref mut expr) => replace_expr(expr, old, new),
+            Def::Store(ref mut loc, ref mut val, _, _) => {
+                replace_expr(loc, old, new) + replace_expr(val, old, new)
+            }
+        })
+    }
+
+    let jmp = jmps.iter_mut().find(|jmp| jmp.id().erase() == stmt)?;
+    let replace_loc = |loc: &mut Loc| match loc {
+        Loc::Computed(ref mut expr) => replace_expr(expr, old, new),
+        _ => 0,
+    };
+
+    Some(match **jmp {
+        Jmp::Branch(ref mut loc) | Jmp::Return(ref mut loc) => replace_loc(loc),
+        Jmp::CBranch(ref mut loc, ref mut cond) => replace_loc(loc) + replace_expr(cond, old, new),
+        Jmp::Call(ref mut loc, ref mut args) => {
+            replace_loc(loc) + args.iter_mut().map(|arg| replace_expr(arg, old, new)).sum::<usize>()
+        }
+        Jmp::Intrinsic(_, ref mut args) => args.iter_mut().map(|arg| replace_expr(arg, old, new)).sum(),
+    })
+}
+
+// replaces each outermost occurrence of old within expr with new
+fn replace_expr(expr: &mut Expr, old: &Expr, new: &Expr) -> usize {
+    if expr == old {
+        *expr = new.clone();
+        return 1
+    }
+
+    expr.operands_mut()
+        .into_iter()
+        .map(|operand| replace_expr(operand, old, new))
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::Var;
+    use crate::prelude::Entity;
+    use crate::types::bv::BitVecT;
+
+    fn var(name: &str) -> Expr {
+        Var::transient(name, BitVecT::with_bits(8, false)).into()
+    }
+
+    #[test]
+    fn test_apply_atomically() -> Result<(), ChangeError> {
+        let mut entry = Blk::new(None);
+        let mut exit = Blk::new(None);
+        let other = Blk::new(None);
+
+        let x = Var::transient("x", BitVecT::with_bits(8, false));
+        let keep = Def::assign(x.clone(), var("a"));
+        let dead = Def::assign(x, var("b"));
+        let (keep_id, dead_id) = (keep.id(), dead.id());
+        entry.add_def(keep);
+        entry.add_def(dead);
+
+        let jmp = Jmp::branch(other.id());
+        let jmp_id = jmp.id();
+        entry.add_jmp(jmp);
+        exit.add_jmp(Jmp::return_(var("ra")));
+
+        let exit_id = exit.id();
+        let mut sub: Entity<Sub> = Sub::new(None, None, vec![entry, exit]);
+
+        // the target is not within the Sub, so none of the changes apply
+        let mut changes = ChangeSet::new();
+        changes
+            .replace_expr(keep_id, var("a"), var("c"))
+            .delete_def(dead_id)
+            .redirect_jmp(jmp_id, other.id());
+        assert!(matches!(changes.apply(&mut sub), Err(ChangeError::UnknownBlk(_))));
+        assert_eq!(sub.blks()[0].defs().len(), 2);
+
+        changes.clear();
+        changes
+            .replace_expr(keep_id, var("a"), var("c"))
+            .delete_def(dead_id)
+            .redirect_jmp(jmp_id, exit_id);
+
+        let preview = changes.preview(&sub)?;
+        assert_eq!(sub.blks()[0].defs().len(), 2);
+        assert_eq!(preview.blks()[0].defs().len(), 1);
+
+        assert_eq!(changes.apply(&mut sub)?, 3);
+        let entry = &sub.blks()[0];
+        assert!(matches!(*entry.defs()[0], Def::Assign(_, ref expr) if *expr == var("c")));
+        assert_eq!(entry.fall_through(), Some(exit_id));
+
+        // the def is no longer within the Sub
+        changes.clear();
+        changes.delete_def(dead_id);
+        assert!(matches!(changes.validate(&sub), Err(ChangeError::UnknownStmt(_))));
+
+        Ok(())
+    }
+}
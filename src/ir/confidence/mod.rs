@@ -0,0 +1,41 @@
+use std::fmt::{self, Display};
+
+/// How much a recovered fact (e.g., the start of a Sub, or the target of a
+/// computed branch) can be trusted. Confidence is recorded as an annotation
+/// on the entities of a Project (see `Project::confidence`).
+///
+/// Levels are ordered from least to most trusted; a fact derived from
+/// others is only as trusted as the least trusted of them (see `combine`).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Confidence {
+    /// Recovered by a heuristic, e.g., a prologue pattern
+    Heuristic,
+    /// Recovered by an analysis whose assumptions may not hold, or given
+    /// by an external tool, e.g., a bounded jump table
+    Likely,
+    /// Given by the user, observed at runtime, or implied by code that is
+    /// itself certain, e.g., the fixed target of a call
+    Certain,
+}
+
+impl Confidence {
+    /// The confidence of a fact derived from facts with confidences `self`
+    /// and `other`.
+    pub fn combine(self, other: Self) -> Self {
+        self.min(other)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Heuristic => "heuristic",
+            Self::Likely => "likely",
+            Self::Certain => "certain",
+        }
+    }
+}
+
+impl Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -1,14 +1,20 @@
 use crate::ir::{Expr, Loc, Var};
-use crate::prelude::Entity;
+use crate::prelude::{intern, Entity, Tagged};
 
 use std::sync::Arc;
 use smallvec::SmallVec;
 
 // effects that affect data flow
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub enum Def {
     Assign(Var, Expr),
     Assume(Expr),
+    // address, value, bits, memory
+    Store(Expr, Expr, u32, Var),
+}
+
+impl Tagged for Def {
+    const TAG: &'static str = "def";
 }
 
 impl Def {
@@ -19,10 +25,14 @@ impl Def {
     pub fn assume(cnd: impl Into<Expr>) -> Entity<Self> {
         Entity::new("def", Self::Assume(cnd.into()))
     }
+
+    pub fn store(loc: impl Into<Expr>, val: impl Into<Expr>, bits: u32, mem: impl Into<Var>) -> Entity<Self> {
+        Entity::new("def", Self::Store(loc.into(), val.into(), bits, mem.into()))
+    }
 }
 
 // effects that affect control flow
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub enum Jmp {
     Branch(Loc),
     CBranch(Loc, Expr),
@@ -31,6 +41,10 @@ pub enum Jmp {
     Return(Loc),
 }
 
+impl Tagged for Jmp {
+    const TAG: &'static str = "jmp";
+}
+
 impl Jmp {
     pub fn branch(loc: impl Into<Loc>) -> Entity<Self> {
         Entity::new("jmp", Self::Branch(loc.into()))
@@ -39,4 +53,17 @@ impl Jmp {
     pub fn cbranch(loc: impl Into<Loc>, cnd: impl Into<Expr>) -> Entity<Self> {
         Entity::new("jmp", Self::CBranch(loc.into(), cnd.into()))
     }
+
+    pub fn call(loc: impl Into<Loc>, args: impl IntoIterator<Item = Expr>) -> Entity<Self> {
+        Entity::new("jmp", Self::Call(loc.into(), args.into_iter().collect()))
+    }
+
+    /// A jmp to the intrinsic named `name` (interned; see `Interner`).
+    pub fn intrinsic(name: impl AsRef<str>, args: impl IntoIterator<Item = Expr>) -> Entity<Self> {
+        Entity::new("jmp", Self::Intrinsic(intern(name.as_ref()), args.into_iter().collect()))
+    }
+
+    pub fn return_(loc: impl Into<Loc>) -> Entity<Self> {
+        Entity::new("jmp", Self::Return(loc.into()))
+    }
 }
\ No newline at end of file
@@ -0,0 +1,87 @@
+use crate::ir::Expr;
+
+/// Assigns costs to expressions; passes that rewrite expressions (e.g.,
+/// simplification) use a cost model to avoid rewrites that make
+/// expressions worse, and users can provide their own to tune the form of
+/// the expressions they obtain.
+pub trait CostModel {
+    /// The cost of the root node of `expr`, excluding its operands.
+    fn node_cost(&self, expr: &Expr) -> u64;
+
+    /// The cost of `expr`; by default, the sum of the costs of its nodes.
+    fn cost(&self, expr: &Expr) -> u64 {
+        self.node_cost(expr)
+            + expr
+                .operands()
+                .into_iter()
+                .map(|expr| self.cost(expr))
+                .sum::<u64>()
+    }
+}
+
+/// A cost model assigning a fixed weight to each kind of expression node;
+/// by default, every node has a weight of one, i.e., the cost of an
+/// expression is its node count.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExprCost {
+    pub val: u64,
+    pub var: u64,
+    pub un_op: u64,
+    pub bin_op: u64,
+    pub un_rel: u64,
+    pub bin_rel: u64,
+    pub cast: u64,
+    pub load: u64,
+    pub extract: u64,
+    pub concat: u64,
+    pub ite: u64,
+    pub intrinsic: u64,
+}
+
+impl Default for ExprCost {
+    fn default() -> Self {
+        Self {
+            val: 1,
+            var: 1,
+            un_op: 1,
+            bin_op: 1,
+            un_rel: 1,
+            bin_rel: 1,
+            cast: 1,
+            load: 1,
+            extract: 1,
+            concat: 1,
+            ite: 1,
+            intrinsic: 1,
+        }
+    }
+}
+
+impl CostModel for ExprCost {
+    fn node_cost(&self, expr: &Expr) -> u64 {
+        match expr {
+            Expr::UnRel(_, _) => self.un_rel,
+            Expr::BinRel(_, _, _) => self.bin_rel,
+            Expr::UnOp(_, _) => self.un_op,
+            Expr::BinOp(_, _, _) => self.bin_op,
+            Expr::Cast(_, _) => self.cast,
+            Expr::Load(_, _, _) => self.load,
+            Expr::Extract(_, _, _) => self.extract,
+            Expr::Concat(_, _) => self.concat,
+            Expr::IfElse(_, _, _) => self.ite,
+            Expr::Intrinsic(_, _, _) => self.intrinsic,
+            Expr::Val(_) => self.val,
+            Expr::Var(_) => self.var,
+        }
+    }
+}
+
+impl<C> CostModel for &C where C: CostModel + ?Sized {
+    fn node_cost(&self, expr: &Expr) -> u64 {
+        (**self).node_cost(expr)
+    }
+
+    fn cost(&self, expr: &Expr) -> u64 {
+        (**self).cost(expr)
+    }
+}
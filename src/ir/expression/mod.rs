@@ -1,5 +1,301 @@
+use crate::ir::{BitVec, Var};
+use crate::prelude::{intern, Entity};
+
+use smallvec::SmallVec;
+use std::sync::Arc;
+
+pub mod cost;
+pub use cost::{CostModel, ExprCost};
+
 #[derive(Clone)]
 pub struct Condition;
 
-#[derive(Clone)]
-pub struct Expr;
\ No newline at end of file
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum UnOp {
+    Not,
+    Neg,
+    Abs,
+    Sqrt,
+    Ceiling,
+    Floor,
+    Round,
+    PopCount,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum UnRel {
+    Nan,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BinOp {
+    And,
+    Or,
+    Xor,
+    Add,
+    Sub,
+    Div,
+    SDiv,
+    Mul,
+    Rem,
+    SRem,
+    Shl,
+    Sar,
+    Shr,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BinRel {
+    Eq,
+    Neq,
+    Lt,
+    Le,
+    SLt,
+    SLe,
+    SBorrow,
+    Carry,
+    SCarry,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Cast {
+    Bool,
+    Float(u32),
+    Signed(u32),
+    Unsigned(u32),
+    High(u32),
+    Low(u32),
+}
+
+impl Cast {
+    pub fn bits(&self) -> u32 {
+        match self {
+            Self::Bool => BOOL_BITS,
+            Self::Float(bits)
+            | Self::Signed(bits)
+            | Self::Unsigned(bits)
+            | Self::High(bits)
+            | Self::Low(bits) => *bits,
+        }
+    }
+}
+
+// booleans are represented as bytes, as in crate::types::BOOL
+const BOOL_BITS: u32 = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Expr {
+    UnRel(UnRel, Box<Expr>),
+    BinRel(BinRel, Box<Expr>, Box<Expr>),
+    UnOp(UnOp, Box<Expr>),
+    BinOp(BinOp, Box<Expr>, Box<Expr>),
+    Cast(Box<Expr>, Cast),
+    // address, bits, memory
+    Load(Box<Expr>, u32, Var),
+    // expr, lsb, msb (exclusive)
+    Extract(Box<Expr>, u32, u32),
+    Concat(Box<Expr>, Box<Expr>),
+    IfElse(Box<Expr>, Box<Expr>, Box<Expr>),
+    Intrinsic(Arc<str>, SmallVec<[Box<Expr>; 4]>, u32),
+    Val(BitVec),
+    Var(Var),
+}
+
+impl From<BitVec> for Expr {
+    fn from(bv: BitVec) -> Self {
+        Self::Val(bv)
+    }
+}
+
+impl From<Var> for Expr {
+    fn from(var: Var) -> Self {
+        Self::Var(var)
+    }
+}
+
+impl From<Entity<Var>> for Expr {
+    fn from(var: Entity<Var>) -> Self {
+        Self::Var(var.into_value())
+    }
+}
+
+impl Expr {
+    pub fn un_rel(op: UnRel, expr: impl Into<Expr>) -> Self {
+        Self::UnRel(op, Box::new(expr.into()))
+    }
+
+    pub fn bin_rel(op: BinRel, lexpr: impl Into<Expr>, rexpr: impl Into<Expr>) -> Self {
+        Self::BinRel(op, Box::new(lexpr.into()), Box::new(rexpr.into()))
+    }
+
+    pub fn un_op(op: UnOp, expr: impl Into<Expr>) -> Self {
+        Self::UnOp(op, Box::new(expr.into()))
+    }
+
+    pub fn bin_op(op: BinOp, lexpr: impl Into<Expr>, rexpr: impl Into<Expr>) -> Self {
+        Self::BinOp(op, Box::new(lexpr.into()), Box::new(rexpr.into()))
+    }
+
+    pub fn cast(expr: impl Into<Expr>, cast: Cast) -> Self {
+        Self::Cast(Box::new(expr.into()), cast)
+    }
+
+    pub fn load(expr: impl Into<Expr>, bits: u32, mem: impl Into<Var>) -> Self {
+        Self::Load(Box::new(expr.into()), bits, mem.into())
+    }
+
+    pub fn extract(expr: impl Into<Expr>, lsb: u32, msb: u32) -> Self {
+        Self::Extract(Box::new(expr.into()), lsb, msb)
+    }
+
+    pub fn extract_low(expr: impl Into<Expr>, bits: u32) -> Self {
+        Self::extract(expr, 0, bits)
+    }
+
+    /// The `bits` most significant bits of `expr`.
+    ///
+    /// Panics if `expr` has fewer than `bits` bits.
+    pub fn extract_high(expr: impl Into<Expr>, bits: u32) -> Self {
+        let expr = expr.into();
+        let ebits = expr.bits();
+        assert!(
+            bits <= ebits,
+            "cannot extract the high {} bits of an expression of {} bits",
+            bits,
+            ebits,
+        );
+        Self::extract(expr, ebits - bits, ebits)
+    }
+
+    pub fn concat(lexpr: impl Into<Expr>, rexpr: impl Into<Expr>) -> Self {
+        Self::Concat(Box::new(lexpr.into()), Box::new(rexpr.into()))
+    }
+
+    pub fn ite(cond: impl Into<Expr>, texpr: impl Into<Expr>, fexpr: impl Into<Expr>) -> Self {
+        Self::IfElse(
+            Box::new(cond.into()),
+            Box::new(texpr.into()),
+            Box::new(fexpr.into()),
+        )
+    }
+
+    /// An application of the intrinsic named `name` (interned; see
+    /// `Interner`).
+    pub fn intrinsic(
+        name: impl AsRef<str>,
+        args: impl IntoIterator<Item = Expr>,
+        bits: u32,
+    ) -> Self {
+        Self::Intrinsic(
+            intern(name.as_ref()),
+            args.into_iter().map(Box::new).collect(),
+            bits,
+        )
+    }
+
+    pub fn is_val(&self) -> bool {
+        matches!(self, Self::Val(_))
+    }
+
+    pub fn is_var(&self) -> bool {
+        matches!(self, Self::Var(_))
+    }
+
+    pub fn val(&self) -> Option<&BitVec> {
+        if let Self::Val(ref bv) = self {
+            Some(bv)
+        } else {
+            None
+        }
+    }
+
+    pub fn var(&self) -> Option<&Var> {
+        if let Self::Var(ref var) = self {
+            Some(var)
+        } else {
+            None
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        match self {
+            Self::UnRel(_, _) | Self::BinRel(_, _, _) => BOOL_BITS,
+            Self::UnOp(_, expr) => expr.bits(),
+            Self::BinOp(_, lexpr, _) => lexpr.bits(),
+            Self::Cast(_, cast) => cast.bits(),
+            Self::Load(_, bits, _) => *bits,
+            Self::Extract(_, lsb, msb) => msb - lsb,
+            Self::Concat(lexpr, rexpr) => lexpr.bits() + rexpr.bits(),
+            Self::IfElse(_, texpr, _) => texpr.bits(),
+            Self::Intrinsic(_, _, bits) => *bits,
+            Self::Val(bv) => bv.bits() as u32,
+            Self::Var(var) => var.bits().unwrap_or(0),
+        }
+    }
+
+    /// The immediate sub-expressions of this expression.
+    pub fn operands(&self) -> SmallVec<[&Expr; 4]> {
+        match self {
+            Self::UnRel(_, expr)
+            | Self::UnOp(_, expr)
+            | Self::Cast(expr, _)
+            | Self::Load(expr, _, _)
+            | Self::Extract(expr, _, _) => SmallVec::from_iter([&**expr]),
+            Self::BinRel(_, lexpr, rexpr)
+            | Self::BinOp(_, lexpr, rexpr)
+            | Self::Concat(lexpr, rexpr) => SmallVec::from_iter([&**lexpr, &**rexpr]),
+            Self::IfElse(cond, texpr, fexpr) => SmallVec::from_iter([&**cond, &**texpr, &**fexpr]),
+            Self::Intrinsic(_, args, _) => args.iter().map(|arg| &**arg).collect(),
+            Self::Val(_) | Self::Var(_) => SmallVec::new(),
+        }
+    }
+
+    /// The immediate sub-expressions of this expression (mutably).
+    pub fn operands_mut(&mut self) -> SmallVec<[&mut Expr; 4]> {
+        match self {
+            Self::UnRel(_, expr)
+            | Self::UnOp(_, expr)
+            | Self::Cast(expr, _)
+            | Self::Load(expr, _, _)
+            | Self::Extract(expr, _, _) => SmallVec::from_iter([&mut **expr]),
+            Self::BinRel(_, lexpr, rexpr)
+            | Self::BinOp(_, lexpr, rexpr)
+            | Self::Concat(lexpr, rexpr) => SmallVec::from_iter([&mut **lexpr, &mut **rexpr]),
+            Self::IfElse(cond, texpr, fexpr) => {
+                SmallVec::from_iter([&mut **cond, &mut **texpr, &mut **fexpr])
+            }
+            Self::Intrinsic(_, args, _) => args.iter_mut().map(|arg| &mut **arg).collect(),
+            Self::Val(_) | Self::Var(_) => SmallVec::new(),
+        }
+    }
+
+    /// Replaces each variable read by the expression for which `f` returns
+    /// an expression by that expression. The memories of loads are not
+    /// considered to be read.
+    pub fn substitute(&mut self, f: &mut impl FnMut(&Var) -> Option<Expr>) {
+        if let Self::Var(ref var) = self {
+            if let Some(expr) = f(var) {
+                *self = expr;
+            }
+        } else {
+            for expr in self.operands_mut() {
+                expr.substitute(f);
+            }
+        }
+    }
+
+    /// The number of nodes in the expression's tree.
+    pub fn node_count(&self) -> usize {
+        1 + self.operands().into_iter().map(Expr::node_count).sum::<usize>()
+    }
+
+    /// The length of the longest path from the root of the expression's
+    /// tree to a leaf; constants and variables have a depth of one.
+    pub fn depth(&self) -> usize {
+        1 + self.operands().into_iter().map(Expr::depth).max().unwrap_or(0)
+    }
+
+    pub fn cost(&self, model: &impl CostModel) -> u64 {
+        model.cost(self)
+    }
+}
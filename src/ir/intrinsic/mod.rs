@@ -0,0 +1,339 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::ir::{Def, Expr, Jmp, Sub, Var};
+use crate::prelude::{intern, Entity, Id};
+use crate::types::Type;
+
+/// Expands an intrinsic, given its arguments, into Defs with the same
+/// semantics; for intrinsic expressions, the Defs must assign the result
+/// to the variable given. Returns None if the intrinsic cannot be expanded
+/// for the given arguments.
+pub type IntrinsicExpansion = Arc<dyn Fn(&[Expr], Option<&Var>) -> Option<Vec<Entity<Def>>> + Send + Sync>;
+
+/// What an intrinsic may do other than compute its result.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SideEffects {
+    reads_memory: bool,
+    writes_memory: bool,
+    // the arguments through which memory may be written
+    written_args: BTreeSet<usize>,
+    clobbers: BTreeSet<Var>,
+    no_return: bool,
+    unknown: bool,
+}
+
+impl SideEffects {
+    /// No side-effects: the result of the intrinsic depends only on its
+    /// arguments.
+    pub fn pure() -> Self {
+        Self::default()
+    }
+
+    /// Arbitrary side-effects, as assumed for intrinsics not registered.
+    pub fn unknown() -> Self {
+        Self { unknown: true, ..Self::default() }
+    }
+
+    pub fn reads_memory(&mut self, reads: bool) -> &mut Self {
+        self.reads_memory = reads;
+        self
+    }
+
+    pub fn writes_memory(&mut self, writes: bool) -> &mut Self {
+        self.writes_memory = writes;
+        self
+    }
+
+    /// The memory pointed to by the argument at `index` may be written
+    /// (e.g., the buffer passed to `read`), but no other memory.
+    pub fn writes_argument(&mut self, index: usize) -> &mut Self {
+        self.written_args.insert(index);
+        self
+    }
+
+    /// The intrinsic may modify `var`.
+    pub fn clobber(&mut self, var: impl Into<Var>) -> &mut Self {
+        self.clobbers.insert(var.into());
+        self
+    }
+
+    /// The intrinsic never returns (e.g., `exit`).
+    pub fn no_return(&mut self, no_return: bool) -> &mut Self {
+        self.no_return = no_return;
+        self
+    }
+
+    pub fn is_pure(&self) -> bool {
+        *self == Self::pure()
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        self.unknown
+    }
+
+    pub fn may_read_memory(&self) -> bool {
+        self.unknown || self.reads_memory
+    }
+
+    pub fn may_write_memory(&self) -> bool {
+        self.unknown || self.writes_memory || !self.written_args.is_empty()
+    }
+
+    /// Returns true if memory may be written through the argument at
+    /// `index`.
+    pub fn may_write_argument(&self, index: usize) -> bool {
+        self.unknown || self.writes_memory || self.written_args.contains(&index)
+    }
+
+    /// The variables the intrinsic may modify; if its side-effects are
+    /// unknown, it may modify any variable.
+    pub fn clobbers(&self) -> impl Iterator<Item = &Var> {
+        self.clobbers.iter()
+    }
+
+    pub fn is_no_return(&self) -> bool {
+        self.no_return
+    }
+}
+
+/// A known intrinsic: its signature, side-effects, and (optionally) its
+/// semantics, as an expansion into Defs.
+#[derive(Clone)]
+pub struct Intrinsic {
+    name: Arc<str>,
+    params: Vec<Id<Type>>,
+    returns: Option<Id<Type>>,
+    effects: SideEffects,
+    expansion: Option<IntrinsicExpansion>,
+}
+
+impl fmt::Debug for Intrinsic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Intrinsic")
+            .field("name", &self.name)
+            .field("params", &self.params)
+            .field("returns", &self.returns)
+            .field("effects", &self.effects)
+            .field("expansion", &self.expansion.is_some())
+            .finish()
+    }
+}
+
+impl Intrinsic {
+    pub fn new(name: impl AsRef<str>, effects: SideEffects) -> Self {
+        Self {
+            name: intern(name.as_ref()),
+            params: Vec::new(),
+            returns: None,
+            effects,
+            expansion: None,
+        }
+    }
+
+    pub fn add_param(&mut self, typ: Id<Type>) -> &mut Self {
+        self.params.push(typ);
+        self
+    }
+
+    pub fn returns(&mut self, typ: Id<Type>) -> &mut Self {
+        self.returns = Some(typ);
+        self
+    }
+
+    /// Expands uses of the intrinsic using `f` (see `IntrinsicExpansion`).
+    pub fn expansion<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&[Expr], Option<&Var>) -> Option<Vec<Entity<Def>>> + Send + Sync + 'static,
+    {
+        self.expansion = Some(Arc::new(f));
+        self
+    }
+
+    pub fn name(&self) -> &Arc<str> {
+        &self.name
+    }
+
+    pub fn params(&self) -> &[Id<Type>] {
+        &self.params
+    }
+
+    pub fn return_type(&self) -> Option<Id<Type>> {
+        self.returns
+    }
+
+    pub fn effects(&self) -> &SideEffects {
+        &self.effects
+    }
+
+    pub fn has_expansion(&self) -> bool {
+        self.expansion.is_some()
+    }
+
+    /// Expands a use of the intrinsic with arguments `args`, whose result
+    /// (if any) is assigned to `result`.
+    pub fn expand(&self, args: &[Expr], result: Option<&Var>) -> Option<Vec<Entity<Def>>> {
+        self.expansion.as_ref().and_then(|f| f(args, result))
+    }
+}
+
+/// The intrinsics known to a Lifter (see `Lifter::intrinsics`), so that
+/// analyses can model them, rather than treating them as having arbitrary
+/// side-effects; e.g.:
+///
+/// ```ignore
+/// let mut popcount = Intrinsic::new("popcount", SideEffects::pure());
+/// popcount.add_param(U32.id()).returns(U32.id());
+/// lifter.intrinsics_mut().register(popcount);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct IntrinsicRegistry {
+    intrinsics: BTreeMap<Arc<str>, Arc<Intrinsic>>,
+}
+
+impl IntrinsicRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `intrinsic`; returns the intrinsic previously registered
+    /// with the same name, if any.
+    pub fn register(&mut self, intrinsic: Intrinsic) -> Option<Arc<Intrinsic>> {
+        self.intrinsics.insert(intrinsic.name.clone(), Arc::new(intrinsic))
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Arc<Intrinsic>> {
+        self.intrinsics.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<Intrinsic>> {
+        self.intrinsics.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.intrinsics.contains_key(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<Intrinsic>> {
+        self.intrinsics.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.intrinsics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intrinsics.is_empty()
+    }
+
+    /// The side-effects of the intrinsic named `name`; those of intrinsics
+    /// not registered are unknown.
+    pub fn effects(&self, name: &str) -> SideEffects {
+        self.get(name)
+            .map(|intrinsic| intrinsic.effects.clone())
+            .unwrap_or_else(SideEffects::unknown)
+    }
+
+    pub fn is_pure(&self, name: &str) -> bool {
+        self.get(name).map(|intrinsic| intrinsic.effects.is_pure()).unwrap_or(false)
+    }
+
+    /// Replaces the uses of intrinsics with expansions within `sub`:
+    /// assignments of intrinsic expressions, and intrinsic jmps that are
+    /// followed by the other flows of their Blks (e.g., a fall-through),
+    /// as the Defs of their expansions are placed before the Blk's flows.
+    /// Returns the number of uses replaced.
+    pub fn expand(&self, sub: &mut Sub) -> usize {
+        let mut expanded = 0;
+
+        for blk in sub.blks_mut().iter_mut() {
+            let defs = std::mem::take(blk.defs_mut());
+            let mut ndefs = Vec::with_capacity(defs.len());
+
+            for def in defs {
+                let expansion = match *def {
+                    Def::Assign(ref var, Expr::Intrinsic(ref name, ref args, _)) => {
+                        let args = args.iter().map(|arg| (**arg).clone()).collect::<Vec<_>>();
+                        self.get(name).and_then(|intrinsic| intrinsic.expand(&args, Some(var)))
+                    }
+                    _ => None,
+                };
+
+                if let Some(expansion) = expansion {
+                    ndefs.extend(expansion);
+                    expanded += 1;
+                } else {
+                    ndefs.push(def);
+                }
+            }
+
+            let expansion = match blk.jmps() {
+                [jmp, _, ..] => match **jmp {
+                    Jmp::Intrinsic(ref name, ref args) => self
+                        .get(name)
+                        .filter(|intrinsic| !intrinsic.effects.no_return)
+                        .and_then(|intrinsic| intrinsic.expand(args, None)),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            if let Some(expansion) = expansion {
+                ndefs.extend(expansion);
+                blk.jmps_mut().remove(0);
+                expanded += 1;
+            }
+
+            *blk.defs_mut() = ndefs;
+        }
+
+        expanded
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::{Blk, BinOp};
+    use crate::prelude::Identifiable;
+    use crate::types::bv::BitVecT;
+    use crate::types::U32;
+
+    #[test]
+    fn test_expand() {
+        let typ = BitVecT::with_bits(32, false);
+        let (x, y, z) = (
+            Var::transient("x", typ),
+            Var::transient("y", typ),
+            Var::transient("z", typ),
+        );
+
+        let mut registry = IntrinsicRegistry::new();
+        let mut add = Intrinsic::new("add", SideEffects::pure());
+        add.add_param(U32.id())
+            .add_param(U32.id())
+            .returns(U32.id())
+            .expansion(|args, result| match (args, result) {
+                ([lhs, rhs], Some(var)) => Some(vec![
+                    Def::assign(var.clone(), Expr::bin_op(BinOp::Add, lhs.clone(), rhs.clone())),
+                ]),
+                _ => None,
+            });
+        registry.register(add);
+
+        assert!(registry.is_pure("add"));
+        assert!(registry.effects("cpuid").is_unknown());
+
+        let mut blk = Blk::new(None);
+        blk.add_def(Def::assign(z.clone(), Expr::intrinsic("add", [Expr::from(x.clone()), Expr::from(y)], 32)));
+        blk.add_def(Def::assign(z, Expr::intrinsic("cpuid", [Expr::from(x)], 32)));
+
+        let mut sub: Entity<Sub> = Sub::new(None, None, vec![blk]);
+
+        assert_eq!(registry.expand(&mut sub), 1);
+        let defs = sub.blks()[0].defs();
+        assert!(matches!(*defs[0], Def::Assign(_, Expr::BinOp(BinOp::Add, _, _))));
+        assert!(matches!(*defs[1], Def::Assign(_, Expr::Intrinsic(_, _, _))));
+    }
+}
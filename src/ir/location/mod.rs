@@ -1,7 +1,7 @@
 use crate::ir::{Addr, Blk, Expr};
 use crate::prelude::Id;
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub enum Loc {
     Resolved(Id<Blk>),
     Fixed(Addr),
@@ -42,4 +42,12 @@ impl Loc {
     pub fn is_computed(&self) -> bool {
         matches!(self, Self::Computed(_))
     }
+
+    pub fn computed(&self) -> Option<&Expr> {
+        if let Self::Computed(ref expr) = self {
+            Some(expr)
+        } else {
+            None
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,464 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::analysis::ContentHash;
+use crate::ir::memory::{Addr, Mem, Permissions, Region, RegionError};
+use crate::prelude::{Endian, Entity};
+
+const MAGIC: &[u8; 8] = b"DLRMIMG\0";
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum ImageError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("not a memory image")]
+    Magic,
+    #[error("unsupported memory image version {0}")]
+    Version(u32),
+    #[error("malformed memory image header: {0}")]
+    Header(String),
+    #[error("blob {0} of memory image is compressed with unsupported codec `{1}`")]
+    Codec(usize, String),
+    #[error("blob {0} of memory image is corrupt")]
+    Corrupt(usize),
+    #[error(transparent)]
+    Region(#[from] RegionError),
+}
+
+/// How the bytes of regions are stored by `MemImage::write_to`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    /// zstd at the given level (see `zstd::encode_all`)
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        #[cfg(feature = "zstd")]
+        return Compression::Zstd(3);
+
+        #[cfg(not(feature = "zstd"))]
+        return Compression::None;
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ImageRegion {
+    name: Arc<str>,
+    addr: Addr,
+    endian: Endian,
+    permissions: Permissions,
+    blob: usize,
+}
+
+#[derive(Debug, Clone)]
+struct ImageModule {
+    name: Arc<str>,
+    mem: Arc<str>,
+    regions: Vec<ImageRegion>,
+}
+
+/// The regions of the memories of one or more modules (e.g., the images of
+/// a multi-module firmware), in a form that can be saved and restored.
+///
+/// The bytes of regions are deduplicated: identical regions, whether of the
+/// same or different modules, are stored once. When written, they can also
+/// be compressed (see `Compression`). Memories restored from an image (see
+/// `mem` and `Project::add_regions_from_image`) borrow its bytes, rather
+/// than copying them.
+#[derive(Debug, Clone, Default)]
+pub struct MemImage {
+    modules: Vec<ImageModule>,
+    blobs: Vec<Arc<[u8]>>,
+    // blobs by the hash of their content
+    index: BTreeMap<u64, Vec<usize>>,
+}
+
+impl MemImage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the regions of `mem` as those of `module`, replacing any
+    /// regions previously added for `module`.
+    pub fn add_mem(&mut self, module: impl Into<Arc<str>>, mem: &Mem) -> &mut Self {
+        let module = module.into();
+        let regions = mem
+            .regions()
+            .iter()
+            .map(|entry| entry.value())
+            .map(|region| ImageRegion {
+                name: region.name().clone(),
+                addr: region.address().clone(),
+                endian: region.endian(),
+                permissions: region.permissions(),
+                blob: self.intern(region.bytes()),
+            })
+            .collect();
+
+        let module = ImageModule {
+            name: module,
+            mem: Arc::from(&*mem.name()),
+            regions,
+        };
+
+        if let Some(existing) = self.modules.iter_mut().find(|m| m.name == module.name) {
+            *existing = module;
+        } else {
+            self.modules.push(module);
+        }
+        self
+    }
+
+    fn intern(&mut self, bytes: &[u8]) -> usize {
+        let hash = ContentHash::new().write(bytes).finish();
+        let candidates = self.index.entry(hash).or_default();
+
+        if let Some(blob) = candidates.iter().find(|blob| &*self.blobs[**blob] == bytes) {
+            return *blob
+        }
+
+        let blob = self.blobs.len();
+        self.blobs.push(Arc::from(bytes));
+        candidates.push(blob);
+        blob
+    }
+
+    pub fn modules(&self) -> impl Iterator<Item = &str> {
+        self.modules.iter().map(|module| &*module.name)
+    }
+
+    pub fn contains_module(&self, module: &str) -> bool {
+        self.modules.iter().any(|m| &*m.name == module)
+    }
+
+    /// The memory of `module`, whose regions borrow the bytes of the image.
+    pub fn mem(&self, module: &str) -> Result<Option<Mem<'_>>, ImageError> {
+        let module = if let Some(module) = self.modules.iter().find(|m| &*m.name == module) {
+            module
+        } else {
+            return Ok(None)
+        };
+
+        let mut mem = Mem::new(module.mem.to_string());
+        for region in self.regions(&module.name)? {
+            mem.add_region(region);
+        }
+        Ok(Some(mem))
+    }
+
+    pub(crate) fn regions(&self, module: &str) -> Result<Vec<Entity<Region<'_>>>, ImageError> {
+        self.modules
+            .iter()
+            .filter(|m| &*m.name == module)
+            .flat_map(|m| m.regions.iter())
+            .map(|region| {
+                Region::try_new(
+                    region.name.clone(),
+                    region.addr.clone(),
+                    region.endian,
+                    &self.blobs[region.blob][..],
+                )
+                .map(|mut entity| {
+                    entity.value_mut().set_permissions(region.permissions);
+                    entity
+                })
+                .map_err(ImageError::from)
+            })
+            .collect()
+    }
+
+    /// The number of bytes of the regions of all modules.
+    pub fn total_bytes(&self) -> usize {
+        self.modules
+            .iter()
+            .flat_map(|m| m.regions.iter())
+            .map(|region| self.blobs[region.blob].len())
+            .sum()
+    }
+
+    /// The number of bytes of the distinct regions of all modules, i.e.,
+    /// that are stored (prior to compression).
+    pub fn unique_bytes(&self) -> usize {
+        self.blobs.iter().map(|blob| blob.len()).sum()
+    }
+
+    /// Writes the image to `writer`: a header, describing the modules and
+    /// their regions as JSON, followed by the (possibly compressed) bytes of
+    /// each distinct region.
+    pub fn write_to(&self, mut writer: impl Write, compression: Compression) -> Result<(), ImageError> {
+        let stored = self.blobs
+            .iter()
+            .map(|blob| encode(blob, compression))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let blobs = self.blobs
+            .iter()
+            .zip(stored.iter())
+            .map(|(blob, (codec, bytes))| {
+                let mut object = Map::new();
+                object.insert("len".to_owned(), Value::from(blob.len() as u64));
+                object.insert("stored".to_owned(), Value::from(bytes.len() as u64));
+                object.insert("codec".to_owned(), Value::from(*codec));
+                object.insert("hash".to_owned(), Value::from(format!("{:016x}", ContentHash::new().write(blob).finish())));
+                Value::Object(object)
+            })
+            .collect::<Vec<_>>();
+
+        let modules = self.modules
+            .iter()
+            .map(|module| {
+                let regions = module.regions
+                    .iter()
+                    .map(|region| {
+                        let endian = if region.endian.is_little() { "little" } else { "big" };
+                        let mut object = Map::new();
+                        object.insert("name".to_owned(), Value::from(&*region.name));
+                        object.insert("addr".to_owned(), Value::from(format!("{:#x}", u128::try_from(&region.addr).unwrap_or(0))));
+                        object.insert("bits".to_owned(), Value::from(region.addr.bits() as u64));
+                        object.insert("endian".to_owned(), Value::from(endian));
+                        object.insert("permissions".to_owned(), Value::from(region.permissions.to_string()));
+                        object.insert("blob".to_owned(), Value::from(region.blob as u64));
+                        Value::Object(object)
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut object = Map::new();
+                object.insert("name".to_owned(), Value::from(&*module.name));
+                object.insert("mem".to_owned(), Value::from(&*module.mem));
+                object.insert("regions".to_owned(), Value::Array(regions));
+                Value::Object(object)
+            })
+            .collect::<Vec<_>>();
+
+        let mut header = Map::new();
+        header.insert("modules".to_owned(), Value::Array(modules));
+        header.insert("blobs".to_owned(), Value::Array(blobs));
+        let header = Value::Object(header).to_string();
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&(header.len() as u64).to_le_bytes())?;
+        writer.write_all(header.as_bytes())?;
+
+        for (_, bytes) in stored.iter() {
+            writer.write_all(bytes)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn to_bytes(&self, compression: Compression) -> Result<Vec<u8>, ImageError> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes, compression)?;
+        Ok(bytes)
+    }
+
+    /// Reads an image written by `write_to`.
+    pub fn read_from(mut reader: impl Read) -> Result<Self, ImageError> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(ImageError::Magic)
+        }
+
+        let mut word = [0u8; 4];
+        reader.read_exact(&mut word)?;
+        let version = u32::from_le_bytes(word);
+        if version != FORMAT_VERSION {
+            return Err(ImageError::Version(version))
+        }
+
+        let mut dword = [0u8; 8];
+        reader.read_exact(&mut dword)?;
+        let length = usize::try_from(u64::from_le_bytes(dword))
+            .map_err(|_| ImageError::Header("header is too large".to_owned()))?;
+
+        let mut header = Vec::new();
+        (&mut reader).take(length as u64).read_to_end(&mut header)?;
+        if header.len() != length {
+            return Err(ImageError::Header("header is truncated".to_owned()))
+        }
+
+        let header = std::str::from_utf8(&header)
+            .map_err(|e| ImageError::Header(e.to_string()))?
+            .parse::<Value>()
+            .map_err(|e| ImageError::Header(e.to_string()))?;
+
+        let mut image = Self::new();
+
+        let blobs = header
+            .get("blobs")
+            .and_then(|blobs| blobs.as_array())
+            .ok_or_else(|| malformed("blobs"))?;
+
+        for (i, blob) in blobs.iter().enumerate() {
+            let field = |name: &str| blob.get(name).ok_or_else(|| malformed(name));
+            let len = field("len")?.as_u64().ok_or_else(|| malformed("len"))? as usize;
+            let stored = field("stored")?.as_u64().ok_or_else(|| malformed("stored"))?;
+            let codec = field("codec")?.as_str().ok_or_else(|| malformed("codec"))?;
+            let hash = field("hash")?
+                .as_str()
+                .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+                .ok_or_else(|| malformed("hash"))?;
+
+            let mut bytes = Vec::new();
+            (&mut reader).take(stored).read_to_end(&mut bytes)?;
+            if bytes.len() as u64 != stored {
+                return Err(ImageError::Corrupt(i))
+            }
+
+            let bytes = decode(i, codec, bytes)?;
+            if bytes.len() != len || ContentHash::new().write(&bytes).finish() != hash {
+                return Err(ImageError::Corrupt(i))
+            }
+
+            image.index.entry(hash).or_default().push(i);
+            image.blobs.push(Arc::from(bytes));
+        }
+
+        let modules = header
+            .get("modules")
+            .and_then(|modules| modules.as_array())
+            .ok_or_else(|| malformed("modules"))?;
+
+        for module in modules {
+            let name = module.get("name").and_then(|name| name.as_str()).ok_or_else(|| malformed("name"))?;
+            let mem = module.get("mem").and_then(|mem| mem.as_str()).ok_or_else(|| malformed("mem"))?;
+
+            let regions = module
+                .get("regions")
+                .and_then(|regions| regions.as_array())
+                .ok_or_else(|| malformed("regions"))?
+                .iter()
+                .map(|region| image.parse_region(region))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            image.modules.push(ImageModule {
+                name: Arc::from(name),
+                mem: Arc::from(mem),
+                regions,
+            });
+        }
+
+        Ok(image)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ImageError> {
+        Self::read_from(bytes)
+    }
+
+    fn parse_region(&self, region: &Value) -> Result<ImageRegion, ImageError> {
+        let field = |name: &str| region.get(name).ok_or_else(|| malformed(name));
+
+        let name = field("name")?.as_str().ok_or_else(|| malformed("name"))?;
+        let bits = field("bits")?
+            .as_u64()
+            .filter(|bits| *bits > 0 && *bits <= 128)
+            .ok_or_else(|| malformed("bits"))? as u32;
+        let addr = field("addr")?
+            .as_str()
+            .and_then(|addr| addr.strip_prefix("0x"))
+            .and_then(|addr| u128::from_str_radix(addr, 16).ok())
+            .map(|addr| Addr::from(addr).as_bits(bits))
+            .ok_or_else(|| malformed("addr"))?;
+        let endian = match field("endian")?.as_str() {
+            Some("little") => Endian::Little,
+            Some("big") => Endian::Big,
+            _ => return Err(malformed("endian")),
+        };
+        // images written before permissions were recorded permit all
+        // accesses
+        let permissions = match region.get("permissions") {
+            Some(permissions) => permissions
+                .as_str()
+                .and_then(Permissions::parse)
+                .ok_or_else(|| malformed("permissions"))?,
+            None => Permissions::all(),
+        };
+        let blob = field("blob")?
+            .as_u64()
+            .map(|blob| blob as usize)
+            .filter(|blob| *blob < self.blobs.len())
+            .ok_or_else(|| malformed("blob"))?;
+
+        Ok(ImageRegion {
+            name: Arc::from(name),
+            addr,
+            endian,
+            permissions,
+            blob,
+        })
+    }
+}
+
+fn malformed(field: &str) -> ImageError {
+    ImageError::Header(format!("missing or invalid field `{}`", field))
+}
+
+fn encode(bytes: &[u8], compression: Compression) -> Result<(&'static str, Vec<u8>), ImageError> {
+    match compression {
+        Compression::None => Ok(("raw", bytes.to_vec())),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd(level) => {
+            let compressed = zstd::encode_all(bytes, level)?;
+            // incompressible bytes are stored as they are
+            if compressed.len() < bytes.len() {
+                Ok(("zstd", compressed))
+            } else {
+                Ok(("raw", bytes.to_vec()))
+            }
+        }
+    }
+}
+
+fn decode(blob: usize, codec: &str, bytes: Vec<u8>) -> Result<Vec<u8>, ImageError> {
+    match codec {
+        "raw" => Ok(bytes),
+        #[cfg(feature = "zstd")]
+        "zstd" => zstd::decode_all(&bytes[..]).map_err(|_| ImageError::Corrupt(blob)),
+        _ => Err(ImageError::Codec(blob, codec.to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dedup_roundtrip() -> Result<(), Box<dyn std::error::Error>> {
+        let firmware = vec![0x90u8; 4096];
+
+        let mut a = Mem::new("M");
+        a.add_region(Region::try_new("flash", 0x8000_0000u32, Endian::Little, &firmware[..])?);
+        a.add_region(Region::try_new("boot", 0x0u32, Endian::Little, vec![1u8, 2, 3, 4])?);
+
+        let mut b = Mem::new("M");
+        b.add_region(Region::try_new("flash", 0x0800_0000u32, Endian::Little, &firmware[..])?);
+
+        let mut image = MemImage::new();
+        image.add_mem("a", &a).add_mem("b", &b);
+
+        assert_eq!(image.total_bytes(), 2 * 4096 + 4);
+        assert_eq!(image.unique_bytes(), 4096 + 4);
+
+        let bytes = image.to_bytes(Compression::default())?;
+        let restored = MemImage::from_bytes(&bytes)?;
+
+        assert_eq!(restored.modules().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(restored.unique_bytes(), 4096 + 4);
+
+        let mem = restored.mem("b")?.unwrap();
+        let region = mem.find_region(&Addr::from(0x0800_0010u32)).unwrap();
+        assert_eq!(region.bytes(), &firmware[..]);
+
+        Ok(())
+    }
+}
@@ -1,19 +1,35 @@
 pub mod address;
 pub use address::Addr;
 
+pub mod image;
+pub use image::{Compression, ImageError, MemImage};
+
 pub mod region;
-pub use region::Region;
+pub use region::{Permissions, Region, RegionError, RegionIOError};
+
+pub mod symbols;
+pub use symbols::{AddrStyle, MemOperands, Symbols};
 
+use crate::ir::value::bv::BitVec;
 use crate::prelude::intervals::collections::IntervalMap;
-use crate::prelude::{Id, Identifiable, Entity, EntityRef};
+use crate::prelude::{Id, Identifiable, Entity, EntityRef, Tagged};
 
 use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct Mem<'r> {
     id: Id<Mem<'r>>,
     name: Cow<'static, str>,
     mapping: IntervalMap<Addr, Entity<Region<'r>>>,
+    // the bytes written to each layer pushed by push_overlay, innermost
+    // last
+    overlays: Vec<BTreeMap<Addr, u8>>,
+}
+
+impl<'r> Tagged for Mem<'r> {
+    const TAG: &'static str = "mem";
 }
 
 impl<'r> Identifiable<Mem<'r>> for Mem<'r> {
@@ -28,6 +44,7 @@ impl<'r> Mem<'r> {
             id: Id::new("mem"),
             name: name.into(),
             mapping: IntervalMap::default(),
+            overlays: Vec::new(),
         }
     }
     
@@ -39,11 +56,341 @@ impl<'r> Mem<'r> {
         self.mapping.insert(region.interval().clone(), region);
     }
     
+    /// The region mapped at `addr`. Its bytes are those mapped, without
+    /// those written within overlays (see `push_overlay`); to read through
+    /// the overlays, use `read_bytes`, `read_bytes_from` or `read_bits`.
     pub fn find_region(&self, addr: &Addr) -> Option<EntityRef<Region<'r>>> {
         self.mapping.find_point(addr).map(|iv| EntityRef::Borrowed(iv.value()))
     }
+
+    /// The region mapped at `addr` (see `find_region`), if it permits its
+    /// bytes to be executed.
+    pub fn find_executable_region(&self, addr: &Addr) -> Option<EntityRef<Region<'r>>> {
+        self.find_region(addr).filter(|region| region.permissions().is_executable())
+    }
+
+    /// The `len` bytes at `addr`, which must all fall within the region
+    /// mapped at `addr`, including those written within overlays; only
+    /// the bytes read are copied, and only if some were written within
+    /// overlays. Returns None if `addr` is not mapped.
+    pub fn read_bytes(&self, addr: &Addr, len: usize) -> Result<Option<Cow<[u8]>>, RegionIOError> {
+        let region = if let Some(entry) = self.mapping.find_point(addr) {
+            entry.value()
+        } else {
+            return Ok(None)
+        };
+
+        let mut bytes = Cow::Borrowed(region.view_bytes(addr, len)?);
+        self.patch(addr, &mut bytes);
+        Ok(Some(bytes))
+    }
+
+    /// The bytes from `addr` to the end of the region mapped at `addr`
+    /// (see `read_bytes`).
+    pub fn read_bytes_from(&self, addr: &Addr) -> Result<Option<Cow<[u8]>>, RegionIOError> {
+        let region = if let Some(entry) = self.mapping.find_point(addr) {
+            entry.value()
+        } else {
+            return Ok(None)
+        };
+
+        let mut bytes = Cow::Borrowed(region.view_bytes_from(addr)?);
+        self.patch(addr, &mut bytes);
+        Ok(Some(bytes))
+    }
+
+    /// The value of `bits` bits at `addr`, laid out as by
+    /// `Region::read_bits`, including the bytes written within overlays.
+    /// Returns None if `addr` is not mapped.
+    pub fn read_bits(&self, addr: &Addr, bits: u32) -> Result<Option<BitVec>, RegionIOError> {
+        let region = if let Some(entry) = self.mapping.find_point(addr) {
+            entry.value()
+        } else {
+            return Ok(None)
+        };
+
+        if bits == 0 {
+            return Err(RegionIOError::Range(region.name().clone()))
+        }
+
+        let mut bytes = Cow::Borrowed(region.view_bytes(addr, region::bits_len(bits))?);
+        self.patch(addr, &mut bytes);
+        Ok(Some(region::bits_from_bytes(region.endian(), &bytes, bits)))
+    }
+
+    // writes the bytes written within overlays over the bytes read at addr
+    fn patch(&self, addr: &Addr, bytes: &mut Cow<[u8]>) {
+        if bytes.is_empty() {
+            return
+        }
+
+        let end = addr + bytes.len();
+        for overlay in self.overlays.iter() {
+            for (patched, byte) in overlay.range(addr.clone()..end.clone()) {
+                // unwrap is safe here: patched is within the bytes read
+                let offset = patched.absolute_difference(addr).unwrap();
+                bytes.to_mut()[offset] = *byte;
+            }
+        }
+    }
     
     pub fn regions(&self) -> &IntervalMap<Addr, Entity<Region<'r>>> {
         &self.mapping
     }
-}
\ No newline at end of file
+
+    /// Overwrites the bytes at `addr` with `bytes`, which must all fall
+    /// within the region mapped at `addr`; if an overlay has been pushed,
+    /// the bytes are written within it, leaving the region unchanged.
+    /// Returns false if `addr` is not mapped.
+    pub fn write_bytes(&mut self, addr: &Addr, bytes: &[u8]) -> Result<bool, RegionIOError> {
+        if let Some(overlay) = self.overlays.last_mut() {
+            let region = if let Some(entry) = self.mapping.find_point(addr) {
+                entry.value()
+            } else {
+                return Ok(false)
+            };
+
+            if !region.contains_range(addr, bytes.len()) {
+                return Err(RegionIOError::OOBWrite(region.name().clone()))
+            }
+
+            for (offset, byte) in bytes.iter().enumerate() {
+                overlay.insert(addr + offset, *byte);
+            }
+            return Ok(true)
+        }
+
+        self.write_regions(addr, bytes)
+    }
+
+    fn write_regions(&mut self, addr: &Addr, bytes: &[u8]) -> Result<bool, RegionIOError> {
+        let interval = if let Some(entry) = self.mapping.find_point(addr) {
+            entry.interval().clone()
+        } else {
+            return Ok(false)
+        };
+
+        // unwrap is safe here: we know that the region is mapped
+        let mut region = self.mapping.remove(&interval).unwrap();
+        let written = region
+            .view_bytes_mut(addr, bytes.len())
+            .map(|view| view.copy_from_slice(bytes));
+
+        self.mapping.insert(interval, region);
+        written.map(|_| true)
+    }
+
+    /// Pushes a copy-on-write layer: until it is popped or committed,
+    /// writes (see `write_bytes`) are made within the layer, shadowing the
+    /// bytes of the regions and of the layers beneath it, e.g., to try
+    /// patches without copying the regions patched. Returns the number of
+    /// layers.
+    pub fn push_overlay(&mut self) -> usize {
+        self.overlays.push(BTreeMap::new());
+        self.overlays.len()
+    }
+
+    /// Discards the innermost overlay and the writes made within it;
+    /// returns false if there is none.
+    pub fn pop_overlay(&mut self) -> bool {
+        self.overlays.pop().is_some()
+    }
+
+    /// Applies the writes made within the innermost overlay to the layer
+    /// beneath it, or to the regions if it is the only layer, and removes
+    /// it; returns false if there is none. If any of its writes cannot be
+    /// applied to the regions, none are, and the overlay is kept.
+    pub fn commit_overlay(&mut self) -> Result<bool, RegionIOError> {
+        if self.overlays.len() > 1 {
+            // unwrap is safe here: there are at least two layers
+            let overlay = self.overlays.pop().unwrap();
+            self.overlays.last_mut().unwrap().extend(overlay);
+            return Ok(true)
+        }
+
+        let overlay = if let Some(overlay) = self.overlays.last() {
+            overlay
+        } else {
+            return Ok(false)
+        };
+
+        // each run of consecutive bytes within a region is written at once
+        let mut runs: Vec<(Addr, Vec<u8>)> = Vec::new();
+        for (addr, byte) in overlay.iter() {
+            match runs.last_mut() {
+                Some((start, bytes)) if &*start + bytes.len() == *addr && self.fits(start, bytes.len() + 1) => {
+                    bytes.push(*byte)
+                }
+                _ => runs.push((addr.clone(), vec![*byte])),
+            }
+        }
+
+        // check every write before making any
+        if let Some((start, _)) = runs.iter().find(|(start, bytes)| !self.fits(start, bytes.len())) {
+            let name = self
+                .mapping
+                .find_point(start)
+                .map(|entry| entry.value().name().clone())
+                .unwrap_or_else(|| Arc::from(&*self.name));
+            return Err(RegionIOError::OOBWrite(name))
+        }
+
+        for (start, bytes) in runs {
+            self.write_regions(&start, &bytes)?;
+        }
+        self.overlays.pop();
+
+        Ok(true)
+    }
+
+    // the count bytes at addr fall within the region mapped at addr
+    fn fits(&self, addr: &Addr, count: usize) -> bool {
+        self.mapping
+            .find_point(addr)
+            .map(|entry| entry.value().contains_range(addr, count))
+            .unwrap_or(false)
+    }
+
+    /// The number of overlays pushed.
+    pub fn overlay_depth(&self) -> usize {
+        self.overlays.len()
+    }
+
+    /// The bytes written within the overlays, as seen through the
+    /// innermost (i.e., those written last at each address).
+    pub fn overlay_bytes(&self) -> BTreeMap<Addr, u8> {
+        self.overlays
+            .iter()
+            .flat_map(|overlay| overlay.iter().map(|(addr, byte)| (addr.clone(), *byte)))
+            .collect()
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::Endian;
+
+    fn mem<'r>() -> Mem<'r> {
+        let mut mem = Mem::new("M");
+        mem.add_region(Region::new("a", 0x1000u32, Endian::Little, vec![0u8; 8]));
+        mem.add_region(Region::new("b", 0x2000u32, Endian::Little, vec![0u8; 8]));
+        mem
+    }
+
+    fn read(mem: &Mem, addr: u32, len: usize) -> Vec<u8> {
+        mem.read_bytes(&Addr::from(addr), len).unwrap().unwrap().into_owned()
+    }
+
+    #[test]
+    fn test_overlay_push_write_pop() -> Result<(), RegionIOError> {
+        let mut mem = mem();
+
+        assert_eq!(mem.push_overlay(), 1);
+        assert!(mem.write_bytes(&Addr::from(0x1002u32), &[1, 2])?);
+        assert!(!mem.write_bytes(&Addr::from(0x3000u32), &[1])?);
+        assert!(mem.write_bytes(&Addr::from(0x1007u32), &[1, 2]).is_err());
+
+        assert_eq!(read(&mem, 0x1000, 4), [0, 0, 1, 2]);
+        assert_eq!(mem.overlay_bytes().len(), 2);
+
+        assert!(mem.pop_overlay());
+        assert!(!mem.pop_overlay());
+        assert_eq!(read(&mem, 0x1000, 4), [0, 0, 0, 0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_overlay_commit() -> Result<(), RegionIOError> {
+        let mut mem = mem();
+
+        mem.push_overlay();
+        mem.write_bytes(&Addr::from(0x1006u32), &[1, 2])?;
+        mem.write_bytes(&Addr::from(0x2000u32), &[3])?;
+
+        assert!(mem.commit_overlay()?);
+        assert!(!mem.commit_overlay()?);
+        assert_eq!(mem.overlay_depth(), 0);
+
+        let region = mem.find_region(&Addr::from(0x1000u32)).unwrap();
+        assert_eq!(region.bytes(), &[0, 0, 0, 0, 0, 0, 1, 2]);
+        assert_eq!(read(&mem, 0x2000, 1), [3]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_overlay_commit_unmapped() -> Result<(), RegionIOError> {
+        let mut mem = mem();
+
+        mem.push_overlay();
+        mem.write_bytes(&Addr::from(0x1000u32), &[1])?;
+        // a write that cannot be applied to the regions
+        mem.overlays[0].insert(Addr::from(0x3000u32), 2);
+
+        assert!(mem.commit_overlay().is_err());
+
+        // nothing is written, and the overlay is kept
+        assert_eq!(mem.overlay_depth(), 1);
+        assert_eq!(mem.find_region(&Addr::from(0x1000u32)).unwrap().bytes()[0], 0);
+        assert_eq!(read(&mem, 0x1000, 1), [1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nested_overlays() -> Result<(), RegionIOError> {
+        let mut mem = mem();
+
+        mem.push_overlay();
+        mem.write_bytes(&Addr::from(0x1000u32), &[1, 1])?;
+        assert_eq!(mem.push_overlay(), 2);
+        mem.write_bytes(&Addr::from(0x1001u32), &[2, 2])?;
+
+        // the innermost overlay shadows those beneath it
+        assert_eq!(read(&mem, 0x1000, 3), [1, 2, 2]);
+
+        mem.pop_overlay();
+        assert_eq!(read(&mem, 0x1000, 3), [1, 1, 0]);
+
+        mem.push_overlay();
+        mem.write_bytes(&Addr::from(0x1002u32), &[3])?;
+
+        // committing the inner overlay writes into the outer one
+        assert!(mem.commit_overlay()?);
+        assert_eq!(mem.overlay_depth(), 1);
+        assert_eq!(read(&mem, 0x1000, 3), [1, 1, 3]);
+        assert_eq!(mem.find_region(&Addr::from(0x1000u32)).unwrap().bytes()[..3], [0, 0, 0]);
+
+        mem.pop_overlay();
+        assert_eq!(read(&mem, 0x1000, 3), [0, 0, 0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_region_under_overlay() -> Result<(), RegionIOError> {
+        let mut mem = mem();
+
+        mem.push_overlay();
+        mem.write_bytes(&Addr::from(0x1004u32), &[0x34, 0x12])?;
+
+        // the region is that mapped; its bytes are read through the overlay
+        let region = mem.find_region(&Addr::from(0x1005u32)).unwrap();
+        assert_eq!(region.name().as_ref(), "a");
+        assert_eq!(region.bytes(), &[0u8; 8]);
+
+        let bytes = mem.read_bytes_from(&Addr::from(0x1003u32))?.unwrap();
+        assert_eq!(&*bytes, &[0, 0x34, 0x12, 0, 0]);
+
+        // bytes read away from the writes are not copied
+        let bytes = mem.read_bytes_from(&Addr::from(0x1006u32))?.unwrap();
+        assert!(matches!(bytes, Cow::Borrowed(_)));
+
+        let value = mem.read_bits(&Addr::from(0x1004u32), 16)?.unwrap();
+        assert_eq!(value, BitVec::from_u64(0x1234, 16));
+        assert!(mem.read_bits(&Addr::from(0x3000u32), 16)?.is_none());
+
+        Ok(())
+    }
+}
@@ -2,6 +2,7 @@
 /// are indexed by addresses of a fixed size and are associated with
 /// a particular endianness.
 use std::borrow::{Borrow, Cow};
+use std::fmt::{self, Display};
 use std::sync::Arc;
 
 use thiserror::Error;
@@ -11,16 +12,89 @@ use crate::ir::value::bv::BitVec;
 
 use crate::prelude::bytes::{ByteCast, Endian, BE, LE};
 use crate::prelude::intervals::Interval;
-use crate::prelude::{Entity, Id};
+use crate::prelude::{Entity, Id, Tagged};
+
+/// The accesses permitted to the bytes of a Region, e.g., as given by the
+/// segment it is loaded from. Regions permit all accesses unless given
+/// otherwise.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Permissions {
+    read: bool,
+    write: bool,
+    execute: bool,
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl Display for Permissions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}",
+            if self.read { 'r' } else { '-' },
+            if self.write { 'w' } else { '-' },
+            if self.execute { 'x' } else { '-' },
+        )
+    }
+}
+
+impl Permissions {
+    pub fn new(read: bool, write: bool, execute: bool) -> Self {
+        Self { read, write, execute }
+    }
+
+    pub fn all() -> Self {
+        Self::new(true, true, true)
+    }
+
+    /// Parses permissions as displayed, e.g., `r-x`.
+    pub fn parse(permissions: &str) -> Option<Self> {
+        let flag = |c: u8, set: u8| match c {
+            b'-' => Some(false),
+            c if c == set => Some(true),
+            _ => None,
+        };
+
+        match *permissions.as_bytes() {
+            [r, w, x] => Some(Self::new(flag(r, b'r')?, flag(w, b'w')?, flag(x, b'x')?)),
+            _ => None,
+        }
+    }
+
+    pub fn is_readable(&self) -> bool {
+        self.read
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.write
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.execute
+    }
+}
 
 #[derive(Clone)]
 pub struct Region<'r> {
     name: Arc<str>,
     range: Interval<Addr>,
     endian: Endian,
+    permissions: Permissions,
     bytes: Cow<'r, [u8]>,
 }
 
+#[derive(Debug, Error)]
+pub enum RegionError {
+    #[error("region `{0}` cannot be empty")]
+    Empty(Arc<str>),
+    #[error("address range of region `{0}` is not representable by {1} bit addresses starting at {2}")]
+    Unrepresentable(Arc<str>, u32, Addr),
+}
+
 #[derive(Debug, Error)]
 pub enum RegionIOError {
     #[error("read/write byte range is unrepresentable for region `{0}`")]
@@ -31,7 +105,13 @@ pub enum RegionIOError {
     OOBWrite(Arc<str>),
 }
 
+impl<'r> Tagged for Region<'r> {
+    const TAG: &'static str = "region";
+}
+
 impl<'r> Region<'r> {
+    /// As `try_new_with`, but panics if the region is empty, or its end is
+    /// not representable.
     pub fn new_with(
         id: Id<Self>,
         name: impl Into<Arc<str>>,
@@ -39,33 +119,43 @@ impl<'r> Region<'r> {
         endian: Endian,
         bytes: impl Into<Cow<'r, [u8]>>,
     ) -> Entity<Self> {
+        Self::try_new_with(id, name, addr, endian, bytes).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new_with(
+        id: Id<Self>,
+        name: impl Into<Arc<str>>,
+        addr: impl Into<Addr>,
+        endian: Endian,
+        bytes: impl Into<Cow<'r, [u8]>>,
+    ) -> Result<Entity<Self>, RegionError> {
+        let name = name.into();
         let address = addr.into();
         let bytes = bytes.into();
         if bytes.len() == 0 {
             // check for zero
-            panic!("region size cannot be zero");
+            return Err(RegionError::Empty(name));
         }
         let last_address = &address + bytes.len();
         if last_address <= address {
             // check for potential overflow
-            panic!(
-                "address range not representable by {} bit addresses starting at {}",
-                address.bits(),
-                address
-            );
+            return Err(RegionError::Unrepresentable(name, address.bits(), address));
         }
 
-        Entity::from_parts(
+        Ok(Entity::from_parts(
             id,
             Self {
-                name: name.into(),
+                name,
                 range: Interval::from(address..last_address),
                 endian,
+                permissions: Permissions::default(),
                 bytes: bytes.into(),
             },
-        )
+        ))
     }
 
+    /// As `try_new`, but panics if the region is empty, or its end is not
+    /// representable.
     pub fn new(
         name: impl Into<Arc<str>>,
         addr: impl Into<Addr>,
@@ -74,6 +164,67 @@ impl<'r> Region<'r> {
     ) -> Entity<Self> {
         Self::new_with(Id::new("region"), name, addr, endian, bytes)
     }
+
+    pub fn try_new(
+        name: impl Into<Arc<str>>,
+        addr: impl Into<Addr>,
+        endian: Endian,
+        bytes: impl Into<Cow<'r, [u8]>>,
+    ) -> Result<Entity<Self>, RegionError> {
+        Self::try_new_with(Id::new("region"), name, addr, endian, bytes)
+    }
+
+    /// Creates a region containing `values` laid out consecutively in the
+    /// byte order given by `endian`, where each value occupies the minimum
+    /// number of bytes needed to represent it. Values whose sizes are not
+    /// multiples of eight are placed as by `write_bits`.
+    ///
+    /// Creating a little- and a big-endian region from the same values
+    /// yields a pair of mirrored regions for which `read_bits` returns the
+    /// same values at the same addresses.
+    pub fn try_from_values<V>(
+        name: impl Into<Arc<str>>,
+        addr: impl Into<Addr>,
+        endian: Endian,
+        values: impl IntoIterator<Item = V>,
+    ) -> Result<Entity<Region<'static>>, RegionError>
+    where
+        V: Borrow<BitVec>,
+    {
+        let address = addr.into();
+        let values = values.into_iter().collect::<Vec<_>>();
+        let size = values
+            .iter()
+            .map(|v| (v.borrow().bits() + 7) / 8)
+            .sum::<usize>();
+
+        let mut region = Region::try_new(name, address.clone(), endian, vec![0u8; size])?;
+        let mut offset = 0;
+
+        for v in values.iter() {
+            let v = v.borrow();
+            // unwrap is safe here: the region is sized to contain all values
+            region.write_bits(&address + offset, v).unwrap();
+            offset += (v.bits() + 7) / 8;
+        }
+
+        Ok(region)
+    }
+
+    /// As `try_from_values`, but panics if `values` is empty, or the
+    /// region's end is not representable.
+    pub fn from_values<V>(
+        name: impl Into<Arc<str>>,
+        addr: impl Into<Addr>,
+        endian: Endian,
+        values: impl IntoIterator<Item = V>,
+    ) -> Entity<Region<'static>>
+    where
+        V: Borrow<BitVec>,
+    {
+        Self::try_from_values(name, addr, endian, values).unwrap_or_else(|e| panic!("{}", e))
+    }
+
     pub fn interval(&self) -> &Interval<Addr> {
         &self.range
     }
@@ -90,6 +241,15 @@ impl<'r> Region<'r> {
         self.endian
     }
 
+    pub fn permissions(&self) -> Permissions {
+        self.permissions
+    }
+
+    pub fn set_permissions(&mut self, permissions: Permissions) -> &mut Self {
+        self.permissions = permissions;
+        self
+    }
+
     pub fn bytes(&self) -> &[u8] {
         &*self.bytes
     }
@@ -104,30 +264,26 @@ impl<'r> Region<'r> {
             && self.interval().contains_point(&(address + (count - 1)))
     }
 
+    /// Reads a value of `bits` bits from `address`; values whose sizes are
+    /// not multiples of eight occupy the least significant bits of the
+    /// bytes read for little-endian regions and the most significant bits
+    /// for big-endian regions (i.e., the bits closest to `address`).
     pub fn read_bits(
         &self,
         address: impl Borrow<Addr>,
         bits: u32,
     ) -> Result<BitVec, RegionIOError> {
-        let aligned = bits % 8 == 0;
-        let count = bits / 8 + if aligned { 0 } else { 1 };
-        let range = self.view_bytes(address, count as usize)?;
-        let bv = if self.endian().is_little() {
-            BitVec::from_le_bytes(range)
-        } else {
-            BitVec::from_be_bytes(range)
-        };
-        if aligned {
-            Ok(bv)
-        } else if self.endian().is_little() {
-            // truncate msb bits
-            Ok(bv.cast(bits as usize))
-        } else {
-            // shift out lsb bits and truncate
-            Ok((bv >> (8 - (bits % 8))).cast(bits as usize))
+        if bits == 0 {
+            return Err(RegionIOError::Range(self.name.clone()));
         }
+
+        let range = self.view_bytes(address, bits_len(bits))?;
+        Ok(bits_from_bytes(self.endian(), range, bits))
     }
 
+    /// Writes `bv` to `address`; the bits of the bytes written that are
+    /// not covered by `bv` are preserved. See `read_bits` for the layout of
+    /// values whose sizes are not multiples of eight.
     pub fn write_bits(
         &mut self,
         address: impl Borrow<Addr>,
@@ -136,6 +292,10 @@ impl<'r> Region<'r> {
         let bv = bv.borrow();
         let bits = bv.bits();
 
+        if bits == 0 {
+            return Err(RegionIOError::Range(self.name.clone()));
+        }
+
         let endian = self.endian();
         let aligned = bits % 8 == 0;
         let count = bits / 8 + if aligned { 0 } else { 1 };
@@ -259,3 +419,89 @@ impl<'r> Region<'r> {
         self.bytes.len()
     }
 }
+
+// the number of bytes holding a value of bits bits
+pub(crate) fn bits_len(bits: u32) -> usize {
+    ((bits + 7) / 8) as usize
+}
+
+// the value of bits bits held by bytes (see Region::read_bits)
+pub(crate) fn bits_from_bytes(endian: Endian, bytes: &[u8], bits: u32) -> BitVec {
+    // values are unsigned so that shifts below are logical
+    let bv = if endian.is_little() {
+        BitVec::from_le_bytes(bytes).unsigned()
+    } else {
+        BitVec::from_be_bytes(bytes).unsigned()
+    };
+    if bits % 8 == 0 {
+        bv
+    } else if endian.is_little() {
+        // truncate msb bits
+        bv.cast(bits as usize)
+    } else {
+        // shift out lsb bits and truncate
+        (bv >> (8 - (bits % 8))).cast(bits as usize)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_permissions() {
+        let rx = Permissions::new(true, false, true);
+        assert_eq!(rx.to_string(), "r-x");
+        assert_eq!(Permissions::parse("r-x"), Some(rx));
+        assert_eq!(Permissions::parse("rwx"), Some(Permissions::all()));
+        assert_eq!(Permissions::parse("x-r"), None);
+        assert_eq!(Permissions::parse("rw"), None);
+    }
+
+    fn values() -> Vec<BitVec> {
+        vec![
+            BitVec::from_u64(0x12, 8),
+            BitVec::from_u64(0x3456, 16),
+            BitVec::from_u64(0x789abcde, 32),
+            BitVec::from_u64(0xabc, 12),
+            BitVec::from_u64(0x5, 3),
+        ]
+    }
+
+    #[test]
+    fn test_mirrored_layout() {
+        let le = Region::from_values("le", 0x1000u32, Endian::Little, values());
+        let be = Region::from_values("be", 0x1000u32, Endian::Big, values());
+
+        assert_eq!(
+            le.bytes(),
+            &[0x12, 0x56, 0x34, 0xde, 0xbc, 0x9a, 0x78, 0xbc, 0x0a, 0x05]
+        );
+        assert_eq!(
+            be.bytes(),
+            &[0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xab, 0xc0, 0xa0]
+        );
+    }
+
+    #[test]
+    fn test_mirrored_read_write() -> Result<(), RegionIOError> {
+        for endian in [Endian::Little, Endian::Big] {
+            let mut region = Region::from_values("test", 0x1000u32, endian, values());
+
+            let mut addr = Addr::from(0x1000u32);
+            for v in values() {
+                assert_eq!(region.read_bits(&addr, v.bits() as u32)?, v);
+                addr = addr + (v.bits() + 7) / 8;
+            }
+
+            // sub-byte writes preserve the surrounding bits
+            let addr = Addr::from(0x1007u32);
+            region.write_bits(&addr, BitVec::from_u64(0x321, 12))?;
+            assert_eq!(region.read_bits(&addr, 12)?, BitVec::from_u64(0x321, 12));
+            assert_eq!(region.read_bits(&addr + 2usize, 3)?, BitVec::from_u64(0x5, 3));
+
+            assert!(region.read_bits(&addr, 0).is_err());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::ir::{Addr, Expr, Mem};
+
+/// How the addresses of memory operands (i.e., of loads and stores) are
+/// rendered by `MemOperands`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AddrStyle {
+    /// As bitvectors, e.g., `0x404020`
+    Raw,
+    /// Relative to the Regions and symbols they fall within, e.g.,
+    /// `.data+0x20 (g_counter)`; addresses within neither are raw
+    Symbolic,
+}
+
+impl Default for AddrStyle {
+    fn default() -> Self {
+        Self::Symbolic
+    }
+}
+
+/// Named ranges of memory, e.g., the data symbols of a binary.
+#[derive(Debug, Clone, Default)]
+pub struct Symbols {
+    // the name and size of each symbol, keyed by its address
+    symbols: BTreeMap<Addr, (Arc<str>, usize)>,
+}
+
+impl Symbols {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names the `size` bytes at `addr` (a size of zero is unknown, so
+    /// only `addr` itself is named); returns the name it replaces.
+    pub fn insert(&mut self, addr: impl Into<Addr>, name: impl Into<Arc<str>>, size: usize) -> Option<Arc<str>> {
+        self.symbols
+            .insert(addr.into(), (name.into(), size))
+            .map(|(name, _)| name)
+    }
+
+    pub fn remove(&mut self, addr: &Addr) -> Option<Arc<str>> {
+        self.symbols.remove(addr).map(|(name, _)| name)
+    }
+
+    /// The symbol starting at `addr`.
+    pub fn get(&self, addr: &Addr) -> Option<&Arc<str>> {
+        self.symbols.get(addr).map(|(name, _)| name)
+    }
+
+    /// The symbol `addr` falls within, and its offset within it.
+    pub fn find(&self, addr: &Addr) -> Option<(&Arc<str>, u64)> {
+        let (start, (name, size)) = self.symbols.range(..=addr.clone()).next_back()?;
+        let offset = u64::try_from(addr - start).ok()?;
+        if offset == 0 || offset < *size as u64 {
+            Some((name, offset))
+        } else {
+            None
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Addr, &Arc<str>, usize)> {
+        self.symbols.iter().map(|(addr, (name, size))| (addr, name, *size))
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+}
+
+/// Renders the addresses of memory operands for listings of the IR, in
+/// the style given by `style`; e.g., as `.data+0x20 (g_counter)` for a
+/// load of the address 0x20 bytes into the Region `.data`, at which the
+/// symbol `g_counter` starts.
+#[derive(Clone, Default)]
+pub struct MemOperands<'a, 'r> {
+    memory: Option<&'a Mem<'r>>,
+    symbols: Option<&'a Symbols>,
+    style: AddrStyle,
+}
+
+impl<'a, 'r> MemOperands<'a, 'r> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Renders addresses relative to the Regions of `memory`.
+    pub fn memory(&mut self, memory: &'a Mem<'r>) -> &mut Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Renders addresses relative to `symbols`.
+    pub fn symbols(&mut self, symbols: &'a Symbols) -> &mut Self {
+        self.symbols = Some(symbols);
+        self
+    }
+
+    pub fn style(&mut self, style: AddrStyle) -> &mut Self {
+        self.style = style;
+        self
+    }
+
+    pub fn is_raw(&self) -> bool {
+        self.style == AddrStyle::Raw
+    }
+
+    fn raw(addr: &Addr) -> String {
+        format!("0x{}", addr)
+    }
+
+    fn with_offset(base: &str, offset: u64) -> String {
+        if offset == 0 {
+            base.to_string()
+        } else {
+            format!("{}+{:#x}", base, offset)
+        }
+    }
+
+    fn region(&self, addr: &Addr) -> Option<String> {
+        let region = self.memory?.find_region(addr)?;
+        let offset = u64::try_from(addr - region.address()).ok()?;
+        Some(Self::with_offset(region.name(), offset))
+    }
+
+    fn symbol(&self, addr: &Addr) -> Option<String> {
+        let (name, offset) = self.symbols?.find(addr)?;
+        Some(Self::with_offset(name, offset))
+    }
+
+    /// Renders `addr`.
+    pub fn render(&self, addr: &Addr) -> String {
+        if self.is_raw() {
+            return Self::raw(addr)
+        }
+
+        match (self.region(addr), self.symbol(addr)) {
+            (Some(region), Some(symbol)) => format!("{} ({})", region, symbol),
+            (Some(region), None) => region,
+            (None, Some(symbol)) => format!("{} ({})", Self::raw(addr), symbol),
+            (None, None) => Self::raw(addr),
+        }
+    }
+
+    /// Renders the address of a memory operand, if it is constant.
+    pub fn render_expr(&self, addr: &Expr) -> Option<String> {
+        addr.val().map(|value| self.render(&Addr::from(value.clone())))
+    }
+}
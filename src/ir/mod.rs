@@ -1,23 +1,47 @@
 pub mod block;
-pub use block::Blk;
+pub use block::{Blk, BlkMerge, BlkStmt, DefIdx, JmpIdx, PhiIdx, StmtIdx};
+
+pub mod cfg;
+pub use cfg::Cfg;
+
+pub mod change;
+pub use change::{Change, ChangeError, ChangeSet};
+
+pub mod confidence;
+pub use confidence::Confidence;
 
 pub mod effect;
 pub use effect::{Def, Jmp};
 
 pub mod expression;
-pub use expression::Expr;
+pub use expression::{BinOp, BinRel, Cast, CostModel, Expr, ExprCost, UnOp, UnRel};
+
+pub mod intrinsic;
+pub use intrinsic::{Intrinsic, IntrinsicExpansion, IntrinsicRegistry, SideEffects};
 
 pub mod location;
 pub use location::Loc;
 
 pub mod memory;
-pub use memory::{Addr, Mem, Region};
+pub use memory::{Addr, AddrStyle, Mem, MemOperands, Region, Symbols};
+
+pub mod pattern;
+pub use pattern::{Bindings, DefPattern, ExprPattern, Pattern};
 
 pub mod phi;
 pub use phi::Phi;
 
+pub mod printer;
+pub use printer::{Printable, Printer};
+
 pub mod project;
-pub use project::{Project, ProjectBuilder};
+pub use project::{ExternalFunction, Imports, Project, ProjectBuilder};
+
+pub mod provenance;
+pub use provenance::Provenance;
+
+pub mod source;
+pub use source::SourceLoc;
 
 pub mod subroutine;
 pub use subroutine::Sub;
@@ -27,4 +51,6 @@ pub use value::bv::BitVec;
 pub use value::fp::Float;
 
 pub mod variable;
-pub use variable::Var;
\ No newline at end of file
+pub use variable::{FreshVars, Var};
+
+pub mod visit;
\ No newline at end of file
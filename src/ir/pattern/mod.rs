@@ -0,0 +1,350 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::ir::{BinOp, BinRel, Def, Expr, UnOp};
+use crate::passes::simplify::{is_commutative_op, is_commutative_rel};
+
+/// A pattern over expressions; see `Pattern`.
+///
+/// Patterns may bind the sub-expressions they match to names (see `bind`);
+/// a name bound more than once must be bound to equal sub-expressions,
+/// e.g., `x ^ x` only matches expressions whose operands are equal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExprPattern {
+    /// Matches any expression
+    Any,
+    /// Matches what the inner pattern matches, binding it to the name
+    Bind(Arc<str>, Box<ExprPattern>),
+    /// Matches a constant with the given (unsigned) value, of any size
+    Val(u64),
+    /// Matches a constant whose (unsigned) value is within the inclusive
+    /// range, of any size
+    ValRange(u64, u64),
+    /// Matches a variable with the given name
+    Var(Arc<str>),
+    UnOp(UnOp, Box<ExprPattern>),
+    BinOp(BinOp, Box<ExprPattern>, Box<ExprPattern>),
+    BinRel(BinRel, Box<ExprPattern>, Box<ExprPattern>),
+    /// Matches a cast of any kind
+    Cast(Box<ExprPattern>),
+    /// Matches a load of any size, from any memory
+    Load(Box<ExprPattern>),
+}
+
+impl ExprPattern {
+    pub fn any() -> Self {
+        Self::Any
+    }
+
+    /// Matches any expression, binding it to `name`.
+    pub fn bind(name: impl Into<Arc<str>>) -> Self {
+        Self::Bind(name.into(), Box::new(Self::Any))
+    }
+
+    pub fn val(value: u64) -> Self {
+        Self::Val(value)
+    }
+
+    pub fn val_range(min: u64, max: u64) -> Self {
+        Self::ValRange(min, max)
+    }
+
+    pub fn var(name: impl Into<Arc<str>>) -> Self {
+        Self::Var(name.into())
+    }
+
+    pub fn un_op(op: UnOp, pattern: ExprPattern) -> Self {
+        Self::UnOp(op, Box::new(pattern))
+    }
+
+    pub fn bin_op(op: BinOp, lpattern: ExprPattern, rpattern: ExprPattern) -> Self {
+        Self::BinOp(op, Box::new(lpattern), Box::new(rpattern))
+    }
+
+    pub fn bin_rel(op: BinRel, lpattern: ExprPattern, rpattern: ExprPattern) -> Self {
+        Self::BinRel(op, Box::new(lpattern), Box::new(rpattern))
+    }
+
+    pub fn cast(pattern: ExprPattern) -> Self {
+        Self::Cast(Box::new(pattern))
+    }
+
+    pub fn load(pattern: ExprPattern) -> Self {
+        Self::Load(Box::new(pattern))
+    }
+
+    /// Binds what this pattern matches to `name`.
+    pub fn named(self, name: impl Into<Arc<str>>) -> Self {
+        Self::Bind(name.into(), Box::new(self))
+    }
+
+    fn match_expr(&self, expr: &Expr, commutative: bool, bindings: &mut Bindings) -> bool {
+        match (self, expr) {
+            (Self::Any, _) => true,
+            (Self::Bind(name, pattern), _) => {
+                if !pattern.match_expr(expr, commutative, bindings) {
+                    return false
+                }
+                match bindings.bindings.get(name) {
+                    Some(bound) => bound == expr,
+                    None => {
+                        bindings.bindings.insert(name.clone(), expr.clone());
+                        true
+                    }
+                }
+            }
+            (Self::Val(value), Expr::Val(bv)) => bv.to_u64() == Some(*value),
+            (Self::ValRange(min, max), Expr::Val(bv)) => {
+                bv.to_u64().map(|value| *min <= value && value <= *max).unwrap_or(false)
+            }
+            (Self::Var(name), Expr::Var(var)) => var.name() == name,
+            (Self::UnOp(pop, pattern), Expr::UnOp(op, expr)) => {
+                pop == op && pattern.match_expr(expr, commutative, bindings)
+            }
+            (Self::BinOp(pop, lpattern, rpattern), Expr::BinOp(op, lexpr, rexpr)) => {
+                pop == op
+                    && Self::match_operands(
+                        (lpattern, rpattern),
+                        (lexpr, rexpr),
+                        commutative && is_commutative_op(*op),
+                        bindings,
+                    )
+            }
+            (Self::BinRel(pop, lpattern, rpattern), Expr::BinRel(op, lexpr, rexpr)) => {
+                pop == op
+                    && Self::match_operands(
+                        (lpattern, rpattern),
+                        (lexpr, rexpr),
+                        commutative && is_commutative_rel(*op),
+                        bindings,
+                    )
+            }
+            (Self::Cast(pattern), Expr::Cast(expr, _)) => pattern.match_expr(expr, commutative, bindings),
+            (Self::Load(pattern), Expr::Load(expr, _, _)) => pattern.match_expr(expr, commutative, bindings),
+            _ => false,
+        }
+    }
+
+    // matches the operands in order and, if swap, in reverse order; the
+    // bindings of a failed attempt are discarded
+    fn match_operands(
+        (lpattern, rpattern): (&ExprPattern, &ExprPattern),
+        (lexpr, rexpr): (&Expr, &Expr),
+        swap: bool,
+        bindings: &mut Bindings,
+    ) -> bool {
+        let mut attempt = bindings.clone();
+        if lpattern.match_expr(lexpr, swap, &mut attempt) && rpattern.match_expr(rexpr, swap, &mut attempt) {
+            *bindings = attempt;
+            return true
+        }
+
+        if swap {
+            let mut attempt = bindings.clone();
+            if lpattern.match_expr(rexpr, swap, &mut attempt) && rpattern.match_expr(lexpr, swap, &mut attempt) {
+                *bindings = attempt;
+                return true
+            }
+        }
+
+        false
+    }
+}
+
+/// A pattern over Defs; see `Pattern`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DefPattern {
+    /// Matches an assignment to a variable with the given name (or any
+    /// variable, if None) of an expression matching the pattern
+    Assign(Option<Arc<str>>, ExprPattern),
+    Assume(ExprPattern),
+    /// Matches a store of a value matching the second pattern to an
+    /// address matching the first
+    Store(ExprPattern, ExprPattern),
+}
+
+impl DefPattern {
+    pub fn assign(var: Option<&str>, pattern: ExprPattern) -> Self {
+        Self::Assign(var.map(Arc::from), pattern)
+    }
+
+    pub fn assume(pattern: ExprPattern) -> Self {
+        Self::Assume(pattern)
+    }
+
+    pub fn store(addr: ExprPattern, value: ExprPattern) -> Self {
+        Self::Store(addr, value)
+    }
+
+    fn match_def(&self, def: &Def, commutative: bool, bindings: &mut Bindings) -> bool {
+        match (self, def) {
+            (Self::Assign(name, pattern), Def::Assign(var, expr)) => {
+                name.as_ref().map(|name| var.name() == name).unwrap_or(true)
+                    && pattern.match_expr(expr, commutative, bindings)
+            }
+            (Self::Assume(pattern), Def::Assume(expr)) => pattern.match_expr(expr, commutative, bindings),
+            (Self::Store(apattern, vpattern), Def::Store(addr, value, _, _)) => {
+                apattern.match_expr(addr, commutative, bindings)
+                    && vpattern.match_expr(value, commutative, bindings)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The sub-expressions bound by a match of a pattern, by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bindings {
+    bindings: BTreeMap<Arc<str>, Expr>,
+}
+
+impl Bindings {
+    pub fn get(&self, name: &str) -> Option<&Expr> {
+        self.bindings.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Expr)> {
+        self.bindings.iter().map(|(name, expr)| (&**name, expr))
+    }
+
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PatternKind {
+    Expr(ExprPattern),
+    Def(DefPattern),
+}
+
+/// A pattern to search for in code (see `Project::find_semantic`): either
+/// an expression pattern, which is matched against each sub-expression of
+/// each statement, or a Def pattern, matched against each Def.
+///
+/// Patterns match structurally; if the pattern is commutative (see
+/// `commutative`), the operands of commutative operations (e.g., `+` and
+/// `==`) also match in either order, e.g., `x * 0x5bd1e995` matches
+/// `0x5bd1e995 * y`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    kind: PatternKind,
+    commutative: bool,
+}
+
+impl From<ExprPattern> for Pattern {
+    fn from(pattern: ExprPattern) -> Self {
+        Self {
+            kind: PatternKind::Expr(pattern),
+            commutative: false,
+        }
+    }
+}
+
+impl From<DefPattern> for Pattern {
+    fn from(pattern: DefPattern) -> Self {
+        Self {
+            kind: PatternKind::Def(pattern),
+            commutative: false,
+        }
+    }
+}
+
+impl Pattern {
+    pub fn commutative(mut self, enabled: bool) -> Self {
+        self.commutative = enabled;
+        self
+    }
+
+    pub fn is_commutative(&self) -> bool {
+        self.commutative
+    }
+
+    pub fn is_expr(&self) -> bool {
+        matches!(self.kind, PatternKind::Expr(_))
+    }
+
+    /// Matches `expr` itself (not its sub-expressions); Def patterns never
+    /// match expressions.
+    pub fn matches_expr(&self, expr: &Expr) -> Option<Bindings> {
+        let mut bindings = Bindings::default();
+        match self.kind {
+            PatternKind::Expr(ref pattern) if pattern.match_expr(expr, self.commutative, &mut bindings) => {
+                Some(bindings)
+            }
+            _ => None,
+        }
+    }
+
+    /// Matches `def`; expression patterns never match Defs.
+    pub fn matches_def(&self, def: &Def) -> Option<Bindings> {
+        let mut bindings = Bindings::default();
+        match self.kind {
+            PatternKind::Def(ref pattern) if pattern.match_def(def, self.commutative, &mut bindings) => {
+                Some(bindings)
+            }
+            _ => None,
+        }
+    }
+}
+
+// calls f on expr and each of its sub-expressions, outermost first
+pub(crate) fn for_each_subexpr<'a>(expr: &'a Expr, f: &mut impl FnMut(&'a Expr)) {
+    f(expr);
+    match expr {
+        Expr::UnRel(_, expr)
+        | Expr::UnOp(_, expr)
+        | Expr::Cast(expr, _)
+        | Expr::Load(expr, _, _)
+        | Expr::Extract(expr, _, _) => for_each_subexpr(expr, f),
+        Expr::BinRel(_, lexpr, rexpr) | Expr::BinOp(_, lexpr, rexpr) | Expr::Concat(lexpr, rexpr) => {
+            for_each_subexpr(lexpr, f);
+            for_each_subexpr(rexpr, f);
+        }
+        Expr::IfElse(cond, texpr, fexpr) => {
+            for_each_subexpr(cond, f);
+            for_each_subexpr(texpr, f);
+            for_each_subexpr(fexpr, f);
+        }
+        Expr::Intrinsic(_, args, _) => {
+            for arg in args.iter() {
+                for_each_subexpr(arg, f);
+            }
+        }
+        Expr::Val(_) | Expr::Var(_) => (),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::{BitVec, Var};
+    use crate::types::U32;
+
+    fn eax() -> Expr {
+        Var::physical("EAX", U32).into()
+    }
+
+    #[test]
+    fn test_match_commutative() {
+        let expr = Expr::bin_op(BinOp::Mul, BitVec::from_u64(0x5bd1e995, 32), eax());
+        let pattern = ExprPattern::bin_op(BinOp::Mul, ExprPattern::bind("x"), ExprPattern::val(0x5bd1e995));
+
+        assert_eq!(Pattern::from(pattern.clone()).matches_expr(&expr), None);
+
+        let bindings = Pattern::from(pattern).commutative(true).matches_expr(&expr);
+        assert_eq!(bindings.as_ref().and_then(|bindings| bindings.get("x")), Some(&eax()));
+    }
+
+    #[test]
+    fn test_match_bound_twice() {
+        let pattern = Pattern::from(ExprPattern::bin_op(BinOp::Xor, ExprPattern::bind("x"), ExprPattern::bind("x")));
+
+        assert!(pattern.matches_expr(&Expr::bin_op(BinOp::Xor, eax(), eax())).is_some());
+        assert!(pattern.matches_expr(&Expr::bin_op(BinOp::Xor, eax(), BitVec::zero(32))).is_none());
+    }
+}
@@ -1,7 +1,41 @@
 use crate::ir::{Expr, Var};
+use crate::prelude::{Entity, Tagged};
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct Phi {
     var: Var,
     choices: Vec<(Expr, Expr)>,
-}
\ No newline at end of file
+}
+
+impl Tagged for Phi {
+    const TAG: &'static str = "phi";
+}
+
+impl Phi {
+    pub fn new(var: impl Into<Var>, choices: Vec<(Expr, Expr)>) -> Entity<Self> {
+        Entity::new("phi", Self {
+            var: var.into(),
+            choices,
+        })
+    }
+
+    pub fn var(&self) -> &Var {
+        &self.var
+    }
+
+    pub fn var_mut(&mut self) -> &mut Var {
+        &mut self.var
+    }
+
+    pub fn choices(&self) -> &[(Expr, Expr)] {
+        &self.choices
+    }
+
+    pub fn choices_mut(&mut self) -> &mut Vec<(Expr, Expr)> {
+        &mut self.choices
+    }
+
+    pub fn parts_mut(&mut self) -> (&mut Var, &mut Vec<(Expr, Expr)>) {
+        (&mut self.var, &mut self.choices)
+    }
+}
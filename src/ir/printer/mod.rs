@@ -0,0 +1,567 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Write};
+
+use crate::ir::{
+    BinOp, BinRel, Blk, Cast, Def, Expr, Jmp, Loc, MemOperands, Phi, Sub, UnOp, UnRel, Var,
+};
+use crate::prelude::{Entity, Id, Identifiable};
+
+/// Renders the IR as readable text, e.g.,
+///
+/// ```text
+/// sub main @ 0x401000
+/// b0 @ 0x401000:
+///   RAX := (RDI + 0x1)
+///   if (RAX == 0x0) goto b1
+///   goto 0x401020
+/// ```
+///
+/// Expressions are written infix, with binary operators parenthesised;
+/// signed operators are suffixed by `s` (e.g., `<s`, `>>s`). Verbosity is
+/// configurable: with `ids`, statements and Blks are prefixed by their
+/// entity ids; with `types`, variables, constants and intrinsics carry
+/// their sizes (e.g., `RAX:64.0`, `0x1:64`). The sizes of loads, stores
+/// and casts are always given, as they change their meaning.
+///
+/// Within a Sub (see `sub`), Blks are labelled `b0`, `b1`, ... in the
+/// order they are held by the Sub; elsewhere, resolved flow targets are
+/// given by their ids. The addresses of memory operands are rendered by
+/// `MemOperands`, if given (e.g., as `.data+0x20 (g_counter)`).
+#[derive(Clone, Default)]
+pub struct Printer<'a, 'r> {
+    ids: bool,
+    types: bool,
+    operands: Option<MemOperands<'a, 'r>>,
+}
+
+impl<'a, 'r> Printer<'a, 'r> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prefix statements and Blks by their entity ids.
+    pub fn ids(&mut self, ids: bool) -> &mut Self {
+        self.ids = ids;
+        self
+    }
+
+    /// Give the sizes of variables, constants and intrinsics.
+    pub fn types(&mut self, types: bool) -> &mut Self {
+        self.types = types;
+        self
+    }
+
+    /// Render the constant addresses of loads and stores with `operands`.
+    pub fn mem_operands(&mut self, operands: MemOperands<'a, 'r>) -> &mut Self {
+        self.operands = Some(operands);
+        self
+    }
+
+    /// A value implementing `Display` that renders `ir` with the printer.
+    pub fn display<'p, T: ?Sized + Printable>(&'p self, ir: &'p T) -> PrintWith<'p, 'a, 'r, T> {
+        PrintWith { printer: self, ir }
+    }
+
+    pub fn expr(&self, expr: &Expr) -> String {
+        let mut s = String::new();
+        self.write_expr(&mut s, expr, false);
+        s
+    }
+
+    pub fn loc(&self, loc: &Loc) -> String {
+        let mut s = String::new();
+        self.write_loc(&mut s, None, loc);
+        s
+    }
+
+    pub fn phi(&self, phi: &Entity<Phi>) -> String {
+        let mut s = String::new();
+        self.write_id(&mut s, phi.id());
+        self.write_phi(&mut s, phi);
+        s
+    }
+
+    pub fn def(&self, def: &Entity<Def>) -> String {
+        let mut s = String::new();
+        self.write_id(&mut s, def.id());
+        self.write_def(&mut s, def);
+        s
+    }
+
+    pub fn jmp(&self, jmp: &Entity<Jmp>) -> String {
+        let mut s = String::new();
+        self.write_id(&mut s, jmp.id());
+        self.write_jmp(&mut s, None, jmp);
+        s
+    }
+
+    pub fn blk(&self, blk: &Entity<Blk>) -> String {
+        let mut s = String::new();
+        self.write_id(&mut s, blk.id());
+        self.write_blk(&mut s, None, Some(blk.id()), blk);
+        s
+    }
+
+    pub fn sub(&self, sub: &Entity<Sub>) -> String {
+        let mut s = String::new();
+        self.write_id(&mut s, sub.id());
+        self.write_sub(&mut s, sub);
+        s
+    }
+
+    // writes to strings are infallible, hence the results of writes are
+    // discarded below
+
+    fn write_id<T>(&self, s: &mut String, id: Id<T>) {
+        if self.ids {
+            let _ = write!(s, "[{}] ", id);
+        }
+    }
+
+    fn write_var(&self, s: &mut String, var: &Var) {
+        if self.types {
+            let _ = write!(s, "{}", var);
+        } else if var.generation() == 0 {
+            s.push_str(var.name());
+        } else {
+            let _ = write!(s, "{}.{}", var.name(), var.generation());
+        }
+    }
+
+    fn write_args<'e>(&self, s: &mut String, args: impl IntoIterator<Item = &'e Expr>) {
+        s.push('(');
+        for (i, arg) in args.into_iter().enumerate() {
+            if i > 0 {
+                s.push_str(", ");
+            }
+            self.write_expr(s, arg, false);
+        }
+        s.push(')');
+    }
+
+    fn write_mem(&self, s: &mut String, mem: &Var, addr: &Expr, bits: u32) {
+        self.write_var(s, mem);
+        s.push('[');
+        match self.operands.as_ref().and_then(|operands| operands.render_expr(addr)) {
+            Some(rendered) => s.push_str(&rendered),
+            None => self.write_expr(s, addr, false),
+        }
+        let _ = write!(s, "]:{}", bits);
+    }
+
+    fn write_expr(&self, s: &mut String, expr: &Expr, nested: bool) {
+        match expr {
+            Expr::UnRel(UnRel::Nan, expr) => {
+                s.push_str("nan");
+                self.write_args(s, [&**expr]);
+            }
+            Expr::UnOp(op, expr) => {
+                let name = match op {
+                    UnOp::Not => "~",
+                    UnOp::Neg => "-",
+                    UnOp::Abs => "abs",
+                    UnOp::Sqrt => "sqrt",
+                    UnOp::Ceiling => "ceiling",
+                    UnOp::Floor => "floor",
+                    UnOp::Round => "round",
+                    UnOp::PopCount => "popcount",
+                };
+                s.push_str(name);
+                if matches!(op, UnOp::Not | UnOp::Neg) {
+                    self.write_expr(s, expr, true);
+                } else {
+                    self.write_args(s, [&**expr]);
+                }
+            }
+            Expr::BinRel(op, lexpr, rexpr) => {
+                let infix = match op {
+                    BinRel::Eq => "==",
+                    BinRel::Neq => "!=",
+                    BinRel::Lt => "<",
+                    BinRel::Le => "<=",
+                    BinRel::SLt => "<s",
+                    BinRel::SLe => "<=s",
+                    BinRel::SBorrow => return self.write_call(s, "sborrow", lexpr, rexpr),
+                    BinRel::Carry => return self.write_call(s, "carry", lexpr, rexpr),
+                    BinRel::SCarry => return self.write_call(s, "scarry", lexpr, rexpr),
+                };
+                self.write_infix(s, infix, lexpr, rexpr);
+            }
+            Expr::BinOp(op, lexpr, rexpr) => {
+                let infix = match op {
+                    BinOp::And => "&",
+                    BinOp::Or => "|",
+                    BinOp::Xor => "^",
+                    BinOp::Add => "+",
+                    BinOp::Sub => "-",
+                    BinOp::Div => "/",
+                    BinOp::SDiv => "/s",
+                    BinOp::Mul => "*",
+                    BinOp::Rem => "%",
+                    BinOp::SRem => "%s",
+                    BinOp::Shl => "<<",
+                    BinOp::Sar => ">>s",
+                    BinOp::Shr => ">>",
+                };
+                self.write_infix(s, infix, lexpr, rexpr);
+            }
+            Expr::Cast(expr, cast) => {
+                let _ = match cast {
+                    Cast::Bool => write!(s, "bool"),
+                    Cast::Float(bits) => write!(s, "float:{}", bits),
+                    Cast::Signed(bits) => write!(s, "signed:{}", bits),
+                    Cast::Unsigned(bits) => write!(s, "unsigned:{}", bits),
+                    Cast::High(bits) => write!(s, "high:{}", bits),
+                    Cast::Low(bits) => write!(s, "low:{}", bits),
+                };
+                self.write_args(s, [&**expr]);
+            }
+            Expr::Load(addr, bits, mem) => self.write_mem(s, mem, addr, *bits),
+            Expr::Extract(expr, lsb, msb) => {
+                self.write_expr(s, expr, true);
+                let _ = write!(s, "[{}..{}]", lsb, msb);
+            }
+            Expr::Concat(lexpr, rexpr) => self.write_infix(s, "++", lexpr, rexpr),
+            Expr::IfElse(cond, texpr, fexpr) => {
+                if nested {
+                    s.push('(');
+                }
+                s.push_str("if ");
+                self.write_expr(s, cond, false);
+                s.push_str(" then ");
+                self.write_expr(s, texpr, false);
+                s.push_str(" else ");
+                self.write_expr(s, fexpr, false);
+                if nested {
+                    s.push(')');
+                }
+            }
+            Expr::Intrinsic(name, args, bits) => {
+                let _ = write!(s, "@{}", name);
+                if self.types {
+                    let _ = write!(s, ":{}", bits);
+                }
+                self.write_args(s, args.iter().map(|arg| &**arg));
+            }
+            Expr::Val(bv) => {
+                let _ = write!(s, "0x{:x}", bv);
+                if self.types {
+                    let _ = write!(s, ":{}", bv.bits());
+                }
+            }
+            Expr::Var(var) => self.write_var(s, var),
+        }
+    }
+
+    fn write_infix(&self, s: &mut String, op: &str, lexpr: &Expr, rexpr: &Expr) {
+        // binary operators are always parenthesised, so that precedence
+        // need not be remembered by the reader
+        s.push('(');
+        self.write_expr(s, lexpr, true);
+        let _ = write!(s, " {} ", op);
+        self.write_expr(s, rexpr, true);
+        s.push(')');
+    }
+
+    fn write_call(&self, s: &mut String, name: &str, lexpr: &Expr, rexpr: &Expr) {
+        s.push_str(name);
+        self.write_args(s, [lexpr, rexpr]);
+    }
+
+    fn write_label(&self, s: &mut String, labels: Option<&BTreeMap<Id<Blk>, usize>>, id: Id<Blk>) {
+        match labels.and_then(|labels| labels.get(&id)) {
+            Some(label) => {
+                let _ = write!(s, "b{}", label);
+            }
+            None => {
+                let _ = write!(s, "{}", id);
+            }
+        }
+    }
+
+    fn write_loc(&self, s: &mut String, labels: Option<&BTreeMap<Id<Blk>, usize>>, loc: &Loc) {
+        match loc {
+            Loc::Resolved(id) => self.write_label(s, labels, *id),
+            Loc::Fixed(addr) => {
+                let _ = write!(s, "0x{}", addr);
+            }
+            Loc::Computed(expr) => {
+                s.push('*');
+                self.write_expr(s, expr, true);
+            }
+        }
+    }
+
+    fn write_phi(&self, s: &mut String, phi: &Phi) {
+        self.write_var(s, phi.var());
+        s.push_str(" := phi(");
+        for (i, (cond, expr)) in phi.choices().iter().enumerate() {
+            if i > 0 {
+                s.push_str(", ");
+            }
+            self.write_expr(s, cond, false);
+            s.push_str(" -> ");
+            self.write_expr(s, expr, false);
+        }
+        s.push(')');
+    }
+
+    fn write_def(&self, s: &mut String, def: &Def) {
+        match def {
+            Def::Assign(var, expr) => {
+                self.write_var(s, var);
+                s.push_str(" := ");
+                self.write_expr(s, expr, false);
+            }
+            Def::Assume(expr) => {
+                s.push_str("assume ");
+                self.write_expr(s, expr, false);
+            }
+            Def::Store(addr, val, bits, mem) => {
+                self.write_mem(s, mem, addr, *bits);
+                s.push_str(" := ");
+                self.write_expr(s, val, false);
+            }
+        }
+    }
+
+    fn write_jmp(&self, s: &mut String, labels: Option<&BTreeMap<Id<Blk>, usize>>, jmp: &Jmp) {
+        match jmp {
+            Jmp::Branch(loc) => {
+                s.push_str("goto ");
+                self.write_loc(s, labels, loc);
+            }
+            Jmp::CBranch(loc, cond) => {
+                s.push_str("if ");
+                self.write_expr(s, cond, false);
+                s.push_str(" goto ");
+                self.write_loc(s, labels, loc);
+            }
+            Jmp::Call(loc, args) => {
+                s.push_str("call ");
+                self.write_loc(s, labels, loc);
+                self.write_args(s, args.iter());
+            }
+            Jmp::Intrinsic(name, args) => {
+                let _ = write!(s, "@{}", name);
+                self.write_args(s, args.iter());
+            }
+            Jmp::Return(loc) => {
+                s.push_str("return ");
+                self.write_loc(s, labels, loc);
+            }
+        }
+    }
+
+    fn write_blk(
+        &self,
+        s: &mut String,
+        labels: Option<&BTreeMap<Id<Blk>, usize>>,
+        id: Option<Id<Blk>>,
+        blk: &Blk,
+    ) {
+        match id {
+            Some(id) => self.write_label(s, labels, id),
+            None => s.push_str("blk"),
+        }
+        if let Some(addr) = blk.addr() {
+            let _ = write!(s, " @ 0x{}", addr);
+        }
+        if blk.is_landing_pad() {
+            s.push_str(" (landing pad)");
+        }
+        s.push_str(":\n");
+
+        for phi in blk.phis() {
+            s.push_str("  ");
+            self.write_id(s, phi.id());
+            self.write_phi(s, phi);
+            s.push('\n');
+        }
+
+        for def in blk.defs() {
+            s.push_str("  ");
+            self.write_id(s, def.id());
+            self.write_def(s, def);
+            s.push('\n');
+        }
+
+        for jmp in blk.jmps() {
+            s.push_str("  ");
+            self.write_id(s, jmp.id());
+            self.write_jmp(s, labels, jmp);
+            s.push('\n');
+        }
+    }
+
+    fn write_sub(&self, s: &mut String, sub: &Sub) {
+        s.push_str("sub");
+        if let Some(symbol) = sub.symbol() {
+            let _ = write!(s, " {}", symbol);
+        }
+        if let Some(addr) = sub.addr() {
+            let _ = write!(s, " @ 0x{}", addr);
+        }
+        s.push('\n');
+
+        let labels = sub
+            .blks()
+            .iter()
+            .enumerate()
+            .map(|(i, blk)| (blk.id(), i))
+            .collect::<BTreeMap<_, _>>();
+
+        for blk in sub.blks() {
+            self.write_id(s, blk.id());
+            self.write_blk(s, Some(&labels), Some(blk.id()), blk);
+        }
+    }
+}
+
+/// IR that can be rendered by a `Printer`.
+pub trait Printable {
+    fn print(&self, printer: &Printer) -> String;
+}
+
+impl Printable for Expr {
+    fn print(&self, printer: &Printer) -> String {
+        printer.expr(self)
+    }
+}
+
+impl Printable for Loc {
+    fn print(&self, printer: &Printer) -> String {
+        printer.loc(self)
+    }
+}
+
+impl Printable for Entity<Phi> {
+    fn print(&self, printer: &Printer) -> String {
+        printer.phi(self)
+    }
+}
+
+impl Printable for Entity<Def> {
+    fn print(&self, printer: &Printer) -> String {
+        printer.def(self)
+    }
+}
+
+impl Printable for Entity<Jmp> {
+    fn print(&self, printer: &Printer) -> String {
+        printer.jmp(self)
+    }
+}
+
+impl Printable for Entity<Blk> {
+    fn print(&self, printer: &Printer) -> String {
+        printer.blk(self)
+    }
+}
+
+impl Printable for Entity<Sub> {
+    fn print(&self, printer: &Printer) -> String {
+        printer.sub(self)
+    }
+}
+
+/// Renders IR with a given `Printer` (see `Printer::display`).
+pub struct PrintWith<'p, 'a, 'r, T: ?Sized> {
+    printer: &'p Printer<'a, 'r>,
+    ir: &'p T,
+}
+
+impl<'p, 'a, 'r, T: ?Sized + Printable> Display for PrintWith<'p, 'a, 'r, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.ir.print(self.printer))
+    }
+}
+
+// the Display implementations render the IR with the default printer;
+// Blks rendered outside of their Subs are labelled `blk`
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        Printer::new().write_expr(&mut s, self, false);
+        f.write_str(&s)
+    }
+}
+
+impl Display for Loc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        Printer::new().write_loc(&mut s, None, self);
+        f.write_str(&s)
+    }
+}
+
+impl Display for Phi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        Printer::new().write_phi(&mut s, self);
+        f.write_str(&s)
+    }
+}
+
+impl Display for Def {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        Printer::new().write_def(&mut s, self);
+        f.write_str(&s)
+    }
+}
+
+impl Display for Jmp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        Printer::new().write_jmp(&mut s, None, self);
+        f.write_str(&s)
+    }
+}
+
+impl Display for Blk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        Printer::new().write_blk(&mut s, None, None, self);
+        f.write_str(&s)
+    }
+}
+
+impl Display for Sub {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        Printer::new().write_sub(&mut s, self);
+        f.write_str(&s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::bv::BitVecT;
+
+    #[test]
+    fn test_print_sub() {
+        let typ = BitVecT::with_bits(64, false);
+        let (rax, rdi) = (Var::from(Var::physical("RAX", typ)), Var::from(Var::physical("RDI", typ)));
+
+        let sum = Expr::BinOp(BinOp::Add, Box::new(Expr::from(rax.clone())), Box::new(Expr::from(rdi.clone())));
+        let cond = Expr::BinRel(BinRel::SLt, Box::new(Expr::from(rax.clone())), Box::new(Expr::from(rdi)));
+
+        let mut blk = Blk::new(None);
+        blk.add_def(Def::assign(rax, Expr::UnOp(UnOp::Neg, Box::new(sum))));
+        let id = blk.id();
+        blk.add_jmp(Jmp::cbranch(id, cond));
+        let sub = Sub::new(None, None, vec![blk]);
+
+        assert_eq!(sub.to_string(), "sub\nb0:\n  RAX := -(RAX + RDI)\n  if (RAX <s RDI) goto b0\n");
+
+        let mut printer = Printer::new();
+        printer.types(true);
+        assert_eq!(
+            printer.def(&sub.blks()[0].defs()[0]),
+            "RAX:64.0 := -(RAX:64.0 + RDI:64.0)",
+        );
+    }
+}
@@ -0,0 +1,512 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::{self, Display};
+use std::sync::Arc;
+
+use crate::ir::{BinOp, Confidence, Def, Expr, Jmp, Loc, Project, Sub, Var};
+use crate::ir::Addr;
+use crate::oracles::{HeuristicSubOracle, SubOracle};
+use crate::prelude::Entity;
+
+/// Why the boundaries of a Sub were adjusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BoundaryReason {
+    /// The Sub reached the start of another Sub via a branch or
+    /// fall-through
+    SubEntry,
+    /// The Sub fell through into a prologue
+    Prologue,
+    /// The Sub's returns reached from a fall-through do not match its
+    /// stack frame, but match a frame starting at the fall-through
+    ReturnMismatch,
+    /// The Sub is never called, and is only branched to by one other Sub
+    BranchOnly,
+}
+
+impl BoundaryReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::SubEntry => "sub entry",
+            Self::Prologue => "prologue",
+            Self::ReturnMismatch => "return mismatch",
+            Self::BranchOnly => "branch only",
+        }
+    }
+}
+
+impl Display for BoundaryReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// An adjustment of the boundaries of the Project's Subs (see
+/// `Project::refine_sub_boundaries`).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BoundaryAdjustment {
+    /// The Sub starting at `sub` no longer includes the code from `at`,
+    /// which starts a Sub of its own
+    Split {
+        sub: Addr,
+        at: Addr,
+        reason: BoundaryReason,
+    },
+    /// The Sub starting at `merged` was removed, as its code is part of
+    /// the Sub starting at `into`
+    Merged {
+        into: Addr,
+        merged: Addr,
+        reason: BoundaryReason,
+    },
+}
+
+impl BoundaryAdjustment {
+    pub fn reason(&self) -> BoundaryReason {
+        match self {
+            Self::Split { reason, .. } | Self::Merged { reason, .. } => *reason,
+        }
+    }
+}
+
+impl Display for BoundaryAdjustment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Split { sub, at, reason } => {
+                write!(f, "split sub at {} at {} ({})", sub, at, reason)
+            }
+            Self::Merged { into, merged, reason } => {
+                write!(f, "merged sub at {} into sub at {} ({})", merged, into, reason)
+            }
+        }
+    }
+}
+
+/// The adjustments made by `Project::refine_sub_boundaries`.
+#[derive(Debug, Clone, Default)]
+pub struct BoundaryReport {
+    adjustments: Vec<BoundaryAdjustment>,
+}
+
+impl BoundaryReport {
+    pub fn adjustments(&self) -> &[BoundaryAdjustment] {
+        &self.adjustments
+    }
+
+    pub fn len(&self) -> usize {
+        self.adjustments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.adjustments.is_empty()
+    }
+}
+
+impl Display for BoundaryReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for adjustment in self.adjustments.iter() {
+            writeln!(f, "{}", adjustment)?;
+        }
+        Ok(())
+    }
+}
+
+// how control reaches a group of Blks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flow {
+    Branch,
+    FallThrough,
+    // the fall-through following a call
+    Return,
+    Call,
+}
+
+// the symbol of a Sub being re-lifted, which must be preserved
+struct Preserved(Option<String>);
+
+impl SubOracle for Preserved {
+    fn sub_starts(&self) -> BTreeSet<Addr> {
+        BTreeSet::new()
+    }
+
+    fn sub_symbol(&self, _addr: &Addr) -> Option<String> {
+        self.0.clone()
+    }
+
+    fn sub_blocks(&self, _addr: &Addr) -> BTreeSet<Addr> {
+        BTreeSet::new()
+    }
+}
+
+impl<'r> Project<'r> {
+    // the fixed flows leaving the group of Blks starting at group
+    fn group_exits(&self, group: &Addr) -> Vec<(Addr, Flow)> {
+        let end = self.group_end(group);
+        let mut exits = Vec::new();
+
+        for blk in self.blk_groups.get(group).into_iter().flatten().filter_map(|id| self.blks.get(id)) {
+            let mut after_call = false;
+            for jmp in blk.jmps() {
+                match **jmp {
+                    Jmp::Branch(Loc::Fixed(ref target)) | Jmp::CBranch(Loc::Fixed(ref target), _) => {
+                        let flow = if after_call {
+                            Flow::Return
+                        } else if Some(target) == end.as_ref() {
+                            Flow::FallThrough
+                        } else {
+                            Flow::Branch
+                        };
+                        exits.push((target.clone(), flow));
+                    }
+                    Jmp::Call(ref loc, _) => {
+                        if let Loc::Fixed(ref target) = loc {
+                            exits.push((target.clone(), Flow::Call));
+                        }
+                        after_call = true;
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        exits
+    }
+
+    // the number of bytes a return pops from the stack, relative to the
+    // stack pointer at the entry of a Sub
+    fn return_height(&self) -> i64 {
+        let arch = self.lifter.architecture();
+        if arch.processor().eq_ignore_ascii_case("x86") {
+            arch.bits() as i64 / 8
+        } else {
+            0
+        }
+    }
+
+    // the stack height at each group of groups reachable from start, and
+    // at each return reached, relative to the height at start; heights
+    // that cannot be determined are omitted
+    fn stack_heights(
+        &self,
+        groups: &BTreeSet<Addr>,
+        start: &Addr,
+        sp: &Var,
+    ) -> (BTreeMap<Addr, i64>, BTreeMap<Addr, i64>) {
+        let popped = self.return_height();
+        let mut heights = BTreeMap::from([(start.clone(), 0i64)]);
+        let mut returns = BTreeMap::new();
+        let mut worklist = VecDeque::from([start.clone()]);
+
+        while let Some(group) = worklist.pop_front() {
+            let mut env = BTreeMap::from([(sp.name().clone(), heights[&group])]);
+
+            for blk in self.blk_groups.get(&group).into_iter().flatten().filter_map(|id| self.blks.get(id)) {
+                for def in blk.defs() {
+                    if let Def::Assign(ref var, ref expr) = **def {
+                        match stack_offset(expr, &env) {
+                            Some(offset) => env.insert(var.name().clone(), offset),
+                            None => env.remove(var.name()),
+                        };
+                    }
+                }
+
+                let mut height = env.get(sp.name()).copied();
+                for jmp in blk.jmps() {
+                    match **jmp {
+                        Jmp::Branch(Loc::Fixed(ref target)) | Jmp::CBranch(Loc::Fixed(ref target), _) => {
+                            if let Some(height) = height {
+                                if groups.contains(target) && !heights.contains_key(target) {
+                                    heights.insert(target.clone(), height);
+                                    worklist.push_back(target.clone());
+                                }
+                            }
+                        }
+                        // we assume callees restore the stack pointer
+                        Jmp::Call(..) => {
+                            height = height.map(|height| height + popped);
+                        }
+                        Jmp::Return(_) => {
+                            if let Some(height) = height {
+                                returns.insert(group.clone(), height);
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+
+        (heights, returns)
+    }
+
+    // the Sub starts reached from sub that should start Subs of their own
+    fn sub_splits(&self, sub: &Sub, prologues: &BTreeSet<Addr>) -> BTreeMap<Addr, BoundaryReason> {
+        let start = if let Some(start) = sub.addr() {
+            start.clone()
+        } else {
+            return BTreeMap::new()
+        };
+
+        let groups = self.sub_groups(sub);
+        let mut preds = BTreeMap::<Addr, Vec<Flow>>::new();
+        for group in groups.iter() {
+            for (target, flow) in self.group_exits(group) {
+                if flow != Flow::Call && groups.contains(&target) {
+                    preds.entry(target).or_default().push(flow);
+                }
+            }
+        }
+
+        let mut splits = BTreeMap::new();
+        let fall_through = |group: &Addr| {
+            preds.get(group).map(|flows| {
+                flows.iter().all(|flow| matches!(flow, Flow::FallThrough | Flow::Return))
+            }).unwrap_or(false)
+        };
+
+        for group in groups.iter().filter(|group| **group != start) {
+            if self.addr_to_subs.contains_key(group) {
+                splits.insert(group.clone(), BoundaryReason::SubEntry);
+            } else if fall_through(group) && prologues.contains(group) {
+                splits.insert(group.clone(), BoundaryReason::Prologue);
+            }
+        }
+
+        // returns that do not match the frame of the Sub, but match that
+        // of a frame starting after a call that does not return
+        let sp = if let Some(sp) = self.lifter.stack_pointer() {
+            sp
+        } else {
+            return splits
+        };
+
+        let expected = self.return_height();
+        let (_, returns) = self.stack_heights(&groups, &start, &sp);
+        if returns.values().all(|height| *height == expected) {
+            return splits
+        }
+
+        let candidates = groups
+            .iter()
+            .filter(|group| **group != start && !splits.contains_key(*group))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for group in candidates {
+            let after_call = preds
+                .get(&group)
+                .map(|flows| flows.iter().all(|flow| *flow == Flow::Return))
+                .unwrap_or(false);
+            if !after_call {
+                continue
+            }
+
+            let (_, from_group) = self.stack_heights(&groups, &group, &sp);
+            let mismatched = from_group
+                .keys()
+                .any(|ret| returns.get(ret).map(|height| *height != expected).unwrap_or(false));
+
+            if mismatched && !from_group.is_empty() && from_group.values().all(|height| *height == expected) {
+                splits.insert(group, BoundaryReason::ReturnMismatch);
+            }
+        }
+
+        splits
+    }
+
+    // the Subs that should be merged into the (sole) Sub branching to them
+    fn sub_merges(&self, prologues: &BTreeSet<Addr>) -> BTreeMap<Addr, Addr> {
+        let mut called = BTreeSet::new();
+        let mut branched = BTreeMap::<Addr, BTreeSet<Addr>>::new();
+
+        for sub in self.subs.values() {
+            let start = if let Some(start) = sub.addr() {
+                start
+            } else {
+                continue
+            };
+
+            for group in self.sub_groups(sub) {
+                for (target, flow) in self.group_exits(&group) {
+                    if flow == Flow::Call {
+                        called.insert(target);
+                    } else if target != *start && self.addr_to_subs.contains_key(&target) {
+                        branched.entry(target).or_default().insert(start.clone());
+                    }
+                }
+            }
+        }
+
+        branched
+            .into_iter()
+            .filter(|(target, _)| !called.contains(target) && !prologues.contains(target))
+            .filter(|(target, _)| {
+                let confidence = self.addr_to_subs.get(target).and_then(|id| self.confidence(*id));
+                confidence.map(|confidence| confidence < Confidence::Certain).unwrap_or(true)
+            })
+            .filter_map(|(target, from)| {
+                if from.len() == 1 {
+                    // unwrap is safe here: from has one element
+                    Some((target, from.into_iter().next().unwrap()))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    // the starts of the groups of Blks that begin with a prologue
+    fn prologue_starts(&self) -> BTreeSet<Addr> {
+        let is_prologue = HeuristicSubOracle::prologue_matcher(&self.lifter);
+        self.blk_groups
+            .keys()
+            .filter(|addr| {
+                self.memory
+                    .read_bytes_from(addr)
+                    .ok()
+                    .flatten()
+                    .map(|bytes| is_prologue(&bytes))
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect()
+    }
+
+    // removes the Sub starting at addr, returning it
+    fn remove_sub(&mut self, addr: &Addr) -> Option<Entity<Sub>> {
+        let id = self.addr_to_subs.remove(addr)?;
+        self.subs_to_addr.remove(&id);
+        let sub = self.subs.remove(&id)?;
+        if let Some(symbol) = sub.symbol() {
+            if self.syms_to_subs.get(&**symbol) == Some(&id) {
+                self.syms_to_subs.remove(&**symbol);
+            }
+        }
+        Some(sub)
+    }
+
+    /// Detects Subs whose boundaries are likely wrong, without adjusting
+    /// them; see `refine_sub_boundaries`.
+    pub fn detect_sub_boundaries(&self) -> Vec<BoundaryAdjustment> {
+        let prologues = self.prologue_starts();
+        let merges = self.sub_merges(&prologues);
+
+        let mut adjustments = merges
+            .iter()
+            .map(|(merged, into)| BoundaryAdjustment::Merged {
+                into: into.clone(),
+                merged: merged.clone(),
+                reason: BoundaryReason::BranchOnly,
+            })
+            .collect::<Vec<_>>();
+
+        for sub in self.subs.values() {
+            let start = if let Some(start) = sub.addr() {
+                start
+            } else {
+                continue
+            };
+
+            for (at, reason) in self.sub_splits(sub, &prologues) {
+                if reason == BoundaryReason::SubEntry && merges.get(&at) == Some(start) {
+                    continue
+                }
+                adjustments.push(BoundaryAdjustment::Split {
+                    sub: start.clone(),
+                    at,
+                    reason,
+                });
+            }
+        }
+
+        adjustments
+    }
+
+    /// Refines the boundaries of the Project's Subs, where recursive
+    /// descent has likely merged two functions, or split one:
+    ///
+    /// - a Sub that is never called, does not begin with a prologue, is
+    ///   not certain (see `Confidence`), and is only branched to by one
+    ///   other Sub is merged into that Sub (e.g., a cold block mistaken for
+    ///   a function);
+    ///
+    /// - a Sub is split where it reaches the start of another Sub, where it
+    ///   falls through into a prologue (e.g., following a call that does
+    ///   not return), and where, following a call, it reaches returns
+    ///   whose stack heights do not match its frame, but match a frame
+    ///   starting after the call.
+    ///
+    /// Split Subs are re-lifted without exploring beyond the starts of
+    /// other Subs, and the code split from them is lifted as new Subs,
+    /// whose starts are heuristic. Returns a report of the adjustments
+    /// made.
+    pub fn refine_sub_boundaries(&mut self) -> BoundaryReport {
+        let adjustments = self.detect_sub_boundaries();
+
+        let mut splits = BTreeMap::<Addr, BTreeSet<Addr>>::new();
+        for adjustment in adjustments.iter() {
+            match adjustment {
+                BoundaryAdjustment::Merged { merged, .. } => {
+                    self.remove_sub(merged);
+                }
+                BoundaryAdjustment::Split { sub, at, .. } => {
+                    splits.entry(sub.clone()).or_default().insert(at.clone());
+                }
+            }
+        }
+
+        let mut stops = self.addr_to_subs.keys().cloned().collect::<BTreeSet<_>>();
+        stops.extend(splits.values().flatten().cloned());
+
+        for (start, ats) in splits {
+            let old = if let Some(old) = self.remove_sub(&start) {
+                old
+            } else {
+                continue
+            };
+
+            let confidence = self.confidence(&old).unwrap_or(Confidence::Likely);
+            let symbol = Preserved(old.symbol().map(|symbol| symbol.to_string()));
+            if let Some((id, _)) = self.lift_sub(&symbol, &start, confidence, &stops) {
+                self.annotations.insert(id, confidence);
+            }
+
+            for at in ats {
+                if !self.addr_to_subs.contains_key(&at) {
+                    self.lift_sub(&Preserved(None), &at, Confidence::Heuristic, &stops);
+                }
+            }
+        }
+
+        BoundaryReport { adjustments }
+    }
+}
+
+// the offset of expr from the stack pointer, given the offsets of the
+// variables in env
+fn stack_offset(expr: &Expr, env: &BTreeMap<Arc<str>, i64>) -> Option<i64> {
+    match expr {
+        Expr::Var(var) => env.get(var.name()).copied(),
+        Expr::BinOp(BinOp::Add, lexpr, rexpr) => match (&**lexpr, &**rexpr) {
+            (expr, Expr::Val(value)) | (Expr::Val(value), expr) => {
+                Some(stack_offset(expr, env)?.wrapping_add(signed(value)?))
+            }
+            _ => None,
+        },
+        Expr::BinOp(BinOp::Sub, lexpr, rexpr) => match &**rexpr {
+            Expr::Val(value) => Some(stack_offset(lexpr, env)?.wrapping_sub(signed(value)?)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// the value of a constant interpreted as signed
+fn signed(value: &crate::ir::BitVec) -> Option<i64> {
+    let bits = value.bits();
+    let unsigned = value.to_u64()?;
+    Some(if bits >= 64 || bits == 0 {
+        unsigned as i64
+    } else {
+        let shift = 64 - bits as u32;
+        ((unsigned << shift) as i64) >> shift
+    })
+}
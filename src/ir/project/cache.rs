@@ -0,0 +1,188 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::analysis::{AnalysisCache, CacheKey, ContentHash, JumpTable};
+use crate::ir::{Addr, Blk, Project, Sub};
+use crate::prelude::Id;
+
+// bumped when the encoding of cached results changes
+const CACHE_VERSION: u64 = 2;
+
+// a jump table recovered for the computed branch at site
+#[derive(Debug, Clone)]
+pub(super) struct CachedTable {
+    pub(super) site: Addr,
+    table: Addr,
+    entry_bits: u32,
+    table_hash: u64,
+    pub(super) targets: BTreeSet<Addr>,
+    entries: usize,
+}
+
+impl<'r> Project<'r> {
+    /// Reuses the results of per-Sub analyses (currently, the jump tables
+    /// recovered by `resolve_jump_tables`) stored in `cache`, and stores
+    /// the results of those analyses performed subsequently; see
+    /// `AnalysisCache`.
+    pub fn set_analysis_cache(&mut self, cache: AnalysisCache) {
+        self.analysis_cache = Some(cache);
+    }
+
+    pub fn clear_analysis_cache(&mut self) {
+        self.analysis_cache = None;
+    }
+
+    pub fn analysis_cache(&self) -> Option<&AnalysisCache> {
+        self.analysis_cache.as_ref()
+    }
+
+    // the bytes of the instructions of the group of Blks starting at addr
+    fn group_bytes(&self, addr: &Addr) -> Option<Vec<u8>> {
+        let size = self.group_end(addr)?.absolute_difference(addr)?;
+        let bytes = self.memory.read_bytes(addr, size).ok()??;
+        Some(bytes.into_owned())
+    }
+
+    /// A hash of the bytes of the instructions lifted for `sub`, and their
+    /// offsets from its start; Subs with the same hash have the same code,
+    /// irrespective of where they are loaded. Returns None if `sub` has no
+    /// address, or the bytes of its Blks are not mapped.
+    pub fn sub_content_hash(&self, sub: &Sub) -> Option<u64> {
+        let start = u64::try_from(sub.addr()?).ok()?;
+        let mut hash = ContentHash::new();
+
+        for group in self.sub_groups(sub) {
+            let offset = u64::try_from(&group).ok()?.wrapping_sub(start);
+            let bytes = self.group_bytes(&group)?;
+            hash.write_u64(offset)
+                .write_u64(bytes.len() as u64)
+                .write(&bytes);
+        }
+
+        Some(hash.finish())
+    }
+
+    // a hash of the language (and the version of its specification) and
+    // lifter settings the Project's Blks were lifted with, and the name and
+    // parameters of an analysis
+    fn analysis_config_hash(&self, analysis: &str, parameters: &str) -> u64 {
+        let arch = self.lifter.architecture();
+        let mut hash = ContentHash::new();
+
+        hash.write_u64(CACHE_VERSION)
+            .write_str(arch.processor())
+            .write_str(arch.variant())
+            .write_u64(arch.bits() as u64)
+            .write_u64(arch.endian().is_little() as u64)
+            .write_str(&self.lifter.spec_version().to_string())
+            .write_str(&format!("{:?}", self.lifter.predicate_normalisation()))
+            .write_str(&format!("{:?}", self.lifter.repeat_normalisation()))
+            .write_str(&format!("{:?}", self.lifter.pointer_modelling()))
+            .write_str(analysis)
+            .write_str(parameters);
+
+        hash.finish()
+    }
+
+    pub(super) fn analysis_key(&self, sub: &Sub, analysis: &str, parameters: &str) -> Option<CacheKey> {
+        self.analysis_cache.as_ref()?;
+        Some(CacheKey {
+            content: self.sub_content_hash(sub)?,
+            config: self.analysis_config_hash(analysis, parameters),
+        })
+    }
+
+    // the address of the instruction each Blk of sub was lifted from
+    pub(super) fn insn_addrs(&self, sub: &Sub) -> BTreeMap<Id<Blk>, Addr> {
+        let mut addrs = BTreeMap::new();
+
+        for group in self.sub_groups(sub) {
+            let mut current = group.clone();
+            for id in self.blk_groups.get(&group).into_iter().flatten() {
+                if let Some(addr) = self.blks.get(id).and_then(|blk| blk.addr()) {
+                    current = addr.clone();
+                }
+                addrs.insert(*id, current.clone());
+            }
+        }
+
+        addrs
+    }
+
+    fn table_hash(&self, table: &Addr, entry_bits: u32, entries: usize) -> Option<u64> {
+        let size = entries * (entry_bits as usize / 8);
+        let bytes = self.memory.read_bytes(table, size).ok()??;
+        Some(ContentHash::new().write(&bytes).finish())
+    }
+
+    // encodes tables, relative to the start of sub, as lines of the form:
+    //
+    //   site table entry_bits table_hash entries target...
+    //
+    // where addresses are hexadecimal offsets from sub's start (modulo
+    // 2^64), and table_hash is the hash of the table's entries, which we
+    // use to check the table is unchanged when reusing the result
+    pub(super) fn encode_jump_tables(&self, sub: &Sub, tables: &[JumpTable]) -> Option<String> {
+        let start = u64::try_from(sub.addr()?).ok()?;
+        let offset = |addr: &Addr| u64::try_from(addr).ok().map(|addr| addr.wrapping_sub(start));
+        let insn_addrs = self.insn_addrs(sub);
+
+        let mut text = String::new();
+        for table in tables {
+            let site = offset(insn_addrs.get(&table.blk())?)?;
+            let entries = table.targets().len();
+            let table_hash = self.table_hash(table.table(), table.entry_bits(), entries)?;
+
+            text.push_str(&format!(
+                "{:x} {:x} {} {:x} {}",
+                site,
+                offset(table.table())?,
+                table.entry_bits(),
+                table_hash,
+                entries,
+            ));
+            for target in table.targets().iter().collect::<BTreeSet<_>>() {
+                text.push_str(&format!(" {:x}", offset(target)?));
+            }
+            text.push('\n');
+        }
+
+        Some(text)
+    }
+
+    // decodes tables encoded by encode_jump_tables; returns None if any
+    // table's entries differ from those it was recovered from
+    pub(super) fn decode_jump_tables(&self, sub: &Sub, text: &str) -> Option<Vec<CachedTable>> {
+        let start = u64::try_from(sub.addr()?).ok()?;
+        let bits = sub.addr()?.bits();
+        let addr = |offset: &str| {
+            u64::from_str_radix(offset, 16)
+                .ok()
+                .map(|offset| Addr::from(start.wrapping_add(offset)).as_bits(bits))
+        };
+
+        let mut tables = Vec::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() < 5 {
+                return None
+            }
+
+            let table = CachedTable {
+                site: addr(fields[0])?,
+                table: addr(fields[1])?,
+                entry_bits: fields[2].parse().ok()?,
+                table_hash: u64::from_str_radix(fields[3], 16).ok()?,
+                entries: fields[4].parse().ok()?,
+                targets: fields[5..].iter().map(|target| addr(target)).collect::<Option<_>>()?,
+            };
+
+            if self.table_hash(&table.table, table.entry_bits, table.entries) != Some(table.table_hash) {
+                return None
+            }
+
+            tables.push(table);
+        }
+
+        Some(tables)
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::ir::cfg::escape;
+use crate::ir::{Jmp, Loc, Project, Sub};
+use crate::prelude::{Id, Identifiable};
+
+// the kind of an edge of the call graph
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum CallKind {
+    Call,
+    TailCall,
+    Callback,
+}
+
+// a node of the call graph: a Sub, or an external function
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Callee {
+    Sub(Id<Sub>),
+    External(Arc<str>),
+}
+
+impl<'r> Project<'r> {
+    // the callees of the jmps of sub; branches to the starts of other Subs
+    // are tail-calls
+    fn callees(&self, sub: &Sub) -> BTreeSet<(Callee, CallKind)> {
+        let mut callees = BTreeSet::new();
+
+        for jmp in sub.blks().iter().flat_map(|blk| blk.jmps()) {
+            let (loc, kind) = match **jmp {
+                Jmp::Call(ref loc, _) => (loc, CallKind::Call),
+                Jmp::Branch(ref loc) => (loc, CallKind::TailCall),
+                _ => continue,
+            };
+
+            if let Some(function) = self.imports.function_at(loc) {
+                callees.insert((Callee::External(function.name().clone()), kind));
+            } else if let Loc::Fixed(ref target) = loc {
+                if let Some(callee) = self.addr_to_subs.get(target) {
+                    if kind == CallKind::Call || Some(target) != sub.addr() {
+                        callees.insert((Callee::Sub(*callee), kind));
+                    }
+                }
+            }
+        }
+
+        callees
+    }
+
+    /// Renders the call graph of the Project's Subs in DOT: Subs are
+    /// labelled by their symbols (or `sub_<addr>`), and the external
+    /// functions called via imports (see `Imports`) are drawn as ellipses.
+    /// Direct calls are solid edges, tail-calls (branches to the starts of
+    /// other Subs) are dashed, and the callbacks registered by Subs (see
+    /// `find_callbacks`) are dotted. Computed calls are not resolved.
+    pub fn call_graph_dot(&self) -> String {
+        let mut nodes = BTreeMap::new();
+        let mut edges = BTreeSet::new();
+
+        for sub in self.subs.values() {
+            let index = nodes.len();
+            nodes.entry(Callee::Sub(sub.id())).or_insert(index);
+        }
+
+        for sub in self.subs.values() {
+            let from = Callee::Sub(sub.id());
+            for (callee, kind) in self.callees(sub) {
+                let index = nodes.len();
+                nodes.entry(callee.clone()).or_insert(index);
+                edges.insert((from.clone(), callee, kind));
+            }
+
+            for cref in self.callback_refs(sub.id()) {
+                if let Some(caller) = cref.caller() {
+                    edges.insert((Callee::Sub(caller), Callee::Sub(sub.id()), CallKind::Callback));
+                }
+            }
+        }
+
+        let mut dot = String::new();
+
+        // writes to strings are infallible
+        let _ = writeln!(dot, "digraph calls {{");
+        let _ = writeln!(dot, "  node [shape=box, fontname=\"monospace\"];");
+
+        let mut ordered = nodes.iter().collect::<Vec<_>>();
+        ordered.sort_by_key(|(_, index)| **index);
+
+        for (callee, index) in ordered {
+            match callee {
+                Callee::Sub(id) => {
+                    let sub = &self.subs[id];
+                    let label = match (sub.symbol(), sub.addr()) {
+                        (Some(symbol), _) => symbol.to_string(),
+                        (None, Some(addr)) => format!("sub_{}", addr),
+                        (None, None) => format!("sub_{}", id),
+                    };
+                    let _ = writeln!(dot, "  n{} [label=\"{}\"];", index, escape(&label));
+                }
+                Callee::External(name) => {
+                    let _ = writeln!(dot, "  n{} [label=\"{}\", shape=ellipse];", index, escape(name));
+                }
+            }
+        }
+
+        for (from, to, kind) in edges {
+            let style = match kind {
+                CallKind::Call => "solid",
+                CallKind::TailCall => "dashed",
+                CallKind::Callback => "dotted",
+            };
+            let _ = writeln!(dot, "  n{} -> n{} [style={}];", nodes[&from], nodes[&to], style);
+        }
+
+        let _ = writeln!(dot, "}}");
+        dot
+    }
+}
@@ -0,0 +1,95 @@
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use crate::analysis::PrototypeInference;
+use crate::ir::{Jmp, Loc, Project, Sub};
+use crate::lift::Lifter;
+use crate::passes::ClobberPass;
+use crate::prelude::Identifiable;
+
+impl<'r> Project<'r> {
+    /// Overrides the calling convention of `sub` (e.g., for a `stdcall`
+    /// Sub of a binary otherwise following `cdecl`) with the convention
+    /// `name` of the Project's language; returns false, leaving it
+    /// unchanged, if the language has no such convention.
+    ///
+    /// Overrides are honoured by `infer_prototypes` and `clobber_pass`,
+    /// and by analyses given the Lifter of `lifter_for`.
+    pub fn set_sub_convention(&mut self, sub: impl Identifiable<Sub>, name: impl AsRef<str>) -> bool {
+        let name = name.as_ref();
+        if self.lifter.conventions().contains(&name) {
+            self.sub_conventions.insert(sub.id(), Arc::from(name));
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn clear_sub_convention(&mut self, sub: impl Identifiable<Sub>) -> Option<Arc<str>> {
+        self.sub_conventions.remove(&sub.id())
+    }
+
+    /// The name of the convention overriding the Project's for `sub`, if
+    /// any.
+    pub fn sub_convention(&self, sub: impl Identifiable<Sub>) -> Option<&Arc<str>> {
+        self.sub_conventions.get(&sub.id())
+    }
+
+    /// The Project's Lifter, using the calling convention of `sub`.
+    pub fn lifter_for(&self, sub: impl Identifiable<Sub>) -> Cow<'_, Lifter> {
+        self.lifter_with(self.sub_convention(sub))
+    }
+
+    fn lifter_with(&self, convention: Option<&Arc<str>>) -> Cow<'_, Lifter> {
+        convention
+            .and_then(|name| self.lifter.with_convention(&**name))
+            .map(Cow::Owned)
+            .unwrap_or(Cow::Borrowed(&self.lifter))
+    }
+
+    // the conventions of the Subs of the Project, None being the Project's
+    pub(super) fn conventions_of_subs(&self) -> BTreeSet<Option<Arc<str>>> {
+        self.subs
+            .keys()
+            .map(|id| self.sub_conventions.get(id).cloned())
+            .collect()
+    }
+
+    // the inference of the prototypes of the Subs following convention
+    pub(super) fn prototype_inference(&self, convention: Option<&Arc<str>>) -> Option<PrototypeInference> {
+        PrototypeInference::from_lifter(&self.lifter_with(convention))
+    }
+
+    /// A `ClobberPass` for the calls of `sub`: a register is treated as
+    /// clobbered only if the conventions of all of the Subs called by
+    /// `sub` clobber it; calls to other targets (e.g., computed or
+    /// external calls) are assumed to follow the Project's convention.
+    pub fn clobber_pass(&self, sub: &Sub) -> ClobberPass {
+        let conventions = sub
+            .blks()
+            .iter()
+            .flat_map(|blk| blk.jmps())
+            .filter_map(|jmp| match **jmp {
+                Jmp::Call(Loc::Fixed(ref target), _) => Some(
+                    self.addr_to_subs
+                        .get(target)
+                        .and_then(|callee| self.sub_conventions.get(callee))
+                        .cloned(),
+                ),
+                Jmp::Call(_, _) => Some(None),
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>();
+
+        let mut conventions = conventions.into_iter();
+        let mut pass = match conventions.next() {
+            Some(convention) => ClobberPass::from_lifter(&self.lifter_with(convention.as_ref())),
+            None => return ClobberPass::from_lifter(&self.lifter),
+        };
+        for convention in conventions {
+            pass.intersect(&ClobberPass::from_lifter(&self.lifter_with(convention.as_ref())));
+        }
+        pass
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::ir::{Addr, Blk, Def, Jmp, Project};
+use crate::prelude::intervals::Interval;
+use crate::prelude::{Erased, Id, Identifiable};
+
+/// The IR derived from one or more bytes of a Project's memory (see
+/// `Project::ir_for_byte`): the instructions covering them, and the Blks
+/// and statements lifted from those instructions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DerivedIr {
+    insns: BTreeSet<Addr>,
+    blks: BTreeSet<Id<Blk>>,
+    defs: BTreeSet<Id<Def>>,
+    jmps: BTreeSet<Id<Jmp>>,
+}
+
+impl DerivedIr {
+    /// The addresses of the instructions covering the bytes; there may be
+    /// more than one per byte if instructions overlap.
+    pub fn insns(&self) -> &BTreeSet<Addr> {
+        &self.insns
+    }
+
+    pub fn blks(&self) -> &BTreeSet<Id<Blk>> {
+        &self.blks
+    }
+
+    pub fn defs(&self) -> &BTreeSet<Id<Def>> {
+        &self.defs
+    }
+
+    pub fn jmps(&self) -> &BTreeSet<Id<Jmp>> {
+        &self.jmps
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.insns.is_empty()
+    }
+
+    pub fn extend(&mut self, other: DerivedIr) {
+        self.insns.extend(other.insns);
+        self.blks.extend(other.blks);
+        self.defs.extend(other.defs);
+        self.jmps.extend(other.jmps);
+    }
+}
+
+/// The machine code generating some IR (see `Project::source_bytes`): the
+/// instructions each statement was lifted from, as the fewest disjoint
+/// byte ranges covering them, e.g., to target a patch or extract a
+/// signature from exactly the encodings of the statements.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceBytes {
+    // the length of each instruction, keyed by its address
+    insns: BTreeMap<Addr, usize>,
+    unmapped: BTreeSet<Id<Erased>>,
+}
+
+impl SourceBytes {
+    /// The addresses and lengths of the instructions generating the IR.
+    pub fn insns(&self) -> impl Iterator<Item = (&Addr, usize)> {
+        self.insns.iter().map(|(addr, len)| (addr, *len))
+    }
+
+    /// The statements without a source instruction, e.g., those created by
+    /// passes; the bytes of those they were derived from can be found via
+    /// their provenance (see `Project::provenance`).
+    pub fn unmapped(&self) -> &BTreeSet<Id<Erased>> {
+        &self.unmapped
+    }
+
+    /// The byte ranges covering the instructions, as their start addresses
+    /// and lengths, in order; instructions that are adjacent or overlap
+    /// share a range.
+    pub fn ranges(&self) -> Vec<(Addr, usize)> {
+        let mut ranges: Vec<(Addr, Addr)> = Vec::new();
+
+        for (addr, len) in self.insns.iter() {
+            let end = addr + *len;
+            match ranges.last_mut() {
+                Some((_, last)) if addr <= last => {
+                    if end > *last {
+                        *last = end;
+                    }
+                }
+                _ => ranges.push((addr.clone(), end)),
+            }
+        }
+
+        ranges
+            .into_iter()
+            .map(|(start, end)| {
+                // unwrap is safe here: ranges end after they start, and
+                // span the bytes of a finite number of instructions
+                let len = u64::try_from(&end - &start).unwrap();
+                (start, len as usize)
+            })
+            .collect()
+    }
+
+    /// The number of bytes covered.
+    pub fn len(&self) -> usize {
+        self.ranges().iter().map(|(_, len)| len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.insns.is_empty()
+    }
+
+    pub fn extend(&mut self, other: SourceBytes) {
+        for (addr, len) in other.insns {
+            let current = self.insns.entry(addr).or_default();
+            *current = (*current).max(len);
+        }
+        self.unmapped.extend(other.unmapped);
+    }
+}
+
+impl<'r> Project<'r> {
+    /// The bytes of the instructions that `stmts` (e.g., Defs or Jmps
+    /// selected by a query) were lifted from; the inverse of
+    /// `ir_for_bytes`. Results for statements of different kinds can be
+    /// combined with `SourceBytes::extend`.
+    pub fn source_bytes<V, E>(&self, stmts: impl IntoIterator<Item = E>) -> SourceBytes
+    where
+        E: Identifiable<V>,
+    {
+        let mut bytes = SourceBytes::default();
+        for stmt in stmts {
+            let id = stmt.id();
+            match self.source_loc(id) {
+                Some(source) => {
+                    let len = bytes.insns.entry(source.addr().clone()).or_default();
+                    *len = (*len).max(source.len());
+                }
+                None => {
+                    bytes.unmapped.insert(id.erase());
+                }
+            }
+        }
+        bytes
+    }
+
+    /// The IR derived from the byte at `addr`, i.e., the Blks, Defs, and
+    /// Jmps lifted (by `add_blk`) from each instruction covering it, e.g.,
+    /// to map a program counter observed at runtime to the IR executed.
+    ///
+    /// Blks are found via the extents of the groups of Blks lifted, and
+    /// the location of the instruction each was lifted from (see
+    /// `SourceLoc`), so queries are logarithmic in the number of groups.
+    /// The ids reported are those of the Project's Blks; the copies of them
+    /// held by Subs share their ids (and those of their statements), unless
+    /// they were created by a pass.
+    pub fn ir_for_byte(&self, addr: &Addr) -> DerivedIr {
+        let mut derived = DerivedIr::default();
+
+        let groups = self.blk_extents.find_all(&Interval::from(addr.clone()..addr + 1usize));
+        for group in groups.iter() {
+            let blks = self
+                .blk_groups
+                .get(group.value())
+                .into_iter()
+                .flatten()
+                .filter_map(|id| self.blks.get(id));
+
+            for blk in blks {
+                let source = match blk.source() {
+                    Some(source) if source.contains(addr) => source,
+                    _ => continue,
+                };
+
+                derived.insns.insert(source.addr().clone());
+                derived.blks.insert(blk.id());
+                derived.defs.extend(blk.defs().iter().map(|def| def.id()));
+                derived.jmps.extend(blk.jmps().iter().map(|jmp| jmp.id()));
+            }
+        }
+
+        derived
+    }
+
+    /// The IR derived from each of the bytes at `addrs` (see
+    /// `ir_for_byte`), e.g., those marked by a coverage bitmap.
+    pub fn ir_for_bytes<A>(&self, addrs: impl IntoIterator<Item = A>) -> DerivedIr
+    where
+        A: std::borrow::Borrow<Addr>,
+    {
+        let mut derived = DerivedIr::default();
+        for addr in addrs {
+            derived.extend(self.ir_for_byte(addr.borrow()));
+        }
+        derived
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::BTreeSet;
+
+use crate::ir::{Addr, BinRel, Blk, Expr, Jmp, Loc, Project};
+use crate::ir::project::ProjectError;
+use crate::prelude::{Entity, Id, Identifiable};
+
+/// The kind of a flow with a computed target.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FlowKind {
+    Branch,
+    CBranch,
+    Call,
+}
+
+/// A flow with a computed target (see `Loc::Computed`), encountered while
+/// lifting the Blks of a Project, e.g., an indirect jump or call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComputedFlow {
+    addr: Addr,
+    blk: Id<Blk>,
+    jmp: Id<Jmp>,
+    kind: FlowKind,
+    target: Expr,
+    resolved: BTreeSet<Addr>,
+}
+
+impl ComputedFlow {
+    /// The address of the instruction the flow was lifted from.
+    pub fn addr(&self) -> &Addr {
+        &self.addr
+    }
+
+    /// The Blk holding the flow.
+    pub fn blk(&self) -> Id<Blk> {
+        self.blk
+    }
+
+    pub fn jmp(&self) -> Id<Jmp> {
+        self.jmp
+    }
+
+    pub fn kind(&self) -> FlowKind {
+        self.kind
+    }
+
+    /// The expression computing the target of the flow.
+    pub fn target(&self) -> &Expr {
+        &self.target
+    }
+
+    /// The targets the flow is known to reach, i.e., those materialised as
+    /// conditional branches preceding it (e.g., by `import_trace`, or
+    /// `resolve_flow`), or as branches following it (e.g., by
+    /// `resolve_jump_tables`).
+    pub fn resolved_targets(&self) -> &BTreeSet<Addr> {
+        &self.resolved
+    }
+
+    pub fn is_resolved(&self) -> bool {
+        !self.resolved.is_empty()
+    }
+}
+
+impl<'r> Project<'r> {
+    // records the flows with computed targets of blk, lifted from the
+    // instruction at addr
+    pub(super) fn track_computed_flows(&mut self, blk: &Entity<Blk>, addr: &Addr) {
+        for jmp in blk.jmps() {
+            if Self::computed_flow(jmp).is_some() {
+                self.computed_flows.insert(jmp.id(), (addr.clone(), blk.id()));
+            }
+        }
+    }
+
+    fn computed_flow(jmp: &Jmp) -> Option<(FlowKind, &Expr)> {
+        match jmp {
+            Jmp::Branch(Loc::Computed(target)) => Some((FlowKind::Branch, target)),
+            Jmp::CBranch(Loc::Computed(target), _) => Some((FlowKind::CBranch, target)),
+            Jmp::Call(Loc::Computed(target), _) => Some((FlowKind::Call, target)),
+            _ => None,
+        }
+    }
+
+    /// Every flow with a computed target of the Blks lifted by the Project
+    /// that remains in the Project, with the targets resolved for it so
+    /// far; returns are not included.
+    pub fn computed_flows(&self) -> Vec<ComputedFlow> {
+        self.computed_flows
+            .iter()
+            .filter_map(|(id, (addr, blk))| {
+                let blk_ref = self.blks.get(blk)?;
+                let position = blk_ref.jmps().iter().position(|jmp| jmp.id() == *id)?;
+                let (kind, target) = Self::computed_flow(&blk_ref.jmps()[position])?;
+
+                // the targets materialised as branches guarded by target == addr
+                let mut resolved = blk_ref.jmps()[..position]
+                    .iter()
+                    .filter_map(|jmp| match **jmp {
+                        Jmp::CBranch(Loc::Fixed(ref to), Expr::BinRel(BinRel::Eq, ref lexpr, _))
+                            if **lexpr == *target =>
+                        {
+                            Some(to.clone())
+                        }
+                        _ => None,
+                    })
+                    .collect::<BTreeSet<_>>();
+
+                // and those materialised as branches following a computed branch
+                if kind == FlowKind::Branch {
+                    resolved.extend(blk_ref.jmps()[position + 1..].iter().map_while(|jmp| match **jmp {
+                        Jmp::Branch(Loc::Fixed(ref to)) => Some(to.clone()),
+                        _ => None,
+                    }));
+                }
+
+                Some(ComputedFlow {
+                    addr: addr.clone(),
+                    blk: *blk,
+                    jmp: *id,
+                    kind,
+                    target: target.clone(),
+                    resolved,
+                })
+            })
+            .collect()
+    }
+
+    /// The flows with computed targets for which no targets have been
+    /// resolved (see `computed_flows`), e.g., to drive resolution until
+    /// none remain.
+    pub fn unresolved_flows(&self) -> Vec<ComputedFlow> {
+        let mut flows = self.computed_flows();
+        flows.retain(|flow| !flow.is_resolved());
+        flows
+    }
+
+    /// Resolves the computed branch `jmp` to `targets` (e.g., as found by
+    /// an external tool), preceding it by a conditional branch to each, in
+    /// the Project's Blk holding it and the copies held by its Subs; the
+    /// targets that do not start a known Blk are lifted. Returns the ids of
+    /// the Blks lifted.
+    pub fn resolve_flow(
+        &mut self,
+        jmp: impl Identifiable<Jmp>,
+        targets: impl IntoIterator<Item = Addr>,
+    ) -> Result<Vec<Id<Blk>>, ProjectError> {
+        let jmp = jmp.id();
+        let targets = targets.into_iter().collect::<BTreeSet<_>>();
+
+        let blk = match self.computed_flows.get(&jmp) {
+            Some((_, blk)) => *blk,
+            None => return Ok(Vec::new()),
+        };
+
+        if let Some(blk) = self.blks.get_mut(&blk) {
+            Self::materialise_targets(blk, jmp, &targets);
+        }
+        for sub in self.subs.values_mut() {
+            if let Some(blk) = sub.blk_mut(blk) {
+                Self::materialise_targets(blk, jmp, &targets);
+            }
+        }
+
+        let mut lifted = Vec::new();
+        for target in targets {
+            if !self.blk_groups.contains_key(&target) {
+                lifted.extend(self.add_blk(target)?);
+            }
+        }
+
+        Ok(lifted)
+    }
+}
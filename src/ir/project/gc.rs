@@ -0,0 +1,136 @@
+use std::collections::BTreeSet;
+
+use crate::ir::{Blk, Jmp, Loc, Project};
+use crate::prelude::{Entity, Erased, Id, Identifiable};
+
+/// The entities found to be unreachable by `Project::gc`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GcReport {
+    blks: BTreeSet<Id<Blk>>,
+    statements: BTreeSet<Id<Erased>>,
+}
+
+impl GcReport {
+    /// The Blks not referenced by any group of lifted Blks, any Sub, or
+    /// any resolved location of a reachable Blk.
+    pub fn blks(&self) -> &BTreeSet<Id<Blk>> {
+        &self.blks
+    }
+
+    /// The phis, Defs, and Jmps of the unreachable Blks that are not also
+    /// held by a reachable Blk.
+    pub fn statements(&self) -> &BTreeSet<Id<Erased>> {
+        &self.statements
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blks.is_empty()
+    }
+}
+
+impl<'r> Project<'r> {
+    /// Finds the Blks of the Project that are no longer reachable, e.g.,
+    /// those left behind when a Sub's Blks are split or merged, without
+    /// removing them; see `gc`.
+    ///
+    /// The roots are the groups of Blks lifted by `add_blk` and the Blks of
+    /// the Project's Subs; Blks are reachable if they are roots, or are
+    /// the targets of resolved locations (see `Loc::Resolved`) of reachable
+    /// Blks.
+    pub fn gc_report(&self) -> GcReport {
+        let sub_blks = || self.subs.values().flat_map(|sub| sub.blks().iter());
+
+        let mut marked = self.blk_groups
+            .values()
+            .flatten()
+            .copied()
+            .chain(sub_blks().map(|blk| blk.id()))
+            .collect::<BTreeSet<_>>();
+
+        let mut pending = marked
+            .iter()
+            .filter_map(|id| self.blks.get(id))
+            .chain(sub_blks())
+            .collect::<Vec<_>>();
+
+        while let Some(blk) = pending.pop() {
+            for target in Self::resolved_targets(blk) {
+                if marked.insert(target) {
+                    pending.extend(self.blks.get(&target));
+                }
+            }
+        }
+
+        let blks = self.blks
+            .keys()
+            .filter(|id| !marked.contains(id))
+            .copied()
+            .collect::<BTreeSet<_>>();
+
+        let live = marked
+            .iter()
+            .filter_map(|id| self.blks.get(id))
+            .chain(sub_blks())
+            .flat_map(Self::statements)
+            .collect::<BTreeSet<_>>();
+
+        let statements = blks
+            .iter()
+            .filter_map(|id| self.blks.get(id))
+            .flat_map(Self::statements)
+            .filter(|id| !live.contains(id))
+            .collect();
+
+        GcReport { blks, statements }
+    }
+
+    /// Removes the Blks of the Project that are no longer reachable (see
+    /// `gc_report`), and their annotations and those of their statements.
+    /// Collection is never performed implicitly, as the ids of the Blks
+    /// removed may still be held by users of the Project.
+    pub fn gc(&mut self) -> GcReport {
+        let report = self.gc_report();
+
+        for id in report.blks.iter() {
+            self.blks.remove(id);
+            self.annotations.remove_all(*id);
+            if let Some(addr) = self.blks_to_addr.remove(id) {
+                if self.addr_to_blks.get(&addr) == Some(id) {
+                    self.addr_to_blks.remove(&addr);
+                }
+            }
+        }
+
+        for id in report.statements.iter() {
+            self.annotations.remove_all(*id);
+        }
+
+        if !report.is_empty() {
+            log::debug!(
+                "collected {} unreachable blocks and {} statements",
+                report.blks.len(),
+                report.statements.len(),
+            );
+        }
+
+        report
+    }
+
+    fn resolved_targets(blk: &Blk) -> impl Iterator<Item = Id<Blk>> + '_ {
+        blk.jmps().iter().filter_map(|jmp| match **jmp {
+            Jmp::Branch(Loc::Resolved(target))
+            | Jmp::CBranch(Loc::Resolved(target), _)
+            | Jmp::Call(Loc::Resolved(target), _)
+            | Jmp::Return(Loc::Resolved(target)) => Some(target),
+            _ => None,
+        })
+    }
+
+    fn statements(blk: &Entity<Blk>) -> impl Iterator<Item = Id<Erased>> + '_ {
+        blk.phis()
+            .iter()
+            .map(|phi| phi.id().erase())
+            .chain(blk.defs().iter().map(|def| def.id().erase()))
+            .chain(blk.jmps().iter().map(|jmp| jmp.id().erase()))
+    }
+}
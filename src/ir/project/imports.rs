@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::ir::{Addr, Expr, Jmp, Loc, Project, SideEffects, Sub};
+use crate::prelude::Identifiable;
+
+/// A function imported from another module (e.g., `malloc` from libc),
+/// with a summary of its effects: whether it is pure, the arguments it
+/// may write through, whether it returns, etc.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalFunction {
+    name: Arc<str>,
+    effects: SideEffects,
+}
+
+impl ExternalFunction {
+    pub fn new(name: impl Into<Arc<str>>, effects: SideEffects) -> Self {
+        Self {
+            name: name.into(),
+            effects,
+        }
+    }
+
+    pub fn name(&self) -> &Arc<str> {
+        &self.name
+    }
+
+    pub fn effects(&self) -> &SideEffects {
+        &self.effects
+    }
+
+    pub fn is_no_return(&self) -> bool {
+        self.effects.is_no_return()
+    }
+}
+
+/// The import thunks of a Project (e.g., the entries of an ELF PLT), and
+/// the slots of its import tables (e.g., those of a PE IAT, or an ELF
+/// GOT), bound to the external functions they transfer control to.
+#[derive(Debug, Clone, Default)]
+pub struct Imports {
+    // the size and function of each thunk, keyed by its address
+    thunks: BTreeMap<Addr, (usize, Arc<ExternalFunction>)>,
+    slots: BTreeMap<Addr, Arc<ExternalFunction>>,
+}
+
+impl Imports {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks the `size` bytes at `addr` as a thunk transferring control to
+    /// `function`.
+    pub fn add_thunk(&mut self, addr: impl Into<Addr>, size: usize, function: ExternalFunction) -> &mut Self {
+        self.thunks.insert(addr.into(), (size, Arc::new(function)));
+        self
+    }
+
+    /// Binds the slot (i.e., pointer) at `addr` of an import table to
+    /// `function`.
+    pub fn add_slot(&mut self, addr: impl Into<Addr>, function: ExternalFunction) -> &mut Self {
+        self.slots.insert(addr.into(), Arc::new(function));
+        self
+    }
+
+    /// The function of the thunk `addr` falls within.
+    pub fn thunk(&self, addr: &Addr) -> Option<&Arc<ExternalFunction>> {
+        let (start, (size, function)) = self.thunks.range(..=addr.clone()).next_back()?;
+        if addr == start || *addr < start + *size {
+            Some(function)
+        } else {
+            None
+        }
+    }
+
+    pub fn slot(&self, addr: &Addr) -> Option<&Arc<ExternalFunction>> {
+        self.slots.get(addr)
+    }
+
+    pub fn thunks(&self) -> impl Iterator<Item = (&Addr, usize, &Arc<ExternalFunction>)> {
+        self.thunks.iter().map(|(addr, (size, function))| (addr, *size, function))
+    }
+
+    pub fn slots(&self) -> impl Iterator<Item = (&Addr, &Arc<ExternalFunction>)> {
+        self.slots.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.thunks.is_empty() && self.slots.is_empty()
+    }
+
+    /// The function `loc` transfers control to: if it is within a thunk,
+    /// or it is loaded from a slot of an import table.
+    pub fn function_at(&self, loc: &Loc) -> Option<&Arc<ExternalFunction>> {
+        match loc {
+            Loc::Fixed(addr) => self.thunk(addr),
+            Loc::Computed(Expr::Load(addr, _, _)) => addr
+                .val()
+                .and_then(|addr| self.slot(&Addr::from(addr.clone()))),
+            _ => None,
+        }
+    }
+
+    /// The function called (or tail-called) by `jmp`, if any.
+    pub fn callee(&self, jmp: &Jmp) -> Option<&Arc<ExternalFunction>> {
+        match jmp {
+            Jmp::Call(loc, _) | Jmp::Branch(loc) => self.function_at(loc),
+            _ => None,
+        }
+    }
+}
+
+impl<'r> Project<'r> {
+    /// The import thunks and import table slots of the Project.
+    pub fn imports(&self) -> &Imports {
+        &self.imports
+    }
+
+    /// Import thunks and slots must be added before the code calling them
+    /// is lifted, so that calls to functions that never return terminate
+    /// their Blks (see `explore_from`); Subs lifted at thunks take the
+    /// names of their functions.
+    pub fn imports_mut(&mut self) -> &mut Imports {
+        &mut self.imports
+    }
+
+    /// Records the external functions called by the jmps of `sub` via the
+    /// Project's import thunks and slots, for queries via `external_call`;
+    /// returns the number of jmps resolved.
+    pub fn resolve_imports(&mut self, sub: &Sub) -> usize {
+        let mut resolved = 0;
+        for jmp in sub.blks().iter().flat_map(|blk| blk.jmps()) {
+            if let Some(function) = self.imports.callee(jmp).cloned() {
+                self.annotations.insert(jmp, function);
+                resolved += 1;
+            }
+        }
+        resolved
+    }
+
+    /// The external function called by `jmp`, if resolved by
+    /// `resolve_imports`.
+    pub fn external_call(&self, jmp: impl Identifiable<Jmp>) -> Option<&Arc<ExternalFunction>> {
+        self.annotations.get::<Arc<ExternalFunction>, Jmp>(jmp)
+    }
+}
@@ -0,0 +1,88 @@
+use crate::ir::{Addr, Blk, Def, Expr, Jmp, Loc, Project};
+use crate::lift::arm::MODE_SWITCHES;
+use crate::lift::IsaMode;
+
+impl<'r> Project<'r> {
+    /// Decode the instructions from `addr` onwards (up to the next address
+    /// given a mode) in `mode`, for ARM languages.
+    pub fn set_isa_mode(&mut self, addr: impl Into<Addr>, mode: IsaMode) {
+        self.isa_ranges.insert(addr.into(), mode);
+    }
+
+    /// Decode the instructions following the mapping symbol `name` at
+    /// `addr` (e.g., `$t`, as given by a loader) in the mode it denotes;
+    /// returns false if `name` is not a mapping symbol for code.
+    pub fn add_mapping_symbol(&mut self, addr: impl Into<Addr>, name: &str) -> bool {
+        if let Some(mode) = IsaMode::from_mapping_symbol(name) {
+            self.set_isa_mode(addr, mode);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The mode given for the instructions at `addr`, if the Project's
+    /// language is an ARM language: that given by the Blk oracle, if any;
+    /// else, that given for `addr` itself (see `set_isa_mode`); else, that
+    /// of the flows to `addr` lifted; else, that given for the closest
+    /// address before `addr`. If no mode is given, None, and the
+    /// instructions are decoded in the mode of the Project's disassembly
+    /// context (e.g., as set by `set_context`).
+    pub fn isa_mode(&self, addr: &Addr) -> Option<IsaMode> {
+        if !self.lifter.is_arm() {
+            return None
+        }
+
+        self.blk_oracle
+            .as_ref()
+            .and_then(|oracle| oracle.blk_isa_mode(addr))
+            .or_else(|| self.isa_ranges.get(addr).copied())
+            .or_else(|| self.isa_entries.get(addr).copied())
+            .or_else(|| self.isa_ranges.range(..=addr.clone()).next_back().map(|(_, mode)| *mode))
+    }
+
+    // the mode the instructions at addr are decoded in: that given for
+    // addr, if any, or that of the Project's disassembly context
+    pub(super) fn lifted_isa_mode(&self, addr: &Addr) -> Option<IsaMode> {
+        self.isa_mode(addr)
+            .or_else(|| self.lifter.isa_mode(&self.disassembly_context, Some(addr)))
+    }
+
+    // records the modes of the fixed targets of the jmps of blk, lifted in
+    // mode: the mode assigned by an interworking branch (e.g., blx), or
+    // mode otherwise
+    pub(super) fn propagate_isa_modes(&mut self, blk: &Blk, mode: IsaMode) {
+        for (target, mode) in Self::target_isa_modes(blk, mode) {
+            // modes given explicitly are not overridden
+            if !self.isa_ranges.contains_key(&target) {
+                self.isa_entries.entry(target).or_insert(mode);
+            }
+        }
+    }
+
+    // the modes of the fixed targets of the jmps of blk, lifted in mode
+    pub(super) fn target_isa_modes(blk: &Blk, mode: IsaMode) -> Vec<(Addr, IsaMode)> {
+        let mut switch = None;
+        for def in blk.defs() {
+            if let Def::Assign(ref var, ref expr) = **def {
+                if var.is_physical() && MODE_SWITCHES.contains(&&**var.name()) {
+                    switch = match expr {
+                        Expr::Val(bv) if bv.is_zero() => Some(IsaMode::Arm),
+                        Expr::Val(_) => Some(IsaMode::Thumb),
+                        _ => None,
+                    };
+                }
+            }
+        }
+
+        blk.jmps()
+            .iter()
+            .filter_map(|jmp| match **jmp {
+                Jmp::Branch(Loc::Fixed(ref target))
+                | Jmp::CBranch(Loc::Fixed(ref target), _)
+                | Jmp::Call(Loc::Fixed(ref target), _) => Some((target.clone(), switch.unwrap_or(mode))),
+                _ => None,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,133 @@
+use std::ops::{Bound, RangeBounds};
+
+use crate::ir::{Addr, Blk, Project, Sub};
+use crate::ir::memory::Region;
+use crate::prelude::{Entity, Identifiable};
+
+impl<'r> Project<'r> {
+    /// The Blks of the Project, in order of their ids.
+    pub fn blks(&self) -> impl Iterator<Item = &Entity<Blk>> {
+        self.blks.values()
+    }
+
+    /// The Blks starting at addresses within `range`, in address order.
+    /// Blks that do not start at an instruction boundary (e.g., those
+    /// split from instructions with internal branches) are not included.
+    pub fn blks_in<R>(&self, range: R) -> impl Iterator<Item = (&Addr, &Entity<Blk>)>
+    where R: RangeBounds<Addr> {
+        self.addr_to_blks
+            .range(range)
+            .filter_map(move |(addr, id)| Some((addr, self.blks.get(id)?)))
+    }
+
+    /// The address of `blk`, if it starts at an instruction boundary.
+    pub fn blk_addr(&self, blk: impl Identifiable<Blk>) -> Option<&Addr> {
+        self.blks_to_addr.get(&blk.id())
+    }
+
+    /// The Subs starting at addresses within `range`, in address order.
+    pub fn subs_in<R>(&self, range: R) -> impl Iterator<Item = (&Addr, &Entity<Sub>)>
+    where R: RangeBounds<Addr> {
+        self.addr_to_subs
+            .range(range)
+            .filter_map(move |(addr, id)| Some((addr, self.subs.get(id)?)))
+    }
+
+    /// The symbols of the Project's Subs, in lexicographic order.
+    pub fn symbols(&self) -> impl Iterator<Item = (&str, &Entity<Sub>)> {
+        self.syms_to_subs
+            .iter()
+            .filter_map(move |(symbol, id)| Some((&**symbol, self.subs.get(id)?)))
+    }
+
+    /// The symbols matching the glob `pattern`, where `*` matches any
+    /// sequence of characters and `?` matches any single character, e.g.,
+    /// `"str*cpy"`.
+    pub fn symbols_matching<'a>(&'a self, pattern: &'a str) -> impl Iterator<Item = (&'a str, &'a Entity<Sub>)> {
+        self.symbols().filter(move |(symbol, _)| glob_match(pattern, symbol))
+    }
+
+    /// The regions mapped by the Project, in address order.
+    pub fn regions(&self) -> impl Iterator<Item = &Entity<Region<'r>>> {
+        self.memory.regions().values()
+    }
+
+    /// The regions overlapping `range`, in address order.
+    pub fn regions_in<R>(&self, range: R) -> impl Iterator<Item = &Entity<Region<'r>>>
+    where R: RangeBounds<Addr> {
+        self.regions().filter(move |region| {
+            // the ends of region intervals are inclusive
+            let interval = region.interval();
+            let above_start = match range.start_bound() {
+                Bound::Included(start) => interval.end() >= start,
+                Bound::Excluded(start) => interval.end() > start,
+                Bound::Unbounded => true,
+            };
+            let below_end = match range.end_bound() {
+                Bound::Included(end) => interval.start() <= end,
+                Bound::Excluded(end) => interval.start() < end,
+                Bound::Unbounded => true,
+            };
+            above_start && below_end
+        })
+    }
+
+    /// The regions whose names match the glob `pattern` (see
+    /// `symbols_matching`), e.g., `".text*"`.
+    pub fn regions_matching<'a>(&'a self, pattern: &'a str) -> impl Iterator<Item = &'a Entity<Region<'r>>> {
+        self.regions().filter(move |region| glob_match(pattern, region.name()))
+    }
+}
+
+// matches text against pattern, where * matches any sequence of characters,
+// and ? any single character
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    let (mut p, mut t) = (0, 0);
+    // the position of the last * seen, and the position in text it was
+    // matched from
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, from)) => {
+                    // let the * match one more character
+                    backtrack = Some((star, from + 1));
+                    p = star + 1;
+                    t = from + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::glob_match;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("str*cpy", "strncpy"));
+        assert!(glob_match("str*cpy", "strcpy"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("mem?et", "memset"));
+        assert!(glob_match("*alloc*", "__libc_malloc_impl"));
+        assert!(!glob_match("str*cpy", "strcat"));
+        assert!(!glob_match("mem?et", "memet"));
+        assert!(!glob_match("", "x"));
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::analysis::ContentHash;
+use crate::ir::{Addr, Blk, Def, Expr, Jmp, Loc, Project, Sub};
+use crate::lift::{Lifter, SpecError, SpecVersion};
+use crate::prelude::{Entity, Id, Identifiable};
+
+/// The differences between the IR of a Project and that lifted for the
+/// same instructions by another Lifter (e.g., one built from a newer
+/// version of the language's specification); see `Project::relift_diff`.
+#[derive(Debug, Clone)]
+pub struct SpecMigration {
+    from: SpecVersion,
+    to: SpecVersion,
+    changed: BTreeSet<Addr>,
+    subs: BTreeMap<Id<Sub>, BTreeSet<Addr>>,
+}
+
+impl SpecMigration {
+    /// The version of the specification the Project was lifted with.
+    pub fn from(&self) -> &SpecVersion {
+        &self.from
+    }
+
+    /// The version of the specification of the other Lifter.
+    pub fn to(&self) -> &SpecVersion {
+        &self.to
+    }
+
+    /// The addresses of the instructions whose IR differs, including those
+    /// that are no longer lifted, or that are newly lifted.
+    pub fn changed_insns(&self) -> &BTreeSet<Addr> {
+        &self.changed
+    }
+
+    /// The Subs containing instructions whose IR differs, along with the
+    /// addresses of those instructions.
+    pub fn changed_subs(&self) -> impl Iterator<Item = (Id<Sub>, &BTreeSet<Addr>)> {
+        self.subs.iter().map(|(id, insns)| (*id, insns))
+    }
+
+    pub fn is_unchanged(&self) -> bool {
+        self.changed.is_empty()
+    }
+}
+
+impl<'r> Project<'r> {
+    /// The version of the specification the Project's Blks are lifted with.
+    pub fn spec_version(&self) -> &SpecVersion {
+        self.lifter.spec_version()
+    }
+
+    /// Checks that IR persisted with the stamp `stamp` (see `SpecVersion`)
+    /// is that which the Project would lift.
+    pub fn check_spec_version(&self, stamp: &str) -> Result<(), SpecError> {
+        self.spec_version().check(&stamp.parse()?)
+    }
+
+    /// Re-lifts the instructions of each group of Blks of the Project (see
+    /// `add_blk`) with `lifter`, and reports the instructions whose IR
+    /// differs from that of the Project, and the Subs containing them. The
+    /// Project is not modified; to migrate, build a Project with `lifter`
+    /// and re-add the Subs reported.
+    ///
+    /// IR is compared modulo the ids of its Blks and statements, and flows
+    /// between Blks are compared by the addresses of their targets.
+    pub fn relift_diff(&self, lifter: &Lifter) -> SpecMigration {
+        let mut lifter = lifter.clone();
+        lifter.set_memory(&self.memory);
+
+        let mut changed = BTreeSet::new();
+        let mut changed_groups = BTreeSet::new();
+
+        for (addr, ids) in self.blk_groups.iter() {
+            let blks = ids.iter().filter_map(|id| self.blks.get(id)).collect::<Vec<_>>();
+            let old = insn_hashes(addr, &blks);
+
+            let new = self
+                .group_end(addr)
+                .and_then(|end| end.absolute_difference(addr))
+                .and_then(|size| {
+                    self.memory.find_executable_region(addr)?;
+                    let bytes = self.memory.read_bytes(addr, size).ok()??;
+                    let mut ctxt = lifter.context();
+                    lifter.lift_blk_with(&mut ctxt, addr, &bytes, Some(size)).ok()
+                })
+                .map(|blks| insn_hashes(addr, &blks.iter().collect::<Vec<_>>()))
+                .unwrap_or_default();
+
+            let group = old
+                .keys()
+                .chain(new.keys())
+                .filter(|insn| old.get(*insn) != new.get(*insn))
+                .cloned()
+                .collect::<BTreeSet<_>>();
+
+            if !group.is_empty() {
+                changed.extend(group);
+                changed_groups.insert(addr.clone());
+            }
+        }
+
+        let mut subs = BTreeMap::new();
+        for sub in self.subs.values() {
+            let insns = self
+                .sub_groups(sub)
+                .intersection(&changed_groups)
+                .flat_map(|group| {
+                    let end = self.group_end(group);
+                    changed
+                        .range(group.clone()..)
+                        .take_while(move |insn| end.as_ref().map(|end| *insn < end).unwrap_or(false))
+                        .cloned()
+                })
+                .collect::<BTreeSet<_>>();
+
+            if !insns.is_empty() {
+                subs.insert(sub.id(), insns);
+            }
+        }
+
+        SpecMigration {
+            from: self.spec_version().clone(),
+            to: lifter.spec_version().clone(),
+            changed,
+            subs,
+        }
+    }
+}
+
+// a hash of the IR of each instruction of the group of blks starting at
+// start, independent of the ids of the Blks and statements
+fn insn_hashes(start: &Addr, blks: &[&Entity<Blk>]) -> BTreeMap<Addr, u64> {
+    let insn_addr = |blk: &Blk| {
+        blk.source()
+            .map(|source| source.addr().clone())
+            .or_else(|| blk.addr().cloned())
+    };
+
+    // flows to Blks are compared by the address of their instruction, and
+    // their position among the Blks of that instruction
+    let mut targets = BTreeMap::new();
+    let mut current = start.clone();
+    let mut index = 0;
+    for blk in blks {
+        let addr = insn_addr(blk).unwrap_or_else(|| current.clone());
+        if addr != current {
+            current = addr.clone();
+            index = 0;
+        }
+        targets.insert(blk.id(), (addr, index));
+        index += 1;
+    }
+
+    let mut hashes = BTreeMap::<Addr, ContentHash>::new();
+    for blk in blks {
+        let (addr, _) = &targets[&blk.id()];
+        let hash = hashes.entry(addr.clone()).or_default();
+
+        for phi in blk.phis() {
+            hash.write_str("phi").write_str(&phi.var().to_string());
+            for (cond, expr) in phi.choices() {
+                hash_expr(hash, cond);
+                hash_expr(hash, expr);
+            }
+        }
+
+        for def in blk.defs() {
+            match **def {
+                Def::Assign(ref var, ref expr) => {
+                    hash.write_str("assign").write_str(&var.to_string());
+                    hash_expr(hash, expr);
+                }
+                Def::Assume(ref expr) => {
+                    hash.write_str("assume");
+                    hash_expr(hash, expr);
+                }
+                Def::Store(ref loc, ref val, bits, ref mem) => {
+                    hash.write_str("store").write_u64(bits as u64).write_str(&mem.to_string());
+                    hash_expr(hash, loc);
+                    hash_expr(hash, val);
+                }
+            }
+        }
+
+        for jmp in blk.jmps() {
+            let (kind, loc, args): (_, Option<&Loc>, &[Expr]) = match **jmp {
+                Jmp::Branch(ref loc) => ("branch", Some(loc), &[]),
+                Jmp::CBranch(ref loc, ref cond) => ("cbranch", Some(loc), std::slice::from_ref(cond)),
+                Jmp::Call(ref loc, ref args) => ("call", Some(loc), args),
+                Jmp::Intrinsic(ref name, ref args) => (&**name, None, args),
+                Jmp::Return(ref loc) => ("return", Some(loc), &[]),
+            };
+
+            hash.write_str(kind);
+            match loc {
+                Some(Loc::Resolved(id)) => {
+                    if let Some((addr, index)) = targets.get(id) {
+                        // the first Blk of an instruction is that at its
+                        // address, as if the flow were not resolved
+                        hash.write_str("fixed").write_str(&addr.to_string());
+                        if *index > 0 {
+                            hash.write_u64(*index as u64);
+                        }
+                    } else {
+                        hash.write_str("resolved");
+                    }
+                }
+                Some(Loc::Fixed(addr)) => {
+                    hash.write_str("fixed").write_str(&addr.to_string());
+                }
+                Some(Loc::Computed(expr)) => {
+                    hash.write_str("computed");
+                    hash_expr(hash, expr);
+                }
+                None => (),
+            }
+            for arg in args {
+                hash_expr(hash, arg);
+            }
+        }
+    }
+
+    hashes.into_iter().map(|(addr, hash)| (addr, hash.finish())).collect()
+}
+
+fn hash_expr(hash: &mut ContentHash, expr: &Expr) {
+    match expr {
+        Expr::UnRel(op, iexpr) => {
+            hash.write_str(&format!("{:?}", op));
+            hash_expr(hash, iexpr);
+        }
+        Expr::BinRel(op, lexpr, rexpr) => {
+            hash.write_str(&format!("{:?}", op));
+            hash_expr(hash, lexpr);
+            hash_expr(hash, rexpr);
+        }
+        Expr::UnOp(op, iexpr) => {
+            hash.write_str(&format!("{:?}", op));
+            hash_expr(hash, iexpr);
+        }
+        Expr::BinOp(op, lexpr, rexpr) => {
+            hash.write_str(&format!("{:?}", op));
+            hash_expr(hash, lexpr);
+            hash_expr(hash, rexpr);
+        }
+        Expr::Cast(iexpr, cast) => {
+            hash.write_str(&format!("{:?}", cast));
+            hash_expr(hash, iexpr);
+        }
+        Expr::Load(addr, bits, mem) => {
+            hash.write_str("load").write_u64(*bits as u64).write_str(&mem.to_string());
+            hash_expr(hash, addr);
+        }
+        Expr::Extract(iexpr, lsb, msb) => {
+            hash.write_str("extract").write_u64(*lsb as u64).write_u64(*msb as u64);
+            hash_expr(hash, iexpr);
+        }
+        Expr::Concat(lexpr, rexpr) => {
+            hash.write_str("concat");
+            hash_expr(hash, lexpr);
+            hash_expr(hash, rexpr);
+        }
+        Expr::IfElse(cond, texpr, fexpr) => {
+            hash.write_str("ite");
+            hash_expr(hash, cond);
+            hash_expr(hash, texpr);
+            hash_expr(hash, fexpr);
+        }
+        Expr::Intrinsic(name, args, bits) => {
+            hash.write_str("intrinsic").write_str(name).write_u64(*bits as u64);
+            for arg in args {
+                hash_expr(hash, arg);
+            }
+        }
+        Expr::Val(bv) => {
+            hash.write_str("val").write_str(&format!("{}:{}", bv, bv.bits()));
+        }
+        Expr::Var(var) => {
+            hash.write_str("var").write_str(&var.to_string());
+        }
+    }
+}
@@ -1,20 +1,70 @@
-use crate::ir::{Addr, Blk, Sub};
-use crate::ir::memory::{Mem, Region};
-use crate::lift::{Lifter, LifterBuilder, LifterBuilderError, LifterError};
-use crate::prelude::{Endian, Entity, EntityRef, Id, Identifiable};
-use crate::oracles::{BlkOracle, SubOracle};
+use crate::analysis::{AddressTaken, AnalysisCache, CallbackRef, ConstantPool, ConstantPropagation, Immediate, Constants, DataflowResult, Hint, Hints, InferredPrototype, InferredTypes, ObfuscationReport, StackFrame, SwitchAnalysis, TypeInference, ValueSet, ValueSetAnalysis, ValueSets};
+use crate::analysis::switch::materialise_switch;
+use crate::export::{AsmError, AsmExport};
+use crate::ir::{Addr, BinRel, BitVec, Blk, BlkMerge, ChangeError, ChangeSet, Confidence, Expr, Intrinsic, IntrinsicRegistry, Jmp, Loc, Provenance, SourceLoc, Sub};
+use crate::ir::memory::{ImageError, Mem, MemImage, MemOperands, Permissions, Region, RegionError, RegionIOError, Symbols};
+use crate::lift::{IsaMode, Lifter, LifterBuilder, LifterBuilderError, LifterError};
+use crate::prelude::{intern, Annotations, Endian, Entity, EntityRef, Erased, Id, Identifiable, Tagged};
+use crate::prelude::intervals::Interval;
+use crate::prelude::intervals::collections::IntervalMap;
+use crate::oracles::{is_no_return_symbol, BlkOracle, HeuristicSubOracle, Observed, SubOracle, Trace};
+use crate::os::{ResolvedSyscall, SyscallTable};
+use crate::types::{TypeDatabase, TypeDef, TypeError};
 
 use fugue::ir::disassembly::ContextDatabase;
 
-use std::borrow::Cow;
-use std::collections::BTreeMap;
+use std::borrow::{Borrow, Cow};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::ops::Range;
 use std::path::Path;
 use std::sync::Arc;
 
 use thiserror::Error;
 
+mod boundaries;
+pub use boundaries::{BoundaryAdjustment, BoundaryReason, BoundaryReport};
+
+mod cache;
+
+mod callgraph;
+
+mod conventions;
+
+mod coverage;
+pub use coverage::{DerivedIr, SourceBytes};
+
+mod flows;
+pub use flows::{ComputedFlow, FlowKind};
+
+mod gc;
+pub use gc::GcReport;
+
+mod imports;
+pub use imports::{ExternalFunction, Imports};
+
+mod isa;
+
+mod iter;
+
+mod migrate;
+pub use migrate::SpecMigration;
+
+#[cfg(feature = "rayon")]
+mod parallel;
+
+mod search;
+pub use search::{MatchSite, SemanticMatch};
+
+mod sweep;
+pub use sweep::{Conflict, Sweep, SweepConfig};
+
+mod watch;
+pub use watch::{Invalidation, WatchId};
+
 pub struct ProjectBuilder {
     lifter_builder: LifterBuilder,
+    blk_oracle: Option<Arc<dyn BlkOracle>>,
+    sub_oracle: Option<Arc<dyn SubOracle>>,
 }
 
 #[derive(Debug, Error)]
@@ -30,25 +80,66 @@ impl ProjectBuilder {
     ) -> Result<Self, ProjectBuilderError> {
         Ok(Self {
             lifter_builder: LifterBuilder::new_with(path, ignore_errors)?,
+            blk_oracle: None,
+            sub_oracle: None,
         })
     }
 
     pub fn new(path: impl AsRef<Path>) -> Result<Self, ProjectBuilderError> {
         Ok(Self {
             lifter_builder: LifterBuilder::new(path)?,
+            blk_oracle: None,
+            sub_oracle: None,
+        })
+    }
+
+    /// See `LifterBuilder::new_from_archive`.
+    pub fn new_from_archive(bytes: &[u8]) -> Result<Self, ProjectBuilderError> {
+        Ok(Self {
+            lifter_builder: LifterBuilder::new_from_archive(bytes)?,
+            blk_oracle: None,
+            sub_oracle: None,
         })
     }
 
+    /// Installs `oracle` as the BlkOracle of the projects built.
+    pub fn with_blk_oracle(&mut self, oracle: Arc<dyn BlkOracle>) -> &mut Self {
+        self.blk_oracle = Some(oracle);
+        self
+    }
+
+    /// Installs `oracle` as the SubOracle of the projects built.
+    pub fn with_sub_oracle(&mut self, oracle: Arc<dyn SubOracle>) -> &mut Self {
+        self.sub_oracle = Some(oracle);
+        self
+    }
+
+    fn with_oracles<'r>(&self, mut project: Entity<Project<'r>>) -> Entity<Project<'r>> {
+        project.blk_oracle = self.blk_oracle.clone();
+        project.sub_oracle = self.sub_oracle.clone();
+        project
+    }
+
     pub fn project<'r>(
         &self,
         name: impl Into<Cow<'static, str>>,
         arch: impl Into<Cow<'static, str>>,
         convention: impl AsRef<str>,
     ) -> Result<Entity<Project<'r>>, ProjectBuilderError> {
-        Ok(Project::new(
+        Ok(self.with_oracles(Project::new(
             name,
             self.lifter_builder.build(arch, convention)?,
-        ))
+        )))
+    }
+
+    /// A project for `arch` using its default calling convention (see
+    /// `LifterBuilder::build_default`).
+    pub fn project_default<'r>(
+        &self,
+        name: impl Into<Cow<'static, str>>,
+        arch: impl Into<Cow<'static, str>>,
+    ) -> Result<Entity<Project<'r>>, ProjectBuilderError> {
+        Ok(self.with_oracles(Project::new(name, self.lifter_builder.build_default(arch)?)))
     }
 
     pub fn project_with<'r>(
@@ -60,13 +151,48 @@ impl ProjectBuilder {
         variant: impl AsRef<str>,
         convention: impl AsRef<str>,
     ) -> Result<Entity<Project<'r>>, ProjectBuilderError> {
-        Ok(Project::new(
+        Ok(self.with_oracles(Project::new(
             name,
             self.lifter_builder.build_with(processor, endian, bits, variant, convention)?,
-        ))
+        )))
+    }
+
+    /// Creates a little-endian and a big-endian project for a bi-endian
+    /// processor; regions added to both projects using
+    /// `Project::add_region_mapping_from_values` will contain the same
+    /// logical values.
+    pub fn project_mirrored<'r>(
+        &self,
+        name: impl Into<Cow<'static, str>>,
+        processor: impl AsRef<str>,
+        bits: u32,
+        variant: impl AsRef<str>,
+        convention: impl AsRef<str>,
+    ) -> Result<(Entity<Project<'r>>, Entity<Project<'r>>), ProjectBuilderError> {
+        let name = name.into();
+        let processor = processor.as_ref();
+        let variant = variant.as_ref();
+        let convention = convention.as_ref();
+
+        let le = self.project_with(name.clone(), processor, Endian::Little, bits, variant, convention)?;
+        let be = self.project_with(name, processor, Endian::Big, bits, variant, convention)?;
+
+        Ok((le, be))
     }
 }
 
+#[derive(Debug, Error)]
+pub enum ProjectError {
+    #[error(transparent)]
+    Lifter(#[from] LifterError),
+    #[error(transparent)]
+    Region(#[from] RegionError),
+    #[error(transparent)]
+    RegionIO(#[from] RegionIOError),
+    #[error("address {0} is not mapped")]
+    Unmapped(Addr),
+}
+
 #[derive(Clone)]
 pub struct Project<'r> {
     name: Cow<'static, str>,
@@ -78,41 +204,131 @@ pub struct Project<'r> {
 
     blk_oracle: Option<Arc<dyn BlkOracle>>,
     sub_oracle: Option<Arc<dyn SubOracle>>,
+
+    annotations: Annotations,
+    // the number of passes applied via apply_pass
+    step: usize,
     
     blks: BTreeMap<Id<Blk>, Entity<Blk>>,
     blks_to_addr: BTreeMap<Id<Blk>, Addr>,
     addr_to_blks: BTreeMap<Addr, Id<Blk>>,
+    // the Blks lifted together by add_blk, keyed by their address
+    blk_groups: BTreeMap<Addr, Vec<Id<Blk>>>,
+    // the extent of the instructions of each group of Blks
+    blk_extents: IntervalMap<Addr, Addr>,
+    // the size hints of the Blk oracle found not to end Blks, and the
+    // sizes lifted instead
+    rejected_size_hints: BTreeMap<Addr, (usize, usize)>,
+    // the flows with computed targets of lifted Blks, with the address of
+    // the instruction they were lifted from and the Blk holding them
+    computed_flows: BTreeMap<Id<Jmp>, (Addr, Id<Blk>)>,
+    // for ARM languages, the modes given from addresses onwards (e.g., by
+    // mapping symbols), and the modes of the targets of flows lifted
+    isa_ranges: BTreeMap<Addr, IsaMode>,
+    isa_entries: BTreeMap<Addr, IsaMode>,
+    sweep_configs: BTreeMap<Id<Region<'r>>, SweepConfig>,
+    // facts asserted by the user
+    hints: Hints,
+    decoding_conflicts: BTreeSet<Conflict>,
+    // where the results of per-Sub analyses are reused from
+    analysis_cache: Option<AnalysisCache>,
+    constant_pool: ConstantPool,
+    types: TypeDatabase,
+    // named ranges of memory, e.g., data symbols
+    data_symbols: Symbols,
+    imports: Imports,
+    // the dependents of addresses and entities; see add_watch
+    watches: watch::Watches,
     
     subs: BTreeMap<Id<Sub>, Entity<Sub>>,
     subs_to_addr: BTreeMap<Id<Sub>, Addr>,
     addr_to_subs: BTreeMap<Addr, Id<Sub>>,
+    // the names of the calling conventions overriding the Lifter's for
+    // individual Subs
+    sub_conventions: BTreeMap<Id<Sub>, Arc<str>>,
     syms_to_subs: BTreeMap<Cow<'static, str>, Id<Sub>>,
 }
 
+impl<'r> Tagged for Project<'r> {
+    const TAG: &'static str = "project";
+}
+
 impl<'r> Project<'r> {
-    pub fn new(name: impl Into<Cow<'static, str>>, lifter: Lifter) -> Entity<Self> {
+    pub fn new(name: impl Into<Cow<'static, str>>, mut lifter: Lifter) -> Entity<Self> {
+        let memory = Mem::new("M");
+        lifter.set_memory(&memory);
+
+        let constant_pool = ConstantPool::new(lifter.endian());
+
         Entity::new("project", Self {
             name: name.into(),
 
             disassembly_context: lifter.context(),
             lifter,
 
-            memory: Mem::new("M"),
+            memory,
 
             blk_oracle: None,
             sub_oracle: None,
+
+            annotations: Default::default(),
+            step: 0,
             
             blks: Default::default(),
             blks_to_addr: Default::default(),
             addr_to_blks: Default::default(),
+            blk_groups: Default::default(),
+            blk_extents: Default::default(),
+            rejected_size_hints: Default::default(),
+            computed_flows: Default::default(),
+            isa_ranges: Default::default(),
+            isa_entries: Default::default(),
+            sweep_configs: Default::default(),
+            hints: Default::default(),
+            decoding_conflicts: Default::default(),
+            analysis_cache: None,
+            constant_pool,
+            types: Default::default(),
+            data_symbols: Default::default(),
+            imports: Default::default(),
+            watches: Default::default(),
 
             subs: Default::default(),
             subs_to_addr: Default::default(),
             addr_to_subs: Default::default(),
+            sub_conventions: Default::default(),
             syms_to_subs: Default::default(),
         })
     }
     
+    pub fn blk_oracle(&self) -> Option<&Arc<dyn BlkOracle>> {
+        self.blk_oracle.as_ref()
+    }
+
+    /// Uses `oracle` for the bounds and successors of the Blks lifted by
+    /// `add_blk` and `add_sub`; see `Chain` to consult multiple oracles.
+    pub fn set_blk_oracle(&mut self, oracle: Arc<dyn BlkOracle>) {
+        self.blk_oracle = Some(oracle);
+    }
+
+    pub fn clear_blk_oracle(&mut self) -> Option<Arc<dyn BlkOracle>> {
+        self.blk_oracle.take()
+    }
+
+    pub fn sub_oracle(&self) -> Option<&Arc<dyn SubOracle>> {
+        self.sub_oracle.as_ref()
+    }
+
+    /// Uses `oracle` for the starts, symbols, and Blks of the Subs lifted by
+    /// `add_sub` and `discover_subs`.
+    pub fn set_sub_oracle(&mut self, oracle: Arc<dyn SubOracle>) {
+        self.sub_oracle = Some(oracle);
+    }
+
+    pub fn clear_sub_oracle(&mut self) -> Option<Arc<dyn SubOracle>> {
+        self.sub_oracle.take()
+    }
+
     pub fn add_region_mapping(&mut self, region: Entity<Region<'r>>) {
         self.memory.add_region(region);
     }
@@ -123,46 +339,102 @@ impl<'r> Project<'r> {
         addr: impl Into<Addr>,
         endian: Endian,
         bytes: impl Into<Cow<'r, [u8]>>,
-    ) {
-        self.memory.add_region(Region::new(name, addr, endian, bytes));
+    ) -> Result<(), ProjectError> {
+        self.memory.add_region(Region::try_new(name, addr, endian, bytes)?);
+        Ok(())
+    }
+
+    /// As `add_region_mapping_with`, for loaders that know the accesses
+    /// the region's segment permits; Blks are not lifted from regions that
+    /// are not executable (see `add_blk`).
+    pub fn add_region_mapping_with_permissions(
+        &mut self,
+        name: impl Into<Arc<str>>,
+        addr: impl Into<Addr>,
+        endian: Endian,
+        permissions: Permissions,
+        bytes: impl Into<Cow<'r, [u8]>>,
+    ) -> Result<(), ProjectError> {
+        let mut region = Region::try_new(name, addr, endian, bytes)?;
+        region.value_mut().set_permissions(permissions);
+        self.memory.add_region(region);
+        Ok(())
     }
     
-    pub fn add_blk(&mut self, addr: impl Into<Addr>) -> Result<Vec<Id<Blk>>, LifterError> {
+    /// Adds the regions of `module` of `image` (see `MemImage`), borrowing
+    /// their bytes from `image`; returns the number of regions added.
+    pub fn add_regions_from_image(&mut self, image: &'r MemImage, module: &str) -> Result<usize, ImageError> {
+        let regions = image.regions(module)?;
+        let count = regions.len();
+        for region in regions {
+            self.memory.add_region(region);
+        }
+        Ok(count)
+    }
+
+    /// Adds a region containing `values` laid out in the endianness of the
+    /// project's language; see `Region::try_from_values`.
+    pub fn add_region_mapping_from_values<V>(
+        &mut self,
+        name: impl Into<Arc<str>>,
+        addr: impl Into<Addr>,
+        values: impl IntoIterator<Item = V>,
+    ) -> Result<(), ProjectError>
+    where
+        V: Borrow<BitVec>,
+    {
+        self.memory.add_region(Region::try_from_values(name, addr, self.lifter.endian(), values)?);
+        Ok(())
+    }
+    
+    /// Lifts the Blks starting at `addr` (see `Lifter::lift_blk_with`),
+    /// returning their ids.
+    ///
+    /// Blks are lifted at most once per address: if `addr` starts a group
+    /// of Blks already lifted, its ids are returned; if `addr` starts an
+    /// instruction within such a group, the group is split at `addr`
+    /// (rather than re-lifting its instructions), and the ids of the Blks
+    /// from `addr` onwards are returned. If `addr` is within an instruction
+    /// of such a group, the bytes from `addr` are lifted as a new group
+    /// that overlaps it, and the two are recorded as conflicting decodings
+    /// (see `conflicts`). If `addr` is not mapped, or is mapped by a region
+    /// that is not executable, no ids are returned.
+    pub fn add_blk(&mut self, addr: impl Into<Addr>) -> Result<Vec<Id<Blk>>, ProjectError> {
         let addr = addr.into();
+
+        if let Some(group) = self.blk_groups.get(&addr) {
+            return Ok(group.clone())
+        }
+
+        if let Some(group) = self.split_blk_group(&addr) {
+            return Ok(group)
+        }
+
         if let Some(region) = self.memory.find_region(&addr) {
-            // unwrap is safe here: we know that addr is in region
-            let bytes = region.view_bytes_from(&addr).unwrap();
+            if !region.permissions().is_executable() {
+                log::warn!("refusing to lift Blk at {} from non-executable region {}", addr, region.name());
+                return Ok(Vec::default())
+            }
+            // unwrap is safe here: the region is mapped at addr
+            let bytes = self.memory.read_bytes_from(&addr)?.unwrap();
             // see if we have some a priori knowledge about the block's bounds
             let size_hint = self.blk_oracle
                 .as_ref()
                 .and_then(|o| o.blk_size(&addr));
-            let blks = self.lifter.lift_blk_with(
+            let mode = self.isa_mode(&addr);
+            let (blks, size) = match self.lifter.lift_blk_extent_in(
                 &mut self.disassembly_context,
                 &addr,
-                bytes,
+                &bytes,
                 size_hint,
-            )?;
-            // if blks is empty, then disassembly likely failed
-            if blks.is_empty () {
-                // error?
-                Ok(Vec::default())
-            } else {
-                // otherwise, we index the blocks into the current project
-                // we take the identity of the first block to represent the
-                // group of blocks formed, which would represent a single
-                // basic block in IDA's block model.
-                let blk_id = blks[0].id();
-                self.blks_to_addr.insert(blk_id, addr.clone());
-                self.addr_to_blks.insert(addr, blk_id);
-                
-                let mut blk_ids = Vec::with_capacity(blks.len());
-                for blk in blks.into_iter() {
-                    let blk_id = blk.id();
-                    blk_ids.push(blk_id);
-                    self.blks.insert(blk_id, blk);
-                }
-                Ok(blk_ids)
-            }
+                mode,
+            ) {
+                Ok(lifted) => lifted,
+                // nothing could be decoded at addr, e.g., as it is data
+                Err(LifterError::Undecodable { .. }) => return Ok(Vec::default()),
+                Err(e) => return Err(e.into()),
+            };
+            Ok(self.index_lifted_group(addr, size_hint, blks, size))
         // this is likely an errors: there is no mapped region corresponding to
         // the address we want to build the block from.
         } else {
@@ -170,7 +442,1178 @@ impl<'r> Project<'r> {
             Ok(Vec::default())
         }
     }
-    
+
+    // records if the size hint for the Blk at addr was rejected by the
+    // lifter, i.e., more than the size hinted was lifted
+    fn verify_size_hint(&mut self, addr: &Addr, size_hint: Option<usize>, size: usize) {
+        match size_hint {
+            Some(hint) if size > hint => {
+                self.rejected_size_hints.insert(addr.clone(), (hint, size));
+            }
+            _ => (),
+        }
+    }
+
+    /// The size hints of the Blk oracle (see `BlkOracle::blk_size`) that
+    /// did not end Blks with block-ending flows, e.g., as the oracle uses
+    /// a different block model, as the start address, the size hinted,
+    /// and the size lifted instead.
+    pub fn rejected_size_hints(&self) -> impl Iterator<Item = (&Addr, usize, usize)> {
+        self.rejected_size_hints
+            .iter()
+            .map(|(addr, (hint, size))| (addr, *hint, *size))
+    }
+
+    // indexes the group of Blks lifted from the size bytes at addr given
+    // size_hint, recording the conflicts of the group
+    fn index_lifted_group(
+        &mut self,
+        addr: Addr,
+        size_hint: Option<usize>,
+        blks: Vec<Entity<Blk>>,
+        size: usize,
+    ) -> Vec<Id<Blk>> {
+        self.verify_size_hint(&addr, size_hint, size);
+        let end = &addr + size;
+        let group = self.index_blk_group(addr.clone(), blks, size);
+        // addr may be within an instruction of a group already lifted, or
+        // the group may extend into one
+        for conflict in self.conflicts_with(&addr, &end) {
+            log::debug!("Blks at {} and {} decode overlapping instructions", conflict.first, conflict.second);
+            self.decoding_conflicts.insert(conflict);
+        }
+        group
+    }
+
+    // indexes the group of Blks lifted from the size bytes at addr
+    fn index_blk_group(&mut self, addr: Addr, blks: Vec<Entity<Blk>>, size: usize) -> Vec<Id<Blk>> {
+        // if blks is empty, then disassembly likely failed
+        if blks.is_empty () {
+            // error?
+            return Vec::default()
+        }
+
+        // otherwise, we index the blocks into the current project
+        // we take the identity of the first block to represent the
+        // group of blocks formed, which would represent a single
+        // basic block in IDA's block model.
+        let blk_id = blks[0].id();
+        self.blks_to_addr.insert(blk_id, addr.clone());
+        self.addr_to_blks.insert(addr.clone(), blk_id);
+
+        let mode = self.lifted_isa_mode(&addr);
+
+        let mut blk_ids = Vec::with_capacity(blks.len());
+        for blk in blks.into_iter() {
+            let blk_id = blk.id();
+            let insn = blk.source().map(|source| source.addr()).or(blk.addr()).unwrap_or(&addr);
+            Provenance::record_lifted(&mut self.annotations, &blk, insn);
+            self.track_computed_flows(&blk, insn);
+            if let Some(mode) = mode {
+                self.propagate_isa_modes(&blk, mode);
+            }
+            if let Some(source) = blk.source().cloned() {
+                self.annotations.insert(blk_id, source.clone());
+                for phi in blk.phis() {
+                    self.annotations.insert(phi, source.clone());
+                }
+                for def in blk.defs() {
+                    self.annotations.insert(def, source.clone());
+                }
+                for jmp in blk.jmps() {
+                    self.annotations.insert(jmp, source.clone());
+                }
+            }
+            blk_ids.push(blk_id);
+            self.blks.insert(blk_id, blk);
+        }
+        if size > 0 {
+            self.blk_extents.insert(Interval::from(addr.clone()..&addr + size), addr.clone());
+        }
+        self.blk_groups.insert(addr, blk_ids.clone());
+        blk_ids
+    }
+
+    // splits the group of Blks containing an instruction starting at addr,
+    // returning the Blks of the group from addr onwards
+    fn split_blk_group(&mut self, addr: &Addr) -> Option<Vec<Id<Blk>>> {
+        let (extent, start, index) = self
+            .blk_extents
+            .find_all(&Interval::from(addr.clone()..addr + 1usize))
+            .into_iter()
+            .find_map(|entry| {
+                let start = entry.value();
+                let index = self.blk_groups.get(start)?.iter().position(|id| {
+                    self.blks.get(id).and_then(|blk| blk.addr()) == Some(addr)
+                })?;
+                Some((entry.interval().clone(), start.clone(), index))
+            })?;
+
+        // unwrap is safe here: we know that the group exists
+        let mut head = self.blk_groups.remove(&start).unwrap();
+        let tail = head.split_off(index);
+
+        // flows between the two groups are no longer resolved within a
+        // single group
+        for (group, other) in [(&head, &tail), (&tail, &head)] {
+            let others = other
+                .iter()
+                .filter_map(|id| Some((*id, self.blks.get(id)?.addr()?.clone())))
+                .collect::<BTreeMap<_, _>>();
+
+            for id in group.iter() {
+                // unwrap is safe here: the Blks of groups are always known
+                let blk = self.blks.get_mut(id).unwrap();
+                for jmp in blk.jmps_mut().iter_mut() {
+                    match **jmp {
+                        Jmp::Branch(ref mut loc)
+                        | Jmp::CBranch(ref mut loc, _)
+                        | Jmp::Call(ref mut loc, _)
+                        | Jmp::Return(ref mut loc) => {
+                            if let Loc::Resolved(target) = loc {
+                                if let Some(target) = others.get(target) {
+                                    *loc = Loc::Fixed(target.clone());
+                                }
+                            }
+                        }
+                        Jmp::Intrinsic(_, _) => (),
+                    }
+                }
+            }
+        }
+
+        self.blk_extents.remove(&extent);
+        self.blk_extents.insert(Interval::from(start.clone()..addr.clone()), start.clone());
+        self.blk_extents.insert(Interval::from(addr.clone()..extent.end().clone()), addr.clone());
+
+        self.blks_to_addr.insert(tail[0], addr.clone());
+        self.addr_to_blks.insert(addr.clone(), tail[0]);
+
+        self.blk_groups.insert(start, head);
+        self.blk_groups.insert(addr.clone(), tail.clone());
+
+        Some(tail)
+    }
+
+    /// The start of the group of Blks lifted by `add_blk` whose
+    /// instructions cover `addr`, if any.
+    pub fn blk_group_containing(&self, addr: &Addr) -> Option<&Addr> {
+        self.blk_extents.find_point(addr).map(|entry| entry.value())
+    }
+
+    // the starts of the groups of Blks (see add_blk) of sub
+    fn sub_groups(&self, sub: &Sub) -> BTreeSet<Addr> {
+        sub.blks()
+            .iter()
+            .filter_map(|blk| self.blks_to_addr.get(&blk.id()).cloned())
+            .collect()
+    }
+
+    // the end of the instructions of the group of Blks starting at addr
+    fn group_end(&self, addr: &Addr) -> Option<Addr> {
+        self.blk_extents
+            .find_all(&Interval::from(addr.clone()..addr + 1usize))
+            .into_iter()
+            .find(|entry| entry.value() == addr)
+            .map(|entry| entry.interval().end().clone())
+    }
+
+    /// Lifts the Blks reachable from `addr` by recursive traversal: the
+    /// fixed targets of branches, conditional branches, and calls (and the
+    /// fall-throughs following them) are lifted in turn, along with the
+    /// successors given by the Project's BlkOracle; computed flows and
+    /// returns are not followed. Blks already lifted are not re-lifted (see
+    /// `add_blk`). Targets that cannot be lifted are skipped, unless `addr`
+    /// itself cannot be lifted. Returns the ids of the Blks reached.
+    pub fn explore_from(&mut self, addr: impl Into<Addr>) -> Result<Vec<Id<Blk>>, ProjectError> {
+        let addr = addr.into();
+        let mut reached = self.add_blk(addr.clone())?;
+
+        let mut visited = BTreeSet::from([addr.clone()]);
+        let mut worklist = VecDeque::new();
+
+        let mut successors = |project: &Self, addr: &Addr, group: &[Id<Blk>], worklist: &mut VecDeque<Addr>| {
+            let (branches, calls) = project.group_flows(group);
+            for target in branches.into_iter().chain(calls) {
+                if visited.insert(target.clone()) {
+                    worklist.push_back(target);
+                }
+            }
+
+            if let Some(ref oracle) = project.blk_oracle {
+                for target in oracle.blk_jmps(addr) {
+                    if visited.insert(target.clone()) {
+                        worklist.push_back(target);
+                    }
+                }
+            }
+        };
+
+        successors(self, &addr, &reached, &mut worklist);
+
+        while let Some(target) = worklist.pop_front() {
+            if self.memory.find_region(&target).is_none() {
+                continue
+            }
+
+            let group = match self.add_blk(target.clone()) {
+                Ok(group) => group,
+                Err(e) => {
+                    log::debug!("could not lift block at {}: {}", target, e);
+                    continue
+                }
+            };
+
+            successors(self, &target, &group, &mut worklist);
+            reached.extend(group);
+        }
+
+        // a target within a group already reached splits it, and so its
+        // Blks are reached twice
+        let mut seen = BTreeSet::new();
+        reached.retain(|id| seen.insert(*id));
+
+        Ok(reached)
+    }
+
+    /// Returns true if the Sub at `addr` is known never to return: as
+    /// hinted (see `Hint::NoReturn`), as flagged by the Project's imports
+    /// or SubOracle, or as its symbol names a common function that never
+    /// returns (see `is_no_return_symbol`).
+    pub fn is_no_return(&self, addr: &Addr) -> bool {
+        if self.hints.is_no_return(addr) || self.imports.thunk(addr).map(|f| f.is_no_return()).unwrap_or(false) {
+            return true
+        }
+
+        if let Some(ref oracle) = self.sub_oracle {
+            if oracle.sub_no_return(addr) || oracle.sub_symbol(addr).map(|s| is_no_return_symbol(&s)).unwrap_or(false) {
+                return true
+            }
+        }
+
+        self.sub_at(addr)
+            .and_then(|sub| sub.symbol())
+            .map(|symbol| is_no_return_symbol(symbol))
+            .unwrap_or(false)
+    }
+
+    // returns false if a call to loc by the instruction at insn never
+    // returns
+    fn call_returns(&self, loc: &Loc, insn: Option<&Addr>) -> bool {
+        let target = match loc {
+            Loc::Fixed(target) => self.is_no_return(target),
+            _ => self.imports.function_at(loc).map(|f| f.is_no_return()).unwrap_or(false),
+        };
+        !target && !insn.map(|addr| self.hints.is_no_return(addr)).unwrap_or(false)
+    }
+
+    // the fixed targets of the branches and calls of a group of Blks; the
+    // fall-throughs of calls that never return (see is_no_return) are
+    // omitted
+    fn group_flows(&self, group: &[Id<Blk>]) -> (Vec<Addr>, Vec<Addr>) {
+        self.blk_flows(group.iter().filter_map(|id| self.blks.get(id)).map(|blk| &**blk))
+    }
+
+    // as group_flows, for a group of Blks that need not be indexed
+    fn blk_flows<'a>(&self, group: impl IntoIterator<Item = &'a Blk>) -> (Vec<Addr>, Vec<Addr>) {
+        let mut branches = Vec::new();
+        let mut calls = Vec::new();
+        let mut insn = None;
+
+        for blk in group {
+            if let Some(addr) = blk.addr() {
+                insn = Some(addr);
+            }
+
+            let mut returns = true;
+            for jmp in blk.jmps() {
+                match **jmp {
+                    Jmp::Branch(Loc::Fixed(ref target)) => {
+                        if returns {
+                            branches.push(target.clone());
+                        }
+                    }
+                    Jmp::CBranch(Loc::Fixed(ref target), _) => {
+                        branches.push(target.clone());
+                    }
+                    Jmp::Call(ref loc, _) => {
+                        if let Loc::Fixed(ref target) = loc {
+                            calls.push(target.clone());
+                        }
+                        returns &= self.call_returns(loc, insn);
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        (branches, calls)
+    }
+
+    /// Discovers the Subs of the Project's memory, starting from the
+    /// addresses given by its SubOracle or, if it has none, those found by a
+    /// `HeuristicSubOracle`. Each Sub is lifted as by `add_sub`; the fixed
+    /// targets of calls within each Sub that are mapped are also treated as
+    /// starts. Returns the ids of the Subs added.
+    pub fn discover_subs(&mut self) -> Vec<Id<Sub>> {
+        let oracle = self.sub_oracle.clone().unwrap_or_else(|| {
+            Arc::new(HeuristicSubOracle::new(&self.lifter, &self.memory))
+        });
+
+        let mut starts = oracle
+            .sub_starts()
+            .into_iter()
+            .map(|start| {
+                let confidence = oracle.sub_confidence(&start).unwrap_or(Confidence::Likely);
+                (start, confidence)
+            })
+            .collect::<VecDeque<_>>();
+        let mut seen = starts.iter().map(|(start, _)| start.clone()).collect::<BTreeSet<_>>();
+        let mut discovered = Vec::new();
+
+        while let Some((start, confidence)) = starts.pop_front() {
+            if self.addr_to_subs.contains_key(&start) {
+                continue
+            }
+
+            let (sub_id, calls) = if let Some(sub) = self.lift_sub(&*oracle, &start, confidence, &BTreeSet::new()) {
+                sub
+            } else {
+                continue
+            };
+
+            discovered.push(sub_id);
+
+            // the targets of calls are as trusted as the code making them
+            for target in calls {
+                if self.memory.find_region(&target).is_some() && seen.insert(target.clone()) {
+                    starts.push_back((target, confidence));
+                }
+            }
+        }
+
+        discovered
+    }
+
+    /// Lifts the Sub starting at `addr`, if it is not already known. Its
+    /// Blks are those reachable from `addr` via fixed branches, together
+    /// with those given by the Project's SubOracle and the successors given
+    /// by its BlkOracle; its symbol is given by the SubOracle. Branches
+    /// between the Blks of the Sub are resolved within the Sub (but not
+    /// within the Project's copies of its Blks). As the Sub is requested
+    /// explicitly, its start is certain (see `Confidence`). Returns None if
+    /// the Sub's entry cannot be lifted.
+    pub fn add_sub(&mut self, addr: impl Into<Addr>) -> Option<Id<Sub>> {
+        let addr = addr.into();
+        if let Some(id) = self.addr_to_subs.get(&addr) {
+            return Some(*id)
+        }
+
+        let oracle = self.sub_oracle.clone().unwrap_or_else(|| Arc::new(BTreeSet::new()));
+        self.lift_sub(&*oracle, &addr, Confidence::Certain, &BTreeSet::new()).map(|(id, _)| id)
+    }
+
+    // lifts the Sub starting at start, without exploring beyond the
+    // addresses in stops; returns its id and the fixed targets of its calls
+    fn lift_sub(
+        &mut self,
+        oracle: &dyn SubOracle,
+        start: &Addr,
+        confidence: Confidence,
+        stops: &BTreeSet<Addr>,
+    ) -> Option<(Id<Sub>, BTreeSet<Addr>)> {
+        let mut groups = BTreeMap::new();
+        let mut worklist = VecDeque::from([start.clone()]);
+        let mut calls = BTreeSet::new();
+
+        // the Blks suggested by the oracle are explored along with the
+        // Sub's start
+        worklist.extend(oracle.sub_blocks(start));
+
+        while let Some(addr) = worklist.pop_front() {
+            if groups.contains_key(&addr) || (addr != *start && stops.contains(&addr)) {
+                continue
+            }
+
+            let group = match self.add_blk(addr.clone()) {
+                Ok(group) if !group.is_empty() => group,
+                Ok(_) => continue,
+                Err(e) => {
+                    log::debug!("could not lift block at {}: {}", addr, e);
+                    continue
+                }
+            };
+
+            let (branches, group_calls) = self.group_flows(&group);
+            worklist.extend(branches);
+            calls.extend(group_calls);
+
+            if let Some(ref blk_oracle) = self.blk_oracle {
+                worklist.extend(blk_oracle.blk_jmps(&addr));
+            }
+
+            groups.insert(addr, group);
+        }
+
+        // groups explored earlier may have been split by later targets
+        for (addr, group) in groups.iter_mut() {
+            if let Some(current) = self.blk_groups.get(addr) {
+                *group = current.clone();
+            }
+        }
+
+        let entry = if let Some(entry) = groups.remove(start) {
+            entry
+        } else {
+            log::debug!("could not lift the entry of sub at {}", start);
+            return None
+        };
+
+        let resolved = groups
+            .iter()
+            .map(|(addr, group)| (addr.clone(), group[0]))
+            .chain(std::iter::once((start.clone(), entry[0])))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut blks = entry
+            .iter()
+            .chain(groups.values().flatten())
+            .filter_map(|id| self.blks.get(id).cloned())
+            .collect::<Vec<_>>();
+
+        for blk in blks.iter_mut() {
+            // the flows following calls that never return are not taken
+            let insn = blk.source().map(|source| source.addr().clone()).or_else(|| blk.addr().cloned());
+            let call = blk.jmps().iter().position(|jmp| match **jmp {
+                Jmp::Call(ref loc, _) => !self.call_returns(loc, insn.as_ref()),
+                _ => false,
+            });
+            if let Some(index) = call {
+                blk.jmps_mut().truncate(index + 1);
+            }
+
+            for jmp in blk.jmps_mut().iter_mut() {
+                match **jmp {
+                    Jmp::Branch(ref mut loc) | Jmp::CBranch(ref mut loc, _) => {
+                        if let Loc::Fixed(ref target) = loc {
+                            if let Some(id) = resolved.get(target) {
+                                *loc = Loc::Resolved(*id);
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        let symbol = oracle
+            .sub_symbol(start)
+            .or_else(|| self.imports.thunk(start).map(|function| function.name().to_string()));
+        let sub = Sub::new(symbol.as_deref().map(intern), start.clone(), blks);
+        let sub_id = sub.id();
+
+        if let Some(symbol) = symbol {
+            self.syms_to_subs.insert(Cow::Owned(symbol), sub_id);
+        }
+
+        self.subs_to_addr.insert(sub_id, start.clone());
+        self.addr_to_subs.insert(start.clone(), sub_id);
+        self.subs.insert(sub_id, sub);
+
+        self.annotations.insert(sub_id, confidence);
+
+        Some((sub_id, calls))
+    }
+
+    pub fn blk(&self, id: impl Identifiable<Blk>) -> Option<&Entity<Blk>> {
+        self.blks.get(&id.id())
+    }
+
+    pub fn sub(&self, id: impl Identifiable<Sub>) -> Option<&Entity<Sub>> {
+        self.subs.get(&id.id())
+    }
+
+    pub fn sub_at(&self, addr: impl Borrow<Addr>) -> Option<&Entity<Sub>> {
+        self.addr_to_subs.get(addr.borrow()).and_then(|id| self.subs.get(id))
+    }
+
+    pub fn sub_by_symbol(&self, symbol: impl AsRef<str>) -> Option<&Entity<Sub>> {
+        self.syms_to_subs.get(symbol.as_ref()).and_then(|id| self.subs.get(id))
+    }
+
+    pub fn subs(&self) -> impl Iterator<Item = &Entity<Sub>> {
+        self.subs.values()
+    }
+
+    /// Re-emits the instructions of `sub` as assembly (see `AsmExport`),
+    /// using the symbols of the Project's Subs as labels, and noting the
+    /// confidence of their starts.
+    pub fn export_asm(&self, sub: &Sub) -> Result<String, AsmError> {
+        let symbols = self
+            .subs
+            .values()
+            .filter_map(|sub| Some((sub.addr()?.clone(), sub.symbol()?.to_string())));
+
+        let confidences = self
+            .subs
+            .iter()
+            .filter_map(|(id, sub)| Some((sub.addr()?.clone(), self.confidence(*id)?)));
+
+        let notes = sub
+            .blks()
+            .iter()
+            .flat_map(|blk| {
+                blk.defs()
+                    .iter()
+                    .map(|def| def.id().erase())
+                    .chain(blk.jmps().iter().map(|jmp| jmp.id().erase()))
+            })
+            .filter_map(|id| {
+                let addr = self.source_loc(id)?.addr().clone();
+                let immediates = self.immediates(id)?;
+                Some(immediates.iter().flat_map(|imm| imm.notes()).map(move |note| (addr.clone(), note.to_string())))
+            })
+            .flatten();
+
+        AsmExport::new(&self.lifter, &self.memory)
+            .symbols(symbols)
+            .confidences(confidences)
+            .notes(notes)
+            .emit(sub)
+    }
+
+    /// Recovers the jump tables of the computed branches within `sub` and
+    /// materialises their targets as branches (see `JumpTable`), both in
+    /// `sub`, where they are resolved to its Blks, and the Project's copies
+    /// of its Blks; targets that do not start a known Blk are lifted. As the bounds of tables are inferred,
+    /// the branches materialised are likely, at best (see `Confidence`).
+    /// If the Project has an analysis cache (see `set_analysis_cache`), the
+    /// tables recovered for a Sub with the same code (and tables) are
+    /// reused. Returns the ids of the Blks lifted.
+    pub fn resolve_jump_tables(&mut self, sub: &mut Sub) -> Result<Vec<Id<Blk>>, ProjectError> {
+        let mut analysis = SwitchAnalysis::new();
+        for reg in self.lifter.callee_saved() {
+            analysis.preserve(reg.name().clone());
+        }
+        let key = self.analysis_key(sub, "switch", &format!("{:?}", analysis));
+        let mut lifted = Vec::new();
+
+        let confidence = sub
+            .addr()
+            .and_then(|addr| self.addr_to_subs.get(addr))
+            .and_then(|id| self.confidence(*id))
+            .unwrap_or(Confidence::Certain)
+            .combine(Confidence::Likely);
+
+        let cached = key.zip(self.analysis_cache.as_ref()).and_then(|(key, cache)| {
+            cache.get("switch", &key, |text| self.decode_jump_tables(sub, text))
+        });
+
+        let targets = if let Some(tables) = cached {
+            let insn_addrs = self.insn_addrs(sub);
+            for table in tables.iter() {
+                let sites = insn_addrs
+                    .iter()
+                    .filter(|(_, addr)| **addr == table.site)
+                    .map(|(id, _)| *id)
+                    .collect::<Vec<_>>();
+
+                for id in sites {
+                    let resolved = Self::blk_addrs(sub);
+                    let computed = |blk: &Blk| {
+                        blk.jmps()
+                            .iter()
+                            .filter(|jmp| matches!(***jmp, Jmp::Branch(Loc::Computed(_))))
+                            .map(|jmp| jmp.id())
+                            .collect::<Vec<_>>()
+                    };
+
+                    if let Some(blk) = sub.blk_mut(id) {
+                        for jmp in computed(blk) {
+                            let resolve = |addr: &Addr| resolved.get(addr).copied();
+                            for branch in materialise_switch(blk, jmp, &table.targets, resolve) {
+                                self.annotations.insert(branch, confidence);
+                            }
+                        }
+                    }
+                    if let Some(blk) = self.blks.get_mut(&id) {
+                        for jmp in computed(blk) {
+                            for branch in materialise_switch(blk, jmp, &table.targets, |_| None) {
+                                self.annotations.insert(branch, confidence);
+                            }
+                        }
+                    }
+                }
+            }
+
+            tables
+                .into_iter()
+                .flat_map(|table| table.targets)
+                .collect::<BTreeSet<_>>()
+        } else {
+            let tables = analysis.recover(sub, &self.memory);
+
+            let cache = key.zip(self.analysis_cache.as_ref());
+            if let (Some((key, cache)), Some(text)) = (cache, self.encode_jump_tables(sub, &tables)) {
+                if let Err(e) = cache.put("switch", key, &text) {
+                    log::warn!("could not cache jump tables: {}", e);
+                }
+            }
+
+            let resolved = Self::blk_addrs(sub);
+            for table in tables.iter() {
+                if let Some(blk) = sub.blk_mut(table.blk()) {
+                    let before = blk.jmps().iter().map(|jmp| jmp.id()).collect::<BTreeSet<_>>();
+                    table.materialise(blk, |addr| resolved.get(addr).copied());
+                    for jmp in blk.jmps().iter().filter(|jmp| !before.contains(&jmp.id())) {
+                        self.annotations.insert(jmp, confidence);
+                    }
+                }
+                if let Some(blk) = self.blks.get_mut(&table.blk()) {
+                    let before = blk.jmps().iter().map(|jmp| jmp.id()).collect::<BTreeSet<_>>();
+                    table.materialise(blk, |_| None);
+                    for jmp in blk.jmps().iter().filter(|jmp| !before.contains(&jmp.id())) {
+                        self.annotations.insert(jmp, confidence);
+                    }
+                }
+            }
+
+            tables
+                .iter()
+                .flat_map(|table| table.targets().iter().cloned())
+                .collect::<BTreeSet<_>>()
+        };
+
+        for target in targets {
+            if !self.addr_to_blks.contains_key(&target) {
+                lifted.extend(self.add_blk(target)?);
+            }
+        }
+
+        Ok(lifted)
+    }
+
+    /// Attaches the dynamic evidence of `trace` to the Project's Blks and
+    /// Subs (see `Observed`), and uses the flows it observed to resolve
+    /// computed branches: each computed branch of a group of Blks lifted by
+    /// `add_blk` is preceded by a conditional branch to each target
+    /// observed leaving the group (other than its fixed targets), in both
+    /// the Project's Blks and the copies held by its Subs. As they were
+    /// observed, the branches materialised are certain (see `Confidence`).
+    /// Targets that do not start a known Blk are lifted. Returns the ids of
+    /// the Blks lifted.
+    pub fn import_trace(&mut self, trace: &Trace) -> Result<Vec<Id<Blk>>, ProjectError> {
+        let extents = self
+            .blk_extents
+            .iter()
+            .map(|entry| (entry.value().clone(), entry.interval().end().clone()))
+            .collect::<Vec<_>>();
+
+        let mut observed = BTreeSet::new();
+
+        for (start, end) in extents {
+            let observation = if let Some(observation) = trace.observed_blk(&start, &end) {
+                observation
+            } else {
+                continue
+            };
+
+            let group = self.blk_groups.get(&start).cloned().unwrap_or_default();
+
+            // the targets explained by the group's fixed flows
+            let mut known = BTreeSet::from([end.clone()]);
+            let mut computed = Vec::new();
+            for (id, blk) in group.iter().filter_map(|id| Some((id, self.blks.get(id)?))) {
+                for jmp in blk.jmps() {
+                    match **jmp {
+                        Jmp::Branch(Loc::Fixed(ref target))
+                        | Jmp::CBranch(Loc::Fixed(ref target), _)
+                        | Jmp::Call(Loc::Fixed(ref target), _) => {
+                            known.insert(target.clone());
+                        }
+                        Jmp::Branch(Loc::Computed(_)) => computed.push((*id, jmp.id())),
+                        _ => (),
+                    }
+                }
+            }
+
+            let targets = observation
+                .targets()
+                .difference(&known)
+                .cloned()
+                .collect::<BTreeSet<_>>();
+
+            if !targets.is_empty() {
+                let mut materialised = Vec::new();
+                for (blk, jmp) in computed {
+                    if let Some(blk) = self.blks.get_mut(&blk) {
+                        materialised.extend(Self::materialise_targets(blk, jmp, &targets));
+                    }
+                    for sub in self.subs.values_mut() {
+                        if let Some(blk) = sub.blk_mut(blk) {
+                            materialised.extend(Self::materialise_targets(blk, jmp, &targets));
+                        }
+                    }
+                }
+                for jmp in materialised {
+                    self.annotations.insert(jmp, Confidence::Certain);
+                }
+                observed.extend(targets);
+            }
+
+            if let Some(first) = group.first() {
+                self.annotations.insert(*first, observation);
+            }
+        }
+
+        for (sub, start) in self.subs_to_addr.iter() {
+            if let Some(observation) = trace.observed_sub(start) {
+                self.annotations.insert(*sub, observation);
+            }
+        }
+
+        let mut lifted = Vec::new();
+        for target in observed {
+            if !self.blk_groups.contains_key(&target) {
+                lifted.extend(self.add_blk(target)?);
+            }
+        }
+
+        Ok(lifted)
+    }
+
+    // the Blks of sub starting at each address
+    fn blk_addrs(sub: &Sub) -> BTreeMap<Addr, Id<Blk>> {
+        sub.blks()
+            .iter()
+            .filter_map(|blk| Some((blk.addr()?.clone(), blk.id())))
+            .collect()
+    }
+
+    // precedes the computed branch jmp of blk with a conditional branch to
+    // each of targets, returning the ids of the branches added
+    fn materialise_targets(blk: &mut Blk, jmp: Id<Jmp>, targets: &BTreeSet<Addr>) -> Vec<Id<Jmp>> {
+        let position = blk.jmps().iter().position(|j| j.id() == jmp);
+        let (position, target) = match position.map(|i| (i, &*blk.jmps()[i])) {
+            Some((i, Jmp::Branch(Loc::Computed(target)))) => (i, target.clone()),
+            _ => return Vec::new(),
+        };
+
+        // targets materialised by an earlier import are not repeated
+        let existing = blk.jmps()[..position]
+            .iter()
+            .filter_map(|jmp| match **jmp {
+                Jmp::CBranch(Loc::Fixed(ref addr), _) => Some(addr.clone()),
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>();
+
+        let bits = target.bits() as usize;
+        let branches = targets.difference(&existing).map(|addr| {
+            let value = BitVec::from(addr.clone()).unsigned_cast(bits);
+            Jmp::cbranch(addr.clone(), Expr::bin_rel(BinRel::Eq, target.clone(), value))
+        }).collect::<Vec<_>>();
+
+        let ids = branches.iter().map(|branch| branch.id()).collect();
+        blk.jmps_mut().splice(position..position, branches);
+        ids
+    }
+
+    /// How much `entity` (e.g., a Sub, or a branch materialised by
+    /// `resolve_jump_tables` or `import_trace`) can be trusted, if recorded.
+    pub fn confidence<V>(&self, entity: impl Identifiable<V>) -> Option<Confidence> {
+        self.annotations.get::<Confidence, V>(entity).copied()
+    }
+
+    /// Asserts `hint` as ground truth for the Project's analyses; see
+    /// `Hints`.
+    pub fn add_hint(&mut self, hint: Hint) {
+        self.hints.add(hint);
+    }
+
+    /// Asserts that the register named `name` holds `value` before the
+    /// instruction at `addr`; returns false if the Project's language has
+    /// no such register.
+    pub fn hint_register(&mut self, addr: impl Into<Addr>, name: &str, value: BitVec) -> bool {
+        let (var, bits) = if let Some(var) = self.lifter.register(name) {
+            match var.bits() {
+                Some(bits) => (var, bits as usize),
+                None => return false,
+            }
+        } else {
+            return false
+        };
+        let value = if value.bits() != bits { value.cast(bits) } else { value };
+        self.add_hint(Hint::Register { addr: addr.into(), var, value });
+        true
+    }
+
+    /// Asserts that the call at `addr`, or any call to the Sub starting at
+    /// `addr`, never returns. Blks lifted subsequently do not follow the
+    /// fall-throughs of such calls.
+    pub fn hint_no_return(&mut self, addr: impl Into<Addr>) {
+        self.add_hint(Hint::NoReturn { addr: addr.into() });
+    }
+
+    /// Asserts that the `size` bytes of memory at `addr` are never
+    /// modified; returns false if they are not mapped.
+    pub fn hint_constant_memory(&mut self, addr: impl Into<Addr>, size: usize) -> bool {
+        let addr = addr.into();
+        let bytes = self
+            .memory
+            .read_bytes(&addr, size)
+            .ok()
+            .flatten()
+            .map(|bytes| Arc::<[u8]>::from(&*bytes));
+
+        if let Some(bytes) = bytes {
+            let endian = self.lifter.endian();
+            self.add_hint(Hint::ConstantMemory { addr, endian, bytes });
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn hints(&self) -> &Hints {
+        &self.hints
+    }
+
+    /// Asserts each of `hints` (e.g., those recorded by another Project) in
+    /// order.
+    pub fn replay_hints(&mut self, hints: &Hints) {
+        for hint in hints.iter() {
+            self.add_hint(hint.clone());
+        }
+    }
+
+    /// Propagates constants within `sub`, treating the Project's hints as
+    /// ground truth.
+    pub fn constants(&self, sub: &Sub) -> DataflowResult<Constants> {
+        ConstantPropagation::with_hints(Arc::new(self.hints.clone())).solve(sub)
+    }
+
+    /// Computes the value sets of the variables of `sub` (see
+    /// `ValueSetAnalysis`), reading memory from the Project, and annotates
+    /// each computed jump of `sub` with the values of its target (see
+    /// `computed_targets`).
+    pub fn value_sets(&mut self, sub: &Sub) -> DataflowResult<ValueSets> {
+        let (result, targets) = {
+            let mut analysis = ValueSetAnalysis::new(self.lifter.stack_pointer());
+            analysis.memory(&self.memory).caller_saved(self.lifter.caller_saved());
+
+            let result = analysis.solve(sub);
+            let targets = analysis.computed_targets(sub, &result);
+            (result, targets)
+        };
+
+        for (jmp, values) in targets {
+            self.annotations.insert(jmp, values);
+        }
+        result
+    }
+
+    /// Reconstructs the stack frame of `sub` (see `Sub::stack_frame`),
+    /// reading memory from the Project; returns None if the stack pointer
+    /// of the Project's language is not known.
+    pub fn stack_frame(&self, sub: &Sub) -> Option<StackFrame> {
+        let mut analysis = ValueSetAnalysis::new(Some(self.lifter.stack_pointer()?));
+        analysis.memory(&self.memory).caller_saved(self.lifter.caller_saved());
+        Some(StackFrame::with_analysis(sub, &analysis))
+    }
+
+    /// Infers the types of the variables and memory of `sub` (see
+    /// `TypeInference`), recording them for later queries via
+    /// `inferred_types`; Subs are inferred independently, so only Subs that
+    /// change need to be re-inferred.
+    pub fn infer_types(&mut self, sub: &Entity<Sub>) -> &InferredTypes {
+        let types = TypeInference::new().infer(sub);
+        self.annotations.insert(sub.id(), types);
+        // unwrap is safe here: we have just inserted the annotation
+        self.annotations.get::<InferredTypes, Sub>(sub.id()).unwrap()
+    }
+
+    /// The types of the variables and memory of `sub`, if inferred by
+    /// `infer_types`.
+    pub fn inferred_types(&self, sub: impl Identifiable<Sub>) -> Option<&InferredTypes> {
+        self.annotations.get::<InferredTypes, Sub>(sub)
+    }
+
+    /// The intrinsics known to the Project's lifter.
+    pub fn intrinsics(&self) -> &IntrinsicRegistry {
+        self.lifter.intrinsics()
+    }
+
+    /// Registers `intrinsic` with the Project's lifter, so that analyses can
+    /// model its uses; returns the intrinsic previously registered with the
+    /// same name, if any.
+    pub fn register_intrinsic(&mut self, intrinsic: Intrinsic) -> Option<Arc<Intrinsic>> {
+        self.lifter.intrinsics_mut().register(intrinsic)
+    }
+
+    /// Replaces the uses of intrinsics within `sub` with their expansions
+    /// (see `IntrinsicRegistry::expand`); returns the number replaced.
+    pub fn expand_intrinsics(&self, sub: &mut Sub) -> usize {
+        self.lifter.intrinsics().expand(sub)
+    }
+
+    /// The types known to the Project, e.g., as recovered from its debug
+    /// information, or imported from other Projects (see `import_types`).
+    pub fn types(&self) -> &TypeDatabase {
+        &self.types
+    }
+
+    pub fn types_mut(&mut self) -> &mut TypeDatabase {
+        &mut self.types
+    }
+
+    /// Adds the types of `types` (e.g., as read by `TypeDatabase::from_jsonl`
+    /// from those of another Project) to the Project's, and the enumerations
+    /// among them to its constant pool. Nothing is added if any conflict
+    /// with the Project's types.
+    pub fn import_types(&mut self, types: &TypeDatabase) -> Result<(), TypeError> {
+        self.types.merge(types)?;
+        for (_, def) in types.iter() {
+            if let TypeDef::Enum(t) = def {
+                self.constant_pool.add_enum(t.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds the Subs whose addresses are taken by the code of the
+    /// Project's Subs or held by its memory (see `AddressTaken`), marking
+    /// them as the candidate targets of indirect calls, with the references
+    /// taking their addresses, for queries via `callback_refs`. Returns the
+    /// number of Subs marked.
+    pub fn find_callbacks(&mut self) -> usize {
+        let mut analysis = AddressTaken::new(self.addr_to_subs.iter().map(|(addr, id)| (addr, *id)));
+        analysis.arguments(self.lifter.integer_argument_registers().unwrap_or_default());
+
+        let mut refs = BTreeMap::<Id<Sub>, Vec<CallbackRef>>::new();
+        let found = self
+            .subs
+            .values()
+            .flat_map(|sub| analysis.scan(sub))
+            .chain(analysis.scan_memory(&self.memory, self.lifter.architecture().bits() as u32));
+
+        for cref in found {
+            refs.entry(cref.sub()).or_default().push(cref);
+        }
+
+        let ids = self.subs.keys().copied().collect::<Vec<_>>();
+        for id in ids {
+            self.annotations.insert(id, refs.remove(&id).unwrap_or_default());
+        }
+
+        self.subs.keys().filter(|id| self.is_address_taken(**id)).count()
+    }
+
+    /// The references taking the address of `sub`, as found by
+    /// `find_callbacks`.
+    pub fn callback_refs(&self, sub: impl Identifiable<Sub>) -> &[CallbackRef] {
+        self.annotations
+            .get::<Vec<CallbackRef>, Sub>(sub)
+            .map(|refs| &refs[..])
+            .unwrap_or(&[])
+    }
+
+    /// Returns true if `sub` was found to be a candidate target of indirect
+    /// calls by `find_callbacks`.
+    pub fn is_address_taken(&self, sub: impl Identifiable<Sub>) -> bool {
+        !self.callback_refs(sub).is_empty()
+    }
+
+    /// The obfuscation metrics of the Project's Subs, ranked by how likely
+    /// each is to be obfuscated (see `ObfuscationMetrics`).
+    pub fn obfuscation_report(&self) -> ObfuscationReport {
+        ObfuscationReport::new(self.subs.values())
+    }
+
+    /// The named ranges of the Project's memory, e.g., the data symbols
+    /// of the binary it was loaded from (cf. `symbols`, of its Subs).
+    pub fn data_symbols(&self) -> &Symbols {
+        &self.data_symbols
+    }
+
+    pub fn data_symbols_mut(&mut self) -> &mut Symbols {
+        &mut self.data_symbols
+    }
+
+    /// Renders the addresses of memory operands relative to the Project's
+    /// Regions and data symbols (see `MemOperands`); use `style` to render
+    /// them as raw addresses.
+    pub fn mem_operands(&self) -> MemOperands<'_, 'r> {
+        let mut operands = MemOperands::new();
+        operands.memory(&self.memory).symbols(&self.data_symbols);
+        operands
+    }
+
+    /// Infers the prototypes of the Project's Subs (see
+    /// `PrototypeInference`) under their calling conventions (see
+    /// `set_sub_convention`), for queries via `inferred_prototype`. Returns
+    /// the number of Subs inferred, which excludes those whose
+    /// convention's registers are not known.
+    pub fn infer_prototypes(&mut self) -> usize {
+        let mut count = 0;
+
+        // the uses of return values are found across all Subs, hence each
+        // convention's inference considers all Subs, and its prototypes
+        // are kept for the Subs following it
+        for convention in self.conventions_of_subs() {
+            let inference = match self.prototype_inference(convention.as_ref()) {
+                Some(inference) => inference,
+                None => continue,
+            };
+
+            let prototypes = inference.infer(self.subs.values());
+            for (id, prototype) in prototypes.iter() {
+                if self.sub_conventions.get(&id) == convention.as_ref() {
+                    self.annotations.insert(id, prototype.clone());
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// The prototype of `sub`, if inferred by `infer_prototypes`.
+    pub fn inferred_prototype(&self, sub: impl Identifiable<Sub>) -> Option<&InferredPrototype> {
+        self.annotations.get::<InferredPrototype, Sub>(sub)
+    }
+
+    /// Resolves the system calls made by `sub` under `table` (see
+    /// `SyscallTable::resolve`), recording them for queries via `syscall`;
+    /// returns the number resolved.
+    pub fn resolve_syscalls(&mut self, sub: &Sub, table: &SyscallTable) -> usize {
+        let resolved = table.resolve(sub);
+        let count = resolved.len();
+        for (id, syscall) in resolved {
+            self.annotations.insert(id, syscall);
+        }
+        count
+    }
+
+    /// The system call made by the statement `stmt` (a Def or Jmp), if
+    /// resolved by `resolve_syscalls`.
+    pub fn syscall<V>(&self, stmt: impl Identifiable<V>) -> Option<&ResolvedSyscall> {
+        self.annotations.get::<ResolvedSyscall, Erased>(stmt.id().erase())
+    }
+
+    /// The constant pool used to note what the immediates of Subs denote
+    /// (see `annotate_immediates`); it is extended with the Project's magic
+    /// numbers and enumerations via `constant_pool_mut`.
+    pub fn constant_pool(&self) -> &ConstantPool {
+        &self.constant_pool
+    }
+
+    pub fn constant_pool_mut(&mut self) -> &mut ConstantPool {
+        &mut self.constant_pool
+    }
+
+    /// Notes what the immediates of the statements of `sub` denote (see
+    /// `ConstantPool`), for queries via `immediates`, and for export (see
+    /// `export_asm`). Returns the number of statements with notes.
+    pub fn annotate_immediates(&mut self, sub: &Sub) -> usize {
+        let found = self.constant_pool.scan(sub);
+        let count = found.len();
+        for (id, immediates) in found {
+            self.annotations.insert(id, immediates);
+        }
+        count
+    }
+
+    /// The noted immediates of the statement `stmt` (a Def or Jmp), if
+    /// recorded by `annotate_immediates`.
+    pub fn immediates<V>(&self, stmt: impl Identifiable<V>) -> Option<&[Immediate]> {
+        self.annotations
+            .get::<Vec<Immediate>, Erased>(stmt.id().erase())
+            .map(|immediates| &immediates[..])
+    }
+
+    /// The values of the target of the computed jump `jmp`, if recorded by
+    /// `value_sets`.
+    pub fn computed_targets(&self, jmp: impl Identifiable<Jmp>) -> Option<&ValueSet> {
+        self.annotations.get::<ValueSet, Jmp>(jmp)
+    }
+
+    /// Records how much `entity` can be trusted, e.g., for facts recovered
+    /// by the user's own analyses.
+    pub fn set_confidence<V>(&mut self, entity: impl Identifiable<V>, confidence: Confidence) {
+        self.annotations.insert(entity, confidence);
+    }
+
+    /// The Subs whose starts are trusted at least as much as `min`.
+    pub fn subs_with_confidence(&self, min: Confidence) -> impl Iterator<Item = &Entity<Sub>> {
+        self.subs
+            .values()
+            .filter(move |sub| self.confidence(*sub).map(|c| c >= min).unwrap_or(false))
+    }
+
+    /// The dynamic evidence attached to `entity` (a Blk or Sub) by
+    /// `import_trace`.
+    pub fn observed<V>(&self, entity: impl Identifiable<V>) -> Option<&Observed> {
+        self.annotations.get::<Observed, V>(entity)
+    }
+
+    /// Merges the Blks of `sub` as given by `merge` (see
+    /// `Sub::normalise_blocks`), as a step of the Project's pipeline (see
+    /// `apply_pass`); for `BlkMerge::Ida`, Blks are not merged across the
+    /// starts of the groups of Blks lifted by `add_blk`. Returns the number
+    /// of Blks merged into their predecessors.
+    pub fn normalise_blocks(&mut self, sub: &mut Sub, merge: BlkMerge) -> usize {
+        let leaders = sub
+            .blks()
+            .iter()
+            .map(|blk| blk.id())
+            .filter(|id| self.blks_to_addr.contains_key(id))
+            .collect::<BTreeSet<_>>();
+
+        self.apply_pass("normalise_blocks", sub, |sub| sub.normalise_blocks_with(merge, &leaders))
+    }
+
+    /// Applies `pass` to `sub` as the next step of the Project's pipeline,
+    /// recording the provenance of the Blks and statements it creates or
+    /// modifies.
+    pub fn apply_pass<R>(
+        &mut self,
+        name: impl Into<Arc<str>>,
+        sub: &mut Sub,
+        pass: impl FnOnce(&mut Sub) -> R,
+    ) -> R {
+        let step = self.step;
+        self.step += 1;
+        Provenance::track(&mut self.annotations, name, step, sub, pass)
+    }
+
+    /// Applies `changes` to `sub` atomically (see `ChangeSet::apply`),
+    /// tracking the provenance of the statements changed as a pass.
+    pub fn apply_changes(&mut self, sub: &mut Sub, changes: &ChangeSet) -> Result<usize, ChangeError> {
+        self.apply_pass("changes", sub, |sub| changes.apply(sub))
+    }
+
+    /// The instruction `entity` (e.g., a Def or Jmp) was lifted from, if
+    /// it was lifted by the Project; see `SourceLoc`.
+    pub fn source_loc<V>(&self, entity: impl Identifiable<V>) -> Option<&SourceLoc> {
+        self.annotations
+            .get::<Arc<SourceLoc>, V>(entity)
+            .map(|source| &**source)
+    }
+
+    /// The provenance of `entity` (e.g., a Blk or a Def), if it was lifted
+    /// by, or transformed via, this Project.
+    pub fn provenance<V>(&self, entity: impl Identifiable<V>) -> Option<&Provenance> {
+        self.annotations.get::<Provenance, V>(entity)
+    }
+
+    pub fn annotations(&self) -> &Annotations {
+        &self.annotations
+    }
+
+    pub fn annotations_mut(&mut self) -> &mut Annotations {
+        &mut self.annotations
+    }
+
     pub fn memory(&self) -> &Mem<'r> {
         &self.memory
     }
@@ -178,4 +1621,154 @@ impl<'r> Project<'r> {
     pub fn lifter(&self) -> &Lifter {
         &self.lifter
     }
-}
\ No newline at end of file
+
+    /// Sets the context variable `name` to `value` for the Project's
+    /// lifting, everywhere or within `range` (see `Lifter::set_context`);
+    /// Blks already lifted are not re-lifted.
+    pub fn set_context(
+        &mut self,
+        name: impl AsRef<str>,
+        value: u32,
+        range: Option<Range<Addr>>,
+    ) -> Result<(), ProjectError> {
+        self.lifter.set_context(&mut self.disassembly_context, name, value, range)?;
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::path::PathBuf;
+    use super::*;
+
+    // mov eax, 1 @ 0x1000; nop @ 0x1005; ret @ 0x1006
+    fn x86_project<'r>() -> Result<Entity<Project<'r>>, Box<dyn std::error::Error>> {
+        let root = env::var("DELIRIUM_TEST_ENV_ROOT")?;
+        let path = PathBuf::from_iter([&root, "processors"]);
+        let mut project = ProjectBuilder::new(&path)?.project("test", "x86:LE:32:default", "gcc")?;
+        project.add_region_mapping_with(
+            "text",
+            0x1000u32,
+            Endian::Little,
+            vec![0xb8, 0x01, 0x00, 0x00, 0x00, 0x90, 0xc3],
+        )?;
+        Ok(project)
+    }
+
+    #[test]
+    fn test_add_blk_at_insn_boundary() -> Result<(), Box<dyn std::error::Error>> {
+        let mut project = x86_project()?;
+
+        let group = project.add_blk(0x1000u32)?;
+        assert!(!group.is_empty());
+
+        // the group is split at the nop, rather than re-lifted
+        let tail = project.add_blk(0x1005u32)?;
+        assert!(!tail.is_empty());
+        assert_eq!(&group[group.len() - tail.len()..], &tail[..]);
+
+        let head = project.add_blk(0x1000u32)?;
+        assert_eq!(head, group[..group.len() - tail.len()]);
+
+        assert_eq!(project.blk_group_containing(&Addr::from(0x1004u32)), Some(&Addr::from(0x1000u32)));
+        assert_eq!(project.blk_group_containing(&Addr::from(0x1006u32)), Some(&Addr::from(0x1005u32)));
+        assert_eq!(project.conflicts().count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_blk_already_present() -> Result<(), Box<dyn std::error::Error>> {
+        let mut project = x86_project()?;
+
+        let group = project.add_blk(0x1000u32)?;
+        let blks = project.blks().count();
+
+        assert_eq!(project.add_blk(0x1000u32)?, group);
+        assert_eq!(project.blks().count(), blks);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_blk_mid_insn() -> Result<(), Box<dyn std::error::Error>> {
+        let mut project = x86_project()?;
+
+        let group = project.add_blk(0x1000u32)?;
+
+        // the immediate of the mov is decoded as a separate group, which
+        // conflicts with the mov's
+        let overlapping = project.add_blk(0x1002u32)?;
+        assert!(!overlapping.is_empty());
+        assert!(overlapping.iter().all(|id| !group.contains(id)));
+        assert_eq!(project.add_blk(0x1000u32)?, group);
+
+        let conflict = Conflict {
+            first: Addr::from(0x1000u32),
+            second: Addr::from(0x1002u32),
+        };
+        assert!(project.conflicts().any(|c| *c == conflict));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sweep_data_region() -> Result<(), Box<dyn std::error::Error>> {
+        let mut project = x86_project()?;
+        project.add_region_mapping_with_permissions(
+            "data",
+            0x2000u32,
+            Endian::Little,
+            Permissions::new(true, true, false),
+            vec![0x90; 16],
+        )?;
+
+        // the region is skipped as a whole, rather than at every step
+        let region = project.memory.find_region(&Addr::from(0x2000u32)).unwrap().id();
+        let sweep = project.sweep_region(region).unwrap();
+        assert!(sweep.blks().is_empty());
+        assert_eq!(sweep.skipped(), 0);
+
+        Ok(())
+    }
+
+    // movs r0, #1 @ 0x1000; bx lr @ 0x1002, in Thumb
+    pub(super) fn thumb_project<'r>() -> Result<Entity<Project<'r>>, Box<dyn std::error::Error>> {
+        let root = env::var("DELIRIUM_TEST_ENV_ROOT")?;
+        let path = PathBuf::from_iter([&root, "processors"]);
+        let mut project = ProjectBuilder::new(&path)?.project("test", "ARM:LE:32:v7", "default")?;
+        project.add_region_mapping_with("text", 0x1000u32, Endian::Little, vec![0x01, 0x20, 0x70, 0x47])?;
+        Ok(project)
+    }
+
+    #[test]
+    fn test_global_thumb_context() -> Result<(), Box<dyn std::error::Error>> {
+        // no mode is given for 0x1000, so the context set stands
+        let mut project = thumb_project()?;
+        project.set_context("TMode", 1, None)?;
+        assert_eq!(project.isa_mode(&Addr::from(0x1000u32)), None);
+
+        let group = project.add_blk(0x1000u32)?;
+        let first = project.blk(group[0]).unwrap();
+        assert_eq!(first.source().map(|source| source.len()), Some(2));
+        assert_eq!(project.lifter().isa_mode(&project.disassembly_context, None), Some(IsaMode::Thumb));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_explicit_isa_mode_precedence() -> Result<(), Box<dyn std::error::Error>> {
+        let mut project = thumb_project()?;
+
+        // a mode propagated to 0x1000 does not override that given for it
+        project.set_isa_mode(0x1000u32, IsaMode::Thumb);
+        project.isa_entries.insert(Addr::from(0x1000u32), IsaMode::Arm);
+        assert_eq!(project.isa_mode(&Addr::from(0x1000u32)), Some(IsaMode::Thumb));
+
+        // but does override that given for an address before it
+        project.isa_entries.insert(Addr::from(0x1002u32), IsaMode::Arm);
+        assert_eq!(project.isa_mode(&Addr::from(0x1002u32)), Some(IsaMode::Arm));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use rayon::prelude::*;
+
+use crate::ir::{Addr, Blk, Project};
+use crate::ir::project::ProjectError;
+use crate::lift::{IsaMode, LifterError};
+use crate::prelude::{Entity, Id};
+
+// a group of Blks lifted, but not yet indexed by the Project: the size hint
+// it was lifted with, its Blks, and the number of bytes lifted
+type Lifted = (Option<usize>, Vec<Entity<Blk>>, usize);
+
+impl<'r> Project<'r> {
+    /// Lifts the Blks starting at each of `addrs` (as by `add_blk`) across
+    /// threads, returning the ids of the Blks starting at each address.
+    ///
+    /// Each thread lifts with its own copy of the Project's disassembly
+    /// context; the groups of Blks lifted are merged into the Project in
+    /// address order, so that addresses within groups lifted earlier split
+    /// them rather than duplicating their instructions (the Blks lifted for
+    /// such addresses are discarded). Addresses that are not mapped map to
+    /// no Blks. The groups are only merged if every address is lifted
+    /// without error; otherwise, the first error is returned, and the
+    /// Project is left unchanged.
+    pub fn add_blks_parallel(
+        &mut self,
+        addrs: impl IntoIterator<Item = Addr>,
+    ) -> Result<BTreeMap<Addr, Vec<Id<Blk>>>, ProjectError> {
+        let addrs = addrs.into_iter().collect::<BTreeSet<_>>();
+
+        let batches = addrs
+            .iter()
+            .filter(|addr| !self.blk_groups.contains_key(*addr))
+            .map(|addr| vec![addr.clone()])
+            .collect::<Vec<_>>();
+
+        let lifted = self
+            .lift_batches(batches, &BTreeMap::new())?
+            .into_iter()
+            .flatten()
+            .collect::<BTreeMap<_, _>>();
+
+        Ok(self.merge_lifted(addrs, lifted))
+    }
+
+    /// Lifts the Blks reachable from each of `roots` (as by
+    /// `explore_from`) across threads, returning the ids of the Blks
+    /// reached.
+    ///
+    /// Work is divided by function: each of `roots`, and each fixed target
+    /// of a call reached, is explored by following its branches, and each
+    /// frontier of a function's traversal is lifted by a single thread with
+    /// its own copy of the Project's disassembly context. The Blks lifted
+    /// are only merged into the Project (see `add_blks_parallel`) once the
+    /// traversal completes, and only if every address is lifted without
+    /// error; otherwise, the first error is returned, and the Project is
+    /// left unchanged.
+    pub fn explore_parallel(
+        &mut self,
+        roots: impl IntoIterator<Item = Addr>,
+    ) -> Result<Vec<Id<Blk>>, ProjectError> {
+        let mut visited = BTreeSet::new();
+        let mut order = Vec::new();
+
+        // the frontier of the traversal of each function
+        let mut functions = roots
+            .into_iter()
+            .filter(|root| visited.insert(root.clone()))
+            .map(|root| vec![root])
+            .collect::<Vec<_>>();
+
+        let mut lifted = BTreeMap::<Addr, Lifted>::new();
+        let mut modes = BTreeMap::new();
+
+        while !functions.is_empty() {
+            let batches = functions
+                .iter()
+                .map(|frontier| {
+                    frontier
+                        .iter()
+                        .filter(|addr| !self.blk_groups.contains_key(*addr))
+                        .filter(|addr| self.memory.find_region(addr).is_some())
+                        .cloned()
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+
+            for (addr, group) in self.lift_batches(batches, &modes)?.into_iter().flatten() {
+                let mode = self
+                    .staged_isa_mode(&addr, &modes)
+                    .or_else(|| self.lifter.isa_mode(&self.disassembly_context, Some(&addr)));
+                if let Some(mode) = mode {
+                    for blk in group.1.iter() {
+                        for (target, mode) in Self::target_isa_modes(blk, mode) {
+                            // modes given explicitly are not overridden
+                            if self.isa_ranges.contains_key(&target) {
+                                continue
+                            }
+                            modes.entry(target).or_insert(mode);
+                        }
+                    }
+                }
+                lifted.insert(addr, group);
+            }
+
+            let mut callees = Vec::new();
+
+            for frontier in functions.iter_mut() {
+                let mut next = Vec::new();
+
+                for addr in frontier.drain(..) {
+                    let (branches, calls) = if let Some(group) = self.blk_groups.get(&addr) {
+                        self.group_flows(group)
+                    } else if let Some((_, blks, _)) = lifted.get(&addr) {
+                        self.blk_flows(blks.iter().map(|blk| &**blk))
+                    } else {
+                        continue
+                    };
+
+                    let suggested = self
+                        .blk_oracle
+                        .as_ref()
+                        .map(|oracle| oracle.blk_jmps(&addr))
+                        .unwrap_or_default();
+
+                    for target in branches.into_iter().chain(suggested) {
+                        if self.memory.find_region(&target).is_some() && visited.insert(target.clone()) {
+                            next.push(target);
+                        }
+                    }
+
+                    for target in calls {
+                        if self.memory.find_region(&target).is_some() && visited.insert(target.clone()) {
+                            callees.push(vec![target]);
+                        }
+                    }
+
+                    order.push(addr);
+                }
+
+                *frontier = next;
+            }
+
+            functions.retain(|frontier| !frontier.is_empty());
+            functions.extend(callees);
+        }
+
+        let groups = self.merge_lifted(order.iter().cloned(), lifted);
+
+        let mut seen = BTreeSet::new();
+        let mut reached = order
+            .iter()
+            .flat_map(|addr| groups[addr].iter().copied())
+            .collect::<Vec<_>>();
+        reached.retain(|id| seen.insert(*id));
+
+        Ok(reached)
+    }
+
+    // the mode of addr, given the modes of the targets of the Blks lifted
+    // but not yet merged
+    fn staged_isa_mode(&self, addr: &Addr, modes: &BTreeMap<Addr, IsaMode>) -> Option<IsaMode> {
+        if !self.lifter.is_arm() {
+            return None
+        }
+
+        self.blk_oracle
+            .as_ref()
+            .and_then(|oracle| oracle.blk_isa_mode(addr))
+            .or_else(|| self.isa_ranges.get(addr).copied())
+            .or_else(|| self.isa_entries.get(addr).copied())
+            .or_else(|| modes.get(addr).copied())
+            .or_else(|| self.isa_mode(addr))
+    }
+
+    // lifts the addresses of each batch in turn within a thread, with its
+    // own copy of the disassembly context; addresses that are not mapped
+    // by executable regions are skipped
+    fn lift_batches(
+        &self,
+        batches: Vec<Vec<Addr>>,
+        modes: &BTreeMap<Addr, IsaMode>,
+    ) -> Result<Vec<Vec<(Addr, Lifted)>>, ProjectError> {
+        // the oracle need not be thread-safe, so size hints (and modes) are
+        // taken up front
+        let batches = batches
+            .into_iter()
+            .map(|batch| {
+                batch
+                    .into_iter()
+                    .filter(|addr| self.memory.find_executable_region(addr).is_some())
+                    .map(|addr| {
+                        let size_hint = self.blk_oracle.as_ref().and_then(|o| o.blk_size(&addr));
+                        let mode = self.staged_isa_mode(&addr, modes);
+                        (addr, size_hint, mode)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter(|batch| !batch.is_empty())
+            .collect::<Vec<_>>();
+
+        let lifter = &self.lifter;
+        let memory = &self.memory;
+        let context = &self.disassembly_context;
+
+        batches
+            .into_par_iter()
+            .map(|batch| {
+                let mut ctxt = context.clone();
+                batch
+                    .into_iter()
+                    .map(|(addr, size_hint, mode)| {
+                        // unwrap is safe here: batches only hold executable addresses
+                        let bytes = memory.read_bytes_from(&addr)?.unwrap();
+                        let (blks, size) = match lifter.lift_blk_extent_in(&mut ctxt, &addr, &bytes, size_hint, mode) {
+                            Ok(lifted) => lifted,
+                            Err(LifterError::Undecodable { .. }) => (Vec::new(), 0),
+                            Err(e) => return Err(e.into()),
+                        };
+                        Ok((addr, (size_hint, blks, size)))
+                    })
+                    .collect::<Result<Vec<_>, ProjectError>>()
+            })
+            .collect()
+    }
+
+    // merges the groups lifted into the Project in address order, returning
+    // the ids of the Blks starting at each of addrs
+    fn merge_lifted(
+        &mut self,
+        addrs: impl IntoIterator<Item = Addr>,
+        mut lifted: BTreeMap<Addr, Lifted>,
+    ) -> BTreeMap<Addr, Vec<Id<Blk>>> {
+        let addrs = addrs.into_iter().collect::<BTreeSet<_>>();
+        let mut groups = BTreeMap::new();
+
+        for addr in addrs {
+            let group = if let Some(group) = self.blk_groups.get(&addr) {
+                group.clone()
+            } else if let Some(group) = self.split_blk_group(&addr) {
+                group
+            } else if let Some((size_hint, blks, size)) = lifted.remove(&addr) {
+                self.index_lifted_group(addr.clone(), size_hint, blks, size)
+            } else {
+                Vec::new()
+            };
+            groups.insert(addr, group);
+        }
+
+        groups
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::path::PathBuf;
+
+    use crate::ir::{Addr, Blk, Project, ProjectBuilder};
+    use crate::prelude::{Endian, Entity};
+
+    // call 0x1010 @ 0x1000; jz 0x1008 @ 0x1005; nop @ 0x1007; ret @ 0x1008;
+    // xor eax, eax @ 0x1010; ret @ 0x1012
+    fn x86_project<'r>() -> Result<Entity<Project<'r>>, Box<dyn std::error::Error>> {
+        let root = env::var("DELIRIUM_TEST_ENV_ROOT")?;
+        let path = PathBuf::from_iter([&root, "processors"]);
+        let mut project = ProjectBuilder::new(&path)?.project("test", "x86:LE:32:default", "gcc")?;
+
+        let mut bytes = vec![0xe8, 0x0b, 0x00, 0x00, 0x00, 0x74, 0x01, 0x90, 0xc3];
+        bytes.resize(0x10, 0x90);
+        bytes.extend([0x31, 0xc0, 0xc3]);
+
+        project.add_region_mapping_with("text", 0x1000u32, Endian::Little, bytes)?;
+        Ok(project)
+    }
+
+    // the addresses of the Blks of each group, and the number of their
+    // statements
+    fn groups(project: &Project) -> Vec<(Addr, Vec<(Option<Addr>, usize, usize)>)> {
+        let shape = |blk: &Blk| (blk.addr().cloned(), blk.defs().len(), blk.jmps().len());
+        project
+            .blk_groups
+            .iter()
+            .map(|(addr, group)| (addr.clone(), group.iter().map(|id| shape(&project.blks[id])).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential() -> Result<(), Box<dyn std::error::Error>> {
+        let mut sequential = x86_project()?;
+        let mut parallel = x86_project()?;
+
+        let reached = sequential.explore_from(Addr::from(0x1000u32))?;
+        let reached_parallel = parallel.explore_parallel([Addr::from(0x1000u32)])?;
+
+        assert_eq!(reached.len(), reached_parallel.len());
+        assert_eq!(groups(&sequential), groups(&parallel));
+        assert!(parallel.blk_groups.contains_key(&Addr::from(0x1010u32)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parallel_global_thumb_context() -> Result<(), Box<dyn std::error::Error>> {
+        let mut project = crate::ir::project::test::thumb_project()?;
+        project.set_context("TMode", 1, None)?;
+
+        // no mode is given for 0x1000, so the context set stands
+        let groups = project.add_blks_parallel([Addr::from(0x1000u32)])?;
+        let first = &project.blks[&groups[&Addr::from(0x1000u32)][0]];
+        assert_eq!(first.source().map(|source| source.len()), Some(2));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::BTreeSet;
+
+use crate::ir::pattern::for_each_subexpr;
+use crate::ir::{Addr, Bindings, Blk, Def, Expr, Jmp, Pattern, Project, Sub};
+use crate::prelude::{Entity, Id, Identifiable};
+
+/// The statement a pattern was matched within.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchSite {
+    Def(Id<Def>),
+    Jmp(Id<Jmp>),
+}
+
+/// A match of a pattern found by `Project::find_semantic`.
+#[derive(Debug, Clone)]
+pub struct SemanticMatch {
+    sub: Option<Id<Sub>>,
+    blk: Id<Blk>,
+    site: MatchSite,
+    addr: Option<Addr>,
+    expr: Option<Expr>,
+    bindings: Bindings,
+}
+
+impl SemanticMatch {
+    /// The Sub containing the match, if the match is within a Sub.
+    pub fn sub(&self) -> Option<Id<Sub>> {
+        self.sub
+    }
+
+    pub fn blk(&self) -> Id<Blk> {
+        self.blk
+    }
+
+    pub fn site(&self) -> MatchSite {
+        self.site
+    }
+
+    /// The address of the instruction the statement matched was lifted
+    /// from, if known (see `Project::source_loc`).
+    pub fn addr(&self) -> Option<&Addr> {
+        self.addr.as_ref()
+    }
+
+    /// The expression matched, for expression patterns.
+    pub fn expr(&self) -> Option<&Expr> {
+        self.expr.as_ref()
+    }
+
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+}
+
+impl<'r> Project<'r> {
+    /// Finds the code of the Project matching `pattern` (see `Pattern`),
+    /// e.g., the multiplications by `0x5bd1e995` that identify MurmurHash:
+    ///
+    /// ```ignore
+    /// let pattern = ExprPattern::bin_op(BinOp::Mul, ExprPattern::bind("x"), ExprPattern::val(0x5bd1e995));
+    /// let matches = project.find_semantic(Pattern::from(pattern).commutative(true));
+    /// ```
+    ///
+    /// The Blks of the Project's Subs are searched (as they may have been
+    /// transformed since they were lifted), followed by the Project's Blks
+    /// that are not within a Sub. Each sub-expression of each statement
+    /// that matches is reported, outermost first.
+    pub fn find_semantic(&self, pattern: impl Into<Pattern>) -> Vec<SemanticMatch> {
+        let pattern = pattern.into();
+        let mut matches = Vec::new();
+        let mut seen = BTreeSet::new();
+
+        for (id, sub) in self.subs.iter() {
+            for blk in sub.blks() {
+                seen.insert(blk.id());
+                self.find_semantic_in(&pattern, Some(*id), blk, &mut matches);
+            }
+        }
+
+        for (id, blk) in self.blks.iter() {
+            if !seen.contains(id) {
+                self.find_semantic_in(&pattern, None, blk, &mut matches);
+            }
+        }
+
+        matches
+    }
+
+    fn find_semantic_in(&self, pattern: &Pattern, sub: Option<Id<Sub>>, blk: &Entity<Blk>, matches: &mut Vec<SemanticMatch>) {
+        let found = |site: MatchSite, expr: Option<&Expr>, bindings: Bindings| {
+            let addr = match site {
+                MatchSite::Def(id) => self.source_loc(id),
+                MatchSite::Jmp(id) => self.source_loc(id),
+            }
+            .or(blk.source())
+            .map(|source| source.addr().clone());
+
+            SemanticMatch {
+                sub,
+                blk: blk.id(),
+                site,
+                addr,
+                expr: expr.cloned(),
+                bindings,
+            }
+        };
+
+        for def in blk.defs() {
+            let site = MatchSite::Def(def.id());
+            if !pattern.is_expr() {
+                if let Some(bindings) = pattern.matches_def(def) {
+                    matches.push(found(site, None, bindings));
+                }
+                continue
+            }
+
+            let exprs: Vec<&Expr> = match **def {
+                Def::Assign(_, ref expr) | Def::Assume(ref expr) => vec![expr],
+                Def::Store(ref addr, ref value, _, _) => vec![addr, value],
+            };
+            for expr in exprs {
+                for_each_subexpr(expr, &mut |expr| {
+                    if let Some(bindings) = pattern.matches_expr(expr) {
+                        matches.push(found(site, Some(expr), bindings));
+                    }
+                });
+            }
+        }
+
+        if !pattern.is_expr() {
+            return
+        }
+
+        for jmp in blk.jmps() {
+            let site = MatchSite::Jmp(jmp.id());
+            let exprs: Vec<&Expr> = match **jmp {
+                Jmp::Branch(ref loc) | Jmp::Return(ref loc) => loc.computed().into_iter().collect(),
+                Jmp::CBranch(ref loc, ref cond) => loc.computed().into_iter().chain(Some(cond)).collect(),
+                Jmp::Call(ref loc, ref args) => loc.computed().into_iter().chain(args.iter()).collect(),
+                Jmp::Intrinsic(_, ref args) => args.iter().collect(),
+            };
+            for expr in exprs {
+                for_each_subexpr(expr, &mut |expr| {
+                    if let Some(bindings) = pattern.matches_expr(expr) {
+                        matches.push(found(site, Some(expr), bindings));
+                    }
+                });
+            }
+        }
+    }
+}
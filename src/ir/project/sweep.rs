@@ -0,0 +1,232 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::ir::{Addr, Blk, Project, Region};
+use crate::prelude::{Id, Identifiable};
+
+/// How `Project::sweep_region` sweeps a region.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SweepConfig {
+    step: Option<usize>,
+    prefer_existing: bool,
+}
+
+impl SweepConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The distance to advance after an address that cannot be lifted;
+    /// defaults to the lifter's instruction alignment, or one byte.
+    pub fn step(&mut self, step: usize) -> &mut Self {
+        self.step = Some(step.max(1));
+        self
+    }
+
+    /// If set, the sweep resynchronises with the Blks already lifted (e.g.,
+    /// by recursive descent) when it reaches an address in the middle of
+    /// one of their instructions, rather than lifting at that address.
+    pub fn prefer_existing(&mut self, prefer: bool) -> &mut Self {
+        self.prefer_existing = prefer;
+        self
+    }
+}
+
+/// A pair of groups of Blks (see `Project::add_blk`) whose instructions
+/// overlap without sharing boundaries, i.e., conflicting decodings of the
+/// same bytes.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Conflict {
+    pub first: Addr,
+    pub second: Addr,
+}
+
+/// The result of sweeping a region.
+#[derive(Debug, Clone, Default)]
+pub struct Sweep {
+    blks: Vec<Id<Blk>>,
+    conflicts: BTreeSet<Conflict>,
+    skipped: usize,
+}
+
+impl Sweep {
+    /// The Blks lifted by, or reached by, the sweep.
+    pub fn blks(&self) -> &[Id<Blk>] {
+        &self.blks
+    }
+
+    /// The conflicting decodings found by the sweep.
+    pub fn conflicts(&self) -> &BTreeSet<Conflict> {
+        &self.conflicts
+    }
+
+    /// The number of addresses at which nothing could be lifted.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+impl<'r> Project<'r> {
+    /// Uses `config` when sweeping `region`.
+    pub fn set_sweep_config(&mut self, region: impl Identifiable<Region<'r>>, config: SweepConfig) {
+        self.sweep_configs.insert(region.id(), config);
+    }
+
+    pub fn sweep_config(&self, region: impl Identifiable<Region<'r>>) -> Option<&SweepConfig> {
+        self.sweep_configs.get(&region.id())
+    }
+
+    // the start of each instruction of the group starting at addr
+    fn insn_starts(&self, addr: &Addr) -> BTreeSet<Addr> {
+        self.blk_groups
+            .get(addr)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.blks.get(id)?.addr().cloned())
+            .collect()
+    }
+
+    // the groups overlapping the group starting at start (and ending at
+    // end) whose instructions do not share its boundaries
+    pub(super) fn conflicts_with(&self, start: &Addr, end: &Addr) -> Vec<Conflict> {
+        if start >= end {
+            return Vec::new()
+        }
+
+        let starts = self.insn_starts(start);
+        self.blk_extents
+            .find_all(&(start.clone()..end.clone()).into())
+            .into_iter()
+            .map(|entry| entry.value().clone())
+            .filter(|other| other != start)
+            .filter(|other| {
+                // insn starts of other within the extent of start's group
+                // must also be insn starts of start's group, and vice versa
+                let others = self.insn_starts(other);
+                let within = |addr: &&Addr| *addr >= start && *addr < end;
+                others.iter().filter(within).any(|addr| !starts.contains(addr))
+                    || (other < start && !others.contains(start))
+            })
+            .map(|other| {
+                let (first, second) = if other < *start {
+                    (other, start.clone())
+                } else {
+                    (start.clone(), other)
+                };
+                Conflict { first, second }
+            })
+            .collect()
+    }
+
+    /// Lifts the Blks of `region` by linear sweep: starting at the region's
+    /// start, each group of Blks is lifted (see `add_blk`) and the sweep
+    /// continues from its end; where nothing can be lifted, the sweep
+    /// advances by the region's configured step (see `SweepConfig`).
+    ///
+    /// Blks already lifted (e.g., by `explore_from`) are reused, and groups
+    /// whose instructions overlap without sharing boundaries are recorded
+    /// as conflicts (see `Project::conflicts`); regions that are not
+    /// executable are not swept. Returns None if the Project has no region
+    /// with the id of `region`.
+    pub fn sweep_region(&mut self, region: impl Identifiable<Region<'r>>) -> Option<Sweep> {
+        let region = region.id();
+        let (interval, executable) = self
+            .memory
+            .regions()
+            .iter()
+            .find(|entry| entry.value().id() == region)
+            .map(|entry| (entry.interval().clone(), entry.value().permissions().is_executable()))?;
+
+        if !executable {
+            log::debug!("not sweeping non-executable region {}", self.memory.find_region(interval.start())?.name());
+            return Some(Sweep::default())
+        }
+
+        let config = self.sweep_configs.get(&region).cloned().unwrap_or_default();
+        let step = config
+            .step
+            .or_else(|| self.lifter.instruction_alignment().map(|alignment| alignment as usize))
+            .unwrap_or(1);
+
+        let mut sweep = Sweep::default();
+        let mut addr = interval.start().clone();
+        let end = interval.end().clone();
+
+        while addr < end {
+            if config.prefer_existing && !self.blk_groups.contains_key(&addr) {
+                // resynchronise with the group covering addr, unless addr
+                // starts one of its instructions
+                let covering = self
+                    .blk_extents
+                    .find_all(&(addr.clone()..&addr + 1usize).into())
+                    .into_iter()
+                    .map(|entry| (entry.value().clone(), entry.interval().end().clone()))
+                    .find(|(start, _)| !self.insn_starts(start).contains(&addr));
+
+                if let Some((_, next)) = covering {
+                    addr = next;
+                    continue
+                }
+            }
+
+            let group = match self.add_blk(addr.clone()) {
+                Ok(group) => group,
+                Err(e) => {
+                    log::trace!("could not lift block at {}: {}", addr, e);
+                    Vec::new()
+                }
+            };
+
+            if group.is_empty() {
+                sweep.skipped += 1;
+                addr = &addr + step;
+                continue
+            }
+
+            // add_blk may have split an existing group at addr
+            let next = self
+                .blk_extents
+                .find_all(&(addr.clone()..&addr + 1usize).into())
+                .into_iter()
+                .find(|entry| *entry.value() == addr)
+                .map(|entry| entry.interval().end().clone())
+                .filter(|next| *next > addr)
+                .unwrap_or_else(|| &addr + step);
+
+            for conflict in self.conflicts_with(&addr, &next) {
+                self.decoding_conflicts.insert(conflict.clone());
+                sweep.conflicts.insert(conflict);
+            }
+
+            sweep.blks.extend(group);
+            addr = next;
+        }
+
+        let mut seen = BTreeSet::new();
+        sweep.blks.retain(|id| seen.insert(*id));
+
+        Some(sweep)
+    }
+
+    /// The conflicting decodings found by sweeps of the Project's regions,
+    /// or by lifting Blks within the instructions of others (see
+    /// `add_blk`).
+    pub fn conflicts(&self) -> impl Iterator<Item = &Conflict> {
+        self.decoding_conflicts.iter()
+    }
+
+    /// The groups of Blks that conflict with the group starting at `addr`.
+    pub fn conflicts_at(&self, addr: &Addr) -> BTreeMap<Addr, &Conflict> {
+        self.decoding_conflicts
+            .iter()
+            .filter_map(|conflict| {
+                if conflict.first == *addr {
+                    Some((conflict.second.clone(), conflict))
+                } else if conflict.second == *addr {
+                    Some((conflict.first.clone(), conflict))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use crate::ir::{Addr, Blk, Project, Sub};
+use crate::ir::project::ProjectError;
+use crate::prelude::{Entity, Erased, Id, Identifiable};
+use crate::prelude::intervals::Interval;
+
+/// A dependent (e.g., the result of an analysis) registered with
+/// `Project::add_watch`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WatchId(u64);
+
+/// The effects of a change to a Project's memory or Blks; see
+/// `Project::patch_bytes` and `Project::relift_blk`.
+#[derive(Debug, Clone, Default)]
+pub struct Invalidation {
+    removed: Vec<Id<Blk>>,
+    relifted: Vec<Id<Blk>>,
+    subs: BTreeSet<Id<Sub>>,
+    watches: BTreeSet<WatchId>,
+}
+
+impl Invalidation {
+    /// The Blks removed from the Project, as their instructions changed.
+    pub fn removed_blks(&self) -> &[Id<Blk>] {
+        &self.removed
+    }
+
+    /// The Blks lifted in place of those removed.
+    pub fn relifted_blks(&self) -> &[Id<Blk>] {
+        &self.relifted
+    }
+
+    /// The Subs containing the Blks removed; they are not re-lifted.
+    pub fn stale_subs(&self) -> &BTreeSet<Id<Sub>> {
+        &self.subs
+    }
+
+    /// The dependents invalidated by the change.
+    pub fn watches(&self) -> &BTreeSet<WatchId> {
+        &self.watches
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.watches.is_empty()
+    }
+}
+
+// the dependents registered with a Project, and what they depend upon
+#[derive(Debug, Clone, Default)]
+pub(super) struct Watches {
+    next: u64,
+    names: BTreeMap<WatchId, Arc<str>>,
+    // ranges keyed by their start, with their (exclusive) end
+    ranges: BTreeMap<Addr, Vec<(Addr, WatchId)>>,
+    entities: BTreeMap<Id<Erased>, BTreeSet<WatchId>>,
+    invalidated: BTreeSet<WatchId>,
+}
+
+impl Watches {
+    fn overlapping(&self, start: &Addr, end: &Addr) -> BTreeSet<WatchId> {
+        self.ranges
+            .range(..end.clone())
+            .flat_map(|(_, ranges)| ranges.iter())
+            .filter(|(range_end, _)| range_end > start)
+            .map(|(_, watch)| *watch)
+            .collect()
+    }
+
+    fn forget(&mut self, watch: WatchId) {
+        self.ranges.retain(|_, ranges| {
+            ranges.retain(|(_, id)| *id != watch);
+            !ranges.is_empty()
+        });
+        self.entities.retain(|_, watches| {
+            watches.remove(&watch);
+            !watches.is_empty()
+        });
+    }
+}
+
+impl<'r> Project<'r> {
+    /// Registers a dependent, named `name` for diagnostics; the addresses
+    /// and entities it depends upon are registered via `watch_range` and
+    /// `watch_entity`. When those change, the dependent is invalidated
+    /// (see `is_invalidated`), rather than all dependents.
+    pub fn add_watch(&mut self, name: impl Into<Arc<str>>) -> WatchId {
+        let watch = WatchId(self.watches.next);
+        self.watches.next += 1;
+        self.watches.names.insert(watch, name.into());
+        watch
+    }
+
+    /// Unregisters `watch`; returns false if it is not registered.
+    pub fn remove_watch(&mut self, watch: WatchId) -> bool {
+        if self.watches.names.remove(&watch).is_none() {
+            return false
+        }
+        self.watches.forget(watch);
+        self.watches.invalidated.remove(&watch);
+        true
+    }
+
+    pub fn watch_name(&self, watch: WatchId) -> Option<&str> {
+        self.watches.names.get(&watch).map(|name| &**name)
+    }
+
+    /// Makes `watch` depend upon the `size` bytes at `addr`; returns false
+    /// if `watch` is not registered.
+    pub fn watch_range(&mut self, watch: WatchId, addr: impl Into<Addr>, size: usize) -> bool {
+        if !self.watches.names.contains_key(&watch) {
+            return false
+        }
+
+        if size == 0 {
+            return true
+        }
+
+        let addr = addr.into();
+        let end = &addr + size;
+        self.watches.ranges.entry(addr).or_default().push((end, watch));
+        true
+    }
+
+    /// Makes `watch` depend upon `entity` (e.g., a Blk or a Sub); returns
+    /// false if `watch` is not registered.
+    pub fn watch_entity<V>(&mut self, watch: WatchId, entity: impl Identifiable<V>) -> bool {
+        if !self.watches.names.contains_key(&watch) {
+            return false
+        }
+
+        self.watches.entities.entry(entity.id().erase()).or_default().insert(watch);
+        true
+    }
+
+    /// Makes `watch` depend upon `sub`, its Blks, and the instructions they
+    /// were lifted from.
+    pub fn watch_sub(&mut self, watch: WatchId, sub: &Entity<Sub>) -> bool {
+        if !self.watch_entity(watch, sub.id()) {
+            return false
+        }
+
+        for blk in sub.blks() {
+            self.watch_entity(watch, blk.id());
+        }
+
+        for start in self.sub_groups(sub) {
+            if let Some(size) = self.group_end(&start).and_then(|end| end.absolute_difference(&start)) {
+                self.watch_range(watch, start, size);
+            }
+        }
+        true
+    }
+
+    pub fn is_invalidated(&self, watch: WatchId) -> bool {
+        self.watches.invalidated.contains(&watch)
+    }
+
+    /// The dependents invalidated since they were last revalidated.
+    pub fn invalidated(&self) -> impl Iterator<Item = WatchId> + '_ {
+        self.watches.invalidated.iter().copied()
+    }
+
+    /// Marks `watch` as valid, and forgets what it depended upon, so that
+    /// its dependencies can be re-registered as it is recomputed.
+    pub fn revalidate(&mut self, watch: WatchId) {
+        if self.watches.invalidated.remove(&watch) {
+            self.watches.forget(watch);
+        }
+    }
+
+    /// Invalidates the dependents of the `size` bytes at `addr`, returning
+    /// those not already invalidated.
+    pub fn invalidate_range(&mut self, addr: impl Into<Addr>, size: usize) -> BTreeSet<WatchId> {
+        let addr = addr.into();
+        let end = &addr + size;
+        let watches = self.watches.overlapping(&addr, &end);
+        self.mark_invalidated(watches)
+    }
+
+    /// Invalidates the dependents of `entity`, returning those not already
+    /// invalidated.
+    pub fn invalidate_entity<V>(&mut self, entity: impl Identifiable<V>) -> BTreeSet<WatchId> {
+        let watches = self.watches
+            .entities
+            .get(&entity.id().erase())
+            .cloned()
+            .unwrap_or_default();
+        self.mark_invalidated(watches)
+    }
+
+    fn mark_invalidated(&mut self, watches: BTreeSet<WatchId>) -> BTreeSet<WatchId> {
+        watches
+            .into_iter()
+            .filter(|watch| self.watches.invalidated.insert(*watch))
+            .collect()
+    }
+
+    /// Overwrites the bytes at `addr` with `bytes`. The groups of Blks (see
+    /// `add_blk`) whose instructions overlap the bytes written are removed
+    /// and re-lifted, and the dependents of the bytes, of the removed Blks,
+    /// and of the Subs containing them, are invalidated.
+    pub fn patch_bytes(&mut self, addr: impl Into<Addr>, bytes: &[u8]) -> Result<Invalidation, ProjectError> {
+        let addr = addr.into();
+        if !self.memory.write_bytes(&addr, bytes)? {
+            return Err(ProjectError::Unmapped(addr))
+        }
+
+        let starts = self.blk_extents
+            .find_all(&Interval::from(addr.clone()..&addr + bytes.len()))
+            .into_iter()
+            .map(|entry| entry.value().clone())
+            .collect::<BTreeSet<_>>();
+
+        let mut invalidation = self.relift_groups(starts);
+        invalidation.watches.extend(self.invalidate_range(addr, bytes.len()));
+
+        Ok(invalidation)
+    }
+
+    /// Removes and re-lifts the group of Blks whose instructions cover
+    /// `addr` (e.g., after a change to the Project's Lifter or hints), and
+    /// invalidates the dependents of the removed Blks and of the Subs
+    /// containing them.
+    pub fn relift_blk(&mut self, addr: impl Into<Addr>) -> Result<Invalidation, ProjectError> {
+        let addr = addr.into();
+        if self.memory.find_region(&addr).is_none() {
+            return Err(ProjectError::Unmapped(addr))
+        }
+
+        let start = self
+            .blk_group_containing(&addr)
+            .cloned()
+            .or_else(|| self.blk_groups.contains_key(&addr).then(|| addr.clone()));
+
+        Ok(self.relift_groups(start.into_iter().collect()))
+    }
+
+    fn relift_groups(&mut self, starts: BTreeSet<Addr>) -> Invalidation {
+        let mut invalidation = Invalidation::default();
+
+        for start in starts.iter() {
+            if let Some(size) = self.group_end(start).and_then(|end| end.absolute_difference(start)) {
+                invalidation.watches.extend(self.invalidate_range(start.clone(), size));
+            }
+            invalidation.removed.extend(self.remove_blk_group(start));
+        }
+
+        let removed = invalidation.removed.iter().copied().collect::<BTreeSet<_>>();
+        invalidation.subs = self.subs
+            .values()
+            .filter(|sub| sub.blks().iter().any(|blk| removed.contains(&blk.id())))
+            .map(|sub| sub.id())
+            .collect();
+
+        for id in removed.iter() {
+            invalidation.watches.extend(self.invalidate_entity(*id));
+        }
+
+        for id in invalidation.subs.clone() {
+            invalidation.watches.extend(self.invalidate_entity(id));
+        }
+
+        for start in starts {
+            match self.add_blk(start.clone()) {
+                Ok(ids) => invalidation.relifted.extend(ids),
+                Err(e) => log::debug!("could not re-lift block at {}: {}", start, e),
+            }
+        }
+
+        invalidation
+    }
+
+    // removes the group of Blks starting at start, returning their ids
+    fn remove_blk_group(&mut self, start: &Addr) -> Vec<Id<Blk>> {
+        let ids = if let Some(ids) = self.blk_groups.remove(start) {
+            ids
+        } else {
+            return Vec::default()
+        };
+
+        if let Some(extent) = self.blk_extents
+            .find_all(&Interval::from(start.clone()..start + 1usize))
+            .into_iter()
+            .find(|entry| entry.value() == start)
+            .map(|entry| entry.interval().clone())
+        {
+            self.blk_extents.remove(&extent);
+        }
+
+        if let Some(id) = self.addr_to_blks.remove(start) {
+            self.blks_to_addr.remove(&id);
+        }
+
+        for id in ids.iter() {
+            self.blks.remove(id);
+        }
+
+        ids
+    }
+}
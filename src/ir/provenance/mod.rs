@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::ir::{Addr, Blk, Def, Jmp, Phi, Sub};
+use crate::prelude::{Annotations, Entity, Erased, Id, Identifiable};
+
+/// An event in the history of an entity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// Created by lifting the instruction at `addr`
+    Lifted { addr: Addr },
+    /// Created by `pass`, applied at step `step`
+    Created { pass: Arc<str>, step: usize },
+    /// Modified by `pass`, applied at step `step`; for Blks, this means
+    /// that their statements were added, removed, or reordered
+    Modified { pass: Arc<str>, step: usize },
+}
+
+/// The history of an entity: how it was created, and the passes that have
+/// modified it since. Provenance is recorded as an annotation on the
+/// entities of a Project (see `Project::provenance`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance {
+    events: Vec<Event>,
+}
+
+impl Provenance {
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// The event that created the entity, if recorded.
+    pub fn origin(&self) -> Option<&Event> {
+        self.events
+            .first()
+            .filter(|event| !matches!(event, Event::Modified { .. }))
+    }
+
+    /// The most recent modification of the entity.
+    pub fn last_modified(&self) -> Option<&Event> {
+        self.events
+            .iter()
+            .rev()
+            .find(|event| matches!(event, Event::Modified { .. }))
+    }
+
+    pub fn record(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    fn record_for<V>(annotations: &mut Annotations, entity: impl Identifiable<V>, event: Event) {
+        annotations
+            .get_or_default::<Provenance, V>(entity)
+            .record(event);
+    }
+
+    fn record_blk(annotations: &mut Annotations, blk: &Entity<Blk>, event: &Event) {
+        Self::record_for(annotations, blk, event.clone());
+        for phi in blk.phis() {
+            Self::record_for(annotations, phi, event.clone());
+        }
+        for def in blk.defs() {
+            Self::record_for(annotations, def, event.clone());
+        }
+        for jmp in blk.jmps() {
+            Self::record_for(annotations, jmp, event.clone());
+        }
+    }
+
+    /// Records that `blk` and its statements were lifted from the
+    /// instruction at `addr`.
+    pub fn record_lifted(annotations: &mut Annotations, blk: &Entity<Blk>, addr: &Addr) {
+        Self::record_blk(annotations, blk, &Event::Lifted { addr: addr.clone() });
+    }
+
+    /// Applies `pass` to `sub`, recording the Blks and statements it
+    /// creates or modifies as having been created or modified by the pass
+    /// named `name` at step `step`.
+    pub fn track<R>(
+        annotations: &mut Annotations,
+        name: impl Into<Arc<str>>,
+        step: usize,
+        sub: &mut Sub,
+        pass: impl FnOnce(&mut Sub) -> R,
+    ) -> R {
+        let before = Snapshot::new(sub);
+        let result = pass(sub);
+
+        let name = name.into();
+        let created = Event::Created { pass: name.clone(), step };
+        let modified = Event::Modified { pass: name, step };
+
+        for blk in sub.blks() {
+            let stmts = if let Some(stmts) = before.blks.get(&blk.id()) {
+                stmts
+            } else {
+                Self::record_blk(annotations, blk, &created);
+                continue
+            };
+
+            if *stmts != Snapshot::stmts(blk) {
+                Self::record_for(annotations, blk, modified.clone());
+            }
+
+            for phi in blk.phis() {
+                match before.phis.get(&phi.id()) {
+                    None => Self::record_for(annotations, phi, created.clone()),
+                    Some(old) if old != &**phi => Self::record_for(annotations, phi, modified.clone()),
+                    _ => (),
+                }
+            }
+
+            for def in blk.defs() {
+                match before.defs.get(&def.id()) {
+                    None => Self::record_for(annotations, def, created.clone()),
+                    Some(old) if old != &**def => Self::record_for(annotations, def, modified.clone()),
+                    _ => (),
+                }
+            }
+
+            for jmp in blk.jmps() {
+                match before.jmps.get(&jmp.id()) {
+                    None => Self::record_for(annotations, jmp, created.clone()),
+                    Some(old) if old != &**jmp => Self::record_for(annotations, jmp, modified.clone()),
+                    _ => (),
+                }
+            }
+        }
+
+        result
+    }
+}
+
+struct Snapshot {
+    blks: BTreeMap<Id<Blk>, Vec<Id<Erased>>>,
+    phis: BTreeMap<Id<Phi>, Phi>,
+    defs: BTreeMap<Id<Def>, Def>,
+    jmps: BTreeMap<Id<Jmp>, Jmp>,
+}
+
+impl Snapshot {
+    fn new(sub: &Sub) -> Self {
+        let mut snapshot = Self {
+            blks: BTreeMap::new(),
+            phis: BTreeMap::new(),
+            defs: BTreeMap::new(),
+            jmps: BTreeMap::new(),
+        };
+
+        for blk in sub.blks() {
+            snapshot.blks.insert(blk.id(), Self::stmts(blk));
+            for phi in blk.phis() {
+                snapshot.phis.insert(phi.id(), (**phi).clone());
+            }
+            for def in blk.defs() {
+                snapshot.defs.insert(def.id(), (**def).clone());
+            }
+            for jmp in blk.jmps() {
+                snapshot.jmps.insert(jmp.id(), (**jmp).clone());
+            }
+        }
+
+        snapshot
+    }
+
+    fn stmts(blk: &Blk) -> Vec<Id<Erased>> {
+        blk.phis()
+            .iter()
+            .map(|phi| phi.id().erase())
+            .chain(blk.defs().iter().map(|def| def.id().erase()))
+            .chain(blk.jmps().iter().map(|jmp| jmp.id().erase()))
+            .collect()
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt::{self, Display};
+use std::sync::Arc;
+
+use crate::ir::Addr;
+
+/// The architectural instruction a Blk (and its statements) was lifted
+/// from: its address and length and, if the Lifter records disassembly
+/// (see `Lifter::set_record_disassembly`), its mnemonic and operands.
+///
+/// Lifted Blks carry the location of their instruction (see `Blk::source`);
+/// Projects also record it as an annotation on each statement lifted (see
+/// `Project::source_loc`), so that statements can be mapped back to their
+/// instructions after passes move them between Blks. `Lifter::disassemble`
+/// gives the locations of instructions without lifting them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLoc {
+    addr: Addr,
+    length: usize,
+    mnemonic: Option<Arc<str>>,
+    operands: Option<Arc<str>>,
+}
+
+impl SourceLoc {
+    pub fn new(addr: impl Into<Addr>, length: usize) -> Self {
+        Self {
+            addr: addr.into(),
+            length,
+            mnemonic: None,
+            operands: None,
+        }
+    }
+
+    pub fn with_disassembly(mut self, mnemonic: impl Into<Arc<str>>, operands: impl Into<Arc<str>>) -> Self {
+        self.mnemonic = Some(mnemonic.into());
+        self.operands = Some(operands.into());
+        self
+    }
+
+    pub fn addr(&self) -> &Addr {
+        &self.addr
+    }
+
+    // the same instruction at addr
+    pub(crate) fn rebased(&self, addr: Addr) -> Self {
+        Self { addr, ..self.clone() }
+    }
+
+    /// The number of bytes of the instruction.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// The address following the instruction.
+    pub fn end(&self) -> Addr {
+        &self.addr + self.length
+    }
+
+    pub fn contains(&self, addr: &Addr) -> bool {
+        *addr >= self.addr && *addr < self.end()
+    }
+
+    pub fn mnemonic(&self) -> Option<&str> {
+        self.mnemonic.as_deref()
+    }
+
+    pub fn operands(&self) -> Option<&str> {
+        self.operands.as_deref()
+    }
+}
+
+impl Display for SourceLoc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.addr)?;
+        if let Some(ref mnemonic) = self.mnemonic {
+            write!(f, ": {}", mnemonic)?;
+            if let Some(operands) = self.operands.as_deref().filter(|operands| !operands.is_empty()) {
+                write!(f, " {}", operands)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,2 +1,173 @@
+use crate::analysis::{ReachingDefinitions, StackFrame, UseDefChains};
+use crate::ir::{Addr, Blk, BlkMerge, Cfg, Jmp, Loc, Var};
+use crate::prelude::{Entity, Id, Identifiable, Tagged};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
 #[derive(Clone)]
-pub struct Sub;
\ No newline at end of file
+pub struct Sub {
+    symbol: Option<Arc<str>>,
+    addr: Option<Addr>,
+    // the first block is the entry
+    blks: Vec<Entity<Blk>>,
+}
+
+impl Tagged for Sub {
+    const TAG: &'static str = "sub";
+}
+
+impl Sub {
+    pub fn new(
+        symbol: impl Into<Option<Arc<str>>>,
+        addr: impl Into<Option<Addr>>,
+        blks: Vec<Entity<Blk>>,
+    ) -> Entity<Self> {
+        Entity::new("sub", Self {
+            symbol: symbol.into(),
+            addr: addr.into(),
+            blks,
+        })
+    }
+
+    pub fn symbol(&self) -> Option<&Arc<str>> {
+        self.symbol.as_ref()
+    }
+
+    pub fn addr(&self) -> Option<&Addr> {
+        self.addr.as_ref()
+    }
+
+    pub fn entry(&self) -> Option<&Entity<Blk>> {
+        self.blks.first()
+    }
+
+    pub fn blks(&self) -> &[Entity<Blk>] {
+        &self.blks
+    }
+
+    pub fn blks_mut(&mut self) -> &mut Vec<Entity<Blk>> {
+        &mut self.blks
+    }
+
+    pub fn blk(&self, id: impl Identifiable<Blk>) -> Option<&Entity<Blk>> {
+        let id = id.id();
+        self.blks.iter().find(|blk| blk.id() == id)
+    }
+
+    pub fn blk_mut(&mut self, id: impl Identifiable<Blk>) -> Option<&mut Entity<Blk>> {
+        let id = id.id();
+        self.blks.iter_mut().find(|blk| blk.id() == id)
+    }
+
+    pub fn add_blk(&mut self, blk: Entity<Blk>) -> Id<Blk> {
+        let id = blk.id();
+        self.blks.push(blk);
+        id
+    }
+
+    /// Merges the Sub's Blks as given by `merge` (see `BlkMerge`); for
+    /// `BlkMerge::Ida`, only the entry and the Blks with multiple
+    /// predecessors are taken to start IDA's blocks (see
+    /// `normalise_blocks_with` and `Project::normalise_blocks`). Returns
+    /// the number of Blks merged into their predecessors.
+    pub fn normalise_blocks(&mut self, merge: BlkMerge) -> usize {
+        self.normalise_blocks_with(merge, &BTreeSet::new())
+    }
+
+    /// As `normalise_blocks`, but for `BlkMerge::Ida`, also takes each of
+    /// `leaders` to start one of IDA's blocks.
+    pub fn normalise_blocks_with(&mut self, merge: BlkMerge, leaders: &BTreeSet<Id<Blk>>) -> usize {
+        if merge == BlkMerge::Strict || self.blks.is_empty() {
+            return 0
+        }
+
+        // the number of flows to each Blk; the entry has an additional
+        // (implicit) predecessor
+        let mut predecessors = BTreeMap::<Id<Blk>, usize>::new();
+        predecessors.insert(self.blks[0].id(), 1);
+        for blk in self.blks.iter() {
+            for jmp in blk.jmps() {
+                match **jmp {
+                    Jmp::Branch(Loc::Resolved(id))
+                    | Jmp::CBranch(Loc::Resolved(id), _)
+                    | Jmp::Call(Loc::Resolved(id), _)
+                    | Jmp::Return(Loc::Resolved(id)) => {
+                        *predecessors.entry(id).or_default() += 1;
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        let order = self.blks.iter().map(|blk| blk.id()).collect::<Vec<_>>();
+        let mut blks = self
+            .blks
+            .drain(..)
+            .map(|blk| (blk.id(), blk))
+            .collect::<BTreeMap<_, _>>();
+        let mut merged = BTreeSet::new();
+
+        for id in order.iter() {
+            if merged.contains(id) {
+                continue
+            }
+
+            // unwrap is safe here: we only remove the Blks we merge
+            let mut blk = blks.remove(id).unwrap();
+            while let Some(next) = blk.fall_through() {
+                let mergeable = next != *id
+                    && predecessors.get(&next) == Some(&1)
+                    && (merge == BlkMerge::Chains || !leaders.contains(&next));
+
+                let other = match blks.remove(&next) {
+                    Some(other) if mergeable => other,
+                    Some(other) => {
+                        blks.insert(next, other);
+                        break
+                    }
+                    None => break,
+                };
+
+                match blk.merge(other) {
+                    Ok(()) => {
+                        merged.insert(next);
+                    }
+                    Err(other) => {
+                        blks.insert(next, other);
+                        break
+                    }
+                }
+            }
+            blks.insert(*id, blk);
+        }
+
+        self.blks = order
+            .into_iter()
+            .filter(|id| !merged.contains(id))
+            .filter_map(|id| blks.remove(&id))
+            .collect();
+
+        merged.len()
+    }
+
+    pub fn cfg(&self) -> Cfg {
+        Cfg::new(self)
+    }
+
+    /// Computes the definitions reaching the entry and exit of each Blk.
+    pub fn reaching_definitions(&self) -> ReachingDefinitions {
+        ReachingDefinitions::new(self)
+    }
+
+    /// Computes the definitions reaching each use of a variable.
+    pub fn use_def_chains(&self) -> UseDefChains {
+        UseDefChains::new(self)
+    }
+
+    /// Reconstructs the stack frame of the Sub, tracking `stack_pointer`
+    /// (e.g., that of the Lifter's convention; see `Lifter::stack_pointer`).
+    pub fn stack_frame(&self, stack_pointer: &Var) -> StackFrame {
+        StackFrame::new(self, stack_pointer)
+    }
+}
@@ -0,0 +1,120 @@
+use crate::prelude::Entity;
+
+use crate::ir::visit::Visit;
+use crate::ir::{Sub, Var};
+use crate::types::TypeSort;
+
+/// Generates fresh (transient) variables with deterministic names, numbered
+/// from zero within a scope (e.g., a Sub, or a pass over one): the same
+/// sequence of requests yields the same names on every run, unlike
+/// `Var::fresh`, whose numbering is global.
+///
+/// Names are of the form `<prefix><n>`; a generator scoped to a Sub (see
+/// `for_sub`) starts numbering after the fresh names already used by it,
+/// so its names do not collide with those of earlier passes.
+#[derive(Debug, Clone)]
+pub struct FreshVars {
+    prefix: String,
+    next: u64,
+}
+
+impl Default for FreshVars {
+    fn default() -> Self {
+        Self::new("v")
+    }
+}
+
+// the greatest number of a fresh name with the given prefix
+struct MaxFresh<'a> {
+    prefix: &'a str,
+    max: Option<u64>,
+}
+
+impl<'ir, 'a> Visit<'ir> for MaxFresh<'a> {
+    fn visit_var(&mut self, var: &'ir Var) {
+        if !var.is_transient() {
+            return
+        }
+
+        let n = var
+            .name()
+            .strip_prefix(self.prefix)
+            .filter(|n| !n.starts_with('0') || n.len() == 1)
+            .and_then(|n| n.parse::<u64>().ok());
+
+        if n > self.max {
+            self.max = n;
+        }
+    }
+}
+
+impl FreshVars {
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            next: 0,
+        }
+    }
+
+    /// A generator scoped to `sub`, whose names follow those of the form
+    /// `<prefix><n>` already used by it.
+    pub fn for_sub(prefix: impl Into<String>, sub: &Sub) -> Self {
+        let mut fresh = Self::new(prefix);
+        let mut visitor = MaxFresh {
+            prefix: &fresh.prefix,
+            max: None,
+        };
+        visitor.visit_sub(sub);
+
+        fresh.next = visitor.max.map(|n| n + 1).unwrap_or(0);
+        fresh
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// The number of the next name generated.
+    pub fn next_index(&self) -> u64 {
+        self.next
+    }
+
+    /// A fresh variable of type `typ`.
+    pub fn fresh(&mut self, typ: impl TypeSort) -> Entity<Var> {
+        let name = format!("{}{}", self.prefix, self.next);
+        self.next += 1;
+        Var::transient(name, typ)
+    }
+
+    /// Restarts numbering from zero, e.g., when entering a new scope.
+    pub fn reset(&mut self) {
+        self.next = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::{Blk, Def, Expr};
+    use crate::types::bv::BitVecT;
+
+    #[test]
+    fn test_fresh_vars() {
+        let typ = BitVecT::with_bits(32, false);
+
+        let mut fresh = FreshVars::default();
+        let (v0, v1) = (fresh.fresh(typ), fresh.fresh(typ));
+        assert_eq!((&**v0.name(), &**v1.name()), ("v0", "v1"));
+
+        let mut blk = Blk::new(None);
+        blk.add_def(Def::assign(Var::from(v1), Expr::from(Var::from(v0))));
+        let sub = Sub::new(None, None, vec![blk]);
+
+        let mut scoped = FreshVars::for_sub("v", &sub);
+        assert_eq!(scoped.next_index(), 2);
+        assert_eq!(&**scoped.fresh(typ).name(), "v2");
+
+        scoped.reset();
+        assert_eq!(&**scoped.fresh(typ).name(), "v0");
+    }
+}
@@ -3,14 +3,17 @@ use std::fmt::{self, Display};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 
-use crate::prelude::{Id, Identifiable, Entity, Erased};
+use crate::prelude::{intern, Id, Identifiable, Entity, Erased, Tagged};
 
 use crate::ir::memory::Mem;
 use crate::types::{Type, TypeSort};
 
+mod fresh;
+pub use fresh::FreshVars;
+
 static UNIQUE_VAR: AtomicU64 = AtomicU64::new(0);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum VarKind {
     Memory {
         id: Id<Erased>,
@@ -25,7 +28,7 @@ pub enum VarKind {
     },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Var {
     name: Arc<str>,
     kind: VarKind,
@@ -44,18 +47,32 @@ impl Display for Var {
     }
 }
 
+impl From<Entity<Var>> for Var {
+    fn from(var: Entity<Var>) -> Self {
+        var.into_value()
+    }
+}
+
+impl Tagged for Var {
+    const TAG: &'static str = "var";
+}
+
 impl Var {
     fn new(name: impl Borrow<str>, kind: VarKind) -> Entity<Self> {
+        Self::with_name(intern(name.borrow()), kind)
+    }
+
+    fn with_name(name: Arc<str>, kind: VarKind) -> Entity<Self> {
         Entity::new("var", Self {
-            name: Arc::from(name.borrow()),
+            name,
             kind,
             generation: 0,
         })
     }
     
-    pub fn memory(memory: &Entity<Mem>) -> Entity<Self> {
+    pub fn memory(memory: &Mem) -> Entity<Self> {
         Entity::new("var", Self {
-            name: Arc::from(memory.name()),
+            name: intern(&memory.name()),
             kind: VarKind::Memory {
                 id: memory.id().erase(),
             },
@@ -77,8 +94,13 @@ impl Var {
         })
     }
     
+    /// A variable with a globally unique name; names depend on the order
+    /// in which they are requested by all threads, so passes whose output
+    /// should be reproducible should use `FreshVars` instead.
     pub fn fresh(typ: impl TypeSort) -> Entity<Self> {
-        Self::new(format!("v{:x}", UNIQUE_VAR.fetch_add(1, Ordering::Relaxed)), VarKind::Transient {
+        // fresh names are unique, and so are not interned
+        let name = format!("v{:x}", UNIQUE_VAR.fetch_add(1, Ordering::Relaxed));
+        Self::with_name(Arc::from(name), VarKind::Transient {
             typ: typ.id(),
             bits: typ.bits(),
         })
@@ -0,0 +1,246 @@
+/// Visitors over our IR/IL.
+///
+/// Each visitor method has a default implementation that walks the
+/// components of the visited value; implementors override the methods
+/// of interest and (optionally) call back into the defaults to continue
+/// the traversal.
+use crate::ir::{Addr, BitVec, Blk, Def, Expr, Jmp, Loc, Phi, Sub, Var};
+use crate::ir::{BinOp, BinRel, Cast, UnOp, UnRel};
+use crate::prelude::{Entity, Id};
+
+pub mod visit_mut;
+pub use visit_mut::VisitMut;
+
+pub trait Visit<'ir> {
+    #[allow(unused)]
+    fn visit_val(&mut self, bv: &'ir BitVec) {}
+    #[allow(unused)]
+    fn visit_var(&mut self, var: &'ir Var) {}
+    #[allow(unused)]
+    fn visit_addr(&mut self, addr: &'ir Addr) {}
+    #[allow(unused)]
+    fn visit_blk_id(&mut self, id: &'ir Id<Blk>) {}
+
+    fn visit_loc_resolved(&mut self, id: &'ir Id<Blk>) {
+        self.visit_blk_id(id)
+    }
+
+    fn visit_loc_fixed(&mut self, addr: &'ir Addr) {
+        self.visit_addr(addr)
+    }
+
+    fn visit_loc_computed(&mut self, expr: &'ir Expr) {
+        self.visit_expr(expr)
+    }
+
+    fn visit_loc(&mut self, loc: &'ir Loc) {
+        match loc {
+            Loc::Resolved(ref id) => self.visit_loc_resolved(id),
+            Loc::Fixed(ref addr) => self.visit_loc_fixed(addr),
+            Loc::Computed(ref expr) => self.visit_loc_computed(expr),
+        }
+    }
+
+    #[allow(unused)]
+    fn visit_cast(&mut self, cast: &'ir Cast) {}
+
+    #[allow(unused)]
+    fn visit_expr_unop_op(&mut self, op: UnOp) {}
+
+    fn visit_expr_unop(&mut self, op: UnOp, expr: &'ir Expr) {
+        self.visit_expr_unop_op(op);
+        self.visit_expr(expr)
+    }
+
+    #[allow(unused)]
+    fn visit_expr_unrel_op(&mut self, op: UnRel) {}
+
+    fn visit_expr_unrel(&mut self, op: UnRel, expr: &'ir Expr) {
+        self.visit_expr_unrel_op(op);
+        self.visit_expr(expr)
+    }
+
+    #[allow(unused)]
+    fn visit_expr_binop_op(&mut self, op: BinOp) {}
+
+    fn visit_expr_binop(&mut self, op: BinOp, lexpr: &'ir Expr, rexpr: &'ir Expr) {
+        self.visit_expr(lexpr);
+        self.visit_expr_binop_op(op);
+        self.visit_expr(rexpr)
+    }
+
+    #[allow(unused)]
+    fn visit_expr_binrel_op(&mut self, op: BinRel) {}
+
+    fn visit_expr_binrel(&mut self, op: BinRel, lexpr: &'ir Expr, rexpr: &'ir Expr) {
+        self.visit_expr(lexpr);
+        self.visit_expr_binrel_op(op);
+        self.visit_expr(rexpr)
+    }
+
+    fn visit_expr_cast(&mut self, expr: &'ir Expr, cast: &'ir Cast) {
+        self.visit_expr(expr);
+        self.visit_cast(cast)
+    }
+
+    #[allow(unused_variables)]
+    fn visit_expr_load(&mut self, expr: &'ir Expr, bits: u32, mem: &'ir Var) {
+        self.visit_expr(expr);
+        self.visit_var(mem)
+    }
+
+    #[allow(unused_variables)]
+    fn visit_expr_extract(&mut self, expr: &'ir Expr, lsb: u32, msb: u32) {
+        self.visit_expr(expr)
+    }
+
+    fn visit_expr_concat(&mut self, lexpr: &'ir Expr, rexpr: &'ir Expr) {
+        self.visit_expr(lexpr);
+        self.visit_expr(rexpr)
+    }
+
+    fn visit_expr_ite(&mut self, cond: &'ir Expr, texpr: &'ir Expr, fexpr: &'ir Expr) {
+        self.visit_expr(cond);
+        self.visit_expr(texpr);
+        self.visit_expr(fexpr)
+    }
+
+    #[allow(unused_variables)]
+    fn visit_expr_intrinsic(&mut self, name: &'ir str, args: &'ir [Box<Expr>], bits: u32) {
+        for arg in args.iter() {
+            self.visit_expr(arg);
+        }
+    }
+
+    fn visit_expr_val(&mut self, bv: &'ir BitVec) {
+        self.visit_val(bv)
+    }
+
+    fn visit_expr_var(&mut self, var: &'ir Var) {
+        self.visit_var(var)
+    }
+
+    fn visit_expr(&mut self, expr: &'ir Expr) {
+        match expr {
+            Expr::UnRel(op, ref expr) => self.visit_expr_unrel(*op, expr),
+            Expr::UnOp(op, ref expr) => self.visit_expr_unop(*op, expr),
+            Expr::BinRel(op, ref lexpr, ref rexpr) => self.visit_expr_binrel(*op, lexpr, rexpr),
+            Expr::BinOp(op, ref lexpr, ref rexpr) => self.visit_expr_binop(*op, lexpr, rexpr),
+            Expr::Cast(ref expr, ref cast) => self.visit_expr_cast(expr, cast),
+            Expr::Load(ref expr, bits, ref mem) => self.visit_expr_load(expr, *bits, mem),
+            Expr::Extract(ref expr, lsb, msb) => self.visit_expr_extract(expr, *lsb, *msb),
+            Expr::Concat(ref lexpr, ref rexpr) => self.visit_expr_concat(lexpr, rexpr),
+            Expr::IfElse(ref cond, ref texpr, ref fexpr) => self.visit_expr_ite(cond, texpr, fexpr),
+            Expr::Intrinsic(ref name, ref args, bits) => {
+                self.visit_expr_intrinsic(name, args, *bits)
+            }
+            Expr::Val(ref bv) => self.visit_expr_val(bv),
+            Expr::Var(ref var) => self.visit_expr_var(var),
+        }
+    }
+
+    fn visit_def_assign(&mut self, var: &'ir Var, expr: &'ir Expr) {
+        self.visit_var(var);
+        self.visit_expr(expr)
+    }
+
+    fn visit_def_assume(&mut self, expr: &'ir Expr) {
+        self.visit_expr(expr)
+    }
+
+    #[allow(unused_variables)]
+    fn visit_def_store(&mut self, loc: &'ir Expr, val: &'ir Expr, bits: u32, mem: &'ir Var) {
+        self.visit_var(mem);
+        self.visit_expr(loc);
+        self.visit_expr(val)
+    }
+
+    fn visit_def(&mut self, def: &'ir Def) {
+        match def {
+            Def::Assign(ref var, ref expr) => self.visit_def_assign(var, expr),
+            Def::Assume(ref expr) => self.visit_def_assume(expr),
+            Def::Store(ref loc, ref val, bits, ref mem) => {
+                self.visit_def_store(loc, val, *bits, mem)
+            }
+        }
+    }
+
+    fn visit_jmp_branch(&mut self, loc: &'ir Loc) {
+        self.visit_loc(loc)
+    }
+
+    fn visit_jmp_cbranch(&mut self, loc: &'ir Loc, cond: &'ir Expr) {
+        self.visit_expr(cond);
+        self.visit_loc(loc)
+    }
+
+    fn visit_jmp_call(&mut self, loc: &'ir Loc, args: &'ir [Expr]) {
+        self.visit_loc(loc);
+        for arg in args {
+            self.visit_expr(arg);
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn visit_jmp_intrinsic(&mut self, name: &'ir str, args: &'ir [Expr]) {
+        for arg in args {
+            self.visit_expr(arg);
+        }
+    }
+
+    fn visit_jmp_return(&mut self, loc: &'ir Loc) {
+        self.visit_loc(loc)
+    }
+
+    fn visit_jmp(&mut self, jmp: &'ir Jmp) {
+        match jmp {
+            Jmp::Branch(ref loc) => self.visit_jmp_branch(loc),
+            Jmp::CBranch(ref loc, ref cond) => self.visit_jmp_cbranch(loc, cond),
+            Jmp::Call(ref loc, ref args) => self.visit_jmp_call(loc, args),
+            Jmp::Intrinsic(ref name, ref args) => self.visit_jmp_intrinsic(name, args),
+            Jmp::Return(ref loc) => self.visit_jmp_return(loc),
+        }
+    }
+
+    fn visit_phi(&mut self, phi: &'ir Phi) {
+        self.visit_var(phi.var());
+        for (cond, expr) in phi.choices() {
+            self.visit_expr(cond);
+            self.visit_expr(expr);
+        }
+    }
+
+    fn visit_blk_phi(&mut self, phi: &'ir Entity<Phi>) {
+        self.visit_phi(phi)
+    }
+
+    fn visit_blk_def(&mut self, def: &'ir Entity<Def>) {
+        self.visit_def(def)
+    }
+
+    fn visit_blk_jmp(&mut self, jmp: &'ir Entity<Jmp>) {
+        self.visit_jmp(jmp)
+    }
+
+    fn visit_blk(&mut self, blk: &'ir Blk) {
+        for phi in blk.phis() {
+            self.visit_blk_phi(phi);
+        }
+        for def in blk.defs() {
+            self.visit_blk_def(def);
+        }
+        for jmp in blk.jmps() {
+            self.visit_blk_jmp(jmp);
+        }
+    }
+
+    fn visit_sub_blk(&mut self, blk: &'ir Entity<Blk>) {
+        self.visit_blk(blk)
+    }
+
+    fn visit_sub(&mut self, sub: &'ir Sub) {
+        for blk in sub.blks() {
+            self.visit_sub_blk(blk);
+        }
+    }
+}
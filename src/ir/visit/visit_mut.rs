@@ -0,0 +1,260 @@
+use crate::ir::{Addr, BitVec, Blk, Def, Expr, Jmp, Loc, Phi, Sub, Var};
+use crate::ir::{BinOp, BinRel, Cast, UnOp, UnRel};
+use crate::prelude::{Entity, Id};
+
+use smallvec::SmallVec;
+
+pub trait VisitMut<'ir> {
+    #[allow(unused)]
+    fn visit_val_mut(&mut self, bv: &'ir mut BitVec) {}
+    #[allow(unused)]
+    fn visit_var_mut(&mut self, var: &'ir mut Var) {}
+    #[allow(unused)]
+    fn visit_addr_mut(&mut self, addr: &'ir mut Addr) {}
+    #[allow(unused)]
+    fn visit_blk_id_mut(&mut self, id: &'ir mut Id<Blk>) {}
+
+    fn visit_loc_resolved_mut(&mut self, id: &'ir mut Id<Blk>) {
+        self.visit_blk_id_mut(id)
+    }
+
+    fn visit_loc_fixed_mut(&mut self, addr: &'ir mut Addr) {
+        self.visit_addr_mut(addr)
+    }
+
+    fn visit_loc_computed_mut(&mut self, expr: &'ir mut Expr) {
+        self.visit_expr_mut(expr)
+    }
+
+    fn visit_loc_mut(&mut self, loc: &'ir mut Loc) {
+        match loc {
+            Loc::Resolved(ref mut id) => self.visit_loc_resolved_mut(id),
+            Loc::Fixed(ref mut addr) => self.visit_loc_fixed_mut(addr),
+            Loc::Computed(ref mut expr) => self.visit_loc_computed_mut(expr),
+        }
+    }
+
+    #[allow(unused)]
+    fn visit_cast_mut(&mut self, cast: &'ir mut Cast) {}
+
+    #[allow(unused)]
+    fn visit_expr_unop_op_mut(&mut self, op: UnOp) {}
+
+    fn visit_expr_unop_mut(&mut self, op: UnOp, expr: &'ir mut Expr) {
+        self.visit_expr_unop_op_mut(op);
+        self.visit_expr_mut(expr)
+    }
+
+    #[allow(unused)]
+    fn visit_expr_unrel_op_mut(&mut self, op: UnRel) {}
+
+    fn visit_expr_unrel_mut(&mut self, op: UnRel, expr: &'ir mut Expr) {
+        self.visit_expr_unrel_op_mut(op);
+        self.visit_expr_mut(expr)
+    }
+
+    #[allow(unused)]
+    fn visit_expr_binop_op_mut(&mut self, op: BinOp) {}
+
+    fn visit_expr_binop_mut(&mut self, op: BinOp, lexpr: &'ir mut Expr, rexpr: &'ir mut Expr) {
+        self.visit_expr_mut(lexpr);
+        self.visit_expr_binop_op_mut(op);
+        self.visit_expr_mut(rexpr)
+    }
+
+    #[allow(unused)]
+    fn visit_expr_binrel_op_mut(&mut self, op: BinRel) {}
+
+    fn visit_expr_binrel_mut(&mut self, op: BinRel, lexpr: &'ir mut Expr, rexpr: &'ir mut Expr) {
+        self.visit_expr_mut(lexpr);
+        self.visit_expr_binrel_op_mut(op);
+        self.visit_expr_mut(rexpr)
+    }
+
+    fn visit_expr_cast_mut(&mut self, expr: &'ir mut Expr, cast: &'ir mut Cast) {
+        self.visit_expr_mut(expr);
+        self.visit_cast_mut(cast)
+    }
+
+    #[allow(unused_variables)]
+    fn visit_expr_load_mut(&mut self, expr: &'ir mut Expr, bits: u32, mem: &'ir mut Var) {
+        self.visit_expr_mut(expr);
+        self.visit_var_mut(mem)
+    }
+
+    #[allow(unused_variables)]
+    fn visit_expr_extract_mut(&mut self, expr: &'ir mut Expr, lsb: u32, msb: u32) {
+        self.visit_expr_mut(expr)
+    }
+
+    fn visit_expr_concat_mut(&mut self, lexpr: &'ir mut Expr, rexpr: &'ir mut Expr) {
+        self.visit_expr_mut(lexpr);
+        self.visit_expr_mut(rexpr)
+    }
+
+    fn visit_expr_ite_mut(&mut self, cond: &'ir mut Expr, texpr: &'ir mut Expr, fexpr: &'ir mut Expr) {
+        self.visit_expr_mut(cond);
+        self.visit_expr_mut(texpr);
+        self.visit_expr_mut(fexpr)
+    }
+
+    #[allow(unused_variables)]
+    fn visit_expr_intrinsic_mut(
+        &mut self,
+        name: &str,
+        args: &'ir mut SmallVec<[Box<Expr>; 4]>,
+        bits: u32,
+    ) {
+        for arg in args.iter_mut() {
+            self.visit_expr_mut(arg);
+        }
+    }
+
+    fn visit_expr_val_mut(&mut self, bv: &'ir mut BitVec) {
+        self.visit_val_mut(bv)
+    }
+
+    fn visit_expr_var_mut(&mut self, var: &'ir mut Var) {
+        self.visit_var_mut(var)
+    }
+
+    fn visit_expr_mut(&mut self, expr: &'ir mut Expr) {
+        match expr {
+            Expr::UnRel(op, ref mut expr) => self.visit_expr_unrel_mut(*op, expr),
+            Expr::UnOp(op, ref mut expr) => self.visit_expr_unop_mut(*op, expr),
+            Expr::BinRel(op, ref mut lexpr, ref mut rexpr) => {
+                self.visit_expr_binrel_mut(*op, lexpr, rexpr)
+            }
+            Expr::BinOp(op, ref mut lexpr, ref mut rexpr) => {
+                self.visit_expr_binop_mut(*op, lexpr, rexpr)
+            }
+            Expr::Cast(ref mut expr, ref mut cast) => self.visit_expr_cast_mut(expr, cast),
+            Expr::Load(ref mut expr, bits, ref mut mem) => {
+                self.visit_expr_load_mut(expr, *bits, mem)
+            }
+            Expr::Extract(ref mut expr, lsb, msb) => self.visit_expr_extract_mut(expr, *lsb, *msb),
+            Expr::Concat(ref mut lexpr, ref mut rexpr) => self.visit_expr_concat_mut(lexpr, rexpr),
+            Expr::IfElse(ref mut cond, ref mut texpr, ref mut fexpr) => {
+                self.visit_expr_ite_mut(cond, texpr, fexpr)
+            }
+            Expr::Intrinsic(ref name, ref mut args, bits) => {
+                self.visit_expr_intrinsic_mut(name, args, *bits)
+            }
+            Expr::Val(ref mut bv) => self.visit_expr_val_mut(bv),
+            Expr::Var(ref mut var) => self.visit_expr_var_mut(var),
+        }
+    }
+
+    fn visit_def_assign_mut(&mut self, var: &'ir mut Var, expr: &'ir mut Expr) {
+        self.visit_var_mut(var);
+        self.visit_expr_mut(expr)
+    }
+
+    fn visit_def_assume_mut(&mut self, expr: &'ir mut Expr) {
+        self.visit_expr_mut(expr)
+    }
+
+    #[allow(unused_variables)]
+    fn visit_def_store_mut(
+        &mut self,
+        loc: &'ir mut Expr,
+        val: &'ir mut Expr,
+        bits: u32,
+        mem: &'ir mut Var,
+    ) {
+        self.visit_var_mut(mem);
+        self.visit_expr_mut(loc);
+        self.visit_expr_mut(val)
+    }
+
+    fn visit_def_mut(&mut self, def: &'ir mut Def) {
+        match def {
+            Def::Assign(ref mut var, ref mut expr) => self.visit_def_assign_mut(var, expr),
+            Def::Assume(ref mut expr) => self.visit_def_assume_mut(expr),
+            Def::Store(ref mut loc, ref mut val, bits, ref mut mem) => {
+                self.visit_def_store_mut(loc, val, *bits, mem)
+            }
+        }
+    }
+
+    fn visit_jmp_branch_mut(&mut self, loc: &'ir mut Loc) {
+        self.visit_loc_mut(loc)
+    }
+
+    fn visit_jmp_cbranch_mut(&mut self, loc: &'ir mut Loc, cond: &'ir mut Expr) {
+        self.visit_expr_mut(cond);
+        self.visit_loc_mut(loc)
+    }
+
+    fn visit_jmp_call_mut(&mut self, loc: &'ir mut Loc, args: &'ir mut SmallVec<[Expr; 4]>) {
+        self.visit_loc_mut(loc);
+        for arg in args.iter_mut() {
+            self.visit_expr_mut(arg);
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn visit_jmp_intrinsic_mut(&mut self, name: &str, args: &'ir mut SmallVec<[Expr; 4]>) {
+        for arg in args.iter_mut() {
+            self.visit_expr_mut(arg);
+        }
+    }
+
+    fn visit_jmp_return_mut(&mut self, loc: &'ir mut Loc) {
+        self.visit_loc_mut(loc)
+    }
+
+    fn visit_jmp_mut(&mut self, jmp: &'ir mut Jmp) {
+        match jmp {
+            Jmp::Branch(ref mut loc) => self.visit_jmp_branch_mut(loc),
+            Jmp::CBranch(ref mut loc, ref mut cond) => self.visit_jmp_cbranch_mut(loc, cond),
+            Jmp::Call(ref mut loc, ref mut args) => self.visit_jmp_call_mut(loc, args),
+            Jmp::Intrinsic(ref name, ref mut args) => self.visit_jmp_intrinsic_mut(name, args),
+            Jmp::Return(ref mut loc) => self.visit_jmp_return_mut(loc),
+        }
+    }
+
+    fn visit_phi_mut(&mut self, phi: &'ir mut Phi) {
+        let (var, choices) = phi.parts_mut();
+        self.visit_var_mut(var);
+        for (cond, expr) in choices.iter_mut() {
+            self.visit_expr_mut(cond);
+            self.visit_expr_mut(expr);
+        }
+    }
+
+    fn visit_blk_phi_mut(&mut self, phi: &'ir mut Entity<Phi>) {
+        self.visit_phi_mut(phi)
+    }
+
+    fn visit_blk_def_mut(&mut self, def: &'ir mut Entity<Def>) {
+        self.visit_def_mut(def)
+    }
+
+    fn visit_blk_jmp_mut(&mut self, jmp: &'ir mut Entity<Jmp>) {
+        self.visit_jmp_mut(jmp)
+    }
+
+    fn visit_blk_mut(&mut self, blk: &'ir mut Blk) {
+        let (phis, defs, jmps) = blk.parts_mut();
+        for phi in phis.iter_mut() {
+            self.visit_blk_phi_mut(phi);
+        }
+        for def in defs.iter_mut() {
+            self.visit_blk_def_mut(def);
+        }
+        for jmp in jmps.iter_mut() {
+            self.visit_blk_jmp_mut(jmp);
+        }
+    }
+
+    fn visit_sub_blk_mut(&mut self, blk: &'ir mut Entity<Blk>) {
+        self.visit_blk_mut(blk)
+    }
+
+    fn visit_sub_mut(&mut self, sub: &'ir mut Sub) {
+        for blk in sub.blks_mut().iter_mut() {
+            self.visit_sub_blk_mut(blk);
+        }
+    }
+}
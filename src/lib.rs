@@ -1,6 +1,17 @@
+pub mod analysis;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod corpus;
+pub mod emu;
+pub mod export;
 pub mod ir;
 pub mod il;
 pub mod oracles;
+pub mod os;
 pub mod lift;
+pub mod passes;
 pub mod prelude;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod symbolic;
 pub mod types;
\ No newline at end of file
@@ -0,0 +1,395 @@
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use thiserror::Error;
+
+use crate::analysis::ContentHash;
+
+const BLOCK: usize = 512;
+
+// the files of the specifications a LanguageDB loads
+const SPEC_EXTENSIONS: [&str; 4] = ["ldefs", "sla", "pspec", "cspec"];
+
+#[derive(Debug, Error)]
+pub enum ArchiveError {
+    #[error("malformed archive header at offset {0}")]
+    Header(usize),
+    #[error("truncated archive entry `{0}`")]
+    Truncated(PathBuf),
+    #[error("archive entry `{0}` is not within the archive's root")]
+    Path(PathBuf),
+    #[error(transparent)]
+    IO(#[from] io::Error),
+}
+
+/// The files of a (ustar or GNU) tar archive of processor specifications,
+/// e.g., as bundled into an application with `include_bytes!`.
+///
+/// Only regular files are kept (directories are implied by their paths);
+/// links and other special entries are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct SpecArchive<'a> {
+    files: BTreeMap<PathBuf, &'a [u8]>,
+    hash: u64,
+}
+
+fn field(header: &[u8], start: usize, len: usize) -> &[u8] {
+    let field = &header[start..start + len];
+    let end = field.iter().position(|b| *b == 0).unwrap_or(len);
+    &field[..end]
+}
+
+fn octal(header: &[u8], start: usize, len: usize) -> Option<usize> {
+    let digits = std::str::from_utf8(field(header, start, len)).ok()?;
+    let digits = digits.trim_matches(|c: char| c == ' ' || c == '\0');
+    if digits.is_empty() {
+        Some(0)
+    } else {
+        usize::from_str_radix(digits, 8).ok()
+    }
+}
+
+fn path(name: &[u8]) -> Result<PathBuf, ArchiveError> {
+    let path = PathBuf::from(String::from_utf8_lossy(name).into_owned());
+    if path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        Ok(path)
+    } else {
+        Err(ArchiveError::Path(path))
+    }
+}
+
+fn header(name: &[u8], size: usize, typeflag: u8) -> Vec<u8> {
+    let mut header = vec![0u8; BLOCK];
+    let len = name.len().min(100);
+    header[..len].copy_from_slice(&name[..len]);
+    header[100..107].copy_from_slice(b"0000644");
+    header[108..115].copy_from_slice(b"0000000");
+    header[116..123].copy_from_slice(b"0000000");
+    header[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+    header[136..147].copy_from_slice(b"00000000000");
+    header[156] = typeflag;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    let sum = header.iter().map(|b| *b as usize).sum::<usize>() + 8 * b' ' as usize;
+    header[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+    header[155] = b' ';
+    header
+}
+
+fn write_entry(archive: &mut Vec<u8>, name: &[u8], data: &[u8], typeflag: u8) {
+    archive.extend(header(name, data.len(), typeflag));
+    archive.extend_from_slice(data);
+    archive.resize((archive.len() + BLOCK - 1) / BLOCK * BLOCK, 0);
+}
+
+fn find_specs(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_specs(&path, files)?;
+        } else if path
+            .extension()
+            .map(|ext| SPEC_EXTENSIONS.iter().any(|spec| ext == *spec))
+            .unwrap_or(false)
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Archives the compiled specifications (i.e., the `.ldefs`, `.sla`,
+/// `.pspec`, and `.cspec` files) beneath `root` of the processors named
+/// `processors` (e.g., `["x86", "ARM"]`), or of every processor if none
+/// are given, for loading with `SpecArchive::new`.
+///
+/// Processors are matched by the names of the directories holding their
+/// specifications (e.g., `x86/data/languages/x86.sla` is of `x86`).
+pub fn archive_processors(root: impl AsRef<Path>, processors: &[&str]) -> Result<Vec<u8>, ArchiveError> {
+    let root = root.as_ref();
+
+    let mut files = Vec::new();
+    find_specs(root, &mut files)?;
+    files.sort();
+
+    let mut archive = Vec::new();
+    for file in files {
+        // unwrap is safe here: the files are found beneath root
+        let name = file.strip_prefix(root).unwrap();
+        if !processors.is_empty()
+            && !name
+                .components()
+                .any(|component| processors.iter().any(|processor| component.as_os_str() == *processor))
+        {
+            continue
+        }
+
+        let name = name
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if name.len() > 100 {
+            let mut long_name = name.as_bytes().to_vec();
+            long_name.push(0);
+            write_entry(&mut archive, b"././@LongLink", &long_name, b'L');
+        }
+        write_entry(&mut archive, name.as_bytes(), &fs::read(&file)?, b'0');
+    }
+    archive.extend([0u8; 2 * BLOCK]);
+
+    Ok(archive)
+}
+
+/// Writes the archive of `archive_processors` to `path`, e.g., within
+/// `OUT_DIR` from a build script, for embedding with
+/// `include_processors!`; returns the archive's size.
+pub fn write_processors(
+    root: impl AsRef<Path>,
+    processors: &[&str],
+    path: impl AsRef<Path>,
+) -> Result<usize, ArchiveError> {
+    let archive = archive_processors(root, processors)?;
+    fs::write(path, &archive)?;
+    Ok(archive.len())
+}
+
+impl<'a> SpecArchive<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self, ArchiveError> {
+        let mut files = BTreeMap::new();
+        let mut long_name = None;
+        let mut offset = 0;
+
+        while offset + BLOCK <= bytes.len() {
+            let header = &bytes[offset..offset + BLOCK];
+
+            // the archive ends with (at least) one block of zeros
+            if header.iter().all(|b| *b == 0) {
+                break
+            }
+
+            let checksum = octal(header, 148, 8).ok_or(ArchiveError::Header(offset))?;
+            let sum = header
+                .iter()
+                .enumerate()
+                .map(|(i, b)| if (148..156).contains(&i) { b' ' as usize } else { *b as usize })
+                .sum::<usize>();
+            if sum != checksum {
+                return Err(ArchiveError::Header(offset))
+            }
+
+            let size = octal(header, 124, 12).ok_or(ArchiveError::Header(offset))?;
+            let name = match long_name.take() {
+                Some(name) => name,
+                None => {
+                    let mut name = Vec::new();
+                    let prefix = field(header, 345, 155);
+                    if &header[257..262] == b"ustar" && !prefix.is_empty() {
+                        name.extend_from_slice(prefix);
+                        name.push(b'/');
+                    }
+                    name.extend_from_slice(field(header, 0, 100));
+                    name
+                }
+            };
+
+            let start = offset + BLOCK;
+            let data = start
+                .checked_add(size)
+                .and_then(|end| bytes.get(start..end))
+                .ok_or_else(|| ArchiveError::Truncated(PathBuf::from(String::from_utf8_lossy(&name).into_owned())))?;
+
+            match header[156] {
+                b'0' | b'\0' | b'7' => {
+                    files.insert(path(&name)?, data);
+                }
+                // GNU's long names precede the entries they name
+                b'L' => {
+                    let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+                    long_name = Some(data[..end].to_vec());
+                }
+                _ => (),
+            }
+
+            offset = start + (size + BLOCK - 1) / BLOCK * BLOCK;
+        }
+
+        let mut hash = ContentHash::new();
+        for (path, data) in files.iter() {
+            hash.write_str(&path.to_string_lossy()).write(data);
+        }
+
+        Ok(Self { files, hash: hash.finish() })
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (&Path, &'a [u8])> {
+        self.files.iter().map(|(path, data)| (path.as_path(), *data))
+    }
+
+    pub fn file(&self, path: impl AsRef<Path>) -> Option<&'a [u8]> {
+        self.files.get(path.as_ref()).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// A hash of the archive's files, identifying its contents.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Writes the archive's files beneath `root`.
+    pub fn unpack(&self, root: impl AsRef<Path>) -> Result<(), ArchiveError> {
+        let root = root.as_ref();
+        for (path, data) in self.files.iter() {
+            let path = root.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, data)?;
+        }
+        Ok(())
+    }
+
+    // writes the archive's files beneath a new directory within the
+    // temporary directory, accessible only to the current user
+    pub(crate) fn unpack_private(&self) -> Result<UnpackedArchive, ArchiveError> {
+        let unpacked = UnpackedArchive { root: private_dir("delirium-specs")? };
+        self.unpack(&unpacked.root)?;
+        Ok(unpacked)
+    }
+}
+
+/// A directory an archive is unpacked into, removed when dropped.
+#[derive(Debug)]
+pub(crate) struct UnpackedArchive {
+    root: PathBuf,
+}
+
+impl UnpackedArchive {
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl Drop for UnpackedArchive {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+// creates a directory beneath the temporary directory named prefix with a
+// random suffix; directories that already exist (e.g., as created by
+// another user) are never used
+fn private_dir(prefix: &str) -> io::Result<PathBuf> {
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+
+    let mut attempts = 0;
+    loop {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(std::process::id());
+        if let Ok(time) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+            hasher.write_u128(time.as_nanos());
+        }
+
+        let dir = std::env::temp_dir().join(format!("{}-{:016x}", prefix, hasher.finish()));
+        match builder.create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempts < 16 => attempts += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut entry = Vec::new();
+        write_entry(&mut entry, name.as_bytes(), data, b'0');
+        entry
+    }
+
+    #[test]
+    fn test_archive() -> Result<(), ArchiveError> {
+        let mut bytes = entry("x86/data/languages/x86.ldefs", b"<language_definitions/>");
+        bytes.extend(entry("x86/data/languages/x86.sla", &[1, 2, 3]));
+        bytes.extend([0u8; 2 * BLOCK]);
+
+        let archive = SpecArchive::new(&bytes)?;
+        assert_eq!(archive.len(), 2);
+        assert_eq!(archive.file("x86/data/languages/x86.sla"), Some(&[1u8, 2, 3][..]));
+
+        let mut unsafe_entry = entry("../x86.sla", &[]);
+        unsafe_entry.extend([0u8; 2 * BLOCK]);
+        assert!(matches!(SpecArchive::new(&unsafe_entry), Err(ArchiveError::Path(_))));
+
+        let name = format!("{}/x86.sla", "languages/".repeat(12));
+        let mut long_name = name.as_bytes().to_vec();
+        long_name.push(0);
+        let mut bytes = Vec::new();
+        write_entry(&mut bytes, b"././@LongLink", &long_name, b'L');
+        bytes.extend(entry(&name, &[4]));
+        bytes.extend([0u8; 2 * BLOCK]);
+        assert_eq!(SpecArchive::new(&bytes)?.file(&name), Some(&[4u8][..]));
+
+        // an entry larger than the archive is truncated (rather than its
+        // end overflowing on 32-bit targets)
+        let mut overflowing = entry("x86.sla", &[]);
+        overflowing[124..136].copy_from_slice(b"77777777777\0");
+        let sum = overflowing
+            .iter()
+            .enumerate()
+            .map(|(i, b)| if (148..156).contains(&i) { b' ' as usize } else { *b as usize })
+            .sum::<usize>();
+        overflowing[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+        assert!(matches!(SpecArchive::new(&overflowing), Err(ArchiveError::Truncated(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpack_private() -> Result<(), ArchiveError> {
+        let mut bytes = entry("x86/data/languages/x86.sla", &[1, 2, 3]);
+        bytes.extend([0u8; 2 * BLOCK]);
+        let archive = SpecArchive::new(&bytes)?;
+
+        // each unpacking has its own directory
+        let first = archive.unpack_private()?;
+        let second = archive.unpack_private()?;
+        assert_ne!(first.root(), second.root());
+        assert_eq!(fs::read(first.root().join("x86/data/languages/x86.sla"))?, [1, 2, 3]);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(first.root())?.permissions().mode() & 0o777, 0o700);
+        }
+
+        let root = first.root().to_owned();
+        drop(first);
+        assert!(!root.exists());
+
+        Ok(())
+    }
+}
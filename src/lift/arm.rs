@@ -0,0 +1,77 @@
+use std::fmt::{self, Display};
+
+use fugue::arch::ArchitectureDef;
+use fugue::ir::disassembly::ContextDatabase;
+
+/// The instruction set an ARM processor decodes instructions in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IsaMode {
+    Arm,
+    Thumb,
+}
+
+impl Display for IsaMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Arm => write!(f, "arm"),
+            Self::Thumb => write!(f, "thumb"),
+        }
+    }
+}
+
+impl IsaMode {
+    /// The mode of the code following a mapping symbol, as emitted by ARM
+    /// toolchains, i.e., `$a` or `$t` (optionally suffixed, e.g., `$t.1`);
+    /// None for other symbols (e.g., `$d`, which marks data).
+    pub fn from_mapping_symbol(name: &str) -> Option<Self> {
+        let (kind, suffix) = name.split_at(name.len().min(2));
+        if !suffix.is_empty() && !suffix.starts_with('.') {
+            return None
+        }
+
+        match kind {
+            "$a" => Some(Self::Arm),
+            "$t" => Some(Self::Thumb),
+            _ => None,
+        }
+    }
+
+    /// The mode of the target of an interworking branch (e.g., `bx`) to
+    /// `target`, i.e., Thumb if its least significant bit is set.
+    pub fn of_interworking_target(target: u64) -> Self {
+        if target & 1 != 0 {
+            Self::Thumb
+        } else {
+            Self::Arm
+        }
+    }
+}
+
+// the context variable selecting Thumb
+pub(crate) const TMODE: &str = "TMode";
+
+// the registers the specification assigns the mode of the target of an
+// interworking branch
+pub(crate) const MODE_SWITCHES: [&str; 2] = ["ISAModeSwitch", "TB"];
+
+pub(crate) fn is_arm(arch: &ArchitectureDef) -> bool {
+    arch.processor().eq_ignore_ascii_case("ARM")
+}
+
+pub(crate) fn set_mode(ctxt: &mut ContextDatabase, mode: IsaMode) -> Result<(), fugue::ir::error::Error> {
+    ctxt.set_variable_default(TMODE, (mode == IsaMode::Thumb) as u32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_mapping_symbols() {
+        assert_eq!(IsaMode::from_mapping_symbol("$t"), Some(IsaMode::Thumb));
+        assert_eq!(IsaMode::from_mapping_symbol("$a.12"), Some(IsaMode::Arm));
+        assert_eq!(IsaMode::from_mapping_symbol("$d"), None);
+        assert_eq!(IsaMode::from_mapping_symbol("$thumb"), None);
+        assert_eq!(IsaMode::of_interworking_target(0x8001), IsaMode::Thumb);
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use fugue::ir::disassembly::ContextDatabase;
+
+use crate::ir::Addr;
+use crate::lift::ecode::lower::ECodeInsn;
+
+// context variables that select between instruction set modes; these are
+// the parts of the context that commonly change how the same bytes decode
+const MODE_VARIABLES: &[&str] = &[
+    "addrsize", "opsize", "longMode", "bit64", "TMode", "ISA_MODE", "vle", "LRset",
+];
+
+// instructions (with their delay slots) longer than this are not cached
+const MAX_INSN_LENGTH: usize = 16;
+
+// the values of the tracked context variables
+pub(crate) type ContextKey = Vec<u32>;
+
+// the bytes decoded as an instruction, the context it was decoded in, and
+// the address it was lifted at, if its lifted code may depend on it
+type Key = (Vec<u8>, ContextKey, Option<Addr>);
+
+#[derive(Clone)]
+struct CachedInsn {
+    insn: ECodeInsn,
+    // the instruction terminates its block
+    stop: bool,
+    // the values of the tracked context variables after lifting
+    after: ContextKey,
+    // the last use of the entry, for eviction
+    tick: u64,
+}
+
+/// An instruction found in a Lifter's instruction cache.
+pub(crate) struct CacheHit {
+    /// The instruction, as lifted at its address (which need not be that
+    /// it was looked up at)
+    pub(crate) insn: ECodeInsn,
+    /// The instruction terminates its block
+    pub(crate) stop: bool,
+    /// The values of the tracked context variables after lifting it
+    pub(crate) after: ContextKey,
+}
+
+#[derive(Clone, Default)]
+struct Entries {
+    capacity: usize,
+    tick: u64,
+    insns: BTreeMap<Key, CachedInsn>,
+    uses: BTreeMap<u64, Key>,
+    hits: u64,
+    misses: u64,
+}
+
+/// Statistics for a Lifter's instruction cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LiftCacheStats {
+    pub entries: usize,
+    pub capacity: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// A least-recently-used cache of lifted instructions, keyed by their
+/// bytes and the context in effect where they were decoded.
+///
+/// The lifted code of an instruction may refer to its own address (e.g.,
+/// PC-relative operands are resolved to absolute addresses), hence an
+/// instruction is first cached for the address it was lifted at; once the
+/// same bytes lifted in the same context at another address give the same
+/// code up to its address, the entry is shared by all addresses.
+pub(crate) struct LiftCache {
+    variables: Vec<Arc<str>>,
+    entries: Mutex<Entries>,
+}
+
+impl LiftCache {
+    pub(crate) fn new(ctxt: &ContextDatabase) -> Self {
+        let variables = MODE_VARIABLES
+            .iter()
+            .filter(|name| ctxt.get_default_value(name).is_some())
+            .map(|name| Arc::from(*name))
+            .collect();
+
+        Self {
+            variables,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, Entries> {
+        // a panic while holding the lock cannot leave the entries in an
+        // inconsistent state that matters: at worst, we lose an entry
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.entries().capacity
+    }
+
+    pub(crate) fn set_capacity(&self, capacity: usize) {
+        let mut entries = self.entries();
+        entries.capacity = capacity;
+        entries.evict();
+    }
+
+    pub(crate) fn clear(&self) {
+        let mut entries = self.entries();
+        entries.insns.clear();
+        entries.uses.clear();
+    }
+
+    pub(crate) fn stats(&self) -> LiftCacheStats {
+        let entries = self.entries();
+        LiftCacheStats {
+            entries: entries.insns.len(),
+            capacity: entries.capacity,
+            hits: entries.hits,
+            misses: entries.misses,
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.capacity() > 0
+    }
+
+    /// Makes the variable part of the keys of cached instructions (e.g., as
+    /// it is given values by `Lifter::set_context`); as this changes the
+    /// keys, the instructions cached are dropped if it was not already.
+    pub(crate) fn track(&mut self, name: &str) {
+        if !self.variables.iter().any(|variable| &**variable == name) {
+            self.variables.push(Arc::from(name));
+            self.clear();
+        }
+    }
+
+    // the values of the tracked variables, as given by value
+    pub(crate) fn context_key(&self, value: impl Fn(&str) -> Option<u32>) -> ContextKey {
+        self.variables
+            .iter()
+            .map(|name| value(name).unwrap_or(0))
+            .collect()
+    }
+
+    // the tracked variables whose values differ between before and after,
+    // with their values after
+    pub(crate) fn changes(&self, before: &ContextKey, after: &ContextKey) -> Vec<(Arc<str>, u32)> {
+        self.variables
+            .iter()
+            .zip(before.iter().zip(after.iter()))
+            .filter(|(_, (before, after))| before != after)
+            .map(|(name, (_, after))| (name.clone(), *after))
+            .collect()
+    }
+
+    // the instruction decoded from a prefix of bytes in context, either
+    // for any address or for addr
+    pub(crate) fn get(&self, addr: &Addr, context: &ContextKey, bytes: &[u8]) -> Option<CacheHit> {
+        let mut entries = self.entries();
+
+        entries.tick += 1;
+        let tick = entries.tick;
+
+        // decoding is deterministic, so at most one prefix is an
+        // instruction in a given context
+        let key = (1..=bytes.len().min(MAX_INSN_LENGTH))
+            .flat_map(|length| {
+                let prefix = bytes[..length].to_vec();
+                [
+                    (prefix.clone(), context.clone(), None),
+                    (prefix, context.clone(), Some(addr.clone())),
+                ]
+            })
+            .find(|key| entries.insns.contains_key(key));
+
+        let key = if let Some(key) = key {
+            key
+        } else {
+            entries.misses += 1;
+            return None
+        };
+
+        // unwrap is safe here: we have just found the key
+        let cached = entries.insns.get_mut(&key).unwrap();
+        let last = cached.tick;
+        cached.tick = tick;
+
+        let hit = CacheHit {
+            insn: cached.insn.clone(),
+            stop: cached.stop,
+            after: cached.after.clone(),
+        };
+
+        entries.uses.remove(&last);
+        entries.uses.insert(tick, key);
+        entries.hits += 1;
+
+        Some(hit)
+    }
+
+    // an instruction decoded from bytes in context at an address other
+    // than addr, if any
+    pub(crate) fn lifted_elsewhere(&self, addr: &Addr, context: &ContextKey, bytes: &[u8]) -> Option<ECodeInsn> {
+        let entries = self.entries();
+        let start = (bytes.to_vec(), context.clone(), None);
+
+        let found = entries
+            .insns
+            .range(start..)
+            .take_while(|((other, ocontext, _), _)| other == bytes && ocontext == context)
+            .find(|((_, _, at), _)| at.as_ref() != Some(addr))
+            .map(|(_, cached)| cached.insn.clone());
+        found
+    }
+
+    // caches insn, decoded from bytes in context; if independent, its code
+    // does not depend on its address, and it is shared by all addresses
+    pub(crate) fn insert(
+        &self,
+        context: ContextKey,
+        bytes: &[u8],
+        insn: &ECodeInsn,
+        stop: bool,
+        after: ContextKey,
+        independent: bool,
+    ) {
+        let mut entries = self.entries();
+        if entries.capacity == 0 || bytes.len() > MAX_INSN_LENGTH {
+            return
+        }
+
+        entries.tick += 1;
+        let tick = entries.tick;
+
+        let key = if independent {
+            // entries for individual addresses are superseded
+            let start = (bytes.to_vec(), context.clone(), None);
+            let superseded = entries
+                .insns
+                .range(start..)
+                .take_while(|((other, ocontext, _), _)| other == bytes && *ocontext == context)
+                .map(|(key, cached)| (key.clone(), cached.tick))
+                .collect::<Vec<_>>();
+
+            for (key, tick) in superseded {
+                entries.insns.remove(&key);
+                entries.uses.remove(&tick);
+            }
+
+            (bytes.to_vec(), context, None)
+        } else {
+            (bytes.to_vec(), context, Some(insn.addr.clone()))
+        };
+
+        let cached = CachedInsn {
+            insn: insn.clone(),
+            stop,
+            after,
+            tick,
+        };
+
+        if let Some(previous) = entries.insns.insert(key.clone(), cached) {
+            entries.uses.remove(&previous.tick);
+        }
+        entries.uses.insert(tick, key);
+        entries.evict();
+    }
+}
+
+impl Entries {
+    fn evict(&mut self) {
+        while self.insns.len() > self.capacity {
+            // unwrap is safe here: uses has an entry for each cached insn
+            let tick = *self.uses.keys().next().unwrap();
+            let key = self.uses.remove(&tick).unwrap();
+            self.insns.remove(&key);
+        }
+    }
+}
+
+impl Clone for LiftCache {
+    fn clone(&self) -> Self {
+        Self {
+            variables: self.variables.clone(),
+            entries: Mutex::new(self.entries().clone()),
+        }
+    }
+}
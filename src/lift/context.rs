@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+use fugue::ir::disassembly::ContextDatabase;
+
+use crate::ir::Addr;
+use crate::lift::{Lifter, LifterError};
+
+// the values of context variables set for ranges of addresses, keyed by
+// variable, then by the start of each range
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ContextRanges {
+    variables: BTreeMap<Arc<str>, BTreeMap<Addr, (Addr, u32)>>,
+}
+
+impl ContextRanges {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.variables.is_empty()
+    }
+
+    // ranges set later take precedence over those they overlap
+    fn insert(&mut self, name: &str, range: Range<Addr>, value: u32) {
+        if range.start >= range.end {
+            return
+        }
+
+        let ranges = self.variables.entry(Arc::from(name)).or_default();
+        let overlapping = ranges
+            .range(..range.end.clone())
+            .filter(|(_, (end, _))| *end > range.start)
+            .map(|(start, (end, value))| (start.clone(), end.clone(), *value))
+            .collect::<Vec<_>>();
+
+        for (start, end, value) in overlapping {
+            ranges.remove(&start);
+            if start < range.start {
+                ranges.insert(start, (range.start.clone(), value));
+            }
+            if end > range.end {
+                ranges.insert(range.end.clone(), (end, value));
+            }
+        }
+
+        ranges.insert(range.start, (range.end, value));
+    }
+
+    fn value(&self, name: &str, addr: &Addr) -> Option<u32> {
+        self.variables
+            .get(name)?
+            .range(..=addr.clone())
+            .next_back()
+            .filter(|(_, (end, _))| addr < end)
+            .map(|(_, (_, value))| *value)
+    }
+
+    // sets the variables of ctxt given values for addr, returning those
+    // changed with their previous values
+    pub(crate) fn enter(&self, ctxt: &mut ContextDatabase, addr: &Addr) -> Result<Vec<(Arc<str>, u32)>, LifterError> {
+        let mut previous = Vec::new();
+        for name in self.variables.keys() {
+            if let Some(value) = self.value(name, addr) {
+                let current = ctxt.get_default_value(name).unwrap_or(0);
+                if current != value {
+                    ctxt.set_variable_default(name, value)?;
+                    previous.push((name.clone(), current));
+                }
+            }
+        }
+        Ok(previous)
+    }
+
+    pub(crate) fn leave(ctxt: &mut ContextDatabase, previous: Vec<(Arc<str>, u32)>) -> Result<(), LifterError> {
+        for (name, value) in previous {
+            ctxt.set_variable_default(&name, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl Lifter {
+    /// Sets the context variable `name` of the Lifter's language (e.g.,
+    /// `TMode` for ARM, or `addrsize` and `opsize` for x86) to `value`:
+    /// for instructions lifted with `ctxt` if `range` is None, or for the
+    /// instructions lifted (with any context) starting within `range`
+    /// otherwise.
+    ///
+    /// Returns an error if the language has no such variable.
+    pub fn set_context(
+        &mut self,
+        ctxt: &mut ContextDatabase,
+        name: impl AsRef<str>,
+        value: u32,
+        range: Option<Range<Addr>>,
+    ) -> Result<(), LifterError> {
+        let name = name.as_ref();
+        if ctxt.get_default_value(name).is_none() {
+            return Err(LifterError::Context(name.to_owned()))
+        }
+
+        match range {
+            Some(range) => self.contexts.insert(name, range, value),
+            None => ctxt.set_variable_default(name, value)?,
+        }
+
+        // cached instructions are distinguished by the variables given
+        // values
+        self.cache.track(name);
+
+        Ok(())
+    }
+
+    /// The value of the context variable `name` for the instruction at
+    /// `addr` lifted with `ctxt` (or for any instruction lifted with
+    /// `ctxt` outside of the ranges given values by `set_context`, if
+    /// `addr` is None).
+    pub fn context_value(&self, ctxt: &ContextDatabase, name: impl AsRef<str>, addr: Option<&Addr>) -> Option<u32> {
+        let name = name.as_ref();
+        addr.and_then(|addr| self.contexts.value(name, addr))
+            .or_else(|| ctxt.get_default_value(name))
+    }
+}
@@ -0,0 +1,302 @@
+use std::fmt::{self, Display};
+
+use crate::ir::Confidence;
+use crate::lift::{Lifter, LifterBuilder, LifterBuilderError};
+use crate::oracles::HeuristicSubOracle;
+use crate::prelude::Endian;
+
+/// The container format the language of a `Candidate` was found in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Container {
+    Elf,
+    Pe,
+    MachO,
+    /// Bytes without a known container, e.g., a firmware image
+    Raw,
+}
+
+/// A language proposed for some bytes by `LifterBuilder::detect`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    processor: &'static str,
+    endian: Endian,
+    bits: u32,
+    variant: &'static str,
+    container: Container,
+    confidence: Confidence,
+    // for Raw, the density of the language's common prologues and returns
+    score: f64,
+}
+
+impl Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}",
+            self.processor,
+            if self.endian.is_big() { "BE" } else { "LE" },
+            self.bits,
+            self.variant,
+        )
+    }
+}
+
+impl Candidate {
+    fn new(
+        processor: &'static str,
+        endian: Endian,
+        bits: u32,
+        variant: &'static str,
+        container: Container,
+    ) -> Self {
+        Self {
+            processor,
+            endian,
+            bits,
+            variant,
+            container,
+            confidence: Confidence::Certain,
+            score: 1.0,
+        }
+    }
+
+    pub fn processor(&self) -> &'static str {
+        self.processor
+    }
+
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn variant(&self) -> &'static str {
+        self.variant
+    }
+
+    pub fn container(&self) -> Container {
+        self.container
+    }
+
+    /// `Certain` if the language is declared by a container's header, and
+    /// `Heuristic` otherwise.
+    pub fn confidence(&self) -> Confidence {
+        self.confidence
+    }
+
+    /// How well the bytes fit the language, used to rank candidates of the
+    /// same confidence.
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+
+    /// Builds a Lifter for the candidate's language using `builder`.
+    pub fn build(&self, builder: &LifterBuilder, convention: impl AsRef<str>) -> Result<Lifter, LifterBuilderError> {
+        builder.build_with(self.processor, self.endian, self.bits, self.variant, convention)
+    }
+}
+
+fn u16_at(bytes: &[u8], offset: usize, endian: Endian) -> Option<u16> {
+    let bytes = <[u8; 2]>::try_from(bytes.get(offset..offset + 2)?).ok()?;
+    Some(if endian.is_big() { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+}
+
+fn u32_at(bytes: &[u8], offset: usize, endian: Endian) -> Option<u32> {
+    let bytes = <[u8; 4]>::try_from(bytes.get(offset..offset + 4)?).ok()?;
+    Some(if endian.is_big() { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+}
+
+fn elf(bytes: &[u8]) -> Option<Candidate> {
+    if !bytes.starts_with(b"\x7fELF") {
+        return None
+    }
+
+    let bits = match bytes.get(4)? {
+        1 => 32,
+        2 => 64,
+        _ => return None,
+    };
+    let endian = match bytes.get(5)? {
+        1 => Endian::Little,
+        2 => Endian::Big,
+        _ => return None,
+    };
+
+    let (processor, bits, variant) = match u16_at(bytes, 18, endian)? {
+        2 => ("sparc", 32, "default"),
+        3 => ("x86", 32, "default"),
+        8 => ("MIPS", bits, "default"),
+        20 => ("PowerPC", 32, "default"),
+        21 => ("PowerPC", 64, "default"),
+        40 => ("ARM", 32, "v8"),
+        43 => ("sparc", 64, "default"),
+        62 => ("x86", 64, "default"),
+        183 => ("AARCH64", 64, "v8A"),
+        243 if bits == 64 => ("RISCV", 64, "RV64GC"),
+        243 => ("RISCV", 32, "RV32GC"),
+        _ => return None,
+    };
+
+    Some(Candidate::new(processor, endian, bits, variant, Container::Elf))
+}
+
+fn pe(bytes: &[u8]) -> Option<Candidate> {
+    if !bytes.starts_with(b"MZ") {
+        return None
+    }
+
+    let header = u32_at(bytes, 0x3c, Endian::Little)? as usize;
+    if bytes.get(header..header + 4)? != b"PE\0\0" {
+        return None
+    }
+
+    let (processor, bits, variant) = match u16_at(bytes, header + 4, Endian::Little)? {
+        0x014c => ("x86", 32, "default"),
+        0x8664 => ("x86", 64, "default"),
+        0x01c0 | 0x01c4 => ("ARM", 32, "v8"),
+        0xaa64 => ("AARCH64", 64, "v8A"),
+        _ => return None,
+    };
+
+    Some(Candidate::new(processor, Endian::Little, bits, variant, Container::Pe))
+}
+
+fn mach_o_cpu(cputype: u32, endian: Endian) -> Option<Candidate> {
+    let (processor, bits, variant) = match cputype {
+        0x0000_0007 => ("x86", 32, "default"),
+        0x0100_0007 => ("x86", 64, "default"),
+        0x0000_000c => ("ARM", 32, "v8"),
+        0x0100_000c => ("AARCH64", 64, "v8A"),
+        0x0000_0012 => ("PowerPC", 32, "default"),
+        0x0100_0012 => ("PowerPC", 64, "default"),
+        _ => return None,
+    };
+    Some(Candidate::new(processor, endian, bits, variant, Container::MachO))
+}
+
+fn mach_o(bytes: &[u8]) -> Vec<Candidate> {
+    let magic = match u32_at(bytes, 0, Endian::Big) {
+        Some(magic) => magic,
+        None => return Vec::new(),
+    };
+
+    match magic {
+        0xfeedface | 0xfeedfacf => u32_at(bytes, 4, Endian::Big)
+            .and_then(|cputype| mach_o_cpu(cputype, Endian::Big))
+            .into_iter()
+            .collect(),
+        0xcefaedfe | 0xcffaedfe => u32_at(bytes, 4, Endian::Little)
+            .and_then(|cputype| mach_o_cpu(cputype, Endian::Little))
+            .into_iter()
+            .collect(),
+        // universal binaries share their magic with Java's class files,
+        // which have far larger (major) versions than binaries have slices
+        0xcafebabe => {
+            let count = u32_at(bytes, 4, Endian::Big).unwrap_or(0) as usize;
+            if count == 0 || count > 16 {
+                return Vec::new()
+            }
+            (0..count)
+                .filter_map(|i| {
+                    let cputype = u32_at(bytes, 8 + i * 20, Endian::Big)?;
+                    // the endianness of each slice is that of its processor
+                    let endian = if cputype & 0xff == 0x12 {
+                        Endian::Big
+                    } else {
+                        Endian::Little
+                    };
+                    mach_o_cpu(cputype, endian)
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+// the languages whose common prologues and returns are known (see
+// HeuristicSubOracle), with whether they have compressed instructions
+const RAW: [(&str, Endian, u32, &str, bool); 8] = [
+    ("x86", Endian::Little, 64, "default", false),
+    ("x86", Endian::Little, 32, "default", false),
+    ("AARCH64", Endian::Little, 64, "v8A", false),
+    ("ARM", Endian::Little, 32, "v8", false),
+    ("ARM", Endian::Big, 32, "v8", false),
+    ("MIPS", Endian::Little, 32, "default", false),
+    ("MIPS", Endian::Big, 32, "default", false),
+    ("RISCV", Endian::Little, 64, "RV64GC", true),
+];
+
+fn raw(bytes: &[u8]) -> Vec<Candidate> {
+    RAW.iter()
+        .filter_map(|(processor, endian, bits, variant, compressed)| {
+            let score = HeuristicSubOracle::pattern_density(processor, *bits, *endian, *compressed, bytes);
+            if score > 0.0 {
+                let mut candidate = Candidate::new(processor, *endian, *bits, variant, Container::Raw);
+                candidate.confidence = Confidence::Heuristic;
+                candidate.score = score;
+                Some(candidate)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+impl LifterBuilder {
+    /// Proposes languages for `bytes`, most likely first.
+    ///
+    /// If `bytes` start with an ELF, PE, or Mach-O header, the language
+    /// declared by the header is proposed (or those of each slice of a
+    /// universal Mach-O binary). Otherwise, languages are proposed by the
+    /// density of their common function prologues and returns within
+    /// `bytes` (see `HeuristicSubOracle`); no candidates are proposed if
+    /// none match.
+    pub fn detect(bytes: &[u8]) -> Vec<Candidate> {
+        let mut candidates = elf(bytes)
+            .into_iter()
+            .chain(pe(bytes))
+            .chain(mach_o(bytes))
+            .collect::<Vec<_>>();
+
+        if candidates.is_empty() {
+            candidates = raw(bytes);
+        }
+
+        candidates.sort_by(|a, b| {
+            b.confidence
+                .cmp(&a.confidence)
+                .then_with(|| b.score.total_cmp(&a.score))
+        });
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let mut elf = vec![0u8; 64];
+        elf[..4].copy_from_slice(b"\x7fELF");
+        elf[4] = 2;
+        elf[5] = 1;
+        elf[18] = 62;
+
+        let candidates = LifterBuilder::detect(&elf);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].to_string(), "x86:LE:64:default");
+        assert_eq!(candidates[0].container(), Container::Elf);
+
+        // push rbp; mov rbp, rsp; ...; ret; int3
+        let sub = [0x55, 0x48, 0x89, 0xe5, 0x31, 0xc0, 0x5d, 0xc3, 0xcc, 0xcc];
+        let raw = sub.repeat(16);
+
+        let candidates = LifterBuilder::detect(&raw);
+        assert!(!candidates.is_empty());
+        assert_eq!(candidates[0].to_string(), "x86:LE:64:default");
+        assert_eq!(candidates[0].confidence(), Confidence::Heuristic);
+    }
+}
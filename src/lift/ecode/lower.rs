@@ -0,0 +1,486 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use fugue::ir::il::ecode::ECode;
+use fugue::ir::il::ecode::BinOp as ECodeBinOp;
+use fugue::ir::il::ecode::BinRel as ECodeBinRel;
+use fugue::ir::il::ecode::Cast as ECodeCast;
+use fugue::ir::il::ecode::Expr as ECodeExpr;
+use fugue::ir::il::ecode::UnOp as ECodeUnOp;
+use fugue::ir::il::ecode::UnRel as ECodeUnRel;
+use fugue::ir::il::ecode::Var as ECodeVar;
+use fugue::ir::il::ecode::{BranchTarget, Stmt};
+
+use crate::ir::{Addr, BitVec, Blk, Def, Expr, Jmp, Loc, SourceLoc, Var};
+use crate::ir::{BinOp, BinRel, Cast, UnOp, UnRel};
+use crate::prelude::{Entity, Identifiable};
+use crate::types::bv::BitVecT;
+
+/// Determines how instructions that repeat themselves (e.g., x86's
+/// `rep`-prefixed string operations) are represented after lifting.
+///
+/// SLEIGH specifications encode such instructions as a guard that skips
+/// the instruction when its count is exhausted, followed by a single
+/// iteration of the operation and a branch back to the start of the
+/// instruction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RepeatNormalisation {
+    /// Lift the instruction into an explicit loop of Blks: the guard
+    /// forms the loop header and the branch back to the start of the
+    /// instruction forms its back-edge.
+    Loop,
+    /// Summarise the instruction as a single intrinsic, named after its
+    /// mnemonic, taking its count and direction as operands. Instructions
+    /// whose count cannot be determined fall back to `Loop`.
+    Intrinsic,
+}
+
+impl Default for RepeatNormalisation {
+    fn default() -> Self {
+        Self::Loop
+    }
+}
+
+/// The intrinsic ending the Blk lifted for an instruction that could not
+/// be decoded, e.g., as the Blk before it runs into data; the Blk gives
+/// its address, and the reason as the operands of its source (see
+/// `SourceLoc::operands`).
+pub const INVALID_INSN: &str = "decode.invalid";
+
+/// A lifted instruction awaiting lowering into Blks.
+#[derive(Clone)]
+pub(crate) struct ECodeInsn {
+    pub(crate) addr: Addr,
+    pub(crate) ecode: ECode,
+    // the name of the intrinsic to summarise the instruction with, if
+    // it is a repeated instruction and we are configured to do so
+    pub(crate) summary: Option<Arc<str>>,
+    // the instruction is a valid target of indirect flows
+    pub(crate) landing_pad: bool,
+    // where the instruction was lifted from, shared by its Blks
+    pub(crate) source: Arc<SourceLoc>,
+}
+
+/// Lowers ECode into our IR.
+///
+/// Register variables are mapped to physical variables named after the
+/// register they correspond to, temporaries to transient variables, and
+/// variables in any other space to accesses to memory.
+pub(crate) struct ECodeLowering<'a> {
+    registers: &'a BTreeMap<(u64, usize), Arc<str>>,
+    memory: &'a Var,
+    addr_bits: u32,
+}
+
+impl<'a> ECodeLowering<'a> {
+    pub(crate) fn new(
+        registers: &'a BTreeMap<(u64, usize), Arc<str>>,
+        memory: &'a Var,
+        addr_bits: u32,
+    ) -> Self {
+        Self {
+            registers,
+            memory,
+            addr_bits,
+        }
+    }
+
+    fn addr(&self, offset: u64) -> Addr {
+        Addr::from(BitVec::from_u64(offset, self.addr_bits as usize))
+    }
+
+    fn var(&self, var: &ECodeVar) -> Var {
+        let bits = var.bits() as u32;
+        let typ = BitVecT::with_bits(bits, false);
+        if var.space().is_register() {
+            let name = self
+                .registers
+                .get(&(var.offset(), var.bits() / 8))
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| format!("reg{:x}", var.offset()));
+            Var::physical(name, typ).into()
+        } else {
+            Var::transient(format!("tmp{:x}", var.offset()), typ).into()
+        }
+    }
+
+    fn is_memory(var: &ECodeVar) -> bool {
+        !(var.space().is_register() || var.space().is_unique())
+    }
+
+    pub(crate) fn expr(&self, expr: &ECodeExpr) -> Expr {
+        match expr {
+            ECodeExpr::UnRel(op, expr) => Expr::un_rel(Self::unrel(*op), self.expr(expr)),
+            ECodeExpr::BinRel(op, lexpr, rexpr) => {
+                Expr::bin_rel(Self::binrel(*op), self.expr(lexpr), self.expr(rexpr))
+            }
+            ECodeExpr::UnOp(op, expr) => Expr::un_op(Self::unop(*op), self.expr(expr)),
+            ECodeExpr::BinOp(op, lexpr, rexpr) => {
+                Expr::bin_op(Self::binop(*op), self.expr(lexpr), self.expr(rexpr))
+            }
+            ECodeExpr::Cast(expr, cast) => Expr::cast(self.expr(expr), Self::cast(cast)),
+            // we model a single memory; the space is implied by the address
+            ECodeExpr::Load(expr, bits, _space) => {
+                Expr::load(self.expr(expr), *bits as u32, self.memory.clone())
+            }
+            ECodeExpr::Extract(expr, lsb, msb) => {
+                Expr::extract(self.expr(expr), *lsb as u32, *msb as u32)
+            }
+            ECodeExpr::Concat(lexpr, rexpr) => Expr::concat(self.expr(lexpr), self.expr(rexpr)),
+            ECodeExpr::IfElse(cond, texpr, fexpr) => {
+                Expr::ite(self.expr(cond), self.expr(texpr), self.expr(fexpr))
+            }
+            ECodeExpr::Call(tgt, args, bits) => {
+                let tgt = match &**tgt {
+                    BranchTarget::Location(loc) => {
+                        Expr::Val(BitVec::from_u64(loc.address().offset(), self.addr_bits as usize))
+                    }
+                    BranchTarget::Computed(expr) => self.expr(expr),
+                };
+                Expr::intrinsic(
+                    "call",
+                    std::iter::once(tgt).chain(args.iter().map(|arg| self.expr(arg))),
+                    *bits as u32,
+                )
+            }
+            ECodeExpr::Intrinsic(name, args, bits) => Expr::intrinsic(
+                name.clone(),
+                args.iter().map(|arg| self.expr(arg)),
+                *bits as u32,
+            ),
+            ECodeExpr::Val(bv) => Expr::Val(bv.clone()),
+            ECodeExpr::Var(var) => {
+                if Self::is_memory(var) {
+                    Expr::load(
+                        BitVec::from_u64(var.offset(), self.addr_bits as usize),
+                        var.bits() as u32,
+                        self.memory.clone(),
+                    )
+                } else {
+                    Expr::Var(self.var(var))
+                }
+            }
+        }
+    }
+
+    fn assign(&self, var: &ECodeVar, expr: &ECodeExpr) -> Entity<Def> {
+        if Self::is_memory(var) {
+            Def::store(
+                BitVec::from_u64(var.offset(), self.addr_bits as usize),
+                self.expr(expr),
+                var.bits() as u32,
+                self.memory.clone(),
+            )
+        } else {
+            Def::assign(self.var(var), self.expr(expr))
+        }
+    }
+
+    fn unrel(op: ECodeUnRel) -> UnRel {
+        match op {
+            ECodeUnRel::NAN => UnRel::Nan,
+        }
+    }
+
+    fn binrel(op: ECodeBinRel) -> BinRel {
+        match op {
+            ECodeBinRel::EQ => BinRel::Eq,
+            ECodeBinRel::NEQ => BinRel::Neq,
+            ECodeBinRel::LT => BinRel::Lt,
+            ECodeBinRel::LE => BinRel::Le,
+            ECodeBinRel::SLT => BinRel::SLt,
+            ECodeBinRel::SLE => BinRel::SLe,
+            ECodeBinRel::SBORROW => BinRel::SBorrow,
+            ECodeBinRel::CARRY => BinRel::Carry,
+            ECodeBinRel::SCARRY => BinRel::SCarry,
+        }
+    }
+
+    fn unop(op: ECodeUnOp) -> UnOp {
+        match op {
+            ECodeUnOp::NOT => UnOp::Not,
+            ECodeUnOp::NEG => UnOp::Neg,
+            ECodeUnOp::ABS => UnOp::Abs,
+            ECodeUnOp::SQRT => UnOp::Sqrt,
+            ECodeUnOp::CEILING => UnOp::Ceiling,
+            ECodeUnOp::FLOOR => UnOp::Floor,
+            ECodeUnOp::ROUND => UnOp::Round,
+            ECodeUnOp::POPCOUNT => UnOp::PopCount,
+        }
+    }
+
+    fn binop(op: ECodeBinOp) -> BinOp {
+        match op {
+            ECodeBinOp::AND => BinOp::And,
+            ECodeBinOp::OR => BinOp::Or,
+            ECodeBinOp::XOR => BinOp::Xor,
+            ECodeBinOp::ADD => BinOp::Add,
+            ECodeBinOp::SUB => BinOp::Sub,
+            ECodeBinOp::DIV => BinOp::Div,
+            ECodeBinOp::SDIV => BinOp::SDiv,
+            ECodeBinOp::MUL => BinOp::Mul,
+            ECodeBinOp::REM => BinOp::Rem,
+            ECodeBinOp::SREM => BinOp::SRem,
+            ECodeBinOp::SHL => BinOp::Shl,
+            ECodeBinOp::SAR => BinOp::Sar,
+            ECodeBinOp::SHR => BinOp::Shr,
+        }
+    }
+
+    fn cast(cast: &ECodeCast) -> Cast {
+        match cast {
+            ECodeCast::Bool => Cast::Bool,
+            ECodeCast::Float(format) => Cast::Float(format.bits() as u32),
+            ECodeCast::Signed(bits) => Cast::Signed(*bits as u32),
+            ECodeCast::Unsigned(bits) => Cast::Unsigned(*bits as u32),
+            ECodeCast::High(bits) => Cast::High(*bits as u32),
+            ECodeCast::Low(bits) => Cast::Low(*bits as u32),
+        }
+    }
+
+    /// Returns the operands of the summarising intrinsic for `ecode` (i.e.,
+    /// its count and, if the architecture has one, its direction flag), if
+    /// it is a repeated instruction.
+    ///
+    /// A repeated instruction begins with a guard `if (count == 0) goto
+    /// <next>` and ends with a branch back to its own start.
+    pub(crate) fn repeat_operands(&self, ecode: &ECode) -> Option<Vec<Expr>> {
+        let address = ecode.address();
+        let naddress = ecode.address() + ecode.length();
+
+        let targets = |tgt: &BranchTarget, offset: u64| match tgt {
+            BranchTarget::Location(loc) => {
+                loc.address().offset() == offset && loc.position() == 0
+            }
+            BranchTarget::Computed(ECodeExpr::Val(bv)) => bv.to_u64() == Some(offset),
+            BranchTarget::Computed(_) => false,
+        };
+
+        let count = match ecode.operations().first()? {
+            Stmt::CBranch(ECodeExpr::BinRel(ECodeBinRel::EQ, lexpr, rexpr), tgt)
+                if targets(tgt, naddress.offset()) =>
+            {
+                match (&**lexpr, &**rexpr) {
+                    (ECodeExpr::Var(var), ECodeExpr::Val(bv))
+                    | (ECodeExpr::Val(bv), ECodeExpr::Var(var))
+                        if bv.is_zero() =>
+                    {
+                        *var
+                    }
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+
+        if !matches!(ecode.operations().last()?, Stmt::Branch(tgt) if targets(tgt, address.offset())) {
+            return None;
+        }
+
+        let mut operands = vec![Expr::Var(self.var(&count))];
+
+        if let Some((&(_, size), name)) = self.registers.iter().find(|(_, name)| &***name == "DF") {
+            let typ = BitVecT::with_bits(8 * size as u32, false);
+            operands.push(Var::physical(&**name, typ).into());
+        }
+
+        Some(operands)
+    }
+
+    /// Lowers a sequence of lifted instructions into Blks; the Blks of each
+    /// instruction are ordered by their position within the instruction,
+    /// and the first Blk of each instruction is given its address.
+    ///
+    /// Flows to the start of any instruction within the sequence are
+    /// resolved to that instruction's first Blk; the final instruction
+    /// falls through to `end`.
+    pub(crate) fn lower(&self, insns: Vec<ECodeInsn>, end: &Addr) -> Vec<Entity<Blk>> {
+        self.lower_until(insns, end, None)
+    }
+
+    /// As `lower`, but the final instruction falls through to a Blk at
+    /// `end` marking that the instruction there could not be decoded
+    /// (see `INVALID_INSN`), giving the reason as its operands.
+    pub(crate) fn lower_invalid(&self, insns: Vec<ECodeInsn>, end: &Addr, reason: impl ToString) -> Vec<Entity<Blk>> {
+        let mut invalid = Blk::new(Some(end.clone()));
+        invalid.set_source(Arc::new(SourceLoc::new(end.clone(), 0).with_disassembly("(bad)", reason.to_string())));
+        invalid.add_jmp(Jmp::intrinsic(INVALID_INSN, []));
+        self.lower_until(insns, end, Some(invalid))
+    }
+
+    fn lower_until(&self, insns: Vec<ECodeInsn>, end: &Addr, invalid: Option<Entity<Blk>>) -> Vec<Entity<Blk>> {
+        // blks for each instruction, keyed by the position of the
+        // operation that begins them
+        let mut insn_blks = insns
+            .iter()
+            .map(|insn| {
+                let mut leaders = BTreeSet::from([0]);
+                if insn.summary.is_none() {
+                    let address = insn.ecode.address();
+                    let op_count = insn.ecode.operations().len();
+                    for (i, stmt) in insn.ecode.operations().iter().enumerate() {
+                        match stmt {
+                            Stmt::Branch(BranchTarget::Location(loc))
+                            | Stmt::CBranch(_, BranchTarget::Location(loc))
+                                if *loc.address() == address && loc.position() < op_count =>
+                            {
+                                leaders.insert(loc.position());
+                                leaders.insert(i + 1);
+                            }
+                            Stmt::Branch(_)
+                            | Stmt::CBranch(_, _)
+                            | Stmt::Call(_, _)
+                            | Stmt::Return(_)
+                            | Stmt::Intrinsic(_, _) => {
+                                leaders.insert(i + 1);
+                            }
+                            _ => (),
+                        }
+                    }
+                    leaders.retain(|pos| *pos < op_count.max(1));
+                }
+                leaders
+                    .into_iter()
+                    .map(|pos| {
+                        let addr = if pos == 0 { Some(insn.addr.clone()) } else { None };
+                        let mut blk = Blk::new(addr);
+                        blk.set_source(insn.source.clone());
+                        (pos, blk)
+                    })
+                    .collect::<BTreeMap<_, _>>()
+            })
+            .collect::<Vec<_>>();
+
+        let locs = insn_blks
+            .iter()
+            .map(|blks| blks.iter().map(|(pos, blk)| (*pos, blk.id())).collect::<BTreeMap<_, _>>())
+            .collect::<Vec<_>>();
+
+        let entries = insns
+            .iter()
+            .zip(locs.iter())
+            .map(|(insn, locs)| (insn.ecode.address().offset(), locs[&0]))
+            .collect::<BTreeMap<_, _>>();
+
+        let next = |k: usize| -> Loc {
+            if let Some(nlocs) = locs.get(k + 1) {
+                Loc::Resolved(nlocs[&0])
+            } else if let Some(ref invalid) = invalid {
+                Loc::Resolved(invalid.id())
+            } else {
+                Loc::Fixed(end.clone())
+            }
+        };
+
+        let target = |k: usize, tgt: &BranchTarget| -> Loc {
+            let ecode = &insns[k].ecode;
+            match tgt {
+                BranchTarget::Location(loc) => {
+                    if *loc.address() == ecode.address() {
+                        if let Some(id) = locs[k].get(&loc.position()) {
+                            Loc::Resolved(*id)
+                        } else {
+                            next(k)
+                        }
+                    } else if let Some(id) = entries.get(&loc.address().offset()).filter(|_| loc.position() == 0) {
+                        Loc::Resolved(*id)
+                    } else {
+                        Loc::Fixed(self.addr(loc.address().offset()))
+                    }
+                }
+                BranchTarget::Computed(ECodeExpr::Val(bv)) if bv.to_u64().is_some() => {
+                    let offset = bv.to_u64().unwrap();
+                    if let Some(id) = entries.get(&offset) {
+                        Loc::Resolved(*id)
+                    } else {
+                        Loc::Fixed(self.addr(offset))
+                    }
+                }
+                BranchTarget::Computed(expr) => Loc::Computed(self.expr(expr)),
+            }
+        };
+
+        let fall = |k: usize, i: usize| -> Loc {
+            if let Some(id) = locs[k].get(&(i + 1)) {
+                Loc::Resolved(*id)
+            } else {
+                next(k)
+            }
+        };
+
+        for (k, insn) in insns.iter().enumerate() {
+            let blks = &mut insn_blks[k];
+
+            if insn.landing_pad {
+                blks.get_mut(&0).unwrap().set_landing_pad(true);
+            }
+
+            if let Some(ref name) = insn.summary {
+                let blk = blks.get_mut(&0).unwrap();
+                let operands = self.repeat_operands(&insn.ecode).unwrap_or_default();
+                blk.add_jmp(Jmp::intrinsic(name.clone(), operands));
+                blk.add_jmp(Jmp::branch(next(k)));
+                continue;
+            }
+
+            let mut current = 0;
+            let mut terminated = false;
+
+            for (i, stmt) in insn.ecode.operations().iter().enumerate() {
+                if i != current && blks.contains_key(&i) {
+                    if !terminated {
+                        let id = blks[&i].id();
+                        blks.get_mut(&current).unwrap().add_jmp(Jmp::branch(id));
+                    }
+                    current = i;
+                    terminated = false;
+                }
+
+                let blk = blks.get_mut(&current).unwrap();
+                match stmt {
+                    Stmt::Assign(var, expr) => blk.add_def(self.assign(var, expr)),
+                    Stmt::Store(loc, val, bits, _space) => blk.add_def(Def::store(
+                        self.expr(loc),
+                        self.expr(val),
+                        *bits as u32,
+                        self.memory.clone(),
+                    )),
+                    Stmt::Branch(tgt) => {
+                        blk.add_jmp(Jmp::branch(target(k, tgt)));
+                        terminated = true;
+                    }
+                    Stmt::CBranch(cond, tgt) => {
+                        blk.add_jmp(Jmp::cbranch(target(k, tgt), self.expr(cond)));
+                        blk.add_jmp(Jmp::branch(fall(k, i)));
+                        terminated = true;
+                    }
+                    Stmt::Call(tgt, args) => {
+                        blk.add_jmp(Jmp::call(target(k, tgt), args.iter().map(|arg| self.expr(arg))));
+                        blk.add_jmp(Jmp::branch(fall(k, i)));
+                        terminated = true;
+                    }
+                    Stmt::Return(tgt) => {
+                        blk.add_jmp(Jmp::return_(target(k, tgt)));
+                        terminated = true;
+                    }
+                    Stmt::Intrinsic(name, args) => {
+                        blk.add_jmp(Jmp::intrinsic(name.clone(), args.iter().map(|arg| self.expr(arg))));
+                        blk.add_jmp(Jmp::branch(fall(k, i)));
+                        terminated = true;
+                    }
+                    Stmt::Skip => (),
+                }
+            }
+
+            if !terminated {
+                blks.get_mut(&current).unwrap().add_jmp(Jmp::branch(next(k)));
+            }
+        }
+
+        insn_blks
+            .into_iter()
+            .flat_map(|blks| blks.into_values())
+            .chain(invalid)
+            .collect()
+    }
+}
@@ -1,2 +1,3 @@
+pub mod lower;
 pub mod passes;
-pub mod utils;
\ No newline at end of file
+pub mod utils;
@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use fugue::ir::il::ecode::ECode;
+use fugue::ir::il::ecode::Expr as ECodeExpr;
+use fugue::ir::il::ecode::{BranchTarget, Stmt};
+
+/// Determines how AArch64 pointer authentication (PAC) and memory tagging
+/// (MTE) operations are represented after lifting.
+///
+/// SLEIGH specifications model these operations as user-defined
+/// operations, which we would otherwise lift as opaque intrinsics whose
+/// names vary between specification versions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PointerModelling {
+    /// Keep each operation as an intrinsic, using a canonical name:
+    /// `pac.sign`, `pac.auth`, or `pac.strip` for pointer authentication,
+    /// and `mte.<operation>` for memory tagging.
+    Intrinsic,
+    /// Treat each operation as a no-op: operations that compute a pointer
+    /// yield their pointer operand unchanged (i.e., signatures and tags
+    /// are never inserted, and authentication always succeeds), and
+    /// operations in statement position are removed.
+    Transparent,
+}
+
+impl Default for PointerModelling {
+    fn default() -> Self {
+        Self::Intrinsic
+    }
+}
+
+// the memory tagging operations, as named by their instructions, or by
+// the user-defined operations specifications model them with
+const TAG_OPERATIONS: [&str; 20] = [
+    "irg",
+    "gmi",
+    "addg",
+    "subg",
+    "subp",
+    "subps",
+    "ldg",
+    "ldgm",
+    "stg",
+    "stgm",
+    "stgp",
+    "st2g",
+    "stzg",
+    "stzgm",
+    "stz2g",
+    "choosenonexcludedtag",
+    "allocationtagfromaddress",
+    "addresswithallocationtag",
+    "loadallocationtag",
+    "storeallocationtag",
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Operation {
+    Sign,
+    Auth,
+    Strip,
+    Generic,
+    Tag,
+    LandingPad,
+}
+
+impl Operation {
+    fn classify(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        if name == "bti" || name == "branchtargetidentification" {
+            Some(Self::LandingPad)
+        } else if name == "pacga" {
+            Some(Self::Generic)
+        } else if name.starts_with("xpac") {
+            Some(Self::Strip)
+        } else if name.starts_with("pac") {
+            Some(Self::Sign)
+        } else if name.starts_with("aut") {
+            Some(Self::Auth)
+        } else if TAG_OPERATIONS.contains(&&*name) {
+            Some(Self::Tag)
+        } else {
+            None
+        }
+    }
+
+    fn canonical_name(&self, name: &str) -> Arc<str> {
+        match self {
+            Self::Sign => Arc::from("pac.sign"),
+            Self::Auth => Arc::from("pac.auth"),
+            Self::Strip => Arc::from("pac.strip"),
+            Self::Generic => Arc::from("pac.generic"),
+            Self::Tag => Arc::from(format!("mte.{}", name.to_ascii_lowercase())),
+            Self::LandingPad => Arc::from("bti"),
+        }
+    }
+
+    // operations whose result is their first operand with its signature
+    // or tag modified; pacga computes a MAC, not a pointer
+    fn is_pointer(&self) -> bool {
+        matches!(self, Self::Sign | Self::Auth | Self::Strip | Self::Tag)
+    }
+}
+
+pub(crate) struct ECodeAArch64Pass {
+    mode: PointerModelling,
+}
+
+impl ECodeAArch64Pass {
+    pub(crate) fn new(mode: PointerModelling) -> Self {
+        Self { mode }
+    }
+
+    fn rewrite_expr(&self, expr: &mut ECodeExpr) {
+        match expr {
+            ECodeExpr::UnRel(_, expr)
+            | ECodeExpr::UnOp(_, expr)
+            | ECodeExpr::Cast(expr, _)
+            | ECodeExpr::Load(expr, _, _)
+            | ECodeExpr::Extract(expr, _, _) => self.rewrite_expr(expr),
+            ECodeExpr::BinRel(_, lexpr, rexpr)
+            | ECodeExpr::BinOp(_, lexpr, rexpr)
+            | ECodeExpr::Concat(lexpr, rexpr) => {
+                self.rewrite_expr(lexpr);
+                self.rewrite_expr(rexpr);
+            }
+            ECodeExpr::IfElse(cond, texpr, fexpr) => {
+                self.rewrite_expr(cond);
+                self.rewrite_expr(texpr);
+                self.rewrite_expr(fexpr);
+            }
+            ECodeExpr::Call(tgt, args, _) => {
+                if let BranchTarget::Computed(ref mut expr) = **tgt {
+                    self.rewrite_expr(expr);
+                }
+                for arg in args.iter_mut() {
+                    self.rewrite_expr(arg);
+                }
+            }
+            ECodeExpr::Intrinsic(name, args, bits) => {
+                for arg in args.iter_mut() {
+                    self.rewrite_expr(arg);
+                }
+
+                let op = if let Some(op) = Operation::classify(name) {
+                    op
+                } else {
+                    return
+                };
+
+                let pointer = if self.mode == PointerModelling::Transparent
+                    && op.is_pointer()
+                    && !args.is_empty()
+                    && args[0].bits() == *bits
+                {
+                    Some(*args.remove(0))
+                } else {
+                    let canonical = op.canonical_name(name);
+                    *name = canonical;
+                    None
+                };
+
+                if let Some(pointer) = pointer {
+                    *expr = pointer;
+                }
+            }
+            ECodeExpr::Val(_) | ECodeExpr::Var(_) => (),
+        }
+    }
+
+    /// Rewrites the PAC, BTI, and MTE operations within `ecode`; returns
+    /// true if the instruction is a BTI landing pad, i.e., a valid target
+    /// of an indirect branch or call.
+    ///
+    /// Landing pads have no effect on the instruction's semantics, and are
+    /// removed so that they do not terminate the instruction's Blk.
+    pub(crate) fn apply(&self, ecode: &mut ECode) -> bool {
+        let mut landing_pad = false;
+
+        for op in ecode.operations_mut().iter_mut() {
+            match op {
+                Stmt::Assign(_, expr) | Stmt::Return(BranchTarget::Computed(expr)) => {
+                    self.rewrite_expr(expr)
+                }
+                Stmt::Store(loc, val, _, _) => {
+                    self.rewrite_expr(loc);
+                    self.rewrite_expr(val);
+                }
+                Stmt::CBranch(cond, tgt) => {
+                    self.rewrite_expr(cond);
+                    if let BranchTarget::Computed(expr) = tgt {
+                        self.rewrite_expr(expr);
+                    }
+                }
+                Stmt::Branch(BranchTarget::Computed(expr)) => self.rewrite_expr(expr),
+                Stmt::Call(tgt, args) => {
+                    if let BranchTarget::Computed(expr) = tgt {
+                        self.rewrite_expr(expr);
+                    }
+                    for arg in args.iter_mut() {
+                        self.rewrite_expr(arg);
+                    }
+                }
+                Stmt::Intrinsic(name, args) => {
+                    for arg in args.iter_mut() {
+                        self.rewrite_expr(arg);
+                    }
+
+                    match Operation::classify(name) {
+                        Some(Operation::LandingPad) => {
+                            landing_pad = true;
+                            *op = Stmt::skip();
+                        }
+                        Some(_) if self.mode == PointerModelling::Transparent => {
+                            *op = Stmt::skip();
+                        }
+                        Some(op) => {
+                            let canonical = op.canonical_name(name);
+                            *name = canonical;
+                        }
+                        None => (),
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        landing_pad
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_classify_tag_operations() {
+        assert_eq!(Operation::classify("IRG"), Some(Operation::Tag));
+        assert_eq!(Operation::classify("AllocationTagFromAddress"), Some(Operation::Tag));
+        // other operations that mention tags are not memory tagging
+        assert_eq!(Operation::classify("set_tag_bits"), None);
+        assert_eq!(Operation::classify("vtag"), None);
+    }
+}
@@ -0,0 +1,162 @@
+use fugue::ir::il::ecode::ECode;
+use fugue::ir::il::ecode::Expr as ECodeExpr;
+use fugue::ir::il::ecode::Var as ECodeVar;
+use fugue::ir::il::ecode::{BranchTarget, Location, Stmt};
+use fugue::ir::space::AddressSpaceId;
+
+use super::Visit;
+
+// the end of the temporaries used by the operations visited
+struct Temporaries(u64);
+
+impl<'ecode> Visit<'ecode> for Temporaries {
+    fn visit_var(&mut self, var: &'ecode ECodeVar) {
+        if var.space().is_unique() {
+            self.0 = self.0.max(var.offset() + (var.bits() as u64 + 7) / 8);
+        }
+    }
+}
+
+/// Splices the operations of the instructions in the delay slots of a
+/// branch (e.g., on MIPS or SPARC) into the branch's ECode, so that they
+/// take effect after the branch's condition and target are computed, but
+/// before control leaves the branch; the ECode then spans the delay slots,
+/// so that its fall-through follows them.
+///
+/// The condition (or computed target) of the flow leaving the branch is
+/// copied into a fresh temporary (in the `unique` space) ahead of the
+/// delay slots, as they may write to the variables it reads (e.g., MIPS's
+/// `beq v0, zero, L; addiu v0, v0, 1`).
+pub(crate) struct ECodeDelaySlotPass {
+    slots: Vec<ECode>,
+    unique: AddressSpaceId,
+}
+
+impl ECodeDelaySlotPass {
+    pub(crate) fn new(slots: Vec<ECode>, unique: AddressSpaceId) -> Self {
+        Self { slots, unique }
+    }
+
+    // a temporary not used by ecode or the delay slots
+    fn temporary(&self, ecode: &ECode, bits: usize) -> ECodeVar {
+        let mut temporaries = Temporaries(0);
+        for op in ecode.operations().iter().chain(self.slots.iter().flat_map(|slot| slot.operations().iter())) {
+            temporaries.visit_stmt(op);
+        }
+        // aligned, as temporaries are
+        ECodeVar::new(self.unique, (temporaries.0 + 0xf) & !0xf, bits, 0)
+    }
+
+    /// Returns false, leaving `ecode` unchanged, if an instruction in a
+    /// delay slot has flows of its own (whose behaviour is undefined on
+    /// most architectures).
+    pub(crate) fn apply(self, ecode: &mut ECode) -> bool {
+        let address = ecode.address();
+        let fall = address.offset() + ecode.length() as u64;
+        let length = ecode.length() + self.slots.iter().map(|slot| slot.length()).sum::<usize>();
+        let end = address.offset() + length as u64;
+
+        // flows to the first delay slot or past the last skip the delay
+        // slots (e.g., MIPS's branch-likely instructions, if not taken)
+        let skips_slots = |tgt: &BranchTarget| match tgt {
+            BranchTarget::Location(loc) => {
+                loc.position() == 0 && (loc.address().offset() == fall || loc.address().offset() == end)
+            }
+            BranchTarget::Computed(ECodeExpr::Val(bv)) => {
+                bv.to_u64().map(|off| off == fall || off == end).unwrap_or(false)
+            }
+            BranchTarget::Computed(_) => false,
+        };
+        let is_local = |tgt: &BranchTarget| {
+            matches!(tgt, BranchTarget::Location(loc) if *loc.address() == address)
+        };
+
+        // the delay slots execute ahead of the first flow leaving the
+        // branch
+        let pos = ecode
+            .operations()
+            .iter()
+            .position(|op| match op {
+                Stmt::Branch(tgt) | Stmt::CBranch(_, tgt) => !is_local(tgt) && !skips_slots(tgt),
+                Stmt::Call(_, _) | Stmt::Return(_) => true,
+                _ => false,
+            })
+            .unwrap_or_else(|| ecode.operations().len());
+
+        // the expression evaluated by the flow leaving the branch, which
+        // must be evaluated before the delay slots
+        let evaluated = ecode.operations().get(pos).and_then(|op| match op {
+            Stmt::CBranch(cond, _) => Some(cond),
+            Stmt::Branch(BranchTarget::Computed(expr))
+            | Stmt::Call(BranchTarget::Computed(expr), _)
+            | Stmt::Return(BranchTarget::Computed(expr)) => Some(expr),
+            _ => None,
+        });
+        let captured = match evaluated {
+            Some(expr) if !matches!(expr, ECodeExpr::Val(_)) => {
+                let var = self.temporary(ecode, expr.bits());
+                Some(Stmt::Assign(var, expr.clone()))
+            }
+            _ => None,
+        };
+        let start = pos + captured.iter().count();
+
+        let mut delayed = Vec::new();
+        for slot in self.slots.iter() {
+            let base = start + delayed.len();
+            let count = slot.operations().len();
+            let saddr = slot.address();
+            let snext = saddr.offset() + slot.length() as u64;
+
+            for op in slot.operations() {
+                let mut op = op.clone();
+                match op {
+                    Stmt::Branch(ref mut tgt) | Stmt::CBranch(_, ref mut tgt) => {
+                        let position = match tgt {
+                            BranchTarget::Location(loc) if *loc.address() == saddr => loc.position(),
+                            BranchTarget::Location(loc) if loc.address().offset() == snext && loc.position() == 0 => count,
+                            _ => return false,
+                        };
+                        *tgt = BranchTarget::Location(Location::new(address.clone(), base + position));
+                    }
+                    Stmt::Call(_, _) | Stmt::Return(_) => return false,
+                    _ => (),
+                }
+                delayed.push(op);
+            }
+        }
+
+        let count = delayed.len() + captured.iter().count();
+        for op in ecode.operations_mut().iter_mut() {
+            if let Stmt::Branch(ref mut tgt) | Stmt::CBranch(_, ref mut tgt) = op {
+                let retarget = match *tgt {
+                    BranchTarget::Location(ref loc) if *loc.address() == address && loc.position() > pos => {
+                        Some(Location::new(address.clone(), loc.position() + count))
+                    }
+                    ref tgt if skips_slots(tgt) => Some(Location::new(address.clone() + length, 0)),
+                    _ => None,
+                };
+                if let Some(loc) = retarget {
+                    *tgt = BranchTarget::Location(loc);
+                }
+            }
+        }
+
+        if let Some(Stmt::Assign(ref var, _)) = captured {
+            let var = ECodeExpr::Var(*var);
+            match ecode.operations_mut()[pos] {
+                Stmt::CBranch(ref mut cond, _) => *cond = var,
+                Stmt::Branch(BranchTarget::Computed(ref mut expr))
+                | Stmt::Call(BranchTarget::Computed(ref mut expr), _)
+                | Stmt::Return(BranchTarget::Computed(ref mut expr)) => *expr = var,
+                _ => (),
+            }
+        }
+
+        ecode.operations_mut().insert_many(pos, captured.into_iter().chain(delayed));
+        ecode.length = length;
+        ecode.delay_slots = 0;
+
+        true
+    }
+}
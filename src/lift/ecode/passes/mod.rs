@@ -10,11 +10,33 @@
 /// - We replace all aliased variables, including register views,
 ///   such as AL, AH, AX when we are dealing with x86, with the
 ///   base register, e.g., EAX or RAX.
+/// 
+/// - We normalise predicated instructions (e.g., ARM conditional
+///   execution and Thumb IT blocks) so that their guards take a
+///   consistent form, or are folded into ite-expressions.
+/// 
+/// - We give AArch64 pointer authentication and memory tagging
+///   operations canonical names (or elide them), and remove BTI
+///   landing pads, recording them on the Blks they begin.
+/// 
+/// - We move the instructions in the delay slots of branches (e.g., on
+///   MIPS) into the branches, ahead of the flows leaving them.
+
+pub(crate) mod aarch64;
+pub(crate) use aarch64::ECodeAArch64Pass;
+pub use aarch64::PointerModelling;
 
 pub(crate) mod aliases;
 #[allow(unused_imports)]
 pub(crate) use aliases::{ECodeVarIndex, ECodeVarAliasNormalisePass};
 
+pub(crate) mod delay;
+pub(crate) use delay::ECodeDelaySlotPass;
+
+pub(crate) mod predicates;
+pub(crate) use predicates::ECodePredicateNormalisePass;
+pub use predicates::PredicateNormalisation;
+
 pub(crate) mod visit;
 pub(crate) use visit::Visit;
 
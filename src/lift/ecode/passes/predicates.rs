@@ -0,0 +1,166 @@
+use std::collections::HashSet;
+
+use fugue::ir::il::ecode::ECode;
+use fugue::ir::il::ecode::Expr as ECodeExpr;
+use fugue::ir::il::ecode::Var as ECodeVar;
+use fugue::ir::il::ecode::{BranchTarget, Location, Stmt};
+
+use crate::lift::ecode::passes::Visit;
+
+/// Determines how predicated instructions (e.g., ARM conditional
+/// execution, instructions within a Thumb IT block, or x86's cmovcc)
+/// are represented after lifting.
+///
+/// SLEIGH specifications encode such instructions as a guard of the
+/// form `if (c) goto <skip>`, followed by the instruction's effects;
+/// however, the position of the guard and the form of its target differ
+/// between specifications.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PredicateNormalisation {
+    /// Keep the guard as a conditional branch, but ensure its target is
+    /// always the fall-through location of the instruction; this yields
+    /// a CBranch diamond when the instruction is lifted into Blks.
+    Branch,
+    /// Fold the guard into the instruction's effects using ite-expressions
+    /// (i.e., `x = e` becomes `x = c ? x : e`), removing the guard entirely.
+    /// Instructions whose guarded effects include control-flow, or whose
+    /// effects clobber the guard's operands, fall back to `Branch`.
+    Select,
+}
+
+impl Default for PredicateNormalisation {
+    fn default() -> Self {
+        Self::Branch
+    }
+}
+
+pub(crate) struct ECodePredicateNormalisePass {
+    mode: PredicateNormalisation,
+}
+
+impl ECodePredicateNormalisePass {
+    pub(crate) fn new(mode: PredicateNormalisation) -> Self {
+        Self { mode }
+    }
+
+    /// Returns the position of the guard within `ecode`, if it has one. A
+    /// guard is a conditional branch whose target skips over all of the
+    /// remaining operations of the instruction, and which is followed only
+    /// by straight-line operations.
+    fn guard(ecode: &ECode) -> Option<usize> {
+        let address = ecode.address();
+        let naddress = ecode.address() + ecode.length();
+        let op_count = ecode.operations().len();
+
+        let skips_rest = |tgt: &BranchTarget| match tgt {
+            BranchTarget::Location(loc) => {
+                (*loc.address() == address && loc.position() == op_count)
+                    || (*loc.address() == naddress && loc.position() == 0)
+            }
+            BranchTarget::Computed(ECodeExpr::Val(bv)) => {
+                bv.to_u64().map(|off| off == naddress.offset()).unwrap_or(false)
+            }
+            BranchTarget::Computed(_) => false,
+        };
+
+        let pos = ecode
+            .operations()
+            .iter()
+            .position(|op| matches!(op, Stmt::CBranch(_, tgt) if skips_rest(tgt)))?;
+
+        let straight_line = ecode.operations()[pos + 1..]
+            .iter()
+            .all(|op| matches!(op, Stmt::Assign(_, _) | Stmt::Store(_, _, _, _) | Stmt::Skip));
+
+        if straight_line {
+            Some(pos)
+        } else {
+            None
+        }
+    }
+
+    /// Determines if the guard at `pos` can be folded into the operations
+    /// following it without changing its meaning.
+    fn is_selectable(ecode: &ECode, pos: usize) -> bool {
+        #[derive(Default)]
+        struct Reads {
+            vars: HashSet<ECodeVar>,
+            loads: bool,
+        }
+
+        impl<'ecode> Visit<'ecode> for Reads {
+            fn visit_var(&mut self, var: &'ecode ECodeVar) {
+                self.vars.insert(*var);
+            }
+
+            fn visit_expr_load(
+                &mut self,
+                expr: &'ecode ECodeExpr,
+                _size: usize,
+                _space: fugue::ir::AddressSpaceId,
+            ) {
+                self.loads = true;
+                self.visit_expr(expr);
+            }
+        }
+
+        let cond = if let Stmt::CBranch(ref cond, _) = ecode.operations()[pos] {
+            cond
+        } else {
+            return false
+        };
+
+        let mut reads = Reads::default();
+        reads.visit_expr(cond);
+
+        ecode.operations()[pos + 1..].iter().all(|op| match op {
+            Stmt::Assign(var, _) => !reads.vars.contains(var),
+            Stmt::Store(_, _, _, _) => !reads.loads,
+            _ => true,
+        })
+    }
+
+    fn select(cond: &ECodeExpr, op: &mut Stmt) {
+        let ite = |c: &ECodeExpr, t: ECodeExpr, f: ECodeExpr| {
+            ECodeExpr::IfElse(Box::new(c.clone()), Box::new(t), Box::new(f))
+        };
+
+        match op {
+            Stmt::Assign(var, expr) => {
+                let value = std::mem::replace(expr, ECodeExpr::from(*var));
+                *expr = ite(cond, ECodeExpr::from(*var), value);
+            }
+            Stmt::Store(loc, val, size, space) => {
+                let orig = ECodeExpr::Load(Box::new(loc.clone()), *size, *space);
+                let value = std::mem::replace(val, orig.clone());
+                *val = ite(cond, orig, value);
+            }
+            _ => (),
+        }
+    }
+
+    pub(crate) fn apply(self, ecode: &mut ECode) {
+        let pos = if let Some(pos) = Self::guard(ecode) {
+            pos
+        } else {
+            return
+        };
+
+        if self.mode == PredicateNormalisation::Select && Self::is_selectable(ecode, pos) {
+            let cond = if let Stmt::CBranch(cond, _) = ecode.operations_mut().remove(pos) {
+                cond
+            } else {
+                unreachable!("guard is always a conditional branch")
+            };
+
+            for op in ecode.operations_mut()[pos..].iter_mut() {
+                Self::select(&cond, op);
+            }
+        } else {
+            let naddress = ecode.address() + ecode.length();
+            if let Stmt::CBranch(_, ref mut tgt) = ecode.operations_mut()[pos] {
+                *tgt = BranchTarget::Location(Location::new(naddress, 0));
+            }
+        }
+    }
+}
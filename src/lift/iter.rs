@@ -0,0 +1,265 @@
+use fugue::ir::disassembly::ContextDatabase;
+use fugue::ir::il::ecode::Stmt;
+
+use std::collections::BTreeSet;
+
+use crate::ir::{Addr, Blk};
+use crate::lift::ecode::lower::ECodeLowering;
+use crate::lift::ecode::utils::{ECodeExt, ECodeTarget};
+use crate::lift::{Lifter, LifterError};
+use crate::prelude::Entity;
+
+/// How control leaves an instruction (see `LiftedInsn::flows`); the
+/// targets of each flow are those of the Jmps of its Blks.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InsnFlow {
+    /// To the instruction following it
+    FallThrough,
+    /// To the start of, or within, the instruction itself (e.g., an x86
+    /// `rep` loop)
+    Internal,
+    /// To another block, at a fixed address
+    Branch,
+    Call,
+    Return,
+    /// Via an intrinsic in statement position, e.g., a system call
+    Intrinsic,
+    /// To a target computed at runtime, other than by a call or return
+    Computed,
+}
+
+impl InsnFlow {
+    fn of(target: &ECodeTarget) -> Self {
+        match target {
+            ECodeTarget::IntraIns(_) => Self::Internal,
+            ECodeTarget::IntraBlk(_) => Self::FallThrough,
+            ECodeTarget::InterBlk(_) => Self::Branch,
+            ECodeTarget::InterSub(_) => Self::Call,
+            ECodeTarget::InterRet(_, _) => Self::Return,
+            ECodeTarget::Intrinsic => Self::Intrinsic,
+            ECodeTarget::Unresolved => Self::Computed,
+        }
+    }
+}
+
+/// The Blks lifted for a single instruction by `LiftIter`.
+///
+/// As each instruction is lowered on its own, flows to the instruction
+/// following it are to its (fixed) address, rather than resolved to the
+/// Blk lifted for it.
+#[derive(Clone)]
+pub struct LiftedInsn {
+    addr: Addr,
+    length: usize,
+    blks: Vec<Entity<Blk>>,
+    flows: BTreeSet<InsnFlow>,
+    ends_blk: bool,
+}
+
+impl LiftedInsn {
+    pub fn addr(&self) -> &Addr {
+        &self.addr
+    }
+
+    /// The number of bytes decoded as the instruction.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// The address of the instruction following this one.
+    pub fn next_addr(&self) -> Addr {
+        &self.addr + self.length
+    }
+
+    pub fn blks(&self) -> &[Entity<Blk>] {
+        &self.blks
+    }
+
+    pub fn into_blks(self) -> Vec<Entity<Blk>> {
+        self.blks
+    }
+
+    /// The kinds of flows leaving the instruction.
+    pub fn flows(&self) -> &BTreeSet<InsnFlow> {
+        &self.flows
+    }
+
+    /// The instruction terminates its block (i.e., `lift_blk` would stop
+    /// lifting after it).
+    pub fn ends_blk(&self) -> bool {
+        self.ends_blk
+    }
+}
+
+/// An iterator over the instructions lifted from a buffer; see
+/// `Lifter::lift_iter`.
+///
+/// Unlike `Lifter::lift_blk`, lifting does not stop at the end of a block:
+/// instructions are lifted until the buffer is exhausted, or an instruction
+/// cannot be lifted, in which case an `Undecodable` error is yielded (so
+/// that callers may resume after it) and the iterator ends.
+pub struct LiftIter<'a> {
+    lifter: &'a Lifter,
+    ctxt: &'a mut ContextDatabase,
+    lowering: ECodeLowering<'a>,
+    addr: Addr,
+    bytes: &'a [u8],
+    offset: usize,
+    done: bool,
+}
+
+impl<'a> LiftIter<'a> {
+    pub(crate) fn new(lifter: &'a Lifter, ctxt: &'a mut ContextDatabase, addr: Addr, bytes: &'a [u8]) -> Self {
+        let lowering = ECodeLowering::new(&lifter.register_names, &lifter.memory, addr.bits());
+        Self {
+            lifter,
+            ctxt,
+            lowering,
+            addr,
+            bytes,
+            offset: 0,
+            done: false,
+        }
+    }
+
+    /// The number of bytes lifted so far.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The address of the next instruction to lift.
+    pub fn next_addr(&self) -> Addr {
+        &self.addr + self.offset
+    }
+
+    fn lift_next(&mut self) -> Result<LiftedInsn, LifterError> {
+        let iaddr = &self.addr + self.offset;
+        let view = &self.bytes[self.offset..];
+
+        let insn = self.lifter
+            .lift_lowered_insn(self.ctxt, &iaddr, view, &self.lowering)
+            .map_err(|e| LifterError::Undecodable { addr: iaddr.clone(), offset: self.offset, source: Box::new(e) })?;
+
+        self.offset += insn.length;
+
+        Ok(insn)
+    }
+}
+
+impl<'a> Iterator for LiftIter<'a> {
+    type Item = Result<LiftedInsn, LifterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.offset >= self.bytes.len() {
+            return None
+        }
+
+        if self.offset == 0 {
+            if let Some(alignment) = self.lifter.alignment {
+                let aligned = u64::try_from(self.addr.clone())
+                    .map_err(LifterError::from)
+                    .and_then(|addr| if addr % alignment == 0 {
+                        Ok(())
+                    } else {
+                        Err(LifterError::Alignment(self.addr.clone()))
+                    });
+
+                if let Err(e) = aligned {
+                    self.done = true;
+                    return Some(Err(e))
+                }
+            }
+        }
+
+        let lifted = self.lift_next();
+
+        // a zero-length instruction would never advance
+        if !matches!(lifted, Ok(ref insn) if !insn.is_empty()) {
+            self.done = true;
+        }
+
+        Some(lifted)
+    }
+}
+
+impl<'a> std::iter::FusedIterator for LiftIter<'a> { }
+
+impl Lifter {
+    // lifts the instruction at iaddr from view, lowering it on its own
+    pub(crate) fn lift_lowered_insn(
+        &self,
+        ctxt: &mut ContextDatabase,
+        iaddr: &Addr,
+        view: &[u8],
+        lowering: &ECodeLowering,
+    ) -> Result<LiftedInsn, LifterError> {
+        let taddr = self.translator.address(u64::try_from(iaddr.clone())?);
+        let (insn, ends_blk) = self.lift_ecode_insn(ctxt, iaddr, taddr, view, lowering)?;
+        let length = insn.ecode.length();
+
+        let mut flows = insn
+            .ecode
+            .branch_targets()
+            .iter()
+            .map(|(_, target)| InsnFlow::of(target))
+            .collect::<BTreeSet<_>>();
+
+        // control reaches the following instruction unless the instruction
+        // ends by branching or returning; e.g., nops lift to no operations,
+        // and so have no branch targets
+        if !matches!(insn.ecode.operations().last(), Some(Stmt::Branch(_) | Stmt::Return(_))) {
+            flows.insert(InsnFlow::FallThrough);
+        }
+
+        let blks = lowering.lower(vec![insn], &(iaddr + length));
+
+        Ok(LiftedInsn {
+            addr: iaddr.clone(),
+            length,
+            blks,
+            flows,
+            ends_blk,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::lift::LifterBuilder;
+
+    fn x86_lifter() -> Result<Lifter, Box<dyn std::error::Error>> {
+        let root = env::var("DELIRIUM_TEST_ENV_ROOT")?;
+        let path = PathBuf::from_iter([&root, "processors"]);
+        Ok(LifterBuilder::new(&path)?.build("x86:LE:32:default", "gcc")?)
+    }
+
+    fn flows(lifter: &Lifter, bytes: &[u8]) -> Result<Vec<InsnFlow>, Box<dyn std::error::Error>> {
+        let mut ctxt = lifter.context();
+        let insn = lifter.lift_insn(&mut ctxt, Addr::from(0x1000u32), bytes)?;
+        Ok(insn.flows().iter().copied().collect())
+    }
+
+    #[test]
+    fn test_insn_flows() -> Result<(), Box<dyn std::error::Error>> {
+        let lifter = x86_lifter()?;
+
+        // nop lifts to no operations
+        assert_eq!(flows(&lifter, &[0x90])?, [InsnFlow::FallThrough]);
+        // jne 0x1012
+        assert_eq!(flows(&lifter, &[0x75, 0x10])?, [InsnFlow::FallThrough, InsnFlow::Branch]);
+        // call 0x1010
+        assert_eq!(flows(&lifter, &[0xe8, 0x0b, 0x00, 0x00, 0x00])?, [InsnFlow::FallThrough, InsnFlow::Call]);
+        // ret
+        assert_eq!(flows(&lifter, &[0xc3])?, [InsnFlow::Return]);
+
+        Ok(())
+    }
+}
@@ -1,23 +1,78 @@
-use fugue::ir::convention::Convention;
-use fugue::ir::{LanguageDB, Translator};
+use fugue::arch::ArchitectureDef;
+use fugue::ir::convention::{Convention, PrototypeOperand};
+use fugue::ir::{AddressValue, LanguageDB, Translator};
 use fugue::ir::disassembly::ContextDatabase;
-use fugue::ir::il::ecode::Stmt;
+use fugue::ir::il::ecode::{ECode, Stmt};
 
 use std::borrow::{Borrow, Cow};
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use thiserror::Error;
 
-use crate::ir::{Addr, Blk};
+use crate::ir::{Addr, Blk, Intrinsic, IntrinsicRegistry, Mem, SideEffects, SourceLoc, Var};
 use crate::prelude::{Endian, Entity};
+use crate::types::bv::BitVecT;
+
+mod archive;
+pub use archive::{archive_processors, write_processors, ArchiveError, SpecArchive};
+use archive::UnpackedArchive;
+
+mod cache;
+use cache::LiftCache;
+
+mod context;
+use context::ContextRanges;
+
+mod detect;
+pub use detect::{Candidate, Container};
+pub use cache::LiftCacheStats;
+
+mod iter;
+pub use iter::{InsnFlow, LiftIter, LiftedInsn};
 
 mod ecode;
-use ecode::passes::ECodeVarIndex;
-use ecode::utils::ECodeExt;
+use ecode::lower::{ECodeInsn, ECodeLowering};
+pub use ecode::lower::{RepeatNormalisation, INVALID_INSN};
+
+pub mod arm;
+pub use arm::IsaMode;
+
+pub mod riscv;
+pub use riscv::RiscVExtension;
+
+mod spec;
+pub use spec::{SpecError, SpecVersion};
+
+mod superset;
+pub use superset::SupersetGraph;
+use ecode::passes::{ECodeAArch64Pass, ECodeDelaySlotPass, ECodePredicateNormalisePass, ECodeVarIndex};
+pub use ecode::passes::{PointerModelling, PredicateNormalisation};
+use ecode::utils::{ECodeExt, ECodeTarget};
+
+/// Builds a `LifterBuilder` from processor specifications embedded in the
+/// binary, given the path to an archive of them (see `write_processors`);
+/// e.g., with a build script writing the archive to `OUT_DIR`:
+///
+/// ```ignore
+/// let builder = include_processors!(concat!(env!("OUT_DIR"), "/processors.tar"))?;
+/// ```
+#[macro_export]
+macro_rules! include_processors {
+    ($path:expr) => {
+        $crate::lift::LifterBuilder::new_from_archive(include_bytes!($path))
+    };
+}
 
 #[derive(Clone)]
 pub struct LifterBuilder {
     language_db: LanguageDB,
+    // the directory of processor specifications the languages are from
+    root: PathBuf,
+    // the directory unpacked by new_from_archive, removed once the
+    // builder and its clones are dropped
+    unpacked: Option<Arc<UnpackedArchive>>,
 }
 
 #[derive(Debug, Error)]
@@ -25,6 +80,8 @@ pub enum LifterBuilderError {
     #[error(transparent)]
     ArchDef(#[from] fugue::arch::ArchDefParseError),
     #[error(transparent)]
+    Archive(#[from] ArchiveError),
+    #[error(transparent)]
     Backend(#[from] fugue::ir::error::Error),
     #[error("unsupported architecture")]
     UnsupportedArch,
@@ -37,13 +94,34 @@ impl LifterBuilder {
         path: impl AsRef<Path>,
         ignore_errors: bool,
     ) -> Result<Self, LifterBuilderError> {
+        let root = path.as_ref().to_owned();
         let language_db = LanguageDB::from_directory_with(path, ignore_errors)?;
-        Ok(Self { language_db })
+        Ok(Self { language_db, root, unpacked: None })
     }
+
     pub fn new(path: impl AsRef<Path>) -> Result<Self, LifterBuilderError> {
         Self::new_with(path, true)
     }
 
+    /// Loads the processor specifications from a tar archive of their
+    /// directory (see `SpecArchive`), e.g., one bundled with the
+    /// application, rather than from a path given by its user.
+    ///
+    /// The LanguageDB only reads specifications from files, hence the
+    /// archive is unpacked into a new directory beneath the temporary
+    /// directory, accessible only to the current user, which is removed
+    /// once the builder (and its clones) are dropped. Directories are
+    /// never shared between calls, so that specifications cannot be
+    /// substituted by other users; on targets without a filesystem
+    /// (e.g., `wasm32-unknown-unknown`), this fails with an
+    /// `ArchiveError`.
+    pub fn new_from_archive(bytes: &[u8]) -> Result<Self, LifterBuilderError> {
+        let unpacked = SpecArchive::new(bytes)?.unpack_private()?;
+        let mut builder = Self::new_with(unpacked.root(), true)?;
+        builder.unpacked = Some(Arc::new(unpacked));
+        Ok(builder)
+    }
+
     pub fn build(
         &self,
         tag: impl Into<Cow<'static, str>>,
@@ -57,9 +135,10 @@ impl LifterBuilder {
             .lookup_str(&*tag)?
             .ok_or_else(|| LifterBuilderError::UnsupportedArch)?;
         let translator = builder.build()?;
+        let spec = SpecVersion::from_directory(&self.root, builder.id());
 
         if let Some(convention) = translator.compiler_conventions().get(&*convention).cloned() {
-            Ok(Lifter::new(translator, convention))
+            Ok(Lifter::new(translator, convention, spec))
         } else {
             Err(LifterBuilderError::UnsupportedConv)
         }
@@ -83,13 +162,94 @@ impl LifterBuilder {
             .lookup(processor, endian, bits as usize, variant)
             .ok_or_else(|| LifterBuilderError::UnsupportedArch)?;
         let translator = builder.build()?;
+        let spec = SpecVersion::from_directory(&self.root, builder.id());
 
         if let Some(convention) = translator.compiler_conventions().get(&*convention).cloned() {
-            Ok(Lifter::new(translator, convention))
+            Ok(Lifter::new(translator, convention, spec))
         } else {
             Err(LifterBuilderError::UnsupportedConv)
         }
     }
+
+    /// Builds a Lifter for `tag` using its default calling convention,
+    /// i.e., the one named `default` or, if the language has no such
+    /// convention, the first by name; the convention can be changed using
+    /// `Lifter::set_convention`.
+    pub fn build_default(&self, tag: impl Into<Cow<'static, str>>) -> Result<Lifter, LifterBuilderError> {
+        let tag = tag.into();
+
+        let builder = self
+            .language_db
+            .lookup_str(&*tag)?
+            .ok_or_else(|| LifterBuilderError::UnsupportedArch)?;
+        let translator = builder.build()?;
+        let spec = SpecVersion::from_directory(&self.root, builder.id());
+
+        if let Some(convention) = default_convention(&translator) {
+            Ok(Lifter::new(translator, convention, spec))
+        } else {
+            Err(LifterBuilderError::UnsupportedConv)
+        }
+    }
+
+    /// The languages of the processor specifications loaded; their
+    /// `Display` forms are the tags accepted by `build` (e.g.,
+    /// `x86:LE:64:default`).
+    pub fn architectures(&self) -> impl Iterator<Item = &ArchitectureDef> + '_ {
+        self.language_db.iter().map(|(arch, _)| arch)
+    }
+
+    /// The names of the calling conventions of the language `tag` accepted
+    /// by `build`, in order; the language is built (i.e., its
+    /// specification is loaded) to find them.
+    pub fn conventions_for(
+        &self,
+        tag: impl AsRef<str>,
+    ) -> Result<impl Iterator<Item = String>, LifterBuilderError> {
+        let builder = self
+            .language_db
+            .lookup_str(tag.as_ref())?
+            .ok_or_else(|| LifterBuilderError::UnsupportedArch)?;
+        let translator = builder.build()?;
+
+        let mut conventions = translator.compiler_conventions().keys().cloned().collect::<Vec<_>>();
+        conventions.sort();
+        Ok(conventions.into_iter())
+    }
+}
+
+// the convention named default, or the first by name
+fn default_convention(translator: &Translator) -> Option<Convention> {
+    let conventions = translator.compiler_conventions();
+    conventions
+        .get("default")
+        .or_else(|| {
+            conventions
+                .iter()
+                .min_by(|(a, _), (b, _)| a.cmp(b))
+                .map(|(_, convention)| convention)
+        })
+        .cloned()
+}
+
+/// Determines where `Lifter::lift_blk` ends the groups of Blks it lifts.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LiftMode {
+    /// End groups at flows to other blocks and at returns, but not at
+    /// calls, as IDA's blocks.
+    IdaStyle,
+    /// End groups after each instruction with flows other than to the
+    /// instruction following it (e.g., calls, intrinsics, and loops
+    /// within instructions), so that each group is a basic block.
+    StrictBasicBlocks,
+    /// Lift a single instruction per group.
+    SingleInstruction,
+}
+
+impl Default for LiftMode {
+    fn default() -> Self {
+        Self::IdaStyle
+    }
 }
 
 #[derive(Clone)]
@@ -97,6 +257,22 @@ pub struct Lifter {
     translator: Translator,
     convention: Convention,
     register_ecode_index: ECodeVarIndex,
+    register_names: BTreeMap<(u64, usize), Arc<str>>,
+    memory: Var,
+    predicates: PredicateNormalisation,
+    repeats: RepeatNormalisation,
+    mode: LiftMode,
+    // None if the language is not AArch64
+    pointers: Option<PointerModelling>,
+    // the alignment of instruction boundaries, if known
+    alignment: Option<u64>,
+    // record the mnemonic and operands of each instruction lifted
+    disassembly: bool,
+    spec: SpecVersion,
+    cache: LiftCache,
+    // the values of context variables for ranges of addresses
+    contexts: ContextRanges,
+    intrinsics: IntrinsicRegistry,
 }
 
 #[derive(Debug, Error)]
@@ -105,27 +281,501 @@ pub enum LifterError {
     AddrSize(#[from] crate::ir::memory::address::AddrConvertError),
     #[error(transparent)]
     Disassembly(#[from] fugue::ir::error::Error),
+    #[error("address {0} is not aligned to an instruction boundary")]
+    Alignment(Addr),
+    #[error("language has no context variable `{0}`")]
+    Context(String),
+    #[error("delay slot at {slot} of the branch at {addr} could not be lifted")]
+    DelaySlot {
+        addr: Addr,
+        slot: Addr,
+        // None if the instruction in the slot is empty
+        source: Option<fugue::ir::error::Error>,
+    },
+    #[error("instruction at {addr} (offset {offset}) could not be decoded: {source}")]
+    Undecodable {
+        addr: Addr,
+        // the offset of the instruction within the bytes lifted
+        offset: usize,
+        source: Box<LifterError>,
+    },
 }
 
 impl Lifter {
-    fn new(translator: Translator, convention: Convention) -> Self {
+    fn new(translator: Translator, convention: Convention, spec: SpecVersion) -> Self {
+        let pointers = if translator.architecture().processor() == "AARCH64" {
+            Some(PointerModelling::default())
+        } else {
+            None
+        };
+
+        // without RVC, all RISC-V instructions are 4-byte aligned; with it,
+        // instructions may begin at any 2-byte boundary. We only accept
+        // 2-byte boundaries when the language's variant includes C: the
+        // specifications do not model misa, so a context cannot disable
+        // RVC for a language that supports it.
+        let alignment = if riscv::is_riscv(translator.architecture()) {
+            if riscv::extensions(translator.architecture()).contains(&RiscVExtension::C) {
+                Some(2)
+            } else {
+                Some(4)
+            }
+        } else {
+            None
+        };
+
+        let cache = LiftCache::new(&translator.context_database());
+
+        // the end of the Blks lifted from undecodable instructions
+        let mut intrinsics = IntrinsicRegistry::new();
+        intrinsics.register(Intrinsic::new(INVALID_INSN, SideEffects::pure().no_return(true).clone()));
+
         Self {
             register_ecode_index: ECodeVarIndex::registers(&translator),
+            register_names: translator
+                .registers()
+                .iter()
+                .map(|((off, sz), name)| ((*off, *sz), Arc::<str>::from(&**name)))
+                .collect(),
             translator,
             convention,
+            memory: Var::memory(&Mem::new("M")).into(),
+            predicates: PredicateNormalisation::default(),
+            repeats: RepeatNormalisation::default(),
+            mode: LiftMode::default(),
+            pointers,
+            alignment,
+            disassembly: false,
+            spec,
+            cache,
+            contexts: ContextRanges::default(),
+            intrinsics,
         }
     }
+
+    // the memory that loads and stores of lifted code are performed on
+    pub(crate) fn set_memory(&mut self, memory: &Mem) {
+        self.memory = Var::memory(memory).into();
+    }
+    
+    pub fn predicate_normalisation(&self) -> PredicateNormalisation {
+        self.predicates
+    }
+
+    pub fn set_predicate_normalisation(&mut self, mode: PredicateNormalisation) {
+        self.predicates = mode;
+        self.cache.clear();
+    }
+
+    /// The standard extensions supported by the lifter's language, if it is
+    /// a RISC-V language.
+    pub fn riscv_extensions(&self) -> Option<BTreeSet<RiscVExtension>> {
+        let arch = self.translator.architecture();
+        if riscv::is_riscv(arch) {
+            Some(riscv::extensions(arch))
+        } else {
+            None
+        }
+    }
+
+    // only applicable to AArch64 languages
+    pub fn pointer_modelling(&self) -> Option<PointerModelling> {
+        self.pointers
+    }
+
+    pub fn set_pointer_modelling(&mut self, mode: PointerModelling) {
+        if let Some(ref mut pointers) = self.pointers {
+            *pointers = mode;
+            self.cache.clear();
+        }
+    }
+
+    pub fn repeat_normalisation(&self) -> RepeatNormalisation {
+        self.repeats
+    }
+
+    pub fn set_repeat_normalisation(&mut self, mode: RepeatNormalisation) {
+        self.repeats = mode;
+        self.cache.clear();
+    }
+
+    pub fn lift_mode(&self) -> LiftMode {
+        self.mode
+    }
+
+    pub fn set_lift_mode(&mut self, mode: LiftMode) {
+        self.mode = mode;
+        self.cache.clear();
+    }
+
+    pub fn record_disassembly(&self) -> bool {
+        self.disassembly
+    }
+
+    /// Records the mnemonic and operands of each instruction lifted in the
+    /// location of its Blks (see `Blk::source`); as this requires
+    /// disassembling each instruction a second time, it is disabled by
+    /// default.
+    pub fn set_record_disassembly(&mut self, enabled: bool) {
+        self.disassembly = enabled;
+        self.cache.clear();
+    }
+
+    /// Caches up to `capacity` lifted instructions, evicting the least
+    /// recently used; a capacity of zero (the default) disables caching.
+    ///
+    /// Instructions are cached by their bytes and the context in effect
+    /// where they are lifted (including values given for ranges by
+    /// `set_context`); of the context, we consider the values of the
+    /// variables that select instruction set modes (e.g., `TMode` for ARM)
+    /// and those given values by `set_context`. Instructions are reused at
+    /// other addresses once lifting them at two addresses shows that their
+    /// code does not depend on their address. Changes an instruction makes
+    /// to the context when lifted are replayed when it is reused.
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.cache.set_capacity(capacity);
+    }
+
+    pub fn cache_stats(&self) -> LiftCacheStats {
+        self.cache.stats()
+    }
+
+    pub fn clear_cache(&self) {
+        self.cache.clear();
+    }
     
+    pub fn endian(&self) -> Endian {
+        self.translator.architecture().endian()
+    }
+
+    pub fn architecture(&self) -> &ArchitectureDef {
+        self.translator.architecture()
+    }
+
+    /// The version of the specification the lifter's language was built
+    /// from.
+    pub fn spec_version(&self) -> &SpecVersion {
+        &self.spec
+    }
+
+    /// The alignment of instruction boundaries, if fixed for the lifter's
+    /// language (e.g., for RISC-V). For RISC-V, this is two bytes only if
+    /// the language's variant includes the C extension; the context is
+    /// not consulted.
+    pub fn instruction_alignment(&self) -> Option<u64> {
+        self.alignment
+    }
+
+    /// The variable for the register named `name` (ignoring case), as it
+    /// appears in lifted code.
+    pub fn register(&self, name: &str) -> Option<Var> {
+        self.register_names
+            .iter()
+            .find(|(_, rname)| rname.eq_ignore_ascii_case(name))
+            .map(|((_, size), rname)| {
+                Var::physical(&**rname, BitVecT::with_bits(*size as u32 * 8, false)).into()
+            })
+    }
+
+    /// The variables for the registers sharing storage with the register
+    /// named `name` (ignoring case), including it, e.g., `AL`, `AX`, and
+    /// `EAX` for `RAX`.
+    pub fn overlapping_registers(&self, name: &str) -> Vec<Var> {
+        let (offset, size) = match self.register_names.iter().find(|(_, rname)| rname.eq_ignore_ascii_case(name)) {
+            Some((&(offset, size), _)) => (offset, size as u64),
+            None => return Vec::new(),
+        };
+
+        self.register_names
+            .iter()
+            .filter(|((roffset, rsize), _)| *roffset < offset + size && offset < *roffset + *rsize as u64)
+            .map(|((_, rsize), rname)| Var::physical(&**rname, BitVecT::with_bits(*rsize as u32 * 8, false)).into())
+            .collect()
+    }
+
+    /// The variable for the stack pointer of the lifter's language, if
+    /// known.
+    pub fn stack_pointer(&self) -> Option<Var> {
+        let candidates: &[&str] = match self.architecture().processor().to_ascii_uppercase().as_str() {
+            "X86" if self.architecture().bits() == 64 => &["RSP"],
+            "X86" => &["ESP"],
+            "POWERPC" | "PPC" => &["r1"],
+            _ => &["SP"],
+        };
+        candidates.iter().find_map(|name| self.register(name))
+    }
+
+    /// The intrinsics known to the lifter, i.e., those that analyses of the
+    /// code it lifts can model.
+    pub fn intrinsics(&self) -> &IntrinsicRegistry {
+        &self.intrinsics
+    }
+
+    pub fn intrinsics_mut(&mut self) -> &mut IntrinsicRegistry {
+        &mut self.intrinsics
+    }
+
+    /// The calling convention the lifter was built with (or switched to
+    /// by `set_convention`).
+    pub fn convention(&self) -> &Convention {
+        &self.convention
+    }
+
+    /// The names of the calling conventions of the lifter's language, in
+    /// order.
+    pub fn conventions(&self) -> Vec<&str> {
+        let mut conventions = self
+            .translator
+            .compiler_conventions()
+            .keys()
+            .map(|name| name.as_str())
+            .collect::<Vec<_>>();
+        conventions.sort_unstable();
+        conventions
+    }
+
+    /// Switches to the calling convention `name` of the lifter's language;
+    /// returns false, leaving the convention unchanged, if the language
+    /// has no such convention.
+    pub fn set_convention(&mut self, name: impl AsRef<str>) -> bool {
+        if let Some(convention) = self.translator.compiler_conventions().get(name.as_ref()) {
+            self.convention = convention.clone();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// A copy of the lifter using the calling convention `name`, e.g., to
+    /// analyse a Sub following a different convention to the others.
+    pub fn with_convention(&self, name: impl AsRef<str>) -> Option<Self> {
+        let mut lifter = self.clone();
+        if lifter.set_convention(name) {
+            Some(lifter)
+        } else {
+            None
+        }
+    }
+
+    fn prototype_registers(&self, operands: &[PrototypeOperand]) -> Vec<Var> {
+        let mut names = Vec::new();
+        for operand in operands {
+            match operand {
+                PrototypeOperand::Register { name, .. } => names.push(name),
+                PrototypeOperand::RegisterJoin { name, other_name, .. } => {
+                    names.push(name);
+                    names.push(other_name);
+                }
+                PrototypeOperand::StackRelative(_, _) => (),
+            }
+        }
+        names.into_iter().filter_map(|name| self.register(name)).collect()
+    }
+
+    /// The registers that calls may modify under the lifter's convention,
+    /// i.e., those killed by calls (e.g., return registers) or likely to
+    /// be trashed by them.
+    pub fn caller_saved(&self) -> Vec<Var> {
+        let prototype = self.convention.default_prototype();
+        let mut regs = self.prototype_registers(prototype.killed_by_call());
+        regs.extend(self.prototype_registers(prototype.likely_trashed()));
+        regs.sort();
+        regs.dedup();
+        regs
+    }
+
+    /// The registers that calls preserve under the lifter's convention.
+    pub fn callee_saved(&self) -> Vec<Var> {
+        self.prototype_registers(self.convention.default_prototype().unaffected())
+    }
+
+    // the registers passing integer arguments (in order), other arguments,
+    // and return values, under the lifter's convention
+    fn abi_registers(&self) -> Option<(&'static [&'static str], &'static [&'static str], &'static [&'static str])> {
+        let windows = self.convention.name().eq_ignore_ascii_case("windows");
+        Some(match self.architecture().processor().to_ascii_uppercase().as_str() {
+            "X86" if self.architecture().bits() == 64 && windows => (
+                &["RCX", "RDX", "R8", "R9"],
+                &["XMM0", "XMM1", "XMM2", "XMM3"],
+                &["RAX", "XMM0"],
+            ),
+            // AL holds the number of vector registers used by variadic calls
+            "X86" if self.architecture().bits() == 64 => (
+                &["RDI", "RSI", "RDX", "RCX", "R8", "R9"],
+                &["RAX", "XMM0", "XMM1", "XMM2", "XMM3", "XMM4", "XMM5", "XMM6", "XMM7"],
+                &["RAX", "RDX", "XMM0"],
+            ),
+            // for fastcall and thiscall
+            "X86" => (&["ECX", "EDX"], &[], &["EAX", "EDX"]),
+            "ARM" => (&["r0", "r1", "r2", "r3"], &[], &["r0", "r1"]),
+            // x8 holds the address of indirect results
+            "AARCH64" => (
+                &["x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7"],
+                &["x8", "q0", "q1", "q2", "q3", "q4", "q5", "q6", "q7"],
+                &["x0", "x1", "q0"],
+            ),
+            "MIPS" => (&["a0", "a1", "a2", "a3"], &["f12", "f14"], &["v0", "v1", "f0"]),
+            "POWERPC" | "PPC" => (
+                &["r3", "r4", "r5", "r6", "r7", "r8", "r9", "r10"],
+                &["f1", "f2", "f3", "f4", "f5", "f6", "f7", "f8"],
+                &["r3", "r4", "f1"],
+            ),
+            "RISCV" => (
+                &["a0", "a1", "a2", "a3", "a4", "a5", "a6", "a7"],
+                &["fa0", "fa1", "fa2", "fa3", "fa4", "fa5", "fa6", "fa7"],
+                &["a0", "a1", "fa0"],
+            ),
+            _ => return None,
+        })
+    }
+
+    fn registers_named(&self, names: &[&str]) -> Vec<Var> {
+        names.iter().filter_map(|name| self.register(name)).collect()
+    }
+
+    /// The registers that may pass arguments to calls under the lifter's
+    /// convention, if known for its language.
+    pub fn argument_registers(&self) -> Option<Vec<Var>> {
+        self.abi_registers().map(|(integer, other, _)| {
+            let mut regs = self.registers_named(integer);
+            regs.extend(self.registers_named(other));
+            regs
+        })
+    }
+
+    /// The registers that pass integer (and pointer) arguments to calls
+    /// under the lifter's convention, in the order of the arguments they
+    /// pass, if known for its language.
+    pub fn integer_argument_registers(&self) -> Option<Vec<Var>> {
+        self.abi_registers().map(|(integer, _, _)| self.registers_named(integer))
+    }
+
+    /// The registers that return values from calls under the lifter's
+    /// convention, the primary return register first, if known for its
+    /// language.
+    pub fn return_registers(&self) -> Option<Vec<Var>> {
+        self.abi_registers().map(|(_, _, returns)| self.registers_named(returns))
+    }
+
     pub fn context(&self) -> ContextDatabase {
         self.translator.context_database()
     }
 
+    /// The lifter's language is an ARM language, i.e., decodes ARM or
+    /// Thumb instructions depending on its context (see `set_isa_mode`).
+    pub fn is_arm(&self) -> bool {
+        arm::is_arm(self.translator.architecture())
+    }
+
+    /// Sets `ctxt` to decode instructions in `mode`; returns false,
+    /// leaving `ctxt` unchanged, if the lifter's language is not an ARM
+    /// language.
+    pub fn set_isa_mode(&self, ctxt: &mut ContextDatabase, mode: IsaMode) -> Result<bool, LifterError> {
+        if self.is_arm() {
+            arm::set_mode(ctxt, mode)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// The mode `ctxt` decodes the instruction at `addr` in (see
+    /// `context_value`); None if the lifter's language is not an ARM
+    /// language.
+    pub fn isa_mode(&self, ctxt: &ContextDatabase, addr: Option<&Addr>) -> Option<IsaMode> {
+        if !self.is_arm() {
+            return None
+        }
+
+        self.context_value(ctxt, arm::TMODE, addr).map(|value| {
+            if value != 0 {
+                IsaMode::Thumb
+            } else {
+                IsaMode::Arm
+            }
+        })
+    }
+
+    // as lift_blk_extent, but decoding in mode, if given, rather than the
+    // mode of ctxt, which is left unchanged
+    pub(crate) fn lift_blk_extent_in(
+        &self,
+        ctxt: &mut ContextDatabase,
+        addr: &Addr,
+        bytes: &[u8],
+        size_hint: Option<usize>,
+        mode: Option<IsaMode>,
+    ) -> Result<(Vec<Entity<Blk>>, usize), LifterError> {
+        let previous = match mode {
+            Some(mode) => {
+                let previous = self.isa_mode(ctxt, None);
+                self.set_isa_mode(ctxt, mode)?;
+                previous
+            }
+            None => None,
+        };
+
+        let lifted = self.lift_blk_extent(ctxt, addr, bytes, size_hint);
+
+        if let Some(previous) = previous {
+            self.set_isa_mode(ctxt, previous)?;
+        }
+        lifted
+    }
+
+    // the mnemonic, operands, and length of the instruction at addr
+    pub(crate) fn disassemble_insn(
+        &self,
+        ctxt: &mut ContextDatabase,
+        addr: &Addr,
+        bytes: &[u8],
+    ) -> Result<(String, String, usize), LifterError> {
+        let taddr = self.translator.address(u64::try_from(addr.clone())?);
+        let insn = self.translator.disassemble(ctxt, taddr, bytes)?;
+        Ok((insn.mnemonic().to_owned(), insn.operands().to_owned(), insn.length()))
+    }
+
+    /// Disassembles the instructions in `bytes` (as formatted by the
+    /// language's specification), until the buffer is exhausted or an
+    /// instruction cannot be decoded; unlike `lift_blk`, disassembly does
+    /// not stop at the end of a block. Returns an error if `addr` is not
+    /// aligned to an instruction boundary.
+    pub fn disassemble(&self, ctxt: &mut ContextDatabase, addr: impl Borrow<Addr>, bytes: &[u8]) -> Result<Vec<SourceLoc>, LifterError> {
+        let addr = addr.borrow();
+
+        if let Some(alignment) = self.alignment {
+            if u64::try_from(addr.clone())? % alignment != 0 {
+                return Err(LifterError::Alignment(addr.clone()))
+            }
+        }
+
+        let mut insns = Vec::new();
+        let mut offset = 0;
+
+        while offset < bytes.len() {
+            let iaddr = addr + offset;
+            match self.disassemble_insn(ctxt, &iaddr, &bytes[offset..]) {
+                Ok((mnemonic, operands, length)) if length > 0 => {
+                    insns.push(SourceLoc::new(iaddr, length).with_disassembly(mnemonic, operands));
+                    offset += length;
+                }
+                _ => {
+                    log::trace!("instruction at {} could not be disassembled", iaddr);
+                    break
+                }
+            }
+        }
+
+        Ok(insns)
+    }
+
     pub fn lift_blk(&self, ctxt: &mut ContextDatabase, addr: impl Borrow<Addr>, bytes: &[u8]) -> Result<Vec<Entity<Blk>>, LifterError> {
         self.lift_blk_with(ctxt, addr, bytes, None)
     }
     
-    // We lift blocks based on IDA's model of basic blocks (i.e., only
-    // terminate a block on local control-flow/a return).
+    // By default, we lift blocks based on IDA's model of basic blocks
+    // (i.e., only terminate a block on local control-flow/a return); see
+    // LiftMode for the alternatives.
     //
     // We note that each lifted instruction may have internal control-flow
     // and so for a given block under IDA's model, we may produce many strict
@@ -141,75 +791,348 @@ impl Lifter {
     //  6. Intrinsic  (intrinsic in statement position)
     //  
     // Each architectural instruction initially becomes one or more blocks; we
-    // can later apply a merge strategy to clean blocks up if needed. However,
-    // this representation enables us to avoid splitting blocks at a later
-    // stage and allows us to build a mapping between each instruction and its 
-    // blocks.
+    // can later apply a merge strategy to clean blocks up if needed (see
+    // Sub::normalise_blocks). However, this representation enables us to
+    // avoid splitting blocks at a later stage and allows us to build a
+    // mapping between each instruction and its blocks.
+    //
+    // The ECode of each instruction is lowered into Blks (see ECodeLowering):
+    // IntraIns flows split the instruction into multiple Blks, flows to the
+    // start of an instruction within the block are resolved to its first
+    // Blk, and the last instruction lifted falls through to the address
+    // following the block. The instruction that ends the block (e.g., a
+    // branch or a return) is lifted as part of it. Prior to lowering, this
+    // function returned no Blks and dropped the block-ending instruction;
+    // callers that relied upon either must now account for them.
+    //
+    // If an instruction cannot be decoded, the block ends with a Blk
+    // marking it (see INVALID_INSN); if the first cannot be, we return
+    // LifterError::Undecodable. If the delay slot of a branch cannot be
+    // lifted, we return LifterError::DelaySlot.
     pub fn lift_blk_with(&self, ctxt: &mut ContextDatabase, addr: impl Borrow<Addr>, bytes: &[u8], size_hint: Option<usize>) -> Result<Vec<Entity<Blk>>, LifterError> {
+        self.lift_blk_extent(ctxt, addr.borrow(), bytes, size_hint)
+            .map(|(blks, _)| blks)
+    }
+
+    /// Lifts the instructions in `bytes` one at a time, yielding the Blks
+    /// of each, so that callers may stop lifting early (e.g., on finding a
+    /// pattern) without lifting the whole buffer; see `LiftIter`.
+    pub fn lift_iter<'a>(&'a self, ctxt: &'a mut ContextDatabase, addr: impl Borrow<Addr>, bytes: &'a [u8]) -> LiftIter<'a> {
+        LiftIter::new(self, ctxt, addr.borrow().clone(), bytes)
+    }
+
+    /// Lifts the single instruction at the start of `bytes`, without
+    /// regard to the block it is part of; flows to the instruction
+    /// following it are to its (fixed) address. Returns an `Undecodable`
+    /// error if the instruction cannot be decoded.
+    pub fn lift_insn(&self, ctxt: &mut ContextDatabase, addr: impl Borrow<Addr>, bytes: &[u8]) -> Result<LiftedInsn, LifterError> {
         let addr = addr.borrow();
+
+        if let Some(alignment) = self.alignment {
+            if u64::try_from(addr.clone())? % alignment != 0 {
+                return Err(LifterError::Alignment(addr.clone()))
+            }
+        }
+
+        let lowering = ECodeLowering::new(&self.register_names, &self.memory, addr.bits());
+        self.lift_lowered_insn(ctxt, addr, bytes, &lowering)
+            .map_err(|e| LifterError::Undecodable { addr: addr.clone(), offset: 0, source: Box::new(e) })
+    }
+
+    // as lift_blk_with, but also returns the number of bytes lifted
+    pub(crate) fn lift_blk_extent(&self, ctxt: &mut ContextDatabase, addr: &Addr, bytes: &[u8], size_hint: Option<usize>) -> Result<(Vec<Entity<Blk>>, usize), LifterError> {
         let actual_size = bytes.len();
         let attempt_size = size_hint
             .map(|hint| actual_size.min(hint))
             .unwrap_or(actual_size);
-        
-        let bytes = &bytes[..attempt_size];
+
+        if let Some(alignment) = self.alignment {
+            if u64::try_from(addr.clone())? % alignment != 0 {
+                return Err(LifterError::Alignment(addr.clone()))
+            }
+        }
 
         log::debug!("lifting block at {} with size boundary of {}", addr, attempt_size);
 
-        let mut blks = Vec::new();
-        let mut stmts = Vec::new();
+        let lowering = ECodeLowering::new(&self.register_names, &self.memory, addr.bits());
+
+        let mut insns = Vec::new();
         let mut offset = 0;
+        let mut bound = attempt_size;
+        let mut undecodable = None;
 
-        while offset < attempt_size {
-            let taddr = self.translator.address(u64::try_from(addr + offset)?);
-            let view = &bytes[offset..];
-
-            log::trace!("lifting instruction at {}", taddr);
-            
-            if let Ok(mut ecode) = self.translator.lift_ecode(ctxt, taddr, view) {
-                log::trace!(
-                    "lifted instruction sequence consists of {} operations over {} bytes",
-                    ecode.operations().len(),
-                    ecode.length()
-                );
-                
-                if ecode.operations.is_empty() {
-                    log::trace!("lifted instruction is a no-op");
-                    ecode.operations_mut().push(Stmt::skip());
-                }
-                
-                let targets = ecode.branch_targets();
-                let length = ecode.length();
-
-                log::trace!(
-                    "lifted instruction sequence consists of {} branch targets",
-                    targets.len(),
-                );
-                
-                let mut should_stop = false;
-                for (i, tgt) in targets.iter() {
-                    log::trace!("- from {}.{}: {}", addr + offset, i, tgt);
-                    should_stop |= tgt.ends_block();
-                }
-                
-                log::trace!(
-                    "lifted instruction should terminate block: {}",
-                    should_stop,
-                );
-                
-                if should_stop {
-                    break
+        loop {
+            // true if the block ends before the bound
+            let mut stopped = false;
+
+            while offset < bound {
+                let iaddr = addr + offset;
+                let taddr = self.translator.address(u64::try_from(iaddr.clone())?);
+                // instructions may extend beyond the bound, so that we can
+                // detect hints that do not fall on instruction boundaries
+                let view = &bytes[offset..];
+
+                match self.lift_ecode_insn(ctxt, &iaddr, taddr, view, &lowering) {
+                    Ok((insn, should_stop)) => {
+                        let length = insn.ecode.length();
+                        offset += length;
+                        insns.push(insn);
+
+                        // the instruction that ends the block is part of it; a
+                        // zero-length instruction would never advance
+                        if should_stop || length == 0 {
+                            stopped = true;
+                            break
+                        }
+                    }
+                    // the branch is decodable, hence the block is not
+                    // lowered as ending in an invalid instruction
+                    Err(e @ LifterError::DelaySlot { .. }) => return Err(e),
+                    Err(e) => {
+                        log::trace!("instruction at {} could not be lifted: {}", iaddr, e);
+                        undecodable = Some(LifterError::Undecodable { addr: iaddr, offset, source: Box::new(e) });
+                        stopped = true;
+                        break
+                    }
                 }
-                
-                stmts.push((ecode, targets));
-                
-                offset += length;
+            }
+
+            // a hinted boundary is only trusted if the block ends there with
+            // a block-ending flow; otherwise, the hint likely comes from a
+            // tool with a different block model, and we lift without it
+            if stopped || bound == actual_size {
+                break
+            }
+
+            log::warn!(
+                "size hint of {} bytes for block at {} is not verified: {}; lifting without it",
+                attempt_size,
+                addr,
+                if offset > bound {
+                    "the boundary falls within an instruction"
+                } else {
+                    "the block does not end at the boundary"
+                },
+            );
+            bound = actual_size;
+        }
+
+        let blks = match undecodable {
+            // nothing could be decoded, e.g., as addr is the start of data
+            Some(e) if insns.is_empty() => return Err(e),
+            Some(e) => lowering.lower_invalid(insns, &(addr + offset), e),
+            None => lowering.lower(insns, &(addr + offset)),
+        };
+
+        log::trace!("lifted {} bytes into {} blocks", offset, blks.len());
+
+        Ok((blks, offset))
+    }
+
+    // lifts the instruction at iaddr (i.e., taddr), returning it and if it
+    // terminates its block; uses the cache, if enabled
+    fn lift_ecode_insn(
+        &self,
+        ctxt: &mut ContextDatabase,
+        iaddr: &Addr,
+        taddr: AddressValue,
+        view: &[u8],
+        lowering: &ECodeLowering,
+    ) -> Result<(ECodeInsn, bool), LifterError> {
+        if self.contexts.is_empty() {
+            return self.lift_ecode_insn_cached(ctxt, iaddr, taddr, view, lowering)
+        }
+
+        // values given for ranges apply to the instruction alone
+        let previous = self.contexts.enter(ctxt, iaddr)?;
+        let lifted = self.lift_ecode_insn_cached(ctxt, iaddr, taddr, view, lowering);
+        ContextRanges::leave(ctxt, previous)?;
+        lifted
+    }
+
+    fn lift_ecode_insn_cached(
+        &self,
+        ctxt: &mut ContextDatabase,
+        iaddr: &Addr,
+        taddr: AddressValue,
+        view: &[u8],
+        lowering: &ECodeLowering,
+    ) -> Result<(ECodeInsn, bool), LifterError> {
+        if !self.cache.is_enabled() {
+            return self.translate_ecode_insn(ctxt, iaddr, taddr, view, lowering)
+        }
+
+        // the context in effect for the instruction (including any values
+        // given for ranges, which are entered before lifting)
+        let context = self.cache.context_key(|name| self.context_value(ctxt, name, Some(iaddr)));
+
+        if let Some(hit) = self.cache.get(iaddr, &context, view) {
+            // replay the changes lifting the instruction made to the context
+            for (name, value) in self.cache.changes(&context, &hit.after) {
+                ctxt.set_variable_default(&name, value)?;
+            }
+
+            let insn = if hit.insn.addr == *iaddr {
+                hit.insn
             } else {
-                log::trace!("instruction could not be lifted");
-                break;
+                self.rebase_ecode_insn(hit.insn, iaddr, taddr)
+            };
+            return Ok((insn, hit.stop))
+        }
+
+        let (insn, stop) = self.translate_ecode_insn(ctxt, iaddr, taddr.clone(), view, lowering)?;
+        let after = self.cache.context_key(|name| ctxt.get_default_value(name));
+        let bytes = &view[..insn.ecode.length()];
+
+        // the same bytes lifted in the same context elsewhere: if they give
+        // the same code up to their address, the code does not depend on it
+        let independent = self
+            .cache
+            .lifted_elsewhere(iaddr, &context, bytes)
+            .map(|other| Self::same_ecode_insn(&self.rebase_ecode_insn(other, iaddr, taddr), &insn))
+            .unwrap_or(false);
+
+        self.cache.insert(context, bytes, &insn, stop, after, independent);
+
+        Ok((insn, stop))
+    }
+
+    // the instruction lifted elsewhere, as if lifted at iaddr (i.e., taddr)
+    fn rebase_ecode_insn(&self, mut insn: ECodeInsn, iaddr: &Addr, taddr: AddressValue) -> ECodeInsn {
+        insn.addr = iaddr.clone();
+        insn.ecode.address = taddr;
+        insn.source = Arc::new(insn.source.rebased(iaddr.clone()));
+        insn
+    }
+
+    fn same_ecode_insn(insn: &ECodeInsn, other: &ECodeInsn) -> bool {
+        insn.ecode.address == other.ecode.address
+            && insn.ecode.length == other.ecode.length
+            && insn.ecode.delay_slots == other.ecode.delay_slots
+            && insn.ecode.operations == other.ecode.operations
+            && insn.summary == other.summary
+            && insn.landing_pad == other.landing_pad
+            && insn.source == other.source
+    }
+
+    // lifts the instructions in the delay slots of the branch at iaddr,
+    // lifted as ecode from view, and moves them into ecode (see
+    // ECodeDelaySlotPass); returns a DelaySlot error if they cannot be
+    // lifted
+    fn fill_delay_slots(&self, ctxt: &mut ContextDatabase, iaddr: &Addr, ecode: &mut ECode, view: &[u8]) -> Result<(), LifterError> {
+        let end = ecode.length() + ecode.delay_slots();
+        let mut offset = ecode.length();
+        let mut slots = Vec::new();
+
+        while offset < end {
+            let error = |source| LifterError::DelaySlot { addr: iaddr.clone(), slot: iaddr + offset, source };
+            let slot = self
+                .translator
+                .lift_ecode(ctxt, ecode.address() + offset, &view[offset.min(view.len())..])
+                .map_err(|e| error(Some(e)))?;
+            if slot.length() == 0 {
+                return Err(error(None))
             }
+            offset += slot.length();
+            slots.push(slot);
+        }
+
+        log::trace!("lifted {} instructions in delay slots", slots.len());
+
+        let unique = self.translator.manager().unique_space_id();
+        if !ECodeDelaySlotPass::new(slots, unique).apply(ecode) {
+            log::warn!("delay slots of branch at {} have flows; lifting the branch without them", ecode.address());
         }
-        Ok(blks)
+        Ok(())
+    }
+
+    fn translate_ecode_insn(&self, ctxt: &mut ContextDatabase, iaddr: &Addr, taddr: AddressValue, view: &[u8], lowering: &ECodeLowering) -> Result<(ECodeInsn, bool), LifterError> {
+        log::trace!("lifting instruction at {}", taddr);
+
+        // disassembled before lifting, as lifting may update the context
+        let disassembly = if self.disassembly {
+            self.translator
+                .disassemble(ctxt, taddr.clone(), view)
+                .ok()
+                .map(|insn| (insn.mnemonic().to_owned(), insn.operands().to_owned()))
+        } else {
+            None
+        };
+        
+        let mut ecode = self.translator.lift_ecode(ctxt, taddr.clone(), view)?;
+
+        if ecode.delay_slots() > 0 {
+            self.fill_delay_slots(ctxt, iaddr, &mut ecode, view)?;
+        }
+
+        log::trace!(
+            "lifted instruction sequence consists of {} operations over {} bytes",
+            ecode.operations().len(),
+            ecode.length()
+        );
+
+        ECodePredicateNormalisePass::new(self.predicates).apply(&mut ecode);
+
+        let landing_pad = if let Some(mode) = self.pointers {
+            // some specifications model BTI as a hint without
+            // semantics, so we fall back to its mnemonic
+            ECodeAArch64Pass::new(mode).apply(&mut ecode)
+                || (ecode.operations().iter().all(|op| matches!(op, Stmt::Skip))
+                    && self.translator
+                        .disassemble(ctxt, taddr.clone(), view)
+                        .map(|insn| insn.mnemonic().eq_ignore_ascii_case("bti"))
+                        .unwrap_or(false))
+        } else {
+            false
+        };
+        
+        if ecode.operations.is_empty() {
+            log::trace!("lifted instruction is a no-op");
+            ecode.operations_mut().push(Stmt::skip());
+        }
+        
+        let targets = ecode.branch_targets();
+
+        log::trace!(
+            "lifted instruction sequence consists of {} branch targets",
+            targets.len(),
+        );
+        
+        let mut should_stop = self.mode == LiftMode::SingleInstruction;
+        for (i, tgt) in targets.iter() {
+            log::trace!("- from {}.{}: {}", iaddr, i, tgt);
+            should_stop |= match self.mode {
+                LiftMode::StrictBasicBlocks => !matches!(tgt, ECodeTarget::IntraBlk(_)),
+                _ => tgt.ends_block(),
+            };
+        }
+        
+        log::trace!(
+            "lifted instruction should terminate block: {}",
+            should_stop,
+        );
+        
+        let summary = if self.repeats == RepeatNormalisation::Intrinsic
+            && lowering.repeat_operands(&ecode).is_some()
+        {
+            self.translator
+                .disassemble(ctxt, taddr, view)
+                .ok()
+                .map(|insn| Arc::from(insn.mnemonic().to_lowercase()))
+        } else {
+            None
+        };
+
+        if let Some(ref name) = summary {
+            log::trace!("lifted instruction is summarised by intrinsic {}", name);
+        }
+
+        let mut source = SourceLoc::new(iaddr.clone(), ecode.length());
+        if let Some((mnemonic, operands)) = disassembly {
+            source = source.with_disassembly(mnemonic, operands);
+        }
+
+        Ok((ECodeInsn { addr: iaddr.clone(), ecode, summary, landing_pad, source: Arc::new(source) }, should_stop))
     }
 }
 
@@ -218,6 +1141,14 @@ mod test {
     use std::env;
     use std::path::PathBuf;
     use super::*;
+    use crate::ir::{Def, Expr, Jmp, Loc};
+    use crate::prelude::Identifiable;
+
+    fn x86_lifter() -> Result<Lifter, Box<dyn std::error::Error>> {
+        let root = env::var("DELIRIUM_TEST_ENV_ROOT")?;
+        let path = PathBuf::from_iter([&root, "processors"]);
+        Ok(LifterBuilder::new(&path)?.build("x86:LE:32:default", "gcc")?)
+    }
     
     #[test]
     fn test_blk_disasm() -> Result<(), Box<dyn std::error::Error>> {
@@ -241,4 +1172,338 @@ mod test {
         
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_lower_fall_through() -> Result<(), Box<dyn std::error::Error>> {
+        let lifter = x86_lifter()?;
+        let mut ctxt = lifter.context();
+
+        // nop; ret; nop
+        let blks = lifter.lift_blk(&mut ctxt, Addr::from(0x1000u32), &[0x90, 0xc3, 0x90])?;
+
+        // the ret ends the block, and is lifted as part of it
+        assert_eq!(blks.len(), 2);
+        assert_eq!(blks[0].addr(), Some(&Addr::from(0x1000u32)));
+        assert_eq!(blks[1].addr(), Some(&Addr::from(0x1001u32)));
+
+        assert_eq!(blks[0].jmps().len(), 1);
+        assert!(matches!(*blks[0].jmps()[0], Jmp::Branch(Loc::Resolved(id)) if id == blks[1].id()));
+        assert!(matches!(*blks[1].jmps()[0], Jmp::Return(_)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lower_conditional_branch() -> Result<(), Box<dyn std::error::Error>> {
+        let lifter = x86_lifter()?;
+        let mut ctxt = lifter.context();
+
+        // jne 0x1012; nop
+        let blks = lifter.lift_blk(&mut ctxt, Addr::from(0x1000u32), &[0x75, 0x10, 0x90])?;
+
+        assert_eq!(blks.len(), 1);
+
+        let jmps = blks[0].jmps();
+        assert_eq!(jmps.len(), 2);
+        assert!(matches!(*jmps[0], Jmp::CBranch(Loc::Fixed(ref tgt), _) if *tgt == Addr::from(0x1012u32)));
+        assert!(matches!(*jmps[1], Jmp::Branch(Loc::Fixed(ref tgt)) if *tgt == Addr::from(0x1002u32)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeat_loop() -> Result<(), Box<dyn std::error::Error>> {
+        let lifter = x86_lifter()?;
+        let mut ctxt = lifter.context();
+
+        // rep stosb
+        let blks = lifter.lift_blk(&mut ctxt, Addr::from(0x1000u32), &[0xf3, 0xaa])?;
+
+        // the guard and the iteration form a loop, whose back-edge targets
+        // the start of the instruction
+        assert!(blks.len() > 1);
+        assert!(blks
+            .iter()
+            .flat_map(|blk| blk.jmps())
+            .any(|jmp| matches!(**jmp, Jmp::Branch(Loc::Resolved(id)) if id == blks[0].id())));
+        assert!(!blks
+            .iter()
+            .flat_map(|blk| blk.jmps())
+            .any(|jmp| matches!(**jmp, Jmp::Intrinsic(_, _))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeat_intrinsic() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lifter = x86_lifter()?;
+        lifter.set_repeat_normalisation(RepeatNormalisation::Intrinsic);
+
+        let mut ctxt = lifter.context();
+
+        // rep stosb
+        let blks = lifter.lift_blk(&mut ctxt, Addr::from(0x1000u32), &[0xf3, 0xaa])?;
+
+        assert_eq!(blks.len(), 1);
+
+        let jmps = blks[0].jmps();
+        assert_eq!(jmps.len(), 2);
+        match *jmps[0] {
+            // the count (ECX) and the direction flag
+            Jmp::Intrinsic(ref name, ref args) => {
+                assert!(name.starts_with("stos"));
+                assert_eq!(args.len(), 2);
+            }
+            _ => panic!("rep stosb is not summarised by an intrinsic"),
+        }
+        assert!(matches!(*jmps[1], Jmp::Branch(Loc::Fixed(ref tgt)) if *tgt == Addr::from(0x1002u32)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeat_intrinsic_without_prefix() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lifter = x86_lifter()?;
+        lifter.set_repeat_normalisation(RepeatNormalisation::Intrinsic);
+
+        let mut ctxt = lifter.context();
+
+        // stosb
+        let blks = lifter.lift_blk(&mut ctxt, Addr::from(0x1000u32), &[0xaa])?;
+
+        // without the prefix, the instruction does not repeat, and is
+        // lifted as it is
+        assert_eq!(blks.len(), 1);
+        assert!(!blks[0].defs().is_empty());
+        assert_eq!(blks[0].jmps().len(), 1);
+        assert!(matches!(*blks[0].jmps()[0], Jmp::Branch(Loc::Fixed(ref tgt)) if *tgt == Addr::from(0x1001u32)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_hits_and_misses() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lifter = x86_lifter()?;
+        lifter.set_cache_capacity(16);
+
+        let mut ctxt = lifter.context();
+
+        // nop; ret
+        let uncached = lifter.lift_blk(&mut ctxt, Addr::from(0x1000u32), &[0x90, 0xc3])?;
+        assert_eq!(lifter.cache_stats().misses, 2);
+
+        // the second lift shows that the code does not depend on its
+        // address, and the third reuses it
+        lifter.lift_blk(&mut ctxt, Addr::from(0x2000u32), &[0x90, 0xc3])?;
+        let cached = lifter.lift_blk(&mut ctxt, Addr::from(0x3000u32), &[0x90, 0xc3])?;
+
+        let stats = lifter.cache_stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (2, 2, 4));
+
+        assert_eq!(cached.len(), uncached.len());
+        assert_eq!(cached[0].addr(), Some(&Addr::from(0x3000u32)));
+        assert_eq!(cached[1].addr(), Some(&Addr::from(0x3001u32)));
+        assert_eq!(cached[0].source().map(|source| source.addr()), Some(&Addr::from(0x3000u32)));
+
+        // call 0x1005 (relative): its target depends on its address
+        lifter.lift_insn(&mut ctxt, Addr::from(0x1000u32), &[0xe8, 0x00, 0x00, 0x00, 0x00])?;
+        lifter.lift_insn(&mut ctxt, Addr::from(0x2000u32), &[0xe8, 0x00, 0x00, 0x00, 0x00])?;
+        let call = lifter.lift_insn(&mut ctxt, Addr::from(0x3000u32), &[0xe8, 0x00, 0x00, 0x00, 0x00])?;
+
+        let stats = lifter.cache_stats();
+        assert_eq!((stats.hits, stats.misses), (2, 7));
+        assert!(call
+            .blks()
+            .iter()
+            .flat_map(|blk| blk.jmps())
+            .any(|jmp| matches!(**jmp, Jmp::Call(Loc::Fixed(ref tgt), _) if *tgt == Addr::from(0x3005u32))));
+
+        // lifted again at the same address
+        lifter.lift_insn(&mut ctxt, Addr::from(0x3000u32), &[0xe8, 0x00, 0x00, 0x00, 0x00])?;
+        assert_eq!(lifter.cache_stats().hits, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_eviction() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lifter = x86_lifter()?;
+        lifter.set_cache_capacity(1);
+
+        let mut ctxt = lifter.context();
+
+        // nop; ret: the ret evicts the nop, and the nop the ret
+        lifter.lift_blk(&mut ctxt, Addr::from(0x1000u32), &[0x90, 0xc3])?;
+        lifter.lift_blk(&mut ctxt, Addr::from(0x1000u32), &[0x90, 0xc3])?;
+
+        let stats = lifter.cache_stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (1, 0, 4));
+
+        // the ret alone is cached
+        lifter.lift_blk(&mut ctxt, Addr::from(0x1001u32), &[0xc3])?;
+        assert_eq!(lifter.cache_stats().hits, 1);
+
+        lifter.set_cache_capacity(0);
+        assert_eq!(lifter.cache_stats().entries, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_differing_context() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lifter = x86_lifter()?;
+        lifter.set_cache_capacity(16);
+
+        let mut ctxt = lifter.context();
+
+        // mov eax, 1 decodes as mov ax, 1 with a 16-bit operand size
+        let bytes = [0xb8, 0x01, 0x00, 0x00, 0x00];
+        lifter.set_context(&mut ctxt, "opsize", 0, Some(Addr::from(0x2000u32)..Addr::from(0x2010u32)))?;
+
+        let wide = lifter.lift_insn(&mut ctxt, Addr::from(0x1000u32), &bytes)?;
+        let narrow = lifter.lift_insn(&mut ctxt, Addr::from(0x2000u32), &bytes)?;
+        let wide_again = lifter.lift_insn(&mut ctxt, Addr::from(0x3000u32), &bytes)?;
+
+        assert_eq!(wide.len(), 5);
+        assert_eq!(narrow.len(), 3);
+        assert_eq!(wide_again.len(), 5);
+
+        // the instruction at 0x2000 is lifted in the context given for its
+        // range, so does not share the entry of the others
+        let stats = lifter.cache_stats();
+        assert_eq!((stats.hits, stats.misses), (0, 3));
+
+        let narrow_again = lifter.lift_insn(&mut ctxt, Addr::from(0x2000u32), &bytes)?;
+        assert_eq!(narrow_again.len(), 3);
+        assert_eq!(lifter.cache_stats().hits, 1);
+
+        Ok(())
+    }
+
+    fn mips_lifter() -> Result<Lifter, Box<dyn std::error::Error>> {
+        let root = env::var("DELIRIUM_TEST_ENV_ROOT")?;
+        let path = PathBuf::from_iter([&root, "processors"]);
+        Ok(LifterBuilder::new(&path)?.build_default("MIPS:BE:32:default")?)
+    }
+
+    #[test]
+    fn test_delay_slot_writes_condition() -> Result<(), Box<dyn std::error::Error>> {
+        let lifter = mips_lifter()?;
+        let mut ctxt = lifter.context();
+
+        // beq v0, zero, 0x1014; addiu v0, v0, 1 (in the delay slot)
+        let bytes = [0x10, 0x40, 0x00, 0x04, 0x24, 0x42, 0x00, 0x01];
+        let blks = lifter.lift_blk(&mut ctxt, Addr::from(0x1000u32), &bytes)?;
+
+        let cond = blks
+            .iter()
+            .flat_map(|blk| blk.jmps().iter())
+            .find_map(|jmp| match **jmp {
+                Jmp::CBranch(_, Expr::Var(ref var)) => Some(var.clone()),
+                _ => None,
+            })
+            .ok_or("no conditional branch on a variable")?;
+
+        let defs = blks
+            .iter()
+            .flat_map(|blk| blk.defs().iter())
+            .filter_map(|def| match **def {
+                Def::Assign(ref var, _) => Some(var.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // the condition is evaluated before the delay slot changes v0
+        let captured = defs.iter().position(|var| *var == cond).ok_or("condition not assigned")?;
+        let slot = defs.iter().position(|var| &**var.name() == "v0").ok_or("v0 not assigned")?;
+        assert!(captured < slot);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_delay_slot_not_lifted() -> Result<(), Box<dyn std::error::Error>> {
+        let lifter = mips_lifter()?;
+        let mut ctxt = lifter.context();
+
+        // beq v0, zero, 0x1014, without the bytes of its delay slot
+        let result = lifter.lift_blk(&mut ctxt, Addr::from(0x1000u32), &[0x10, 0x40, 0x00, 0x04]);
+        assert!(matches!(result, Err(LifterError::DelaySlot { .. })));
+
+        Ok(())
+    }
+
+    fn arm_lifter() -> Result<Lifter, Box<dyn std::error::Error>> {
+        let root = env::var("DELIRIUM_TEST_ENV_ROOT")?;
+        let path = PathBuf::from_iter([&root, "processors"]);
+        Ok(LifterBuilder::new(&path)?.build_default("ARM:LE:32:v7")?)
+    }
+
+    #[test]
+    fn test_predicate_branch() -> Result<(), Box<dyn std::error::Error>> {
+        let lifter = arm_lifter()?;
+        let mut ctxt = lifter.context();
+
+        // moveq r0, #1
+        let blks = lifter.lift_blk(&mut ctxt, Addr::from(0x1000u32), &[0x01, 0x00, 0xa0, 0x03])?;
+
+        // the guard skips to the fall-through of the instruction
+        let guard = blks
+            .iter()
+            .flat_map(|blk| blk.jmps().iter())
+            .find(|jmp| matches!(***jmp, Jmp::CBranch(_, _)))
+            .ok_or("guard not lifted as a conditional branch")?;
+
+        assert!(matches!(**guard, Jmp::CBranch(Loc::Fixed(ref tgt), _) if *tgt == Addr::from(0x1004u32)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_predicate_select() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lifter = arm_lifter()?;
+        lifter.set_predicate_normalisation(PredicateNormalisation::Select);
+
+        let mut ctxt = lifter.context();
+
+        // moveq r0, #1
+        let blks = lifter.lift_blk(&mut ctxt, Addr::from(0x1000u32), &[0x01, 0x00, 0xa0, 0x03])?;
+
+        // the guard is folded into the assignment to r0
+        assert_eq!(blks.len(), 1);
+        assert!(blks[0].jmps().iter().all(|jmp| !matches!(**jmp, Jmp::CBranch(_, _))));
+
+        let selected = blks[0].defs().iter().any(|def| {
+            matches!(**def, Def::Assign(ref var, Expr::IfElse(_, _, _)) if &**var.name() == "r0")
+        });
+        assert!(selected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_riscv_alignment() -> Result<(), Box<dyn std::error::Error>> {
+        let root = env::var("DELIRIUM_TEST_ENV_ROOT")?;
+        let path = PathBuf::from_iter([&root, "processors"]);
+        let builder = LifterBuilder::new(&path)?;
+
+        // addi a0, a0, 1
+        let bytes = [0x13, 0x05, 0x15, 0x00];
+
+        // without RVC, 2-byte boundaries are not instruction boundaries
+        let lifter = builder.build_default("RISCV:LE:64:RV64G")?;
+        let mut ctxt = lifter.context();
+        assert_eq!(lifter.instruction_alignment(), Some(4));
+        assert!(matches!(
+            lifter.lift_insn(&mut ctxt, Addr::from(0x1002u64), &bytes),
+            Err(LifterError::Alignment(_))
+        ));
+
+        let lifter = builder.build_default("RISCV:LE:64:RV64GC")?;
+        let mut ctxt = lifter.context();
+        assert_eq!(lifter.instruction_alignment(), Some(2));
+        assert!(lifter.lift_insn(&mut ctxt, Addr::from(0x1002u64), &bytes).is_ok());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::BTreeSet;
+use std::fmt::{self, Display};
+
+use fugue::arch::ArchitectureDef;
+
+/// The standard RISC-V extensions that a language may support.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RiscVExtension {
+    /// Integer multiplication and division
+    M,
+    /// Atomic instructions
+    A,
+    /// Single-precision floating-point
+    F,
+    /// Double-precision floating-point
+    D,
+    /// Compressed (2-byte) instructions
+    C,
+    /// Vector operations
+    V,
+}
+
+impl Display for RiscVExtension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let c = match self {
+            Self::M => 'M',
+            Self::A => 'A',
+            Self::F => 'F',
+            Self::D => 'D',
+            Self::C => 'C',
+            Self::V => 'V',
+        };
+        write!(f, "{}", c)
+    }
+}
+
+pub(crate) fn is_riscv(arch: &ArchitectureDef) -> bool {
+    arch.processor().eq_ignore_ascii_case("RISCV")
+}
+
+/// Determines the extensions supported by a RISC-V language from its
+/// variant, e.g., `RV64GC` or `RV32IMC`; the default variant supports
+/// `GC`. `G` is shorthand for `IMAFD`.
+pub(crate) fn extensions(arch: &ArchitectureDef) -> BTreeSet<RiscVExtension> {
+    let variant = arch.variant().to_ascii_uppercase();
+    let isa = if variant == "DEFAULT" {
+        "GC"
+    } else {
+        variant
+            .strip_prefix("RV32")
+            .or_else(|| variant.strip_prefix("RV64"))
+            .or_else(|| variant.strip_prefix("RV128"))
+            .unwrap_or(&variant)
+    };
+
+    let mut extensions = BTreeSet::new();
+
+    // extensions following the single-letter ones (e.g., _Zicsr) are not
+    // standard extensions we track
+    for c in isa.chars().take_while(|c| *c != '_') {
+        match c {
+            'G' => extensions.extend([
+                RiscVExtension::M,
+                RiscVExtension::A,
+                RiscVExtension::F,
+                RiscVExtension::D,
+            ]),
+            'M' => { extensions.insert(RiscVExtension::M); }
+            'A' => { extensions.insert(RiscVExtension::A); }
+            'F' => { extensions.insert(RiscVExtension::F); }
+            'D' => { extensions.insert(RiscVExtension::D); }
+            'C' => { extensions.insert(RiscVExtension::C); }
+            'V' => { extensions.insert(RiscVExtension::V); }
+            _ => (),
+        }
+    }
+
+    extensions
+}
@@ -0,0 +1,269 @@
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::analysis::ContentHash;
+
+#[derive(Debug, Error)]
+pub enum SpecError {
+    #[error("malformed specification stamp `{0}`")]
+    Malformed(String),
+    #[error("lifted with language {found}, but the lifter's language is {expected}")]
+    Language { expected: Arc<str>, found: Arc<str> },
+    #[error("lifted with specification {found}, but the lifter's specification is {expected}")]
+    Version { expected: SpecVersion, found: SpecVersion },
+}
+
+/// The version of the processor specification a Lifter's language was
+/// built from: the language's id, the version declared for it by its
+/// `.ldefs` file (if any), and a hash of the specification files it is
+/// built from (its `.ldefs`, `.sla`, `.pspec`, and `.cspec` files).
+///
+/// Upgrading the specifications (e.g., to those of a newer Ghidra release)
+/// may change the IR lifted for the same bytes; applications that persist
+/// lifted IR should store the version's stamp (its `Display` form) with it,
+/// and check it on load via `SpecVersion::check` (or
+/// `Project::check_spec_version`). `Project::relift_diff` reports which
+/// instructions and Subs would be lifted differently by another Lifter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SpecVersion {
+    language: Arc<str>,
+    version: Option<Arc<str>>,
+    hash: u64,
+}
+
+impl SpecVersion {
+    pub fn new(language: impl Into<Arc<str>>, version: Option<Arc<str>>, hash: u64) -> Self {
+        Self {
+            language: language.into(),
+            version,
+            hash,
+        }
+    }
+
+    /// The version of the specification of `language` within the
+    /// processor specifications at `root`; files that cannot be read are
+    /// ignored.
+    pub fn from_directory(root: impl AsRef<Path>, language: impl Into<Arc<str>>) -> Self {
+        let language = language.into();
+
+        let mut ldefs = Vec::new();
+        find_ldefs(root.as_ref(), &mut ldefs);
+
+        for path in ldefs {
+            let text = if let Ok(text) = fs::read_to_string(&path) {
+                text
+            } else {
+                continue
+            };
+
+            if let Some(element) = language_element(&text, &language) {
+                let dir = path.parent().unwrap_or_else(|| Path::new(""));
+                let version = attribute(element, "version").map(Arc::from);
+
+                let mut hash = ContentHash::new();
+                hash.write_str(&language).write(text.as_bytes());
+
+                let mut files = ["slafile", "processorspec"]
+                    .iter()
+                    .filter_map(|name| attribute(element, name))
+                    .chain(attributes(element, "spec"))
+                    .collect::<Vec<_>>();
+                files.sort_unstable();
+                files.dedup();
+
+                for file in files {
+                    hash.write_str(file);
+                    if let Ok(bytes) = fs::read(dir.join(file)) {
+                        hash.write_u64(bytes.len() as u64).write(&bytes);
+                    }
+                }
+
+                return Self::new(language, version, hash.finish())
+            }
+        }
+
+        let hash = ContentHash::new().write_str(&language).finish();
+        Self::new(language, None, hash)
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// The version declared for the language by its `.ldefs` file.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// A hash of the specification files of the language.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Checks that IR stamped with `found` is that which would be lifted
+    /// by a Lifter with this version.
+    pub fn check(&self, found: &SpecVersion) -> Result<(), SpecError> {
+        if self.language != found.language {
+            Err(SpecError::Language {
+                expected: self.language.clone(),
+                found: found.language.clone(),
+            })
+        } else if self.hash != found.hash {
+            Err(SpecError::Version {
+                expected: self.clone(),
+                found: found.clone(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// stamps are of the form language@version#hash, where version is `-` if
+// the language does not declare one
+impl Display for SpecVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}@{}#{:016x}",
+            self.language,
+            self.version.as_deref().unwrap_or("-"),
+            self.hash,
+        )
+    }
+}
+
+impl FromStr for SpecVersion {
+    type Err = SpecError;
+
+    fn from_str(stamp: &str) -> Result<Self, Self::Err> {
+        let malformed = || SpecError::Malformed(stamp.to_owned());
+
+        let (rest, hash) = stamp.trim().rsplit_once('#').ok_or_else(malformed)?;
+        let (language, version) = rest.rsplit_once('@').ok_or_else(malformed)?;
+        let hash = u64::from_str_radix(hash, 16).map_err(|_| malformed())?;
+
+        if language.is_empty() || version.is_empty() {
+            return Err(malformed())
+        }
+
+        let version = if version == "-" { None } else { Some(Arc::from(version)) };
+        Ok(Self::new(language, version, hash))
+    }
+}
+
+fn find_ldefs(dir: &Path, ldefs: &mut Vec<PathBuf>) {
+    let mut entries = if let Ok(entries) = fs::read_dir(dir) {
+        entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect::<Vec<_>>()
+    } else {
+        return
+    };
+
+    // so that the first declaration of a language found is deterministic
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            find_ldefs(&path, ldefs);
+        } else if path.extension().map(|ext| ext == "ldefs").unwrap_or(false) {
+            ldefs.push(path);
+        }
+    }
+}
+
+// the text of the <language> element of text whose id is language
+fn language_element<'a>(text: &'a str, language: &str) -> Option<&'a str> {
+    let mut rest = text;
+    while let Some(start) = rest.find("<language") {
+        let element = &rest[start..];
+
+        // skip, e.g., <language_definitions>
+        if !element["<language".len()..].starts_with(char::is_whitespace) {
+            rest = &element["<language".len()..];
+            continue
+        }
+
+        let end = element
+            .find("</language>")
+            .or_else(|| element.find("/>").map(|end| end + 2))
+            .unwrap_or(element.len());
+        let (element, tail) = element.split_at(end);
+
+        let tag = &element[..element.find('>').unwrap_or(element.len())];
+        if attribute(tag, "id") == Some(language) {
+            return Some(element)
+        }
+        rest = tail;
+    }
+    None
+}
+
+// the values of the attributes named name within text
+fn attributes<'a>(text: &'a str, name: &str) -> Vec<&'a str> {
+    let pattern = format!("{}=\"", name);
+    text.match_indices(&pattern)
+        // skip attributes whose names end with name, e.g., xid for id
+        .filter(|(index, _)| !text[..*index].ends_with(|c: char| c.is_alphanumeric() || c == '_'))
+        .filter_map(|(index, _)| {
+            let value = &text[index + pattern.len()..];
+            value.find('"').map(|end| &value[..end])
+        })
+        .collect()
+}
+
+fn attribute<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    attributes(text, name).into_iter().next()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LDEFS: &str = r#"<language_definitions>
+  <language processor="x86" endian="little" size="32" variant="default" version="2.13"
+            slafile="x86.sla" processorspec="x86.pspec" id="x86:LE:32:default">
+    <compiler name="gcc" spec="x86gcc.cspec" id="gcc"/>
+    <compiler name="Visual Studio" spec="x86win.cspec" id="windows"/>
+  </language>
+  <language processor="x86" endian="little" size="64" variant="default" version="2.13"
+            slafile="x86-64.sla" processorspec="x86-64.pspec" id="x86:LE:64:default">
+    <compiler name="gcc" spec="x86-64-gcc.cspec" id="gcc"/>
+  </language>
+</language_definitions>"#;
+
+    #[test]
+    fn test_language_element() {
+        let element = language_element(LDEFS, "x86:LE:64:default").unwrap();
+        assert_eq!(attribute(element, "slafile"), Some("x86-64.sla"));
+        assert_eq!(attribute(element, "version"), Some("2.13"));
+        assert_eq!(attributes(element, "spec"), vec!["x86-64-gcc.cspec"]);
+
+        let element = language_element(LDEFS, "x86:LE:32:default").unwrap();
+        assert_eq!(attributes(element, "spec"), vec!["x86gcc.cspec", "x86win.cspec"]);
+        // processorspec is not matched as spec
+        assert_eq!(attribute(element, "processorspec"), Some("x86.pspec"));
+
+        assert!(language_element(LDEFS, "x86:LE:16:default").is_none());
+    }
+
+    #[test]
+    fn test_stamp() -> Result<(), SpecError> {
+        let version = SpecVersion::new("x86:LE:64:default", Some(Arc::from("2.13")), 0x1234);
+        assert_eq!(version.to_string().parse::<SpecVersion>()?, version);
+
+        let unversioned = SpecVersion::new("x86:LE:64:default", None, 0x1234);
+        assert_eq!(unversioned.to_string().parse::<SpecVersion>()?, unversioned);
+        assert!(version.check(&unversioned).is_ok());
+
+        let upgraded = SpecVersion::new("x86:LE:64:default", Some(Arc::from("2.14")), 0x5678);
+        assert!(matches!(version.check(&upgraded), Err(SpecError::Version { .. })));
+
+        assert!("x86:LE:64:default".parse::<SpecVersion>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
+
+use fugue::ir::disassembly::ContextDatabase;
+
+use crate::ir::{Addr, Jmp, Loc};
+use crate::lift::ecode::lower::ECodeLowering;
+use crate::lift::{InsnFlow, LiftedInsn, Lifter, LifterError};
+
+/// The candidate instructions decoded at every offset of a buffer (see
+/// `Lifter::lift_superset`), i.e., a superset of its true instructions,
+/// with the flows between them.
+///
+/// Candidates whose bytes overlap conflict: at most one of them can be a
+/// true instruction.
+#[derive(Clone)]
+pub struct SupersetGraph {
+    addr: Addr,
+    insns: BTreeMap<usize, LiftedInsn>,
+    // the offsets of the fall-through and fixed targets of each candidate
+    // within the buffer
+    successors: BTreeMap<usize, BTreeSet<usize>>,
+    undecodable: Vec<usize>,
+    max_len: usize,
+}
+
+impl SupersetGraph {
+    /// The address of the start of the buffer.
+    pub fn addr(&self) -> &Addr {
+        &self.addr
+    }
+
+    /// The candidates, by their offset within the buffer.
+    pub fn insns(&self) -> impl Iterator<Item = (usize, &LiftedInsn)> {
+        self.insns.iter().map(|(offset, insn)| (*offset, insn))
+    }
+
+    pub fn insn(&self, offset: usize) -> Option<&LiftedInsn> {
+        self.insns.get(&offset)
+    }
+
+    pub fn len(&self) -> usize {
+        self.insns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.insns.is_empty()
+    }
+
+    /// The offsets at which no instruction could be decoded.
+    pub fn undecodable(&self) -> &[usize] {
+        &self.undecodable
+    }
+
+    /// The offsets of the candidates the candidate at `offset` may flow
+    /// to (i.e., falls through to or branches to) within the buffer.
+    pub fn successors(&self, offset: usize) -> impl Iterator<Item = usize> + '_ {
+        self.successors.get(&offset).into_iter().flatten().copied()
+    }
+
+    /// The offsets of the candidates whose bytes overlap those of the
+    /// candidate at `offset`.
+    pub fn conflicts(&self, offset: usize) -> Vec<usize> {
+        let len = match self.insns.get(&offset) {
+            Some(insn) => insn.len(),
+            None => return Vec::new(),
+        };
+
+        self.insns
+            .range(offset.saturating_sub(self.max_len)..offset + len)
+            .filter(|(other, insn)| **other != offset && **other + insn.len() > offset)
+            .map(|(other, _)| *other)
+            .collect()
+    }
+
+    /// The offsets of the candidates that never flow into bytes that
+    /// cannot be decoded (or to offsets at which only such candidates
+    /// start), as true instructions cannot; the others are likely data.
+    pub fn viable(&self) -> BTreeSet<usize> {
+        let mut predecessors = BTreeMap::<usize, Vec<usize>>::new();
+        for (offset, successors) in self.successors.iter() {
+            for successor in successors {
+                predecessors.entry(*successor).or_default().push(*offset);
+            }
+        }
+
+        let mut invalid = BTreeSet::new();
+        let mut worklist = self
+            .successors
+            .values()
+            .flatten()
+            .filter(|successor| !self.insns.contains_key(*successor))
+            .copied()
+            .collect::<Vec<_>>();
+
+        while let Some(offset) = worklist.pop() {
+            if invalid.insert(offset) {
+                worklist.extend(predecessors.get(&offset).into_iter().flatten().copied());
+            }
+        }
+
+        self.insns
+            .keys()
+            .filter(|offset| !invalid.contains(*offset))
+            .copied()
+            .collect()
+    }
+}
+
+impl Lifter {
+    /// Lifts a candidate instruction at every offset of `bytes` (or at
+    /// every offset aligned to an instruction boundary, if the language
+    /// requires alignment), as for shingled disassembly; see
+    /// `SupersetGraph`.
+    ///
+    /// Each candidate is lifted in its own copy of `ctxt`, so that changes
+    /// made to the context by one candidate do not affect the others.
+    pub fn lift_superset(
+        &self,
+        ctxt: &ContextDatabase,
+        addr: impl Borrow<Addr>,
+        bytes: &[u8],
+    ) -> Result<SupersetGraph, LifterError> {
+        let addr = addr.borrow();
+        let base = u64::try_from(addr.clone())?;
+        let lowering = ECodeLowering::new(&self.register_names, &self.memory, addr.bits());
+
+        let mut insns = BTreeMap::new();
+        let mut successors = BTreeMap::new();
+        let mut undecodable = Vec::new();
+        let mut max_len = 0;
+
+        for offset in 0..bytes.len() {
+            if let Some(alignment) = self.alignment {
+                if (base + offset as u64) % alignment != 0 {
+                    continue
+                }
+            }
+
+            let iaddr = addr + offset;
+            let insn = match self.lift_lowered_insn(&mut ctxt.clone(), &iaddr, &bytes[offset..], &lowering) {
+                Ok(insn) if !insn.is_empty() => insn,
+                _ => {
+                    undecodable.push(offset);
+                    continue
+                }
+            };
+
+            let mut targets = BTreeSet::new();
+            if insn.flows().contains(&InsnFlow::FallThrough) {
+                targets.insert(offset + insn.len());
+            }
+            for jmp in insn.blks().iter().flat_map(|blk| blk.jmps()) {
+                if let Jmp::Branch(Loc::Fixed(ref target))
+                | Jmp::CBranch(Loc::Fixed(ref target), _)
+                | Jmp::Call(Loc::Fixed(ref target), _) = **jmp
+                {
+                    let target = u64::try_from(target)?;
+                    if target >= base && target - base < bytes.len() as u64 {
+                        targets.insert((target - base) as usize);
+                    }
+                }
+            }
+            // flows past the end of the buffer are unknown, rather than
+            // undecodable
+            targets.retain(|target| *target < bytes.len());
+
+            max_len = max_len.max(insn.len());
+            successors.insert(offset, targets);
+            insns.insert(offset, insn);
+        }
+
+        log::debug!("lifted {} candidate instructions from {} bytes at {}", insns.len(), bytes.len(), addr);
+
+        Ok(SupersetGraph {
+            addr: addr.clone(),
+            insns,
+            successors,
+            undecodable,
+            max_len,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::lift::LifterBuilder;
+
+    #[test]
+    fn test_superset_through_nops() -> Result<(), Box<dyn std::error::Error>> {
+        let root = env::var("DELIRIUM_TEST_ENV_ROOT")?;
+        let path = PathBuf::from_iter([&root, "processors"]);
+        let lifter = LifterBuilder::new(&path)?.build("x86:LE:32:default", "gcc")?;
+        let ctxt = lifter.context();
+
+        // xor eax, eax @ 0; nop @ 2; nop @ 3; ret @ 4
+        let graph = lifter.lift_superset(&ctxt, Addr::from(0x1000u32), &[0x31, 0xc0, 0x90, 0x90, 0xc3])?;
+
+        // the padding does not end the paths through it
+        assert_eq!(graph.successors(0).collect::<Vec<_>>(), [2]);
+        assert_eq!(graph.successors(2).collect::<Vec<_>>(), [3]);
+        assert_eq!(graph.successors(3).collect::<Vec<_>>(), [4]);
+        assert_eq!(graph.successors(4).count(), 0);
+        assert!([0, 2, 3, 4].iter().all(|offset| graph.viable().contains(offset)));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use crate::ir::{Addr, Confidence};
+use crate::lift::IsaMode;
+use crate::oracles::{BlkOracle, SubOracle};
+
+/// Composes multiple oracles, e.g., symbols from a loader, Blk extents
+/// from Ghidra, and additional Sub starts supplied by the user.
+///
+/// Oracles are consulted in the order they are added (i.e., earlier
+/// oracles have priority): single-valued queries (`blk_size`,
+/// `blk_isa_mode`, and `sub_symbol`) are answered by the first oracle
+/// that gives an answer; set-valued queries (`blk_jmps`, `sub_starts`,
+/// and `sub_blocks`) are answered by the union of the answers of all
+/// oracles. A Sub never returns if any oracle claims so.
+#[derive(Clone, Default)]
+pub struct Chain {
+    blk_oracles: Vec<Arc<dyn BlkOracle>>,
+    sub_oracles: Vec<Arc<dyn SubOracle>>,
+}
+
+impl Chain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn blk_oracle(&mut self, oracle: Arc<dyn BlkOracle>) -> &mut Self {
+        self.blk_oracles.push(oracle);
+        self
+    }
+
+    pub fn sub_oracle(&mut self, oracle: Arc<dyn SubOracle>) -> &mut Self {
+        self.sub_oracles.push(oracle);
+        self
+    }
+
+    /// Adds `oracle` as both a BlkOracle and a SubOracle.
+    pub fn oracle<O>(&mut self, oracle: O) -> &mut Self
+    where
+        O: BlkOracle + SubOracle + 'static,
+    {
+        let oracle = Arc::new(oracle);
+        self.blk_oracles.push(oracle.clone());
+        self.sub_oracles.push(oracle);
+        self
+    }
+
+    pub fn blk_oracles(&self) -> &[Arc<dyn BlkOracle>] {
+        &self.blk_oracles
+    }
+
+    pub fn sub_oracles(&self) -> &[Arc<dyn SubOracle>] {
+        &self.sub_oracles
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blk_oracles.is_empty() && self.sub_oracles.is_empty()
+    }
+}
+
+impl BlkOracle for Chain {
+    fn blk_size(&self, addr: &Addr) -> Option<usize> {
+        self.blk_oracles.iter().find_map(|oracle| oracle.blk_size(addr))
+    }
+
+    fn blk_jmps(&self, addr: &Addr) -> BTreeSet<Addr> {
+        self.blk_oracles
+            .iter()
+            .flat_map(|oracle| oracle.blk_jmps(addr))
+            .collect()
+    }
+
+    fn blk_isa_mode(&self, addr: &Addr) -> Option<IsaMode> {
+        self.blk_oracles.iter().find_map(|oracle| oracle.blk_isa_mode(addr))
+    }
+}
+
+impl SubOracle for Chain {
+    fn sub_starts(&self) -> BTreeSet<Addr> {
+        self.sub_oracles
+            .iter()
+            .flat_map(|oracle| oracle.sub_starts())
+            .collect()
+    }
+
+    fn sub_symbol(&self, addr: &Addr) -> Option<String> {
+        self.sub_oracles.iter().find_map(|oracle| oracle.sub_symbol(addr))
+    }
+
+    fn sub_blocks(&self, addr: &Addr) -> BTreeSet<Addr> {
+        self.sub_oracles
+            .iter()
+            .flat_map(|oracle| oracle.sub_blocks(addr))
+            .collect()
+    }
+
+    // the most trusted claim made by any oracle
+    fn sub_confidence(&self, addr: &Addr) -> Option<Confidence> {
+        self.sub_oracles
+            .iter()
+            .filter_map(|oracle| oracle.sub_confidence(addr))
+            .max()
+    }
+
+    fn sub_no_return(&self, addr: &Addr) -> bool {
+        self.sub_oracles.iter().any(|oracle| oracle.sub_no_return(addr))
+    }
+}
+
+/// Sub starts supplied directly, e.g., by the user.
+impl SubOracle for BTreeSet<Addr> {
+    fn sub_starts(&self) -> BTreeSet<Addr> {
+        self.clone()
+    }
+
+    fn sub_symbol(&self, _addr: &Addr) -> Option<String> {
+        None
+    }
+
+    fn sub_blocks(&self, _addr: &Addr) -> BTreeSet<Addr> {
+        BTreeSet::new()
+    }
+
+    fn sub_confidence(&self, addr: &Addr) -> Option<Confidence> {
+        self.contains(addr).then(|| Confidence::Certain)
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::ir::{Addr, Confidence};
+use crate::oracles::{BlkOracle, SubOracle};
+
+#[derive(Debug, Error)]
+pub enum GhidraOracleError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("malformed XML: {0}")]
+    Xml(String),
+    #[error("invalid address `{0}`")]
+    Address(String),
+    #[error("missing field `{0}`")]
+    Missing(&'static str),
+}
+
+#[derive(Debug, Clone, Default)]
+struct Function {
+    symbol: Option<String>,
+    blks: BTreeSet<Addr>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Block {
+    size: usize,
+    jmps: BTreeSet<Addr>,
+}
+
+/// A SubOracle and BlkOracle backed by analysis exported from Ghidra.
+///
+/// Two formats are supported: Ghidra's XML export (`File > Export
+/// Program... > XML`), from which we take the entry point and name of each
+/// `FUNCTION`; and JSON, as produced by a script iterating over the
+/// program's functions and their basic blocks (e.g., via
+/// `BasicBlockModel`), of the form:
+///
+/// ```json
+/// { "functions": [
+///     { "entry": "00401000", "name": "main",
+///       "blocks": [
+///         { "start": "00401000", "end": "0040100f",
+///           "successors": ["00401010"] } ] } ] }
+/// ```
+///
+/// where each block's `end` is inclusive (i.e., its maximum address, as
+/// reported by Ghidra), or is replaced by its `size` in bytes. Addresses
+/// are hexadecimal strings (optionally qualified by their address space,
+/// e.g., `ram:00401000`, or prefixed by `0x`), or integers.
+///
+/// As Ghidra's XML export does not include basic blocks, an oracle built
+/// from it does not know the extents of Blks.
+#[derive(Debug, Clone, Default)]
+pub struct GhidraOracle {
+    bits: u32,
+    subs: BTreeMap<Addr, Function>,
+    blks: BTreeMap<Addr, Block>,
+}
+
+impl GhidraOracle {
+    fn new(bits: u32) -> Self {
+        Self {
+            bits,
+            ..Default::default()
+        }
+    }
+
+    /// Loads an export from `path`, in XML if it begins with `<`, and JSON
+    /// otherwise; addresses are `bits` wide.
+    pub fn from_file(path: impl AsRef<Path>, bits: u32) -> Result<Self, GhidraOracleError> {
+        let text = fs::read_to_string(path)?;
+        if text.trim_start().starts_with('<') {
+            Self::from_xml(&text, bits)
+        } else {
+            Self::from_json(&text, bits)
+        }
+    }
+
+    pub fn from_json(text: &str, bits: u32) -> Result<Self, GhidraOracleError> {
+        let value = text.parse::<Value>()?;
+        let functions = value
+            .get("functions")
+            .unwrap_or(&value)
+            .as_array()
+            .ok_or(GhidraOracleError::Missing("functions"))?;
+
+        let mut oracle = Self::new(bits);
+
+        for function in functions.iter() {
+            let entry = oracle.json_address(function.get("entry"), "entry")?;
+            let symbol = function
+                .get("name")
+                .and_then(|name| name.as_str())
+                .map(String::from);
+
+            let mut blks = BTreeSet::new();
+
+            for block in function
+                .get("blocks")
+                .and_then(|blocks| blocks.as_array())
+                .into_iter()
+                .flatten()
+            {
+                let start = oracle.json_address(block.get("start"), "start")?;
+                let size = if let Some(size) = block.get("size") {
+                    size.as_u64().ok_or(GhidraOracleError::Missing("size"))? as usize
+                } else {
+                    let end = oracle.json_address(block.get("end"), "end")?;
+                    end.absolute_difference(&start)
+                        .filter(|_| end >= start)
+                        .ok_or_else(|| GhidraOracleError::Address(end.to_string()))?
+                        + 1
+                };
+
+                let jmps = block
+                    .get("successors")
+                    .and_then(|successors| successors.as_array())
+                    .into_iter()
+                    .flatten()
+                    .map(|successor| oracle.json_address(Some(successor), "successors"))
+                    .collect::<Result<BTreeSet<_>, _>>()?;
+
+                blks.insert(start.clone());
+                oracle.blks.insert(start, Block { size, jmps });
+            }
+
+            let sub = oracle.subs.entry(entry).or_default();
+            sub.symbol = sub.symbol.take().or(symbol);
+            sub.blks.extend(blks);
+        }
+
+        Ok(oracle)
+    }
+
+    pub fn from_xml(text: &str, bits: u32) -> Result<Self, GhidraOracleError> {
+        let mut oracle = Self::new(bits);
+
+        for tag in XmlTags::new(text) {
+            let tag = tag?;
+            if tag.closing || tag.name != "FUNCTION" {
+                continue
+            }
+
+            let entry = tag
+                .attribute("ENTRY_POINT")
+                .ok_or(GhidraOracleError::Missing("ENTRY_POINT"))?;
+            let entry = oracle.address(entry)?;
+            let symbol = tag.attribute("NAME").map(String::from);
+
+            let sub = oracle.subs.entry(entry).or_default();
+            sub.symbol = sub.symbol.take().or(symbol);
+        }
+
+        Ok(oracle)
+    }
+
+    fn address(&self, text: &str) -> Result<Addr, GhidraOracleError> {
+        // strip the address space, e.g., ram:00401000
+        let digits = text.rsplit(':').next().unwrap_or(text).trim();
+        let digits = digits
+            .strip_prefix("0x")
+            .or_else(|| digits.strip_prefix("0X"))
+            .unwrap_or(digits);
+
+        u64::from_str_radix(digits, 16)
+            .map(|addr| Addr::from(addr).as_bits(self.bits))
+            .map_err(|_| GhidraOracleError::Address(text.to_owned()))
+    }
+
+    fn json_address(
+        &self,
+        value: Option<&Value>,
+        field: &'static str,
+    ) -> Result<Addr, GhidraOracleError> {
+        match value {
+            Some(Value::String(text)) => self.address(text),
+            Some(value) => value
+                .as_u64()
+                .map(|addr| Addr::from(addr).as_bits(self.bits))
+                .ok_or_else(|| GhidraOracleError::Address(value.to_string())),
+            None => Err(GhidraOracleError::Missing(field)),
+        }
+    }
+
+    /// The number of functions in the export.
+    pub fn len(&self) -> usize {
+        self.subs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subs.is_empty()
+    }
+}
+
+impl BlkOracle for GhidraOracle {
+    fn blk_size(&self, addr: &Addr) -> Option<usize> {
+        self.blks.get(addr).map(|blk| blk.size)
+    }
+
+    fn blk_jmps(&self, addr: &Addr) -> BTreeSet<Addr> {
+        self.blks
+            .get(addr)
+            .map(|blk| blk.jmps.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl SubOracle for GhidraOracle {
+    fn sub_starts(&self) -> BTreeSet<Addr> {
+        self.subs.keys().cloned().collect()
+    }
+
+    fn sub_symbol(&self, addr: &Addr) -> Option<String> {
+        self.subs.get(addr).and_then(|sub| sub.symbol.clone())
+    }
+
+    fn sub_blocks(&self, addr: &Addr) -> BTreeSet<Addr> {
+        self.subs
+            .get(addr)
+            .map(|sub| sub.blks.clone())
+            .unwrap_or_default()
+    }
+
+    fn sub_confidence(&self, addr: &Addr) -> Option<Confidence> {
+        self.subs.get(addr).map(|_| Confidence::Likely)
+    }
+}
+
+// a start or end tag of an XML document
+struct XmlTag<'a> {
+    name: &'a str,
+    closing: bool,
+    attributes: Vec<(&'a str, String)>,
+}
+
+impl<'a> XmlTag<'a> {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+// a minimal reader for the tags of an XML document; this is sufficient
+// for Ghidra's exports, which do not contain CDATA sections or DTDs with
+// internal subsets
+struct XmlTags<'a> {
+    text: &'a str,
+}
+
+impl<'a> XmlTags<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text }
+    }
+
+    fn unescape(value: &str) -> String {
+        value
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&")
+    }
+
+    fn parse(tag: &'a str) -> Result<XmlTag<'a>, GhidraOracleError> {
+        let (closing, tag) = match tag.strip_prefix('/') {
+            Some(tag) => (true, tag),
+            None => (false, tag),
+        };
+        let tag = tag.strip_suffix('/').unwrap_or(tag);
+
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        let name = &tag[..name_end];
+        let mut rest = &tag[name_end..];
+        let mut attributes = Vec::new();
+
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                break
+            }
+
+            let eq = rest
+                .find('=')
+                .ok_or_else(|| GhidraOracleError::Xml(format!("attribute without value in <{}>", name)))?;
+            let key = rest[..eq].trim();
+            let value = rest[eq + 1..].trim_start();
+
+            let quote = value
+                .chars()
+                .next()
+                .filter(|c| *c == '"' || *c == '\'')
+                .ok_or_else(|| GhidraOracleError::Xml(format!("unquoted attribute {} in <{}>", key, name)))?;
+            let end = value[1..]
+                .find(quote)
+                .ok_or_else(|| GhidraOracleError::Xml(format!("unterminated attribute {} in <{}>", key, name)))?;
+
+            attributes.push((key, Self::unescape(&value[1..end + 1])));
+            rest = &value[end + 2..];
+        }
+
+        Ok(XmlTag {
+            name,
+            closing,
+            attributes,
+        })
+    }
+}
+
+impl<'a> Iterator for XmlTags<'a> {
+    type Item = Result<XmlTag<'a>, GhidraOracleError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = self.text.find('<')?;
+            let text = &self.text[start + 1..];
+
+            // skip comments, declarations, and processing instructions
+            let (skip, terminator) = if text.starts_with("!--") {
+                (true, "-->")
+            } else if text.starts_with('?') {
+                (true, "?>")
+            } else if text.starts_with('!') {
+                (true, ">")
+            } else {
+                (false, ">")
+            };
+
+            let end = match text.find(terminator) {
+                Some(end) => end,
+                None => {
+                    self.text = "";
+                    return Some(Err(GhidraOracleError::Xml("unterminated tag".to_owned())))
+                }
+            };
+
+            self.text = &text[end + terminator.len()..];
+
+            if !skip {
+                return Some(Self::parse(text[..end].trim()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_xml() -> Result<(), GhidraOracleError> {
+        let xml = r#"<?xml version="1.0" standalone="yes"?>
+            <!-- exported by Ghidra -->
+            <PROGRAM NAME="test" EXE_FORMAT="Executable and Linking Format (ELF)">
+              <FUNCTIONS>
+                <FUNCTION ENTRY_POINT="00401000" NAME="main" LIBRARY_FUNCTION="n">
+                  <ADDRESS_RANGE START="00401000" END="0040101f" />
+                </FUNCTION>
+                <FUNCTION ENTRY_POINT="ram:00401020" NAME="a&amp;b" />
+              </FUNCTIONS>
+            </PROGRAM>"#;
+
+        let oracle = GhidraOracle::from_xml(xml, 32)?;
+
+        assert_eq!(oracle.len(), 2);
+        assert_eq!(oracle.sub_symbol(&Addr::from(0x401000u32)).as_deref(), Some("main"));
+        assert_eq!(oracle.sub_symbol(&Addr::from(0x401020u32)).as_deref(), Some("a&b"));
+        assert_eq!(oracle.blk_size(&Addr::from(0x401000u32)), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_json() -> Result<(), GhidraOracleError> {
+        let json = r#"{ "functions": [
+            { "entry": "0x401000", "name": "main",
+              "blocks": [
+                { "start": "00401000", "end": "0040100f", "successors": ["00401010"] },
+                { "start": 4198416, "size": 4 } ] } ] }"#;
+
+        let oracle = GhidraOracle::from_json(json, 32)?;
+        let entry = Addr::from(0x401000u32);
+
+        assert_eq!(oracle.sub_starts().into_iter().collect::<Vec<_>>(), vec![entry.clone()]);
+        assert_eq!(oracle.sub_blocks(&entry).len(), 2);
+        assert_eq!(oracle.blk_size(&entry), Some(0x10));
+        assert_eq!(oracle.blk_size(&Addr::from(0x401010u32)), Some(4));
+        assert!(oracle.blk_jmps(&entry).contains(&Addr::from(0x401010u32)));
+
+        Ok(())
+    }
+}
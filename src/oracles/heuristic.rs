@@ -0,0 +1,304 @@
+use std::collections::BTreeSet;
+
+use crate::ir::{Addr, Confidence, Mem};
+use crate::lift::Lifter;
+use crate::oracles::SubOracle;
+use crate::prelude::Endian;
+
+// a byte pattern; bits of bytes that are not set in mask are ignored
+#[derive(Debug, Clone)]
+struct Pattern {
+    bytes: Vec<u8>,
+    mask: Vec<u8>,
+}
+
+impl Pattern {
+    fn bytes(bytes: &[u8]) -> Self {
+        Self {
+            bytes: bytes.to_vec(),
+            mask: vec![0xff; bytes.len()],
+        }
+    }
+
+    fn word(value: u32, mask: u32, endian: Endian) -> Self {
+        if endian.is_little() {
+            Self {
+                bytes: value.to_le_bytes().to_vec(),
+                mask: mask.to_le_bytes().to_vec(),
+            }
+        } else {
+            Self {
+                bytes: value.to_be_bytes().to_vec(),
+                mask: mask.to_be_bytes().to_vec(),
+            }
+        }
+    }
+
+    fn half(value: u16, endian: Endian) -> Self {
+        Self::bytes(&if endian.is_little() {
+            value.to_le_bytes()
+        } else {
+            value.to_be_bytes()
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    fn matches(&self, bytes: &[u8]) -> bool {
+        bytes.len() >= self.bytes.len()
+            && self
+                .bytes
+                .iter()
+                .zip(self.mask.iter())
+                .zip(bytes.iter())
+                .all(|((b, m), v)| b & m == v & m)
+    }
+}
+
+// the patterns used to recognise function boundaries for a language
+#[derive(Debug, Clone, Default)]
+struct Patterns {
+    // the granularity at which we scan for patterns
+    step: usize,
+    // the alignment of functions following padding
+    alignment: u64,
+    prologues: Vec<Pattern>,
+    returns: Vec<Pattern>,
+    padding: Vec<Pattern>,
+}
+
+impl Patterns {
+    fn new(lifter: &Lifter) -> Self {
+        let arch = lifter.architecture();
+        let compressed = lifter.instruction_alignment() == Some(2);
+        Self::for_language(arch.processor(), arch.bits() as u32, arch.endian(), compressed)
+    }
+
+    // compressed is for languages with optional 16-bit instructions
+    fn for_language(processor: &str, bits: u32, endian: Endian, compressed: bool) -> Self {
+        let word = |value, mask| Pattern::word(value, mask, endian);
+
+        match processor.to_ascii_uppercase().as_str() {
+            "X86" if bits == 64 => Self {
+                step: 1,
+                alignment: 16,
+                prologues: vec![
+                    // endbr64
+                    Pattern::bytes(&[0xf3, 0x0f, 0x1e, 0xfa]),
+                    // push rbp; mov rbp, rsp
+                    Pattern::bytes(&[0x55, 0x48, 0x89, 0xe5]),
+                    Pattern::bytes(&[0x55, 0x48, 0x8b, 0xec]),
+                ],
+                returns: vec![Pattern::bytes(&[0xc3])],
+                padding: vec![Pattern::bytes(&[0xcc]), Pattern::bytes(&[0x90])],
+            },
+            "X86" => Self {
+                step: 1,
+                alignment: 16,
+                prologues: vec![
+                    // endbr32
+                    Pattern::bytes(&[0xf3, 0x0f, 0x1e, 0xfb]),
+                    // push ebp; mov ebp, esp
+                    Pattern::bytes(&[0x55, 0x89, 0xe5]),
+                    Pattern::bytes(&[0x55, 0x8b, 0xec]),
+                ],
+                returns: vec![Pattern::bytes(&[0xc3])],
+                padding: vec![Pattern::bytes(&[0xcc]), Pattern::bytes(&[0x90])],
+            },
+            "AARCH64" => Self {
+                step: 4,
+                alignment: 4,
+                prologues: vec![
+                    // stp x29, x30, [sp, #-n]!
+                    word(0xa9807bfd, 0xffc07fff),
+                    // paciasp
+                    word(0xd503233f, 0xffffffff),
+                    // bti c, bti jc
+                    word(0xd503245f, 0xffffff7f),
+                ],
+                returns: vec![word(0xd65f03c0, 0xffffffff)],
+                padding: vec![word(0x00000000, 0xffffffff), word(0xd503201f, 0xffffffff)],
+            },
+            "ARM" => Self {
+                step: 4,
+                alignment: 4,
+                prologues: vec![
+                    // push {..., lr}
+                    word(0xe92d4000, 0xffff4000),
+                ],
+                returns: vec![
+                    // bx lr
+                    word(0xe12fff1e, 0xffffffff),
+                    // pop {..., pc}
+                    word(0xe8bd8000, 0xffff8000),
+                ],
+                padding: vec![word(0x00000000, 0xffffffff), word(0xe320f000, 0xffffffff)],
+            },
+            "RISCV" => {
+                let mut patterns = Self {
+                    step: if compressed { 2 } else { 4 },
+                    alignment: if compressed { 2 } else { 4 },
+                    prologues: vec![
+                        // addi sp, sp, -n
+                        word(0x80010113, 0x800fffff),
+                    ],
+                    returns: vec![word(0x00008067, 0xffffffff)],
+                    padding: vec![word(0x00000013, 0xffffffff), word(0x00000000, 0xffffffff)],
+                };
+                if compressed {
+                    // c.ret, c.nop
+                    patterns.returns.push(Pattern::half(0x8082, endian));
+                    patterns.padding.push(Pattern::half(0x0001, endian));
+                }
+                patterns
+            }
+            "MIPS" => Self {
+                step: 4,
+                alignment: 4,
+                prologues: vec![
+                    // addiu sp, sp, -n
+                    word(0x27bd8000, 0xffff8000),
+                ],
+                returns: vec![
+                    // jr ra
+                    word(0x03e00008, 0xffffffff),
+                ],
+                padding: vec![word(0x00000000, 0xffffffff)],
+            },
+            _ => Self {
+                step: 1,
+                alignment: 1,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn matching<'a>(patterns: &'a [Pattern], bytes: &[u8]) -> Option<&'a Pattern> {
+        patterns.iter().find(|pattern| pattern.matches(bytes))
+    }
+
+    // the prologues and returns matched at the positions of bytes scanned,
+    // per position scanned
+    fn density(&self, bytes: &[u8]) -> f64 {
+        if self.prologues.is_empty() || bytes.is_empty() {
+            return 0.0
+        }
+
+        let positions = (0..bytes.len()).step_by(self.step);
+        let count = positions.len();
+        let matches = positions
+            .filter(|offset| {
+                let rest = &bytes[*offset..];
+                Self::matching(&self.prologues, rest).is_some() || Self::matching(&self.returns, rest).is_some()
+            })
+            .count();
+
+        matches as f64 / count as f64
+    }
+
+    fn padding_len(&self, bytes: &[u8]) -> usize {
+        let mut offset = 0;
+        while let Some(pattern) = Self::matching(&self.padding, &bytes[offset..]) {
+            offset += pattern.len();
+        }
+        offset
+    }
+}
+
+/// A SubOracle for binaries without symbols, or for which no external
+/// tool's output is available.
+///
+/// We consider an address to start a Sub if the bytes at it match a common
+/// prologue for the language (e.g., `push rbp; mov rbp, rsp` or `endbr64`
+/// for x86-64), or if it is the first suitably aligned address following a
+/// return and padding (e.g., `int3` or `nop` instructions). Additional
+/// starts (e.g., the targets of calls) may be added as they are found;
+/// `Project::discover_subs` harvests the targets of calls within the Subs
+/// it discovers.
+///
+/// The oracle does not know the symbols or Blks of the Subs it finds.
+#[derive(Debug, Clone, Default)]
+pub struct HeuristicSubOracle {
+    starts: BTreeSet<Addr>,
+}
+
+impl HeuristicSubOracle {
+    /// Scans the regions of `memory` for the starts of Subs in the language
+    /// of `lifter`.
+    pub fn new(lifter: &Lifter, memory: &Mem) -> Self {
+        let patterns = Patterns::new(lifter);
+        let mut starts = BTreeSet::new();
+
+        for entry in memory.regions().iter() {
+            let region = entry.value();
+            let bytes = region.bytes();
+            let base = region.address();
+
+            let mut offset = 0;
+            while offset < bytes.len() {
+                let rest = &bytes[offset..];
+                let addr = base + offset;
+
+                if Patterns::matching(&patterns.prologues, rest).is_some() {
+                    starts.insert(addr);
+                } else if let Some(ret) = Patterns::matching(&patterns.returns, rest) {
+                    let padding = patterns.padding_len(&rest[ret.len()..]);
+                    let next = offset + ret.len() + padding;
+                    let aligned = u64::try_from(base + next)
+                        .map(|addr| addr % patterns.alignment == 0)
+                        .unwrap_or(false);
+
+                    if padding > 0 && aligned && next < bytes.len() {
+                        starts.insert(base + next);
+                    }
+                }
+
+                offset += patterns.step;
+            }
+        }
+
+        Self { starts }
+    }
+
+    /// A predicate on bytes that holds if they begin with a common
+    /// prologue for the language of `lifter`.
+    pub fn prologue_matcher(lifter: &Lifter) -> impl Fn(&[u8]) -> bool {
+        let prologues = Patterns::new(lifter).prologues;
+        move |bytes| Patterns::matching(&prologues, bytes).is_some()
+    }
+
+    /// The density of the common prologues and returns of the language
+    /// within `bytes`, i.e., their number per instruction position; `0.0`
+    /// if the language's patterns are not known.
+    pub fn pattern_density(processor: &str, bits: u32, endian: Endian, compressed: bool, bytes: &[u8]) -> f64 {
+        Patterns::for_language(processor, bits, endian, compressed).density(bytes)
+    }
+
+    pub fn add_start(&mut self, addr: impl Into<Addr>) {
+        self.starts.insert(addr.into());
+    }
+
+    pub fn extend_starts(&mut self, addrs: impl IntoIterator<Item = Addr>) {
+        self.starts.extend(addrs);
+    }
+}
+
+impl SubOracle for HeuristicSubOracle {
+    fn sub_starts(&self) -> BTreeSet<Addr> {
+        self.starts.clone()
+    }
+
+    fn sub_symbol(&self, _addr: &Addr) -> Option<String> {
+        None
+    }
+
+    fn sub_blocks(&self, _addr: &Addr) -> BTreeSet<Addr> {
+        BTreeSet::new()
+    }
+
+    fn sub_confidence(&self, addr: &Addr) -> Option<Confidence> {
+        self.starts.get(addr).map(|_| Confidence::Heuristic)
+    }
+}
@@ -1,13 +1,100 @@
-use crate::ir::Addr;
+use crate::ir::{Addr, Confidence};
+use crate::lift::IsaMode;
 use std::collections::BTreeSet;
 
+pub mod chain;
+pub use chain::Chain;
+
+pub mod ghidra;
+pub use ghidra::GhidraOracle;
+
+pub mod heuristic;
+pub use heuristic::HeuristicSubOracle;
+
+pub mod trace;
+pub use trace::{Observed, Trace, TraceError};
+
+pub mod unwind;
+pub use unwind::{Unwind, UnwindError, UnwindOracle};
+
 pub trait BlkOracle {
     fn blk_size(&self, addr: &Addr) -> Option<usize>;
     fn blk_jmps(&self, addr: &Addr) -> BTreeSet<Addr>;
+
+    /// The instruction set of the Blk at `addr`, for languages with more
+    /// than one (e.g., ARM and Thumb), if known to the oracle.
+    #[allow(unused)]
+    fn blk_isa_mode(&self, addr: &Addr) -> Option<IsaMode> {
+        None
+    }
 }
 
 pub trait SubOracle {
     fn sub_starts(&self) -> BTreeSet<Addr>;
     fn sub_symbol(&self, addr: &Addr) -> Option<String>;
     fn sub_blocks(&self, addr: &Addr) -> BTreeSet<Addr>;
+
+    /// How much the oracle's claim that a Sub starts at `addr` can be
+    /// trusted, if it makes such a claim.
+    #[allow(unused)]
+    fn sub_confidence(&self, addr: &Addr) -> Option<Confidence> {
+        None
+    }
+
+    /// Returns true if the Sub starting at `addr` is known never to return
+    /// (e.g., as flagged by a loader's knowledge of the function), so that
+    /// the fall-throughs of calls to it are not lifted.
+    #[allow(unused)]
+    fn sub_no_return(&self, addr: &Addr) -> bool {
+        false
+    }
+}
+
+// the symbols of common functions that never return
+const NO_RETURN_SYMBOLS: &[&str] = &[
+    "abort",
+    "exit",
+    "_exit",
+    "_Exit",
+    "quick_exit",
+    "pthread_exit",
+    "thrd_exit",
+    "longjmp",
+    "_longjmp",
+    "siglongjmp",
+    "__longjmp_chk",
+    "err",
+    "errx",
+    "verr",
+    "verrx",
+    "__assert_fail",
+    "__assert_perror_fail",
+    "__assert_rtn",
+    "_assert",
+    "__stack_chk_fail",
+    "__stack_chk_fail_local",
+    "__fortify_fail",
+    "__chk_fail",
+    "__cxa_throw",
+    "__cxa_rethrow",
+    "__cxa_bad_cast",
+    "__cxa_bad_typeid",
+    "__cxa_pure_virtual",
+    "_Unwind_Resume",
+    "_ZSt9terminatev",
+    "ExitProcess",
+    "ExitThread",
+    "FreeLibraryAndExitThread",
+    "RtlExitUserThread",
+    "_invalid_parameter_noinfo_noreturn",
+    "__fastfail",
+];
+
+/// Returns true if `symbol` names a common library function that never
+/// returns (e.g., `abort` or `__stack_chk_fail`), ignoring the decorations
+/// of imports (e.g., `exit@plt` or `__imp_ExitProcess`).
+pub fn is_no_return_symbol(symbol: &str) -> bool {
+    let symbol = symbol.split('@').next().unwrap_or(symbol);
+    let symbol = symbol.strip_prefix("__imp_").unwrap_or(symbol);
+    NO_RETURN_SYMBOLS.contains(&symbol)
 }
\ No newline at end of file
@@ -0,0 +1,335 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::ir::{Addr, BitVec, Confidence};
+use crate::oracles::{BlkOracle, SubOracle};
+
+#[derive(Debug, Error)]
+pub enum TraceError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("line {0}: {1}")]
+    Json(usize, #[source] serde_json::Error),
+    #[error("line {0}: malformed event {1}")]
+    Event(usize, String),
+    #[error("line {0}: invalid address `{1}`")]
+    Address(usize, String),
+}
+
+/// Dynamic evidence about a Blk or Sub, recorded as an annotation on the
+/// entities of a Project (see `Project::import_trace`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Observed {
+    count: usize,
+    targets: BTreeSet<Addr>,
+    arguments: Vec<Vec<BitVec>>,
+}
+
+impl Observed {
+    /// The number of times the Blk was executed, or the Sub was called.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The targets of the flows observed leaving a Blk.
+    pub fn targets(&self) -> &BTreeSet<Addr> {
+        &self.targets
+    }
+
+    /// The argument values observed for each call of a Sub.
+    pub fn arguments(&self) -> &[Vec<BitVec>] {
+        &self.arguments
+    }
+}
+
+/// An execution trace, recording the blocks executed, the control flows
+/// between them, and the calls made (with their argument values, if
+/// known).
+///
+/// Traces are read as JSON lines, where each line is either an event of
+/// the form:
+///
+/// ```json
+/// { "type": "block", "start": "0x401000", "size": 16 }
+/// { "type": "edge", "from": "0x40100e", "to": "0x401200" }
+/// { "type": "call", "site": "0x401010", "target": "0x402000", "args": ["0x1", 7] }
+/// ```
+///
+/// or an event (or array of events) produced by Frida's Stalker, parsed
+/// with `Stalker.parse(events, { annotate: true, stringify: true })`, e.g.,
+/// `["block", "0x401000", "0x401010"]` or `["call", "0x401010",
+/// "0x402000", 0]`. Consecutive blocks are taken to be connected by a flow
+/// from the first to the second. The `size` of a block and the `args` of a
+/// call are optional; addresses are hexadecimal strings prefixed by `0x`,
+/// or integers.
+///
+/// As a BlkOracle, a trace gives the sizes of the blocks and the targets of
+/// the flows it observed; as a SubOracle, it gives the targets of the calls
+/// it observed as Sub starts.
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    bits: u32,
+    blocks: BTreeMap<Addr, (Option<usize>, usize)>,
+    edges: BTreeMap<Addr, BTreeSet<Addr>>,
+    calls: BTreeMap<Addr, BTreeSet<Addr>>,
+    arguments: BTreeMap<Addr, Vec<Vec<BitVec>>>,
+    // the last block executed, for inferring edges between blocks
+    last: Option<Addr>,
+}
+
+impl Trace {
+    /// An empty trace whose addresses are `bits` wide.
+    pub fn new(bits: u32) -> Self {
+        Self {
+            bits,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>, bits: u32) -> Result<Self, TraceError> {
+        Self::from_jsonl(&fs::read_to_string(path)?, bits)
+    }
+
+    pub fn from_jsonl(text: &str, bits: u32) -> Result<Self, TraceError> {
+        let mut trace = Self::new(bits);
+        for (i, line) in text.lines().enumerate() {
+            trace.add_line(i + 1, line)?;
+        }
+        Ok(trace)
+    }
+
+    fn add_line(&mut self, line: usize, text: &str) -> Result<(), TraceError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(())
+        }
+
+        let value = text.parse::<Value>().map_err(|e| TraceError::Json(line, e))?;
+        match value {
+            Value::Array(ref events) if events.iter().all(|event| event.is_array()) => {
+                for event in events.iter() {
+                    self.add_event(line, event)?;
+                }
+                Ok(())
+            }
+            ref event => self.add_event(line, event),
+        }
+    }
+
+    fn add_event(&mut self, line: usize, event: &Value) -> Result<(), TraceError> {
+        let malformed = || TraceError::Event(line, event.to_string());
+
+        if let Some(fields) = event.as_array() {
+            // a Stalker event: [kind, location, target, depth]
+            let kind = fields.first().and_then(|kind| kind.as_str()).ok_or_else(malformed)?;
+            let field = |i: usize| fields.get(i).ok_or_else(malformed);
+            match kind {
+                "block" | "compile" => {
+                    let start = self.address(line, field(1)?)?;
+                    let end = self.address(line, field(2)?)?;
+                    let size = end.absolute_difference(&start).filter(|_| end > start);
+                    if kind == "block" {
+                        self.add_block(start, size);
+                    } else {
+                        self.blocks.entry(start).or_insert((size, 0));
+                    }
+                }
+                "call" => {
+                    let site = self.address(line, field(1)?)?;
+                    let target = self.address(line, field(2)?)?;
+                    self.add_call(site, target, None);
+                }
+                "ret" | "exec" => (),
+                _ => return Err(malformed()),
+            }
+            return Ok(())
+        }
+
+        let kind = event.get("type").and_then(|kind| kind.as_str()).ok_or_else(malformed)?;
+        let field = |name: &str| event.get(name).ok_or_else(malformed);
+        match kind {
+            "block" => {
+                let start = self.address(line, field("start")?)?;
+                let size = event.get("size").and_then(|size| size.as_u64()).map(|size| size as usize);
+                self.add_block(start, size);
+            }
+            "edge" => {
+                let from = self.address(line, field("from")?)?;
+                let to = self.address(line, field("to")?)?;
+                self.add_edge(from, to);
+            }
+            "call" => {
+                let site = self.address(line, field("site")?)?;
+                let target = self.address(line, field("target")?)?;
+                let arguments = event
+                    .get("args")
+                    .and_then(|args| args.as_array())
+                    .map(|args| {
+                        args.iter()
+                            .map(|arg| self.address(line, arg).map(|arg| BitVec::from(arg).unsigned()))
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .transpose()?;
+                self.add_call(site, target, arguments);
+            }
+            _ => return Err(malformed()),
+        }
+
+        Ok(())
+    }
+
+    fn address(&self, line: usize, value: &Value) -> Result<Addr, TraceError> {
+        let invalid = || TraceError::Address(line, value.to_string());
+        let value = match value {
+            Value::String(text) => {
+                let digits = text.trim();
+                let digits = digits
+                    .strip_prefix("0x")
+                    .or_else(|| digits.strip_prefix("0X"))
+                    .unwrap_or(digits);
+                u64::from_str_radix(digits, 16).map_err(|_| invalid())?
+            }
+            value => value.as_u64().ok_or_else(invalid)?,
+        };
+        Ok(Addr::from(value).as_bits(self.bits))
+    }
+
+    /// Records an execution of the block at `start`, following the block
+    /// last executed.
+    pub fn add_block(&mut self, start: Addr, size: Option<usize>) {
+        if let Some(last) = self.last.take() {
+            self.add_edge(last, start.clone());
+        }
+
+        let block = self.blocks.entry(start.clone()).or_insert((None, 0));
+        block.0 = block.0.or(size);
+        block.1 += 1;
+
+        self.last = Some(start);
+    }
+
+    /// Records a flow from `from` (the start of a block, or the address of
+    /// the instruction performing the flow) to `to`.
+    pub fn add_edge(&mut self, from: Addr, to: Addr) {
+        self.edges.entry(from).or_default().insert(to);
+    }
+
+    /// Records a call from `site` to `target`, with the given argument
+    /// values.
+    pub fn add_call(&mut self, site: Addr, target: Addr, arguments: Option<Vec<BitVec>>) {
+        self.calls.entry(site).or_default().insert(target.clone());
+        self.arguments.entry(target).or_default().extend(arguments);
+    }
+
+    /// The blocks executed, and the number of times each was executed.
+    pub fn blocks(&self) -> impl Iterator<Item = (&Addr, usize)> {
+        self.blocks.iter().map(|(addr, (_, count))| (addr, *count))
+    }
+
+    /// The targets of the flows observed from addresses in `from..to`.
+    pub fn edges_within(&self, from: &Addr, to: &Addr) -> BTreeSet<Addr> {
+        if from >= to {
+            return BTreeSet::new()
+        }
+        self.edges
+            .range(from.clone()..to.clone())
+            .flat_map(|(_, targets)| targets.iter().cloned())
+            .collect()
+    }
+
+    /// The targets of the calls observed from addresses in `from..to`.
+    pub fn calls_within(&self, from: &Addr, to: &Addr) -> BTreeSet<Addr> {
+        if from >= to {
+            return BTreeSet::new()
+        }
+        self.calls
+            .range(from.clone()..to.clone())
+            .flat_map(|(_, targets)| targets.iter().cloned())
+            .collect()
+    }
+
+    /// The number of calls to `target` observed, and the argument values
+    /// observed for those calls.
+    pub fn calls_to(&self, target: &Addr) -> (usize, &[Vec<BitVec>]) {
+        let count = self
+            .calls
+            .values()
+            .filter(|targets| targets.contains(target))
+            .count();
+        let arguments = self
+            .arguments
+            .get(target)
+            .map(|arguments| &arguments[..])
+            .unwrap_or(&[]);
+        (count.max(arguments.len()), arguments)
+    }
+
+    pub(crate) fn observed_blk(&self, start: &Addr, end: &Addr) -> Option<Observed> {
+        let count = self.blocks.get(start).map(|(_, count)| *count).unwrap_or(0);
+        let targets = self.edges_within(start, end);
+        if count == 0 && targets.is_empty() {
+            return None
+        }
+        Some(Observed {
+            count,
+            targets,
+            arguments: Vec::new(),
+        })
+    }
+
+    pub(crate) fn observed_sub(&self, start: &Addr) -> Option<Observed> {
+        let (count, arguments) = self.calls_to(start);
+        if count == 0 {
+            return None
+        }
+        Some(Observed {
+            count,
+            targets: BTreeSet::new(),
+            arguments: arguments.to_vec(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty() && self.edges.is_empty() && self.calls.is_empty()
+    }
+}
+
+impl BlkOracle for Trace {
+    fn blk_size(&self, addr: &Addr) -> Option<usize> {
+        self.blocks.get(addr).and_then(|(size, _)| *size)
+    }
+
+    fn blk_jmps(&self, addr: &Addr) -> BTreeSet<Addr> {
+        match self.blk_size(addr) {
+            Some(size) => self.edges_within(addr, &(addr + size)),
+            None => self.edges.get(addr).cloned().unwrap_or_default(),
+        }
+    }
+}
+
+impl SubOracle for Trace {
+    fn sub_starts(&self) -> BTreeSet<Addr> {
+        self.calls.values().flatten().cloned().collect()
+    }
+
+    fn sub_symbol(&self, _addr: &Addr) -> Option<String> {
+        None
+    }
+
+    fn sub_blocks(&self, _addr: &Addr) -> BTreeSet<Addr> {
+        BTreeSet::new()
+    }
+
+    // calls that were observed are certain
+    fn sub_confidence(&self, addr: &Addr) -> Option<Confidence> {
+        self.calls
+            .values()
+            .any(|targets| targets.contains(addr))
+            .then(|| Confidence::Certain)
+    }
+}
@@ -0,0 +1,413 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::ir::{Addr, Confidence};
+use crate::oracles::SubOracle;
+
+#[derive(Debug, Error)]
+pub enum UnwindError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("not a PE image: {0}")]
+    NotPe(&'static str),
+    #[error("unsupported machine {0:#06x}; expected x86-64")]
+    Machine(u16),
+    #[error("truncated {0}")]
+    Truncated(&'static str),
+    #[error("RVA {0:#x} is not within a section")]
+    Rva(u32),
+}
+
+// the general purpose registers, as numbered by unwind codes
+const REGISTERS: [&str; 16] = [
+    "RAX", "RCX", "RDX", "RBX", "RSP", "RBP", "RSI", "RDI", "R8", "R9", "R10", "R11", "R12",
+    "R13", "R14", "R15",
+];
+
+const UNW_FLAG_CHAININFO: u8 = 0x4;
+
+/// The unwind information describing a function (or a fragment of one) on
+/// Windows x64, as recorded in a RUNTIME_FUNCTION entry of an image's
+/// `.pdata` section and the UNWIND_INFO it refers to (in `.xdata`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unwind {
+    start: Addr,
+    end: Addr,
+    prologue_size: usize,
+    frame_register: Option<&'static str>,
+    frame_offset: usize,
+    stack_allocation: usize,
+    pushed: Vec<&'static str>,
+    saved: Vec<(&'static str, usize)>,
+    parent: Option<Addr>,
+}
+
+impl Unwind {
+    /// The first address of the function.
+    pub fn start(&self) -> &Addr {
+        &self.start
+    }
+
+    /// The address following the function's last byte.
+    pub fn end(&self) -> &Addr {
+        &self.end
+    }
+
+    /// The size of the function's prologue in bytes.
+    pub fn prologue_size(&self) -> usize {
+        self.prologue_size
+    }
+
+    /// The register established as the frame pointer by the prologue, if
+    /// any, and its offset from RSP when established.
+    pub fn frame_register(&self) -> Option<(&'static str, usize)> {
+        self.frame_register.map(|register| (register, self.frame_offset))
+    }
+
+    /// The number of bytes the prologue moves RSP by, including pushes of
+    /// non-volatile registers (but not the return address).
+    pub fn stack_allocation(&self) -> usize {
+        self.stack_allocation
+    }
+
+    /// The non-volatile registers pushed by the prologue, in order.
+    pub fn pushed(&self) -> &[&'static str] {
+        &self.pushed
+    }
+
+    /// The non-volatile registers saved (by `mov`) by the prologue, and the
+    /// offsets from RSP (after allocation) at which they are saved.
+    pub fn saved(&self) -> &[(&'static str, usize)] {
+        &self.saved
+    }
+
+    /// The start of the function this is a fragment of, if its unwind
+    /// information is chained to that of another function.
+    pub fn parent(&self) -> Option<&Addr> {
+        self.parent.as_ref()
+    }
+}
+
+/// A SubOracle backed by the exception directory of a Windows x64 (PE32+)
+/// image, which records the extent and prologue of every function that is
+/// not a leaf function, without the need for debug information.
+///
+/// Function fragments whose unwind information is chained to that of
+/// another function (e.g., code moved out of line by the compiler) are
+/// reported as blocks of the function they are chained to, rather than as
+/// Sub starts.
+#[derive(Debug, Clone, Default)]
+pub struct UnwindOracle {
+    functions: BTreeMap<Addr, Unwind>,
+}
+
+impl UnwindOracle {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, UnwindError> {
+        Self::from_pe(&fs::read(path)?)
+    }
+
+    /// Parses the RUNTIME_FUNCTION entries of the PE image in `bytes`;
+    /// addresses are relative to the image's preferred base.
+    pub fn from_pe(bytes: &[u8]) -> Result<Self, UnwindError> {
+        let image = Image::parse(bytes)?;
+        let mut oracle = Self::default();
+
+        let (directory, size) = match image.exception_directory {
+            Some(directory) => directory,
+            None => return Ok(oracle),
+        };
+
+        let entries = image.slice(directory, size as usize, "exception directory")?;
+
+        for entry in entries.chunks_exact(12) {
+            let start = u32_at(entry, 0).unwrap_or(0);
+            let end = u32_at(entry, 4).unwrap_or(0);
+            let info = u32_at(entry, 8).unwrap_or(0);
+
+            if start == 0 && end == 0 {
+                continue
+            }
+
+            let unwind = image.unwind(start, end, info)?;
+            oracle.functions.insert(unwind.start.clone(), unwind);
+        }
+
+        Ok(oracle)
+    }
+
+    /// The unwind information of the function (or fragment) starting at
+    /// `addr`.
+    pub fn unwind(&self, addr: &Addr) -> Option<&Unwind> {
+        self.functions.get(addr)
+    }
+
+    /// The unwind information of the function (or fragment) containing
+    /// `addr`.
+    pub fn unwind_containing(&self, addr: &Addr) -> Option<&Unwind> {
+        self.functions
+            .range(..=addr.clone())
+            .next_back()
+            .map(|(_, unwind)| unwind)
+            .filter(|unwind| *addr < unwind.end)
+    }
+
+    /// The unwind information of each function and fragment, ordered by
+    /// start address.
+    pub fn iter(&self) -> impl Iterator<Item = &Unwind> {
+        self.functions.values()
+    }
+
+    // the function a fragment belongs to, following chains of fragments
+    fn root<'a>(&'a self, mut unwind: &'a Unwind) -> &'a Unwind {
+        // bound the walk, in case of malformed (cyclic) chains
+        for _ in 0..self.functions.len() {
+            match unwind.parent.as_ref().and_then(|parent| self.functions.get(parent)) {
+                Some(parent) if parent.start != unwind.start => unwind = parent,
+                _ => break,
+            }
+        }
+        unwind
+    }
+
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+}
+
+impl SubOracle for UnwindOracle {
+    fn sub_starts(&self) -> BTreeSet<Addr> {
+        self.functions
+            .values()
+            .filter(|unwind| unwind.parent.is_none())
+            .map(|unwind| unwind.start.clone())
+            .collect()
+    }
+
+    fn sub_symbol(&self, _addr: &Addr) -> Option<String> {
+        None
+    }
+
+    // the starts of the fragments chained to the function at addr
+    fn sub_blocks(&self, addr: &Addr) -> BTreeSet<Addr> {
+        self.functions
+            .values()
+            .filter(|unwind| unwind.parent.is_some() && self.root(unwind).start == *addr)
+            .map(|unwind| unwind.start.clone())
+            .collect()
+    }
+
+    // the compiler emits unwind information for the functions it generates
+    fn sub_confidence(&self, addr: &Addr) -> Option<Confidence> {
+        self.functions
+            .get(addr)
+            .filter(|unwind| unwind.parent.is_none())
+            .map(|_| Confidence::Certain)
+    }
+}
+
+// the parts of a PE image we need to locate unwind information
+struct Image<'a> {
+    bytes: &'a [u8],
+    base: u64,
+    // (virtual address, virtual size, file offset, file size)
+    sections: Vec<(u32, u32, u32, u32)>,
+    exception_directory: Option<(u32, u32)>,
+}
+
+impl<'a> Image<'a> {
+    fn parse(bytes: &'a [u8]) -> Result<Self, UnwindError> {
+        if !bytes.starts_with(b"MZ") {
+            return Err(UnwindError::NotPe("missing DOS signature"))
+        }
+
+        let pe = u32_at(bytes, 0x3c).ok_or(UnwindError::Truncated("DOS header"))? as usize;
+        if bytes.get(pe..pe + 4) != Some(&b"PE\0\0"[..]) {
+            return Err(UnwindError::NotPe("missing PE signature"))
+        }
+
+        let machine = u16_at(bytes, pe + 4).ok_or(UnwindError::Truncated("COFF header"))?;
+        if machine != 0x8664 {
+            return Err(UnwindError::Machine(machine))
+        }
+
+        let sections = u16_at(bytes, pe + 6).ok_or(UnwindError::Truncated("COFF header"))?;
+        let optional_size = u16_at(bytes, pe + 20).ok_or(UnwindError::Truncated("COFF header"))?;
+
+        let optional = pe + 24;
+        if u16_at(bytes, optional) != Some(0x20b) {
+            return Err(UnwindError::NotPe("expected a PE32+ optional header"))
+        }
+
+        let base = u64_at(bytes, optional + 24).ok_or(UnwindError::Truncated("optional header"))?;
+        let directories = u32_at(bytes, optional + 108).ok_or(UnwindError::Truncated("optional header"))?;
+
+        // the exception directory is the fourth data directory
+        let exception_directory = if directories > 3 && optional_size as usize >= 112 + 4 * 8 {
+            let rva = u32_at(bytes, optional + 112 + 3 * 8).ok_or(UnwindError::Truncated("data directories"))?;
+            let size = u32_at(bytes, optional + 116 + 3 * 8).ok_or(UnwindError::Truncated("data directories"))?;
+            Some((rva, size)).filter(|(rva, size)| *rva != 0 && *size != 0)
+        } else {
+            None
+        };
+
+        let table = optional + optional_size as usize;
+        let sections = (0..sections as usize)
+            .map(|i| {
+                let header = table + i * 40;
+                Some((
+                    u32_at(bytes, header + 12)?,
+                    u32_at(bytes, header + 8)?,
+                    u32_at(bytes, header + 20)?,
+                    u32_at(bytes, header + 16)?,
+                ))
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or(UnwindError::Truncated("section table"))?;
+
+        Ok(Self {
+            bytes,
+            base,
+            sections,
+            exception_directory,
+        })
+    }
+
+    fn address(&self, rva: u32) -> Addr {
+        Addr::from(self.base.wrapping_add(rva as u64)).as_bits(64)
+    }
+
+    fn slice(&self, rva: u32, size: usize, what: &'static str) -> Result<&'a [u8], UnwindError> {
+        let (va, _, offset, _) = self
+            .sections
+            .iter()
+            .find(|(va, vsize, _, fsize)| rva >= *va && rva - va < (*vsize).max(*fsize))
+            .ok_or(UnwindError::Rva(rva))?;
+
+        let start = *offset as usize + (rva - va) as usize;
+        self.bytes
+            .get(start..start + size)
+            .ok_or(UnwindError::Truncated(what))
+    }
+
+    // parses the UNWIND_INFO at info for the function at start..end
+    fn unwind(&self, start: u32, end: u32, info: u32) -> Result<Unwind, UnwindError> {
+        let header = self.slice(info, 4, "unwind information")?;
+        let flags = header[0] >> 3;
+        let prologue_size = header[1] as usize;
+        let count = header[2] as usize;
+        let frame_register = header[3] & 0xf;
+        let frame_offset = (header[3] >> 4) as usize * 16;
+
+        let codes = self.slice(info.wrapping_add(4), count * 2, "unwind codes")?;
+        let slot = |i: usize| u16_at(codes, i * 2).map(|slot| slot as usize);
+
+        let mut stack_allocation = 0;
+        let mut pushed = Vec::new();
+        let mut saved = Vec::new();
+
+        let mut i = 0;
+        while i < count {
+            let op = codes[i * 2 + 1] & 0xf;
+            let op_info = (codes[i * 2 + 1] >> 4) as usize;
+
+            let slots = match op {
+                // UWOP_PUSH_NONVOL
+                0 => {
+                    pushed.push(REGISTERS[op_info]);
+                    stack_allocation += 8;
+                    1
+                }
+                // UWOP_ALLOC_LARGE
+                1 if op_info == 0 => {
+                    stack_allocation += slot(i + 1).ok_or(UnwindError::Truncated("unwind codes"))? * 8;
+                    2
+                }
+                1 => {
+                    let low = slot(i + 1).ok_or(UnwindError::Truncated("unwind codes"))?;
+                    let high = slot(i + 2).ok_or(UnwindError::Truncated("unwind codes"))?;
+                    stack_allocation += (high << 16) | low;
+                    3
+                }
+                // UWOP_ALLOC_SMALL
+                2 => {
+                    stack_allocation += op_info * 8 + 8;
+                    1
+                }
+                // UWOP_SAVE_NONVOL
+                4 => {
+                    let offset = slot(i + 1).ok_or(UnwindError::Truncated("unwind codes"))?;
+                    saved.push((REGISTERS[op_info], offset * 8));
+                    2
+                }
+                // UWOP_SAVE_NONVOL_FAR
+                5 => {
+                    let low = slot(i + 1).ok_or(UnwindError::Truncated("unwind codes"))?;
+                    let high = slot(i + 2).ok_or(UnwindError::Truncated("unwind codes"))?;
+                    saved.push((REGISTERS[op_info], (high << 16) | low));
+                    3
+                }
+                // UWOP_EPILOG (version 2) and UWOP_SAVE_XMM128
+                6 | 8 => 2,
+                // UWOP_SAVE_XMM128_FAR and a spare code
+                7 | 9 => 3,
+                // UWOP_PUSH_MACHFRAME
+                10 => {
+                    stack_allocation += if op_info == 0 { 0x28 } else { 0x30 };
+                    1
+                }
+                // UWOP_SET_FPREG and unknown codes
+                _ => 1,
+            };
+
+            i += slots;
+        }
+
+        // codes are padded to an even number of slots; a chained function
+        // entry follows them
+        let parent = if flags & UNW_FLAG_CHAININFO != 0 {
+            let chained = info.wrapping_add(4 + ((count + 1) & !1) as u32 * 2);
+            let entry = self.slice(chained, 12, "chained unwind information")?;
+            // unwrap is safe here: we know that entry has 12 bytes
+            Some(self.address(u32_at(entry, 0).unwrap()))
+        } else {
+            None
+        };
+
+        Ok(Unwind {
+            start: self.address(start),
+            end: self.address(end),
+            prologue_size,
+            frame_register: (frame_register != 0).then(|| REGISTERS[frame_register as usize]),
+            frame_offset,
+            stack_allocation,
+            pushed,
+            saved,
+            parent,
+        })
+    }
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Option<u16> {
+    let bytes = bytes.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> Option<u64> {
+    let bytes = bytes.get(offset..offset + 8)?;
+    let mut word = [0u8; 8];
+    word.copy_from_slice(bytes);
+    Some(u64::from_le_bytes(word))
+}
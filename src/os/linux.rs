@@ -0,0 +1,247 @@
+use crate::lift::Lifter;
+use crate::os::{Syscall, SyscallTable, SyscallTrigger};
+
+// (number, name, arity); the commonly used system calls of each table
+type Entries = &'static [(u64, &'static str, usize)];
+
+// the registers passing the number, arguments, and result of a system
+// call, those clobbered by the kernel, how code enters the kernel, and
+// the table's entries
+type Abi = (&'static str, &'static [&'static str], &'static str, &'static [&'static str], Vec<SyscallTrigger>, Entries);
+
+// the system calls that never return
+const NO_RETURN: &[&str] = &["exit", "exit_group"];
+
+const X86_64: Entries = &[
+    (0, "read", 3),
+    (1, "write", 3),
+    (2, "open", 3),
+    (3, "close", 1),
+    (4, "stat", 2),
+    (5, "fstat", 2),
+    (9, "mmap", 6),
+    (10, "mprotect", 3),
+    (11, "munmap", 2),
+    (12, "brk", 1),
+    (13, "rt_sigaction", 4),
+    (16, "ioctl", 3),
+    (21, "access", 2),
+    (22, "pipe", 1),
+    (32, "dup", 1),
+    (33, "dup2", 2),
+    (35, "nanosleep", 2),
+    (39, "getpid", 0),
+    (41, "socket", 3),
+    (42, "connect", 3),
+    (56, "clone", 5),
+    (57, "fork", 0),
+    (59, "execve", 3),
+    (60, "exit", 1),
+    (61, "wait4", 4),
+    (62, "kill", 2),
+    (63, "uname", 1),
+    (72, "fcntl", 3),
+    (79, "getcwd", 2),
+    (80, "chdir", 1),
+    (83, "mkdir", 2),
+    (87, "unlink", 1),
+    (89, "readlink", 3),
+    (96, "gettimeofday", 2),
+    (102, "getuid", 0),
+    (158, "arch_prctl", 2),
+    (186, "gettid", 0),
+    (201, "time", 1),
+    (202, "futex", 6),
+    (218, "set_tid_address", 1),
+    (228, "clock_gettime", 2),
+    (231, "exit_group", 1),
+    (257, "openat", 4),
+    (318, "getrandom", 3),
+];
+
+const X86: Entries = &[
+    (1, "exit", 1),
+    (2, "fork", 0),
+    (3, "read", 3),
+    (4, "write", 3),
+    (5, "open", 3),
+    (6, "close", 1),
+    (7, "waitpid", 3),
+    (11, "execve", 3),
+    (12, "chdir", 1),
+    (13, "time", 1),
+    (20, "getpid", 0),
+    (37, "kill", 2),
+    (45, "brk", 1),
+    (54, "ioctl", 3),
+    (90, "mmap", 1),
+    (91, "munmap", 2),
+    (120, "clone", 5),
+    (122, "uname", 1),
+    (125, "mprotect", 3),
+    (192, "mmap2", 6),
+    (224, "gettid", 0),
+    (240, "futex", 6),
+    (243, "set_thread_area", 1),
+    (252, "exit_group", 1),
+    (295, "openat", 4),
+    (355, "getrandom", 3),
+];
+
+const ARM: Entries = &[
+    (1, "exit", 1),
+    (2, "fork", 0),
+    (3, "read", 3),
+    (4, "write", 3),
+    (5, "open", 3),
+    (6, "close", 1),
+    (11, "execve", 3),
+    (12, "chdir", 1),
+    (20, "getpid", 0),
+    (37, "kill", 2),
+    (45, "brk", 1),
+    (54, "ioctl", 3),
+    (91, "munmap", 2),
+    (120, "clone", 5),
+    (122, "uname", 1),
+    (125, "mprotect", 3),
+    (192, "mmap2", 6),
+    (224, "gettid", 0),
+    (240, "futex", 6),
+    (248, "exit_group", 1),
+    (322, "openat", 4),
+    (384, "getrandom", 3),
+];
+
+// the generic table, used by AArch64 and RISC-V
+const GENERIC: Entries = &[
+    (17, "getcwd", 2),
+    (23, "dup", 1),
+    (29, "ioctl", 3),
+    (56, "openat", 4),
+    (57, "close", 1),
+    (63, "read", 3),
+    (64, "write", 3),
+    (78, "readlinkat", 4),
+    (93, "exit", 1),
+    (94, "exit_group", 1),
+    (96, "set_tid_address", 1),
+    (98, "futex", 6),
+    (113, "clock_gettime", 2),
+    (129, "kill", 2),
+    (160, "uname", 1),
+    (172, "getpid", 0),
+    (178, "gettid", 0),
+    (214, "brk", 1),
+    (215, "munmap", 2),
+    (220, "clone", 5),
+    (221, "execve", 3),
+    (222, "mmap", 6),
+    (226, "mprotect", 3),
+    (260, "wait4", 4),
+    (278, "getrandom", 3),
+];
+
+// the o32 ABI, whose system calls are numbered from 4000
+const MIPS: Entries = &[
+    (4001, "exit", 1),
+    (4002, "fork", 0),
+    (4003, "read", 3),
+    (4004, "write", 3),
+    (4005, "open", 3),
+    (4006, "close", 1),
+    (4011, "execve", 3),
+    (4020, "getpid", 0),
+    (4037, "kill", 2),
+    (4045, "brk", 1),
+    (4054, "ioctl", 3),
+    (4090, "mmap", 6),
+    (4091, "munmap", 2),
+    (4120, "clone", 5),
+    (4122, "uname", 1),
+    (4125, "mprotect", 3),
+    (4246, "exit_group", 1),
+    (4288, "openat", 4),
+];
+
+/// The Linux syscall table for the language of `lifter`, if known.
+pub fn syscall_table(lifter: &Lifter) -> Option<SyscallTable> {
+    let bits = lifter.architecture().bits();
+    let (number, arguments, result, clobbers, triggers, entries): Abi = match lifter.architecture().processor().to_ascii_uppercase().as_str() {
+        "X86" if bits == 64 => (
+            "RAX",
+            &["RDI", "RSI", "RDX", "R10", "R8", "R9"],
+            "RAX",
+            // syscall saves the return address and flags in RCX and R11
+            &["RCX", "R11"],
+            vec![SyscallTrigger::intrinsic("syscall")],
+            X86_64,
+        ),
+        "X86" => (
+            "EAX",
+            &["EBX", "ECX", "EDX", "ESI", "EDI", "EBP"],
+            "EAX",
+            &[],
+            vec![SyscallTrigger::intrinsic_with("swi", 0x80), SyscallTrigger::intrinsic("sysenter")],
+            X86,
+        ),
+        // the EABI, which passes the number in r7 (rather than via svc)
+        "ARM" => (
+            "r7",
+            &["r0", "r1", "r2", "r3", "r4", "r5", "r6"],
+            "r0",
+            &[],
+            vec![SyscallTrigger::intrinsic("software_interrupt")],
+            ARM,
+        ),
+        "AARCH64" => (
+            "x8",
+            &["x0", "x1", "x2", "x3", "x4", "x5"],
+            "x0",
+            &[],
+            vec![SyscallTrigger::intrinsic("CallSupervisor")],
+            GENERIC,
+        ),
+        "RISCV" => (
+            "a7",
+            &["a0", "a1", "a2", "a3", "a4", "a5"],
+            "a0",
+            &[],
+            vec![SyscallTrigger::intrinsic("ecall")],
+            GENERIC,
+        ),
+        // a3 is set if the system call fails; arguments after the fourth
+        // are passed on the stack
+        "MIPS" if bits == 32 => (
+            "v0",
+            &["a0", "a1", "a2", "a3"],
+            "v0",
+            &["a3"],
+            vec![SyscallTrigger::intrinsic("syscall")],
+            MIPS,
+        ),
+        _ => return None,
+    };
+
+    let mut table = SyscallTable::new(
+        lifter.register(number)?,
+        arguments.iter().map(|name| lifter.register(name)).collect::<Option<Vec<_>>>()?,
+        lifter.register(result)?,
+    );
+
+    for name in clobbers {
+        table.clobber(lifter.register(name)?);
+    }
+
+    for trigger in triggers {
+        table.trigger(trigger);
+    }
+
+    for (number, name, arity) in entries {
+        let mut syscall = Syscall::new(*number, *name, *arity);
+        syscall.no_return(NO_RETURN.contains(name));
+        table.add(syscall);
+    }
+
+    Some(table)
+}
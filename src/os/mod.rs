@@ -0,0 +1,22 @@
+/// Models of the operating systems programs run on.
+///
+/// - `syscall` maps the system calls made by lifted code (e.g., via the
+///   intrinsics of `syscall`, `svc`, or `int 0x80`) to the entries of
+///   per-OS syscall tables: their names, and the registers passing their
+///   numbers, arguments, and results. Resolved system calls are recorded as
+///   annotations of the IR (see `Project::resolve_syscalls`), and can be
+///   emulated via stubs (see `SyscallStubs`).
+///
+/// - `linux` provides the syscall tables of Linux for the architectures
+///   we lift.
+
+pub mod linux;
+
+pub mod syscall;
+pub use syscall::{ResolvedSyscall, Syscall, SyscallStub, SyscallStubs, SyscallTable, SyscallTrigger};
+
+/// The operating systems with syscall tables; see `SyscallTable::for_lifter`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Os {
+    Linux,
+}
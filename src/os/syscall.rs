@@ -0,0 +1,458 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::analysis::constants::{ConstantPropagation, Constants};
+use crate::analysis::dataflow::Dataflow;
+use crate::emu::{EmuError, Emulator, State};
+use crate::ir::{Addr, BitVec, Blk, Def, Expr, Intrinsic, IntrinsicRegistry, Jmp, Loc, SideEffects, Sub, Var};
+use crate::lift::Lifter;
+use crate::os::{linux, Os};
+use crate::prelude::{Erased, Id, Identifiable};
+
+/// An entry of a syscall table.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Syscall {
+    number: u64,
+    name: Arc<str>,
+    arity: usize,
+    no_return: bool,
+}
+
+impl Syscall {
+    pub fn new(number: u64, name: impl Into<Arc<str>>, arity: usize) -> Self {
+        Self {
+            number,
+            name: name.into(),
+            arity,
+            no_return: false,
+        }
+    }
+
+    /// The system call never returns (e.g., `exit`).
+    pub fn no_return(&mut self, no_return: bool) -> &mut Self {
+        self.no_return = no_return;
+        self
+    }
+
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    pub fn name(&self) -> &Arc<str> {
+        &self.name
+    }
+
+    /// The number of arguments the system call takes.
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    pub fn is_no_return(&self) -> bool {
+        self.no_return
+    }
+}
+
+/// How lifted code enters the kernel.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SyscallTrigger {
+    /// An intrinsic (as a jmp, or an expression assigned by a def) named
+    /// `name`, whose first argument is `operand`, if given (e.g., 0x80 for
+    /// `int 0x80` on x86).
+    Intrinsic { name: Arc<str>, operand: Option<u64> },
+    /// A call to a fixed address, e.g., of a vsyscall entry point.
+    Call(Addr),
+}
+
+impl SyscallTrigger {
+    pub fn intrinsic(name: impl Into<Arc<str>>) -> Self {
+        Self::Intrinsic { name: name.into(), operand: None }
+    }
+
+    pub fn intrinsic_with(name: impl Into<Arc<str>>, operand: u64) -> Self {
+        Self::Intrinsic { name: name.into(), operand: Some(operand) }
+    }
+
+    fn matches_intrinsic(&self, iname: &str, args: &[Expr], constants: Option<&Constants>) -> bool {
+        match self {
+            Self::Intrinsic { name, operand: None } => &**name == iname,
+            Self::Intrinsic { name, operand: Some(operand) } => {
+                &**name == iname
+                    && args
+                        .first()
+                        .and_then(|arg| match constants {
+                            Some(constants) => constants.evaluate(arg),
+                            None => arg.val().cloned(),
+                        })
+                        .and_then(|value| value.to_u64())
+                        .map(|value| value == *operand)
+                        .unwrap_or(false)
+            }
+            Self::Call(_) => false,
+        }
+    }
+
+    fn matches_jmp(&self, jmp: &Jmp, constants: Option<&Constants>) -> bool {
+        match (self, jmp) {
+            (Self::Call(addr), Jmp::Call(Loc::Fixed(target), _)) => addr == target,
+            (_, Jmp::Intrinsic(name, args)) => self.matches_intrinsic(name, args, constants),
+            _ => false,
+        }
+    }
+
+    fn matches_def(&self, def: &Def, constants: Option<&Constants>) -> bool {
+        match def {
+            Def::Assign(_, Expr::Intrinsic(name, args, _)) => {
+                let args = args.iter().map(|arg| (**arg).clone()).collect::<Vec<_>>();
+                self.matches_intrinsic(name, &args, constants)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A system call made by lifted code, as resolved by
+/// `SyscallTable::resolve`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedSyscall {
+    number: u64,
+    syscall: Option<Syscall>,
+    arguments: Vec<Var>,
+}
+
+impl ResolvedSyscall {
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    /// The entry of the table for the system call's number, if any.
+    pub fn syscall(&self) -> Option<&Syscall> {
+        self.syscall.as_ref()
+    }
+
+    pub fn name(&self) -> Option<&Arc<str>> {
+        self.syscall.as_ref().map(|syscall| syscall.name())
+    }
+
+    /// The registers passing the system call's arguments; all of the
+    /// table's argument registers if its arity is not known.
+    pub fn arguments(&self) -> &[Var] {
+        &self.arguments
+    }
+
+    pub fn is_no_return(&self) -> bool {
+        self.syscall.as_ref().map(|syscall| syscall.is_no_return()).unwrap_or(false)
+    }
+}
+
+/// The system calls of an OS on an architecture: the registers passing
+/// the number of a system call, its arguments (in order), and its result,
+/// the registers the kernel may modify, how lifted code enters the kernel
+/// (see `SyscallTrigger`), and the name and arity of each system call.
+#[derive(Debug, Clone)]
+pub struct SyscallTable {
+    number: Var,
+    arguments: Vec<Var>,
+    result: Var,
+    clobbers: Vec<Var>,
+    triggers: Vec<SyscallTrigger>,
+    syscalls: BTreeMap<u64, Syscall>,
+}
+
+impl SyscallTable {
+    pub fn new(number: Var, arguments: impl IntoIterator<Item = Var>, result: Var) -> Self {
+        Self {
+            number,
+            arguments: arguments.into_iter().collect(),
+            result,
+            clobbers: Vec::new(),
+            triggers: Vec::new(),
+            syscalls: BTreeMap::new(),
+        }
+    }
+
+    /// The table of `os` for the language of `lifter`, with the registers
+    /// it names; returns None if the table is not known, or the lifter's
+    /// language does not define its registers.
+    pub fn for_lifter(os: Os, lifter: &Lifter) -> Option<Self> {
+        match os {
+            Os::Linux => linux::syscall_table(lifter),
+        }
+    }
+
+    /// The kernel may modify `var` (in addition to the result register).
+    pub fn clobber(&mut self, var: Var) -> &mut Self {
+        self.clobbers.push(var);
+        self
+    }
+
+    pub fn trigger(&mut self, trigger: SyscallTrigger) -> &mut Self {
+        self.triggers.push(trigger);
+        self
+    }
+
+    /// Adds `syscall` to the table, replacing any entry with its number.
+    pub fn add(&mut self, syscall: Syscall) -> &mut Self {
+        self.syscalls.insert(syscall.number, syscall);
+        self
+    }
+
+    pub fn number_register(&self) -> &Var {
+        &self.number
+    }
+
+    pub fn argument_registers(&self) -> &[Var] {
+        &self.arguments
+    }
+
+    pub fn result_register(&self) -> &Var {
+        &self.result
+    }
+
+    pub fn clobbers(&self) -> &[Var] {
+        &self.clobbers
+    }
+
+    pub fn triggers(&self) -> &[SyscallTrigger] {
+        &self.triggers
+    }
+
+    pub fn get(&self, number: u64) -> Option<&Syscall> {
+        self.syscalls.get(&number)
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&Syscall> {
+        self.syscalls.values().find(|syscall| &*syscall.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Syscall> {
+        self.syscalls.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.syscalls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.syscalls.is_empty()
+    }
+
+    /// Returns true if `jmp` enters the kernel, assuming that the operands
+    /// of intrinsics are immediates.
+    pub fn is_syscall(&self, jmp: &Jmp) -> bool {
+        self.triggers.iter().any(|trigger| trigger.matches_jmp(jmp, None))
+    }
+
+    fn resolved(&self, number: Option<u64>) -> Option<ResolvedSyscall> {
+        let number = number?;
+        let syscall = self.get(number).cloned();
+        let arity = syscall
+            .as_ref()
+            .map(|syscall| syscall.arity.min(self.arguments.len()))
+            .unwrap_or(self.arguments.len());
+
+        Some(ResolvedSyscall {
+            number,
+            syscall,
+            arguments: self.arguments[..arity].to_vec(),
+        })
+    }
+
+    fn number_in(&self, constants: &Constants) -> Option<u64> {
+        constants.get(&self.number).and_then(|value| value.to_u64())
+    }
+
+    /// Resolves the system calls made by `sub` whose numbers are constant
+    /// (see `ConstantPropagation`), returning the statements (defs or
+    /// jmps) that make them.
+    pub fn resolve(&self, sub: &Sub) -> Vec<(Id<Erased>, ResolvedSyscall)> {
+        let analysis = ConstantPropagation::new();
+        let result = analysis.solve(sub);
+        let mut resolved = Vec::new();
+
+        for blk in sub.blks() {
+            let mut constants = match result.entry(blk) {
+                Some(constants) if constants.is_reachable() => constants.clone(),
+                _ => continue,
+            };
+            self.resolve_blk(&analysis, blk, &mut constants, &mut resolved);
+        }
+
+        resolved
+    }
+
+    fn resolve_blk(
+        &self,
+        analysis: &ConstantPropagation,
+        blk: &Blk,
+        constants: &mut Constants,
+        resolved: &mut Vec<(Id<Erased>, ResolvedSyscall)>,
+    ) {
+        analysis.transfer_start(blk, constants);
+        for phi in blk.phis() {
+            analysis.transfer_phi(phi, constants);
+        }
+
+        for def in blk.defs() {
+            if self.triggers.iter().any(|trigger| trigger.matches_def(def, Some(constants))) {
+                if let Some(syscall) = self.resolved(self.number_in(constants)) {
+                    resolved.push((def.id().erase(), syscall));
+                }
+            }
+            analysis.transfer_def(def, constants);
+        }
+
+        for jmp in blk.jmps() {
+            if self.triggers.iter().any(|trigger| trigger.matches_jmp(jmp, Some(constants))) {
+                if let Some(syscall) = self.resolved(self.number_in(constants)) {
+                    resolved.push((jmp.id().erase(), syscall));
+                }
+            }
+        }
+    }
+
+    /// Registers the intrinsics entering the kernel with `registry`, as
+    /// reading and writing memory, and modifying the result register and
+    /// the registers clobbered by the kernel.
+    pub fn register_intrinsics(&self, registry: &mut IntrinsicRegistry) {
+        let mut effects = SideEffects::pure();
+        effects.reads_memory(true).writes_memory(true).clobber(self.result.clone());
+        for var in self.clobbers.iter() {
+            effects.clobber(var.clone());
+        }
+
+        for trigger in self.triggers.iter() {
+            if let SyscallTrigger::Intrinsic { name, .. } = trigger {
+                registry.register(Intrinsic::new(name.clone(), effects.clone()));
+            }
+        }
+    }
+}
+
+/// Emulates a system call, given the values of its arguments; returns the
+/// value of its result, if any.
+pub type SyscallStub<'a, 'r> =
+    Arc<dyn Fn(&mut State<'a, 'r>, &[BitVec]) -> Result<Option<BitVec>, EmuError> + Send + Sync + 'a>;
+
+/// The stubs emulating the system calls of a syscall table, by name, e.g.:
+///
+/// ```ignore
+/// let mut stubs = SyscallStubs::new(table);
+/// stubs.stub("getpid", |_, _| Ok(Some(BitVec::from_u64(1337, 64))));
+/// stubs.install(&mut emulator);
+/// ```
+///
+/// Stubs are installed as hooks of the intrinsic jmps entering the kernel
+/// (see `Emulator::hook_intrinsic`); emulation stops with an error at
+/// system calls without stubs, and the stubs of intrinsic expressions and
+/// calls entering the kernel are not supported.
+#[derive(Clone)]
+pub struct SyscallStubs<'a, 'r> {
+    table: Arc<SyscallTable>,
+    stubs: BTreeMap<Arc<str>, SyscallStub<'a, 'r>>,
+}
+
+impl<'a, 'r> SyscallStubs<'a, 'r> {
+    pub fn new(table: impl Into<Arc<SyscallTable>>) -> Self {
+        Self {
+            table: table.into(),
+            stubs: BTreeMap::new(),
+        }
+    }
+
+    /// Emulates the system call named `name` using `stub`.
+    pub fn stub<F>(&mut self, name: impl Into<Arc<str>>, stub: F) -> &mut Self
+    where
+        F: Fn(&mut State<'a, 'r>, &[BitVec]) -> Result<Option<BitVec>, EmuError> + Send + Sync + 'a,
+    {
+        self.stubs.insert(name.into(), Arc::new(stub));
+        self
+    }
+
+    fn call(&self, state: &mut State<'a, 'r>, iname: &str, args: &[BitVec]) -> Result<(), EmuError> {
+        let table = &self.table;
+
+        let operand = table.triggers.iter().find_map(|trigger| match trigger {
+            SyscallTrigger::Intrinsic { name, operand } if &**name == iname => Some(*operand),
+            _ => None,
+        });
+
+        // the same intrinsic may have other uses, e.g., `int 3` on x86
+        if let Some(Some(operand)) = operand {
+            if args.first().and_then(|arg| arg.to_u64()) != Some(operand) {
+                return Err(EmuError::Intrinsic(iname.into()))
+            }
+        }
+
+        let number = state
+            .var(&table.number)
+            .ok_or_else(|| EmuError::Unassigned(table.number.clone()))?
+            .to_u64()
+            .ok_or_else(|| EmuError::Unassigned(table.number.clone()))?;
+
+        let resolved = table.resolved(Some(number)).ok_or(EmuError::Syscall(number))?;
+        let stub = resolved
+            .name()
+            .and_then(|name| self.stubs.get(name))
+            .cloned()
+            .ok_or(EmuError::Syscall(number))?;
+
+        let args = resolved
+            .arguments()
+            .iter()
+            .map(|var| state.var(var).cloned().ok_or_else(|| EmuError::Unassigned(var.clone())))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(result) = stub(state, &args)? {
+            state.set_var(table.result.clone(), result);
+        }
+        Ok(())
+    }
+
+    /// Hooks the intrinsic jmps of `emulator` that enter the kernel, so
+    /// that the system calls they make are emulated by the stubs.
+    pub fn install(self, emulator: &mut Emulator<'a, 'r>) {
+        let stubs = Arc::new(self);
+        for trigger in stubs.table.triggers.iter() {
+            if let SyscallTrigger::Intrinsic { name, .. } = trigger {
+                let (stubs, iname) = (stubs.clone(), name.clone());
+                emulator.hook_intrinsic(name.clone(), move |state, args| stubs.call(state, &iname, args));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::bv::BitVecT;
+
+    #[test]
+    fn test_table() {
+        let reg = |name: &str| -> Var { Var::physical(name, BitVecT::with_bits(64, false)).into() };
+
+        let mut table = SyscallTable::new(reg("RAX"), ["RDI", "RSI", "RDX"].iter().map(|name| reg(name)), reg("RAX"));
+        table
+            .clobber(reg("RCX"))
+            .trigger(SyscallTrigger::intrinsic("syscall"))
+            .add(Syscall::new(1, "write", 3))
+            .add(Syscall::new(39, "getpid", 0))
+            .add(Syscall::new(60, "exit", 1).no_return(true).clone());
+
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.by_name("getpid").map(|syscall| syscall.number()), Some(39));
+        assert!(table.get(60).unwrap().is_no_return());
+
+        let exit = table.resolved(Some(60)).unwrap();
+        assert_eq!(exit.arguments().len(), 1);
+        assert!(exit.is_no_return());
+        assert_eq!(table.resolved(Some(1000)).unwrap().arguments().len(), 3);
+
+        assert!(table.is_syscall(&Jmp::intrinsic("syscall", [])));
+        assert!(!table.is_syscall(&Jmp::intrinsic("cpuid", [])));
+
+        let mut registry = IntrinsicRegistry::new();
+        table.register_intrinsics(&mut registry);
+        assert!(registry.effects("syscall").may_write_memory());
+        assert!(!registry.effects("syscall").is_unknown());
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use crate::analysis::dataflow::{self, Dataflow, Direction, Lattice};
+use crate::ir::visit::Visit;
+use crate::ir::{Blk, Def, Expr, Jmp, Loc, Phi, Sub, Var};
+use crate::lift::Lifter;
+use crate::prelude::Entity;
+
+/// The clobbered registers that are live at some point of a Sub.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct LiveClobbers(BTreeSet<Arc<str>>);
+
+impl Lattice for LiveClobbers {
+    fn bottom() -> Self {
+        Self::default()
+    }
+
+    fn join(&mut self, other: &Self) {
+        self.0.extend(other.0.iter().cloned());
+    }
+}
+
+struct Uses<'a> {
+    clobbered: &'a BTreeSet<Arc<str>>,
+    live: &'a mut LiveClobbers,
+}
+
+impl<'ir, 'a> Visit<'ir> for Uses<'a> {
+    fn visit_var(&mut self, var: &'ir Var) {
+        if var.is_physical() && self.clobbered.contains(var.name()) {
+            self.live.0.insert(var.name().clone());
+        }
+    }
+
+    // the variable assigned is defined, not read
+    fn visit_def_assign(&mut self, _var: &'ir Var, expr: &'ir Expr) {
+        self.visit_expr(expr)
+    }
+}
+
+/// Removes assignments to caller-saved registers that are overwritten by a
+/// call before they are read, e.g., the flags and scratch registers set by
+/// the code preceding a call, which generic DCE (see `DcePass`) must keep,
+/// since it assumes that calls read all registers.
+///
+/// A register is clobbered if calls may modify it, and it does not share
+/// storage with a register passing arguments to them (see
+/// `Lifter::caller_saved` and `Lifter::argument_registers`), e.g., AL,
+/// which passes the number of vector registers used by variadic calls
+/// under the SysV convention, is not clobbered, as it is part of RAX; the
+/// registers read by the explicit arguments of a call remain live. Clobbered registers are assumed to be
+/// read on all other flows out of a Sub (e.g., returns, as they may hold
+/// return values), and by calls within Blks whose other flows are not
+/// fall-throughs (where the effect of the call cannot be separated from
+/// the other flows).
+#[derive(Debug, Clone, Default)]
+pub struct ClobberPass {
+    clobbered: BTreeSet<Arc<str>>,
+}
+
+impl ClobberPass {
+    /// Creates a pass for calls that clobber the registers `clobbered`.
+    pub fn new(clobbered: impl IntoIterator<Item = Var>) -> Self {
+        Self {
+            clobbered: clobbered
+                .into_iter()
+                .filter(|var| var.is_physical())
+                .map(|var| var.name().clone())
+                .collect(),
+        }
+    }
+
+    /// Creates a pass for the calling convention of `lifter`; if the
+    /// registers passing arguments are not known for its language, no
+    /// register is treated as clobbered.
+    pub fn from_lifter(lifter: &Lifter) -> Self {
+        let arguments = match lifter.argument_registers() {
+            Some(arguments) => arguments,
+            None => return Self::default(),
+        };
+
+        let mut pass = Self::new(lifter.caller_saved());
+        for var in arguments.iter().chain(lifter.stack_pointer().iter()) {
+            // arguments may be passed in parts of registers (e.g., AL)
+            for part in lifter.overlapping_registers(var.name()) {
+                pass.clobbered.remove(part.name());
+            }
+        }
+        pass
+    }
+
+    /// Treat (physical) variables named `name` as not clobbered by calls.
+    pub fn preserve(&mut self, name: &str) -> &mut Self {
+        self.clobbered.remove(name);
+        self
+    }
+
+    /// Treat only the registers clobbered by both passes as clobbered,
+    /// e.g., for calls that may follow either's convention.
+    pub fn intersect(&mut self, other: &Self) -> &mut Self {
+        self.clobbered.retain(|name| other.clobbered.contains(name));
+        self
+    }
+
+    pub fn clobbered(&self) -> impl Iterator<Item = &Arc<str>> {
+        self.clobbered.iter()
+    }
+
+    fn is_clobbered(&self, var: &Var) -> bool {
+        var.is_physical() && self.clobbered.contains(var.name())
+    }
+
+    fn all(&self) -> LiveClobbers {
+        LiveClobbers(self.clobbered.clone())
+    }
+
+    fn uses<'a>(&'a self, live: &'a mut LiveClobbers) -> Uses<'a> {
+        Uses { clobbered: &self.clobbered, live }
+    }
+
+    // a Blk whose flows are a call, optionally followed by a fall-through to
+    // another Blk of the Sub
+    fn is_call_blk(sub: &Sub, blk: &Blk) -> bool {
+        match blk.jmps() {
+            [call] => matches!(**call, Jmp::Call(_, _)),
+            [call, next] => {
+                matches!(**call, Jmp::Call(_, _))
+                    && matches!(**next, Jmp::Branch(Loc::Resolved(id)) if sub.blk(id).is_some())
+            }
+            _ => false,
+        }
+    }
+
+    fn has_side_effects(expr: &Expr) -> bool {
+        struct Intrinsics(bool);
+
+        impl<'ir> Visit<'ir> for Intrinsics {
+            fn visit_expr_intrinsic(&mut self, _name: &'ir str, _args: &'ir [Box<Expr>], _bits: u32) {
+                self.0 = true;
+            }
+        }
+
+        let mut intrinsics = Intrinsics(false);
+        intrinsics.visit_expr(expr);
+        intrinsics.0
+    }
+
+    fn is_dead(&self, live: &LiveClobbers, def: &Def) -> bool {
+        match def {
+            Def::Assign(var, expr) => {
+                self.is_clobbered(var) && !live.0.contains(var.name()) && !Self::has_side_effects(expr)
+            }
+            Def::Assume(_) | Def::Store(_, _, _, _) => false,
+        }
+    }
+
+    fn kill(&self, def: &Def, live: &mut LiveClobbers) {
+        if let Def::Assign(ref var, _) = def {
+            if self.is_clobbered(var) {
+                live.0.remove(var.name());
+            }
+        }
+    }
+
+    fn transfer_jmps(&self, blk: &Blk, calls: bool, live: &mut LiveClobbers) {
+        for jmp in blk.jmps().iter().rev() {
+            if calls && matches!(**jmp, Jmp::Call(_, _)) {
+                live.0.clear();
+            } else if matches!(**jmp, Jmp::Call(_, _)) {
+                *live = self.all();
+            }
+            self.uses(live).visit_jmp(jmp);
+        }
+    }
+
+    /// Removes the assignments to clobbered registers from `sub` that are
+    /// dead across calls; returns the number of Defs removed.
+    pub fn apply(&self, sub: &mut Sub) -> usize {
+        if self.clobbered.is_empty() {
+            return 0
+        }
+
+        let result = dataflow::solve(&Analysis { pass: self, sub }, sub, &sub.cfg());
+
+        let calls = sub
+            .blks()
+            .iter()
+            .map(|blk| Self::is_call_blk(sub, blk))
+            .collect::<Vec<_>>();
+
+        let mut removed = 0;
+
+        for (blk, calls) in sub.blks_mut().iter_mut().zip(calls) {
+            let mut live = result.exit(&*blk).cloned().unwrap_or_default();
+            self.transfer_jmps(blk, calls, &mut live);
+
+            let defs = std::mem::take(blk.defs_mut());
+            let mut kept = Vec::with_capacity(defs.len());
+
+            for def in defs.into_iter().rev() {
+                if self.is_dead(&live, &def) {
+                    removed += 1;
+                } else {
+                    self.kill(&def, &mut live);
+                    self.uses(&mut live).visit_def(&def);
+                    kept.push(def);
+                }
+            }
+
+            kept.reverse();
+            *blk.defs_mut() = kept;
+        }
+
+        removed
+    }
+}
+
+struct Analysis<'a> {
+    pass: &'a ClobberPass,
+    sub: &'a Sub,
+}
+
+impl<'a> Dataflow for Analysis<'a> {
+    type Value = LiveClobbers;
+
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn boundary(&self, sub: &Sub, blk: &Entity<Blk>) -> LiveClobbers {
+        if ClobberPass::is_call_blk(sub, blk) {
+            return LiveClobbers::default()
+        }
+
+        let leaves = blk.jmps().iter().any(|jmp| match **jmp {
+            Jmp::Branch(Loc::Resolved(id)) | Jmp::CBranch(Loc::Resolved(id), _) => sub.blk(id).is_none(),
+            _ => true,
+        });
+
+        if leaves {
+            self.pass.all()
+        } else {
+            LiveClobbers::default()
+        }
+    }
+
+    fn transfer_phi(&self, phi: &Entity<Phi>, live: &mut LiveClobbers) {
+        if self.pass.is_clobbered(phi.var()) {
+            live.0.remove(phi.var().name());
+        }
+        let mut uses = self.pass.uses(live);
+        for (cond, expr) in phi.choices() {
+            uses.visit_expr(cond);
+            uses.visit_expr(expr);
+        }
+    }
+
+    fn transfer_def(&self, def: &Entity<Def>, live: &mut LiveClobbers) {
+        if self.pass.is_dead(live, def) {
+            return
+        }
+
+        self.pass.kill(def, live);
+        self.pass.uses(live).visit_def(def);
+    }
+
+    fn transfer_blk(&self, blk: &Blk, live: &mut LiveClobbers) {
+        let calls = ClobberPass::is_call_blk(self.sub, blk);
+        self.pass.transfer_jmps(blk, calls, live);
+        for def in blk.defs().iter().rev() {
+            self.transfer_def(def, live);
+        }
+        for phi in blk.phis().iter().rev() {
+            self.transfer_phi(phi, live);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::ir::{Addr, BitVec};
+    use crate::lift::LifterBuilder;
+    use crate::prelude::Identifiable;
+    use crate::types::bv::BitVecT;
+
+    fn reg(name: &str, bits: u32) -> Var {
+        Var::physical(name, BitVecT::with_bits(bits, false)).into()
+    }
+
+    // assigns each register 0 (in order), then calls 0x2000 and returns
+    fn caller(regs: &[Var]) -> Entity<Sub> {
+        let mut call = Blk::new(None);
+        let mut next = Blk::new(None);
+        for var in regs {
+            call.add_def(Def::assign(var.clone(), BitVec::from_u64(0, var.bits().unwrap_or(0) as usize)));
+        }
+        call.add_jmp(Jmp::call(Loc::Fixed(Addr::from(0x2000u32)), []));
+        call.add_jmp(Jmp::branch(next.id()));
+        next.add_jmp(Jmp::return_(Expr::from(reg("EAX", 32))));
+
+        Sub::new(None, Addr::from(0x1000u32), vec![call, next])
+    }
+
+    #[test]
+    fn test_assigned_not_read() {
+        // ECX := 0; ECX := 1; return: ECX is live at the return, but its
+        // assignment does not make it live before it, so the first is dead
+        let mut blk = Blk::new(None);
+        blk.add_def(Def::assign(reg("ECX", 32), BitVec::from_u64(0, 32)));
+        blk.add_def(Def::assign(reg("ECX", 32), BitVec::from_u64(1, 32)));
+        blk.add_jmp(Jmp::return_(Expr::from(reg("EAX", 32))));
+
+        let mut sub = Sub::new(None, Addr::from(0x1000u32), vec![blk]);
+        let pass = ClobberPass::new([reg("ECX", 32)]);
+
+        assert_eq!(pass.apply(&mut sub), 1);
+        assert_eq!(sub.blks()[0].defs().len(), 1);
+        assert!(matches!(*sub.blks()[0].defs()[0], Def::Assign(_, Expr::Val(ref bv)) if bv.to_u64() == Some(1)));
+    }
+
+    #[test]
+    fn test_variadic_count_preserved() -> Result<(), Box<dyn std::error::Error>> {
+        let root = env::var("DELIRIUM_TEST_ENV_ROOT")?;
+        let path = PathBuf::from_iter([&root, "processors"]);
+        let lifter = LifterBuilder::new(&path)?.build("x86:LE:64:default", "gcc")?;
+
+        let pass = ClobberPass::from_lifter(&lifter);
+        for name in ["AL", "AX", "EAX", "RAX"] {
+            assert!(!pass.clobbered().any(|clobbered| &**clobbered == name));
+        }
+
+        // mov al, 0; call printf
+        let mut sub = caller(&[reg("AL", 8)]);
+        assert_eq!(pass.apply(&mut sub), 0);
+        assert_eq!(sub.blks()[0].defs().len(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use crate::analysis::dataflow::Dataflow;
+use crate::analysis::Liveness;
+use crate::ir::{IntrinsicRegistry, Sub};
+
+/// Removes each Def within `sub` whose assigned variable is never read;
+/// see `DcePass`. Returns the number of Defs removed.
+pub fn dce(sub: &mut Sub) -> usize {
+    DcePass::new().apply(sub)
+}
+
+/// A liveness-based dead code elimination pass over the Defs of a Sub.
+///
+/// Assignments to transient variables are dead unless read later within
+/// the Sub; physical variables are assumed to be live as described by
+/// `Liveness`. Stores, assumptions, and assignments of expressions
+/// containing intrinsics (which may have side-effects) are never removed,
+/// unless the intrinsics are known to be pure (see `intrinsics`).
+///
+/// Since dead assignments do not make the variables they read live,
+/// chains of dead assignments are removed in a single application.
+#[derive(Default)]
+pub struct DcePass {
+    liveness: Liveness,
+}
+
+impl DcePass {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Never remove assignments to (physical) variables named `name`, and
+    /// treat them as live on return.
+    pub fn preserve(&mut self, name: impl Into<Arc<str>>) -> &mut Self {
+        self.liveness.preserve(name);
+        self
+    }
+
+    pub fn preserved(&self) -> impl Iterator<Item = &Arc<str>> {
+        self.liveness.preserved()
+    }
+
+    /// Treat the intrinsics of `registry` without side-effects as pure.
+    pub fn intrinsics(&mut self, registry: &IntrinsicRegistry) -> &mut Self {
+        for intrinsic in registry.iter().filter(|intrinsic| intrinsic.effects().is_pure()) {
+            self.liveness.pure_intrinsic(intrinsic.name().clone());
+        }
+        self
+    }
+
+    /// Removes dead Defs from `sub`; returns the number of Defs removed.
+    pub fn apply(&mut self, sub: &mut Sub) -> usize {
+        let result = self.liveness.solve(sub);
+        let mut removed = 0;
+
+        for blk in sub.blks_mut().iter_mut() {
+            let mut live = result.exit(&*blk).cloned().unwrap_or_default();
+
+            for jmp in blk.jmps().iter().rev() {
+                self.liveness.transfer_jmp(jmp, &mut live);
+            }
+
+            let defs = std::mem::take(blk.defs_mut());
+            let mut kept = Vec::with_capacity(defs.len());
+
+            for def in defs.into_iter().rev() {
+                if self.liveness.is_dead(&live, &def) {
+                    removed += 1;
+                } else {
+                    self.liveness.transfer_def(&def, &mut live);
+                    kept.push(def);
+                }
+            }
+
+            kept.reverse();
+            *blk.defs_mut() = kept;
+        }
+
+        removed
+    }
+}
@@ -0,0 +1,28 @@
+/// Transformation passes over our IR/IL.
+/// 
+/// Unlike the passes applied to ECode during lifting, these passes
+/// operate on Blks and Subs, and may be applied selectively by users
+/// of the crate.
+/// 
+/// - `simplify` folds constants, removes identity operations, and
+///   canonicalises expressions.
+/// 
+/// - `dce` removes assignments whose variables are never read.
+/// 
+/// - `clobber` removes assignments to caller-saved registers that are
+///   overwritten by calls before being read, per the calling convention.
+/// 
+/// - `tailcall` classifies branches to other Subs as tail calls, and
+///   normalises them into calls followed by returns.
+
+pub mod clobber;
+pub use clobber::ClobberPass;
+
+pub mod dce;
+pub use dce::{dce, DcePass};
+
+pub mod simplify;
+pub use simplify::{simplify, simplify_with, SimplifyPass};
+
+pub mod tailcall;
+pub use tailcall::{TailCall, TailCallNormalisation, TailCallPass};
@@ -0,0 +1,468 @@
+use std::mem::replace;
+
+use crate::ir::visit::VisitMut;
+use crate::ir::{BinOp, BinRel, BitVec, Blk, Cast, CostModel, Expr, ExprCost, Sub, UnOp};
+
+/// Simplifies an expression bottom-up by folding constant sub-expressions,
+/// removing identity operations, collapsing chains of extracts and concats
+/// (e.g., as introduced by alias normalisation), and placing the operands
+/// of commutative operations into a canonical order (with constants on the
+/// right).
+pub fn simplify(expr: Expr) -> Expr {
+    simplify_with(expr, &ExprCost::default())
+}
+
+/// Simplifies an expression as `simplify`, but does not apply rewrites
+/// that increase the cost of the expression under `model`.
+pub fn simplify_with(expr: Expr, model: &impl CostModel) -> Expr {
+    let simplify = |expr| simplify_with(expr, model);
+
+    let mut expr = simplify_operands(expr, &simplify);
+    while let Some(nexpr) = rewrite(&expr) {
+        let nexpr = simplify_operands(nexpr, &simplify);
+        if model.cost(&nexpr) > model.cost(&expr) {
+            break
+        }
+        expr = nexpr;
+    }
+    expr
+}
+
+fn simplify_operands(expr: Expr, simplify: &impl Fn(Expr) -> Expr) -> Expr {
+    match expr {
+        Expr::UnRel(op, expr) => Expr::UnRel(op, Box::new(simplify(*expr))),
+        Expr::UnOp(op, expr) => Expr::UnOp(op, Box::new(simplify(*expr))),
+        Expr::BinRel(op, lexpr, rexpr) => {
+            Expr::BinRel(op, Box::new(simplify(*lexpr)), Box::new(simplify(*rexpr)))
+        }
+        Expr::BinOp(op, lexpr, rexpr) => {
+            Expr::BinOp(op, Box::new(simplify(*lexpr)), Box::new(simplify(*rexpr)))
+        }
+        Expr::Cast(expr, cast) => Expr::Cast(Box::new(simplify(*expr)), cast),
+        Expr::Load(expr, bits, mem) => Expr::Load(Box::new(simplify(*expr)), bits, mem),
+        Expr::Extract(expr, lsb, msb) => Expr::Extract(Box::new(simplify(*expr)), lsb, msb),
+        Expr::Concat(lexpr, rexpr) => {
+            Expr::Concat(Box::new(simplify(*lexpr)), Box::new(simplify(*rexpr)))
+        }
+        Expr::IfElse(cond, texpr, fexpr) => Expr::IfElse(
+            Box::new(simplify(*cond)),
+            Box::new(simplify(*texpr)),
+            Box::new(simplify(*fexpr)),
+        ),
+        Expr::Intrinsic(name, args, bits) => Expr::Intrinsic(
+            name,
+            args.into_iter().map(|arg| Box::new(simplify(*arg))).collect(),
+            bits,
+        ),
+        expr @ (Expr::Val(_) | Expr::Var(_)) => expr,
+    }
+}
+
+fn bool_val(value: bool) -> BitVec {
+    BitVec::from_u64(value as u64, 8)
+}
+
+fn is_zero(expr: &Expr) -> bool {
+    expr.val().map(|bv| bv.is_zero()).unwrap_or(false)
+}
+
+fn is_one(expr: &Expr) -> bool {
+    expr.val().map(|bv| bv.is_one()).unwrap_or(false)
+}
+
+fn is_ones(expr: &Expr) -> bool {
+    expr.val()
+        .map(|bv| bv.clone().unsigned() == BitVec::max_value_with(bv.bits(), false))
+        .unwrap_or(false)
+}
+
+// intrinsics may have side-effects, so we must not discard or merge them
+fn is_pure(expr: &Expr) -> bool {
+    match expr {
+        Expr::UnRel(_, expr) | Expr::UnOp(_, expr) | Expr::Cast(expr, _) => is_pure(expr),
+        Expr::Load(expr, _, _) | Expr::Extract(expr, _, _) => is_pure(expr),
+        Expr::BinRel(_, lexpr, rexpr) | Expr::BinOp(_, lexpr, rexpr) | Expr::Concat(lexpr, rexpr) => {
+            is_pure(lexpr) && is_pure(rexpr)
+        }
+        Expr::IfElse(cond, texpr, fexpr) => is_pure(cond) && is_pure(texpr) && is_pure(fexpr),
+        Expr::Intrinsic(_, _, _) => false,
+        Expr::Val(_) | Expr::Var(_) => true,
+    }
+}
+
+pub(crate) fn is_commutative_op(op: BinOp) -> bool {
+    matches!(op, BinOp::Add | BinOp::Mul | BinOp::And | BinOp::Or | BinOp::Xor)
+}
+
+pub(crate) fn is_commutative_rel(op: BinRel) -> bool {
+    matches!(op, BinRel::Eq | BinRel::Neq)
+}
+
+// constants are placed on the right; otherwise we order operands by their
+// structure
+fn out_of_order(lexpr: &Expr, rexpr: &Expr) -> bool {
+    match (lexpr.is_val(), rexpr.is_val()) {
+        (true, false) => true,
+        (false, true) => false,
+        _ => lexpr > rexpr,
+    }
+}
+
+pub(crate) fn fold_unop(op: UnOp, bv: &BitVec) -> Option<BitVec> {
+    match op {
+        UnOp::Not => Some(!bv),
+        UnOp::Neg => Some(-bv),
+        _ => None,
+    }
+}
+
+pub(crate) fn fold_binop(op: BinOp, lbv: &BitVec, rbv: &BitVec) -> Option<BitVec> {
+    let bits = lbv.bits();
+    let is_shift = matches!(op, BinOp::Shl | BinOp::Shr | BinOp::Sar);
+
+    if !is_shift && bits != rbv.bits() {
+        return None
+    }
+
+    let lbv = lbv.clone().unsigned();
+    let rbv = rbv.clone().unsigned();
+
+    Some(match op {
+        BinOp::And => lbv & rbv,
+        BinOp::Or => lbv | rbv,
+        BinOp::Xor => lbv ^ rbv,
+        BinOp::Add => lbv + rbv,
+        BinOp::Sub => lbv - rbv,
+        BinOp::Mul => lbv * rbv,
+        BinOp::Div | BinOp::Rem | BinOp::SDiv | BinOp::SRem if rbv.is_zero() => return None,
+        BinOp::Div => lbv / rbv,
+        BinOp::Rem => lbv % rbv,
+        BinOp::SDiv => (lbv.signed() / rbv.signed()).unsigned(),
+        BinOp::SRem => (lbv.signed() % rbv.signed()).unsigned(),
+        BinOp::Shl | BinOp::Shr | BinOp::Sar => {
+            let amount = rbv.to_u32().filter(|amount| (*amount as usize) < bits);
+            match (op, amount) {
+                (BinOp::Shl, Some(amount)) => lbv << amount,
+                (BinOp::Shr, Some(amount)) => lbv >> amount,
+                (BinOp::Sar, Some(amount)) => (lbv.signed() >> amount).unsigned(),
+                (BinOp::Sar, None) => (lbv.signed() >> (bits as u32 - 1)).unsigned(),
+                (_, None) => BitVec::zero(bits),
+                _ => unreachable!(),
+            }
+        }
+    })
+}
+
+pub(crate) fn fold_binrel(op: BinRel, lbv: &BitVec, rbv: &BitVec) -> Option<BitVec> {
+    if lbv.bits() != rbv.bits() {
+        return None
+    }
+
+    let (ulbv, urbv) = (lbv.clone().unsigned(), rbv.clone().unsigned());
+    let (slbv, srbv) = (lbv.clone().signed(), rbv.clone().signed());
+
+    Some(bool_val(match op {
+        BinRel::Eq => ulbv == urbv,
+        BinRel::Neq => ulbv != urbv,
+        BinRel::Lt => ulbv < urbv,
+        BinRel::Le => ulbv <= urbv,
+        BinRel::SLt => slbv < srbv,
+        BinRel::SLe => slbv <= srbv,
+        BinRel::Carry => ulbv.carry(&urbv),
+        BinRel::SCarry => slbv.signed_carry(&srbv),
+        BinRel::SBorrow => slbv.signed_borrow(&srbv),
+    }))
+}
+
+pub(crate) fn fold_cast(bv: &BitVec, cast: Cast) -> Option<BitVec> {
+    let bits = bv.bits();
+    Some(match cast {
+        Cast::Bool => bool_val(!bv.is_zero()),
+        Cast::Signed(nbits) => bv.signed_cast(nbits as usize).unsigned(),
+        Cast::Unsigned(nbits) | Cast::Low(nbits) => bv.unsigned_cast(nbits as usize),
+        Cast::High(nbits) if (nbits as usize) <= bits => {
+            (bv.clone().unsigned() >> (bits - nbits as usize) as u32).unsigned_cast(nbits as usize)
+        }
+        Cast::High(_) | Cast::Float(_) => return None,
+    })
+}
+
+pub(crate) fn fold_extract(bv: &BitVec, lsb: u32, msb: u32) -> BitVec {
+    (bv.clone().unsigned() >> lsb).unsigned_cast((msb - lsb) as usize)
+}
+
+pub(crate) fn fold_concat(lbv: &BitVec, rbv: &BitVec) -> BitVec {
+    let bits = lbv.bits() + rbv.bits();
+    (lbv.unsigned_cast(bits) << rbv.bits() as u32) | rbv.unsigned_cast(bits)
+}
+
+// Attempts a single rewrite at the root of `expr`, assuming its operands
+// are already simplified.
+fn rewrite(expr: &Expr) -> Option<Expr> {
+    match expr {
+        Expr::UnOp(op, expr) => match (op, &**expr) {
+            (_, Expr::Val(bv)) => fold_unop(*op, bv).map(Expr::from),
+            (UnOp::Not, Expr::UnOp(UnOp::Not, expr)) | (UnOp::Neg, Expr::UnOp(UnOp::Neg, expr)) => {
+                Some((**expr).clone())
+            }
+            _ => None,
+        },
+        Expr::BinRel(op, lexpr, rexpr) => {
+            if let (Expr::Val(lbv), Expr::Val(rbv)) = (&**lexpr, &**rexpr) {
+                return fold_binrel(*op, lbv, rbv).map(Expr::from)
+            }
+
+            if lexpr == rexpr && is_pure(lexpr) {
+                match op {
+                    BinRel::Eq | BinRel::Le | BinRel::SLe => return Some(bool_val(true).into()),
+                    BinRel::Neq | BinRel::Lt | BinRel::SLt => return Some(bool_val(false).into()),
+                    _ => (),
+                }
+            }
+
+            if is_commutative_rel(*op) && out_of_order(lexpr, rexpr) {
+                return Some(Expr::BinRel(*op, rexpr.clone(), lexpr.clone()))
+            }
+
+            None
+        }
+        Expr::BinOp(op, lexpr, rexpr) => rewrite_binop(*op, lexpr, rexpr),
+        Expr::Cast(expr, cast) => {
+            if let Expr::Val(ref bv) = **expr {
+                return fold_cast(bv, *cast).map(Expr::from)
+            }
+
+            let bits = expr.bits();
+            match cast {
+                Cast::Signed(nbits) | Cast::Unsigned(nbits) if *nbits == bits => {
+                    Some((**expr).clone())
+                }
+                Cast::Signed(nbits) | Cast::Unsigned(nbits) | Cast::Low(nbits) if *nbits < bits => {
+                    Some(Expr::extract_low((**expr).clone(), *nbits))
+                }
+                Cast::Low(nbits) if *nbits == bits => Some((**expr).clone()),
+                Cast::High(nbits) if *nbits <= bits => {
+                    Some(Expr::extract((**expr).clone(), bits - nbits, bits))
+                }
+                _ => None,
+            }
+        }
+        Expr::Extract(expr, lsb, msb) => rewrite_extract(expr, *lsb, *msb),
+        Expr::Concat(lexpr, rexpr) => {
+            match (&**lexpr, &**rexpr) {
+                (Expr::Val(lbv), Expr::Val(rbv)) => Some(fold_concat(lbv, rbv).into()),
+                // zero-extension
+                (Expr::Val(lbv), _) if lbv.is_zero() => Some(Expr::cast(
+                    (**rexpr).clone(),
+                    Cast::Unsigned(lbv.bits() as u32 + rexpr.bits()),
+                )),
+                // adjacent extracts of the same expression
+                (Expr::Extract(lexpr, llsb, lmsb), Expr::Extract(rexpr, rlsb, rmsb))
+                    if lexpr == rexpr && llsb == rmsb =>
+                {
+                    Some(Expr::extract((**lexpr).clone(), *rlsb, *lmsb))
+                }
+                _ => None,
+            }
+        }
+        Expr::IfElse(cond, texpr, fexpr) => {
+            if let Expr::Val(ref bv) = **cond {
+                Some(if bv.is_zero() { (**fexpr).clone() } else { (**texpr).clone() })
+            } else if texpr == fexpr && is_pure(cond) {
+                Some((**texpr).clone())
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+fn rewrite_binop(op: BinOp, lexpr: &Expr, rexpr: &Expr) -> Option<Expr> {
+    if let (Expr::Val(lbv), Expr::Val(rbv)) = (lexpr, rexpr) {
+        return fold_binop(op, lbv, rbv).map(Expr::from)
+    }
+
+    if is_commutative_op(op) && out_of_order(lexpr, rexpr) {
+        return Some(Expr::bin_op(op, rexpr.clone(), lexpr.clone()))
+    }
+
+    // identities of the form x op c
+    match op {
+        BinOp::Add | BinOp::Sub | BinOp::Or | BinOp::Xor | BinOp::Shl | BinOp::Shr | BinOp::Sar
+            if is_zero(rexpr) =>
+        {
+            return Some(lexpr.clone())
+        }
+        BinOp::Mul | BinOp::Div | BinOp::SDiv if is_one(rexpr) => return Some(lexpr.clone()),
+        BinOp::And if is_ones(rexpr) => return Some(lexpr.clone()),
+        BinOp::And | BinOp::Mul if is_zero(rexpr) && is_pure(lexpr) => {
+            return Some(rexpr.clone())
+        }
+        BinOp::Or if is_ones(rexpr) && is_pure(lexpr) => return Some(rexpr.clone()),
+        _ => (),
+    }
+
+    // identities of the form x op x
+    if lexpr == rexpr && is_pure(lexpr) {
+        match op {
+            BinOp::And | BinOp::Or => return Some(lexpr.clone()),
+            BinOp::Xor | BinOp::Sub => return Some(BitVec::zero(lexpr.bits() as usize).into()),
+            _ => (),
+        }
+    }
+
+    // x - c => x + -c; this enables reassociation of constants below
+    if let (BinOp::Sub, Expr::Val(rbv)) = (op, rexpr) {
+        return Some(Expr::bin_op(BinOp::Add, lexpr.clone(), -rbv))
+    }
+
+    // (x op c1) op c2 => x op (c1 op c2)
+    if let (Expr::BinOp(iop, ilexpr, irexpr), Expr::Val(rbv)) = (lexpr, rexpr) {
+        if *iop == op && is_commutative_op(op) {
+            if let Expr::Val(ref ibv) = **irexpr {
+                let cbv = fold_binop(op, ibv, rbv)?;
+                return Some(Expr::bin_op(op, (**ilexpr).clone(), cbv))
+            }
+        }
+    }
+
+    None
+}
+
+fn rewrite_extract(expr: &Expr, lsb: u32, msb: u32) -> Option<Expr> {
+    if lsb == 0 && msb == expr.bits() {
+        return Some(expr.clone())
+    }
+
+    match expr {
+        Expr::Val(bv) => Some(fold_extract(bv, lsb, msb).into()),
+        Expr::Extract(iexpr, ilsb, _) => {
+            Some(Expr::extract((**iexpr).clone(), ilsb + lsb, ilsb + msb))
+        }
+        Expr::Concat(hexpr, lexpr) => {
+            let lbits = lexpr.bits();
+            if msb <= lbits {
+                Some(Expr::extract((**lexpr).clone(), lsb, msb))
+            } else if lsb >= lbits {
+                Some(Expr::extract((**hexpr).clone(), lsb - lbits, msb - lbits))
+            } else {
+                None
+            }
+        }
+        Expr::Cast(iexpr, Cast::Signed(_) | Cast::Unsigned(_)) if msb <= iexpr.bits() => {
+            Some(Expr::extract((**iexpr).clone(), lsb, msb))
+        }
+        // bits beyond a zero-extended value are known to be zero
+        Expr::Cast(iexpr, Cast::Unsigned(_)) if lsb >= iexpr.bits() => {
+            Some(BitVec::zero((msb - lsb) as usize).into())
+        }
+        _ => None,
+    }
+}
+
+/// Applies `simplify` to each expression within a Blk or Sub, using the
+/// given cost model.
+#[derive(Default)]
+pub struct SimplifyPass<C = ExprCost> {
+    model: C,
+}
+
+impl SimplifyPass {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<C> SimplifyPass<C> where C: CostModel {
+    pub fn with_cost_model(model: C) -> Self {
+        Self { model }
+    }
+
+    pub fn cost_model(&self) -> &C {
+        &self.model
+    }
+
+    pub fn apply_blk(&mut self, blk: &mut Blk) {
+        self.visit_blk_mut(blk);
+    }
+
+    pub fn apply(&mut self, sub: &mut Sub) {
+        self.visit_sub_mut(sub);
+    }
+}
+
+impl<'ir, C> VisitMut<'ir> for SimplifyPass<C> where C: CostModel {
+    fn visit_expr_mut(&mut self, expr: &'ir mut Expr) {
+        let nexpr = replace(expr, Expr::Val(BitVec::zero(1)));
+        *expr = simplify_with(nexpr, &self.model);
+    }
+}
+
+impl Expr {
+    pub fn simplify(self) -> Self {
+        simplify(self)
+    }
+
+    pub fn simplify_with(self, model: &impl CostModel) -> Self {
+        simplify_with(self, model)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::Var;
+    use crate::types::U32;
+
+    fn rax() -> Expr {
+        Var::physical("RAX", crate::types::U64).into()
+    }
+
+    #[test]
+    fn test_fold_identities() {
+        let expr = Expr::bin_op(BinOp::Add, rax(), BitVec::zero(64));
+        assert_eq!(expr.simplify(), rax());
+
+        let expr = Expr::bin_op(
+            BinOp::Add,
+            BitVec::from_u64(1, 32),
+            BitVec::from_u64(2, 32),
+        );
+        assert_eq!(expr.simplify(), Expr::from(BitVec::from_u64(3, 32)));
+
+        let eax = Expr::from(Var::physical("EAX", U32));
+        let expr = Expr::bin_op(BinOp::Xor, eax.clone(), eax);
+        assert_eq!(expr.simplify(), Expr::from(BitVec::zero(32)));
+    }
+
+    #[test]
+    fn test_collapse_alias_chains() {
+        // AL := extract(concat(extract_high(RAX, 56), extract_low(RAX, 8)), 0, 8)
+        let expr = Expr::extract_low(
+            Expr::concat(Expr::extract_high(rax(), 56), Expr::extract_low(rax(), 8)),
+            8,
+        );
+        assert_eq!(expr.simplify(), Expr::extract_low(rax(), 8));
+
+        // concat(extract(RAX, 8, 64), extract(RAX, 0, 8)) == RAX
+        let expr = Expr::concat(Expr::extract(rax(), 8, 64), Expr::extract_low(rax(), 8));
+        assert_eq!(expr.simplify(), rax());
+    }
+
+    #[test]
+    fn test_cost_model() {
+        let eax = Expr::from(Var::physical("EAX", U32));
+        let expr = Expr::concat(BitVec::zero(32), eax.clone());
+
+        assert_eq!(expr.node_count(), 3);
+        assert_eq!(expr.depth(), 2);
+
+        // by default, zero-extension is canonicalised as a cast
+        assert_eq!(expr.clone().simplify(), Expr::cast(eax, Cast::Unsigned(64)));
+
+        // ...but not if casts are more costly than the concat they replace
+        let model = ExprCost { cast: 3, ..Default::default() };
+        assert_eq!(expr.clone().simplify_with(&model), expr);
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::BTreeSet;
+
+use crate::ir::{Addr, Blk, Expr, Jmp, Loc, Sub};
+use crate::prelude::{Annotations, Entity, Id, Identifiable};
+
+/// Annotates Jmps classified as tail calls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TailCall;
+
+/// How tail calls are represented once detected.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TailCallNormalisation {
+    /// Only annotate the branches with `TailCall`
+    Annotate,
+    /// Replace each unconditional branch with a call to its target,
+    /// followed by a return (conditional branches are only annotated)
+    Call,
+}
+
+impl Default for TailCallNormalisation {
+    fn default() -> Self {
+        Self::Call
+    }
+}
+
+/// Classifies branches to the entry points of other Subs as tail calls.
+///
+/// Only unconditional branches are rewritten as calls (see
+/// `TailCallNormalisation::Call`); a conditional branch has no call
+/// counterpart, so tail calls through conditional branches are only
+/// annotated, whatever the normalisation.
+///
+/// The return following each normalised tail call is to the Sub's
+/// (unknown) return address, given by the intrinsic `return_address`,
+/// since the callee returns directly to the Sub's caller.
+#[derive(Debug, Clone, Default)]
+pub struct TailCallPass {
+    entries: BTreeSet<Addr>,
+    entry_blks: BTreeSet<Id<Blk>>,
+    mode: TailCallNormalisation,
+}
+
+impl TailCallPass {
+    /// Creates a pass for a program whose Subs start at `entries`.
+    pub fn new(entries: impl IntoIterator<Item = Addr>) -> Self {
+        Self {
+            entries: entries.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Treat `blk` as the entry Blk of a Sub (e.g., for resolved flows).
+    pub fn entry_blk(&mut self, blk: impl Identifiable<Blk>) -> &mut Self {
+        self.entry_blks.insert(blk.id());
+        self
+    }
+
+    pub fn normalisation(&self) -> TailCallNormalisation {
+        self.mode
+    }
+
+    pub fn set_normalisation(&mut self, mode: TailCallNormalisation) -> &mut Self {
+        self.mode = mode;
+        self
+    }
+
+    fn is_entry(&self, sub: &Sub, loc: &Loc) -> bool {
+        match loc {
+            Loc::Fixed(addr) => self.entries.contains(addr) && sub.addr() != Some(addr),
+            Loc::Resolved(id) => {
+                self.entry_blks.contains(id) && sub.entry().map(|blk| blk.id()) != Some(*id)
+            }
+            Loc::Computed(_) => false,
+        }
+    }
+
+    /// Returns true if `jmp` (within `sub`) is a tail call.
+    pub fn is_tail_call(&self, sub: &Sub, jmp: &Jmp) -> bool {
+        match jmp {
+            Jmp::Branch(loc) | Jmp::CBranch(loc, _) => self.is_entry(sub, loc),
+            _ => false,
+        }
+    }
+
+    /// Classifies the tail calls of `sub`, annotating each in
+    /// `annotations` and normalising them as configured; returns the
+    /// number of tail calls found.
+    pub fn apply(&self, sub: &mut Sub, annotations: &mut Annotations) -> usize {
+        let bits = sub.addr().map(|addr| addr.bits()).unwrap_or(64);
+        let mut count = 0;
+
+        let classified = sub
+            .blks()
+            .iter()
+            .flat_map(|blk| blk.jmps().iter())
+            .filter(|jmp| self.is_tail_call(sub, jmp))
+            .map(|jmp| jmp.id())
+            .collect::<BTreeSet<_>>();
+
+        for blk in sub.blks_mut().iter_mut() {
+            let jmps = std::mem::take(blk.jmps_mut());
+            let mut normalised = Vec::with_capacity(jmps.len());
+
+            for jmp in jmps.into_iter() {
+                if !classified.contains(&jmp.id()) {
+                    normalised.push(jmp);
+                    continue
+                }
+
+                count += 1;
+                annotations.insert(&jmp, TailCall);
+
+                match (self.mode, jmp.into_parts()) {
+                    (TailCallNormalisation::Call, (id, Jmp::Branch(loc))) => {
+                        normalised.push(Entity::from_parts(id, Jmp::Call(loc, Default::default())));
+                        normalised.push(Jmp::return_(Expr::intrinsic("return_address", [], bits)));
+                    }
+                    (_, (id, jmp)) => {
+                        normalised.push(Entity::from_parts(id, jmp));
+                    }
+                }
+            }
+
+            *blk.jmps_mut() = normalised;
+        }
+
+        count
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::{BinRel, BitVec, Var};
+    use crate::types::bv::BitVecT;
+
+    // a Sub at 0x1000 branching to 0x2000 if EAX == 0, and to 0x3000
+    // otherwise
+    fn tail_calls() -> Entity<Sub> {
+        let eax: Var = Var::physical("EAX", BitVecT::with_bits(32, false)).into();
+        let cond = Expr::bin_rel(BinRel::Eq, eax, BitVec::from_u64(0, 32));
+
+        let mut blk = Blk::new(Some(Addr::from(0x1000u32)));
+        blk.add_jmp(Jmp::cbranch(Addr::from(0x2000u32), cond));
+        blk.add_jmp(Jmp::branch(Addr::from(0x3000u32)));
+
+        Sub::new(None, Addr::from(0x1000u32), vec![blk])
+    }
+
+    fn entries() -> Vec<Addr> {
+        [0x1000u32, 0x2000, 0x3000].into_iter().map(Addr::from).collect()
+    }
+
+    #[test]
+    fn test_tail_call_through_branch() {
+        let mut sub = tail_calls();
+        let branch = sub.blks()[0].jmps()[1].id();
+        let mut annotations = Annotations::new();
+
+        assert_eq!(TailCallPass::new(entries()).apply(&mut sub, &mut annotations), 2);
+        assert!(annotations.get::<TailCall, _>(branch).is_some());
+
+        let jmps = sub.blks()[0].jmps();
+        assert_eq!(jmps.len(), 3);
+        assert_eq!(jmps[1].id(), branch);
+        assert!(matches!(*jmps[1], Jmp::Call(Loc::Fixed(ref addr), _) if *addr == Addr::from(0x3000u32)));
+        assert!(matches!(*jmps[2], Jmp::Return(_)));
+    }
+
+    #[test]
+    fn test_tail_call_through_cbranch() {
+        let mut sub = tail_calls();
+        let cbranch = sub.blks()[0].jmps()[0].id();
+        let mut annotations = Annotations::new();
+
+        assert_eq!(TailCallPass::new(entries()).apply(&mut sub, &mut annotations), 2);
+        assert!(annotations.get::<TailCall, _>(cbranch).is_some());
+
+        // the conditional branch is annotated, but not rewritten
+        let jmps = sub.blks()[0].jmps();
+        assert_eq!(jmps[0].id(), cbranch);
+        assert!(matches!(*jmps[0], Jmp::CBranch(Loc::Fixed(ref addr), _) if *addr == Addr::from(0x2000u32)));
+
+        // nor under any normalisation
+        let mut sub = tail_calls();
+        let cbranch = sub.blks()[0].jmps()[0].id();
+        let mut annotations = Annotations::new();
+
+        let mut pass = TailCallPass::new([Addr::from(0x2000u32)]);
+        pass.set_normalisation(TailCallNormalisation::Annotate);
+        assert_eq!(pass.apply(&mut sub, &mut annotations), 1);
+        assert!(annotations.get::<TailCall, _>(cbranch).is_some());
+        assert!(matches!(*sub.blks()[0].jmps()[0], Jmp::CBranch(_, _)));
+    }
+}
@@ -2,4 +2,16 @@ use crate::prelude::Id;
 
 pub trait Identifiable<V> {
     fn id(&self) -> Id<V>;
+}
+
+impl<V> Identifiable<V> for Id<V> {
+    fn id(&self) -> Id<V> {
+        *self
+    }
+}
+
+impl<V, T> Identifiable<V> for &T where T: Identifiable<V> {
+    fn id(&self) -> Id<V> {
+        (**self).id()
+    }
 }
\ No newline at end of file
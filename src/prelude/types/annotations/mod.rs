@@ -0,0 +1,142 @@
+use std::any::{Any, TypeId};
+use std::collections::BTreeMap;
+
+use crate::prelude::{Erased, Id, Identifiable};
+
+trait Annotation: Any + Send + Sync {
+    fn clone_box(&self) -> Box<dyn Annotation>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T> Annotation for T where T: Any + Clone + Send + Sync {
+    fn clone_box(&self) -> Box<dyn Annotation> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+
+/// Typed values attached to entities, stored separately from the entities
+/// themselves (and hence preserved when entities are rewritten, so long as
+/// they keep their ids). Each entity may have at most one annotation of
+/// each type.
+#[derive(Default)]
+pub struct Annotations {
+    annotations: BTreeMap<(Id<Erased>, TypeId), Box<dyn Annotation>>,
+}
+
+impl Clone for Annotations {
+    fn clone(&self) -> Self {
+        Self {
+            annotations: self
+                .annotations
+                .iter()
+                .map(|(key, value)| (*key, (**value).clone_box()))
+                .collect(),
+        }
+    }
+}
+
+impl Annotations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn key<T: 'static, V>(entity: impl Identifiable<V>) -> (Id<Erased>, TypeId) {
+        (entity.id().erase(), TypeId::of::<T>())
+    }
+
+    /// Attaches `value` to `entity`, returning the annotation of the same
+    /// type it replaces.
+    pub fn insert<T, V>(&mut self, entity: impl Identifiable<V>, value: T) -> Option<T>
+    where
+        T: Any + Clone + Send + Sync,
+    {
+        self.annotations
+            .insert(Self::key::<T, V>(entity), Box::new(value))
+            .and_then(|old| old.into_any().downcast::<T>().ok())
+            .map(|old| *old)
+    }
+
+    pub fn get<T, V>(&self, entity: impl Identifiable<V>) -> Option<&T>
+    where
+        T: Any + Clone + Send + Sync,
+    {
+        self.annotations
+            .get(&Self::key::<T, V>(entity))
+            .and_then(|value| (**value).as_any().downcast_ref::<T>())
+    }
+
+    pub fn get_mut<T, V>(&mut self, entity: impl Identifiable<V>) -> Option<&mut T>
+    where
+        T: Any + Clone + Send + Sync,
+    {
+        self.annotations
+            .get_mut(&Self::key::<T, V>(entity))
+            .and_then(|value| (**value).as_any_mut().downcast_mut::<T>())
+    }
+
+    /// The annotation of type `T` attached to `entity`, attaching the
+    /// default value of `T` if there is none.
+    pub fn get_or_default<T, V>(&mut self, entity: impl Identifiable<V>) -> &mut T
+    where
+        T: Any + Clone + Default + Send + Sync,
+    {
+        self.annotations
+            .entry(Self::key::<T, V>(entity))
+            .or_insert_with(|| Box::new(T::default()))
+            .as_any_mut()
+            .downcast_mut::<T>()
+            // unwrap is safe here: the key determines the value's type
+            .unwrap()
+    }
+
+    pub fn remove<T, V>(&mut self, entity: impl Identifiable<V>) -> Option<T>
+    where
+        T: Any + Clone + Send + Sync,
+    {
+        self.annotations
+            .remove(&Self::key::<T, V>(entity))
+            .and_then(|old| old.into_any().downcast::<T>().ok())
+            .map(|old| *old)
+    }
+
+    /// Removes all annotations attached to `entity`.
+    pub fn remove_all<V>(&mut self, entity: impl Identifiable<V>) {
+        let id = entity.id().erase();
+        self.annotations.retain(|(eid, _), _| *eid != id);
+    }
+
+    /// The entities with annotations of type `T`, and their annotations.
+    pub fn iter<T>(&self) -> impl Iterator<Item = (Id<Erased>, &T)>
+    where
+        T: Any + Clone + Send + Sync,
+    {
+        let tid = TypeId::of::<T>();
+        self.annotations
+            .iter()
+            .filter(move |((_, t), _)| *t == tid)
+            .filter_map(|((id, _), value)| Some((*id, (**value).as_any().downcast_ref::<T>()?)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.annotations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.annotations.is_empty()
+    }
+}
@@ -1,5 +1,6 @@
 use std::borrow::Cow;
 use std::cmp::Ordering;
+use std::fmt::{self, Display};
 use std::hash::{Hash, Hasher};
 use std::ops::{Deref, DerefMut};
 
@@ -47,6 +48,12 @@ impl<V> Hash for Entity<V> {
     }
 }
 
+impl<V> Display for Entity<V> where V: Display {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
 impl<V> Deref for Entity<V> {
     type Target = V;
 
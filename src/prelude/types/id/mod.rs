@@ -2,8 +2,42 @@ use ron_uuid::UUID;
 use std::fmt::{self, Display};
 use std::marker::PhantomData;
 
+use thiserror::Error;
+
 use crate::prelude::Erased;
 
+/// The kinds of entity that ids may identify, by the tags given to their
+/// ids (e.g., `"blk"` for `Blk`s); conversions between ids (see
+/// `Id::transmute`) are checked against them.
+pub trait Tagged {
+    const TAG: &'static str;
+
+    /// Returns true if an id tagged `tag` may identify this kind of entity.
+    fn accepts(tag: &str) -> bool {
+        tag == Self::TAG
+    }
+}
+
+impl Tagged for Erased {
+    const TAG: &'static str = "erased";
+
+    // erased ids (and the ids of types, see `types::Type`) may identify
+    // any kind of entity
+    fn accepts(_tag: &str) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum IdError {
+    #[error("id {id} of a `{found}` cannot identify a `{expected}`")]
+    Mismatch {
+        id: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+}
+
 #[derive(educe::Educe)]
 #[educe(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Id<T> {
@@ -46,14 +80,48 @@ impl<T> Id<T> {
     pub fn erase(self) -> Id<Erased> {
         self.transmute::<Erased>()
     }
-    
-    pub fn transmute<U>(self) -> Id<U> {
+
+    /// Converts the id to an id of another kind of entity.
+    ///
+    /// Panics if the id's tag is not accepted by `U`; see `try_transmute`
+    /// for a fallible conversion.
+    pub fn transmute<U: Tagged>(self) -> Id<U> {
+        assert!(
+            U::accepts(self.tag),
+            "id {} of a `{}` cannot identify a `{}`",
+            self,
+            self.tag,
+            U::TAG,
+        );
+        self.transmute_unchecked()
+    }
+
+    /// Converts the id to an id of another kind of entity, if its tag is
+    /// accepted by `U`, e.g., to recover the id of a Blk from an erased id.
+    pub fn try_transmute<U: Tagged>(self) -> Result<Id<U>, IdError> {
+        if U::accepts(self.tag) {
+            Ok(self.transmute_unchecked())
+        } else {
+            Err(IdError::Mismatch {
+                id: self.to_string(),
+                expected: U::TAG,
+                found: self.tag,
+            })
+        }
+    }
+
+    fn transmute_unchecked<U>(self) -> Id<U> {
         Id {
             tag: self.tag,
             uuid: self.uuid,
             marker: PhantomData,
         }
     }
+
+    /// Returns true if the id may identify a `U`.
+    pub fn is<U: Tagged>(&self) -> bool {
+        U::accepts(self.tag)
+    }
     
     pub fn invalid(tag: &'static str) -> Self {
         Self {
@@ -78,4 +146,37 @@ impl<T> Id<T> {
     pub fn uuid(&self) -> UUID {
         self.uuid
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Blk;
+
+    impl Tagged for Blk {
+        const TAG: &'static str = "blk";
+    }
+
+    struct Mem;
+
+    impl Tagged for Mem {
+        const TAG: &'static str = "mem";
+    }
+
+    #[test]
+    fn test_transmute() {
+        let id = Id::<Blk>::new("blk").erase();
+        assert!(id.is::<Blk>());
+        assert!(id.try_transmute::<Blk>().is_ok());
+        assert!(matches!(
+            id.try_transmute::<Mem>(),
+            Err(IdError::Mismatch { expected: "mem", found: "blk", .. })
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot identify a `mem`")]
+    fn test_transmute_mismatch() {
+        Id::<Blk>::new("blk").erase().transmute::<Mem>();
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, OnceLock, RwLock};
+
+// the number of independently locked shards of an Interner
+const SHARDS: usize = 16;
+
+/// A pool of shared strings (e.g., the names of registers, symbols, and
+/// intrinsics), so that each distinct string is allocated once, however
+/// many times it is interned. Interners can be shared between threads:
+/// strings are partitioned between independently locked shards, and
+/// strings already interned are found under a read lock.
+///
+/// The global interner (see `intern`) is used by the constructors of
+/// `Var`s, intrinsic expressions and jmps, and the symbols of Subs lifted
+/// by Projects; strings interned are never freed from it, so it should
+/// not be used for names that are generated without bound.
+pub struct Interner {
+    shards: [RwLock<HashSet<Arc<str>>>; SHARDS],
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        Self {
+            shards: Default::default(),
+        }
+    }
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The global interner.
+    pub fn global() -> &'static Interner {
+        static GLOBAL: OnceLock<Interner> = OnceLock::new();
+        GLOBAL.get_or_init(Interner::new)
+    }
+
+    fn shard(&self, s: &str) -> &RwLock<HashSet<Arc<str>>> {
+        let mut hasher = DefaultHasher::new();
+        s.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+
+    /// The shared copy of `s`, interning it if it has not been seen before.
+    pub fn intern(&self, s: &str) -> Arc<str> {
+        let shard = self.shard(s);
+
+        // a poisoned shard is still consistent: the only writes are inserts
+        if let Some(interned) = shard.read().unwrap_or_else(|e| e.into_inner()).get(s) {
+            return interned.clone()
+        }
+
+        let mut shard = shard.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(interned) = shard.get(s) {
+            return interned.clone()
+        }
+
+        let interned = Arc::<str>::from(s);
+        shard.insert(interned.clone());
+        interned
+    }
+
+    /// The shared copy of `s`, if it has been interned.
+    pub fn get(&self, s: &str) -> Option<Arc<str>> {
+        self.shard(s).read().unwrap_or_else(|e| e.into_inner()).get(s).cloned()
+    }
+
+    /// The number of distinct strings interned.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.read().unwrap_or_else(|e| e.into_inner()).len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops the interner's copies of the strings it holds; copies held
+    /// elsewhere are unaffected, but are no longer shared with strings
+    /// interned later.
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard.write().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+    }
+}
+
+/// Interns `s` with the global interner (see `Interner`).
+pub fn intern(s: &str) -> Arc<str> {
+    Interner::global().intern(s)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_intern() {
+        let interner = Interner::new();
+        let rax = interner.intern("RAX");
+        assert!(Arc::ptr_eq(&rax, &interner.intern(&String::from("RAX"))));
+        assert!(!Arc::ptr_eq(&rax, &interner.intern("RBX")));
+        assert_eq!(interner.len(), 2);
+        assert!(interner.get("RCX").is_none());
+
+        let threads = (0..4)
+            .map(|_| std::thread::spawn(|| intern("__stack_chk_fail")))
+            .collect::<Vec<_>>();
+        let interned = threads.into_iter().map(|t| t.join().unwrap()).collect::<Vec<_>>();
+        assert!(interned.windows(2).all(|w| Arc::ptr_eq(&w[0], &w[1])));
+    }
+}
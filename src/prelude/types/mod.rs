@@ -4,6 +4,9 @@ pub use bytes::{BE, LE, Endian};
 pub use intervals;
 pub use intervals::Interval;
 
+pub mod annotations;
+pub use annotations::Annotations;
+
 pub mod entity;
 pub use entity::{Entity, EntityRef};
 
@@ -11,4 +14,7 @@ pub mod erased;
 pub use erased::Erased;
 
 pub mod id;
-pub use id::Id;
\ No newline at end of file
+pub use id::{Id, IdError, Tagged};
+
+pub mod intern;
+pub use intern::{intern, Interner};
\ No newline at end of file
@@ -0,0 +1,238 @@
+/// Python bindings (with the `python` feature), built as the extension
+/// module `delirium` with the `extension-module` feature and the `cdylib`
+/// crate type (e.g., `cargo rustc --features extension-module
+/// --crate-type cdylib`, or with maturin).
+///
+/// - `ProjectBuilder` builds `Project`s for a language, given the path to
+///   the processor specifications.
+///
+/// - `Project` maps regions of bytes, lifts Blks, and discovers Subs.
+///
+/// - `Sub` and `Blk` are snapshots of the IR of a Project, i.e., they are
+///   not updated when the Project changes; they render with the default
+///   `Printer` via `str`.
+///
+/// - `Printer` renders Subs and Blks with ids and types, if requested.
+///
+/// Addresses are given and returned as ints; ids are given as strings.
+
+use std::borrow::Cow;
+use std::fmt::Display;
+
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::export::JsonExport;
+use crate::ir::{Addr, Blk, Printer, Project, ProjectBuilder, Sub};
+use crate::prelude::{Endian, Entity, Id, Identifiable};
+
+fn error(e: impl Display) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
+
+fn addr(addr: Option<&Addr>) -> PyResult<Option<u64>> {
+    addr.map(|addr| u64::try_from(addr).map_err(|e| PyValueError::new_err(e.to_string())))
+        .transpose()
+}
+
+#[pyclass(name = "ProjectBuilder", unsendable)]
+pub struct PyProjectBuilder(ProjectBuilder);
+
+#[pymethods]
+impl PyProjectBuilder {
+    #[new]
+    #[pyo3(signature = (path, ignore_errors = false))]
+    fn new(path: &str, ignore_errors: bool) -> PyResult<Self> {
+        ProjectBuilder::new_with(path, ignore_errors)
+            .map(Self)
+            .map_err(error)
+    }
+
+    /// Builds a project for `arch` (e.g., `"x86:LE:64:default"`).
+    fn project(&self, name: String, arch: String, convention: &str) -> PyResult<PyProject> {
+        self.0
+            .project(name, arch, convention)
+            .map(PyProject)
+            .map_err(error)
+    }
+}
+
+#[pyclass(name = "Project", unsendable)]
+pub struct PyProject(Entity<Project<'static>>);
+
+#[pymethods]
+impl PyProject {
+    /// Maps `data` at `addr`.
+    #[pyo3(signature = (name, addr, data, big_endian = false))]
+    fn map(&mut self, name: &str, addr: u64, data: Vec<u8>, big_endian: bool) -> PyResult<()> {
+        let endian = if big_endian { Endian::Big } else { Endian::Little };
+        self.0
+            .add_region_mapping_with(name, addr, endian, Cow::Owned(data))
+            .map_err(error)
+    }
+
+    /// Lifts the Blks starting at `addr`.
+    fn add_blk(&mut self, addr: u64) -> PyResult<Vec<PyBlk>> {
+        let ids = self.0.add_blk(addr).map_err(error)?;
+        Ok(self.blks_of(ids))
+    }
+
+    /// Lifts the Blks reachable from `addr`.
+    fn explore_from(&mut self, addr: u64) -> PyResult<Vec<PyBlk>> {
+        let ids = self.0.explore_from(addr).map_err(error)?;
+        Ok(self.blks_of(ids))
+    }
+
+    fn add_sub(&mut self, addr: u64) -> Option<PySub> {
+        let id = self.0.add_sub(addr)?;
+        self.0.sub(id).cloned().map(PySub)
+    }
+
+    fn discover_subs(&mut self) -> Vec<PySub> {
+        let ids = self.0.discover_subs();
+        ids.into_iter()
+            .filter_map(|id| self.0.sub(id).cloned().map(PySub))
+            .collect()
+    }
+
+    fn subs(&self) -> Vec<PySub> {
+        self.0.subs().cloned().map(PySub).collect()
+    }
+
+    fn sub_at(&self, addr: u64) -> Option<PySub> {
+        self.0.sub_at(Addr::from(addr)).cloned().map(PySub)
+    }
+
+    fn sub_by_symbol(&self, symbol: &str) -> Option<PySub> {
+        self.0.sub_by_symbol(symbol).cloned().map(PySub)
+    }
+
+    fn blks(&self) -> Vec<PyBlk> {
+        self.0.blks().cloned().map(PyBlk).collect()
+    }
+}
+
+impl PyProject {
+    // the Blks of ids, in their order
+    fn blks_of(&self, ids: Vec<Id<Blk>>) -> Vec<PyBlk> {
+        ids.into_iter()
+            .filter_map(|id| self.0.blk(id).cloned().map(PyBlk))
+            .collect()
+    }
+}
+
+#[pyclass(name = "Sub", unsendable)]
+#[derive(Clone)]
+pub struct PySub(Entity<Sub>);
+
+#[pymethods]
+impl PySub {
+    #[getter]
+    fn id(&self) -> String {
+        self.0.id().to_string()
+    }
+
+    #[getter]
+    fn symbol(&self) -> Option<String> {
+        self.0.symbol().map(|symbol| symbol.to_string())
+    }
+
+    #[getter]
+    fn addr(&self) -> PyResult<Option<u64>> {
+        addr(self.0.addr())
+    }
+
+    #[getter]
+    fn blks(&self) -> Vec<PyBlk> {
+        self.0.blks().iter().cloned().map(PyBlk).collect()
+    }
+
+    /// The Sub as a JSON document (see `JsonExport`).
+    fn to_json(&self) -> String {
+        JsonExport::new().document([&self.0]).to_string()
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.blks().len()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+#[pyclass(name = "Blk", unsendable)]
+#[derive(Clone)]
+pub struct PyBlk(Entity<Blk>);
+
+#[pymethods]
+impl PyBlk {
+    #[getter]
+    fn id(&self) -> String {
+        self.0.id().to_string()
+    }
+
+    #[getter]
+    fn addr(&self) -> PyResult<Option<u64>> {
+        addr(self.0.addr())
+    }
+
+    #[getter]
+    fn is_landing_pad(&self) -> bool {
+        self.0.is_landing_pad()
+    }
+
+    /// The statements of the Blk, rendered with the default Printer.
+    fn stmts(&self) -> Vec<String> {
+        let printer = Printer::new();
+        let phis = self.0.phis().iter().map(|phi| printer.phi(phi));
+        let defs = self.0.defs().iter().map(|def| printer.def(def));
+        let jmps = self.0.jmps().iter().map(|jmp| printer.jmp(jmp));
+        phis.chain(defs).chain(jmps).collect()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+#[pyclass(name = "Printer", unsendable)]
+pub struct PyPrinter {
+    ids: bool,
+    types: bool,
+}
+
+#[pymethods]
+impl PyPrinter {
+    #[new]
+    #[pyo3(signature = (ids = false, types = false))]
+    fn new(ids: bool, types: bool) -> Self {
+        Self { ids, types }
+    }
+
+    fn sub(&self, sub: &PySub) -> String {
+        self.printer().sub(&sub.0)
+    }
+
+    fn blk(&self, blk: &PyBlk) -> String {
+        self.printer().blk(&blk.0)
+    }
+}
+
+impl PyPrinter {
+    fn printer(&self) -> Printer<'static, 'static> {
+        let mut printer = Printer::new();
+        printer.ids(self.ids).types(self.types);
+        printer
+    }
+}
+
+#[pymodule]
+fn delirium(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyProjectBuilder>()?;
+    m.add_class::<PyProject>()?;
+    m.add_class::<PySub>()?;
+    m.add_class::<PyBlk>()?;
+    m.add_class::<PyPrinter>()?;
+    Ok(())
+}
@@ -0,0 +1,237 @@
+/// Symbolic execution of our IR/IL.
+///
+/// - `SymState` treats the variables it does not bind as symbolic, and
+///   evaluates expressions and executes defs over them, accumulating the
+///   conditions of the paths taken.
+///
+/// - `smtlib` exports conditions over the IR's expressions as SMT-LIB2
+///   scripts, for use with any solver; with the `z3` feature, scripts can
+///   be solved directly (see `SmtScript::solve`).
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::analysis::cache::CacheStats;
+use crate::analysis::memo::{EnvVersion, ExprCache};
+use crate::ir::{BinRel, BitVec, Def, Expr, Jmp, Loc, Var};
+
+pub mod smtlib;
+pub use smtlib::{SmtError, SmtScript, SmtSymbol};
+
+#[cfg(feature = "z3")]
+mod z3;
+
+/// A symbolic state: the values of the variables bound to expressions
+/// (other variables are symbolic, i.e., stand for themselves), the stores
+/// performed, and the conditions of the path taken to reach the state.
+///
+/// Loads are resolved to the value of the most recent store to the same
+/// address (compared syntactically, after simplification) of the same
+/// size; stores to other addresses are assumed not to alias, and loads of
+/// addresses not stored to remain loads.
+///
+/// The values of expressions are cached until the bindings or stores of
+/// the state change; clones of a state share their cache, so states forked
+/// from a common prefix of a path do not re-simplify the same expressions.
+#[derive(Clone, Default)]
+pub struct SymState {
+    values: BTreeMap<Var, Expr>,
+    stores: Vec<(Expr, Expr, u32)>,
+    conditions: Vec<Expr>,
+    env: EnvVersion,
+    cache: Arc<Mutex<ExprCache>>,
+}
+
+impl SymState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds `var` to `value`; returns its previous value, if bound.
+    pub fn bind(&mut self, var: impl Into<Var>, value: impl Into<Expr>) -> Option<Expr> {
+        self.changed();
+        self.values.insert(var.into(), value.into())
+    }
+
+    /// Treats `var` as symbolic; returns its previous value, if bound.
+    pub fn make_symbolic(&mut self, var: &Var) -> Option<Expr> {
+        self.changed();
+        self.values.remove(var)
+    }
+
+    pub fn value(&self, var: &Var) -> Option<&Expr> {
+        self.values.get(var)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = (&Var, &Expr)> {
+        self.values.iter()
+    }
+
+    /// The value of `expr` in the state: bound variables and resolved loads
+    /// are substituted, and the result simplified.
+    pub fn eval(&self, expr: &Expr) -> Expr {
+        if let Some(value) = self.cache().get(self.env, expr) {
+            return value.clone()
+        }
+
+        // the cache is not held while substituting, as resolving loads
+        // evaluates their addresses
+        let value = self.substitute(expr);
+
+        let mut cache = self.cache();
+        let value = Expr::clone(&cache.simplify(&value));
+        cache.insert(self.env, expr, value.clone());
+        value
+    }
+
+    /// The hits and misses of the cache of the values of expressions.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache().stats()
+    }
+
+    fn cache(&self) -> MutexGuard<'_, ExprCache> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn changed(&mut self) {
+        self.env = EnvVersion::fresh();
+    }
+
+    fn substitute(&self, expr: &Expr) -> Expr {
+        match expr {
+            Expr::Var(var) => self.values.get(var).cloned().unwrap_or_else(|| expr.clone()),
+            Expr::Load(addr, bits, mem) => {
+                let addr = self.eval(addr);
+                self.stores
+                    .iter()
+                    .rev()
+                    .find(|(saddr, _, sbits)| *saddr == addr && sbits == bits)
+                    .map(|(_, value, _)| value.clone())
+                    .unwrap_or_else(|| Expr::load(addr, *bits, mem.clone()))
+            }
+            _ => {
+                let mut expr = expr.clone();
+                for operand in expr.operands_mut() {
+                    *operand = self.substitute(operand);
+                }
+                expr
+            }
+        }
+    }
+
+    /// Records a store of the `bits`-bit `value` at `addr`, e.g., to
+    /// initialise memory.
+    pub fn store(&mut self, addr: impl Into<Expr>, value: impl Into<Expr>, bits: u32) {
+        let addr = self.eval(&addr.into());
+        let value = self.eval(&value.into());
+        self.changed();
+        self.stores.push((addr, value, bits));
+    }
+
+    pub fn exec_def(&mut self, def: &Def) {
+        match def {
+            Def::Assign(var, expr) => {
+                let value = self.eval(expr);
+                self.changed();
+                self.values.insert(var.clone(), value);
+            }
+            Def::Assume(cond) => {
+                let cond = self.eval(cond);
+                self.add_condition(cond);
+            }
+            Def::Store(addr, value, bits, _) => {
+                let addr = self.eval(addr);
+                let value = self.eval(value);
+                self.changed();
+                self.stores.push((addr, value, *bits));
+            }
+        }
+    }
+
+    /// Follows `jmp`, assuming that it is taken (or not, if `taken` is
+    /// false); only conditional branches can be not taken. Returns the
+    /// value of its target, if it is taken and has a computed target.
+    pub fn follow(&mut self, jmp: &Jmp, taken: bool) -> Option<Expr> {
+        let loc = match jmp {
+            Jmp::CBranch(loc, cond) => {
+                let cond = self.eval(cond);
+                if taken {
+                    self.add_condition(cond);
+                } else {
+                    let bits = cond.bits() as usize;
+                    self.add_condition(Expr::bin_rel(BinRel::Eq, cond, BitVec::zero(bits)));
+                }
+                loc
+            }
+            Jmp::Branch(loc) | Jmp::Call(loc, _) | Jmp::Return(loc) => loc,
+            Jmp::Intrinsic(_, _) => return None,
+        };
+
+        match loc {
+            Loc::Computed(expr) if taken => Some(self.eval(expr)),
+            _ => None,
+        }
+    }
+
+    /// Adds `cond` (which holds when non-zero) to the path condition;
+    /// conditions that are trivially true are not recorded.
+    pub fn add_condition(&mut self, cond: Expr) {
+        match cond {
+            Expr::Val(ref bv) if !bv.is_zero() => (),
+            _ => self.conditions.push(cond),
+        }
+    }
+
+    pub fn conditions(&self) -> &[Expr] {
+        &self.conditions
+    }
+
+    /// The path condition is trivially unsatisfiable (i.e., a condition
+    /// simplified to false).
+    pub fn is_infeasible(&self) -> bool {
+        self.conditions
+            .iter()
+            .any(|cond| matches!(cond, Expr::Val(bv) if bv.is_zero()))
+    }
+
+    /// A script asserting the path condition.
+    pub fn to_smtlib(&self) -> Result<SmtScript, SmtError> {
+        let mut script = SmtScript::new();
+        for cond in self.conditions.iter() {
+            script.assert(cond)?;
+        }
+        Ok(script)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::bv::BitVecT;
+
+    #[test]
+    fn test_path_condition() -> Result<(), SmtError> {
+        let typ = BitVecT::with_bits(32, false);
+        let (w, x, y, z) = (
+            Var::transient("w", typ),
+            Var::transient("x", typ),
+            Var::transient("y", typ),
+            Var::transient("z", typ),
+        );
+
+        let mut state = SymState::new();
+        state.bind(x.clone(), Expr::from(y.clone()));
+        state.exec_def(&Def::assign(z.clone(), Expr::from(x.clone())));
+        assert!(state.value(&z) == Some(&Expr::from(y.clone())));
+
+        let jmp = Jmp::cbranch(Loc::Computed(Expr::from(z.clone())), Expr::bin_rel(BinRel::SLt, z, w));
+        assert!(state.follow(&jmp, false).is_none());
+
+        let script = state.to_smtlib()?.to_string();
+        assert!(script.contains("(declare-const |y:32.0| (_ BitVec 32))"));
+        assert!(script.contains("(bvslt |y:32.0| |w:32.0|)"));
+        assert!(!script.contains("|x:32.0|"));
+
+        Ok(())
+    }
+}
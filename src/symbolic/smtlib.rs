@@ -0,0 +1,388 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+use thiserror::Error;
+
+use crate::ir::{BinOp, BinRel, BitVec, Cast, Expr, UnOp, Var};
+use crate::prelude::Endian;
+
+#[derive(Debug, Error)]
+pub enum SmtError {
+    #[error("expression {0:?} cannot be expressed in QF_BV")]
+    Unsupported(Expr),
+    #[error("memory is not modelled by arrays")]
+    NoMemory,
+    #[error("solver error: {0}")]
+    Solver(String),
+}
+
+/// A declaration of a free variable of an SmtScript.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SmtSymbol {
+    /// A variable of the IR
+    Var(Var),
+    /// An abstraction of a load from memory
+    Load(Expr),
+    /// A memory of the IR, as an array of bytes
+    Memory(Var),
+}
+
+/// An SMT-LIB2 script (in the QF_BV logic) asserting conditions over the
+/// IR's expressions, e.g., the path condition of a `SymState`.
+///
+/// Variables are declared as bit-vector constants named by their
+/// `Display` form (e.g., `|RAX:64.0|`). Booleans are represented as in the
+/// IR, i.e., as bytes that are non-zero when true; a condition asserted
+/// holds when it is non-zero. Loads are abstracted as fresh constants (one
+/// per distinct load expression), hence a script may be satisfiable when
+/// no memory contents satisfy it.
+///
+/// When memory is modelled by arrays (see `memory`), the script is in the
+/// QF_ABV logic: memories are declared as arrays from addresses to bytes
+/// (e.g., `|M.0|`), and loads select their bytes from them.
+#[derive(Debug, Clone, Default)]
+pub struct SmtScript {
+    decls: BTreeMap<String, (SmtSymbol, u32)>,
+    loads: BTreeMap<Expr, String>,
+    asserts: Vec<String>,
+    // the size of addresses and the byte order of memory, if it is
+    // modelled by arrays
+    memory: Option<(u32, Endian)>,
+}
+
+impl SmtScript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Model memory by arrays indexed by addresses of `addr_bits` bits,
+    /// rather than abstracting loads; multi-byte values are loaded and
+    /// stored in the byte order given by `endian`.
+    pub fn memory(&mut self, addr_bits: u32, endian: Endian) -> &mut Self {
+        self.memory = Some((addr_bits, endian));
+        self
+    }
+
+    /// The script uses arrays, i.e., is in the QF_ABV logic.
+    pub fn has_arrays(&self) -> bool {
+        self.decls
+            .values()
+            .any(|(symbol, _)| matches!(symbol, SmtSymbol::Memory(_)))
+    }
+
+    /// Asserts that `cond` is non-zero.
+    pub fn assert(&mut self, cond: &Expr) -> Result<&mut Self, SmtError> {
+        let term = self.term(cond)?;
+        self.asserts.push(format!("(distinct {} {})", term, zero(cond.bits())));
+        Ok(self)
+    }
+
+    /// The term for `expr`, declaring the variables it refers to.
+    pub fn term(&mut self, expr: &Expr) -> Result<String, SmtError> {
+        let unsupported = || SmtError::Unsupported(expr.clone());
+
+        Ok(match expr {
+            Expr::Val(bv) => val(bv),
+            Expr::Var(var) => {
+                let name = format!("|{}|", var);
+                let bits = var.bits().ok_or_else(unsupported)?;
+                self.decls.insert(name.clone(), (SmtSymbol::Var(var.clone()), bits));
+                name
+            }
+            Expr::UnOp(op, iexpr) => {
+                let term = self.term(iexpr)?;
+                let bits = iexpr.bits();
+                match op {
+                    UnOp::Not => format!("(bvnot {})", term),
+                    UnOp::Neg => format!("(bvneg {})", term),
+                    UnOp::Abs => format!("(ite (bvslt {0} {1}) (bvneg {0}) {0})", term, zero(bits)),
+                    UnOp::PopCount => {
+                        let bits = (0..bits)
+                            .map(|i| format!("((_ zero_extend {}) ((_ extract {1} {1}) {2}))", bits - 1, i, term))
+                            .collect::<Vec<_>>();
+                        match bits.len() {
+                            0 => return Err(unsupported()),
+                            1 => bits[0].clone(),
+                            _ => format!("(bvadd {})", bits.join(" ")),
+                        }
+                    }
+                    UnOp::Sqrt | UnOp::Ceiling | UnOp::Floor | UnOp::Round => return Err(unsupported()),
+                }
+            }
+            Expr::BinOp(op, lexpr, rexpr) => {
+                let lterm = self.term(lexpr)?;
+                let rterm = resize(self.term(rexpr)?, rexpr.bits(), lexpr.bits());
+                let op = match op {
+                    BinOp::And => "bvand",
+                    BinOp::Or => "bvor",
+                    BinOp::Xor => "bvxor",
+                    BinOp::Add => "bvadd",
+                    BinOp::Sub => "bvsub",
+                    BinOp::Mul => "bvmul",
+                    BinOp::Div => "bvudiv",
+                    BinOp::SDiv => "bvsdiv",
+                    BinOp::Rem => "bvurem",
+                    BinOp::SRem => "bvsrem",
+                    BinOp::Shl => "bvshl",
+                    BinOp::Shr => "bvlshr",
+                    BinOp::Sar => "bvashr",
+                };
+                format!("({} {} {})", op, lterm, rterm)
+            }
+            Expr::BinRel(op, lexpr, rexpr) => {
+                let bits = lexpr.bits();
+                let (l, r) = (self.term(lexpr)?, self.term(rexpr)?);
+                let sign = |term: &str| format!("((_ extract {0} {0}) {1})", bits - 1, term);
+                let rel = match op {
+                    BinRel::Eq => format!("(= {} {})", l, r),
+                    BinRel::Neq => format!("(distinct {} {})", l, r),
+                    BinRel::Lt => format!("(bvult {} {})", l, r),
+                    BinRel::Le => format!("(bvule {} {})", l, r),
+                    BinRel::SLt => format!("(bvslt {} {})", l, r),
+                    BinRel::SLe => format!("(bvsle {} {})", l, r),
+                    BinRel::Carry => format!("(bvult (bvadd {0} {1}) {0})", l, r),
+                    BinRel::SCarry => format!(
+                        "(and (= {} {}) (distinct {} {}))",
+                        sign(&l),
+                        sign(&r),
+                        sign(&l),
+                        sign(&format!("(bvadd {} {})", l, r)),
+                    ),
+                    BinRel::SBorrow => format!(
+                        "(and (distinct {} {}) (distinct {} {}))",
+                        sign(&l),
+                        sign(&r),
+                        sign(&l),
+                        sign(&format!("(bvsub {} {})", l, r)),
+                    ),
+                };
+                boolean(rel)
+            }
+            Expr::Cast(iexpr, cast) => {
+                let term = self.term(iexpr)?;
+                let bits = iexpr.bits();
+                match *cast {
+                    Cast::Bool => boolean(format!("(distinct {} {})", term, zero(bits))),
+                    Cast::Signed(nbits) if nbits > bits => {
+                        format!("((_ sign_extend {}) {})", nbits - bits, term)
+                    }
+                    Cast::Signed(nbits) | Cast::Unsigned(nbits) | Cast::Low(nbits) => resize(term, bits, nbits),
+                    Cast::High(nbits) if nbits <= bits && nbits > 0 => {
+                        format!("((_ extract {} {}) {})", bits - 1, bits - nbits, term)
+                    }
+                    Cast::High(_) | Cast::Float(_) => return Err(unsupported()),
+                }
+            }
+            Expr::Extract(iexpr, lsb, msb) if msb > lsb => {
+                format!("((_ extract {} {}) {})", msb - 1, lsb, self.term(iexpr)?)
+            }
+            Expr::Concat(lexpr, rexpr) => format!("(concat {} {})", self.term(lexpr)?, self.term(rexpr)?),
+            Expr::IfElse(cond, texpr, fexpr) => format!(
+                "(ite (distinct {} {}) {} {})",
+                self.term(cond)?,
+                zero(cond.bits()),
+                self.term(texpr)?,
+                self.term(fexpr)?,
+            ),
+            Expr::Load(addr, bits, mem) if self.memory.is_some() => {
+                // unwrap is safe here: we know that memory is modelled
+                let (addr_bits, endian) = self.memory.unwrap();
+                if *bits == 0 || bits % 8 != 0 {
+                    return Err(unsupported())
+                }
+
+                let array = self.array(mem, addr_bits);
+                let addr = resize(self.term(addr)?, addr.bits(), addr_bits);
+
+                // bytes are concatenated most significant first
+                let mut bytes = (0..bits / 8)
+                    .map(|i| format!("(select {} {})", array, offset(&addr, i, addr_bits)))
+                    .collect::<Vec<_>>();
+                if endian.is_little() {
+                    bytes.reverse();
+                }
+                concat(bytes)
+            }
+            Expr::Load(_, bits, _) => {
+                if let Some(name) = self.loads.get(expr) {
+                    return Ok(name.clone())
+                }
+                let name = format!("|load!{}|", self.loads.len());
+                self.loads.insert(expr.clone(), name.clone());
+                self.decls.insert(name.clone(), (SmtSymbol::Load(expr.clone()), *bits));
+                name
+            }
+            Expr::Extract(_, _, _) | Expr::UnRel(_, _) | Expr::Intrinsic(_, _, _) => return Err(unsupported()),
+        })
+    }
+
+    /// The array holding the contents of `mem` after storing the `bits`
+    /// least significant bits of `val` at `addr` (i.e., the effect of a
+    /// `Def::Store`); memory must be modelled by arrays.
+    pub fn store(&mut self, addr: &Expr, val: &Expr, bits: u32, mem: &Var) -> Result<String, SmtError> {
+        let (addr_bits, endian) = self.memory.ok_or(SmtError::NoMemory)?;
+        if bits == 0 || bits % 8 != 0 {
+            return Err(SmtError::Unsupported(val.clone()))
+        }
+
+        let array = self.array(mem, addr_bits);
+        let addr = resize(self.term(addr)?, addr.bits(), addr_bits);
+        let val = resize(self.term(val)?, val.bits(), bits);
+
+        Ok((0..bits / 8).fold(array, |array, i| {
+            let lsb = if endian.is_little() { i * 8 } else { bits - (i + 1) * 8 };
+            format!(
+                "(store {} {} ((_ extract {} {}) {}))",
+                array,
+                offset(&addr, i, addr_bits),
+                lsb + 7,
+                lsb,
+                val,
+            )
+        }))
+    }
+
+    // the array for mem, declaring it
+    fn array(&mut self, mem: &Var, addr_bits: u32) -> String {
+        let name = format!("|{}|", mem);
+        self.decls.insert(name.clone(), (SmtSymbol::Memory(mem.clone()), addr_bits));
+        name
+    }
+
+    /// The free variables of the script, with their sizes in bits; the
+    /// size of a memory is that of its addresses.
+    pub fn symbols(&self) -> impl Iterator<Item = (&str, &SmtSymbol, u32)> {
+        self.decls
+            .iter()
+            .map(|(name, (symbol, bits))| (name.trim_matches('|'), symbol, *bits))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.asserts.is_empty()
+    }
+
+    // the declarations and assertions of the script, without commands
+    pub(crate) fn write_body(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        for (name, (symbol, bits)) in self.decls.iter() {
+            if let SmtSymbol::Memory(_) = symbol {
+                writeln!(f, "(declare-const {} (Array (_ BitVec {}) (_ BitVec 8)))", name, bits)?;
+            } else {
+                writeln!(f, "(declare-const {} (_ BitVec {}))", name, bits)?;
+            }
+        }
+        for assert in self.asserts.iter() {
+            writeln!(f, "(assert {})", assert)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for SmtScript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "(set-logic {})", if self.has_arrays() { "QF_ABV" } else { "QF_BV" })?;
+        self.write_body(f)?;
+        writeln!(f, "(check-sat)")?;
+        writeln!(f, "(get-model)")
+    }
+}
+
+fn val(bv: &BitVec) -> String {
+    let bits = bv.bits();
+    let value = bv.clone().unsigned();
+
+    if let Some(value) = value.to_u64().filter(|_| bits <= 64) {
+        format!("(_ bv{} {})", value, bits)
+    } else if bits % 8 == 0 {
+        let mut bytes = vec![0u8; bits / 8];
+        value.to_be_bytes(&mut bytes);
+        let digits = bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+        format!("#x{}", digits)
+    } else {
+        let digits = (0..bits as u32)
+            .rev()
+            .map(|i| if (value.clone() >> i).unsigned_cast(1).is_zero() { '0' } else { '1' })
+            .collect::<String>();
+        format!("#b{}", digits)
+    }
+}
+
+impl Expr {
+    /// The SMT-LIB2 term for the expression, declaring the variables it
+    /// refers to in `ctx` (see `SmtScript::term`).
+    pub fn to_smtlib(&self, ctx: &mut SmtScript) -> Result<String, SmtError> {
+        ctx.term(self)
+    }
+}
+
+fn zero(bits: u32) -> String {
+    format!("(_ bv0 {})", bits)
+}
+
+// booleans are bytes, as in the IR
+fn boolean(rel: String) -> String {
+    format!("(ite {} (_ bv1 8) (_ bv0 8))", rel)
+}
+
+// the address i bytes after addr
+fn offset(addr: &str, i: u32, addr_bits: u32) -> String {
+    if i == 0 {
+        addr.to_owned()
+    } else {
+        format!("(bvadd {} (_ bv{} {}))", addr, i, addr_bits)
+    }
+}
+
+// concatenates terms, most significant first; concat is binary
+fn concat(mut terms: Vec<String>) -> String {
+    let last = terms.pop().unwrap_or_default();
+    terms
+        .into_iter()
+        .rev()
+        .fold(last, |rest, term| format!("(concat {} {})", term, rest))
+}
+
+// zero-extends or truncates term from bits to nbits
+fn resize(term: String, bits: u32, nbits: u32) -> String {
+    if nbits > bits {
+        format!("((_ zero_extend {}) {})", nbits - bits, term)
+    } else if nbits < bits && nbits > 0 {
+        format!("((_ extract {} 0) {})", nbits - 1, term)
+    } else {
+        term
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::Mem;
+    use crate::types::bv::BitVecT;
+
+    #[test]
+    fn test_memory_arrays() -> Result<(), SmtError> {
+        let typ = BitVecT::with_bits(32, false);
+        let ptr = Var::transient("p", typ);
+        let mem = Var::memory(&Mem::new("M"));
+
+        let mut script = SmtScript::new();
+        script.memory(32, Endian::Little);
+
+        let load = Expr::load(ptr.clone(), 16, mem.clone());
+        assert_eq!(
+            load.to_smtlib(&mut script)?,
+            "(concat (select |M.0| (bvadd |p:32.0| (_ bv1 32))) (select |M.0| |p:32.0|))",
+        );
+
+        let store = script.store(&Expr::from(ptr.clone()), &Expr::from(ptr), 16, &mem)?;
+        assert_eq!(
+            store,
+            "(store (store |M.0| |p:32.0| ((_ extract 7 0) ((_ extract 15 0) |p:32.0|))) \
+             (bvadd |p:32.0| (_ bv1 32)) ((_ extract 15 8) ((_ extract 15 0) |p:32.0|)))",
+        );
+
+        assert!(script.has_arrays());
+        assert!(script.to_string().contains("(declare-const |M.0| (Array (_ BitVec 32) (_ BitVec 8)))"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::BTreeMap;
+
+use z3::ast::{Ast, BV};
+use z3::{Config, Context, SatResult, Solver};
+
+use crate::ir::BitVec;
+use crate::symbolic::{SmtError, SmtScript, SmtSymbol};
+
+impl SmtScript {
+    /// Solves the script using Z3; returns a value for each of its free
+    /// variables (see `symbols`) satisfying its assertions, or None if
+    /// they are unsatisfiable.
+    pub fn solve(&self) -> Result<Option<BTreeMap<String, (SmtSymbol, BitVec)>>, SmtError> {
+        let mut body = String::new();
+        self.write_body(&mut body)
+            .map_err(|e| SmtError::Solver(e.to_string()))?;
+
+        let config = Config::new();
+        let ctx = Context::new(&config);
+        let solver = Solver::new(&ctx);
+        solver.from_string(body);
+
+        match solver.check() {
+            SatResult::Sat => (),
+            SatResult::Unsat => return Ok(None),
+            SatResult::Unknown => {
+                let reason = solver.get_reason_unknown().unwrap_or_else(|| "unknown".to_owned());
+                return Err(SmtError::Solver(reason))
+            }
+        }
+
+        let model = solver
+            .get_model()
+            .ok_or_else(|| SmtError::Solver("no model".to_owned()))?;
+
+        let mut values = BTreeMap::new();
+        for (name, symbol, bits) in self.symbols() {
+            // memories have no value as a bit-vector
+            if let SmtSymbol::Memory(_) = symbol {
+                continue
+            }
+            let constant = BV::new_const(&ctx, name, bits);
+            let value = model
+                .eval(&constant, true)
+                .ok_or_else(|| SmtError::Solver(format!("no value for {}", name)))?;
+
+            // values are read 64 bits at a time, most significant first
+            let mut result: Option<BitVec> = None;
+            let mut hi = bits;
+            while hi > 0 {
+                let lo = hi.saturating_sub(64);
+                let chunk = value
+                    .extract(hi - 1, lo)
+                    .simplify()
+                    .as_u64()
+                    .ok_or_else(|| SmtError::Solver(format!("no value for {}", name)))?;
+                let chunk = BitVec::from_u64(chunk, bits as usize);
+                result = Some(match result {
+                    Some(result) => (result << (hi - lo)) | chunk,
+                    None => chunk,
+                });
+                hi = lo;
+            }
+            let value = result.unwrap_or_else(|| BitVec::zero(bits as usize));
+
+            values.insert(name.to_owned(), (symbol.clone(), value));
+        }
+
+        Ok(Some(values))
+    }
+}
@@ -0,0 +1,33 @@
+use crate::prelude::{Id, Identifiable};
+use crate::types::{Type, derived_type_id};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ArrayT {
+    id: Id<Type>,
+    element: Id<Type>,
+    count: u64,
+}
+
+impl ArrayT {
+    pub fn new(element: Id<Type>, count: u64) -> Self {
+        Self {
+            id: derived_type_id(&format!("array/{}/{}", element, count)),
+            element,
+            count,
+        }
+    }
+
+    pub fn element_type(&self) -> Id<Type> {
+        self.element
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl Identifiable<Type> for ArrayT {
+    fn id(&self) -> Id<Type> {
+        self.id
+    }
+}
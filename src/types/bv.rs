@@ -34,6 +34,31 @@ impl BitVecT {
             bits,
         }
     }
+
+    // types for non-standard sizes derive their ids from their size
+    pub const fn with_bits(bits: u32, signed: bool) -> Self {
+        match (bits, signed) {
+            (8, false) => U8,
+            (16, false) => U16,
+            (32, false) => U32,
+            (64, false) => U64,
+            (128, false) => U128,
+            (256, false) => U256,
+            (512, false) => U512,
+            (8, true) => I8,
+            (16, true) => I16,
+            (32, true) => I32,
+            (64, true) => I64,
+            (128, true) => I128,
+            (256, true) => I256,
+            (512, true) => I512,
+            _ => Self::new(bits, signed, 0xb17ec7_0000000000 | ((signed as u64) << 32) | bits as u64),
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.signed
+    }
 }
 
 impl Identifiable<Type> for BitVecT {
@@ -0,0 +1,871 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::prelude::{Id, Identifiable};
+use crate::types::{Type, TypeSort, type_id, type_key};
+use crate::types::array::ArrayT;
+use crate::types::bool::{BoolT, BOOL};
+use crate::types::bv::{self, BitVecT};
+use crate::types::enumeration::EnumT;
+use crate::types::float::{self, FloatT};
+use crate::types::function::FunctionT;
+use crate::types::pointer::PointerT;
+use crate::types::structure::{Field, StructT, UnionT};
+
+#[derive(Debug, Error)]
+pub enum TypeError {
+    #[error("type {0} is not known")]
+    Unknown(Id<Type>),
+    #[error("type {0} is declared but not defined")]
+    Incomplete(Id<Type>),
+    #[error("type {0} contains itself")]
+    Recursive(Id<Type>),
+    #[error("type {0} has no size")]
+    Unsized(Id<Type>),
+    #[error("type {0} is too large")]
+    TooLarge(Id<Type>),
+    #[error("type {0} is already defined differently")]
+    Redefined(Id<Type>),
+    #[error("type name `{0}` already refers to a different type")]
+    NameTaken(Arc<str>),
+    #[error("line {0}: {1}")]
+    Json(usize, #[source] serde_json::Error),
+    #[error("line {0}: malformed type {1}")]
+    Malformed(usize, String),
+}
+
+/// A type known to a `TypeDatabase`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeDef {
+    Bool,
+    BitVec(BitVecT),
+    Float(FloatT),
+    Pointer(PointerT),
+    Array(ArrayT),
+    Struct(StructT),
+    Union(UnionT),
+    Enum(EnumT),
+    Function(FunctionT),
+}
+
+impl Identifiable<Type> for TypeDef {
+    fn id(&self) -> Id<Type> {
+        match self {
+            Self::Bool => BOOL.id(),
+            Self::BitVec(t) => t.id(),
+            Self::Float(t) => t.id(),
+            Self::Pointer(t) => t.id(),
+            Self::Array(t) => t.id(),
+            Self::Struct(t) => t.id(),
+            Self::Union(t) => t.id(),
+            Self::Enum(t) => t.id(),
+            Self::Function(t) => t.id(),
+        }
+    }
+}
+
+impl TypeDef {
+    // the name the type can be looked up by, if it is not anonymous
+    fn name(&self) -> Option<Arc<str>> {
+        match self {
+            Self::Bool => Some(Arc::from(&*BOOL.name())),
+            Self::BitVec(t) => Some(Arc::from(&*t.name())),
+            Self::Float(t) => Some(Arc::from(&*t.name())),
+            Self::Struct(t) => t.name().map(|name| Arc::from(format!("struct {}", name))),
+            Self::Union(t) => t.name().map(|name| Arc::from(format!("union {}", name))),
+            Self::Enum(t) => t.name().map(|name| Arc::from(format!("enum {}", name))),
+            Self::Pointer(_) | Self::Array(_) | Self::Function(_) => None,
+        }
+    }
+}
+
+impl From<BoolT> for TypeDef {
+    fn from(_: BoolT) -> Self {
+        Self::Bool
+    }
+}
+
+impl From<BitVecT> for TypeDef {
+    fn from(t: BitVecT) -> Self {
+        Self::BitVec(t)
+    }
+}
+
+impl From<FloatT> for TypeDef {
+    fn from(t: FloatT) -> Self {
+        Self::Float(t)
+    }
+}
+
+impl From<PointerT> for TypeDef {
+    fn from(t: PointerT) -> Self {
+        Self::Pointer(t)
+    }
+}
+
+impl From<ArrayT> for TypeDef {
+    fn from(t: ArrayT) -> Self {
+        Self::Array(t)
+    }
+}
+
+impl From<StructT> for TypeDef {
+    fn from(t: StructT) -> Self {
+        Self::Struct(t)
+    }
+}
+
+impl From<UnionT> for TypeDef {
+    fn from(t: UnionT) -> Self {
+        Self::Union(t)
+    }
+}
+
+impl From<EnumT> for TypeDef {
+    fn from(t: EnumT) -> Self {
+        Self::Enum(t)
+    }
+}
+
+impl From<FunctionT> for TypeDef {
+    fn from(t: FunctionT) -> Self {
+        Self::Function(t)
+    }
+}
+
+/// The size and alignment of a type, in bytes, and the offsets of its
+/// fields (for structs) or members (for unions).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    size: u64,
+    align: u64,
+    offsets: Vec<u64>,
+}
+
+impl Layout {
+    fn scalar(size: u64, align: u64) -> Self {
+        Self { size, align, offsets: Vec::new() }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn align(&self) -> u64 {
+        self.align
+    }
+
+    pub fn offsets(&self) -> &[u64] {
+        &self.offsets
+    }
+}
+
+fn round_up(value: u64, align: u64) -> Option<u64> {
+    let rem = value % align;
+    if rem == 0 {
+        Some(value)
+    } else {
+        value.checked_add(align - rem)
+    }
+}
+
+/// Interns types by id, and computes their layouts.
+///
+/// As the ids of types are derived from their definitions (or, for named
+/// composite types, their names), interning a type twice yields the same
+/// id. Types may refer to types that are declared (see `declare_struct`)
+/// but not yet defined, e.g., a struct with a pointer to itself.
+///
+/// Types can be looked up by name (see `lookup`): primitives by their
+/// names (e.g., `u32`), named composite types by their C-like names (e.g.,
+/// `struct node`, or just `node`, if unambiguous), and any type by the
+/// aliases given to it (see `alias`), as for C's typedefs.
+///
+/// Layouts follow the C rules: fields are aligned to their natural
+/// alignment (their size, rounded up to a power of two, and at most
+/// `max_align`), unless their structs are packed or their offsets are
+/// fixed.
+///
+/// The ids of types are derived from stable keys (see `type_key`), so a
+/// database can be written to, and read from, JSON lines (see `to_jsonl`),
+/// and merged into others (see `merge`), e.g., to share the types recovered
+/// for a library across the Projects of the programs linking it; e.g.:
+///
+/// ```json
+/// { "id": "5c1d87ab03e2f0a9", "kind": "struct", "name": "node", "packed": false, "fields": [{ "name": "next", "type": "e1a3b9f02c4d5e67" }] }
+/// { "id": "e1a3b9f02c4d5e67", "kind": "pointer", "pointee": "5c1d87ab03e2f0a9", "bits": 64 }
+/// { "kind": "alias", "name": "node_t", "type": "5c1d87ab03e2f0a9" }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TypeDatabase {
+    types: BTreeMap<Id<Type>, TypeDef>,
+    declared: BTreeMap<Id<Type>, Arc<str>>,
+    names: BTreeMap<Arc<str>, Id<Type>>,
+    aliases: BTreeMap<Arc<str>, Id<Type>>,
+    max_align: u64,
+}
+
+impl Default for TypeDatabase {
+    fn default() -> Self {
+        let mut db = Self {
+            types: BTreeMap::new(),
+            declared: BTreeMap::new(),
+            names: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+            max_align: 8,
+        };
+
+        db.insert(TypeDef::Bool);
+        for t in [bv::U8, bv::U16, bv::U32, bv::U64, bv::U128, bv::U256, bv::U512,
+                  bv::I8, bv::I16, bv::I32, bv::I64, bv::I128, bv::I256, bv::I512] {
+            db.insert(TypeDef::BitVec(t));
+        }
+        for t in [float::F32, float::F64, float::F80] {
+            db.insert(TypeDef::Float(t));
+        }
+
+        db
+    }
+}
+
+impl TypeDatabase {
+    /// A database of the primitive types.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The maximum alignment of any type, in bytes.
+    pub fn max_align(&mut self, align: u64) -> &mut Self {
+        self.max_align = align.max(1);
+        self
+    }
+
+    fn insert(&mut self, def: TypeDef) -> Id<Type> {
+        let id = def.id();
+        self.declared.remove(&id);
+        if let Some(name) = def.name() {
+            self.names.insert(name, id);
+        }
+        self.types.insert(id, def);
+        id
+    }
+
+    fn declare(&mut self, id: Id<Type>, name: Arc<str>) -> Id<Type> {
+        if !self.types.contains_key(&id) {
+            self.names.insert(name.clone(), id);
+            self.declared.insert(id, name);
+        }
+        id
+    }
+
+    /// Declares the struct named `name`, so that it can be referred to
+    /// before it is defined.
+    pub fn declare_struct(&mut self, name: impl Into<Arc<str>>) -> Id<Type> {
+        let name = name.into();
+        self.declare(StructT::named_id(&name), Arc::from(format!("struct {}", name)))
+    }
+
+    /// Declares the union named `name`; see `declare_struct`.
+    pub fn declare_union(&mut self, name: impl Into<Arc<str>>) -> Id<Type> {
+        let name = name.into();
+        self.declare(UnionT::named_id(&name), Arc::from(format!("union {}", name)))
+    }
+
+    /// Makes `name` an alias of the type `id`, as for a C typedef. It is an
+    /// error to alias a name already referring to a different type.
+    pub fn alias(&mut self, name: impl Into<Arc<str>>, id: Id<Type>) -> Result<&mut Self, TypeError> {
+        let name = name.into();
+        match self.names.get(&name).or_else(|| self.aliases.get(&name)) {
+            Some(existing) if *existing != id => Err(TypeError::NameTaken(name)),
+            _ => {
+                self.aliases.insert(name, id);
+                Ok(self)
+            }
+        }
+    }
+
+    /// The type named `name`: a primitive (e.g., `u32`), a named composite
+    /// type (e.g., `struct node`, or `node`, if no other type has that
+    /// name), or an alias.
+    pub fn lookup(&self, name: &str) -> Option<Id<Type>> {
+        let name = name.trim();
+        if let Some(id) = self.names.get(name).or_else(|| self.aliases.get(name)) {
+            return Some(*id)
+        }
+
+        let mut ids = ["struct", "union", "enum"]
+            .iter()
+            .filter_map(|kind| self.names.get(&*format!("{} {}", kind, name)));
+
+        match (ids.next(), ids.next()) {
+            (Some(id), None) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// The types defined, ordered by id.
+    pub fn iter(&self) -> impl Iterator<Item = (Id<Type>, &TypeDef)> {
+        self.types.iter().map(|(id, def)| (*id, def))
+    }
+
+    /// The types declared, but not yet defined, and their names.
+    pub fn declared(&self) -> impl Iterator<Item = (Id<Type>, &Arc<str>)> {
+        self.declared.iter().map(|(id, name)| (*id, name))
+    }
+
+    /// The names of the types that can be looked up by name (excluding
+    /// aliases), in order.
+    pub fn names(&self) -> impl Iterator<Item = (&Arc<str>, Id<Type>)> {
+        self.names.iter().map(|(name, id)| (name, *id))
+    }
+
+    pub fn aliases(&self) -> impl Iterator<Item = (&Arc<str>, Id<Type>)> {
+        self.aliases.iter().map(|(name, id)| (name, *id))
+    }
+
+    /// Adds the types, declarations, and aliases of `other`. Nothing is
+    /// added if any type or alias of `other` conflicts with one of this
+    /// database.
+    pub fn merge(&mut self, other: &TypeDatabase) -> Result<(), TypeError> {
+        for (id, def) in other.iter() {
+            if matches!(self.types.get(&id), Some(existing) if existing != def) {
+                return Err(TypeError::Redefined(id))
+            }
+        }
+
+        for (name, id) in other.aliases() {
+            if matches!(self.names.get(name).or_else(|| self.aliases.get(name)), Some(existing) if *existing != id) {
+                return Err(TypeError::NameTaken(name.clone()))
+            }
+        }
+
+        for (_, def) in other.iter() {
+            self.insert(def.clone());
+        }
+        for (id, name) in other.declared() {
+            self.declare(id, name.clone());
+        }
+        for (name, id) in other.aliases() {
+            self.aliases.insert(name.clone(), id);
+        }
+
+        Ok(())
+    }
+
+    /// Interns `def`, completing its declaration, if it was declared. It is
+    /// an error to add a named type whose name is already defined as a
+    /// different type.
+    pub fn add(&mut self, def: impl Into<TypeDef>) -> Result<Id<Type>, TypeError> {
+        let def = def.into();
+        let id = def.id();
+
+        match self.types.get(&id) {
+            Some(existing) if *existing != def => Err(TypeError::Redefined(id)),
+            Some(_) => Ok(id),
+            None => Ok(self.insert(def)),
+        }
+    }
+
+    pub fn get(&self, id: Id<Type>) -> Option<&TypeDef> {
+        self.types.get(&id)
+    }
+
+    pub fn contains(&self, id: Id<Type>) -> bool {
+        self.types.contains_key(&id)
+    }
+
+    /// True if `id` is declared, but not yet defined.
+    pub fn is_incomplete(&self, id: Id<Type>) -> bool {
+        self.declared.contains_key(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    fn missing(&self, id: Id<Type>) -> TypeError {
+        if self.declared.contains_key(&id) {
+            TypeError::Incomplete(id)
+        } else {
+            TypeError::Unknown(id)
+        }
+    }
+
+    fn natural(&self, bits: u32) -> Layout {
+        let size = (bits as u64 + 7) / 8;
+        Layout::scalar(size, size.max(1).next_power_of_two().min(self.max_align))
+    }
+
+    pub fn layout(&self, id: Id<Type>) -> Result<Layout, TypeError> {
+        self.layout_with(id, &mut Vec::new())
+    }
+
+    fn layout_with(&self, id: Id<Type>, within: &mut Vec<Id<Type>>) -> Result<Layout, TypeError> {
+        if within.contains(&id) {
+            return Err(TypeError::Recursive(id))
+        }
+
+        let def = self.types.get(&id).ok_or_else(|| self.missing(id))?;
+
+        within.push(id);
+        let layout = match def {
+            TypeDef::Bool => Ok(Layout::scalar(1, 1)),
+            TypeDef::BitVec(t) => Ok(self.natural(t.bits())),
+            TypeDef::Float(t) => Ok(self.natural(t.bits())),
+            TypeDef::Pointer(t) => Ok(self.natural(t.bits())),
+            TypeDef::Enum(t) => Ok(self.natural(t.repr().bits())),
+            TypeDef::Function(_) => Err(TypeError::Unsized(id)),
+            TypeDef::Array(t) => {
+                let element = self.layout_with(t.element_type(), within)?;
+                element.size
+                    .checked_mul(t.count())
+                    .map(|size| Layout::scalar(size, element.align))
+                    .ok_or(TypeError::TooLarge(id))
+            }
+            TypeDef::Struct(t) => self.struct_layout(id, t, within),
+            TypeDef::Union(t) => {
+                let mut layout = Layout::scalar(0, 1);
+                for member in t.members() {
+                    let member = self.layout_with(member.type_id(), within)?;
+                    layout.size = layout.size.max(member.size);
+                    layout.align = layout.align.max(member.align);
+                    layout.offsets.push(0);
+                }
+                layout.size = round_up(layout.size, layout.align).ok_or(TypeError::TooLarge(id))?;
+                Ok(layout)
+            }
+        };
+        within.pop();
+
+        layout
+    }
+
+    fn struct_layout(&self, id: Id<Type>, t: &StructT, within: &mut Vec<Id<Type>>) -> Result<Layout, TypeError> {
+        let mut layout = Layout::scalar(0, 1);
+        let mut next = 0u64;
+
+        for field in t.fields() {
+            let field_layout = self.layout_with(field.type_id(), within)?;
+            let align = if t.is_packed() { 1 } else { field_layout.align };
+
+            let offset = match field.offset() {
+                Some(offset) => offset,
+                None => round_up(next, align).ok_or(TypeError::TooLarge(id))?,
+            };
+            let end = offset.checked_add(field_layout.size).ok_or(TypeError::TooLarge(id))?;
+
+            next = end;
+            layout.size = layout.size.max(end);
+            layout.align = layout.align.max(align);
+            layout.offsets.push(offset);
+        }
+
+        layout.size = round_up(layout.size, layout.align).ok_or(TypeError::TooLarge(id))?;
+        Ok(layout)
+    }
+
+    pub fn size_of(&self, id: Id<Type>) -> Result<u64, TypeError> {
+        self.layout(id).map(|layout| layout.size)
+    }
+
+    pub fn align_of(&self, id: Id<Type>) -> Result<u64, TypeError> {
+        self.layout(id).map(|layout| layout.align)
+    }
+
+    /// The offset of the field (or member) named `name` of the struct (or
+    /// union) `id`; None if it has no such field.
+    pub fn field_offset(&self, id: Id<Type>, name: &str) -> Result<Option<u64>, TypeError> {
+        let index = match self.types.get(&id).ok_or_else(|| self.missing(id))? {
+            TypeDef::Struct(t) => t.fields().iter().position(|field| &**field.name() == name),
+            TypeDef::Union(t) => t.members().iter().position(|member| &**member.name() == name),
+            _ => None,
+        };
+
+        match index {
+            Some(index) => Ok(self.layout(id)?.offsets.get(index).copied()),
+            None => Ok(None),
+        }
+    }
+
+    /// A C-like rendering of the type `id`, e.g., `struct node*` or
+    /// `u8[16]`.
+    pub fn type_name(&self, id: Id<Type>) -> String {
+        // types may only refer to themselves via named types, which are
+        // rendered by name, so the depth is only bounded defensively
+        self.type_name_with(id, 16)
+    }
+
+    fn type_name_with(&self, id: Id<Type>, depth: usize) -> String {
+        if depth == 0 {
+            return "...".to_owned()
+        }
+
+        let def = match self.types.get(&id) {
+            Some(def) => def,
+            None => return self.declared.get(&id).map(|name| name.to_string()).unwrap_or_else(|| "?".to_owned()),
+        };
+
+        let named = |kind: &str, name: Option<&Arc<str>>| match name {
+            Some(name) => format!("{} {}", kind, name),
+            None => format!("{} {{...}}", kind),
+        };
+
+        match def {
+            TypeDef::Bool => BOOL.name().into_owned(),
+            TypeDef::BitVec(t) => t.name().into_owned(),
+            TypeDef::Float(t) => t.name().into_owned(),
+            TypeDef::Pointer(t) => format!("{}*", self.type_name_with(t.pointee_type(), depth - 1)),
+            TypeDef::Array(t) => format!("{}[{}]", self.type_name_with(t.element_type(), depth - 1), t.count()),
+            TypeDef::Struct(t) => named("struct", t.name()),
+            TypeDef::Union(t) => named("union", t.name()),
+            TypeDef::Enum(t) => named("enum", t.name()),
+            TypeDef::Function(t) => {
+                let mut params = t.params()
+                    .iter()
+                    .map(|param| self.type_name_with(*param, depth - 1))
+                    .collect::<Vec<_>>();
+                if t.is_variadic() {
+                    params.push("...".to_owned());
+                }
+                let returns = t.return_type()
+                    .map(|returns| self.type_name_with(returns, depth - 1))
+                    .unwrap_or_else(|| "void".to_owned());
+                format!("{}({})", returns, params.join(", "))
+            }
+        }
+    }
+}
+
+fn key_value(id: Id<Type>) -> Value {
+    // every constructor of types derives its id from a key, so ids outside
+    // of the scheme are not expected; they are written as invalid keys
+    Value::from(format!("{:016x}", type_key(id).unwrap_or(0)))
+}
+
+fn fields_value(fields: &[Field]) -> Value {
+    let fields = fields
+        .iter()
+        .map(|field| {
+            let mut object = Map::new();
+            object.insert("name".to_owned(), Value::from(&**field.name()));
+            object.insert("type".to_owned(), key_value(field.type_id()));
+            if let Some(offset) = field.offset() {
+                object.insert("offset".to_owned(), Value::from(offset));
+            }
+            Value::Object(object)
+        })
+        .collect::<Vec<_>>();
+    Value::Array(fields)
+}
+
+fn type_object(id: Id<Type>, def: &TypeDef) -> Map<String, Value> {
+    let mut object = Map::new();
+    object.insert("id".to_owned(), key_value(id));
+
+    let mut field = |name: &str, value: Value| {
+        object.insert(name.to_owned(), value);
+    };
+
+    match def {
+        TypeDef::Bool => field("kind", Value::from("bool")),
+        TypeDef::BitVec(t) => {
+            field("kind", Value::from("bitvec"));
+            field("bits", Value::from(t.bits() as u64));
+            field("signed", Value::from(t.is_signed()));
+        }
+        TypeDef::Float(t) => {
+            field("kind", Value::from("float"));
+            field("bits", Value::from(t.bits() as u64));
+        }
+        TypeDef::Pointer(t) => {
+            field("kind", Value::from("pointer"));
+            field("pointee", key_value(t.pointee_type()));
+            field("bits", Value::from(t.bits() as u64));
+        }
+        TypeDef::Array(t) => {
+            field("kind", Value::from("array"));
+            field("element", key_value(t.element_type()));
+            field("count", Value::from(t.count()));
+        }
+        TypeDef::Struct(t) => {
+            field("kind", Value::from("struct"));
+            if let Some(name) = t.name() {
+                field("name", Value::from(&**name));
+            }
+            field("packed", Value::from(t.is_packed()));
+            field("fields", fields_value(t.fields()));
+        }
+        TypeDef::Union(t) => {
+            field("kind", Value::from("union"));
+            if let Some(name) = t.name() {
+                field("name", Value::from(&**name));
+            }
+            field("members", fields_value(t.members()));
+        }
+        TypeDef::Enum(t) => {
+            field("kind", Value::from("enum"));
+            if let Some(name) = t.name() {
+                field("name", Value::from(&**name));
+            }
+            field("bits", Value::from(t.repr().bits() as u64));
+            field("signed", Value::from(t.repr().is_signed()));
+            // values may not fit within JSON's numbers
+            let variants = t
+                .variants()
+                .map(|(name, value)| {
+                    let mut variant = Map::new();
+                    variant.insert("name".to_owned(), Value::from(&**name));
+                    variant.insert("value".to_owned(), Value::from(value.to_string()));
+                    Value::Object(variant)
+                })
+                .collect::<Vec<_>>();
+            field("variants", Value::Array(variants));
+        }
+        TypeDef::Function(t) => {
+            field("kind", Value::from("function"));
+            field("returns", t.return_type().map(key_value).unwrap_or(Value::Null));
+            field("params", Value::Array(t.params().iter().map(|param| key_value(*param)).collect()));
+            field("variadic", Value::from(t.is_variadic()));
+        }
+    }
+
+    object
+}
+
+fn parse_key(value: Option<&Value>) -> Option<Id<Type>> {
+    let key = u64::from_str_radix(value?.as_str()?, 16).ok()?;
+    Some(type_id(key))
+}
+
+fn parse_fields(value: Option<&Value>) -> Option<Vec<Field>> {
+    value?
+        .as_array()?
+        .iter()
+        .map(|field| {
+            let name = field.get("name")?.as_str()?;
+            let typ = parse_key(field.get("type"))?;
+            Some(match field.get("offset") {
+                Some(offset) => Field::at(name, typ, offset.as_u64()?),
+                None => Field::new(name, typ),
+            })
+        })
+        .collect()
+}
+
+fn parse_type(value: &Value, id: Id<Type>) -> Option<TypeDef> {
+    let bits = || Some(value.get("bits")?.as_u64()? as u32);
+    let flag = |name: &str| value.get(name).and_then(|flag| flag.as_bool()).unwrap_or(false);
+    let name = value.get("name").and_then(|name| name.as_str());
+
+    Some(match value.get("kind")?.as_str()? {
+        "bool" => TypeDef::Bool,
+        "bitvec" => TypeDef::BitVec(BitVecT::new(bits()?, flag("signed"), type_key(id)?)),
+        "float" => TypeDef::Float(FloatT::new(bits()?, type_key(id)?)),
+        "pointer" => TypeDef::Pointer(PointerT::with_id(id, parse_key(value.get("pointee"))?, bits()?)),
+        "array" => TypeDef::Array(ArrayT::new(
+            parse_key(value.get("element"))?,
+            value.get("count")?.as_u64()?,
+        )),
+        "struct" => {
+            let mut t = name.map(StructT::named).unwrap_or_else(StructT::anonymous);
+            for field in parse_fields(value.get("fields"))? {
+                t.add_field(field);
+            }
+            t.packed(flag("packed"));
+            TypeDef::Struct(t)
+        }
+        "union" => {
+            let mut t = name.map(UnionT::named).unwrap_or_else(UnionT::anonymous);
+            for member in parse_fields(value.get("members"))? {
+                t.add_member(member.name().clone(), member.type_id());
+            }
+            TypeDef::Union(t)
+        }
+        "enum" => {
+            let repr = BitVecT::with_bits(bits()?, flag("signed"));
+            let mut t = name.map(|name| EnumT::named(name, repr)).unwrap_or_else(|| EnumT::anonymous(repr));
+            for variant in value.get("variants")?.as_array()? {
+                let value = variant.get("value")?.as_str()?.parse::<i128>().ok()?;
+                t.add_variant(variant.get("name")?.as_str()?, value);
+            }
+            TypeDef::Enum(t)
+        }
+        "function" => {
+            let returns = match value.get("returns") {
+                Some(returns) if !returns.is_null() => Some(parse_key(Some(returns))?),
+                _ => None,
+            };
+            let mut t = FunctionT::new(returns);
+            for param in value.get("params")?.as_array()? {
+                t.add_param(parse_key(Some(param))?);
+            }
+            t.variadic(flag("variadic"));
+            TypeDef::Function(t)
+        }
+        _ => return None,
+    })
+}
+
+impl TypeDatabase {
+    /// The database as JSON lines: its types, ordered by id, followed by
+    /// its declarations and aliases. References between types are by their
+    /// keys (see `type_key`).
+    pub fn to_jsonl(&self) -> String {
+        let mut text = String::new();
+        let mut line = |object: Map<String, Value>| {
+            text.push_str(&Value::Object(object).to_string());
+            text.push('\n');
+        };
+
+        for (id, def) in self.iter() {
+            line(type_object(id, def));
+        }
+
+        for (id, name) in self.declared() {
+            let mut object = Map::new();
+            object.insert("id".to_owned(), key_value(id));
+            object.insert("kind".to_owned(), Value::from("declared"));
+            object.insert("name".to_owned(), Value::from(&**name));
+            line(object);
+        }
+
+        for (name, id) in self.aliases() {
+            let mut object = Map::new();
+            object.insert("kind".to_owned(), Value::from("alias"));
+            object.insert("name".to_owned(), Value::from(&**name));
+            object.insert("type".to_owned(), key_value(id));
+            line(object);
+        }
+
+        text
+    }
+
+    /// Reads a database of the primitive types, extended by the types
+    /// written as JSON lines (see `to_jsonl`). The id recorded for each
+    /// type must be that derived from its definition.
+    pub fn from_jsonl(text: &str) -> Result<Self, TypeError> {
+        let mut db = Self::new();
+
+        for (i, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let line_no = i + 1;
+            let value = line
+                .parse::<Value>()
+                .map_err(|e| TypeError::Json(line_no, e))?;
+            let malformed = || TypeError::Malformed(line_no, line.to_owned());
+
+            let name = || value.get("name").and_then(|name| name.as_str()).ok_or_else(malformed);
+
+            match value.get("kind").and_then(|kind| kind.as_str()) {
+                Some("alias") => {
+                    let id = parse_key(value.get("type")).ok_or_else(malformed)?;
+                    db.alias(name()?, id)?;
+                }
+                Some("declared") => {
+                    let id = parse_key(value.get("id")).ok_or_else(malformed)?;
+                    db.declare(id, Arc::from(name()?));
+                }
+                _ => {
+                    let id = parse_key(value.get("id")).ok_or_else(malformed)?;
+                    let def = parse_type(&value, id).ok_or_else(malformed)?;
+                    if def.id() != id {
+                        return Err(malformed())
+                    }
+                    db.add(def)?;
+                }
+            }
+        }
+
+        Ok(db)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{U8, U16, U32, U64};
+    use crate::types::structure::Field;
+
+    #[test]
+    fn test_layout() -> Result<(), TypeError> {
+        let mut db = TypeDatabase::new();
+
+        let node = db.declare_struct("node");
+        let next = db.add(PointerT::to(node, 64))?;
+
+        let mut t = StructT::named("node");
+        t.add_field(Field::new("tag", U8.id()))
+            .add_field(Field::new("value", U32.id()))
+            .add_field(Field::new("len", U16.id()))
+            .add_field(Field::new("next", next));
+
+        assert!(db.is_incomplete(node));
+        assert!(matches!(db.layout(next), Ok(_)));
+        assert!(matches!(db.layout(node), Err(TypeError::Incomplete(_))));
+
+        assert_eq!(db.add(t)?, node);
+
+        let layout = db.layout(node)?;
+        assert_eq!(layout.offsets(), &[0, 4, 8, 16]);
+        assert_eq!(layout.size(), 24);
+        assert_eq!(layout.align(), 8);
+        assert_eq!(db.field_offset(node, "len")?, Some(8));
+        assert_eq!(db.type_name(next), "struct node*");
+
+        let array = db.add(ArrayT::new(U64.id(), 4))?;
+        assert_eq!(db.size_of(array)?, 32);
+        assert_eq!(db.type_name(array), "u64[4]");
+
+        let mut cyclic = StructT::named("cyclic");
+        cyclic.add_field(Field::new("inner", StructT::named_id("cyclic")));
+        let cyclic = db.add(cyclic)?;
+        assert!(matches!(db.layout(cyclic), Err(TypeError::Recursive(_))));
+
+        let mut redefined = StructT::named("node");
+        redefined.add_field(Field::new("tag", U8.id()));
+        assert!(matches!(db.add(redefined), Err(TypeError::Redefined(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lookup() -> Result<(), TypeError> {
+        let mut db = TypeDatabase::new();
+        assert_eq!(db.lookup("u32"), Some(U32.id()));
+
+        let node = db.declare_struct("node");
+        assert_eq!(db.lookup("struct node"), Some(node));
+        assert_eq!(db.lookup("node"), Some(node));
+
+        let mut t = EnumT::named("node", U8);
+        t.add_variant("LEAF", 0);
+        let kind = db.add(t)?;
+        assert_eq!(db.lookup("enum node"), Some(kind));
+        assert_eq!(db.lookup("node"), None);
+
+        db.alias("node_t", node)?;
+        assert_eq!(db.lookup("node_t"), Some(node));
+        assert!(matches!(db.alias("node_t", kind), Err(TypeError::NameTaken(_))));
+
+        let mut other = TypeDatabase::new();
+        other.alias("byte", U8.id())?;
+        other.alias("node_t", kind)?;
+        assert!(matches!(db.merge(&other), Err(TypeError::NameTaken(_))));
+        assert_eq!(db.lookup("byte"), None);
+
+        let mut other = TypeDatabase::new();
+        other.alias("byte", U8.id())?;
+        db.merge(&other)?;
+        assert_eq!(db.lookup("byte"), Some(U8.id()));
+        assert!(db.declared().any(|(id, _)| id == node));
+        assert!(db.iter().any(|(id, _)| id == kind));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::prelude::{Id, Identifiable};
+use crate::types::{Type, TypeSort, derived_type_id};
+use crate::types::bv::BitVecT;
+
+/// An enumeration of named constants, represented as a bit-vector; as for
+/// structs, named enums derive their ids from their names.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EnumT {
+    name: Option<Arc<str>>,
+    repr: BitVecT,
+    variants: Vec<(Arc<str>, i128)>,
+}
+
+impl EnumT {
+    pub fn named(name: impl Into<Arc<str>>, repr: BitVecT) -> Self {
+        Self { name: Some(name.into()), repr, variants: Vec::new() }
+    }
+
+    pub fn anonymous(repr: BitVecT) -> Self {
+        Self { name: None, repr, variants: Vec::new() }
+    }
+
+    /// The id of the enum named `name`.
+    pub fn named_id(name: &str) -> Id<Type> {
+        derived_type_id(&format!("enum {}", name))
+    }
+
+    pub fn add_variant(&mut self, name: impl Into<Arc<str>>, value: i128) -> &mut Self {
+        self.variants.push((name.into(), value));
+        self
+    }
+
+    pub fn name(&self) -> Option<&Arc<str>> {
+        self.name.as_ref()
+    }
+
+    /// The bit-vector type the enum is represented as.
+    pub fn repr(&self) -> BitVecT {
+        self.repr
+    }
+
+    pub fn variants(&self) -> impl Iterator<Item = (&Arc<str>, i128)> {
+        self.variants.iter().map(|(name, value)| (name, *value))
+    }
+
+    /// The name of the variant with value `value`, if any.
+    pub fn variant_named(&self, value: i128) -> Option<&Arc<str>> {
+        self.variants.iter().find(|(_, v)| *v == value).map(|(name, _)| name)
+    }
+}
+
+impl Identifiable<Type> for EnumT {
+    fn id(&self) -> Id<Type> {
+        match self.name {
+            Some(ref name) => Self::named_id(name),
+            None => {
+                let mut key = format!("enum/{}", self.repr.name());
+                for (name, value) in self.variants.iter() {
+                    let _ = write!(key, "/{}={}", name, value);
+                }
+                derived_type_id(&key)
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::fmt::Write;
+
+use crate::prelude::{Id, Identifiable};
+use crate::types::{Type, derived_type_id};
+
+/// A function signature: the types of its parameters and of its return
+/// value, if any. Function types have no size; pointers to them do.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FunctionT {
+    returns: Option<Id<Type>>,
+    params: Vec<Id<Type>>,
+    variadic: bool,
+}
+
+impl FunctionT {
+    pub fn new(returns: Option<Id<Type>>) -> Self {
+        Self { returns, params: Vec::new(), variadic: false }
+    }
+
+    pub fn add_param(&mut self, typ: Id<Type>) -> &mut Self {
+        self.params.push(typ);
+        self
+    }
+
+    pub fn variadic(&mut self, variadic: bool) -> &mut Self {
+        self.variadic = variadic;
+        self
+    }
+
+    pub fn return_type(&self) -> Option<Id<Type>> {
+        self.returns
+    }
+
+    pub fn params(&self) -> &[Id<Type>] {
+        &self.params
+    }
+
+    pub fn is_variadic(&self) -> bool {
+        self.variadic
+    }
+}
+
+impl Identifiable<Type> for FunctionT {
+    fn id(&self) -> Id<Type> {
+        let mut key = String::from("fn");
+        match self.returns {
+            Some(returns) => { let _ = write!(key, "/{}", returns); }
+            None => key.push_str("/void"),
+        }
+        for param in self.params.iter() {
+            let _ = write!(key, "/{}", param);
+        }
+        if self.variadic {
+            key.push_str("/...");
+        }
+        derived_type_id(&key)
+    }
+}
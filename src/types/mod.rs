@@ -1,16 +1,26 @@
 use std::borrow::Cow;
 use ron_uuid::UUID;
 
-use crate::prelude::{Erased, Identifiable};
+use crate::prelude::{Erased, Id, Identifiable};
 
+pub mod array;
 pub mod bool;
 pub mod bv;
+pub mod database;
+pub mod enumeration;
 pub mod float;
+pub mod function;
 pub mod pointer;
+pub mod structure;
 
+pub use self::array::ArrayT;
 pub use self::bool::BOOL;
 pub use self::bv::{U8, U16, U32, U64, U128, U256, U512, I8, I16, I32, I64, I128, I256, I512};
+pub use self::database::{Layout, TypeDatabase, TypeDef, TypeError};
+pub use self::enumeration::EnumT;
 pub use self::float::{F32, F64, F80};
+pub use self::function::FunctionT;
+pub use self::structure::{Field, StructT, UnionT};
 
 const TYPE_SCOPE: u64 = 0x21341e3f58957821;
 
@@ -18,6 +28,32 @@ pub const fn type_uuid(id: u64) -> UUID {
     UUID::Name { scope: TYPE_SCOPE, name: id }
 }
 
+/// The id of the type whose key (within the scheme of `type_uuid`) is
+/// `key`.
+pub const fn type_id(key: u64) -> Id<Type> {
+    Id::from_parts("type", type_uuid(key))
+}
+
+/// The key of the type `id` within the scheme of `type_uuid`, which is
+/// stable across processes, and so can be used to persist references to
+/// types; None if `id` was not constructed by `type_id`.
+pub fn type_key(id: Id<Type>) -> Option<u64> {
+    match id.uuid() {
+        UUID::Name { scope: TYPE_SCOPE, name } => Some(name),
+        _ => None,
+    }
+}
+
+// composite and inferred types derive their ids from keys describing them,
+// so that equal types share ids
+pub(crate) fn derived_type_id(key: &str) -> Id<Type> {
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in key.bytes() {
+        hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+    }
+    type_id(hash)
+}
+
 pub type Type = Erased;
 
 pub trait TypeSort: Identifiable<Type> {
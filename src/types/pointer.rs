@@ -1,7 +1,7 @@
 use std::borrow::Cow;
 
 use crate::prelude::{Id, Identifiable};
-use crate::types::{Type, TypeSort, type_uuid};
+use crate::types::{Type, TypeSort, derived_type_id, type_uuid};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct PointerT {
@@ -19,6 +19,22 @@ impl PointerT {
         }
     }
     
+    // pointers to inferred types derive their ids from their pointee and
+    // size, so that equal pointer types share ids
+    pub fn to(pointee: Id<Type>, bits: u32) -> Self {
+        Self {
+            id: derived_type_id(&format!("ptr/{}/{}", pointee, bits)),
+            pointee,
+            bits,
+        }
+    }
+
+    // pointers read back from a TypeDatabase keep the ids they were
+    // constructed with
+    pub(crate) fn with_id(id: Id<Type>, pointee: Id<Type>, bits: u32) -> Self {
+        Self { id, pointee, bits }
+    }
+
     pub fn pointee_type(&self) -> Id<Type> {
         self.pointee
     }
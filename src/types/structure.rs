@@ -0,0 +1,162 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::prelude::{Id, Identifiable};
+use crate::types::{Type, derived_type_id};
+
+/// A member of a struct or union.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Field {
+    name: Arc<str>,
+    typ: Id<Type>,
+    // the offset of the field, if fixed (e.g., by recovered accesses),
+    // rather than computed from the layout of the fields preceding it
+    offset: Option<u64>,
+}
+
+impl Field {
+    pub fn new(name: impl Into<Arc<str>>, typ: Id<Type>) -> Self {
+        Self { name: name.into(), typ, offset: None }
+    }
+
+    pub fn at(name: impl Into<Arc<str>>, typ: Id<Type>, offset: u64) -> Self {
+        Self { name: name.into(), typ, offset: Some(offset) }
+    }
+
+    pub fn name(&self) -> &Arc<str> {
+        &self.name
+    }
+
+    pub fn type_id(&self) -> Id<Type> {
+        self.typ
+    }
+
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+}
+
+fn fields_key(kind: &str, fields: &[Field], packed: bool) -> String {
+    let mut key = String::from(kind);
+    for field in fields {
+        let _ = write!(key, "/{}:{}", field.name, field.typ);
+        if let Some(offset) = field.offset {
+            let _ = write!(key, "@{}", offset);
+        }
+    }
+    if packed {
+        key.push_str("/packed");
+    }
+    key
+}
+
+/// A struct: named structs derive their ids from their names, so that they
+/// can be referred to (e.g., by their own fields, via pointers) before they
+/// are defined (see `TypeDatabase::declare_struct`); anonymous structs
+/// derive their ids from their fields.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StructT {
+    name: Option<Arc<str>>,
+    fields: Vec<Field>,
+    packed: bool,
+}
+
+impl StructT {
+    pub fn named(name: impl Into<Arc<str>>) -> Self {
+        Self { name: Some(name.into()), fields: Vec::new(), packed: false }
+    }
+
+    pub fn anonymous() -> Self {
+        Self { name: None, fields: Vec::new(), packed: false }
+    }
+
+    /// The id of the struct named `name`.
+    pub fn named_id(name: &str) -> Id<Type> {
+        derived_type_id(&format!("struct {}", name))
+    }
+
+    pub fn add_field(&mut self, field: Field) -> &mut Self {
+        self.fields.push(field);
+        self
+    }
+
+    /// Lays out the fields of the struct without padding.
+    pub fn packed(&mut self, packed: bool) -> &mut Self {
+        self.packed = packed;
+        self
+    }
+
+    pub fn name(&self) -> Option<&Arc<str>> {
+        self.name.as_ref()
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|field| &*field.name == name)
+    }
+
+    pub fn is_packed(&self) -> bool {
+        self.packed
+    }
+}
+
+impl Identifiable<Type> for StructT {
+    fn id(&self) -> Id<Type> {
+        match self.name {
+            Some(ref name) => Self::named_id(name),
+            None => derived_type_id(&fields_key("struct", &self.fields, self.packed)),
+        }
+    }
+}
+
+/// A union: its members all start at offset zero; as for structs, named
+/// unions derive their ids from their names.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnionT {
+    name: Option<Arc<str>>,
+    members: Vec<Field>,
+}
+
+impl UnionT {
+    pub fn named(name: impl Into<Arc<str>>) -> Self {
+        Self { name: Some(name.into()), members: Vec::new() }
+    }
+
+    pub fn anonymous() -> Self {
+        Self { name: None, members: Vec::new() }
+    }
+
+    /// The id of the union named `name`.
+    pub fn named_id(name: &str) -> Id<Type> {
+        derived_type_id(&format!("union {}", name))
+    }
+
+    pub fn add_member(&mut self, name: impl Into<Arc<str>>, typ: Id<Type>) -> &mut Self {
+        self.members.push(Field::new(name, typ));
+        self
+    }
+
+    pub fn name(&self) -> Option<&Arc<str>> {
+        self.name.as_ref()
+    }
+
+    pub fn members(&self) -> &[Field] {
+        &self.members
+    }
+
+    pub fn member(&self, name: &str) -> Option<&Field> {
+        self.members.iter().find(|member| &*member.name == name)
+    }
+}
+
+impl Identifiable<Type> for UnionT {
+    fn id(&self) -> Id<Type> {
+        match self.name {
+            Some(ref name) => Self::named_id(name),
+            None => derived_type_id(&fields_key("union", &self.members, false)),
+        }
+    }
+}
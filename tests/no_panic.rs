@@ -0,0 +1,145 @@
+// Applies pseudo-random sequences of operations to Projects built from
+// untrusted input (arbitrary bytes mapped at arbitrary addresses); any
+// operation may fail, but none may panic.
+//
+// The seed and number of rounds can be set via DELIRIUM_FUZZ_SEED and
+// DELIRIUM_FUZZ_ROUNDS; the seed of a failing run is reported so that it can
+// be replayed.
+
+use std::env;
+use std::path::PathBuf;
+
+use delirium::ir::{Addr, BitVec, ProjectBuilder};
+
+// xorshift64*, so that rounds are reproducible from their seed
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n.max(1)
+    }
+
+    fn bytes(&mut self, n: usize) -> Vec<u8> {
+        (0..n).map(|_| self.next() as u8).collect()
+    }
+
+    // addresses are biased towards the bounds of regions and of the
+    // address space, where most edge cases are
+    fn addr(&mut self, starts: &[u32]) -> u32 {
+        match self.below(4) {
+            0 if !starts.is_empty() => {
+                let start = starts[self.below(starts.len() as u64) as usize];
+                start.wrapping_add(self.below(80) as u32).wrapping_sub(8)
+            }
+            1 => u32::MAX - self.below(32) as u32,
+            2 => self.below(32) as u32,
+            _ => self.next() as u32,
+        }
+    }
+}
+
+fn round(root: &str, seed: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let mut rng = Rng::new(seed);
+
+    let path = PathBuf::from_iter([root, "processors"]);
+    let builder = ProjectBuilder::new(&path)?;
+    let mut project = builder.project("fuzz", "x86:LE:32:default", "gcc")?;
+
+    let endian = project.lifter().endian();
+    let mut starts = Vec::new();
+
+    for _ in 0..64 {
+        match rng.below(10) {
+            0 | 1 => {
+                let addr = rng.addr(&starts);
+                let size = rng.below(64) as usize;
+                let bytes = rng.bytes(size);
+                if project.add_region_mapping_with("fuzz", addr, endian, bytes).is_ok() {
+                    starts.push(addr);
+                }
+            }
+            2 => {
+                let _ = project.add_blk(Addr::from(rng.addr(&starts)));
+            }
+            3 => {
+                let _ = project.explore_from(Addr::from(rng.addr(&starts)));
+            }
+            4 => {
+                let _ = project.add_sub(Addr::from(rng.addr(&starts)));
+            }
+            5 => {
+                let mut subs = project.subs().map(|sub| (**sub).clone()).collect::<Vec<_>>();
+                for sub in subs.iter_mut() {
+                    let _ = project.resolve_jump_tables(sub);
+                    let _ = project.export_asm(sub);
+                }
+            }
+            6 => {
+                let addr = Addr::from(rng.addr(&starts));
+                let name = ["EAX", "ESP", "ZF", "XMM0", "NOT_A_REGISTER"][rng.below(5) as usize];
+                let value = BitVec::from_u64(rng.next(), 1 + rng.below(64) as usize);
+                project.hint_register(addr.clone(), name, value);
+                project.hint_constant_memory(addr, rng.below(128) as usize);
+            }
+            7 => {
+                let addr = Addr::from(rng.addr(&starts));
+                let size = rng.below(16) as usize;
+                let bytes = rng.bytes(size);
+                let lifter = project.lifter();
+                let mut ctxt = lifter.context();
+                for lifted in lifter.lift_iter(&mut ctxt, addr, &bytes) {
+                    let _ = lifted;
+                }
+            }
+            8 => {
+                let addr = Addr::from(rng.addr(&starts));
+                let size = rng.below(8) as usize;
+                let bytes = rng.bytes(size);
+                let _ = project.patch_bytes(addr, &bytes);
+                project.gc();
+            }
+            _ => {
+                project.discover_subs();
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_no_panic_on_untrusted_input() -> Result<(), Box<dyn std::error::Error>> {
+    let root = env::var("DELIRIUM_TEST_ENV_ROOT")?;
+
+    let seed = env::var("DELIRIUM_FUZZ_SEED")
+        .ok()
+        .and_then(|seed| seed.parse::<u64>().ok())
+        .unwrap_or(0x6465_6c69_7269_756d);
+    let rounds = env::var("DELIRIUM_FUZZ_ROUNDS")
+        .ok()
+        .and_then(|rounds| rounds.parse::<u64>().ok())
+        .unwrap_or(16);
+
+    let mut rng = Rng::new(seed);
+    for i in 0..rounds {
+        let round_seed = rng.next();
+        let result = std::panic::catch_unwind(|| round(&root, round_seed).map_err(|e| e.to_string()));
+        match result {
+            Ok(result) => result?,
+            Err(_) => panic!("round {} panicked (DELIRIUM_FUZZ_SEED={})", i, seed),
+        }
+    }
+
+    Ok(())
+}